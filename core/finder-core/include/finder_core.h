@@ -21,9 +21,20 @@ typedef struct {
 
 typedef struct {
     const char *q;
-    const char *glob;
+    const char *glob; /* newline-separated; a path matching any is kept; null = any */
     int32_t scope;
     int32_t limit;
+    int32_t timeout_ms; /* 0 = no limit; past it, hits found so far */
+    int32_t snippet_chars; /* 0 = no snippets */
+    int64_t mtime_min; /* unix seconds, inclusive; 0 = unbounded */
+    int64_t mtime_max; /* unix seconds, inclusive; 0 = unbounded */
+    uint64_t size_min; /* bytes, inclusive; 0 = unbounded */
+    uint64_t size_max; /* bytes, inclusive; 0 = unbounded */
+    const char *exts; /* comma-separated, e.g. "md,txt"; null = any */
+    int32_t offset; /* hits to skip, for later pages */
+    const char *exclude_globs; /* newline-separated, e.g. "**/node_modules/**"; null = none */
+    int32_t recency_half_life_days; /* boost recently modified files; 0 = off */
+    const char *path_penalties; /* newline-separated "glob=factor"; null = demote backups, archives and copies; "" = none */
 } FCQuery;
 
 typedef struct {
@@ -32,19 +43,92 @@ typedef struct {
     int64_t mtime;
     uint64_t size;
     float score;
+    char *summary;
+    bool is_alias;
+    char *alias_target;
+    char *snippet; /* HTML-escaped preview with matches in <b>, or null */
 } FCHit;
 
 typedef struct {
     FCHit *hits;
     int32_t count;
+    bool timed_out; /* timeout_ms ran out; hits holds what was found in time */
 } FCResults;
 
+typedef struct {
+    int32_t update; /* 0 added, 1 updated, 2 unchanged, -1 failed */
+    bool content_indexed;
+    char *error;    /* null unless something failed; free with fc_free_reindex_result */
+} FCReindexResult;
+
+typedef struct {
+    uint64_t docs_added;
+    uint64_t searches;
+    uint64_t unchanged_skips; /* files skipped as unchanged */
+    uint64_t reader_reloads;
+} FCQuickStats;
+
+typedef struct FCSession FCSession;
+
+/* Owns `results`; release them with fc_free_results. Runs on a worker thread. */
+typedef void (*FCSearchCallback)(uint64_t request_id, FCResults results);
+
+typedef bool (*FCSummarizeFn)(const char *path, const char *lead, char *out, size_t out_len);
+
+/* Write the rewritten query into `out` and return true, or return false to keep `raw`. */
+typedef bool (*FCRewriteFn)(const char *raw, char *out, size_t out_len);
+
+#define FC_EVENT_COMMIT 1
+#define FC_EVENT_SCAN_FINISHED 2
+#define FC_EVENT_SIZE_THRESHOLD 3
+#define FC_EVENT_CORRUPTION 4
+#define FC_EVENT_STANDING_MATCH 5
+
+/* `value`: commit generation, files seen, index bytes, or standing query id.
+   `message`: scanned roots or a standing query's new matches (one per line),
+   or the corruption error, else null; valid only during the call, which runs
+   on the indexing thread. */
+typedef void (*FCEventFn)(int32_t kind, uint64_t value, const char *message);
+
+char *fc_default_index_dir(void); /* null on failure; free with fc_free_string */
+void fc_free_string(char *string);
 bool fc_init_index(const char *index_dir);
+bool fc_init_index_in_memory(void);
+bool fc_init_index_read_only(const char *index_dir);
 void fc_close_index(void);
+void fc_set_summarizer(FCSummarizeFn callback);
+void fc_set_builtin_summarizer(int32_t max_sentences);
+void fc_set_event_callback(FCEventFn callback);
+void fc_set_index_size_alert(uint64_t threshold_bytes);
+uint64_t fc_add_standing_query(const char *name, const FCQuery *query); /* 0 on failure */
+bool fc_remove_standing_query(uint64_t id);
+void fc_set_query_rewriter(FCRewriteFn callback);
+/* Scripts Finder, which a sandboxed app may not do: there, trash with
+   FileManager.trashItem and call fc_forget_removed instead. */
+bool fc_move_to_trash(const char *path);
+int64_t fc_forget_removed(const char *path); /* files dropped; -1 if still on disk or on failure */
+bool fc_reveal_target(const char *path);
 bool fc_should_reindex(const FCFileMeta *meta);
 bool fc_add_or_update(const FCFileMeta *meta, const char *utf8_content_or_null);
+FCReindexResult fc_reindex_path(const char *path);
+void fc_free_reindex_result(FCReindexResult *result);
 bool fc_commit_and_refresh(void);
+bool fc_refresh_if_changed(void);
 FCResults fc_search(const FCQuery *query);
+int64_t fc_count(const FCQuery *query); /* -1 on failure */
+FCQuickStats fc_quick_stats(void); /* counters since the index was opened; lock-free */
+/* "ext\tcount" lines, most common first; null on failure; free with fc_free_string */
+char *fc_count_by_extension(const FCQuery *query);
+/* as above, estimated on large indexes; estimates are written "~count" */
+char *fc_count_by_extension_approx(const FCQuery *query);
+char *fc_truncate(const char *text, int max_graphemes); /* free with fc_free_string */
+FCResults fc_search_prefix(const char *typed, int limit);
+/* request ids must increase; a new request cancels in-flight ones with the same or a lower id */
+bool fc_search_async(const FCQuery *query, uint64_t request_id, FCSearchCallback callback);
+bool fc_cancel_search(uint64_t request_id);
+FCSession *fc_session_pin(void);
+FCResults fc_session_search(const FCSession *session, const FCQuery *query);
+void fc_session_release(FCSession *session);
 void fc_free_results(FCResults *results);
 
 #ifdef __cplusplus
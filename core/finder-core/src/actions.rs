@@ -0,0 +1,173 @@
+use crate::alias::resolve_alias;
+use crate::indexer;
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::{env, fs};
+
+/// Set by macOS for every process running in an App Sandbox container.
+const SANDBOX_CONTAINER_VAR: &str = "APP_SANDBOX_CONTAINER_ID";
+
+/// Move `path` (a file or a folder) to the platform trash, where the user
+/// can restore it, then drop it from the index and commit so the result
+/// list stays in step with the disk. Uses Finder on macOS, so "Put Back"
+/// works, and `gio trash` elsewhere. Returns the files removed from the
+/// index; 0 from a read-only process, whose writer catches up on its next
+/// scan.
+///
+/// A sandboxed macOS app may not script Finder, so there this fails; such
+/// a host trashes the file itself with `FileManager.trashItem` and then
+/// calls [`forget_removed`].
+pub fn move_to_trash(path: &Path) -> Result<usize> {
+    if fs::symlink_metadata(path).is_err() {
+        forget(path)?;
+        bail!("{} no longer exists", path.display());
+    }
+    if cfg!(target_os = "macos") && env::var_os(SANDBOX_CONTAINER_VAR).is_some() {
+        bail!("a sandboxed app can't script Finder; trash from the host, then forget_removed");
+    }
+    let command = trash_command(path).context("moving to the trash is not supported here")?;
+    run(command).with_context(|| format!("failed moving {} to the trash", path.display()))?;
+    forget(path)
+}
+
+/// Show `path` selected in the file manager. Aliases and shortcuts reveal
+/// what they point to. A path that vanished is dropped from the index
+/// instead, and reported as an error. Returns the path that was revealed.
+pub fn reveal_target(path: &Path) -> Result<PathBuf> {
+    let Ok(meta) = fs::metadata(path) else {
+        forget(path)?;
+        bail!("{} no longer exists", path.display());
+    };
+    let ext = path.extension().map(|ext| ext.to_string_lossy());
+    let target = resolve_alias(path, ext.as_deref(), meta.len())
+        .map(PathBuf::from)
+        .filter(|target| target.exists())
+        .unwrap_or_else(|| path.to_path_buf());
+    let command = reveal_command(&target).context("revealing files is not supported here")?;
+    run(command).with_context(|| format!("failed revealing {}", target.display()))?;
+    Ok(target)
+}
+
+/// Drop `path`, or everything below it, from the index and commit, after
+/// the host removed it from disk itself, e.g. with `FileManager.trashItem`
+/// from a sandboxed app. Fails while `path` still exists. Returns the
+/// files removed from the index, as [`move_to_trash`] does.
+pub fn forget_removed(path: &Path) -> Result<usize> {
+    if fs::symlink_metadata(path).is_ok() {
+        bail!("{} is still on disk", path.display());
+    }
+    forget(path)
+}
+
+fn forget(path: &Path) -> Result<usize> {
+    Ok(indexer::forget_path(path)?.unwrap_or(0))
+}
+
+fn run(mut command: Command) -> Result<()> {
+    let output = command.output().context("failed to start helper")?;
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+fn trash_command(path: &Path) -> Option<Command> {
+    if cfg!(target_os = "macos") {
+        // The path goes in as an argument so it never needs AppleScript
+        // quoting.
+        let mut command = Command::new("osascript");
+        command.args([
+            "-e",
+            "on run argv",
+            "-e",
+            "tell application \"Finder\" to delete (POSIX file (item 1 of argv) as alias)",
+            "-e",
+            "end run",
+        ]);
+        command.arg(path);
+        Some(command)
+    } else if cfg!(unix) {
+        let mut command = Command::new("gio");
+        command.arg("trash").arg("--").arg(path);
+        Some(command)
+    } else {
+        None
+    }
+}
+
+fn reveal_command(path: &Path) -> Option<Command> {
+    if cfg!(target_os = "macos") {
+        let mut command = Command::new("open");
+        command.arg("-R").arg(path);
+        Some(command)
+    } else if cfg!(unix) {
+        // File managers differ in how they select an item; opening the
+        // containing folder works everywhere.
+        let folder = if path.is_dir() { path } else { path.parent()? };
+        let mut command = Command::new("gio");
+        command.arg("open").arg("--").arg(folder);
+        Some(command)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{forget_removed, move_to_trash, reveal_target};
+    use crate::query::{SearchDomain, SearchQuery};
+    use crate::{commit, index_root, init_index, search, RootOptions};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn vanished_results_are_dropped_from_the_index() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let index_dir = tempdir().unwrap();
+        init_index(index_dir.path().to_str().unwrap()).unwrap();
+        let docs = tempdir().unwrap();
+        let gone = docs.path().join("gone-report.txt");
+        let kept = docs.path().join("kept-report.txt");
+        fs::write(&gone, "q3 numbers").unwrap();
+        fs::write(&kept, "q4 numbers").unwrap();
+        index_root(docs.path(), &RootOptions::default()).unwrap();
+        commit().unwrap();
+        fs::remove_file(&gone).unwrap();
+
+        assert!(reveal_target(&gone).is_err());
+        assert!(move_to_trash(&gone).is_err());
+        assert!(forget_removed(&kept).is_err(), "still on disk");
+        let hits = search(SearchQuery {
+            term: "report".into(),
+            search_in: SearchDomain::Name,
+            ..SearchQuery::default()
+        })
+        .unwrap();
+        let names: Vec<_> = hits.iter().map(|hit| hit.name.as_str()).collect();
+        assert_eq!(names, vec!["kept-report.txt"]);
+    }
+
+    #[test]
+    fn files_the_host_trashed_are_forgotten() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let index_dir = tempdir().unwrap();
+        init_index(index_dir.path().to_str().unwrap()).unwrap();
+        let docs = tempdir().unwrap();
+        let trashed = docs.path().join("trashed-report.txt");
+        fs::write(&trashed, "q3 numbers").unwrap();
+        index_root(docs.path(), &RootOptions::default()).unwrap();
+        commit().unwrap();
+
+        // What FileManager.trashItem leaves behind: nothing at the path.
+        fs::remove_file(&trashed).unwrap();
+        assert_eq!(forget_removed(&trashed).unwrap(), 1);
+        let hits = search(SearchQuery {
+            term: "report".into(),
+            search_in: SearchDomain::Name,
+            ..SearchQuery::default()
+        })
+        .unwrap();
+        assert!(hits.is_empty());
+    }
+}
@@ -0,0 +1,198 @@
+use std::fs;
+use std::path::Path;
+
+/// Alias and shortcut files are tiny; anything bigger is not worth parsing.
+const MAX_ALIAS_BYTES: u64 = 64 * 1024;
+
+const BOOKMARK_MAGIC: &[u8; 4] = b"book";
+const BOOKMARK_TOC_MAGIC: u32 = 0xffff_fffe;
+const BOOKMARK_KEY_PATH: u32 = 0x1004;
+const BOOKMARK_TYPE_STRING: u32 = 0x0101;
+const BOOKMARK_TYPE_ARRAY: u32 = 0x0601;
+
+const LNK_HEADER_SIZE: u32 = 0x4c;
+const LNK_HAS_ID_LIST: u32 = 0x1;
+const LNK_HAS_LINK_INFO: u32 = 0x2;
+const LNK_VOLUME_AND_LOCAL_PATH: u32 = 0x1;
+
+/// Resolve the target of a macOS Finder alias (bookmark data) or a Windows
+/// `.lnk` shortcut. Only the file's own bytes are read; the target is not
+/// checked for existence. `None` for anything that isn't a readable alias.
+pub fn resolve_alias(path: &Path, ext: Option<&str>, size: u64) -> Option<String> {
+    let ext = ext.map(str::to_ascii_lowercase);
+    // Finder aliases usually have no extension, so those are sniffed too.
+    let candidate = matches!(ext.as_deref(), None | Some("alias") | Some("lnk"));
+    if !candidate || size == 0 || size > MAX_ALIAS_BYTES {
+        return None;
+    }
+    let bytes = fs::read(path).ok()?;
+    if bytes.starts_with(BOOKMARK_MAGIC) {
+        parse_bookmark(&bytes)
+    } else if ext.as_deref() == Some("lnk") {
+        parse_lnk(&bytes)
+    } else {
+        None
+    }
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Option<u32> {
+    let slice = bytes.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes(slice.try_into().ok()?))
+}
+
+fn c_string_at(bytes: &[u8], offset: usize) -> Option<String> {
+    let tail = bytes.get(offset..)?;
+    let end = tail.iter().position(|byte| *byte == 0)?;
+    Some(String::from_utf8_lossy(&tail[..end]).into_owned())
+}
+
+/// Bookmark data: a header, then a data area holding typed records and a
+/// table of contents mapping keys to record offsets. The target path is the
+/// array of component strings stored under [`BOOKMARK_KEY_PATH`].
+fn parse_bookmark(bytes: &[u8]) -> Option<String> {
+    let data = u32_at(bytes, 12)? as usize;
+    let mut toc = data.checked_add(u32_at(bytes, data)? as usize)?;
+    // Bound the walk so a corrupt next-TOC pointer can't loop forever.
+    for _ in 0..16 {
+        if u32_at(bytes, toc + 4)? != BOOKMARK_TOC_MAGIC {
+            return None;
+        }
+        let next = u32_at(bytes, toc + 12)? as usize;
+        let count = u32_at(bytes, toc + 16)? as usize;
+        for entry in 0..count.min(1024) {
+            let at = toc + 20 + entry * 12;
+            if u32_at(bytes, at)? == BOOKMARK_KEY_PATH {
+                let record = data + u32_at(bytes, at + 4)? as usize;
+                return bookmark_path(bytes, data, record);
+            }
+        }
+        if next == 0 {
+            return None;
+        }
+        toc = data.checked_add(next)?;
+    }
+    None
+}
+
+fn bookmark_path(bytes: &[u8], data: usize, record: usize) -> Option<String> {
+    let len = u32_at(bytes, record)? as usize;
+    if u32_at(bytes, record + 4)? != BOOKMARK_TYPE_ARRAY {
+        return None;
+    }
+    let mut path = String::new();
+    for slot in (0..len).step_by(4) {
+        let item = data + u32_at(bytes, record + 8 + slot)? as usize;
+        let item_len = u32_at(bytes, item)? as usize;
+        if u32_at(bytes, item + 4)? != BOOKMARK_TYPE_STRING {
+            return None;
+        }
+        let component = bytes.get(item + 8..item + 8 + item_len)?;
+        path.push('/');
+        path.push_str(&String::from_utf8_lossy(component));
+    }
+    (!path.is_empty()).then_some(path)
+}
+
+/// Shell link: fixed header, optional ID list, then a LinkInfo block whose
+/// local base path plus common suffix form the target.
+fn parse_lnk(bytes: &[u8]) -> Option<String> {
+    if u32_at(bytes, 0)? != LNK_HEADER_SIZE {
+        return None;
+    }
+    let flags = u32_at(bytes, 0x14)?;
+    let mut offset = LNK_HEADER_SIZE as usize;
+    if flags & LNK_HAS_ID_LIST != 0 {
+        let size = u16::from_le_bytes(bytes.get(offset..offset + 2)?.try_into().ok()?);
+        offset += 2 + size as usize;
+    }
+    if flags & LNK_HAS_LINK_INFO == 0 {
+        return None;
+    }
+    let info_flags = u32_at(bytes, offset + 8)?;
+    if info_flags & LNK_VOLUME_AND_LOCAL_PATH == 0 {
+        return None;
+    }
+    let base = c_string_at(bytes, offset + u32_at(bytes, offset + 16)? as usize)?;
+    let suffix = c_string_at(bytes, offset + u32_at(bytes, offset + 24)? as usize)?;
+    let target = base + &suffix;
+    (!target.is_empty()).then_some(target)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{parse_bookmark, parse_lnk};
+
+    /// Minimal bookmark holding only a path array.
+    pub(crate) fn bookmark_bytes(components: &[&str]) -> Vec<u8> {
+        let mut data = vec![0u8; 4];
+        let mut offsets = Vec::new();
+        for component in components {
+            offsets.push(data.len() as u32);
+            data.extend((component.len() as u32).to_le_bytes());
+            data.extend(0x0101u32.to_le_bytes());
+            data.extend(component.as_bytes());
+            while !data.len().is_multiple_of(4) {
+                data.push(0);
+            }
+        }
+        let array = data.len() as u32;
+        data.extend((offsets.len() as u32 * 4).to_le_bytes());
+        data.extend(0x0601u32.to_le_bytes());
+        for offset in offsets {
+            data.extend(offset.to_le_bytes());
+        }
+        let toc = data.len() as u32;
+        data[0..4].copy_from_slice(&toc.to_le_bytes());
+        data.extend(32u32.to_le_bytes());
+        data.extend(0xffff_fffeu32.to_le_bytes());
+        data.extend(1u32.to_le_bytes());
+        data.extend(0u32.to_le_bytes());
+        data.extend(1u32.to_le_bytes());
+        data.extend(0x1004u32.to_le_bytes());
+        data.extend(array.to_le_bytes());
+        data.extend(0u32.to_le_bytes());
+
+        let mut bytes = b"book".to_vec();
+        bytes.extend(((0x30 + data.len()) as u32).to_le_bytes());
+        bytes.extend(0x1004_0000u32.to_le_bytes());
+        bytes.extend(0x30u32.to_le_bytes());
+        bytes.resize(0x30, 0);
+        bytes.extend(data);
+        bytes
+    }
+
+    /// Minimal shell link with a LinkInfo block and no ID list.
+    pub(crate) fn lnk_bytes(base: &str, suffix: &str) -> Vec<u8> {
+        let mut bytes = vec![0u8; 0x4c];
+        bytes[0..4].copy_from_slice(&0x4cu32.to_le_bytes());
+        bytes[0x14..0x18].copy_from_slice(&0x2u32.to_le_bytes());
+        let base_offset = 28u32;
+        let suffix_offset = base_offset + base.len() as u32 + 1;
+        let size = suffix_offset + suffix.len() as u32 + 1;
+        for value in [size, 28, 1, 0, base_offset, 0, suffix_offset] {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes.extend(base.as_bytes());
+        bytes.push(0);
+        bytes.extend(suffix.as_bytes());
+        bytes.push(0);
+        bytes
+    }
+
+    #[test]
+    fn parses_bookmark_and_shell_link_targets() {
+        let bookmark = bookmark_bytes(&["Users", "me", "Reports", "Q3 summary.pdf"]);
+        assert_eq!(
+            parse_bookmark(&bookmark).as_deref(),
+            Some("/Users/me/Reports/Q3 summary.pdf")
+        );
+        assert!(parse_bookmark(b"book but not really").is_none());
+
+        let lnk = lnk_bytes("C:\\Users\\me\\", "budget.xlsx");
+        assert_eq!(
+            parse_lnk(&lnk).as_deref(),
+            Some("C:\\Users\\me\\budget.xlsx")
+        );
+        assert!(parse_lnk(&[0u8; 10]).is_none());
+    }
+}
@@ -0,0 +1,142 @@
+use std::env;
+use std::error::Error;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use finder_core::{generate_corpus, CorpusKind, CorpusSpec};
+
+#[derive(Debug)]
+struct Args {
+    out: Option<PathBuf>,
+    spec: CorpusSpec,
+}
+
+impl Args {
+    fn parse() -> Result<Self, String> {
+        let mut args = env::args_os();
+        let _program = args.next();
+        let mut config = Args {
+            out: None,
+            spec: CorpusSpec::default(),
+        };
+
+        while let Some(arg) = args.next() {
+            let arg_str = arg.to_string_lossy();
+            match arg_str.as_ref() {
+                "--help" | "-h" => {
+                    print_usage();
+                    std::process::exit(0);
+                }
+                "--out" => {
+                    let value = next_value(&mut args, "--out")?;
+                    config.out = Some(PathBuf::from(value));
+                }
+                "--files" => {
+                    let value = next_value(&mut args, "--files")?;
+                    config.spec.files = parse_count(&value, "--files")?;
+                }
+                "--depth" => {
+                    let value = next_value(&mut args, "--depth")?;
+                    config.spec.depth = parse_count(&value, "--depth")?;
+                }
+                "--fanout" => {
+                    let value = next_value(&mut args, "--fanout")?;
+                    config.spec.fanout = parse_count(&value, "--fanout")?;
+                }
+                "--mix" => {
+                    let value = next_value(&mut args, "--mix")?;
+                    config.spec.mix = parse_mix(&value.to_string_lossy())?;
+                }
+                "--max-file-kb" => {
+                    let value = next_value(&mut args, "--max-file-kb")?;
+                    let kb = parse_count(&value, "--max-file-kb")? as u64;
+                    config.spec.max_file_bytes = kb.saturating_mul(1024);
+                }
+                "--seed" => {
+                    let value = next_value(&mut args, "--seed")?;
+                    config.spec.seed = parse_count(&value, "--seed")? as u64;
+                }
+                unknown => {
+                    return Err(format!("unknown argument: {}", unknown));
+                }
+            }
+        }
+
+        if config.out.is_none() {
+            return Err("--out must be provided".into());
+        }
+
+        Ok(config)
+    }
+}
+
+fn next_value(args: &mut impl Iterator<Item = OsString>, flag: &str) -> Result<OsString, String> {
+    args.next()
+        .ok_or_else(|| format!("missing value for {}", flag))
+}
+
+/// Integers with an optional `k` or `M` suffix, so `--files 1M` reads
+/// naturally.
+fn parse_count(value: &OsString, flag: &str) -> Result<usize, String> {
+    let raw = value.to_string_lossy();
+    let (digits, scale) = match raw.trim().strip_suffix(['k', 'K']) {
+        Some(digits) => (digits, 1_000),
+        None => match raw.trim().strip_suffix(['m', 'M']) {
+            Some(digits) => (digits, 1_000_000),
+            None => (raw.trim(), 1),
+        },
+    };
+    digits
+        .parse::<usize>()
+        .ok()
+        .and_then(|count| count.checked_mul(scale))
+        .ok_or_else(|| format!("{} expects an integer such as 500, 10k or 1M", flag))
+}
+
+fn parse_mix(list: &str) -> Result<Vec<CorpusKind>, String> {
+    list.split(',')
+        .filter(|item| !item.trim().is_empty())
+        .map(|item| CorpusKind::parse(item).ok_or_else(|| format!("invalid mix kind: {}", item)))
+        .collect()
+}
+
+fn print_usage() {
+    eprintln!("finder-core synthetic corpus generator");
+    eprintln!("\nUsage:");
+    eprintln!("  cargo run -p finder-core --bin bench_gen -- --out <path> [options]\n");
+    eprintln!("Options:");
+    eprintln!("  --out <path>              New or empty folder to fill");
+    eprintln!("  --files <N>               Files to create, e.g. 50k or 1M (default 10000)");
+    eprintln!("  --depth <N>               Deepest folder level (default 6)");
+    eprintln!("  --fanout <N>              Subfolders per folder (default 6)");
+    eprintln!("  --mix <list>              Comma-separated kinds: code,docs,media (default all)");
+    eprintln!("  --max-file-kb <KB>        Cap on any one file (default 1024)");
+    eprintln!("  --seed <N>                Same seed, same tree (default 42)");
+    eprintln!("  --help                    Show this message");
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse().unwrap_or_else(|err| {
+        eprintln!("error: {err}");
+        eprintln!("Use --help to see available options.");
+        std::process::exit(1);
+    });
+    let out = args.out.expect("checked in parse");
+
+    let start = Instant::now();
+    let report = generate_corpus(&out, &args.spec).map_err(|err| {
+        eprintln!("error: {err:#}");
+        err
+    })?;
+    println!(
+        "[GEN] out={} files={} folders={} bytes={}KB seed={} took={:.2}s",
+        out.display(),
+        report.files,
+        report.folders,
+        report.bytes / 1024,
+        args.spec.seed,
+        start.elapsed().as_secs_f64()
+    );
+    Ok(())
+}
@@ -0,0 +1,215 @@
+use std::env;
+use std::ffi::OsString;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use finder_core::{PathFilter, PathFilterEngine};
+
+const FOLDERS: [&str; 12] = [
+    "src",
+    "docs",
+    "node_modules",
+    "build",
+    "target",
+    "photos",
+    "2024",
+    "archive",
+    "old",
+    "clients",
+    "notes",
+    "lib",
+];
+const EXTENSIONS: [&str; 10] = [
+    "rs", "md", "txt", "jpg", "pdf", "json", "ts", "py", "log", "tmp",
+];
+
+#[derive(Debug)]
+struct Args {
+    paths: usize,
+    globs: usize,
+    rounds: usize,
+    seed: u64,
+}
+
+impl Args {
+    fn parse() -> Result<Self, String> {
+        let mut args = env::args_os();
+        let _program = args.next();
+        let mut config = Args {
+            paths: 200_000,
+            globs: 50,
+            rounds: 3,
+            seed: 42,
+        };
+
+        while let Some(arg) = args.next() {
+            let arg_str = arg.to_string_lossy();
+            match arg_str.as_ref() {
+                "--help" | "-h" => {
+                    print_usage();
+                    std::process::exit(0);
+                }
+                "--paths" => {
+                    let value = next_value(&mut args, "--paths")?;
+                    config.paths = parse_number(&value, "--paths")?;
+                }
+                "--globs" => {
+                    let value = next_value(&mut args, "--globs")?;
+                    config.globs = parse_number(&value, "--globs")?;
+                }
+                "--rounds" => {
+                    let value = next_value(&mut args, "--rounds")?;
+                    config.rounds = parse_number(&value, "--rounds")?.max(1);
+                }
+                "--seed" => {
+                    let value = next_value(&mut args, "--seed")?;
+                    config.seed = parse_number(&value, "--seed")? as u64;
+                }
+                unknown => {
+                    return Err(format!("unknown argument: {}", unknown));
+                }
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+fn next_value(args: &mut impl Iterator<Item = OsString>, flag: &str) -> Result<OsString, String> {
+    args.next()
+        .ok_or_else(|| format!("missing value for {}", flag))
+}
+
+fn parse_number(value: &OsString, flag: &str) -> Result<usize, String> {
+    value
+        .to_string_lossy()
+        .trim()
+        .parse()
+        .map_err(|_| format!("{} expects a whole number", flag))
+}
+
+fn print_usage() {
+    eprintln!("finder-core path filter benchmark: glob sets vs one compiled automaton");
+    eprintln!("\nUsage:");
+    eprintln!("  cargo run --release -p finder-core --bin bench_globs -- [options]\n");
+    eprintln!("Options:");
+    eprintln!("  --paths <N>               Synthetic paths to filter (default 200000)");
+    eprintln!(
+        "  --globs <N>               Globs, split evenly into include and exclude (default 50)"
+    );
+    eprintln!(
+        "  --rounds <N>              Timed passes per engine; the fastest counts (default 3)"
+    );
+    eprintln!("  --seed <N>                Same seed, same paths and globs (default 42)");
+    eprintln!("  --help                    Show this message");
+}
+
+/// Deterministic paths shaped like a home folder: a few folder levels,
+/// some with suffixes, and a numbered file with a common extension.
+fn synthetic_paths(count: usize, seed: u64) -> Vec<String> {
+    let mut state = seed;
+    let mut next = move |bound: usize| {
+        state = state
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        ((state >> 33) as usize) % bound
+    };
+    (0..count)
+        .map(|_| {
+            let mut path = String::from("/Users/me");
+            for level in 0..1 + next(6) {
+                path.push('/');
+                path.push_str(FOLDERS[next(FOLDERS.len())]);
+                if level % 2 == 1 {
+                    path.push_str("-x");
+                }
+            }
+            let ext = EXTENSIONS[next(EXTENSIONS.len())];
+            path.push_str(&format!("/file{}.{ext}", next(1000)));
+            path
+        })
+        .collect()
+}
+
+/// A rule set of the kinds people write: extensions, folders anywhere,
+/// anchored folder prefixes and wildcarded names.
+fn synthetic_globs(count: usize) -> Vec<String> {
+    let mut globs: Vec<String> = EXTENSIONS.iter().map(|ext| format!("**/*.{ext}")).collect();
+    for folder in FOLDERS {
+        globs.push(format!("**/{folder}/**"));
+        globs.push(format!("**/{folder}-x/*.tmp"));
+        globs.push(format!("/Users/me/{folder}*/**"));
+    }
+    globs.extend((0..10).map(|n| format!("**/file{n}?.*")));
+    globs.truncate(count);
+    globs
+}
+
+fn run(
+    engine: PathFilterEngine,
+    include: &[String],
+    exclude: &[String],
+    paths: &[String],
+    rounds: usize,
+) -> Result<(Duration, Duration, usize), String> {
+    let start = Instant::now();
+    let filter = PathFilter::new(include, exclude, engine)
+        .map_err(|err| format!("{err:#}"))?
+        .ok_or("no globs to benchmark")?;
+    let build = start.elapsed();
+    let mut best = Duration::MAX;
+    let mut allowed = 0;
+    for _ in 0..rounds {
+        let start = Instant::now();
+        allowed = paths
+            .iter()
+            .filter(|path| filter.allows(black_box(path)))
+            .count();
+        best = best.min(start.elapsed());
+    }
+    Ok((build, best, allowed))
+}
+
+fn main() {
+    let args = Args::parse().unwrap_or_else(|err| {
+        eprintln!("error: {err}");
+        eprintln!("Use --help to see available options.");
+        std::process::exit(1);
+    });
+
+    let paths = synthetic_paths(args.paths, args.seed);
+    let globs = synthetic_globs(args.globs);
+    let (include, exclude) = globs.split_at(globs.len().div_ceil(2));
+    println!(
+        "[BENCH] paths={} include={} exclude={}",
+        paths.len(),
+        include.len(),
+        exclude.len()
+    );
+
+    let mut counts = Vec::new();
+    for (label, engine) in [
+        ("globset", PathFilterEngine::GlobSet),
+        ("automaton", PathFilterEngine::Automaton),
+    ] {
+        match run(engine, include, exclude, &paths, args.rounds) {
+            Ok((build, best, allowed)) => {
+                println!(
+                    "[BENCH] engine={label} build={:.2}ms filter={:.1}ms per_path={:.0}ns allowed={allowed}",
+                    build.as_secs_f64() * 1000.0,
+                    best.as_secs_f64() * 1000.0,
+                    best.as_nanos() as f64 / paths.len().max(1) as f64,
+                );
+                counts.push(allowed);
+            }
+            Err(err) => {
+                eprintln!("error: {err}");
+                std::process::exit(1);
+            }
+        }
+    }
+    if counts.windows(2).any(|pair| pair[0] != pair[1]) {
+        eprintln!("error: engines disagree on which paths pass");
+        std::process::exit(1);
+    }
+}
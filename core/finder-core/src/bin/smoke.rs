@@ -7,24 +7,28 @@ use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
 use finder_core::{
-    add_or_update_file, commit, init_index, load_index_state, read_plain_text, scan_root, search,
-    IndexUpdate, IndexedDocument, PlainTextExtraction, SearchDomain, SearchQuery,
+    add_or_update_batch, add_or_update_file, add_or_update_file_streaming, commit,
+    default_index_dir, evaluate, init_index, load_index_state, load_judgments,
+    read_plain_text_with, scan_root_with_rules, search, stream_plain_text, FileMeta, IndexUpdate,
+    IndexedDocument, NoiseRules, NormalizeOptions, PlainTextExtraction, RankingProfile,
+    SearchDomain, SearchQuery, SniffConfig, SniffDecision,
 };
 
-const DEFAULT_INDEX_DIR: &str = "/tmp/finder-index";
 const DEFAULT_COMMIT_THRESHOLD: usize = 1000;
 const DEFAULT_COMMIT_INTERVAL_MS: u64 = 2_000;
 const DEFAULT_MAX_BYTES: u64 = 1_572_864;
 const DEFAULT_LIMIT: usize = 50;
-const DEFAULT_SKIP_EXT: &str = ".pkg,.dmg,.app";
+const DEFAULT_SKIP_EXT: &str = ".pkg,.dmg";
 const BENCH_RUNS: usize = 5;
+const DEFAULT_EVAL_K: usize = 10;
 
 #[derive(Debug)]
 struct Args {
-    index_dir: PathBuf,
+    /// `None` uses the platform default from `default_index_dir`.
+    index_dir: Option<PathBuf>,
     roots: Vec<PathBuf>,
     queries: Vec<String>,
-    glob: Option<String>,
+    globs: Vec<String>,
     commit_every: usize,
     commit_ms: u64,
     limit: usize,
@@ -33,17 +37,32 @@ struct Args {
     writer_mem_mb: usize,
     max_bytes: u64,
     sniff_bytes: usize,
+    binary_ratio: f32,
+    stream_chunk_bytes: Option<usize>,
     skip_exts: Vec<String>,
     scope: SearchDomain,
+    entities: bool,
+    normalize: bool,
+    stem: bool,
+    summaries: bool,
+    metadata_only: bool,
+    in_memory: bool,
+    low_memory: bool,
+    burst: usize,
+    noise_names: Vec<String>,
+    noise_hashes: Vec<String>,
+    ranking: RankingProfile,
+    judgments: Option<PathBuf>,
+    eval_k: usize,
 }
 
 impl Default for Args {
     fn default() -> Self {
         Self {
-            index_dir: PathBuf::from(DEFAULT_INDEX_DIR),
+            index_dir: None,
             roots: Vec::new(),
             queries: Vec::new(),
-            glob: None,
+            globs: Vec::new(),
             commit_every: DEFAULT_COMMIT_THRESHOLD,
             commit_ms: DEFAULT_COMMIT_INTERVAL_MS,
             limit: DEFAULT_LIMIT,
@@ -52,8 +71,23 @@ impl Default for Args {
             writer_mem_mb: 384,
             max_bytes: DEFAULT_MAX_BYTES,
             sniff_bytes: 8192,
+            binary_ratio: SniffConfig::default().max_non_printable_ratio,
+            stream_chunk_bytes: None,
             skip_exts: parse_exts(DEFAULT_SKIP_EXT),
             scope: SearchDomain::Both,
+            entities: false,
+            normalize: false,
+            stem: false,
+            summaries: false,
+            metadata_only: false,
+            in_memory: false,
+            low_memory: false,
+            burst: 0,
+            noise_names: Vec::new(),
+            noise_hashes: Vec::new(),
+            ranking: RankingProfile::default(),
+            judgments: None,
+            eval_k: DEFAULT_EVAL_K,
         }
     }
 }
@@ -73,7 +107,7 @@ impl Args {
                 }
                 "--index-dir" => {
                     let value = next_value(&mut args, "--index-dir")?;
-                    config.index_dir = PathBuf::from(value);
+                    config.index_dir = Some(PathBuf::from(value));
                 }
                 "--root" => {
                     let value = next_value(&mut args, "--root")?;
@@ -85,7 +119,7 @@ impl Args {
                 }
                 "--glob" => {
                     let value = next_value(&mut args, "--glob")?;
-                    config.glob = Some(value.to_string_lossy().to_string());
+                    config.globs.push(value.to_string_lossy().to_string());
                 }
                 "--commit-every" => {
                     let value = next_value(&mut args, "--commit-every")?;
@@ -102,6 +136,43 @@ impl Args {
                 "--reindex" => {
                     config.reindex = true;
                 }
+                "--entities" => {
+                    config.entities = true;
+                }
+                "--normalize" => {
+                    config.normalize = true;
+                }
+                "--stem" => {
+                    config.stem = true;
+                }
+                "--summaries" => {
+                    config.summaries = true;
+                }
+                "--metadata-only" => {
+                    config.metadata_only = true;
+                }
+                "--in-memory" => {
+                    config.in_memory = true;
+                }
+                "--low-memory" => {
+                    config.low_memory = true;
+                }
+                "--burst" => {
+                    let value = next_value(&mut args, "--burst")?;
+                    config.burst = parse_usize(&value, "--burst")?;
+                }
+                "--noise-name" => {
+                    let value = next_value(&mut args, "--noise-name")?;
+                    config
+                        .noise_names
+                        .extend(parse_list(&value.to_string_lossy()));
+                }
+                "--noise-hash" => {
+                    let value = next_value(&mut args, "--noise-hash")?;
+                    config
+                        .noise_hashes
+                        .extend(parse_list(&value.to_string_lossy()));
+                }
                 "--threads" => {
                     let value = next_value(&mut args, "--threads")?;
                     let parsed = parse_usize(&value, "--threads")?;
@@ -119,6 +190,18 @@ impl Args {
                     let value = next_value(&mut args, "--sniff-bytes")?;
                     config.sniff_bytes = parse_usize(&value, "--sniff-bytes")?;
                 }
+                "--binary-ratio" => {
+                    let value = next_value(&mut args, "--binary-ratio")?;
+                    config.binary_ratio = value
+                        .to_string_lossy()
+                        .parse::<f32>()
+                        .map_err(|_| "--binary-ratio expects a number".to_string())?;
+                }
+                "--stream-chunk-kb" => {
+                    let value = next_value(&mut args, "--stream-chunk-kb")?;
+                    let kb = parse_usize(&value, "--stream-chunk-kb")?;
+                    config.stream_chunk_bytes = Some(kb.saturating_mul(1024));
+                }
                 "--skip-ext" => {
                     let value = next_value(&mut args, "--skip-ext")?;
                     config.skip_exts = parse_exts(&value.to_string_lossy());
@@ -127,6 +210,18 @@ impl Args {
                     let value = next_value(&mut args, "--scope")?;
                     config.scope = parse_scope(&value.to_string_lossy())?;
                 }
+                "--ranking" => {
+                    let value = next_value(&mut args, "--ranking")?;
+                    config.ranking = parse_ranking(&value.to_string_lossy())?;
+                }
+                "--judgments" => {
+                    let value = next_value(&mut args, "--judgments")?;
+                    config.judgments = Some(PathBuf::from(value));
+                }
+                "--eval-k" => {
+                    let value = next_value(&mut args, "--eval-k")?;
+                    config.eval_k = parse_usize(&value, "--eval-k")?;
+                }
                 unknown => {
                     return Err(format!("unknown argument: {}", unknown));
                 }
@@ -145,6 +240,10 @@ impl Args {
             return Err("--commit-ms must be greater than 0".into());
         }
 
+        if config.eval_k == 0 {
+            return Err("--eval-k must be greater than 0".into());
+        }
+
         Ok(config)
     }
 }
@@ -175,6 +274,13 @@ fn parse_exts(list: &str) -> Vec<String> {
         .collect()
 }
 
+fn parse_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
 fn parse_scope(value: &str) -> Result<SearchDomain, String> {
     match value.to_lowercase().as_str() {
         "name" => Ok(SearchDomain::Name),
@@ -184,15 +290,23 @@ fn parse_scope(value: &str) -> Result<SearchDomain, String> {
     }
 }
 
+fn parse_ranking(value: &str) -> Result<RankingProfile, String> {
+    match value.to_lowercase().as_str() {
+        "shallow" => Ok(RankingProfile::Shallow),
+        "recent" => Ok(RankingProfile::Recent),
+        other => Err(format!("invalid ranking: {}", other)),
+    }
+}
+
 fn print_usage() {
     eprintln!("finder-core smoke test");
     eprintln!("\nUsage:");
     eprintln!("  cargo run -p finder-core --bin smoke -- --root <path> [options]\n");
     eprintln!("Options:");
-    eprintln!("  --index-dir <path>        Index directory (default: /tmp/finder-index)");
+    eprintln!("  --index-dir <path>        Index directory (default: per-user data directory)");
     eprintln!("  --root <path>             Root folder to scan (repeatable)");
     eprintln!("  --q <query>               Query to benchmark (repeatable)");
-    eprintln!("  --glob <pattern>          Optional glob filter");
+    eprintln!("  --glob <pattern>          Path glob filter; repeat to match any of several");
     eprintln!("  --threads <N>             Tantivy writer threads (default num_cpus)");
     eprintln!("  --writer-mem-mb <MB>      Tantivy writer memory in MB (default 384)");
     eprintln!("  --commit-every <N>        Commit every N documents (default 1000)");
@@ -200,17 +314,37 @@ fn print_usage() {
     eprintln!("  --max-bytes <B>           Skip files larger than this (default 1572864)");
     eprintln!("  --sniff-bytes <N>         Bytes to sniff for binary detection (default 8192)");
     eprintln!(
-        "  --skip-ext <list>         Comma-separated extensions to skip (default .pkg,.dmg,.app)"
+        "  --binary-ratio <R>        Non-printable fraction treated as binary (default 0.10)"
     );
+    eprintln!("  --stream-chunk-kb <KB>    Stream content into the index in KB-sized chunks");
+    eprintln!("  --skip-ext <list>         Comma-separated extensions to skip (default .pkg,.dmg)");
+    eprintln!("  --noise-name <list>       Extra comma-separated file names to skip as noise");
+    eprintln!("  --noise-hash <list>       Comma-separated content checksums to skip as noise");
     eprintln!("  --scope <name|content|both>  Default scope for bare queries (default both)");
     eprintln!("  --limit <N>               Max hits per query (default 50)");
+    eprintln!("  --ranking <shallow|recent>  Tie-break profile for queries (default shallow)");
+    eprintln!("  --judgments <file>        Score precision@k and MRR against query<TAB>path lines");
+    eprintln!("  --eval-k <N>              Cutoff k for --judgments (default 10)");
     eprintln!("  --reindex                 Remove index directory before indexing");
+    eprintln!("  --normalize               Collapse whitespace, strip page boilerplate and base64");
+    eprintln!("  --stem                    Index content with English stemming (new indexes only)");
+    eprintln!(
+        "  --low-memory              Single writer thread, minimal heap and stored-field cache"
+    );
+    eprintln!("  --in-memory               Use a throwaway RAM index instead of --index-dir");
+    eprintln!(
+        "  --burst <N>               Time N synthetic updates per-file vs batched (adds N docs; pair with --in-memory)"
+    );
+    eprintln!("  --metadata-only           Index names and metadata only; never read content");
+    eprintln!("  --summaries               Store lead-sentence summaries and print them with hits");
+    eprintln!("  --entities                Index emails, phones, dates and amounts from content");
     eprintln!("  --help                    Show this message");
 }
 
 #[derive(Default)]
 struct Stats {
     files_seen: usize,
+    skipped_noise: usize,
     added: usize,
     updated: usize,
     skipped_dedup: usize,
@@ -236,12 +370,14 @@ fn main() -> Result<(), Box<dyn Error>> {
 }
 
 fn run(args: Args) -> Result<(), Box<dyn Error>> {
-    if args.reindex && args.index_dir.exists() {
-        println!(
-            "[INFO] removing existing index dir {}",
-            args.index_dir.display()
-        );
-        fs::remove_dir_all(&args.index_dir)?;
+    let index_dir = match (&args.index_dir, args.in_memory) {
+        (Some(dir), _) => dir.clone(),
+        (None, false) => default_index_dir()?,
+        (None, true) => PathBuf::new(),
+    };
+    if args.reindex && !args.in_memory && index_dir.exists() {
+        println!("[INFO] removing existing index dir {}", index_dir.display());
+        fs::remove_dir_all(&index_dir)?;
     }
 
     let writer_threads = args
@@ -250,9 +386,39 @@ fn run(args: Args) -> Result<(), Box<dyn Error>> {
     finder_core::configure_indexer(finder_core::IndexSettings {
         writer_threads,
         writer_heap_bytes: args.writer_mem_mb.saturating_mul(1024 * 1024),
+        extract_entities: args.entities,
+        metadata_only: args.metadata_only,
+        low_memory: args.low_memory,
+        // Smoke reads files itself rather than through `index_root`, so
+        // there are no extraction reservations to govern.
+        soft_memory_limit_bytes: 0,
+        query_limits: finder_core::QueryLimits::default(),
+        ranking: finder_core::RankingConfig::default(),
+        // Every timed query should reach the index.
+        result_cache_entries: 0,
+        content_analyzer: if args.stem {
+            finder_core::ContentAnalyzer::English
+        } else {
+            finder_core::ContentAnalyzer::Plain
+        },
+        normalize: if args.normalize {
+            NormalizeOptions::all()
+        } else {
+            NormalizeOptions::default()
+        },
     });
 
-    init_index(path_to_str(&args.index_dir)?)?;
+    if args.summaries {
+        finder_core::set_summarizer(Some(std::sync::Arc::new(
+            finder_core::LeadSentences::default(),
+        )));
+    }
+
+    if args.in_memory {
+        finder_core::init_index_in_memory()?;
+    } else {
+        init_index(path_to_str(&index_dir)?)?;
+    }
 
     let mut existing_index: HashMap<String, IndexedDocument> = if args.reindex {
         HashMap::new()
@@ -278,13 +444,24 @@ fn run(args: Args) -> Result<(), Box<dyn Error>> {
         args.scope
     );
 
+    let sniff_config = SniffConfig {
+        max_non_printable_ratio: args.binary_ratio,
+        ..SniffConfig::default()
+    };
+    let noise_rules = NoiseRules::default()
+        .with_names(&args.noise_names)
+        .with_hashes(&args.noise_hashes);
+
     for root in &args.roots {
         let scan_start = Instant::now();
-        let metas = scan_root(root)?;
+        let report = scan_root_with_rules(root, &noise_rules)?;
+        let metas = report.files;
+        stats.skipped_noise += report.skipped_noise;
         println!(
-            "[INFO] scan completed for {}: {} files ({} s)",
+            "[INFO] scan completed for {}: {} files, {} noise skipped ({} s)",
             root.display(),
             metas.len(),
+            report.skipped_noise,
             format_seconds(scan_start.elapsed())
         );
 
@@ -319,28 +496,63 @@ fn run(args: Args) -> Result<(), Box<dyn Error>> {
 
             let snapshot = IndexedDocument::from_meta(&meta);
             let limit = args.max_bytes.min(usize::MAX as u64) as usize;
-            let PlainTextExtraction {
-                content: content_opt,
-                bytes_read,
-                was_binary,
-            } = match read_plain_text(Path::new(&meta.path), limit, args.sniff_bytes) {
-                Ok(extraction) => extraction,
-                Err(err) => {
-                    eprintln!("[WARN] failed to read {}: {err}", meta.path);
-                    PlainTextExtraction {
-                        content: None,
-                        bytes_read: 0,
-                        was_binary: false,
+            let update = if args.metadata_only || meta.skips_content() {
+                add_or_update_file(meta, None, args.reindex)?
+            } else if let Some(chunk_bytes) = args.stream_chunk_bytes {
+                let path = meta.path.clone();
+                add_or_update_file_streaming(meta, args.reindex, |sink| {
+                    match stream_plain_text(
+                        Path::new(&path),
+                        limit,
+                        args.sniff_bytes,
+                        chunk_bytes,
+                        &sniff_config,
+                        |chunk| {
+                            sink.push(chunk);
+                            Ok(())
+                        },
+                    ) {
+                        Ok(streamed) => {
+                            stats.bytes_read += streamed.bytes_read;
+                            if streamed.was_binary {
+                                stats.skipped_binary += 1;
+                            }
+                        }
+                        Err(err) => eprintln!("[WARN] failed to read {path}: {err}"),
                     }
-                }
-            };
+                    Ok(())
+                })?
+            } else {
+                let PlainTextExtraction {
+                    content: content_opt,
+                    bytes_read,
+                    was_binary,
+                    ..
+                } = match read_plain_text_with(
+                    Path::new(&meta.path),
+                    limit,
+                    args.sniff_bytes,
+                    &sniff_config,
+                ) {
+                    Ok(extraction) => extraction,
+                    Err(err) => {
+                        eprintln!("[WARN] failed to read {}: {err}", meta.path);
+                        PlainTextExtraction {
+                            content: None,
+                            bytes_read: 0,
+                            was_binary: false,
+                            sniff: SniffDecision::default(),
+                        }
+                    }
+                };
 
-            stats.bytes_read += bytes_read;
-            if was_binary {
-                stats.skipped_binary += 1;
-            }
+                stats.bytes_read += bytes_read;
+                if was_binary {
+                    stats.skipped_binary += 1;
+                }
 
-            let update = add_or_update_file(meta, content_opt, args.reindex)?;
+                add_or_update_file(meta, content_opt, args.reindex)?
+            };
             match update {
                 IndexUpdate::Added => stats.added += 1,
                 IndexUpdate::Updated => stats.updated += 1,
@@ -370,8 +582,9 @@ fn run(args: Args) -> Result<(), Box<dyn Error>> {
 
     let total_elapsed = start.elapsed();
     println!(
-        "[INFO] files={} added={} updated={} skipped_dedup={} skipped_large={} skipped_ext={} skipped_zero={} skipped_binary={} bytes_read={}KB commits={} total={} s throughput={:.1} docs/min",
+        "[INFO] files={} skipped_noise={} added={} updated={} skipped_dedup={} skipped_large={} skipped_ext={} skipped_zero={} skipped_binary={} bytes_read={}KB commits={} total={} s throughput={:.1} docs/min",
         stats.files_seen,
+        stats.skipped_noise,
         stats.added,
         stats.updated,
         stats.skipped_dedup,
@@ -385,7 +598,25 @@ fn run(args: Args) -> Result<(), Box<dyn Error>> {
         docs_per_minute(&stats, total_elapsed)
     );
 
+    let index = finder_core::index_stats()?;
+    println!(
+        "[INFO] index docs={} segments={} content_docs={} metadata_only_docs={} metadata_only={}",
+        index.num_docs,
+        index.num_segments,
+        index.content_docs,
+        index.metadata_only_docs,
+        index.metadata_only
+    );
+    print_size_breakdown()?;
+    if args.burst > 0 {
+        run_burst(args.burst)?;
+    }
+    if let Some(judgments) = &args.judgments {
+        run_eval(judgments, &args)?;
+    }
+
     if args.queries.is_empty() {
+        print_memory_stats()?;
         return Ok(());
     }
 
@@ -395,8 +626,10 @@ fn run(args: Args) -> Result<(), Box<dyn Error>> {
         let search_query = SearchQuery {
             term: term.clone(),
             search_in: domain,
-            path_glob: args.glob.clone(),
+            path_globs: args.globs.clone(),
             limit: args.limit,
+            ranking: args.ranking,
+            ..SearchQuery::default()
         };
 
         let mut durations = Vec::with_capacity(BENCH_RUNS);
@@ -424,9 +657,125 @@ fn run(args: Args) -> Result<(), Box<dyn Error>> {
 
         for hit in last_results.iter().take(5) {
             println!("  • {} — {}", hit.name, hit.path);
+            if let Some(summary) = &hit.summary {
+                println!("      {}", summary);
+            }
         }
     }
 
+    print_memory_stats()
+}
+
+/// Score the golden set in `path` against the index just built.
+fn run_eval(path: &Path, args: &Args) -> Result<(), Box<dyn Error>> {
+    let judgments = load_judgments(path)?;
+    let template = SearchQuery {
+        search_in: args.scope,
+        path_globs: args.globs.clone(),
+        ranking: args.ranking,
+        ..SearchQuery::default()
+    };
+    let report = evaluate(&judgments, &template, args.eval_k)?;
+    for query in &report.queries {
+        println!(
+            "[EVAL] query=\"{}\" p@{}={:.3} rr={:.3} missed={}",
+            query.query,
+            report.k,
+            query.precision_at_k,
+            query.reciprocal_rank,
+            query.missed.len()
+        );
+    }
+    println!(
+        "[EVAL] queries={} ranking={:?} mean_p@{}={:.3} mrr={:.3}",
+        report.queries.len(),
+        args.ranking,
+        report.k,
+        report.mean_precision_at_k,
+        report.mrr
+    );
+    Ok(())
+}
+
+/// Compare per-file and batched updates on a burst of `count` modified files.
+fn run_burst(count: usize) -> Result<(), Box<dyn Error>> {
+    let burst = |mtime: i64| -> Vec<(FileMeta, Option<String>)> {
+        (0..count)
+            .map(|i| {
+                let meta = FileMeta {
+                    path: format!("/__burst__/file-{i}.txt"),
+                    name: format!("file-{i}.txt"),
+                    ext: Some("txt".into()),
+                    modified_at: mtime,
+                    size: 64,
+                    ..FileMeta::default()
+                };
+                (meta, Some(format!("burst revision {mtime} of file {i}")))
+            })
+            .collect()
+    };
+
+    add_or_update_batch(burst(1), false)?;
+    commit()?;
+
+    let per_file_start = Instant::now();
+    for (meta, content) in burst(2) {
+        add_or_update_file(meta, content, false)?;
+    }
+    commit()?;
+    let per_file = per_file_start.elapsed();
+
+    let batched_start = Instant::now();
+    add_or_update_batch(burst(3), false)?;
+    commit()?;
+    let batched = batched_start.elapsed();
+
+    println!(
+        "[BURST] files={} per_file={}ms batched={}ms",
+        count,
+        per_file.as_millis(),
+        batched.as_millis()
+    );
+    Ok(())
+}
+
+fn print_memory_stats() -> Result<(), Box<dyn Error>> {
+    let memory = finder_core::memory_stats()?;
+    println!(
+        "[MEM] low_memory={} writer_threads={} writer_heap={}MB doc_cache={}/{} blocks (~{}KB, hits={} misses={}) index_disk={}KB",
+        memory.low_memory,
+        memory.writer_threads,
+        memory.writer_heap_bytes / (1024 * 1024),
+        memory.doc_store_cache_entries,
+        memory.doc_store_cache_blocks,
+        memory.doc_store_cache_bytes / 1024,
+        memory.doc_store_cache_hits,
+        memory.doc_store_cache_misses,
+        memory.index_disk_bytes / 1024
+    );
+    Ok(())
+}
+
+fn print_size_breakdown() -> Result<(), Box<dyn Error>> {
+    let breakdown = finder_core::index_size_breakdown()?;
+    let store: u64 = breakdown.segments.iter().map(|s| s.store_bytes).sum();
+    println!(
+        "[SIZE] total={}KB segments={} stored={}KB",
+        breakdown.total_bytes / 1024,
+        breakdown.segments.len(),
+        store / 1024
+    );
+    for field in breakdown.fields.iter().take(5) {
+        println!(
+            "  {}={}KB (postings={}KB positions={}KB terms={}KB fast={}KB)",
+            field.field,
+            field.total_bytes / 1024,
+            field.postings_bytes / 1024,
+            field.positions_bytes / 1024,
+            field.termdict_bytes / 1024,
+            field.fast_field_bytes / 1024
+        );
+    }
     Ok(())
 }
 
@@ -0,0 +1,142 @@
+use std::env;
+use std::error::Error;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use finder_core::{init_index, init_index_in_memory, run_soak, SoakConfig};
+
+#[derive(Debug, Default)]
+struct Args {
+    /// New or empty folder to mutate; a fresh temporary folder when unset.
+    dir: Option<PathBuf>,
+    /// On-disk index; an in-memory one when unset.
+    index_dir: Option<PathBuf>,
+    config: SoakConfig,
+}
+
+impl Args {
+    fn parse() -> Result<Self, String> {
+        let mut args = env::args_os();
+        let _program = args.next();
+        let mut config = Args::default();
+
+        while let Some(arg) = args.next() {
+            let arg_str = arg.to_string_lossy();
+            match arg_str.as_ref() {
+                "--help" | "-h" => {
+                    print_usage();
+                    std::process::exit(0);
+                }
+                "--dir" => {
+                    let value = next_value(&mut args, "--dir")?;
+                    config.dir = Some(PathBuf::from(value));
+                }
+                "--index-dir" => {
+                    let value = next_value(&mut args, "--index-dir")?;
+                    config.index_dir = Some(PathBuf::from(value));
+                }
+                "--secs" => {
+                    let value = next_value(&mut args, "--secs")?;
+                    config.config.duration = Duration::from_secs(parse_u64(&value, "--secs")?);
+                }
+                "--files" => {
+                    let value = next_value(&mut args, "--files")?;
+                    config.config.files = parse_u64(&value, "--files")? as usize;
+                }
+                "--query-threads" => {
+                    let value = next_value(&mut args, "--query-threads")?;
+                    config.config.query_threads = parse_u64(&value, "--query-threads")? as usize;
+                }
+                "--index-ms" => {
+                    let value = next_value(&mut args, "--index-ms")?;
+                    config.config.index_interval =
+                        Duration::from_millis(parse_u64(&value, "--index-ms")?);
+                }
+                "--settle-ms" => {
+                    let value = next_value(&mut args, "--settle-ms")?;
+                    config.config.settle = Duration::from_millis(parse_u64(&value, "--settle-ms")?);
+                }
+                "--seed" => {
+                    let value = next_value(&mut args, "--seed")?;
+                    config.config.seed = parse_u64(&value, "--seed")?;
+                }
+                unknown => {
+                    return Err(format!("unknown argument: {}", unknown));
+                }
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+fn next_value(args: &mut impl Iterator<Item = OsString>, flag: &str) -> Result<OsString, String> {
+    args.next()
+        .ok_or_else(|| format!("missing value for {}", flag))
+}
+
+fn parse_u64(value: &OsString, flag: &str) -> Result<u64, String> {
+    value
+        .to_string_lossy()
+        .parse::<u64>()
+        .map_err(|_| format!("{} expects an integer", flag))
+}
+
+fn print_usage() {
+    eprintln!("finder-core soak test");
+    eprintln!("\nUsage:");
+    eprintln!("  cargo run -p finder-core --bin soak -- [options]\n");
+    eprintln!("Options:");
+    eprintln!("  --dir <path>              New or empty folder to mutate (default: temporary)");
+    eprintln!("  --index-dir <path>        Index directory (default: in memory)");
+    eprintln!("  --secs <N>                How long to run (default 60)");
+    eprintln!("  --files <N>               Files kept in the tree (default 200)");
+    eprintln!("  --query-threads <N>       Concurrent searching threads (default 2)");
+    eprintln!("  --index-ms <T>            Pause between indexing passes (default 250)");
+    eprintln!("  --settle-ms <T>           Longest a change may take to show up (default 3000)");
+    eprintln!("  --seed <N>                Seed for the mutation sequence (default 1)");
+    eprintln!("  --help                    Show this message");
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse().unwrap_or_else(|err| {
+        eprintln!("error: {err}");
+        eprintln!("Use --help to see available options.");
+        std::process::exit(1);
+    });
+
+    match &args.index_dir {
+        Some(dir) => init_index(&dir.to_string_lossy())?,
+        None => init_index_in_memory()?,
+    }
+    let dir = args
+        .dir
+        .clone()
+        .unwrap_or_else(|| env::temp_dir().join(format!("finder-soak-{}", std::process::id())));
+    println!(
+        "[SOAK] dir={} secs={} files={} query_threads={} seed={}",
+        dir.display(),
+        args.config.duration.as_secs(),
+        args.config.files,
+        args.config.query_threads,
+        args.config.seed
+    );
+    let report = run_soak(&dir, &args.config)?;
+    println!(
+        "[SOAK] mutations={} index_passes={} index_errors={} queries={} checks={} violations={}",
+        report.mutations,
+        report.index_passes,
+        report.index_errors,
+        report.queries,
+        report.checks,
+        report.violation_count
+    );
+    for violation in &report.violations {
+        println!("  ✗ {violation}");
+    }
+    if !report.is_clean() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,258 @@
+use crate::git::TrackedFiles;
+use crate::indexer::{self, load_index_state};
+use crate::pipeline::{has_skipped_ext, index_file, IndexReport, RootOptions};
+use crate::roots::normalize_roots;
+use crate::scanner::{scan_root_with, FileMeta};
+use anyhow::Result;
+use std::collections::HashSet;
+use std::path::Path;
+
+const DEFAULT_RECENT_CONTENT_FILES: usize = 20_000;
+const DEFAULT_BACKFILL_COMMIT_EVERY: usize = 5_000;
+
+/// First-run "fast start": names for everything, then content for the most
+/// recently modified files, then content for the rest.
+#[derive(Debug, Clone)]
+pub struct BootstrapOptions {
+    /// Applied to every root.
+    pub root: RootOptions,
+    /// How many of the newest files get content in the second phase.
+    pub recent_content_files: usize,
+    /// Commit during backfill after this many files so search results keep
+    /// improving while it runs. `0` commits only at the end.
+    pub backfill_commit_every: usize,
+}
+
+impl Default for BootstrapOptions {
+    fn default() -> Self {
+        Self {
+            root: RootOptions::default(),
+            recent_content_files: DEFAULT_RECENT_CONTENT_FILES,
+            backfill_commit_every: DEFAULT_BACKFILL_COMMIT_EVERY,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootstrapPhase {
+    Names,
+    RecentContent,
+    Backfill,
+    Done,
+}
+
+/// Emitted when a phase starts and after every file within it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BootstrapProgress {
+    pub phase: BootstrapPhase,
+    pub completed: usize,
+    pub total: usize,
+}
+
+/// Per-phase counters for [`bootstrap_roots`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BootstrapReport {
+    pub names: IndexReport,
+    pub recent_content: IndexReport,
+    pub backfill: IndexReport,
+}
+
+/// Index `roots` in three committed phases so the app is searchable by name
+/// almost immediately and by content for recent work shortly after. Files
+/// already indexed with content and unchanged since are left alone, so an
+/// interrupted bootstrap can simply be run again. Overlapping roots are
+/// collapsed as in [`index_roots`](crate::index_roots).
+pub fn bootstrap_roots<P, F>(
+    roots: &[P],
+    options: &BootstrapOptions,
+    mut on_progress: F,
+) -> Result<BootstrapReport>
+where
+    P: AsRef<Path>,
+    F: FnMut(BootstrapProgress),
+{
+    let metadata_only = options.root.metadata_only || indexer::settings()?.metadata_only;
+    let existing = load_index_state()?;
+    let mut report = BootstrapReport::default();
+
+    let mut files: Vec<FileMeta> = Vec::new();
+    let mut seen = HashSet::new();
+    let mut tracked = options.root.git_tracked_only.then(TrackedFiles::default);
+    for root in normalize_roots(roots).roots {
+        let scan = scan_root_with(root.as_ref(), &options.root.noise, metadata_only)?;
+        report.names.skipped_noise += scan.skipped_noise;
+        for meta in scan.files {
+            report.names.files_seen += 1;
+            let untracked = tracked
+                .as_mut()
+                .map(|tracked| tracked.is_untracked(Path::new(&meta.path)))
+                .unwrap_or(false);
+            if !seen.insert(meta.identity()) {
+                report.names.skipped_duplicate += 1;
+            } else if untracked {
+                report.names.skipped_untracked += 1;
+            } else if has_skipped_ext(meta.ext.as_deref(), &options.root.skip_exts) {
+                report.names.skipped_ext += 1;
+            } else {
+                files.push(meta);
+            }
+        }
+    }
+    // Roots are each sorted newest-first; merge them so "recent" is global.
+    files.sort_by_key(|meta| std::cmp::Reverse(meta.modified_at));
+
+    let total = files.len();
+    on_progress(BootstrapProgress {
+        phase: BootstrapPhase::Names,
+        completed: 0,
+        total,
+    });
+    let mut pending_content = Vec::new();
+    for (done, meta) in files.into_iter().enumerate() {
+        let complete = existing
+            .get(&meta.identity())
+            .map(|doc| doc.has_content && doc.matches_meta(&meta))
+            .unwrap_or(false);
+        if complete {
+            report.names.unchanged += 1;
+        } else {
+            if !metadata_only {
+                pending_content.push(meta.clone());
+            }
+            index_file(meta, &options.root, false, true, &mut report.names)?;
+        }
+        on_progress(BootstrapProgress {
+            phase: BootstrapPhase::Names,
+            completed: done + 1,
+            total,
+        });
+    }
+    indexer::commit()?;
+
+    let split = options.recent_content_files.min(pending_content.len());
+    let backfill = pending_content.split_off(split);
+    run_content_phase(
+        BootstrapPhase::RecentContent,
+        pending_content,
+        options,
+        0,
+        &mut report.recent_content,
+        &mut on_progress,
+    )?;
+    run_content_phase(
+        BootstrapPhase::Backfill,
+        backfill,
+        options,
+        options.backfill_commit_every,
+        &mut report.backfill,
+        &mut on_progress,
+    )?;
+
+    on_progress(BootstrapProgress {
+        phase: BootstrapPhase::Done,
+        completed: total,
+        total,
+    });
+    Ok(report)
+}
+
+fn run_content_phase<F>(
+    phase: BootstrapPhase,
+    files: Vec<FileMeta>,
+    options: &BootstrapOptions,
+    commit_every: usize,
+    report: &mut IndexReport,
+    on_progress: &mut F,
+) -> Result<()>
+where
+    F: FnMut(BootstrapProgress),
+{
+    let total = files.len();
+    on_progress(BootstrapProgress {
+        phase,
+        completed: 0,
+        total,
+    });
+    for (done, meta) in files.into_iter().enumerate() {
+        report.files_seen += 1;
+        // The names phase just wrote this document, so dedup would skip it.
+        index_file(meta, &options.root, true, false, report)?;
+        if commit_every > 0 && (done + 1) % commit_every == 0 {
+            indexer::commit()?;
+        }
+        on_progress(BootstrapProgress {
+            phase,
+            completed: done + 1,
+            total,
+        });
+    }
+    indexer::commit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bootstrap_roots, BootstrapOptions, BootstrapPhase};
+    use crate::indexer::init_index;
+    use crate::query::{search, SearchDomain, SearchQuery};
+    use std::fs;
+    use std::time::{Duration, SystemTime};
+    use tempfile::tempdir;
+
+    fn content_hits(term: &str) -> usize {
+        search(SearchQuery {
+            term: term.into(),
+            search_in: SearchDomain::Content,
+            path_globs: Vec::new(),
+            limit: 10,
+            ..SearchQuery::default()
+        })
+        .unwrap()
+        .len()
+    }
+
+    #[test]
+    fn indexes_names_then_recent_content_then_backfill() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let docs = tempdir().unwrap();
+        let now = SystemTime::now();
+        for (i, name) in ["newest", "middle", "oldest"].iter().enumerate() {
+            let path = docs.path().join(format!("{name}.txt"));
+            fs::write(&path, format!("{name} body")).unwrap();
+            let file = fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(now - Duration::from_secs(3600 * i as u64))
+                .unwrap();
+        }
+        let index_dir = tempdir().unwrap();
+        init_index(index_dir.path().to_str().unwrap()).unwrap();
+
+        let options = BootstrapOptions {
+            recent_content_files: 1,
+            ..BootstrapOptions::default()
+        };
+        let mut events = Vec::new();
+        let report = bootstrap_roots(&[docs.path()], &options, |progress| {
+            events.push(progress);
+        })
+        .unwrap();
+
+        assert_eq!(report.names.names_only, 3);
+        assert_eq!(report.recent_content.content_indexed, 1);
+        assert_eq!(report.backfill.content_indexed, 2);
+        assert_eq!(content_hits("newest"), 1);
+        assert_eq!(content_hits("oldest"), 1);
+
+        let phases: Vec<_> = events.iter().map(|event| event.phase).collect();
+        let first_backfill = phases
+            .iter()
+            .position(|phase| *phase == BootstrapPhase::Backfill)
+            .unwrap();
+        assert!(phases[..first_backfill].contains(&BootstrapPhase::RecentContent));
+        assert_eq!(events.last().unwrap().phase, BootstrapPhase::Done);
+
+        // A second run finds everything complete and reads nothing.
+        let rerun = bootstrap_roots(&[docs.path()], &options, |_| {}).unwrap();
+        assert_eq!(rerun.names.unchanged, 3);
+        assert_eq!(rerun.recent_content.files_seen, 0);
+        assert_eq!(rerun.backfill.files_seen, 0);
+    }
+}
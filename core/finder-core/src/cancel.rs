@@ -0,0 +1,122 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tantivy::collector::{Collector, SegmentCollector};
+use tantivy::{DocId, Score, SegmentOrdinal, SegmentReader, TantivyError};
+
+/// Documents collected between checks of the cancel flag and deadline.
+const CHECK_EVERY_DOCS: u32 = 1024;
+
+/// Shared flag that stops an in-flight search. Clones observe the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// True when both tokens share one flag.
+    pub(crate) fn same_as(&self, other: &CancelToken) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Wraps a collector so a cancelled search stops opening segments and
+/// ignores the rest of the segment it is in. Past `deadline` it stops the
+/// same way but keeps what it collected, and sets `timed_out`.
+pub(crate) struct Cancellable<C> {
+    pub inner: C,
+    pub token: Option<CancelToken>,
+    pub deadline: Option<Instant>,
+    pub timed_out: Arc<AtomicBool>,
+}
+
+impl<C> Cancellable<C> {
+    fn cancelled(&self) -> bool {
+        self.token.as_ref().is_some_and(CancelToken::is_cancelled)
+    }
+}
+
+/// Whether `deadline` has passed, noting it in `timed_out` if so.
+pub(crate) fn expired(deadline: Option<Instant>, timed_out: &AtomicBool) -> bool {
+    let expired = deadline.is_some_and(|deadline| Instant::now() >= deadline);
+    if expired {
+        timed_out.store(true, Ordering::Relaxed);
+    }
+    expired
+}
+
+impl<C: Collector> Collector for Cancellable<C> {
+    type Fruit = C::Fruit;
+    type Child = CancellableSegment<C::Child>;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        segment: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        if self.cancelled() {
+            return Err(TantivyError::InternalError("search cancelled".into()));
+        }
+        Ok(CancellableSegment {
+            inner: self.inner.for_segment(segment_local_id, segment)?,
+            token: self.token.clone(),
+            deadline: self.deadline,
+            timed_out: self.timed_out.clone(),
+            seen: 0,
+            stopped: expired(self.deadline, &self.timed_out),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        self.inner.requires_scoring()
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<<Self::Child as SegmentCollector>::Fruit>,
+    ) -> tantivy::Result<Self::Fruit> {
+        self.inner.merge_fruits(segment_fruits)
+    }
+}
+
+pub(crate) struct CancellableSegment<C> {
+    inner: C,
+    token: Option<CancelToken>,
+    deadline: Option<Instant>,
+    timed_out: Arc<AtomicBool>,
+    seen: u32,
+    stopped: bool,
+}
+
+impl<C: SegmentCollector> SegmentCollector for CancellableSegment<C> {
+    type Fruit = C::Fruit;
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        if self.stopped {
+            return;
+        }
+        self.seen = self.seen.wrapping_add(1);
+        if self.seen.is_multiple_of(CHECK_EVERY_DOCS) {
+            let cancelled = self.token.as_ref().is_some_and(CancelToken::is_cancelled);
+            if cancelled || expired(self.deadline, &self.timed_out) {
+                self.stopped = true;
+                return;
+            }
+        }
+        self.inner.collect(doc, score);
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        self.inner.harvest()
+    }
+}
@@ -0,0 +1,302 @@
+use anyhow::{bail, Context, Result};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Spread of generated modification times, back from now.
+const MTIME_SPAN_SECS: u64 = 3 * 365 * 86_400;
+const WORDS: [&str; 48] = [
+    "alpha", "budget", "client", "draft", "export", "final", "growth", "invoice", "journal",
+    "kernel", "ledger", "meeting", "notes", "outline", "payroll", "quarter", "report", "review",
+    "schema", "summary", "travel", "update", "vendor", "weekly", "archive", "backup", "config",
+    "design", "event", "forecast", "guide", "handbook", "index", "launch", "memo", "network",
+    "plan", "proposal", "roadmap", "sales", "service", "status", "task", "template", "test",
+    "user", "vision", "workflow",
+];
+const FOLDERS: [&str; 16] = [
+    "src", "docs", "projects", "archive", "clients", "assets", "photos", "reports", "lib", "notes",
+    "shared", "exports", "misc", "old", "work", "personal",
+];
+
+/// A family of files with its own extensions and sizes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CorpusKind {
+    /// Source files: many, small, word-heavy text.
+    Code,
+    /// Documents and notes: fewer, larger text.
+    Docs,
+    /// Images, audio and video: binary filler, the largest files.
+    Media,
+}
+
+impl CorpusKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "code" => Some(Self::Code),
+            "docs" => Some(Self::Docs),
+            "media" => Some(Self::Media),
+            _ => None,
+        }
+    }
+
+    /// Extensions with relative weights.
+    fn extensions(self) -> &'static [(&'static str, u32)] {
+        match self {
+            Self::Code => &[
+                ("rs", 20),
+                ("ts", 20),
+                ("py", 15),
+                ("js", 15),
+                ("json", 10),
+                ("md", 10),
+                ("toml", 5),
+                ("swift", 5),
+            ],
+            Self::Docs => &[
+                ("txt", 30),
+                ("md", 25),
+                ("csv", 15),
+                ("html", 10),
+                ("log", 10),
+                ("rtf", 10),
+            ],
+            Self::Media => &[
+                ("jpg", 45),
+                ("png", 25),
+                ("mp3", 15),
+                ("mov", 10),
+                ("heic", 5),
+            ],
+        }
+    }
+
+    /// Typical file size in bytes; actual sizes range from a sixteenth of
+    /// it to sixteen times it, skewed small like real trees.
+    fn typical_bytes(self) -> u64 {
+        match self {
+            Self::Code => 3 * 1024,
+            Self::Docs => 12 * 1024,
+            Self::Media => 256 * 1024,
+        }
+    }
+
+    fn is_text(self) -> bool {
+        !matches!(self, Self::Media)
+    }
+}
+
+/// Shape of a synthetic tree. The same spec and seed always produce the
+/// same names, contents and timestamps.
+#[derive(Debug, Clone)]
+pub struct CorpusSpec {
+    pub files: usize,
+    /// Deepest folder level below the root.
+    pub depth: usize,
+    /// Subfolders per folder.
+    pub fanout: usize,
+    /// Kinds drawn from in turn; empty uses all three.
+    pub mix: Vec<CorpusKind>,
+    /// Upper bound on any one file, so a large run fits on a laptop disk.
+    pub max_file_bytes: u64,
+    pub seed: u64,
+}
+
+impl Default for CorpusSpec {
+    fn default() -> Self {
+        Self {
+            files: 10_000,
+            depth: 6,
+            fanout: 6,
+            mix: Vec::new(),
+            max_file_bytes: 1024 * 1024,
+            seed: 42,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CorpusReport {
+    pub files: usize,
+    pub folders: usize,
+    pub bytes: u64,
+}
+
+/// Write the tree described by `spec` under `root`, which must be new or
+/// empty, for reproducible scanner, indexer and query benchmarks.
+pub fn generate_corpus(root: &Path, spec: &CorpusSpec) -> Result<CorpusReport> {
+    if fs::read_dir(root).is_ok_and(|mut entries| entries.next().is_some()) {
+        bail!("{} is not empty", root.display());
+    }
+    if spec.depth == 0 || spec.fanout == 0 {
+        bail!("depth and fanout must be at least 1");
+    }
+    fs::create_dir_all(root).with_context(|| format!("failed creating {}", root.display()))?;
+    let mix = if spec.mix.is_empty() {
+        vec![CorpusKind::Code, CorpusKind::Docs, CorpusKind::Media]
+    } else {
+        spec.mix.clone()
+    };
+
+    let mut rng = SplitMix64(spec.seed);
+    let mut report = CorpusReport::default();
+    let mut made: HashSet<PathBuf> = HashSet::new();
+    let now = SystemTime::now();
+    let mut buf = Vec::new();
+    for n in 0..spec.files {
+        let kind = mix[n % mix.len()];
+        let folder = random_folder(root, spec, &mut rng);
+        if !made.contains(&folder) {
+            fs::create_dir_all(&folder)
+                .with_context(|| format!("failed creating {}", folder.display()))?;
+            let mut ancestor = folder.as_path();
+            while ancestor != root && made.insert(ancestor.to_path_buf()) {
+                report.folders += 1;
+                ancestor = ancestor.parent().unwrap_or(root);
+            }
+        }
+
+        let ext = pick_weighted(kind.extensions(), &mut rng);
+        let name = format!(
+            "{}_{}_{n}.{ext}",
+            WORDS[rng.below(WORDS.len() as u64) as usize],
+            WORDS[rng.below(WORDS.len() as u64) as usize],
+        );
+        let size = file_size(kind, spec.max_file_bytes, &mut rng);
+        fill(&mut buf, kind, size, &mut rng);
+        let path = folder.join(name);
+        let mut file =
+            File::create(&path).with_context(|| format!("failed creating {}", path.display()))?;
+        file.write_all(&buf)?;
+        let age = Duration::from_secs(rng.below(MTIME_SPAN_SECS));
+        file.set_modified(now.checked_sub(age).unwrap_or(now))?;
+        report.files += 1;
+        report.bytes += size;
+    }
+    Ok(report)
+}
+
+fn random_folder(root: &Path, spec: &CorpusSpec, rng: &mut SplitMix64) -> PathBuf {
+    let levels = 1 + rng.below(spec.depth as u64) as usize;
+    let mut folder = root.to_path_buf();
+    for level in 0..levels {
+        let slot = rng.below(spec.fanout as u64) as usize;
+        let word = FOLDERS[(slot + level * 5) % FOLDERS.len()];
+        folder.push(format!("{word}-{slot}"));
+    }
+    folder
+}
+
+fn pick_weighted(choices: &[(&'static str, u32)], rng: &mut SplitMix64) -> &'static str {
+    let total: u32 = choices.iter().map(|(_, weight)| weight).sum();
+    let mut roll = rng.below(u64::from(total)) as u32;
+    for (choice, weight) in choices {
+        if roll < *weight {
+            return choice;
+        }
+        roll -= weight;
+    }
+    choices[0].0
+}
+
+/// The kind's typical size scaled by 2^x for x in -4..=4, taking the
+/// smaller of two rolls so small files dominate.
+fn file_size(kind: CorpusKind, max_bytes: u64, rng: &mut SplitMix64) -> u64 {
+    let shift = rng.below(9).min(rng.below(9)) as i32 - 4;
+    let size = if shift >= 0 {
+        kind.typical_bytes() << shift
+    } else {
+        kind.typical_bytes() >> -shift
+    };
+    size.clamp(1, max_bytes.max(1))
+}
+
+fn fill(buf: &mut Vec<u8>, kind: CorpusKind, size: u64, rng: &mut SplitMix64) {
+    buf.clear();
+    let size = size as usize;
+    if kind.is_text() {
+        while buf.len() < size {
+            buf.extend_from_slice(WORDS[rng.below(WORDS.len() as u64) as usize].as_bytes());
+            buf.push(if rng.below(12) == 0 { b'\n' } else { b' ' });
+        }
+        buf.truncate(size);
+    } else {
+        while buf.len() < size {
+            buf.extend_from_slice(&rng.next().to_le_bytes());
+        }
+        buf.truncate(size);
+    }
+}
+
+/// Small, fast and seedable; the quality a file-tree generator needs.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        if bound == 0 {
+            0
+        } else {
+            self.next() % bound
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_corpus, CorpusKind, CorpusSpec};
+    use std::fs;
+    use tempfile::tempdir;
+    use walkdir::WalkDir;
+
+    #[test]
+    fn generates_the_same_tree_for_the_same_seed() {
+        let spec = CorpusSpec {
+            files: 60,
+            depth: 3,
+            fanout: 4,
+            mix: vec![CorpusKind::Code, CorpusKind::Docs],
+            max_file_bytes: 8 * 1024,
+            seed: 7,
+        };
+        let listing = |root: &std::path::Path| -> Vec<(String, u64)> {
+            let mut files: Vec<_> = WalkDir::new(root)
+                .into_iter()
+                .map(|entry| entry.unwrap())
+                .filter(|entry| entry.file_type().is_file())
+                .map(|entry| {
+                    let relative = entry.path().strip_prefix(root).unwrap();
+                    let size = entry.metadata().unwrap().len();
+                    (relative.to_string_lossy().into_owned(), size)
+                })
+                .collect();
+            files.sort();
+            files
+        };
+
+        let (first, second) = (tempdir().unwrap(), tempdir().unwrap());
+        let report = generate_corpus(first.path(), &spec).unwrap();
+        generate_corpus(second.path(), &spec).unwrap();
+        let files = listing(first.path());
+        assert_eq!(files, listing(second.path()));
+        assert_eq!(report.files, 60);
+        assert_eq!(
+            report.bytes,
+            files.iter().map(|(_, size)| size).sum::<u64>()
+        );
+        assert!(files.iter().all(|(path, size)| {
+            path.split('/').count() <= 4 && *size <= 8 * 1024 && !path.ends_with(".jpg")
+        }));
+        let sample = first.path().join(&files[0].0);
+        assert!(fs::read_to_string(sample).is_ok());
+        assert!(generate_corpus(first.path(), &spec).is_err());
+    }
+}
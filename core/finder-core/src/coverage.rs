@@ -0,0 +1,78 @@
+use crate::pipeline::IndexReport;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// What happened to the files of one extension since the process started
+/// (or since [`reset_extraction_coverage`]).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExtensionCoverage {
+    /// Lowercased extension without the dot; empty for files without one.
+    pub ext: String,
+    pub files_seen: u64,
+    /// Written with extracted content.
+    pub content_indexed: u64,
+    /// Left as they were because nothing changed since the last pass.
+    pub unchanged: u64,
+    /// Skipped by extension, e.g. disk images and installers.
+    pub skipped_ext: u64,
+    /// Indexed by name only because they exceed the content size limit.
+    pub too_large: u64,
+    /// Indexed by name only because their content looks binary.
+    pub binary: u64,
+    pub errors: u64,
+}
+
+impl ExtensionCoverage {
+    /// Files indexed by name only for any reason (size, binary content,
+    /// age, metadata-only roots, bundles, failed reads).
+    pub fn names_only(&self) -> u64 {
+        self.files_seen
+            .saturating_sub(self.content_indexed + self.unchanged + self.skipped_ext)
+    }
+}
+
+static COVERAGE: Lazy<Mutex<HashMap<String, ExtensionCoverage>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Per-extension extraction outcomes, most frequently seen extensions first.
+pub fn extraction_coverage() -> Vec<ExtensionCoverage> {
+    let mut rows: Vec<_> = COVERAGE.lock().unwrap().values().cloned().collect();
+    rows.sort_by(|a, b| b.files_seen.cmp(&a.files_seen).then(a.ext.cmp(&b.ext)));
+    rows
+}
+
+pub fn reset_extraction_coverage() {
+    COVERAGE.lock().unwrap().clear();
+}
+
+fn update(ext: Option<&str>, apply: impl FnOnce(&mut ExtensionCoverage)) {
+    let key = ext.map(str::to_ascii_lowercase).unwrap_or_default();
+    let mut coverage = COVERAGE.lock().unwrap();
+    let row = coverage
+        .entry(key.clone())
+        .or_insert_with(|| ExtensionCoverage {
+            ext: key,
+            ..ExtensionCoverage::default()
+        });
+    row.files_seen += 1;
+    apply(row);
+}
+
+pub(crate) fn record_skipped_ext(ext: Option<&str>) {
+    update(ext, |row| row.skipped_ext += 1);
+}
+
+/// Attribute what indexing one file added to the run's report (the
+/// difference between `before` and `after`) to the file's extension.
+pub(crate) fn record_file(ext: Option<&str>, before: &IndexReport, after: &IndexReport) {
+    let delta =
+        |field: fn(&IndexReport) -> usize| field(after).saturating_sub(field(before)) as u64;
+    update(ext, |row| {
+        row.content_indexed += delta(|report| report.content_indexed);
+        row.unchanged += delta(|report| report.unchanged);
+        row.too_large += delta(|report| report.too_large);
+        row.binary += delta(|report| report.binary);
+        row.errors += delta(|report| report.read_errors);
+    });
+}
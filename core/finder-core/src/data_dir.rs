@@ -0,0 +1,149 @@
+use anyhow::{anyhow, Context, Result};
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where tools used to put the index before there was a per-user default.
+pub const LEGACY_INDEX_DIR: &str = "/tmp/finder-index";
+
+/// The per-user index directory for this platform, created if missing:
+///
+/// - macOS: `~/Library/Application Support/MySearchBuddy/Index`
+/// - Linux and other Unixes: `$XDG_DATA_HOME/my-search-buddy/index`,
+///   falling back to `~/.local/share/my-search-buddy/index`
+/// - Windows: `%LOCALAPPDATA%\MySearchBuddy\Index`
+///
+/// An index left at [`LEGACY_INDEX_DIR`] is moved here the first time, so
+/// switching to this helper keeps existing installs' data.
+pub fn default_index_dir() -> Result<PathBuf> {
+    let dir = platform_index_dir(env::consts::OS, |name| env::var_os(name))
+        .ok_or_else(|| anyhow!("cannot determine a per-user data directory"))?;
+    prepare_index_dir(&dir, Path::new(LEGACY_INDEX_DIR))?;
+    Ok(dir)
+}
+
+fn platform_index_dir<F>(os: &str, var: F) -> Option<PathBuf>
+where
+    F: Fn(&str) -> Option<OsString>,
+{
+    let non_empty = |name: &str| {
+        var(name)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    };
+    match os {
+        "macos" => Some(
+            non_empty("HOME")?
+                .join("Library/Application Support")
+                .join("MySearchBuddy")
+                .join("Index"),
+        ),
+        "windows" => Some(
+            non_empty("LOCALAPPDATA")?
+                .join("MySearchBuddy")
+                .join("Index"),
+        ),
+        _ => {
+            let data_home = non_empty("XDG_DATA_HOME")
+                .filter(|path| path.is_absolute())
+                .or_else(|| Some(non_empty("HOME")?.join(".local/share")))?;
+            Some(data_home.join("my-search-buddy").join("index"))
+        }
+    }
+}
+
+/// Create `dir`, first moving an index found at `legacy` into it when `dir`
+/// holds no index of its own.
+fn prepare_index_dir(dir: &Path, legacy: &Path) -> Result<()> {
+    let has_index = |path: &Path| path.join("meta.json").is_file();
+    if !has_index(dir) && has_index(legacy) {
+        if let Some(parent) = dir.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed creating {}", parent.display()))?;
+        }
+        // An empty directory left by an earlier run would block the rename.
+        let _ = fs::remove_dir(dir);
+        if fs::rename(legacy, dir).is_err() {
+            copy_flat_dir(legacy, dir)?;
+            fs::remove_dir_all(legacy)
+                .with_context(|| format!("failed removing {}", legacy.display()))?;
+        }
+    }
+    fs::create_dir_all(dir).with_context(|| format!("failed creating {}", dir.display()))
+}
+
+/// Copy the files of an index directory, which has no subdirectories, e.g.
+/// when `/tmp` is on another volume and a rename can't work.
+fn copy_flat_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to).with_context(|| format!("failed creating {}", to.display()))?;
+    let entries =
+        fs::read_dir(from).with_context(|| format!("failed listing {}", from.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            fs::copy(entry.path(), to.join(entry.file_name()))
+                .with_context(|| format!("failed copying {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{platform_index_dir, prepare_index_dir};
+    use std::ffi::OsString;
+    use std::fs;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    #[test]
+    fn resolves_per_platform_locations() {
+        let env = |pairs: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                pairs
+                    .iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| OsString::from(value))
+            }
+        };
+        let home = &[("HOME", "/home/me")];
+        assert_eq!(
+            platform_index_dir("linux", env(home)),
+            Some(PathBuf::from("/home/me/.local/share/my-search-buddy/index"))
+        );
+        let xdg = &[("HOME", "/home/me"), ("XDG_DATA_HOME", "/data/me")];
+        assert_eq!(
+            platform_index_dir("linux", env(xdg)),
+            Some(PathBuf::from("/data/me/my-search-buddy/index"))
+        );
+        assert_eq!(
+            platform_index_dir("macos", env(&[("HOME", "/Users/me")])),
+            Some(PathBuf::from(
+                "/Users/me/Library/Application Support/MySearchBuddy/Index"
+            ))
+        );
+        assert_eq!(platform_index_dir("windows", env(home)), None);
+    }
+
+    #[test]
+    fn moves_a_legacy_index_once() {
+        let root = tempdir().unwrap();
+        let legacy = root.path().join("legacy");
+        fs::create_dir(&legacy).unwrap();
+        fs::write(legacy.join("meta.json"), "{}").unwrap();
+        fs::write(legacy.join("segment.idx"), "data").unwrap();
+        let dir = root.path().join("data/my-search-buddy/index");
+
+        prepare_index_dir(&dir, &legacy).unwrap();
+        assert!(!legacy.exists());
+        assert_eq!(fs::read_to_string(dir.join("segment.idx")).unwrap(), "data");
+
+        // A newer legacy index never overwrites the one already in place.
+        fs::create_dir(&legacy).unwrap();
+        fs::write(legacy.join("meta.json"), "{\"newer\": true}").unwrap();
+        prepare_index_dir(&dir, &legacy).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("meta.json")).unwrap(), "{}");
+        assert!(legacy.exists());
+    }
+}
@@ -0,0 +1,377 @@
+use crate::query;
+use anyhow::{bail, Result};
+use once_cell::sync::Lazy;
+use std::env;
+use std::sync::RwLock;
+
+const DAY_SECS: i64 = 86_400;
+const MONTHS: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+/// Monday first, matching [`weekday`].
+const WEEKDAYS: [&str; 7] = [
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+/// How dates typed into `modified:` filters are read: where days start
+/// and which way round numeric dates go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateLocale {
+    /// Seconds east of UTC; days, weeks and months start at local midnight.
+    pub utc_offset_secs: i32,
+    /// `04/03/2024` is 4 March rather than April 3.
+    pub day_first: bool,
+    /// Weeks run Monday to Sunday rather than Sunday to Saturday.
+    pub week_starts_monday: bool,
+}
+
+impl DateLocale {
+    /// Conventions of a POSIX or BCP 47 locale name such as `en_US.UTF-8`
+    /// or `de-DE`: month-first dates and Sunday weeks in the US and a few
+    /// other regions, day-first dates and Monday weeks elsewhere.
+    pub fn from_name(name: &str, utc_offset_secs: i32) -> Self {
+        let region = name
+            .split(['.', '@'])
+            .next()
+            .unwrap_or_default()
+            .split(['_', '-'])
+            .nth(1)
+            .unwrap_or_default()
+            .to_ascii_uppercase();
+        let month_first = matches!(region.as_str(), "US" | "PH" | "FM" | "MH" | "PW");
+        let sunday_weeks = month_first || matches!(region.as_str(), "CA" | "JP" | "BR" | "IL");
+        DateLocale {
+            utc_offset_secs,
+            day_first: !month_first && region != "CA" && region != "JP",
+            week_starts_monday: !sunday_weeks,
+        }
+    }
+}
+
+impl Default for DateLocale {
+    /// From `LC_ALL`, `LC_TIME` or `LANG`, in UTC; US conventions when none
+    /// is set, as for GUI apps, which should call [`set_date_locale`].
+    fn default() -> Self {
+        let name = ["LC_ALL", "LC_TIME", "LANG"]
+            .into_iter()
+            .filter_map(|key| env::var(key).ok())
+            .find(|value| !value.is_empty() && value != "C" && value != "POSIX")
+            .unwrap_or_else(|| "en_US".to_string());
+        DateLocale::from_name(&name, 0)
+    }
+}
+
+static LOCALE: Lazy<RwLock<DateLocale>> = Lazy::new(|| RwLock::new(DateLocale::default()));
+
+/// Read `modified:` dates with `locale` from now on.
+pub fn set_date_locale(locale: DateLocale) {
+    *LOCALE.write().unwrap() = locale;
+    query::clear_result_cache();
+}
+
+pub(crate) fn date_locale() -> DateLocale {
+    *LOCALE.read().unwrap()
+}
+
+/// Inclusive bounds for `op value`. Periods (days, months, `last week`)
+/// compare as a whole: `>2024-03` is from April on. Relative ages such as
+/// `2w` name a moment: alone or with `>` they mean since then, with `<`
+/// before then.
+pub(crate) fn date_bounds(
+    op: &str,
+    value: &str,
+    now: i64,
+    locale: &DateLocale,
+) -> Result<(Option<i64>, Option<i64>)> {
+    let value = value.trim().to_lowercase();
+    if let Some(since) = relative(&value, now, locale) {
+        return Ok(match op {
+            "<" | "<=" => (None, Some(since)),
+            _ => (Some(since), None),
+        });
+    }
+    let Some((start, end)) = period(&value, now, locale) else {
+        bail!(
+            "unrecognized date {value:?}; try 2024-03-15, 2024-03, today, \"last tuesday\", 7d or 2w"
+        );
+    };
+    Ok(match op {
+        ">" => (Some(end), None),
+        ">=" => (Some(start), None),
+        "<" => (None, Some(start - 1)),
+        "<=" => (None, Some(end - 1)),
+        _ => (Some(start), Some(end - 1)),
+    })
+}
+
+/// `12h`, `7d`, `2w`, `3m` or `1y` ago; months and years by the calendar.
+fn relative(value: &str, now: i64, locale: &DateLocale) -> Option<i64> {
+    let digits = value.find(|ch: char| !ch.is_ascii_digit())?;
+    let count: i64 = value[..digits].parse().ok()?;
+    let unit = value[digits..].trim();
+    let secs = match unit {
+        "h" | "hour" | "hours" => count * 3_600,
+        "d" | "day" | "days" => count * DAY_SECS,
+        "w" | "week" | "weeks" => count * 7 * DAY_SECS,
+        "m" | "mo" | "month" | "months" => return Some(months_before(now, count, locale)),
+        "y" | "year" | "years" => return Some(months_before(now, count * 12, locale)),
+        _ => return None,
+    };
+    Some(now - secs)
+}
+
+fn months_before(now: i64, months: i64, locale: &DateLocale) -> i64 {
+    let offset = i64::from(locale.utc_offset_secs);
+    let local = now + offset;
+    let (year, month, day) = civil_from_days(local.div_euclid(DAY_SECS));
+    let index = year * 12 + (month - 1) - months;
+    let (year, month) = (index.div_euclid(12), index.rem_euclid(12) + 1);
+    let day = day.min(days_in_month(year, month));
+    days_from_civil(year, month, day) * DAY_SECS + local.rem_euclid(DAY_SECS) - offset
+}
+
+/// The local period `value` names, as `[start, end)` unix seconds.
+fn period(value: &str, now: i64, locale: &DateLocale) -> Option<(i64, i64)> {
+    let offset = i64::from(locale.utc_offset_secs);
+    let today = (now + offset).div_euclid(DAY_SECS);
+    let days = |first: i64, count: i64| {
+        Some((
+            first * DAY_SECS - offset,
+            (first + count) * DAY_SECS - offset,
+        ))
+    };
+    let whole_month = |year: i64, month: i64| {
+        let first = days_from_civil(year, month, 1);
+        days(first, days_in_month(year, month))
+    };
+    let week_start = |day: i64| {
+        let shift = if locale.week_starts_monday { 0 } else { 1 };
+        day - (weekday(day) + shift) % 7
+    };
+    let (year, month_now, _) = civil_from_days(today);
+    let words: Vec<&str> = value.split_whitespace().collect();
+    match words.as_slice() {
+        ["today"] => days(today, 1),
+        ["yesterday"] => days(today - 1, 1),
+        ["this", "week"] => days(week_start(today), 7),
+        ["last", "week"] => days(week_start(today) - 7, 7),
+        ["this", "month"] => whole_month(year, month_now),
+        ["last", "month"] if month_now == 1 => whole_month(year - 1, 12),
+        ["last", "month"] => whole_month(year, month_now - 1),
+        ["this", "year"] => days(days_from_civil(year, 1, 1), days_in_year(year)),
+        ["last", "year"] => days(days_from_civil(year - 1, 1, 1), days_in_year(year - 1)),
+        [day] if weekday_index(day).is_some() => {
+            let back = (weekday(today) - weekday_index(day)? + 7) % 7;
+            days(today - back, 1)
+        }
+        ["last", day] => {
+            let back = (weekday(today) - weekday_index(day)? + 6) % 7 + 1;
+            days(today - back, 1)
+        }
+        [name, year] => whole_month(parse_year(year)?, month_index(name)?),
+        [day, name, year] | [name, day, year] if month_index(name).is_some() => {
+            let (year, month) = (parse_year(year)?, month_index(name)?);
+            let day: i64 = day.trim_end_matches([',', '.']).parse().ok()?;
+            (1..=days_in_month(year, month))
+                .contains(&day)
+                .then_some(())?;
+            days(days_from_civil(year, month, day), 1)
+        }
+        [single] => numeric_date(single, locale).and_then(|(year, month, day)| match day {
+            Some(day) => days(days_from_civil(year, month, day), 1),
+            None if month == 0 => days(days_from_civil(year, 1, 1), days_in_year(year)),
+            None => whole_month(year, month),
+        }),
+        _ => None,
+    }
+}
+
+/// `2024`, `2024-03`, `2024-03-15`, or a locale-ordered `15/03/2024`,
+/// `15.03.2024`. Month 0 stands for the whole year.
+fn numeric_date(text: &str, locale: &DateLocale) -> Option<(i64, i64, Option<i64>)> {
+    let parts: Vec<&str> = text.split(['-', '/', '.']).collect();
+    let number = |part: &str| -> Option<i64> {
+        (!part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+            .then(|| part.parse().ok())
+            .flatten()
+    };
+    let (year, month, day) = match parts.as_slice() {
+        [year] if year.len() == 4 => (number(year)?, 0, None),
+        [year, month] if year.len() == 4 => (number(year)?, number(month)?, None),
+        [year, month, day] if year.len() == 4 => {
+            (number(year)?, number(month)?, Some(number(day)?))
+        }
+        [a, b, year] if year.len() == 4 || year.len() == 2 => {
+            let year = parse_year(year)?;
+            let (a, b) = (number(a)?, number(b)?);
+            // Dots are day-first everywhere they are used.
+            let (day, month) = if locale.day_first || text.contains('.') {
+                (a, b)
+            } else {
+                (b, a)
+            };
+            (year, month, Some(day))
+        }
+        _ => return None,
+    };
+    if month != 0 && !(1..=12).contains(&month) {
+        return None;
+    }
+    if let Some(day) = day {
+        if month == 0 || !(1..=days_in_month(year, month)).contains(&day) {
+            return None;
+        }
+    }
+    Some((year, month, day))
+}
+
+fn parse_year(text: &str) -> Option<i64> {
+    let year: i64 = text.parse().ok()?;
+    match text.len() {
+        4 => Some(year),
+        2 => Some(2000 + year),
+        _ => None,
+    }
+}
+
+/// 1-based month for a full or three-letter English month name.
+fn month_index(name: &str) -> Option<i64> {
+    let name = name.trim_end_matches([',', '.']);
+    (name.len() >= 3)
+        .then(|| MONTHS.iter().position(|month| month.starts_with(name)))
+        .flatten()
+        .map(|index| index as i64 + 1)
+}
+
+/// 0 for Monday, for a full or three-letter English weekday name.
+fn weekday_index(name: &str) -> Option<i64> {
+    (name.len() >= 3)
+        .then(|| WEEKDAYS.iter().position(|day| day.starts_with(name)))
+        .flatten()
+        .map(|index| index as i64)
+}
+
+/// 0 for Monday; day 0 of the epoch was a Thursday.
+fn weekday(days: i64) -> i64 {
+    (days + 3).rem_euclid(7)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if days_in_year(year) == 366 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn days_in_year(year: i64) -> i64 {
+    if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) {
+        366
+    } else {
+        365
+    }
+}
+
+/// Days since 1970-01-01, after Howard Hinnant's date algorithms.
+pub(crate) fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// `(year, month, day)` of a day counted from 1970-01-01.
+pub(crate) fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (yoe + era * 400 + i64::from(month <= 2), month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{days_from_civil, DateLocale, DAY_SECS};
+    use crate::term_filters::extract_at;
+
+    #[test]
+    fn reads_absolute_relative_and_named_dates() {
+        // Wednesday 2024-03-20 12:00 UTC.
+        let now = days_from_civil(2024, 3, 20) * DAY_SECS + 12 * 3_600;
+        let us = DateLocale::from_name("en_US.UTF-8", 0);
+        let day = |y, m, d| days_from_civil(y, m, d) * DAY_SECS;
+        let bounds = |term: &str, locale: &DateLocale| {
+            let filters = extract_at(term, now, locale).unwrap();
+            (filters.term, filters.mtime_min, filters.mtime_max)
+        };
+
+        assert_eq!(
+            bounds("budget modified:2024-03 pdf", &us),
+            (
+                "budget pdf".into(),
+                Some(day(2024, 3, 1)),
+                Some(day(2024, 4, 1) - 1)
+            )
+        );
+        assert_eq!(
+            bounds("modified:>\u{201c}last tuesday\u{201d}", &us),
+            (String::new(), Some(day(2024, 3, 20)), None)
+        );
+        assert_eq!(bounds("modified:7d", &us).1, Some(now - 7 * DAY_SECS));
+        assert_eq!(bounds("modified:<2w", &us).2, Some(now - 14 * DAY_SECS));
+        assert_eq!(
+            bounds("modified:3m", &us).1,
+            Some(day(2023, 12, 20) + 12 * 3_600)
+        );
+        assert_eq!(
+            bounds("modified:\"this week\"", &us).1,
+            Some(day(2024, 3, 17))
+        );
+        assert_eq!(
+            bounds("modified:\"march 2024\" modified:>=2024-03-10", &us).1,
+            Some(day(2024, 3, 10))
+        );
+        // Numeric order follows the locale; local midnight moves the day.
+        assert_eq!(bounds("modified:04/03/2024", &us).1, Some(day(2024, 4, 3)));
+        let berlin = DateLocale::from_name("de_DE", 3_600);
+        assert_eq!(
+            bounds("modified:04/03/2024", &berlin).1,
+            Some(day(2024, 3, 4) - 3_600)
+        );
+        assert_eq!(
+            bounds("modified:\"this week\"", &berlin).1,
+            Some(day(2024, 3, 18) - 3_600)
+        );
+        // Quoted text is left alone; nonsense is an error.
+        assert_eq!(
+            bounds("\"modified:today\"", &us),
+            ("\"modified:today\"".into(), None, None)
+        );
+        assert!(extract_at("modified:someday", now, &us).is_err());
+    }
+}
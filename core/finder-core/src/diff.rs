@@ -0,0 +1,402 @@
+use crate::indexer;
+use crate::query::{
+    DefaultOperator, EntryKind, QueryMode, SearchDomain, SearchHit, SearchQuery, ShouldMatch,
+};
+use anyhow::{bail, Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Name of the saved-search snapshot sidecar inside the index directory.
+pub const RESULT_SNAPSHOTS_FILE_NAME: &str = ".finder-result-snapshots";
+/// Older snapshots of a query are dropped beyond this many.
+const MAX_SNAPSHOTS_PER_QUERY: usize = 8;
+/// Queries not snapshotted for the longest are forgotten beyond this many.
+const MAX_QUERIES: usize = 64;
+/// Starts every snapshot key; bumped when [`key`] changes what it covers,
+/// so snapshots under older keys are never compared and age out.
+const KEY_VERSION: &str = "v2";
+
+/// How the hits of a query differ between two result sets.
+#[derive(Debug, Clone, Default)]
+pub struct ResultsDiff {
+    /// Index generation the newer results were taken at; pass it to the next
+    /// [`diff_results`] call. 0 from [`diff_hits`] and in-memory indexes.
+    pub generation: u64,
+    /// Hits whose path was not in the older results.
+    pub added: Vec<SearchHit>,
+    /// Hits present in both whose modification time or size changed.
+    pub changed: Vec<SearchHit>,
+    /// Paths that no longer match.
+    pub removed: Vec<String>,
+}
+
+impl ResultsDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Compare two result lists by path, e.g. the hits of two queries.
+pub fn diff_hits(before: &[SearchHit], after: &[SearchHit]) -> ResultsDiff {
+    let before: Snapshot = before.iter().map(entry).collect();
+    diff_against(&before, after.to_vec(), 0)
+}
+
+/// Record which files `query` matches at the current index generation and
+/// return that generation, as the baseline for a later [`diff_results`].
+pub fn snapshot_results(query: SearchQuery) -> Result<u64> {
+    let (generation, hits) = current_hits(&query)?;
+    let snapshots = indexer::result_snapshots()?;
+    snapshots.record(&key(&query), generation, hits.iter().map(entry).collect())?;
+    Ok(generation)
+}
+
+/// What `query` matches now compared with the latest snapshot taken at or
+/// before `since_generation`, e.g. "new files matching this saved search
+/// since yesterday". The current results become a snapshot too, so calls
+/// can chain through [`ResultsDiff::generation`]. Only the first
+/// `query.limit` hits take part; fails when no old enough snapshot of this
+/// query was recorded with [`snapshot_results`] or a previous diff.
+pub fn diff_results(query: SearchQuery, since_generation: u64) -> Result<ResultsDiff> {
+    let key = key(&query);
+    let snapshots = indexer::result_snapshots()?;
+    let Some(before) = snapshots.at_or_before(&key, since_generation) else {
+        bail!("no snapshot of this query at or before generation {since_generation}");
+    };
+    let (generation, hits) = current_hits(&query)?;
+    snapshots.record(&key, generation, hits.iter().map(entry).collect())?;
+    Ok(diff_against(&before, hits, generation))
+}
+
+fn current_hits(query: &SearchQuery) -> Result<(u64, Vec<SearchHit>)> {
+    // Read the generation first so a commit racing the search is reported
+    // again next time rather than skipped.
+    let generation = indexer::index_generation()?;
+    let hits = crate::search(query.clone())?;
+    Ok((generation, hits))
+}
+
+fn diff_against(before: &Snapshot, after: Vec<SearchHit>, generation: u64) -> ResultsDiff {
+    let mut diff = ResultsDiff {
+        generation,
+        ..ResultsDiff::default()
+    };
+    let mut seen = HashSet::with_capacity(after.len());
+    for hit in after {
+        let (path, state) = entry(&hit);
+        match before.get(&path) {
+            None => diff.added.push(hit),
+            Some(old) if *old != state => diff.changed.push(hit),
+            Some(_) => {}
+        }
+        seen.insert(path);
+    }
+    diff.removed = before
+        .keys()
+        .filter(|path| !seen.contains(*path))
+        .cloned()
+        .collect();
+    diff.removed.sort();
+    diff
+}
+
+/// Path → (mtime, size) of every hit.
+type Snapshot = HashMap<String, (i64, u64)>;
+/// A query's key and its snapshots, oldest first.
+type QuerySnapshots = (String, Vec<(u64, Snapshot)>);
+
+fn entry(hit: &SearchHit) -> (String, (i64, u64)) {
+    (
+        hit.path.clone(),
+        (hit.modified_at.unwrap_or(0), hit.size.unwrap_or(0)),
+    )
+}
+
+/// Snapshots are per query, keyed by the options that decide which files
+/// match and how many take part: options that only rank or present hits
+/// don't make a different query. Built field by field rather than from
+/// `Debug`, so a key stays the same across releases until [`KEY_VERSION`]
+/// says otherwise; lists are sorted, as their order doesn't matter.
+fn key(query: &SearchQuery) -> String {
+    let domain = match query.search_in {
+        SearchDomain::Name => "name",
+        SearchDomain::Content => "content",
+        SearchDomain::Both => "both",
+    };
+    let mode = match query.mode {
+        QueryMode::Words => "words",
+        QueryMode::Regex => "regex",
+        QueryMode::Prefix => "prefix",
+    };
+    let operator = match query.default_operator {
+        DefaultOperator::And => "and",
+        DefaultOperator::Or => "or",
+    };
+    let should_match = |should: Option<ShouldMatch>| match should {
+        None => String::new(),
+        Some(ShouldMatch::Words(words)) => words.to_string(),
+        Some(ShouldMatch::Percent(percent)) => format!("{percent}%"),
+    };
+    let mut fields = vec![
+        KEY_VERSION.to_string(),
+        format!("term={}", escape_key_part(&query.term)),
+        format!("in={domain}"),
+        format!("mode={mode}"),
+        format!("operator={operator}"),
+        format!(
+            "should={}/{}",
+            should_match(query.min_should_match.name),
+            should_match(query.min_should_match.content)
+        ),
+        format!("case={}", query.case_sensitive),
+        format!("fuzzy={}", optional(query.fuzzy)),
+        format!("limit={}", query.limit),
+        format!("globs={}", key_list(&query.path_globs)),
+        format!("exclude={}", key_list(&query.exclude_globs)),
+        format!("ext={}", key_list(&query.extensions)),
+        format!(
+            "roots={}",
+            key_list(query.roots.iter().map(|root| root.to_string_lossy()))
+        ),
+        format!(
+            "mtime={}..{}",
+            optional(query.mtime_min),
+            optional(query.mtime_max)
+        ),
+        format!(
+            "size={}..{}",
+            optional(query.size_min),
+            optional(query.size_max)
+        ),
+        format!(
+            "kind={}",
+            query.kind.map(EntryKind::term).unwrap_or_default()
+        ),
+    ];
+    if let Some(within) = &query.within {
+        fields.push(format!("within={}", key_list(within)));
+    }
+    fields.join("\t")
+}
+
+fn optional(value: Option<impl ToString>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+/// `items` escaped, sorted and joined with commas.
+fn key_list<S: AsRef<str>>(items: impl IntoIterator<Item = S>) -> String {
+    let mut items: Vec<String> = items
+        .into_iter()
+        .map(|item| escape_key_part(item.as_ref()))
+        .collect();
+    items.sort();
+    items.join(",")
+}
+
+/// Escape what separates key fields, list items and snapshot file lines.
+fn escape_key_part(part: &str) -> String {
+    part.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace(',', "\\,")
+}
+
+/// Recent result snapshots per query. Saved next to on-disk indexes after
+/// every change, by whichever process records them; in-memory indexes keep
+/// them for the process lifetime.
+pub(crate) struct ResultSnapshots {
+    path: Option<PathBuf>,
+    /// Least recently recorded query first.
+    queries: Mutex<Vec<QuerySnapshots>>,
+}
+
+impl ResultSnapshots {
+    pub(crate) fn open(dir: Option<&Path>) -> Self {
+        let path = dir.map(|dir| dir.join(RESULT_SNAPSHOTS_FILE_NAME));
+        let queries = path
+            .as_deref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|raw| parse_snapshots(&raw))
+            .unwrap_or_default();
+        Self {
+            path,
+            queries: Mutex::new(queries),
+        }
+    }
+
+    fn at_or_before(&self, key: &str, generation: u64) -> Option<Snapshot> {
+        let queries = self.queries.lock().unwrap();
+        let (_, snapshots) = queries.iter().find(|(known, _)| known == key)?;
+        snapshots
+            .iter()
+            .rev()
+            .find(|(taken, _)| *taken <= generation)
+            .map(|(_, snapshot)| snapshot.clone())
+    }
+
+    fn record(&self, key: &str, generation: u64, snapshot: Snapshot) -> Result<()> {
+        let mut queries = self.queries.lock().unwrap();
+        let mut snapshots = match queries.iter().position(|(known, _)| known == key) {
+            Some(slot) => queries.remove(slot).1,
+            None => Vec::new(),
+        };
+        snapshots.retain(|(taken, _)| *taken != generation);
+        snapshots.push((generation, snapshot));
+        snapshots.sort_by_key(|(taken, _)| *taken);
+        let excess = snapshots.len().saturating_sub(MAX_SNAPSHOTS_PER_QUERY);
+        snapshots.drain(..excess);
+        queries.push((key.to_string(), snapshots));
+        let excess = queries.len().saturating_sub(MAX_QUERIES);
+        queries.drain(..excess);
+        self.save(&queries)
+    }
+
+    fn save(&self, queries: &[QuerySnapshots]) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut out = String::new();
+        for (key, snapshots) in queries {
+            out.push_str(&format!("Q\t{key}\n"));
+            for (generation, snapshot) in snapshots {
+                out.push_str(&format!("G\t{generation}\n"));
+                for (file, (mtime, size)) in snapshot {
+                    if !file.contains('\n') {
+                        out.push_str(&format!("{mtime}\t{size}\t{file}\n"));
+                    }
+                }
+            }
+        }
+        // Write then rename so a crash never leaves a truncated file.
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, out).with_context(|| format!("failed writing {}", tmp.display()))?;
+        fs::rename(&tmp, path).with_context(|| format!("failed publishing {}", path.display()))
+    }
+}
+
+/// `Q<TAB>key` starts a query, `G<TAB>generation` one of its snapshots, and
+/// `mtime<TAB>size<TAB>path` lines list the snapshot's files. Lines that
+/// don't fit are dropped.
+fn parse_snapshots(raw: &str) -> Vec<QuerySnapshots> {
+    let mut queries: Vec<QuerySnapshots> = Vec::new();
+    for line in raw.lines() {
+        if let Some(key) = line.strip_prefix("Q\t") {
+            queries.push((key.to_string(), Vec::new()));
+        } else if let Some(generation) = line.strip_prefix("G\t") {
+            if let (Some((_, snapshots)), Ok(generation)) = (queries.last_mut(), generation.parse())
+            {
+                snapshots.push((generation, Snapshot::new()));
+            }
+        } else if let Some((_, snapshots)) = queries.last_mut() {
+            let mut parts = line.splitn(3, '\t');
+            let parsed = (|| {
+                let mtime = parts.next()?.parse().ok()?;
+                let size = parts.next()?.parse().ok()?;
+                Some((parts.next()?.to_string(), (mtime, size)))
+            })();
+            if let (Some((_, snapshot)), Some((file, state))) = (snapshots.last_mut(), parsed) {
+                snapshot.insert(file, state);
+            }
+        }
+    }
+    queries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff_results, key, snapshot_results, ResultSnapshots};
+    use crate::query::{SearchDomain, SearchQuery, SortBy};
+    use crate::scanner::FileMeta;
+    use crate::{add_or_update_file, close_index, commit, init_index};
+    use std::collections::HashMap;
+    use tempfile::tempdir;
+
+    fn add(name: &str, inode: u64, mtime: i64) {
+        let meta = FileMeta {
+            path: format!("/reports/{name}"),
+            name: name.into(),
+            inode,
+            dev: 1,
+            modified_at: mtime,
+            ..FileMeta::default()
+        };
+        add_or_update_file(meta, None, false).unwrap();
+    }
+
+    #[test]
+    fn reports_new_changed_and_removed_hits_since_a_generation() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        add("report-q1.txt", 1, 100);
+        add("report-q2.txt", 2, 100);
+        commit().unwrap();
+
+        let query = || SearchQuery {
+            term: "report".into(),
+            search_in: SearchDomain::Name,
+            ..SearchQuery::default()
+        };
+        let baseline = snapshot_results(query()).unwrap();
+        assert!(diff_results(query(), baseline - 1).is_err());
+
+        add("report-q3.txt", 3, 100);
+        add("report-q2.txt", 2, 200);
+        add("notes-q1.txt", 1, 300);
+        commit().unwrap();
+
+        let diff = diff_results(query(), baseline).unwrap();
+        let names = |hits: &[crate::SearchHit]| -> Vec<String> {
+            hits.iter().map(|hit| hit.name.clone()).collect()
+        };
+        assert_eq!(names(&diff.added), vec!["report-q3.txt"]);
+        assert_eq!(names(&diff.changed), vec!["report-q2.txt"]);
+        assert_eq!(diff.removed, vec!["/reports/report-q1.txt"]);
+        assert!(diff.generation > baseline);
+
+        close_index();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        assert!(diff_results(query(), diff.generation).unwrap().is_empty());
+    }
+
+    #[test]
+    fn keys_queries_by_what_they_match() {
+        let query = SearchQuery {
+            term: "q3\treport".into(),
+            search_in: SearchDomain::Name,
+            extensions: vec!["pdf".into(), "md".into()],
+            mtime_min: Some(100),
+            ..SearchQuery::default()
+        };
+        assert_eq!(
+            key(&query),
+            "v2\tterm=q3\\treport\tin=name\tmode=words\toperator=and\tshould=/\t\
+             case=false\tfuzzy=\tlimit=50\tglobs=\texclude=\text=md,pdf\troots=\t\
+             mtime=100..\tsize=..\tkind="
+        );
+        let presented = SearchQuery {
+            sort_by: SortBy::Name,
+            snippet_chars: Some(200),
+            extensions: vec!["md".into(), "pdf".into()],
+            ..query.clone()
+        };
+        assert_eq!(key(&presented), key(&query));
+        let narrowed = SearchQuery {
+            within: Some(Vec::new()),
+            ..query.clone()
+        };
+        assert_ne!(key(&narrowed), key(&query));
+    }
+
+    #[test]
+    fn keeps_a_bounded_number_of_snapshots() {
+        let snapshots = ResultSnapshots::open(None);
+        for generation in 0..20 {
+            snapshots.record("q", generation, HashMap::new()).unwrap();
+        }
+        assert!(snapshots.at_or_before("q", 5).is_none());
+        assert!(snapshots.at_or_before("q", 12).is_some());
+    }
+}
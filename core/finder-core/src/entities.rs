@@ -0,0 +1,159 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::BTreeSet;
+
+/// Upper bound on distinct values kept per entity kind so a single huge file
+/// (e.g. a mailing-list archive) can't bloat its document.
+const MAX_ENTITIES_PER_KIND: usize = 256;
+
+static EMAIL_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b[a-z0-9._%+\-]+@([a-z0-9\-]+(?:\.[a-z0-9\-]+)*\.[a-z]{2,})\b").unwrap()
+});
+
+static PHONE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?:\+\d{1,3}[\s.\-]?)?(?:\(\d{2,4}\)|\d{2,4})[\s.\-]?\d{3,4}[\s.\-]?\d{3,4}\b")
+        .unwrap()
+});
+
+static DATE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b(\d{4})-(\d{2})-(\d{2})\b").unwrap());
+
+static AMOUNT_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)(?:[$€£]\s?(\d{1,3}(?:,\d{3})+|\d+)(\.\d{1,2})?)|(?:\b(\d{1,3}(?:,\d{3})+|\d+)(\.\d{1,2})?\s?(?:usd|eur|gbp)\b)",
+    )
+    .unwrap()
+});
+
+/// Structured values pulled out of extracted document text. Each list holds
+/// the normalized terms that get written into the matching index field.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExtractedEntities {
+    /// Lowercased addresses plus their `@domain` suffix, so `email:@acme.com`
+    /// matches every address at that domain.
+    pub emails: Vec<String>,
+    /// Digits only, keeping a leading `+` for international numbers.
+    pub phones: Vec<String>,
+    /// ISO dates expanded to day, month and year granularity
+    /// (`2023-07-14`, `2023-07`, `2023`).
+    pub dates: Vec<String>,
+    /// Monetary amounts without currency symbols or thousands separators.
+    pub amounts: Vec<String>,
+}
+
+impl ExtractedEntities {
+    pub fn is_empty(&self) -> bool {
+        self.emails.is_empty()
+            && self.phones.is_empty()
+            && self.dates.is_empty()
+            && self.amounts.is_empty()
+    }
+}
+
+/// Scan `text` for email addresses, phone numbers, ISO dates and currency
+/// amounts, returning deduplicated, normalized values ready for indexing.
+pub fn extract_entities(text: &str) -> ExtractedEntities {
+    let mut emails = BTreeSet::new();
+    for caps in EMAIL_RE.captures_iter(text) {
+        if emails.len() >= MAX_ENTITIES_PER_KIND {
+            break;
+        }
+        let address = caps[0].to_lowercase();
+        let domain = format!("@{}", caps[1].to_lowercase());
+        emails.insert(address);
+        emails.insert(domain);
+    }
+
+    let mut dates = BTreeSet::new();
+    for caps in DATE_RE.captures_iter(text) {
+        if dates.len() >= MAX_ENTITIES_PER_KIND {
+            break;
+        }
+        let (year, month, day) = (&caps[1], &caps[2], &caps[3]);
+        let valid_month = matches!(month.parse::<u32>(), Ok(1..=12));
+        let valid_day = matches!(day.parse::<u32>(), Ok(1..=31));
+        if !valid_month || !valid_day {
+            continue;
+        }
+        dates.insert(format!("{year}-{month}-{day}"));
+        dates.insert(format!("{year}-{month}"));
+        dates.insert(year.to_string());
+    }
+
+    let mut phones = BTreeSet::new();
+    for found in PHONE_RE.find_iter(text) {
+        if phones.len() >= MAX_ENTITIES_PER_KIND {
+            break;
+        }
+        let raw = found.as_str();
+        // ISO dates look like phone numbers to the pattern above.
+        if DATE_RE.is_match(raw) {
+            continue;
+        }
+        let digits: String = raw.chars().filter(char::is_ascii_digit).collect();
+        if !(7..=15).contains(&digits.len()) {
+            continue;
+        }
+        if raw.starts_with('+') {
+            phones.insert(format!("+{digits}"));
+        } else {
+            phones.insert(digits);
+        }
+    }
+
+    let mut amounts = BTreeSet::new();
+    for caps in AMOUNT_RE.captures_iter(text) {
+        if amounts.len() >= MAX_ENTITIES_PER_KIND {
+            break;
+        }
+        let (whole, fraction) = match caps.get(1) {
+            Some(whole) => (whole.as_str(), caps.get(2)),
+            None => (&caps[3], caps.get(4)),
+        };
+        let mut normalized = whole.replace(',', "");
+        if let Some(fraction) = fraction {
+            normalized.push_str(fraction.as_str());
+        }
+        amounts.insert(normalized);
+    }
+
+    ExtractedEntities {
+        emails: emails.into_iter().collect(),
+        phones: phones.into_iter().collect(),
+        dates: dates.into_iter().collect(),
+        amounts: amounts.into_iter().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::extract_entities;
+
+    #[test]
+    fn extracts_emails_with_domains() {
+        let entities = extract_entities("Contact Jane.Doe@Acme.com or ops@acme.com today.");
+        assert_eq!(
+            entities.emails,
+            vec!["@acme.com", "jane.doe@acme.com", "ops@acme.com"]
+        );
+    }
+
+    #[test]
+    fn expands_iso_dates_and_rejects_invalid() {
+        let entities = extract_entities("Signed 2023-07-14, void 2023-13-40.");
+        assert_eq!(entities.dates, vec!["2023", "2023-07", "2023-07-14"]);
+        assert!(entities.phones.is_empty());
+    }
+
+    #[test]
+    fn normalizes_phones_and_amounts() {
+        let entities =
+            extract_entities("Call +1 415-555-0134 about the $1,250.00 invoice (300 EUR deposit).");
+        assert_eq!(entities.phones, vec!["+14155550134"]);
+        assert_eq!(entities.amounts, vec!["1250.00", "300"]);
+    }
+
+    #[test]
+    fn empty_text_has_no_entities() {
+        assert!(extract_entities("nothing to see here").is_empty());
+    }
+}
@@ -0,0 +1,188 @@
+use crate::query::{search, SearchQuery};
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Hits searched for the first relevant one when computing reciprocal rank.
+const RANK_DEPTH: usize = 100;
+
+/// One labeled query from a golden set: the paths a good ranking should
+/// put near the top.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Judgment {
+    pub query: String,
+    /// Absolute paths, or paths relative to any folder (`docs/plan.md`
+    /// matches `/Users/me/docs/plan.md`) so golden sets travel between
+    /// machines.
+    pub relevant: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryEval {
+    pub query: String,
+    /// Share of the top `k` hits that are relevant.
+    pub precision_at_k: f64,
+    /// 1 / rank of the first relevant hit, or 0 when none is in the top
+    /// hundred.
+    pub reciprocal_rank: f64,
+    /// Relevant paths missing from the top `k`.
+    pub missed: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalReport {
+    pub k: usize,
+    pub queries: Vec<QueryEval>,
+    pub mean_precision_at_k: f64,
+    /// Mean reciprocal rank.
+    pub mrr: f64,
+}
+
+/// Read a judgment file: one `query<TAB>path[<TAB>path…]` per line, with
+/// lines for the same query merged. Blank lines and `#` comments are
+/// skipped.
+pub fn load_judgments(path: &Path) -> Result<Vec<Judgment>> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("failed reading {}", path.display()))?;
+    parse_judgments(&text).with_context(|| format!("invalid judgments in {}", path.display()))
+}
+
+pub fn parse_judgments(text: &str) -> Result<Vec<Judgment>> {
+    let mut judgments: Vec<Judgment> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut parts = line.split('\t');
+        let query = parts.next().unwrap_or_default().trim();
+        let relevant: Vec<String> = parts
+            .map(str::trim)
+            .filter(|path| !path.is_empty())
+            .map(str::to_string)
+            .collect();
+        if query.is_empty() || relevant.is_empty() {
+            bail!("line {} needs a query and at least one path", number + 1);
+        }
+        match judgments.iter_mut().find(|j| j.query == query) {
+            Some(judgment) => judgment.relevant.extend(relevant),
+            None => judgments.push(Judgment {
+                query: query.to_string(),
+                relevant,
+            }),
+        }
+    }
+    Ok(judgments)
+}
+
+/// Run every judged query against the current index, using `template` for
+/// everything but the term (scope, ranking profile, filters), and score
+/// precision@`k` and mean reciprocal rank.
+pub fn evaluate(judgments: &[Judgment], template: &SearchQuery, k: usize) -> Result<EvalReport> {
+    if k == 0 {
+        bail!("k must be at least 1");
+    }
+    let mut queries = Vec::with_capacity(judgments.len());
+    for judgment in judgments {
+        let hits = search(SearchQuery {
+            term: judgment.query.clone(),
+            limit: k.max(RANK_DEPTH),
+            offset: 0,
+            snippet_chars: None,
+            ..template.clone()
+        })
+        .with_context(|| format!("query {:?} failed", judgment.query))?;
+        let relevant_at = |rank: usize| {
+            judgment
+                .relevant
+                .iter()
+                .any(|path| is_same_file(&hits[rank].path, path))
+        };
+        let found = (0..hits.len().min(k))
+            .filter(|rank| relevant_at(*rank))
+            .count();
+        let first = (0..hits.len()).find(|rank| relevant_at(*rank));
+        let missed = judgment
+            .relevant
+            .iter()
+            .filter(|path| !hits.iter().take(k).any(|hit| is_same_file(&hit.path, path)))
+            .cloned()
+            .collect();
+        queries.push(QueryEval {
+            query: judgment.query.clone(),
+            precision_at_k: found as f64 / k as f64,
+            reciprocal_rank: first.map_or(0.0, |rank| 1.0 / (rank + 1) as f64),
+            missed,
+        });
+    }
+    let mean = |value: fn(&QueryEval) -> f64| {
+        if queries.is_empty() {
+            0.0
+        } else {
+            queries.iter().map(value).sum::<f64>() / queries.len() as f64
+        }
+    };
+    Ok(EvalReport {
+        k,
+        mean_precision_at_k: mean(|q| q.precision_at_k),
+        mrr: mean(|q| q.reciprocal_rank),
+        queries,
+    })
+}
+
+fn is_same_file(hit: &str, judged: &str) -> bool {
+    hit == judged
+        || (!judged.starts_with('/')
+            && hit
+                .strip_suffix(judged)
+                .is_some_and(|folder| folder.ends_with('/')))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{evaluate, parse_judgments};
+    use crate::query::{SearchDomain, SearchQuery};
+    use crate::{commit, index_root, init_index_in_memory, RootOptions};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn scores_precision_and_reciprocal_rank() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        init_index_in_memory().unwrap();
+        let docs = tempdir().unwrap();
+        fs::create_dir_all(docs.path().join("archive/old")).unwrap();
+        fs::write(docs.path().join("budget.xlsx"), "x").unwrap();
+        fs::write(docs.path().join("archive/old/budget draft.xlsx"), "x").unwrap();
+        fs::write(docs.path().join("roadmap.md"), "x").unwrap();
+        index_root(docs.path(), &RootOptions::default()).unwrap();
+        commit().unwrap();
+
+        let judgments = parse_judgments(
+            "# golden set\n\
+             budget\tarchive/old/budget draft.xlsx\n\
+             roadmap\troadmap.md\n\
+             budget\tbudget.xlsx\n",
+        )
+        .unwrap();
+        assert_eq!(judgments.len(), 2);
+        assert!(parse_judgments("query with no paths\n").is_err());
+
+        let template = SearchQuery {
+            search_in: SearchDomain::Name,
+            ..SearchQuery::default()
+        };
+        let report = evaluate(&judgments, &template, 1).unwrap();
+        assert_eq!(report.queries[0].precision_at_k, 1.0);
+        assert_eq!(
+            report.queries[0].missed,
+            vec!["archive/old/budget draft.xlsx"]
+        );
+        assert_eq!(report.mrr, 1.0);
+
+        let unjudged = parse_judgments("budget\tnope.txt\n").unwrap();
+        let report = evaluate(&unjudged, &template, 2).unwrap();
+        assert_eq!(report.mean_precision_at_k, 0.0);
+        assert_eq!(report.mrr, 0.0);
+    }
+}
@@ -0,0 +1,187 @@
+use crate::pipeline::IndexReport;
+use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use tantivy::TantivyError;
+
+/// Something the host may want to reflect in its UI without polling.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexEvent {
+    /// A commit was published. `generation` is the shared commit counter
+    /// (0 for in-memory indexes).
+    CommitCompleted { opstamp: u64, generation: u64 },
+    /// [`index_root`](crate::index_root), [`index_roots`](crate::index_roots)
+    /// or [`reindex_subtree`](crate::reindex_subtree) finished.
+    ScanFinished {
+        roots: Vec<String>,
+        report: IndexReport,
+    },
+    /// The index grew past the size set with [`set_index_size_alert`].
+    /// Fires once per crossing, not on every commit above it.
+    SizeThresholdCrossed {
+        index_bytes: u64,
+        threshold_bytes: u64,
+    },
+    /// Tantivy reported damaged index files.
+    CorruptionDetected { message: String },
+    /// A commit brought files that now match the standing query `id`
+    /// (see [`add_standing_query`](crate::add_standing_query)).
+    StandingQueryMatched {
+        id: u64,
+        name: String,
+        paths: Vec<String>,
+    },
+}
+
+/// Receives [`IndexEvent`]s. Called on the thread that caused the event,
+/// so implementations should hand work off rather than block.
+pub trait IndexObserver: Send + Sync {
+    fn on_event(&self, event: &IndexEvent);
+}
+
+type Observers = Vec<(u64, Arc<dyn IndexObserver>)>;
+
+static OBSERVERS: Lazy<RwLock<Observers>> = Lazy::new(|| RwLock::new(Vec::new()));
+static NEXT_OBSERVER_ID: AtomicU64 = AtomicU64::new(1);
+/// 0 disables the size alert.
+static SIZE_ALERT_BYTES: AtomicU64 = AtomicU64::new(0);
+static ABOVE_SIZE_ALERT: AtomicBool = AtomicBool::new(false);
+
+/// Register `observer`; the returned id removes it again.
+pub fn add_index_observer(observer: Arc<dyn IndexObserver>) -> u64 {
+    let id = NEXT_OBSERVER_ID.fetch_add(1, Ordering::Relaxed);
+    OBSERVERS.write().unwrap().push((id, observer));
+    id
+}
+
+/// Returns false when no observer has that id.
+pub fn remove_index_observer(id: u64) -> bool {
+    let mut observers = OBSERVERS.write().unwrap();
+    let before = observers.len();
+    observers.retain(|(known, _)| *known != id);
+    observers.len() != before
+}
+
+/// Fire [`IndexEvent::SizeThresholdCrossed`] when a commit leaves the index
+/// larger than `threshold_bytes`; `None` turns the alert off.
+pub fn set_index_size_alert(threshold_bytes: Option<u64>) {
+    SIZE_ALERT_BYTES.store(threshold_bytes.unwrap_or(0), Ordering::Relaxed);
+    ABOVE_SIZE_ALERT.store(false, Ordering::Relaxed);
+}
+
+pub(crate) fn size_alert_bytes() -> Option<u64> {
+    Some(SIZE_ALERT_BYTES.load(Ordering::Relaxed)).filter(|bytes| *bytes > 0)
+}
+
+pub(crate) fn emit(event: IndexEvent) {
+    // Cloned out so an observer may register or remove observers.
+    let observers: Vec<_> = OBSERVERS
+        .read()
+        .unwrap()
+        .iter()
+        .map(|(_, observer)| observer.clone())
+        .collect();
+    for observer in observers {
+        observer.on_event(&event);
+    }
+}
+
+/// Compare the index size after a commit against the alert threshold.
+pub(crate) fn check_index_size(index_bytes: u64) {
+    let Some(threshold_bytes) = size_alert_bytes() else {
+        return;
+    };
+    let above = index_bytes > threshold_bytes;
+    if ABOVE_SIZE_ALERT.swap(above, Ordering::Relaxed) != above && above {
+        emit(IndexEvent::SizeThresholdCrossed {
+            index_bytes,
+            threshold_bytes,
+        });
+    }
+}
+
+/// Emit [`IndexEvent::CorruptionDetected`] when `err` was caused by damaged
+/// index files, and hand the error back unchanged.
+pub(crate) fn report_corruption(err: anyhow::Error) -> anyhow::Error {
+    let corrupt = err.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<TantivyError>(),
+            Some(TantivyError::DataCorruption(_))
+        )
+    });
+    if corrupt {
+        emit(IndexEvent::CorruptionDetected {
+            message: format!("{err:#}"),
+        });
+    }
+    err
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        add_index_observer, remove_index_observer, set_index_size_alert, IndexEvent, IndexObserver,
+    };
+    use crate::{commit, index_root, init_index, RootOptions};
+    use std::fs;
+    use std::sync::{Arc, Mutex};
+    use tempfile::tempdir;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<IndexEvent>>);
+
+    impl IndexObserver for Recorder {
+        fn on_event(&self, event: &IndexEvent) {
+            self.0.lock().unwrap().push(event.clone());
+        }
+    }
+
+    impl Recorder {
+        fn kinds(&self) -> Vec<&'static str> {
+            self.0
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|event| match event {
+                    IndexEvent::CommitCompleted { .. } => "commit",
+                    IndexEvent::ScanFinished { .. } => "scan",
+                    IndexEvent::SizeThresholdCrossed { .. } => "size",
+                    IndexEvent::CorruptionDetected { .. } => "corruption",
+                    IndexEvent::StandingQueryMatched { .. } => "standing",
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn observers_hear_scans_commits_size_alerts_and_corruption() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let index_dir = tempdir().unwrap();
+        init_index(index_dir.path().to_str().unwrap()).unwrap();
+        let docs = tempdir().unwrap();
+        fs::write(docs.path().join("a.txt"), "alpha").unwrap();
+
+        let recorder = Arc::new(Recorder::default());
+        let id = add_index_observer(recorder.clone());
+        set_index_size_alert(Some(1));
+        index_root(docs.path(), &RootOptions::default()).unwrap();
+        commit().unwrap();
+        commit().unwrap();
+        set_index_size_alert(None);
+        assert_eq!(recorder.kinds(), vec!["scan", "commit", "size", "commit"]);
+        match &recorder.0.lock().unwrap()[0] {
+            IndexEvent::ScanFinished { roots, report } => {
+                assert_eq!(roots, &vec![docs.path().to_string_lossy().into_owned()]);
+                assert_eq!(report.added, 1);
+            }
+            other => panic!("unexpected event {other:?}"),
+        }
+
+        crate::close_index();
+        fs::write(index_dir.path().join("meta.json"), "{ not json").unwrap();
+        assert!(init_index(index_dir.path().to_str().unwrap()).is_err());
+        assert!(remove_index_observer(id));
+        assert!(!remove_index_observer(id));
+        assert_eq!(recorder.kinds().last(), Some(&"corruption"));
+    }
+}
@@ -0,0 +1,217 @@
+use crate::query::SearchHit;
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// How [`export_hits`] puts each hit into the destination folder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportMode {
+    #[default]
+    Copy,
+    /// Same file, second name; needs source and destination on one volume.
+    Hardlink,
+    Symlink,
+}
+
+/// What to do when the destination already has an entry with a hit's name,
+/// including one exported earlier in the same call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportConflict {
+    /// Export as `name (2).ext`, `name (3).ext`, ...
+    #[default]
+    Rename,
+    Skip,
+    /// Replace existing files; existing folders are never replaced.
+    Overwrite,
+}
+
+/// Emitted after every hit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExportProgress {
+    pub completed: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExportReport {
+    /// Destination of every exported hit, in hit order.
+    pub exported: Vec<PathBuf>,
+    /// Exported under a new name because of a conflict.
+    pub renamed: usize,
+    /// Left out because of a conflict.
+    pub skipped: usize,
+    /// Source path and error for hits that could not be exported.
+    pub failed: Vec<(String, String)>,
+}
+
+/// Gather the files behind `hits` into `dest_dir` (created if needed), e.g.
+/// every 2023 invoice a query found. A hit that fails is recorded in the
+/// report and the rest still go through.
+pub fn export_hits<F>(
+    hits: &[SearchHit],
+    dest_dir: &Path,
+    mode: ExportMode,
+    on_conflict: ExportConflict,
+    mut on_progress: F,
+) -> Result<ExportReport>
+where
+    F: FnMut(ExportProgress),
+{
+    fs::create_dir_all(dest_dir)
+        .with_context(|| format!("failed creating {}", dest_dir.display()))?;
+    let mut report = ExportReport::default();
+    for (done, hit) in hits.iter().enumerate() {
+        let source = Path::new(&hit.path);
+        let name = source
+            .file_name()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(&hit.name));
+        match destination(dest_dir, &name, on_conflict) {
+            Some((dest, renamed)) => match export_one(source, &dest, mode) {
+                Ok(()) => {
+                    report.renamed += usize::from(renamed);
+                    report.exported.push(dest);
+                }
+                Err(err) => report.failed.push((hit.path.clone(), format!("{err:#}"))),
+            },
+            None => report.skipped += 1,
+        }
+        on_progress(ExportProgress {
+            completed: done + 1,
+            total: hits.len(),
+        });
+    }
+    Ok(report)
+}
+
+/// Where a hit named `name` goes, and whether it had to be renamed; `None`
+/// to skip it.
+fn destination(dir: &Path, name: &Path, on_conflict: ExportConflict) -> Option<(PathBuf, bool)> {
+    let dest = dir.join(name);
+    if fs::symlink_metadata(&dest).is_err() {
+        return Some((dest, false));
+    }
+    match on_conflict {
+        ExportConflict::Skip => None,
+        ExportConflict::Overwrite => Some((dest, false)),
+        ExportConflict::Rename => {
+            let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+            let ext = name
+                .extension()
+                .map(|ext| format!(".{}", ext.to_string_lossy()))
+                .unwrap_or_default();
+            (2..)
+                .map(|n| dir.join(format!("{stem} ({n}){ext}")))
+                .find(|candidate| fs::symlink_metadata(candidate).is_err())
+                .map(|dest| (dest, true))
+        }
+    }
+}
+
+fn export_one(source: &Path, dest: &Path, mode: ExportMode) -> Result<()> {
+    let meta =
+        fs::metadata(source).with_context(|| format!("failed reading {}", source.display()))?;
+    if let Ok(existing) = fs::symlink_metadata(dest) {
+        if existing.is_dir() {
+            bail!("{} is a folder and is not replaced", dest.display());
+        }
+        fs::remove_file(dest).with_context(|| format!("failed replacing {}", dest.display()))?;
+    }
+    match mode {
+        ExportMode::Copy if meta.is_dir() => copy_dir(source, dest),
+        ExportMode::Copy => fs::copy(source, dest).map(|_| ()).map_err(Into::into),
+        ExportMode::Hardlink if meta.is_dir() => bail!("folders cannot be hard-linked"),
+        ExportMode::Hardlink => fs::hard_link(source, dest).map_err(Into::into),
+        ExportMode::Symlink => symlink(source, dest, meta.is_dir()).map_err(Into::into),
+    }
+}
+
+/// Copy a folder, e.g. an app or document bundle, with everything in it.
+fn copy_dir(source: &Path, dest: &Path) -> Result<()> {
+    for entry in WalkDir::new(source) {
+        let entry = entry?;
+        let target = dest.join(entry.path().strip_prefix(source)?);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)?;
+        } else if entry.file_type().is_file() {
+            fs::copy(entry.path(), &target)
+                .with_context(|| format!("failed copying {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn symlink(source: &Path, dest: &Path, _is_dir: bool) -> io::Result<()> {
+    std::os::unix::fs::symlink(source, dest)
+}
+
+#[cfg(windows)]
+fn symlink(source: &Path, dest: &Path, is_dir: bool) -> io::Result<()> {
+    if is_dir {
+        std::os::windows::fs::symlink_dir(source, dest)
+    } else {
+        std::os::windows::fs::symlink_file(source, dest)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn symlink(_source: &Path, _dest: &Path, _is_dir: bool) -> io::Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{export_hits, ExportConflict, ExportMode};
+    use crate::query::SearchHit;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn exports_with_renames_skips_and_failures() {
+        let src = tempdir().unwrap();
+        fs::create_dir_all(src.path().join("2023")).unwrap();
+        fs::create_dir_all(src.path().join("2024")).unwrap();
+        let hits: Vec<SearchHit> = ["2023/invoice.pdf", "2024/invoice.pdf", "missing.pdf"]
+            .iter()
+            .map(|rel| {
+                let path = src.path().join(rel);
+                if !rel.starts_with("missing") {
+                    fs::write(&path, rel.as_bytes()).unwrap();
+                }
+                SearchHit {
+                    path: path.to_string_lossy().into_owned(),
+                    name: "invoice.pdf".into(),
+                    ..SearchHit::default()
+                }
+            })
+            .collect();
+
+        let dest = tempdir().unwrap();
+        let out = dest.path().join("invoices");
+        let mut progress = Vec::new();
+        let report = export_hits(&hits, &out, ExportMode::Copy, ExportConflict::Rename, |p| {
+            progress.push(p.completed)
+        })
+        .unwrap();
+        assert_eq!(progress, vec![1, 2, 3]);
+        assert_eq!(report.renamed, 1);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(
+            fs::read_to_string(out.join("invoice (2).pdf")).unwrap(),
+            "2024/invoice.pdf"
+        );
+
+        let again = export_hits(
+            &hits[..1],
+            &out,
+            ExportMode::Hardlink,
+            ExportConflict::Skip,
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!((again.skipped, again.exported.len()), (1, 0));
+    }
+}
@@ -3,11 +3,69 @@ use std::fs;
 use std::io::Read;
 use std::path::Path;
 
+const DEFAULT_MAX_NON_PRINTABLE_RATIO: f32 = 0.10;
+const DEFAULT_MIN_UTF16_ZERO_RATIO: f32 = 0.40;
+/// Smallest chunk the streaming reader will hand out, so tiny settings don't
+/// degrade into per-byte callbacks.
+const MIN_STREAM_CHUNK_BYTES: usize = 4 * 1024;
+/// Samples shorter than this many code units are too small to guess UTF-16 from.
+const MIN_UTF16_GUESS_UNITS: usize = 4;
+
+/// Tunables for the binary sniffing heuristic.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SniffConfig {
+    /// Fraction of control characters above which a sample is treated as binary.
+    pub max_non_printable_ratio: f32,
+    /// Guess UTF-16 for BOM-less files whose sample has NUL bytes in every
+    /// other position (typical of Windows-exported text).
+    pub detect_utf16_without_bom: bool,
+    /// Minimum fraction of NULs in the high-byte positions for the BOM-less
+    /// UTF-16 guess to kick in.
+    pub min_utf16_zero_ratio: f32,
+}
+
+impl Default for SniffConfig {
+    fn default() -> Self {
+        Self {
+            max_non_printable_ratio: DEFAULT_MAX_NON_PRINTABLE_RATIO,
+            detect_utf16_without_bom: true,
+            min_utf16_zero_ratio: DEFAULT_MIN_UTF16_ZERO_RATIO,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextEncoding {
+    #[default]
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
+}
+
+/// How the sniffer classified a file, kept on the extraction result so
+/// misclassifications can be debugged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SniffDecision {
+    /// False when sniffing was disabled (`sniff_bytes == 0`) or the file was empty.
+    pub sniffed: bool,
+    pub encoding: TextEncoding,
+    /// Whether `encoding` came from a byte order mark rather than a guess.
+    pub from_bom: bool,
+    /// Units examined by the heuristic (bytes for UTF-8, code units otherwise).
+    pub sampled: usize,
+    /// Control characters found among the sampled units.
+    pub non_printable: usize,
+    pub binary: bool,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PlainTextExtraction {
     pub content: Option<String>,
     pub bytes_read: usize,
     pub was_binary: bool,
+    pub sniff: SniffDecision,
 }
 
 /// Read up to `size_limit` bytes from a plain-text file, sniffing the first
@@ -18,6 +76,18 @@ pub fn read_plain_text<P: AsRef<Path>>(
     path: P,
     size_limit: usize,
     sniff_bytes: usize,
+) -> Result<PlainTextExtraction> {
+    read_plain_text_with(path, size_limit, sniff_bytes, &SniffConfig::default())
+}
+
+/// Same as [`read_plain_text`] with explicit sniffing thresholds. UTF-16 and
+/// UTF-32 files (with a BOM, or UTF-16 guessed from the NUL pattern) are
+/// decoded instead of being rejected as binary.
+pub fn read_plain_text_with<P: AsRef<Path>>(
+    path: P,
+    size_limit: usize,
+    sniff_bytes: usize,
+    config: &SniffConfig,
 ) -> Result<PlainTextExtraction> {
     let path = path.as_ref();
     let metadata = fs::metadata(path).with_context(|| {
@@ -32,6 +102,7 @@ pub fn read_plain_text<P: AsRef<Path>>(
             content: None,
             bytes_read: 0,
             was_binary: false,
+            sniff: SniffDecision::default(),
         });
     }
 
@@ -44,6 +115,7 @@ pub fn read_plain_text<P: AsRef<Path>>(
 
     let max_bytes = metadata.len().min(size_limit as u64) as usize;
     let mut buffer = Vec::with_capacity(max_bytes);
+    let mut decision = SniffDecision::default();
 
     if sniff_bytes > 0 && max_bytes > 0 {
         let sniff_len = sniff_bytes.min(max_bytes);
@@ -61,15 +133,18 @@ pub fn read_plain_text<P: AsRef<Path>>(
                 content: Some(String::new()),
                 bytes_read: 0,
                 was_binary: false,
+                sniff: decision,
             });
         }
 
         buffer.extend_from_slice(&head);
-        if looks_binary(&head) {
+        decision = sniff(&head, config);
+        if decision.binary {
             return Ok(PlainTextExtraction {
                 content: None,
                 bytes_read: buffer.len(),
                 was_binary: true,
+                sniff: decision,
             });
         }
     }
@@ -92,45 +167,396 @@ pub fn read_plain_text<P: AsRef<Path>>(
             content: Some(String::new()),
             bytes_read,
             was_binary: false,
+            sniff: decision,
         });
     }
 
-    let content = match String::from_utf8(buffer) {
-        Ok(text) => text,
-        Err(err) => {
-            let lossy = err.into_bytes();
-            String::from_utf8_lossy(&lossy).into_owned()
+    if !decision.sniffed {
+        if let Some((encoding, _)) = detect_bom(&buffer) {
+            decision.encoding = encoding;
+            decision.from_bom = true;
         }
-    };
+    }
+
+    let content = decode(buffer, decision.encoding);
 
     Ok(PlainTextExtraction {
         content: Some(content),
         bytes_read,
         was_binary: false,
+        sniff: decision,
     })
 }
 
+/// Summary of a [`stream_plain_text`] run. The text itself has already been
+/// handed to the chunk callback.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct StreamedExtraction {
+    pub bytes_read: usize,
+    pub chunks: usize,
+    pub was_binary: bool,
+    pub sniff: SniffDecision,
+}
+
+/// Streaming counterpart of [`read_plain_text_with`]: decodes the file in
+/// `chunk_bytes` pieces and passes each piece of text to `on_chunk`, so at most
+/// one chunk is buffered regardless of `size_limit`. Multi-byte sequences that
+/// straddle a chunk boundary are carried over to the next chunk.
+pub fn stream_plain_text<P, F>(
+    path: P,
+    size_limit: usize,
+    sniff_bytes: usize,
+    chunk_bytes: usize,
+    config: &SniffConfig,
+    mut on_chunk: F,
+) -> Result<StreamedExtraction>
+where
+    P: AsRef<Path>,
+    F: FnMut(&str) -> Result<()>,
+{
+    let path = path.as_ref();
+    let metadata = fs::metadata(path).with_context(|| {
+        format!(
+            "failed to stat file for plain text extraction: {}",
+            path.display()
+        )
+    })?;
+
+    if metadata.len() > size_limit as u64 {
+        return Ok(StreamedExtraction::default());
+    }
+
+    let file = fs::File::open(path).with_context(|| {
+        format!(
+            "failed to open file for plain text extraction: {}",
+            path.display()
+        )
+    })?;
+
+    let max_bytes = metadata.len().min(size_limit as u64);
+    let chunk_bytes = chunk_bytes.max(MIN_STREAM_CHUNK_BYTES);
+    let mut reader = file.take(max_bytes);
+    let mut result = StreamedExtraction::default();
+    let mut chunk = vec![0u8; chunk_bytes.max(sniff_bytes)];
+
+    let mut first = read_full(&mut reader, &mut chunk[..chunk_bytes.max(sniff_bytes)])
+        .with_context(|| {
+            format!(
+                "failed reading file head for plain text extraction: {}",
+                path.display()
+            )
+        })?;
+    result.bytes_read = first;
+    if first == 0 {
+        return Ok(result);
+    }
+
+    if sniff_bytes > 0 {
+        result.sniff = sniff(&chunk[..first.min(sniff_bytes)], config);
+        if result.sniff.binary {
+            result.was_binary = true;
+            return Ok(result);
+        }
+    } else if let Some((encoding, _)) = detect_bom(&chunk[..first]) {
+        result.sniff.encoding = encoding;
+        result.sniff.from_bom = true;
+    }
+
+    let mut decoder = ChunkDecoder::new(result.sniff.encoding, &chunk[..first]);
+    loop {
+        let last = (result.bytes_read as u64) >= max_bytes;
+        let text = decoder.decode(&chunk[..first], last);
+        if !text.is_empty() {
+            on_chunk(&text)?;
+            result.chunks += 1;
+        }
+        if last {
+            break;
+        }
+
+        first = read_full(&mut reader, &mut chunk[..chunk_bytes]).with_context(|| {
+            format!(
+                "failed reading file body for plain text extraction: {}",
+                path.display()
+            )
+        })?;
+        if first == 0 {
+            let tail = decoder.decode(&[], true);
+            if !tail.is_empty() {
+                on_chunk(&tail)?;
+                result.chunks += 1;
+            }
+            break;
+        }
+        result.bytes_read += first;
+    }
+
+    Ok(result)
+}
+
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+/// Incremental decoder that keeps incomplete trailing code units between chunks.
+struct ChunkDecoder {
+    encoding: TextEncoding,
+    carry: Vec<u8>,
+    skip: usize,
+}
+
+impl ChunkDecoder {
+    fn new(encoding: TextEncoding, first_chunk: &[u8]) -> Self {
+        let skip = match detect_bom(first_chunk) {
+            Some((bom_encoding, len)) if bom_encoding == encoding => len,
+            _ => 0,
+        };
+        Self {
+            encoding,
+            carry: Vec::new(),
+            skip,
+        }
+    }
+
+    fn decode(&mut self, bytes: &[u8], last: bool) -> String {
+        let mut pending = std::mem::take(&mut self.carry);
+        let skip = std::mem::take(&mut self.skip).min(bytes.len());
+        pending.extend_from_slice(&bytes[skip..]);
+
+        match self.encoding {
+            TextEncoding::Utf8 => {
+                let mut out = String::new();
+                let mut rest = &pending[..];
+                loop {
+                    match std::str::from_utf8(rest) {
+                        Ok(text) => {
+                            out.push_str(text);
+                            break;
+                        }
+                        Err(err) => {
+                            let (valid, after) = rest.split_at(err.valid_up_to());
+                            out.push_str(std::str::from_utf8(valid).unwrap_or_default());
+                            match err.error_len() {
+                                Some(len) => {
+                                    out.push(char::REPLACEMENT_CHARACTER);
+                                    rest = &after[len..];
+                                }
+                                None if last => {
+                                    out.push(char::REPLACEMENT_CHARACTER);
+                                    break;
+                                }
+                                None => {
+                                    self.carry = after.to_vec();
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                }
+                out
+            }
+            TextEncoding::Utf16Le | TextEncoding::Utf16Be => {
+                let mut usable = pending.len() - pending.len() % 2;
+                let units: Vec<u16> = code_units(&pending[..usable], self.encoding)
+                    .into_iter()
+                    .map(|unit| unit as u16)
+                    .collect();
+                let mut units = &units[..];
+                if !last {
+                    if let Some(&unit) = units.last() {
+                        if (0xD800..=0xDBFF).contains(&unit) {
+                            units = &units[..units.len() - 1];
+                            usable -= 2;
+                        }
+                    }
+                    self.carry = pending[usable..].to_vec();
+                }
+                String::from_utf16_lossy(units)
+            }
+            TextEncoding::Utf32Le | TextEncoding::Utf32Be => {
+                let usable = pending.len() - pending.len() % 4;
+                if !last {
+                    self.carry = pending[usable..].to_vec();
+                }
+                code_units(&pending[..usable], self.encoding)
+                    .into_iter()
+                    .map(|unit| char::from_u32(unit).unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect()
+            }
+        }
+    }
+}
+
 pub fn looks_binary(head: &[u8]) -> bool {
+    sniff(head, &SniffConfig::default()).binary
+}
+
+/// Classify a file head: detect the text encoding (BOM first, then the
+/// UTF-16 NUL pattern) and apply the non-printable ratio in that encoding.
+pub fn sniff(head: &[u8], config: &SniffConfig) -> SniffDecision {
+    let mut decision = SniffDecision {
+        sniffed: !head.is_empty(),
+        ..SniffDecision::default()
+    };
     if head.is_empty() {
-        return false;
+        return decision;
     }
-    if head.iter().any(|&b| b == 0) {
-        return true;
+
+    let body = match detect_bom(head) {
+        Some((encoding, bom_len)) => {
+            decision.encoding = encoding;
+            decision.from_bom = true;
+            &head[bom_len..]
+        }
+        None => {
+            if config.detect_utf16_without_bom {
+                if let Some(encoding) = guess_utf16(head, config.min_utf16_zero_ratio) {
+                    decision.encoding = encoding;
+                }
+            }
+            head
+        }
+    };
+
+    let units = code_units(body, decision.encoding);
+    decision.sampled = units.len();
+    if units.is_empty() {
+        return decision;
     }
-    let non_printable = head
+    decision.non_printable = units.iter().filter(|&&unit| is_control(unit)).count();
+
+    let has_nul = units.contains(&0);
+    let ratio = decision.non_printable as f32 / units.len() as f32;
+    decision.binary = has_nul || ratio > config.max_non_printable_ratio;
+    decision
+}
+
+fn is_control(unit: u32) -> bool {
+    unit < 9 || (unit > 13 && unit < 32)
+}
+
+fn detect_bom(bytes: &[u8]) -> Option<(TextEncoding, usize)> {
+    // UTF-32LE must be checked before UTF-16LE since their BOMs share a prefix.
+    if bytes.starts_with(&[0xFF, 0xFE, 0x00, 0x00]) {
+        Some((TextEncoding::Utf32Le, 4))
+    } else if bytes.starts_with(&[0x00, 0x00, 0xFE, 0xFF]) {
+        Some((TextEncoding::Utf32Be, 4))
+    } else if bytes.starts_with(&[0xFF, 0xFE]) {
+        Some((TextEncoding::Utf16Le, 2))
+    } else if bytes.starts_with(&[0xFE, 0xFF]) {
+        Some((TextEncoding::Utf16Be, 2))
+    } else if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        Some((TextEncoding::Utf8, 3))
+    } else {
+        None
+    }
+}
+
+fn guess_utf16(head: &[u8], min_zero_ratio: f32) -> Option<TextEncoding> {
+    let pairs = head.len() / 2;
+    if pairs < MIN_UTF16_GUESS_UNITS {
+        return None;
+    }
+    let even_zeros = head
         .iter()
-        .filter(|&&b| b < 9 || (b > 13 && b < 32))
+        .step_by(2)
+        .take(pairs)
+        .filter(|&&b| b == 0)
         .count();
-    (non_printable as f32 / head.len() as f32) > 0.10
+    let odd_zeros = head.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+    let threshold = (pairs as f32 * min_zero_ratio).ceil() as usize;
+    // Mostly-ASCII UTF-16 has NULs in the high byte and almost never in the low byte.
+    if odd_zeros >= threshold && even_zeros * 10 < pairs {
+        Some(TextEncoding::Utf16Le)
+    } else if even_zeros >= threshold && odd_zeros * 10 < pairs {
+        Some(TextEncoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+fn code_units(bytes: &[u8], encoding: TextEncoding) -> Vec<u32> {
+    match encoding {
+        TextEncoding::Utf8 => bytes.iter().map(|&b| b as u32).collect(),
+        TextEncoding::Utf16Le => bytes
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]) as u32)
+            .collect(),
+        TextEncoding::Utf16Be => bytes
+            .chunks_exact(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]) as u32)
+            .collect(),
+        TextEncoding::Utf32Le => bytes
+            .chunks_exact(4)
+            .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+            .collect(),
+        TextEncoding::Utf32Be => bytes
+            .chunks_exact(4)
+            .map(|c| u32::from_be_bytes([c[0], c[1], c[2], c[3]]))
+            .collect(),
+    }
+}
+
+fn decode(buffer: Vec<u8>, encoding: TextEncoding) -> String {
+    let bom_len = match detect_bom(&buffer) {
+        Some((bom_encoding, len)) if bom_encoding == encoding => len,
+        _ => 0,
+    };
+
+    match encoding {
+        TextEncoding::Utf8 => {
+            let bytes = if bom_len > 0 {
+                buffer[bom_len..].to_vec()
+            } else {
+                buffer
+            };
+            match String::from_utf8(bytes) {
+                Ok(text) => text,
+                Err(err) => {
+                    let lossy = err.into_bytes();
+                    String::from_utf8_lossy(&lossy).into_owned()
+                }
+            }
+        }
+        TextEncoding::Utf16Le | TextEncoding::Utf16Be => {
+            let units: Vec<u16> = code_units(&buffer[bom_len..], encoding)
+                .into_iter()
+                .map(|unit| unit as u16)
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        TextEncoding::Utf32Le | TextEncoding::Utf32Be => code_units(&buffer[bom_len..], encoding)
+            .into_iter()
+            .map(|unit| char::from_u32(unit).unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect(),
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{looks_binary, read_plain_text};
+    use super::{
+        looks_binary, read_plain_text, read_plain_text_with, sniff, stream_plain_text, SniffConfig,
+        TextEncoding,
+    };
     use std::char::REPLACEMENT_CHARACTER;
     use std::fs;
     use tempfile::tempdir;
 
+    fn utf16le(text: &str, bom: bool) -> Vec<u8> {
+        let mut bytes = if bom { vec![0xFF, 0xFE] } else { Vec::new() };
+        for unit in text.encode_utf16() {
+            bytes.extend_from_slice(&unit.to_le_bytes());
+        }
+        bytes
+    }
+
     #[test]
     fn reads_small_utf8_file() {
         let dir = tempdir().unwrap();
@@ -179,6 +605,8 @@ mod tests {
         assert!(text.content.is_none());
         assert!(text.was_binary);
         assert_eq!(text.bytes_read, b"\x00\x01\x02\x03rest".len().min(8));
+        assert!(text.sniff.binary);
+        assert!(text.sniff.non_printable > 0);
     }
 
     #[test]
@@ -186,4 +614,131 @@ mod tests {
         assert!(!looks_binary(b"Hello, world!"));
         assert!(looks_binary(b"\x00bad"));
     }
+
+    #[test]
+    fn decodes_utf16_with_and_without_bom() {
+        let dir = tempdir().unwrap();
+        for (name, bom) in [("with_bom.txt", true), ("no_bom.txt", false)] {
+            let file_path = dir.path().join(name);
+            fs::write(&file_path, utf16le("quarterly report", bom)).unwrap();
+
+            let text = read_plain_text(&file_path, 1024, 4096).unwrap();
+            assert!(!text.was_binary, "{name} treated as binary");
+            assert_eq!(text.content.as_deref(), Some("quarterly report"));
+            assert_eq!(text.sniff.encoding, TextEncoding::Utf16Le);
+            assert_eq!(text.sniff.from_bom, bom);
+        }
+    }
+
+    #[test]
+    fn decodes_utf32_and_strips_utf8_bom() {
+        let dir = tempdir().unwrap();
+        let utf32_path = dir.path().join("utf32.txt");
+        let mut bytes = vec![0x00, 0x00, 0xFE, 0xFF];
+        for ch in "héllo".chars() {
+            bytes.extend_from_slice(&(ch as u32).to_be_bytes());
+        }
+        fs::write(&utf32_path, bytes).unwrap();
+        let text = read_plain_text(&utf32_path, 1024, 4096).unwrap();
+        assert_eq!(text.content.as_deref(), Some("héllo"));
+        assert_eq!(text.sniff.encoding, TextEncoding::Utf32Be);
+
+        let utf8_path = dir.path().join("bom.txt");
+        fs::write(&utf8_path, b"\xEF\xBB\xBFplain").unwrap();
+        let text = read_plain_text(&utf8_path, 1024, 0).unwrap();
+        assert_eq!(text.content.as_deref(), Some("plain"));
+        assert!(!text.sniff.sniffed);
+        assert!(text.sniff.from_bom);
+    }
+
+    #[test]
+    fn thresholds_are_configurable() {
+        let head = b"mostly text\x01\x02";
+        assert!(sniff(head, &SniffConfig::default()).binary);
+
+        let lenient = SniffConfig {
+            max_non_printable_ratio: 0.5,
+            ..SniffConfig::default()
+        };
+        assert!(!sniff(head, &lenient).binary);
+
+        let strict_utf16 = SniffConfig {
+            detect_utf16_without_bom: false,
+            ..SniffConfig::default()
+        };
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("no_bom.txt");
+        fs::write(&file_path, utf16le("notes", false)).unwrap();
+        let text = read_plain_text_with(&file_path, 1024, 4096, &strict_utf16).unwrap();
+        assert!(text.was_binary);
+    }
+
+    #[test]
+    fn streams_in_bounded_chunks_without_splitting_characters() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("large.txt");
+        let line = "héllo wörld ✓\n";
+        let text = line.repeat(2_000);
+        fs::write(&file_path, &text).unwrap();
+
+        let mut collected = String::new();
+        let mut largest = 0;
+        let result = stream_plain_text(
+            &file_path,
+            1 << 20,
+            512,
+            4096,
+            &SniffConfig::default(),
+            |chunk| {
+                largest = largest.max(chunk.len());
+                collected.push_str(chunk);
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        assert_eq!(collected, text);
+        assert_eq!(result.bytes_read, text.len());
+        assert!(result.chunks > 1);
+        assert!(largest <= 4096 + 4);
+        assert!(!result.was_binary);
+    }
+
+    #[test]
+    fn streams_utf16_and_stops_on_binary() {
+        let dir = tempdir().unwrap();
+        let utf16_path = dir.path().join("utf16.txt");
+        let text = "Ünïcode line\n".repeat(1_000);
+        fs::write(&utf16_path, utf16le(&text, true)).unwrap();
+
+        let mut collected = String::new();
+        let result = stream_plain_text(
+            &utf16_path,
+            1 << 20,
+            0,
+            4097,
+            &SniffConfig::default(),
+            |chunk| {
+                collected.push_str(chunk);
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(collected, text);
+        assert_eq!(result.sniff.encoding, TextEncoding::Utf16Le);
+
+        let binary_path = dir.path().join("blob.bin");
+        fs::write(&binary_path, [0u8; 64]).unwrap();
+        let result = stream_plain_text(
+            &binary_path,
+            1 << 20,
+            16,
+            4096,
+            &SniffConfig::default(),
+            |_| panic!("binary content must not be streamed"),
+        )
+        .unwrap();
+        assert!(result.was_binary);
+        assert_eq!(result.chunks, 0);
+    }
 }
@@ -0,0 +1,537 @@
+use crate::plist::{parse_plist, PlistValue};
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Nesting bound for JSON documents; deeper input is treated as unparsable.
+const MAX_JSON_DEPTH: usize = 128;
+
+/// Configuration formats whose keys and values are indexed instead of raw syntax.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StructuredFormat {
+    Json,
+    Yaml,
+    Plist,
+}
+
+impl StructuredFormat {
+    pub fn from_ext(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "json" | "jsonc" | "geojson" | "webmanifest" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "plist" => Some(Self::Plist),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructuredExtraction {
+    /// One `dotted.key.path: value` line per scalar, in document order.
+    pub content: String,
+    pub bytes_read: usize,
+}
+
+/// Read a JSON, YAML or plist file and flatten it into searchable lines.
+/// `Ok(None)` when the file is over `size_limit` or doesn't parse, so the
+/// caller can fall back to plain-text extraction.
+pub fn read_structured_text<P: AsRef<Path>>(
+    path: P,
+    format: StructuredFormat,
+    size_limit: usize,
+) -> Result<Option<StructuredExtraction>> {
+    let path = path.as_ref();
+    let metadata = fs::metadata(path)
+        .with_context(|| format!("failed to stat structured file: {}", path.display()))?;
+    if metadata.len() > size_limit as u64 {
+        return Ok(None);
+    }
+    let bytes = fs::read(path)
+        .with_context(|| format!("failed to read structured file: {}", path.display()))?;
+    Ok(
+        flatten_structured(&bytes, format).map(|content| StructuredExtraction {
+            content,
+            bytes_read: bytes.len(),
+        }),
+    )
+}
+
+/// Flatten structured bytes; see [`read_structured_text`].
+pub fn flatten_structured(bytes: &[u8], format: StructuredFormat) -> Option<String> {
+    let mut out = Flattened::default();
+    match format {
+        StructuredFormat::Plist => out.plist(&parse_plist(bytes)?),
+        StructuredFormat::Json => {
+            let text = std::str::from_utf8(bytes).ok()?;
+            let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+            let mut parser = JsonParser {
+                bytes: text.as_bytes(),
+                at: 0,
+                out: &mut out,
+            };
+            parser.value(0)?;
+            parser.skip_ws();
+            if parser.at != parser.bytes.len() {
+                return None;
+            }
+        }
+        StructuredFormat::Yaml => out.yaml(std::str::from_utf8(bytes).ok()?),
+    }
+    Some(out.text)
+}
+
+/// Every scalar of a JSON document as a `(dotted.key.path, value)` pair,
+/// in document order; `None` when it doesn't parse.
+pub(crate) fn json_fields(text: &str) -> Option<Vec<(String, String)>> {
+    let mut out = Flattened {
+        fields: Some(Vec::new()),
+        ..Flattened::default()
+    };
+    let mut parser = JsonParser {
+        bytes: text.as_bytes(),
+        at: 0,
+        out: &mut out,
+    };
+    parser.value(0)?;
+    parser.skip_ws();
+    if parser.at != parser.bytes.len() {
+        return None;
+    }
+    out.fields
+}
+
+/// Output buffer plus the key path leading to the value being visited.
+#[derive(Default)]
+struct Flattened {
+    text: String,
+    path: Vec<String>,
+    /// Also collect each scalar with its key path, for [`json_fields`].
+    fields: Option<Vec<(String, String)>>,
+}
+
+impl Flattened {
+    fn emit(&mut self, value: &str) {
+        let value = value.trim();
+        if self.path.is_empty() && value.is_empty() {
+            return;
+        }
+        if let Some(fields) = &mut self.fields {
+            fields.push((self.path.join("."), value.to_string()));
+            return;
+        }
+        self.text.push_str(&self.path.join("."));
+        if !value.is_empty() {
+            if !self.path.is_empty() {
+                self.text.push_str(": ");
+            }
+            self.text.push_str(value);
+        }
+        self.text.push('\n');
+    }
+
+    fn plist(&mut self, value: &PlistValue) {
+        match value {
+            PlistValue::String(text) => self.emit(text),
+            PlistValue::Integer(number) => self.emit(&number.to_string()),
+            PlistValue::Real(number) => self.emit(&number.to_string()),
+            PlistValue::Bool(flag) => self.emit(&flag.to_string()),
+            PlistValue::Array(items) => {
+                for item in items {
+                    self.plist(item);
+                }
+            }
+            PlistValue::Dict(entries) => {
+                if entries.is_empty() {
+                    self.emit("");
+                }
+                for (key, value) in entries {
+                    self.path.push(key.clone());
+                    self.plist(value);
+                    self.path.pop();
+                }
+            }
+            PlistValue::Other => self.emit(""),
+        }
+    }
+
+    /// Line-oriented YAML: block mappings and sequences by indentation,
+    /// block scalars joined, and flow collections kept as their text. Good
+    /// enough to make keys and values searchable without a full parser.
+    fn yaml(&mut self, text: &str) {
+        // Indent of each open mapping key; each one owns a path entry.
+        let mut levels: Vec<usize> = Vec::new();
+        let mut lines = text.lines().peekable();
+        while let Some(raw) = lines.next() {
+            let line = strip_yaml_comment(raw);
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed == "---" || trimmed == "..." {
+                continue;
+            }
+            let mut indent = line.len() - line.trim_start().len();
+            while levels.last().is_some_and(|open| *open >= indent) {
+                levels.pop();
+                self.path.pop();
+            }
+            // Sequence items are keyed by their parent; a mapping inside an
+            // item is indented to where its first key starts.
+            let mut entry = trimmed;
+            while let Some(item) = entry
+                .strip_prefix('-')
+                .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+            {
+                let item = item.trim_start();
+                indent += entry.len() - item.len();
+                entry = item;
+            }
+            if entry.is_empty() {
+                continue;
+            }
+            let Some((key, value)) = split_yaml_key(entry) else {
+                self.emit(unquote(entry));
+                continue;
+            };
+            self.path.push(unquote(key).to_string());
+            match value {
+                "" => {
+                    levels.push(indent);
+                    continue;
+                }
+                "|" | ">" | "|-" | ">-" | "|+" | ">+" => {
+                    let mut block = String::new();
+                    while let Some(next) = lines.peek() {
+                        let next_indent = next.len() - next.trim_start().len();
+                        if !next.trim().is_empty() && next_indent <= indent {
+                            break;
+                        }
+                        block.push_str(next.trim());
+                        block.push(' ');
+                        lines.next();
+                    }
+                    self.emit(&block);
+                }
+                value => self.emit(unquote(value)),
+            }
+            self.path.pop();
+        }
+    }
+}
+
+fn strip_yaml_comment(line: &str) -> &str {
+    if line.trim_start().starts_with('#') {
+        return "";
+    }
+    let mut quote = None;
+    let mut previous = ' ';
+    for (at, ch) in line.char_indices() {
+        match (quote, ch) {
+            (None, '"' | '\'') if previous == ' ' || previous == ':' => quote = Some(ch),
+            (Some(open), _) if ch == open => quote = None,
+            (None, '#') if previous.is_whitespace() => return &line[..at],
+            _ => {}
+        }
+        previous = ch;
+    }
+    line
+}
+
+/// `key: value` or `key:` outside quotes; `None` for plain scalars.
+fn split_yaml_key(entry: &str) -> Option<(&str, &str)> {
+    let search_from = match entry.chars().next() {
+        Some(quote @ ('"' | '\'')) => entry[1..].find(quote)? + 2,
+        Some('[' | '{') => return None,
+        _ => 0,
+    };
+    let colon = entry[search_from..]
+        .match_indices(':')
+        .map(|(at, _)| at + search_from)
+        .find(|at| {
+            entry[at + 1..]
+                .chars()
+                .next()
+                .is_none_or(char::is_whitespace)
+        })?;
+    Some((entry[..colon].trim(), entry[colon + 1..].trim()))
+}
+
+fn unquote(value: &str) -> &str {
+    let value = value.trim();
+    for quote in ['"', '\''] {
+        if value.len() >= 2 && value.starts_with(quote) && value.ends_with(quote) {
+            return &value[1..value.len() - 1];
+        }
+    }
+    value
+}
+
+struct JsonParser<'a, 'o> {
+    bytes: &'a [u8],
+    at: usize,
+    out: &'o mut Flattened,
+}
+
+impl JsonParser<'_, '_> {
+    fn skip_ws(&mut self) {
+        loop {
+            while self
+                .bytes
+                .get(self.at)
+                .is_some_and(|byte| byte.is_ascii_whitespace())
+            {
+                self.at += 1;
+            }
+            // JSONC comments, as found in editor settings files.
+            if self.bytes[self.at..].starts_with(b"//") {
+                while self.bytes.get(self.at).is_some_and(|byte| *byte != b'\n') {
+                    self.at += 1;
+                }
+            } else if self.bytes[self.at..].starts_with(b"/*") {
+                match find(&self.bytes[self.at + 2..], b"*/") {
+                    Some(end) => self.at += end + 4,
+                    None => self.at = self.bytes.len(),
+                }
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        self.skip_ws();
+        if self.bytes.get(self.at) == Some(&byte) {
+            self.at += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Option<()> {
+        if depth > MAX_JSON_DEPTH {
+            return None;
+        }
+        self.skip_ws();
+        match *self.bytes.get(self.at)? {
+            b'{' => {
+                self.at += 1;
+                if self.eat(b'}') {
+                    self.out.emit("");
+                    return Some(());
+                }
+                loop {
+                    self.skip_ws();
+                    let key = self.string()?;
+                    if !self.eat(b':') {
+                        return None;
+                    }
+                    self.out.path.push(key);
+                    self.value(depth + 1)?;
+                    self.out.path.pop();
+                    if self.eat(b',') {
+                        // Trailing commas are tolerated, as in JSONC.
+                        if self.eat(b'}') {
+                            return Some(());
+                        }
+                        continue;
+                    }
+                    return self.eat(b'}').then_some(());
+                }
+            }
+            b'[' => {
+                self.at += 1;
+                if self.eat(b']') {
+                    return Some(());
+                }
+                loop {
+                    self.value(depth + 1)?;
+                    if self.eat(b',') {
+                        if self.eat(b']') {
+                            return Some(());
+                        }
+                        continue;
+                    }
+                    return self.eat(b']').then_some(());
+                }
+            }
+            b'"' => {
+                let text = self.string()?;
+                self.out.emit(&text);
+                Some(())
+            }
+            _ => {
+                let start = self.at;
+                while self
+                    .bytes
+                    .get(self.at)
+                    .is_some_and(|byte| byte.is_ascii_alphanumeric() || b"+-.".contains(byte))
+                {
+                    self.at += 1;
+                }
+                let literal = std::str::from_utf8(&self.bytes[start..self.at]).ok()?;
+                match literal {
+                    "null" => self.out.emit(""),
+                    "true" | "false" => self.out.emit(literal),
+                    _ if literal.parse::<f64>().is_ok() => self.out.emit(literal),
+                    _ => return None,
+                }
+                Some(())
+            }
+        }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        if self.bytes.get(self.at) != Some(&b'"') {
+            return None;
+        }
+        self.at += 1;
+        let mut text = String::new();
+        loop {
+            let start = self.at;
+            while self
+                .bytes
+                .get(self.at)
+                .is_some_and(|byte| *byte != b'"' && *byte != b'\\')
+            {
+                self.at += 1;
+            }
+            text.push_str(std::str::from_utf8(&self.bytes[start..self.at]).ok()?);
+            match *self.bytes.get(self.at)? {
+                b'"' => {
+                    self.at += 1;
+                    return Some(text);
+                }
+                _ => {
+                    let escape = *self.bytes.get(self.at + 1)?;
+                    self.at += 2;
+                    match escape {
+                        b'n' => text.push('\n'),
+                        b't' => text.push('\t'),
+                        b'r' => text.push('\r'),
+                        b'b' | b'f' => text.push(' '),
+                        b'u' => text.push(self.unicode_escape()?),
+                        other => text.push(other as char),
+                    }
+                }
+            }
+        }
+    }
+
+    /// `\uXXXX`, combining surrogate pairs; lone surrogates become U+FFFD.
+    fn unicode_escape(&mut self) -> Option<char> {
+        let high = self.hex4()?;
+        if (0xd800..0xdc00).contains(&high) && self.bytes[self.at..].starts_with(b"\\u") {
+            self.at += 2;
+            let low = self.hex4()?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Some('\u{fffd}');
+            }
+            let combined = 0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00);
+            return Some(char::from_u32(combined).unwrap_or('\u{fffd}'));
+        }
+        Some(char::from_u32(high).unwrap_or('\u{fffd}'))
+    }
+
+    fn hex4(&mut self) -> Option<u32> {
+        let digits = std::str::from_utf8(self.bytes.get(self.at..self.at + 4)?).ok()?;
+        self.at += 4;
+        u32::from_str_radix(digits, 16).ok()
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{flatten_structured, read_structured_text, StructuredFormat};
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn lines(text: &str) -> Vec<&str> {
+        text.lines().collect()
+    }
+
+    #[test]
+    fn flattens_json_keys_and_values() {
+        let json = r#"{
+            // editor settings
+            "network": {"ProxyHost": "proxy.corp.example", "Port": 8080, "tags": ["aé", "b",]},
+            "enabled": true,
+            "empty": {},
+            "note": null
+        }"#;
+        let text = flatten_structured(json.as_bytes(), StructuredFormat::Json).unwrap();
+        assert_eq!(
+            lines(&text),
+            vec![
+                "network.ProxyHost: proxy.corp.example",
+                "network.Port: 8080",
+                "network.tags: aé",
+                "network.tags: b",
+                "enabled: true",
+                "empty",
+                "note",
+            ]
+        );
+        assert!(flatten_structured(b"{\"a\": }", StructuredFormat::Json).is_none());
+        assert!(flatten_structured(b"{} trailing", StructuredFormat::Json).is_none());
+    }
+
+    #[test]
+    fn flattens_yaml_blocks_and_sequences() {
+        let yaml = "\
+# deployment
+---
+server:
+  host: \"db.internal\"   # primary
+  ports:
+    - 5432
+    - 5433
+  replicas:
+    - name: east
+      zone: us-east-1
+description: |
+  Multi-line
+  text block
+url: http://example.com/#anchor
+";
+        let text = flatten_structured(yaml.as_bytes(), StructuredFormat::Yaml).unwrap();
+        assert_eq!(
+            lines(&text),
+            vec![
+                "server.host: db.internal",
+                "server.ports: 5432",
+                "server.ports: 5433",
+                "server.replicas.name: east",
+                "server.replicas.zone: us-east-1",
+                "description: Multi-line text block",
+                "url: http://example.com/#anchor",
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_plists_and_respects_the_size_limit() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("com.example.proxy.plist");
+        fs::write(
+            &path,
+            crate::plist::tests::binary_plist(&[("ProxyHost", "10.0.0.1")]),
+        )
+        .unwrap();
+        let extraction = read_structured_text(&path, StructuredFormat::Plist, 1 << 20)
+            .unwrap()
+            .unwrap();
+        assert_eq!(extraction.content, "ProxyHost: 10.0.0.1\n");
+        assert!(read_structured_text(&path, StructuredFormat::Plist, 4)
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            StructuredFormat::from_ext("YML"),
+            Some(StructuredFormat::Yaml)
+        );
+        assert_eq!(StructuredFormat::from_ext("txt"), None);
+    }
+}
@@ -0,0 +1,737 @@
+use crate::indexer::{self, IndexFields};
+use crate::query::{build_query, SearchQuery};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use tantivy::collector::{Collector, DocSetCollector, SegmentCollector};
+use tantivy::columnar::{Column, StrColumn};
+use tantivy::fastfield::FacetReader;
+use tantivy::query::{BooleanQuery, EnableScoring, Occur, Query, TermSetQuery};
+use tantivy::schema::{Facet, TantivyDocument, Value};
+use tantivy::{DocId, Score, Searcher, SegmentOrdinal, SegmentReader, Term};
+
+/// Documents [`count_by_extension_approx`] reads at most (give or take a
+/// segment) before scaling its tallies up to the whole index.
+const APPROX_SAMPLE_DOCS: u64 = 50_000;
+
+/// How many matching files have one extension, for filter chips such as
+/// "pdf (42)".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionCount {
+    /// Lowercased, without the dot.
+    pub ext: String,
+    pub count: u64,
+}
+
+/// Extension counts that may be estimates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FacetCounts {
+    pub counts: Vec<ExtensionCount>,
+    /// True when `counts` were scaled up from a sample, so the UI can show
+    /// "~12k" and ask for exact counts once the user settles.
+    pub approx: bool,
+}
+
+/// How many matches lie anywhere below one subfolder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FolderCount {
+    pub folder: PathBuf,
+    pub count: u64,
+}
+
+/// One column of a drill-down view: the matches in a folder, split by the
+/// subfolder they lie in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FolderLevel {
+    pub folder: PathBuf,
+    /// Matches directly inside `folder`.
+    pub direct: u64,
+    /// Subfolders holding matches, most first (ties by path).
+    pub subfolders: Vec<FolderCount>,
+}
+
+/// How many matches lie below one root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RootCount {
+    pub root: PathBuf,
+    pub count: u64,
+}
+
+/// Matching files per extension for `query`, most common first (ties
+/// by name). The query's own `extensions` filter is left out so every chip
+/// keeps a count while one is selected; files without an extension are not
+/// listed. Like [`crate::count`], this counts before path globs.
+pub fn count_by_extension(query: SearchQuery) -> Result<Vec<ExtensionCount>> {
+    let searcher = indexer::reader()
+        .context("reader not available")?
+        .searcher();
+    let fields = indexer::fields()?;
+    let query = SearchQuery {
+        extensions: Vec::new(),
+        ..query
+    };
+    let Some(built) = build_query(&query, searcher.index(), &fields)? else {
+        return Ok(Vec::new());
+    };
+    let (mut totals, chunked) = searcher
+        .search(&*built, &ExtensionCounts)
+        .context("tantivy search execution failed")?;
+    for ext in chunk_only_files(&searcher, &*built, &fields, chunked)? {
+        *totals.entry(ext).or_insert(0) += 1;
+    }
+    Ok(sorted_counts(totals))
+}
+
+/// Like [`count_by_extension`], but on large indexes only reads a spread
+/// of segments holding about fifty thousand documents and scales their
+/// tallies up. Fast enough to run on every keystroke; rare extensions can
+/// be missing or off, so follow up with the exact counts.
+pub fn count_by_extension_approx(query: SearchQuery) -> Result<FacetCounts> {
+    count_by_extension_sampled(query, APPROX_SAMPLE_DOCS)
+}
+
+fn count_by_extension_sampled(query: SearchQuery, sample_docs: u64) -> Result<FacetCounts> {
+    let searcher = indexer::reader()
+        .context("reader not available")?
+        .searcher();
+    let total: u64 = searcher
+        .segment_readers()
+        .iter()
+        .map(|segment| u64::from(segment.num_docs()))
+        .sum();
+    if total <= sample_docs {
+        return Ok(FacetCounts {
+            counts: count_by_extension(query)?,
+            approx: false,
+        });
+    }
+
+    let fields = indexer::fields()?;
+    let query = SearchQuery {
+        extensions: Vec::new(),
+        ..query
+    };
+    let Some(built) = build_query(&query, searcher.index(), &fields)? else {
+        return Ok(FacetCounts {
+            counts: Vec::new(),
+            approx: false,
+        });
+    };
+    let (totals, sampled) = sample_segments(&searcher, &*built, &fields, total, sample_docs)?;
+    let scale = total as f64 / sampled.max(1) as f64;
+    let scaled = totals
+        .into_iter()
+        .map(|(ext, count)| (ext, (count as f64 * scale).round() as u64))
+        .collect();
+    Ok(FacetCounts {
+        counts: sorted_counts(scaled),
+        approx: sampled < total,
+    })
+}
+
+/// Tallies from the segments holding every `total / sample_docs`-th
+/// document, so old and new parts of the index are both represented,
+/// and how many documents those segments hold.
+fn sample_segments(
+    searcher: &Searcher,
+    query: &dyn Query,
+    fields: &IndexFields,
+    total: u64,
+    sample_docs: u64,
+) -> Result<(HashMap<String, u64>, u64)> {
+    let weight = query
+        .weight(EnableScoring::disabled_from_searcher(searcher))
+        .context("failed preparing facet query")?;
+    let step = (total / sample_docs.max(1)).max(1);
+    let mut next_pick = 0;
+    let mut start = 0;
+    let mut sampled = 0;
+    let mut fruits = Vec::new();
+    for (ord, segment) in searcher.segment_readers().iter().enumerate() {
+        let end = start + u64::from(segment.num_docs());
+        if next_pick < end {
+            let fruit = ExtensionCounts
+                .collect_segment(&*weight, ord as SegmentOrdinal, segment)
+                .context("tantivy search execution failed")?;
+            fruits.push(fruit);
+            sampled += end - start;
+            next_pick += (end - next_pick).div_ceil(step) * step;
+        }
+        start = end;
+    }
+    let (mut totals, chunked) = ExtensionCounts
+        .merge_fruits(fruits)
+        .context("tantivy search execution failed")?;
+    for ext in chunk_only_files(searcher, query, fields, chunked)? {
+        *totals.entry(ext).or_insert(0) += 1;
+    }
+    Ok((totals, sampled))
+}
+
+/// Where `query`'s matches live, for a column view drilled down to
+/// `folder`: one level for `folder` and each of its ancestors, outermost
+/// first, all counted in one pass over the matches. Like
+/// [`crate::count`], this counts before path globs.
+pub fn count_by_folder<P: AsRef<Path>>(query: SearchQuery, folder: P) -> Result<Vec<FolderLevel>> {
+    let searcher = indexer::reader()
+        .context("reader not available")?
+        .searcher();
+    let fields = indexer::fields()?;
+    let mut folders: Vec<&Path> = folder.as_ref().ancestors().collect();
+    folders.reverse();
+    let collector = FolderCounts {
+        folders: folders
+            .iter()
+            .map(|folder| folder_components(folder))
+            .collect(),
+    };
+    let totals = match build_query(&query, searcher.index(), &fields)? {
+        Some(built) => collector.count(&searcher, &*built, &fields)?,
+        None => vec![(0, HashMap::new()); folders.len()],
+    };
+    Ok(folders
+        .into_iter()
+        .zip(totals)
+        .map(|(folder, (direct, below))| {
+            let mut subfolders: Vec<FolderCount> = below
+                .into_iter()
+                .map(|(name, count)| FolderCount {
+                    folder: folder.join(name),
+                    count,
+                })
+                .collect();
+            subfolders.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.folder.cmp(&b.folder)));
+            FolderLevel {
+                folder: folder.to_path_buf(),
+                direct,
+                subfolders,
+            }
+        })
+        .collect())
+}
+
+/// How many of `query`'s matches lie below each of `roots`, in the order
+/// given, for a sidebar such as "Documents (84) · External SSD (12)", all
+/// counted in one pass. The query's own `roots` filter is left out so every
+/// root keeps a count while one is selected; a match below nested roots
+/// counts for each. Like [`crate::count`], this counts before path globs.
+pub fn counts_by_root(query: SearchQuery, roots: &[PathBuf]) -> Result<Vec<RootCount>> {
+    let searcher = indexer::reader()
+        .context("reader not available")?
+        .searcher();
+    let fields = indexer::fields()?;
+    let query = SearchQuery {
+        roots: Vec::new(),
+        ..query
+    };
+    let collector = FolderCounts {
+        folders: roots.iter().map(|root| folder_components(root)).collect(),
+    };
+    let totals = match build_query(&query, searcher.index(), &fields)? {
+        Some(built) => collector.count(&searcher, &*built, &fields)?,
+        None => vec![(0, HashMap::new()); roots.len()],
+    };
+    Ok(roots
+        .iter()
+        .zip(totals)
+        .map(|(root, (direct, below))| RootCount {
+            root: root.clone(),
+            count: direct + below.values().sum::<u64>(),
+        })
+        .collect())
+}
+
+/// The `folder` facet of files directly inside `folder`.
+pub(crate) fn folder_facet(folder: &Path) -> Facet {
+    Facet::from_path(folder_components(folder))
+}
+
+fn folder_components(folder: &Path) -> Vec<String> {
+    folder
+        .components()
+        .filter_map(|component| match component {
+            Component::Prefix(prefix) => Some(prefix.as_os_str().to_string_lossy().into_owned()),
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn sorted_counts(totals: HashMap<String, u64>) -> Vec<ExtensionCount> {
+    let mut counts: Vec<ExtensionCount> = totals
+        .into_iter()
+        .map(|(ext, count)| ExtensionCount { ext, count })
+        .collect();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.ext.cmp(&b.ext)));
+    counts
+}
+
+/// Chunk documents of long files that matched, by path, with what each file
+/// counts toward. They are left out of the tallies, and counted by
+/// [`chunk_only_files`] only for files whose own document did not match.
+type Chunked<K> = HashMap<String, K>;
+
+/// Notes a segment's matching chunk documents instead of tallying them.
+struct SegmentChunks {
+    chunk: Option<Column<u64>>,
+    paths: Option<StrColumn>,
+    /// Path ordinal and tally ordinal of each matching chunk document.
+    matched: Vec<(u64, u64)>,
+}
+
+impl SegmentChunks {
+    fn open(segment: &SegmentReader) -> tantivy::Result<Self> {
+        let fast = segment.fast_fields();
+        Ok(SegmentChunks {
+            chunk: fast.u64("chunk").ok(),
+            paths: fast.str("path")?,
+            matched: Vec::new(),
+        })
+    }
+
+    /// Whether `doc` is a chunk document, noted under `ord` if so.
+    fn note(&mut self, doc: DocId, ord: u64) -> bool {
+        let is_chunk = self
+            .chunk
+            .as_ref()
+            .and_then(|chunk| chunk.first(doc))
+            .is_some_and(|chunk| chunk > 0);
+        if is_chunk {
+            if let Some(path) = self
+                .paths
+                .as_ref()
+                .and_then(|paths| paths.term_ords(doc).next())
+            {
+                self.matched.push((path, ord));
+            }
+        }
+        is_chunk
+    }
+
+    /// The noted files, with `resolve` turning tally ordinals into keys.
+    fn resolve<K>(&self, mut resolve: impl FnMut(u64) -> Option<K>) -> Chunked<K> {
+        let mut chunked = HashMap::new();
+        let Some(paths) = &self.paths else {
+            return chunked;
+        };
+        let mut path = String::new();
+        for &(path_ord, ord) in &self.matched {
+            path.clear();
+            if !paths.ord_to_str(path_ord, &mut path).unwrap_or(false) {
+                continue;
+            }
+            if !chunked.contains_key(&path) {
+                if let Some(key) = resolve(ord) {
+                    chunked.insert(path.clone(), key);
+                }
+            }
+        }
+        chunked
+    }
+}
+
+/// Keys of the files in `chunked` whose own document does not match
+/// `query`, so a long file matched in several places still counts once.
+fn chunk_only_files<K>(
+    searcher: &Searcher,
+    query: &dyn Query,
+    fields: &IndexFields,
+    mut chunked: Chunked<K>,
+) -> Result<Vec<K>> {
+    if chunked.is_empty() {
+        return Ok(Vec::new());
+    }
+    let paths = chunked
+        .keys()
+        .map(|path| Term::from_field_text(fields.path, path));
+    let own = BooleanQuery::new(vec![
+        (Occur::Must, query.box_clone()),
+        (Occur::Must, Box::new(TermSetQuery::new(paths))),
+        (Occur::Must, Box::new(indexer::own_documents(fields))),
+    ]);
+    let matched = searcher
+        .search(&own, &DocSetCollector)
+        .context("tantivy search execution failed")?;
+    for address in matched {
+        let doc: TantivyDocument = searcher
+            .doc(address)
+            .context("failed to fetch stored document")?;
+        if let Some(path) = doc.get_first(fields.path).and_then(|value| value.as_str()) {
+            chunked.remove(path);
+        }
+    }
+    Ok(chunked.into_values().collect())
+}
+
+/// Tallies the `ext` fast field's term ordinals per segment, then resolves
+/// them to extensions once, merging spellings that differ only in case.
+struct ExtensionCounts;
+
+struct SegmentExtensionCounts {
+    column: Option<StrColumn>,
+    by_ord: Vec<u64>,
+    chunks: SegmentChunks,
+}
+
+impl Collector for ExtensionCounts {
+    type Fruit = (HashMap<String, u64>, Chunked<String>);
+    type Child = SegmentExtensionCounts;
+
+    fn for_segment(
+        &self,
+        _segment_local_id: SegmentOrdinal,
+        segment: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        let column = segment.fast_fields().str("ext")?;
+        let terms = column.as_ref().map_or(0, |column| column.num_terms());
+        Ok(SegmentExtensionCounts {
+            column,
+            by_ord: vec![0; terms],
+            chunks: SegmentChunks::open(segment)?,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, segment_fruits: Vec<Self::Fruit>) -> tantivy::Result<Self::Fruit> {
+        let mut merged = HashMap::new();
+        let mut chunked = HashMap::new();
+        for (fruit, fruit_chunked) in segment_fruits {
+            for (ext, count) in fruit {
+                *merged.entry(ext).or_insert(0) += count;
+            }
+            chunked.extend(fruit_chunked);
+        }
+        Ok((merged, chunked))
+    }
+}
+
+impl SegmentCollector for SegmentExtensionCounts {
+    type Fruit = (HashMap<String, u64>, Chunked<String>);
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        if let Some(column) = &self.column {
+            if let Some(ord) = column.term_ords(doc).next() {
+                if !self.chunks.note(doc, ord) {
+                    self.by_ord[ord as usize] += 1;
+                }
+            }
+        }
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        let mut counts = HashMap::new();
+        let Some(column) = self.column else {
+            return (counts, HashMap::new());
+        };
+        let ext_of = |ord: u64| {
+            let mut ext = String::new();
+            (column.ord_to_str(ord, &mut ext).unwrap_or(false) && !ext.is_empty())
+                .then(|| ext.to_lowercase())
+        };
+        for (ord, count) in self.by_ord.into_iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            if let Some(ext) = ext_of(ord as u64) {
+                *counts.entry(ext).or_insert(0) += count;
+            }
+        }
+        let chunked = self.chunks.resolve(ext_of);
+        (counts, chunked)
+    }
+}
+
+/// Tallies the `folder` facet's ordinals per segment, then resolves each
+/// folder once and credits it to every requested folder it lies in.
+struct FolderCounts {
+    /// Components of each folder to count in.
+    folders: Vec<Vec<String>>,
+}
+
+struct SegmentFolderCounts {
+    reader: FacetReader,
+    by_ord: Vec<u64>,
+    folders: Vec<Vec<String>>,
+    chunks: SegmentChunks,
+}
+
+/// Per folder: matches directly inside, and per subfolder name.
+type LevelCounts = Vec<(u64, HashMap<String, u64>)>;
+
+impl FolderCounts {
+    /// Tallies for `query`, each matching file counted once.
+    fn count(
+        &self,
+        searcher: &Searcher,
+        query: &dyn Query,
+        fields: &IndexFields,
+    ) -> Result<LevelCounts> {
+        let (mut counts, chunked) = searcher
+            .search(query, self)
+            .context("tantivy search execution failed")?;
+        for components in chunk_only_files(searcher, query, fields, chunked)? {
+            let components: Vec<&str> = components.iter().map(String::as_str).collect();
+            credit(&self.folders, &components, 1, &mut counts);
+        }
+        Ok(counts)
+    }
+}
+
+impl Collector for FolderCounts {
+    type Fruit = (LevelCounts, Chunked<Vec<String>>);
+    type Child = SegmentFolderCounts;
+
+    fn for_segment(
+        &self,
+        _segment_local_id: SegmentOrdinal,
+        segment: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        let reader = segment.facet_reader("folder")?;
+        Ok(SegmentFolderCounts {
+            by_ord: vec![0; reader.num_facets()],
+            reader,
+            folders: self.folders.clone(),
+            chunks: SegmentChunks::open(segment)?,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, segment_fruits: Vec<Self::Fruit>) -> tantivy::Result<Self::Fruit> {
+        let mut merged = vec![(0, HashMap::new()); self.folders.len()];
+        let mut chunked = HashMap::new();
+        for (fruit, fruit_chunked) in segment_fruits {
+            for ((direct, below), (fruit_direct, fruit_below)) in merged.iter_mut().zip(fruit) {
+                *direct += fruit_direct;
+                for (name, count) in fruit_below {
+                    *below.entry(name).or_insert(0) += count;
+                }
+            }
+            chunked.extend(fruit_chunked);
+        }
+        Ok((merged, chunked))
+    }
+}
+
+impl SegmentCollector for SegmentFolderCounts {
+    type Fruit = (LevelCounts, Chunked<Vec<String>>);
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        if let Some(ord) = self.reader.facet_ords(doc).next() {
+            if !self.chunks.note(doc, ord) {
+                self.by_ord[ord as usize] += 1;
+            }
+        }
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        let mut counts = vec![(0, HashMap::new()); self.folders.len()];
+        let mut facet = Facet::root();
+        for (ord, count) in self.by_ord.into_iter().enumerate() {
+            if count == 0 || self.reader.facet_from_ord(ord as u64, &mut facet).is_err() {
+                continue;
+            }
+            credit(&self.folders, &facet.to_path(), count, &mut counts);
+        }
+        let chunked = self.chunks.resolve(|ord| {
+            let mut facet = Facet::root();
+            self.reader.facet_from_ord(ord, &mut facet).ok()?;
+            Some(facet.to_path().into_iter().map(str::to_string).collect())
+        });
+        (counts, chunked)
+    }
+}
+
+/// Credit `count` matches in the folder made of `components` to every
+/// requested folder it lies in.
+fn credit(folders: &[Vec<String>], components: &[&str], count: u64, counts: &mut LevelCounts) {
+    for (folder, (direct, below)) in folders.iter().zip(counts.iter_mut()) {
+        let inside =
+            folder.len() <= components.len() && folder.iter().zip(components).all(|(a, b)| a == b);
+        if !inside {
+            continue;
+        }
+        match components.get(folder.len()) {
+            Some(name) => *below.entry(name.to_string()).or_insert(0) += count,
+            None => *direct += count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        count_by_extension, count_by_extension_sampled, count_by_folder, counts_by_root,
+        ExtensionCount, FolderCount, RootCount,
+    };
+    use crate::query::{SearchDomain, SearchQuery};
+    use crate::{commit, index_root, init_index_in_memory, RootOptions};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn counts_matches_per_extension() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        init_index_in_memory().unwrap();
+        let docs = tempdir().unwrap();
+        for name in [
+            "budget.pdf",
+            "budget-2023.PDF",
+            "budget.docx",
+            "budget notes",
+            "holiday.pdf",
+        ] {
+            fs::write(docs.path().join(name), "x").unwrap();
+        }
+        index_root(docs.path(), &RootOptions::default()).unwrap();
+        commit().unwrap();
+
+        let counts = count_by_extension(SearchQuery {
+            term: "budget".into(),
+            search_in: SearchDomain::Name,
+            extensions: vec!["docx".into()],
+            ..SearchQuery::default()
+        })
+        .unwrap();
+        let chip = |ext: &str, count| ExtensionCount {
+            ext: ext.into(),
+            count,
+        };
+        assert_eq!(counts, vec![chip("pdf", 2), chip("docx", 1)]);
+        assert!(count_by_extension(SearchQuery::default())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn counts_matches_per_subfolder_at_every_level() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        init_index_in_memory().unwrap();
+        let docs = tempdir().unwrap();
+        let work = docs.path().join("work");
+        fs::create_dir_all(work.join("2023")).unwrap();
+        fs::create_dir_all(work.join("2024")).unwrap();
+        fs::create_dir_all(docs.path().join("home")).unwrap();
+        for name in [
+            "work/invoice a.pdf",
+            "work/2023/invoice b.pdf",
+            "work/2024/invoice c.pdf",
+            "work/2024/invoice d.pdf",
+            "home/invoice e.pdf",
+            "home/letter.pdf",
+        ] {
+            fs::write(docs.path().join(name), "x").unwrap();
+        }
+        index_root(docs.path(), &RootOptions::default()).unwrap();
+        commit().unwrap();
+
+        let query = SearchQuery {
+            term: "invoice".into(),
+            search_in: SearchDomain::Name,
+            ..SearchQuery::default()
+        };
+        let levels = count_by_folder(query, &work).unwrap();
+        assert_eq!(levels.len(), work.ancestors().count());
+        let below = |folder: &std::path::Path, count| FolderCount {
+            folder: folder.to_path_buf(),
+            count,
+        };
+        let top = &levels[levels.len() - 2];
+        assert_eq!(top.folder, docs.path());
+        assert_eq!(top.direct, 0);
+        assert_eq!(
+            top.subfolders,
+            vec![below(&work, 4), below(&docs.path().join("home"), 1)]
+        );
+        let inner = levels.last().unwrap();
+        assert_eq!(inner.direct, 1);
+        assert_eq!(
+            inner.subfolders,
+            vec![below(&work.join("2024"), 2), below(&work.join("2023"), 1)]
+        );
+        assert_eq!(levels[0].subfolders.iter().map(|f| f.count).sum::<u64>(), 5);
+    }
+
+    #[test]
+    fn counts_matches_below_each_root() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        init_index_in_memory().unwrap();
+        let docs = tempdir().unwrap();
+        let ssd = tempdir().unwrap();
+        fs::create_dir(docs.path().join("taxes")).unwrap();
+        for path in [
+            docs.path().join("invoice a.pdf"),
+            docs.path().join("taxes").join("invoice b.pdf"),
+            ssd.path().join("invoice c.pdf"),
+            ssd.path().join("photo.jpg"),
+        ] {
+            fs::write(path, "x").unwrap();
+        }
+        let roots = vec![
+            docs.path().to_path_buf(),
+            ssd.path().to_path_buf(),
+            docs.path().join("taxes"),
+            docs.path().join("empty"),
+        ];
+        crate::index_roots(&roots[..2], &RootOptions::default()).unwrap();
+        commit().unwrap();
+
+        let counts = counts_by_root(
+            SearchQuery {
+                term: "invoice".into(),
+                search_in: SearchDomain::Name,
+                roots: vec![ssd.path().to_path_buf()],
+                ..SearchQuery::default()
+            },
+            &roots,
+        )
+        .unwrap();
+        let counted: Vec<u64> = counts.iter().map(|count| count.count).collect();
+        assert_eq!(counted, [2, 1, 1, 0]);
+        assert_eq!(
+            counts[1],
+            RootCount {
+                root: ssd.path().to_path_buf(),
+                count: 1
+            }
+        );
+    }
+
+    #[test]
+    fn approximates_counts_from_sampled_segments() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        init_index_in_memory().unwrap();
+        // Six segments of ten files, eight pdf and two txt in each; too
+        // few segments for the merge policy to combine.
+        let docs = tempdir().unwrap();
+        for batch in 0..6 {
+            let folder = docs.path().join(format!("batch {batch}"));
+            fs::create_dir(&folder).unwrap();
+            for n in 0..10 {
+                let ext = if n % 4 == 3 { "txt" } else { "pdf" };
+                fs::write(folder.join(format!("report {n}.{ext}")), "x").unwrap();
+            }
+            index_root(docs.path(), &RootOptions::default()).unwrap();
+            commit().unwrap();
+        }
+        let query = || SearchQuery {
+            term: "report".into(),
+            search_in: SearchDomain::Name,
+            ..SearchQuery::default()
+        };
+
+        let exact = count_by_extension_sampled(query(), 1_000).unwrap();
+        assert!(!exact.approx);
+        assert_eq!(exact.counts, count_by_extension(query()).unwrap());
+
+        // A sample point every 30 documents reads two of the segments.
+        let approx = count_by_extension_sampled(query(), 2).unwrap();
+        assert!(approx.approx);
+        assert_eq!(approx.counts, exact.counts);
+    }
+}
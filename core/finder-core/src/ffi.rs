@@ -1,8 +1,48 @@
-use crate::query::{SearchDomain, SearchQuery};
-use crate::{add_or_update_file, close_index, commit, init_index, search, FileMeta, IndexUpdate};
+// The C ABI hands us raw pointers by design; each entry point null-checks
+// before dereferencing.
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use crate::events::{
+    add_index_observer, remove_index_observer, set_index_size_alert, IndexEvent, IndexObserver,
+};
+use crate::facets::{count_by_extension, count_by_extension_approx};
+use crate::query::{
+    count, search_prefix, PathPenalty, RecencyBoost, SearchDomain, SearchHit, SearchPage,
+    SearchQuery,
+};
+use crate::rewrite::{set_query_rewriter, QueryRewriter};
+use crate::standing::{add_standing_query, remove_standing_query};
+use crate::stats::quick_stats;
+use crate::summary::{set_summarizer, LeadSentences, Summarizer};
+use crate::truncate::truncate_graphemes;
+use crate::{
+    add_or_update_file, close_index, commit, default_index_dir, forget_removed, init_index,
+    init_index_in_memory, init_index_read_only, move_to_trash, refresh_if_changed, reindex_path,
+    reveal_target, search_cancellable, search_page, search_page_cancellable, CancelToken, FileMeta,
+    IndexUpdate, RootOptions, SearchSession,
+};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
 use std::ffi::{CStr, CString};
 use std::os::raw::{c_char, c_int};
+use std::path::Path;
 use std::ptr;
+use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+/// Capacity of the buffer handed to host summarizer callbacks.
+const FFI_SUMMARY_BUFFER_BYTES: usize = 512;
+
+/// Capacity of the buffer handed to host query rewriter callbacks.
+const FFI_REWRITE_BUFFER_BYTES: usize = 1024;
+
+/// How long `fc_search` waits past `timeout_ms` for the partial results of a
+/// search that ran out of time before cancelling it.
+const SEARCH_GRACE: Duration = Duration::from_millis(50);
+
+/// Async searches still running, by host request id.
+static IN_FLIGHT: Lazy<Mutex<HashMap<u64, CancelToken>>> = Lazy::new(|| Mutex::new(HashMap::new()));
 
 #[repr(C)]
 pub struct FCFileMeta {
@@ -18,9 +58,41 @@ pub struct FCFileMeta {
 #[repr(C)]
 pub struct FCQuery {
     pub q: *const c_char,
+    /// Newline-separated globs; a path matching any of them is kept. Null
+    /// or empty keeps every path.
     pub glob: *const c_char,
     pub scope: c_int,
     pub limit: c_int,
+    /// Longest `fc_search` may block, in milliseconds; 0 waits for the search
+    /// to finish however long it takes.
+    pub timeout_ms: c_int,
+    /// Longest content preview to return per hit, in characters; 0 returns
+    /// none and avoids re-reading matched files.
+    pub snippet_chars: c_int,
+    /// Only files modified at or after this unix time; 0 means no bound.
+    pub mtime_min: i64,
+    /// Only files modified at or before this unix time; 0 means no bound.
+    pub mtime_max: i64,
+    /// Only files of at least this many bytes; 0 means no bound.
+    pub size_min: u64,
+    /// Only files of at most this many bytes; 0 means no bound.
+    pub size_max: u64,
+    /// Comma-separated extensions to keep (`"md,txt"`); null or empty keeps
+    /// every extension.
+    pub exts: *const c_char,
+    /// Hits to skip, for fetching later pages.
+    pub offset: c_int,
+    /// Newline-separated globs whose paths are left out
+    /// (`"**/node_modules/**"`); null or empty excludes nothing.
+    pub exclude_globs: *const c_char,
+    /// Half-life in days of a boost for recently modified files, so they
+    /// rank above older files matching as well; 0 ranks on the text alone.
+    pub recency_half_life_days: c_int,
+    /// Newline-separated `glob=factor` lines (`"*/old/*=0.5"`) multiplying
+    /// the score of matching paths. Null applies
+    /// [`PathPenalty::defaults`], which demote backups, archives and
+    /// copies; an empty string applies none.
+    pub path_penalties: *const c_char,
 }
 
 #[repr(C)]
@@ -30,12 +102,158 @@ pub struct FCHit {
     pub mtime: i64,
     pub size: u64,
     pub score: f32,
+    /// Stored document summary, or null when none was generated.
+    pub summary: *mut c_char,
+    /// True when the hit is a Finder alias or shortcut pointing elsewhere.
+    pub is_alias: bool,
+    /// Resolved alias target, or null for ordinary files.
+    pub alias_target: *mut c_char,
+    /// Content preview as HTML with matched terms in `<b>`, or null.
+    pub snippet: *mut c_char,
 }
 
 #[repr(C)]
 pub struct FCResults {
     pub hits: *mut FCHit,
     pub count: c_int,
+    /// Set when the query's `timeout_ms` ran out; `hits` then holds what was
+    /// found in time.
+    pub timed_out: bool,
+}
+
+/// Outcome of [`fc_reindex_path`]. Release with [`fc_free_reindex_result`].
+#[repr(C)]
+pub struct FCReindexResult {
+    /// 0 added, 1 updated, 2 unchanged, -1 the file could not be indexed.
+    pub update: c_int,
+    /// True when the document was written with extracted content.
+    pub content_indexed: bool,
+    /// Why indexing or content extraction failed, or null.
+    pub error: *mut c_char,
+}
+
+/// Counters from [`fc_quick_stats`]; see [`crate::QuickStats`].
+#[repr(C)]
+pub struct FCQuickStats {
+    pub docs_added: u64,
+    pub searches: u64,
+    pub unchanged_skips: u64,
+    pub reader_reloads: u64,
+}
+
+/// Receives the results of [`fc_search_async`]. The host owns `results` and
+/// must release them with [`fc_free_results`]. Runs on a background thread.
+pub type FCSearchCallback = extern "C" fn(request_id: u64, results: FCResults);
+
+impl FCResults {
+    fn empty() -> Self {
+        FCResults {
+            hits: ptr::null_mut(),
+            count: 0,
+            timed_out: false,
+        }
+    }
+}
+
+/// Host-provided summarizer. Receives the file path and the leading UTF-8
+/// content, writes a NUL-terminated summary into `out` (at most `out_len`
+/// bytes including the terminator) and returns true, or returns false to store
+/// no summary.
+pub type FCSummarizeFn = extern "C" fn(
+    path: *const c_char,
+    lead: *const c_char,
+    out: *mut c_char,
+    out_len: usize,
+) -> bool;
+
+struct CallbackSummarizer(FCSummarizeFn);
+
+impl Summarizer for CallbackSummarizer {
+    fn summarize(&self, meta: &FileMeta, lead: &str) -> Option<String> {
+        let path = CString::new(meta.path.as_str()).ok()?;
+        let lead = CString::new(lead.replace('\0', "")).ok()?;
+        let mut out = vec![0 as c_char; FFI_SUMMARY_BUFFER_BYTES];
+        if !(self.0)(path.as_ptr(), lead.as_ptr(), out.as_mut_ptr(), out.len()) {
+            return None;
+        }
+        // Never trust the host to terminate the buffer.
+        out[FFI_SUMMARY_BUFFER_BYTES - 1] = 0;
+        let summary = unsafe { CStr::from_ptr(out.as_ptr()) }
+            .to_string_lossy()
+            .into_owned();
+        (!summary.is_empty()).then_some(summary)
+    }
+}
+
+/// Host-provided query rewriter. Receives the raw query, writes the
+/// NUL-terminated replacement into `out` (at most `out_len` bytes including
+/// the terminator) and returns true, or returns false to keep the query.
+pub type FCRewriteFn = extern "C" fn(raw: *const c_char, out: *mut c_char, out_len: usize) -> bool;
+
+struct CallbackRewriter(FCRewriteFn);
+
+impl QueryRewriter for CallbackRewriter {
+    fn rewrite(&self, raw: &str) -> Option<String> {
+        let raw = CString::new(raw.replace('\0', "")).ok()?;
+        let mut out = vec![0 as c_char; FFI_REWRITE_BUFFER_BYTES];
+        if !(self.0)(raw.as_ptr(), out.as_mut_ptr(), out.len()) {
+            return None;
+        }
+        out[FFI_REWRITE_BUFFER_BYTES - 1] = 0;
+        let rewritten = unsafe { CStr::from_ptr(out.as_ptr()) }
+            .to_string_lossy()
+            .into_owned();
+        Some(rewritten)
+    }
+}
+
+/// Host-provided index event callback. `kind` is one of the `FC_EVENT_*`
+/// constants; `value` is the commit generation, the number of files seen by
+/// a scan, the index size in bytes, or the standing query id; `message`
+/// holds the scanned roots or a standing query's new matches (one per line),
+/// or the corruption error, and is null otherwise. Both are only valid
+/// during the call, which happens on the indexing thread.
+pub type FCEventFn = extern "C" fn(kind: c_int, value: u64, message: *const c_char);
+
+pub const FC_EVENT_COMMIT: c_int = 1;
+pub const FC_EVENT_SCAN_FINISHED: c_int = 2;
+pub const FC_EVENT_SIZE_THRESHOLD: c_int = 3;
+pub const FC_EVENT_CORRUPTION: c_int = 4;
+pub const FC_EVENT_STANDING_MATCH: c_int = 5;
+
+/// Observer id of the callback installed by [`fc_set_event_callback`].
+static EVENT_OBSERVER: Lazy<Mutex<Option<u64>>> = Lazy::new(|| Mutex::new(None));
+
+struct CallbackObserver(FCEventFn);
+
+impl IndexObserver for CallbackObserver {
+    fn on_event(&self, event: &IndexEvent) {
+        let (kind, value, message) = match event {
+            IndexEvent::CommitCompleted { generation, .. } => (FC_EVENT_COMMIT, *generation, None),
+            IndexEvent::ScanFinished { roots, report } => (
+                FC_EVENT_SCAN_FINISHED,
+                report.files_seen as u64,
+                Some(roots.join("\n")),
+            ),
+            IndexEvent::SizeThresholdCrossed { index_bytes, .. } => {
+                (FC_EVENT_SIZE_THRESHOLD, *index_bytes, None)
+            }
+            IndexEvent::CorruptionDetected { message } => {
+                (FC_EVENT_CORRUPTION, 0, Some(message.clone()))
+            }
+            IndexEvent::StandingQueryMatched { id, paths, .. } => {
+                (FC_EVENT_STANDING_MATCH, *id, Some(paths.join("\n")))
+            }
+        };
+        let message = message.and_then(|message| CString::new(message.replace('\0', "")).ok());
+        (self.0)(
+            kind,
+            value,
+            message
+                .as_ref()
+                .map_or(ptr::null(), |message| message.as_ptr()),
+        );
+    }
 }
 
 #[no_mangle]
@@ -54,11 +272,138 @@ pub extern "C" fn fc_init_index(path: *const c_char) -> bool {
     }
 }
 
+/// Per-user index directory for this platform, created (and a legacy
+/// `/tmp/finder-index` moved there) if needed. Null on failure; release the
+/// string with `fc_free_string`.
+#[no_mangle]
+pub extern "C" fn fc_default_index_dir() -> *mut c_char {
+    match default_index_dir() {
+        Ok(dir) => CString::new(dir.to_string_lossy().into_owned())
+            .map(CString::into_raw)
+            .unwrap_or(ptr::null_mut()),
+        Err(err) => {
+            eprintln!("[ffi] default_index_dir failed: {err:#}");
+            ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn fc_free_string(string: *mut c_char) {
+    if !string.is_null() {
+        unsafe {
+            drop(CString::from_raw(string));
+        }
+    }
+}
+
+/// Open an ephemeral RAM-backed index; everything else works as with
+/// `fc_init_index`, but nothing is persisted.
+#[no_mangle]
+pub extern "C" fn fc_init_index_in_memory() -> bool {
+    match init_index_in_memory() {
+        Ok(()) => true,
+        Err(err) => {
+            eprintln!("[ffi] init_index_in_memory failed: {err}");
+            false
+        }
+    }
+}
+
+/// Open an existing index for searching while another process owns the
+/// writer. Add/update and commit calls fail on this handle.
+#[no_mangle]
+pub extern "C" fn fc_init_index_read_only(path: *const c_char) -> bool {
+    let Some(path_str) = to_string(path) else {
+        eprintln!("[ffi] fc_init_index_read_only called with null path");
+        return false;
+    };
+
+    match init_index_read_only(&path_str) {
+        Ok(()) => true,
+        Err(err) => {
+            eprintln!("[ffi] init_index_read_only failed: {err}");
+            false
+        }
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn fc_close_index() {
     close_index();
 }
 
+/// Route summaries through a host callback; pass null to disable summaries.
+#[no_mangle]
+pub extern "C" fn fc_set_summarizer(callback: Option<FCSummarizeFn>) {
+    set_summarizer(
+        callback.map(|callback| Arc::new(CallbackSummarizer(callback)) as Arc<dyn Summarizer>),
+    );
+}
+
+/// Install a callback that rewrites every query string before it is
+/// parsed; null removes it.
+#[no_mangle]
+pub extern "C" fn fc_set_query_rewriter(callback: Option<FCRewriteFn>) {
+    set_query_rewriter(
+        callback.map(|callback| Arc::new(CallbackRewriter(callback)) as Arc<dyn QueryRewriter>),
+    );
+}
+
+/// Install the callback told about commits, finished scans, size alerts
+/// and corruption, replacing any previous one; null removes it.
+#[no_mangle]
+pub extern "C" fn fc_set_event_callback(callback: Option<FCEventFn>) {
+    let mut installed = EVENT_OBSERVER.lock().unwrap();
+    if let Some(id) = installed.take() {
+        remove_index_observer(id);
+    }
+    *installed = callback.map(|callback| add_index_observer(Arc::new(CallbackObserver(callback))));
+}
+
+/// Report new matches of `query` as `FC_EVENT_STANDING_MATCH` events after
+/// each commit. Returns the id carried by those events, or 0 on failure.
+#[no_mangle]
+pub extern "C" fn fc_add_standing_query(name: *const c_char, query: *const FCQuery) -> u64 {
+    let Some(query_ref) = (unsafe { query.as_ref() }) else {
+        eprintln!("[ffi] fc_add_standing_query received null query pointer");
+        return 0;
+    };
+    let name = to_string(name).unwrap_or_default();
+    match add_standing_query(name, search_query_from_ffi(query_ref)) {
+        Ok(id) => id,
+        Err(err) => {
+            eprintln!("[ffi] add_standing_query failed: {err:#}");
+            0
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn fc_remove_standing_query(id: u64) -> bool {
+    remove_standing_query(id)
+}
+
+/// Fire `FC_EVENT_SIZE_THRESHOLD` once a commit leaves the index larger
+/// than `threshold_bytes`; 0 turns the alert off.
+#[no_mangle]
+pub extern "C" fn fc_set_index_size_alert(threshold_bytes: u64) {
+    set_index_size_alert(Some(threshold_bytes).filter(|bytes| *bytes > 0));
+}
+
+/// Use the built-in first-N-sentences summarizer; `max_sentences <= 0` disables summaries.
+#[no_mangle]
+pub extern "C" fn fc_set_builtin_summarizer(max_sentences: c_int) {
+    if max_sentences <= 0 {
+        set_summarizer(None);
+        return;
+    }
+    set_summarizer(Some(Arc::new(LeadSentences {
+        sentences: max_sentences as usize,
+        ..LeadSentences::default()
+    })));
+}
+
 #[no_mangle]
 pub extern "C" fn fc_add_or_update(meta: *const FCFileMeta, content: *const c_char) -> bool {
     let Some(file_meta) = file_meta_from_ffi(meta) else {
@@ -76,6 +421,108 @@ pub extern "C" fn fc_add_or_update(meta: *const FCFileMeta, content: *const c_ch
     }
 }
 
+/// Stat, extract and rewrite one file right away, bypassing dedup. The
+/// change becomes searchable after `fc_commit_and_refresh`.
+#[no_mangle]
+pub extern "C" fn fc_reindex_path(path: *const c_char) -> FCReindexResult {
+    let error_result = |message: String| FCReindexResult {
+        update: -1,
+        content_indexed: false,
+        error: CString::new(message.replace('\0', ""))
+            .map(CString::into_raw)
+            .unwrap_or(ptr::null_mut()),
+    };
+    let Some(path) = to_string(path) else {
+        return error_result("null path".into());
+    };
+
+    match reindex_path(&path, &RootOptions::default()) {
+        Ok(outcome) => FCReindexResult {
+            update: match outcome.update {
+                IndexUpdate::Added => 0,
+                IndexUpdate::Updated => 1,
+                IndexUpdate::Skipped => 2,
+            },
+            content_indexed: outcome.content_indexed,
+            error: outcome
+                .error
+                .and_then(|error| CString::new(error).ok())
+                .map(CString::into_raw)
+                .unwrap_or(ptr::null_mut()),
+        },
+        Err(err) => {
+            eprintln!("[ffi] reindex_path failed: {err:#}");
+            error_result(format!("{err:#}"))
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn fc_free_reindex_result(result: *mut FCReindexResult) {
+    let Some(result) = (unsafe { result.as_mut() }) else {
+        return;
+    };
+    if !result.error.is_null() {
+        unsafe {
+            drop(CString::from_raw(result.error));
+        }
+        result.error = ptr::null_mut();
+    }
+}
+
+/// Move a result to the trash and drop it from the index. Fails in a
+/// sandboxed macOS app, which trashes with `FileManager.trashItem` and
+/// calls [`fc_forget_removed`] instead.
+#[no_mangle]
+pub extern "C" fn fc_move_to_trash(path: *const c_char) -> bool {
+    let Some(path) = to_string(path) else {
+        eprintln!("[ffi] fc_move_to_trash called with null path");
+        return false;
+    };
+    match move_to_trash(Path::new(&path)) {
+        Ok(_) => true,
+        Err(err) => {
+            eprintln!("[ffi] move_to_trash failed: {err:#}");
+            false
+        }
+    }
+}
+
+/// Drop a result the host removed from disk (e.g. trashed) from the
+/// index. Returns the files dropped, or -1 when the path still exists or
+/// the index couldn't be updated.
+#[no_mangle]
+pub extern "C" fn fc_forget_removed(path: *const c_char) -> i64 {
+    let Some(path) = to_string(path) else {
+        eprintln!("[ffi] fc_forget_removed called with null path");
+        return -1;
+    };
+    match forget_removed(Path::new(&path)) {
+        Ok(removed) => removed as i64,
+        Err(err) => {
+            eprintln!("[ffi] forget_removed failed: {err:#}");
+            -1
+        }
+    }
+}
+
+/// Show a result (or an alias's target) in the file manager; a result that
+/// vanished is dropped from the index and false is returned.
+#[no_mangle]
+pub extern "C" fn fc_reveal_target(path: *const c_char) -> bool {
+    let Some(path) = to_string(path) else {
+        eprintln!("[ffi] fc_reveal_target called with null path");
+        return false;
+    };
+    match reveal_target(Path::new(&path)) {
+        Ok(_) => true,
+        Err(err) => {
+            eprintln!("[ffi] reveal_target failed: {err:#}");
+            false
+        }
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn fc_should_reindex(meta: *const FCFileMeta) -> bool {
     let Some(file_meta) = file_meta_from_ffi(meta) else {
@@ -103,53 +550,372 @@ pub extern "C" fn fc_commit_and_refresh() -> bool {
     }
 }
 
+/// Returns true when the writer process committed and the reader reloaded.
+#[no_mangle]
+pub extern "C" fn fc_refresh_if_changed() -> bool {
+    match refresh_if_changed() {
+        Ok(reloaded) => reloaded,
+        Err(err) => {
+            eprintln!("[ffi] refresh_if_changed failed: {err}");
+            false
+        }
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn fc_search(query: *const FCQuery) -> FCResults {
     let Some(query_ref) = (unsafe { query.as_ref() }) else {
         eprintln!("[ffi] fc_search received null query pointer");
-        return FCResults {
-            hits: ptr::null_mut(),
-            count: 0,
-        };
+        return FCResults::empty();
     };
 
-    let term = to_string(query_ref.q).unwrap_or_default();
-    let glob = to_string(query_ref.glob);
-    let scope = match query_ref.scope {
+    let search_query = search_query_from_ffi(query_ref);
+    let budget =
+        (query_ref.timeout_ms > 0).then(|| Duration::from_millis(query_ref.timeout_ms as u64));
+    match search_within(search_query, budget) {
+        Some(Ok(page)) => FCResults {
+            timed_out: page.timed_out,
+            ..results_from_hits(page.hits)
+        },
+        Some(Err(err)) => {
+            eprintln!("[ffi] search failed: {err}");
+            FCResults::empty()
+        }
+        None => {
+            eprintln!(
+                "[ffi] search exceeded its {}ms budget",
+                query_ref.timeout_ms
+            );
+            FCResults {
+                timed_out: true,
+                ..FCResults::empty()
+            }
+        }
+    }
+}
+
+/// `text` cut to at most `max_graphemes` characters with a trailing `…`,
+/// never splitting a character or emoji. Free with [`fc_free_string`];
+/// null when `text` is null.
+#[no_mangle]
+pub extern "C" fn fc_truncate(text: *const c_char, max_graphemes: c_int) -> *mut c_char {
+    let Some(text) = to_string(text) else {
+        return ptr::null_mut();
+    };
+    CString::new(truncate_graphemes(&text, max_graphemes.max(0) as usize))
+        .map(CString::into_raw)
+        .unwrap_or(ptr::null_mut())
+}
+
+/// Number of documents matching `query` without fetching them, or -1 on
+/// failure. Paging fields are ignored.
+#[no_mangle]
+pub extern "C" fn fc_count(query: *const FCQuery) -> i64 {
+    let Some(query_ref) = (unsafe { query.as_ref() }) else {
+        eprintln!("[ffi] fc_count received null query pointer");
+        return -1;
+    };
+    match count(search_query_from_ffi(query_ref)) {
+        Ok(total) => total as i64,
+        Err(err) => {
+            eprintln!("[ffi] count failed: {err:#}");
+            -1
+        }
+    }
+}
+
+/// Activity counters since the index was opened; lock-free, so cheap to
+/// poll every second.
+#[no_mangle]
+pub extern "C" fn fc_quick_stats() -> FCQuickStats {
+    let stats = quick_stats();
+    FCQuickStats {
+        docs_added: stats.docs_added,
+        searches: stats.searches,
+        unchanged_skips: stats.unchanged_skips,
+        reader_reloads: stats.reader_reloads,
+    }
+}
+
+/// Matching documents per extension as `ext<TAB>count` lines, most common
+/// first; see [`count_by_extension`]. Free with [`fc_free_string`]; null on
+/// failure.
+#[no_mangle]
+pub extern "C" fn fc_count_by_extension(query: *const FCQuery) -> *mut c_char {
+    let Some(query_ref) = (unsafe { query.as_ref() }) else {
+        eprintln!("[ffi] fc_count_by_extension received null query pointer");
+        return ptr::null_mut();
+    };
+    match count_by_extension(search_query_from_ffi(query_ref)) {
+        Ok(counts) => {
+            let lines: String = counts
+                .iter()
+                .map(|chip| format!("{}\t{}\n", chip.ext, chip.count))
+                .collect();
+            CString::new(lines)
+                .map(CString::into_raw)
+                .unwrap_or(ptr::null_mut())
+        }
+        Err(err) => {
+            eprintln!("[ffi] count_by_extension failed: {err:#}");
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Like [`fc_count_by_extension`], but estimated on large indexes; see
+/// [`count_by_extension_approx`]. Estimated counts are written `~count`.
+#[no_mangle]
+pub extern "C" fn fc_count_by_extension_approx(query: *const FCQuery) -> *mut c_char {
+    let Some(query_ref) = (unsafe { query.as_ref() }) else {
+        eprintln!("[ffi] fc_count_by_extension_approx received null query pointer");
+        return ptr::null_mut();
+    };
+    match count_by_extension_approx(search_query_from_ffi(query_ref)) {
+        Ok(facets) => {
+            let mark = if facets.approx { "~" } else { "" };
+            let lines: String = facets
+                .counts
+                .iter()
+                .map(|chip| format!("{}\t{mark}{}\n", chip.ext, chip.count))
+                .collect();
+            CString::new(lines)
+                .map(CString::into_raw)
+                .unwrap_or(ptr::null_mut())
+        }
+        Err(err) => {
+            eprintln!("[ffi] count_by_extension_approx failed: {err:#}");
+            ptr::null_mut()
+        }
+    }
+}
+
+/// As-you-type lookup of file names by word prefixes; see
+/// [`search_prefix`].
+#[no_mangle]
+pub extern "C" fn fc_search_prefix(typed: *const c_char, limit: c_int) -> FCResults {
+    let Some(typed) = to_string(typed) else {
+        eprintln!("[ffi] fc_search_prefix called with null text");
+        return FCResults::empty();
+    };
+    match search_prefix(&typed, limit.max(1) as usize) {
+        Ok(hits) => results_from_hits(hits),
+        Err(err) => {
+            eprintln!("[ffi] search_prefix failed: {err:#}");
+            FCResults::empty()
+        }
+    }
+}
+
+/// Start a search on a background thread and report through `callback`.
+/// `request_id` is chosen by the host and must increase with each request,
+/// e.g. a keystroke generation: starting a search cancels every one still
+/// running with the same or a lower id, whose callbacks then aren't called,
+/// so only the latest keystroke's results arrive. `timeout_ms` is ignored
+/// here, since nothing blocks. Returns false when the query is null or no
+/// thread could be started.
+#[no_mangle]
+pub extern "C" fn fc_search_async(
+    query: *const FCQuery,
+    request_id: u64,
+    callback: Option<FCSearchCallback>,
+) -> bool {
+    let (Some(query_ref), Some(callback)) = (unsafe { query.as_ref() }, callback) else {
+        eprintln!("[ffi] fc_search_async received a null query or callback");
+        return false;
+    };
+    let search_query = search_query_from_ffi(query_ref);
+    let token = CancelToken::new();
+    {
+        let mut in_flight = in_flight();
+        in_flight.retain(|id, previous| {
+            let superseded = *id <= request_id;
+            if superseded {
+                previous.cancel();
+            }
+            !superseded
+        });
+        in_flight.insert(request_id, token.clone());
+    }
+
+    let worker_token = token.clone();
+    let spawned = thread::Builder::new()
+        .name("fc-search-async".into())
+        .spawn(move || {
+            let outcome = search_cancellable(search_query, &worker_token);
+            {
+                let mut in_flight = in_flight();
+                if in_flight
+                    .get(&request_id)
+                    .is_some_and(|current| current.same_as(&worker_token))
+                {
+                    in_flight.remove(&request_id);
+                }
+            }
+            let results = match outcome {
+                Ok(Some(hits)) => results_from_hits(hits),
+                Ok(None) => return,
+                Err(err) => {
+                    eprintln!("[ffi] async search {request_id} failed: {err}");
+                    FCResults::empty()
+                }
+            };
+            if worker_token.is_cancelled() {
+                let mut results = results;
+                fc_free_results(&mut results);
+                return;
+            }
+            callback(request_id, results);
+        });
+    if let Err(err) = spawned {
+        eprintln!("[ffi] failed to start async search: {err}");
+        in_flight().remove(&request_id);
+        return false;
+    }
+    true
+}
+
+/// Cancel an async search. Its callback will not be called (unless it was
+/// already running). Returns false when no search with that id is in flight.
+#[no_mangle]
+pub extern "C" fn fc_cancel_search(request_id: u64) -> bool {
+    match in_flight().remove(&request_id) {
+        Some(token) => {
+            token.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Opaque handle to a pinned [`SearchSession`].
+pub struct FCSession(SearchSession);
+
+/// Pin the current index snapshot so paged searches stay consistent across
+/// commits. Null on failure; release with `fc_session_release`.
+#[no_mangle]
+pub extern "C" fn fc_session_pin() -> *mut FCSession {
+    match SearchSession::pin() {
+        Ok(session) => Box::into_raw(Box::new(FCSession(session))),
+        Err(err) => {
+            eprintln!("[ffi] session pin failed: {err}");
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Search within a pinned session. `timeout_ms` is ignored.
+#[no_mangle]
+pub extern "C" fn fc_session_search(session: *const FCSession, query: *const FCQuery) -> FCResults {
+    let (Some(session), Some(query_ref)) = (unsafe { session.as_ref() }, unsafe { query.as_ref() })
+    else {
+        eprintln!("[ffi] fc_session_search received a null session or query");
+        return FCResults::empty();
+    };
+    match session.0.search(search_query_from_ffi(query_ref)) {
+        Ok(hits) => results_from_hits(hits),
+        Err(err) => {
+            eprintln!("[ffi] session search failed: {err}");
+            FCResults::empty()
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn fc_session_release(session: *mut FCSession) {
+    if !session.is_null() {
+        drop(unsafe { Box::from_raw(session) });
+    }
+}
+
+fn in_flight() -> MutexGuard<'static, HashMap<u64, CancelToken>> {
+    IN_FLIGHT.lock().expect("in-flight search map poisoned")
+}
+
+fn search_query_from_ffi(query: &FCQuery) -> SearchQuery {
+    let term = to_string(query.q).unwrap_or_default();
+    let scope = match query.scope {
         0 => SearchDomain::Name,
         1 => SearchDomain::Content,
         2 => SearchDomain::Both,
         _ => SearchDomain::Both,
     };
-    let limit = if query_ref.limit <= 0 {
+    let limit = if query.limit <= 0 {
         50
     } else {
-        query_ref.limit as usize
+        query.limit as usize
     };
 
-    let search_query = SearchQuery {
+    SearchQuery {
         term,
         search_in: scope,
-        path_glob: glob,
+        path_globs: glob_lines(query.glob),
         limit,
-    };
+        offset: query.offset.max(0) as usize,
+        snippet_chars: (query.snippet_chars > 0).then_some(query.snippet_chars as usize),
+        mtime_min: (query.mtime_min != 0).then_some(query.mtime_min),
+        mtime_max: (query.mtime_max != 0).then_some(query.mtime_max),
+        size_min: (query.size_min != 0).then_some(query.size_min),
+        size_max: (query.size_max != 0).then_some(query.size_max),
+        extensions: to_string(query.exts)
+            .map(|exts| {
+                exts.split(',')
+                    .map(str::trim)
+                    .filter(|ext| !ext.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        exclude_globs: glob_lines(query.exclude_globs),
+        recency: (query.recency_half_life_days > 0).then(|| RecencyBoost {
+            half_life_days: query.recency_half_life_days as f32,
+            ..RecencyBoost::default()
+        }),
+        path_penalties: penalty_lines(query.path_penalties),
+        ..SearchQuery::default()
+    }
+}
 
-    let hits = match search(search_query) {
-        Ok(results) => results,
-        Err(err) => {
-            eprintln!("[ffi] search failed: {err}");
-            return FCResults {
-                hits: ptr::null_mut(),
-                count: 0,
-            };
-        }
-    };
+/// Non-blank lines of a nullable C string, one glob per line.
+fn glob_lines(ptr: *const c_char) -> Vec<String> {
+    to_string(ptr)
+        .map(|globs| {
+            globs
+                .lines()
+                .map(str::trim)
+                .filter(|glob| !glob.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
 
+/// `glob=factor` lines of a nullable C string; null means the defaults.
+fn penalty_lines(ptr: *const c_char) -> Vec<PathPenalty> {
+    if ptr.is_null() {
+        return PathPenalty::defaults();
+    }
+    to_string(ptr)
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .filter_map(|line| {
+            let parsed = line.rsplit_once('=').and_then(|(pattern, factor)| {
+                let factor = factor.trim().parse::<f32>().ok()?;
+                Some(PathPenalty::new(pattern.trim(), factor))
+            });
+            if parsed.is_none() {
+                eprintln!("[ffi] ignoring malformed path penalty {line:?}");
+            }
+            parsed
+        })
+        .collect()
+}
+
+fn results_from_hits(hits: Vec<SearchHit>) -> FCResults {
     if hits.is_empty() {
-        return FCResults {
-            hits: ptr::null_mut(),
-            count: 0,
-        };
+        return FCResults::empty();
     }
 
     let mut ffi_hits = Vec::with_capacity(hits.len());
@@ -159,12 +925,29 @@ pub extern "C" fn fc_search(query: *const FCQuery) -> FCResults {
             (Ok(path_cstr), Ok(name_cstr)) => {
                 let path_ptr = path_cstr.into_raw();
                 let name_ptr = name_cstr.into_raw();
+                let summary_ptr = hit
+                    .summary
+                    .and_then(|summary| CString::new(summary).ok())
+                    .map_or(ptr::null_mut(), CString::into_raw);
+                let is_alias = hit.alias_target.is_some();
+                let alias_target_ptr = hit
+                    .alias_target
+                    .and_then(|target| CString::new(target).ok())
+                    .map_or(ptr::null_mut(), CString::into_raw);
+                let snippet_ptr = hit
+                    .snippet
+                    .and_then(|snippet| CString::new(snippet.to_html()).ok())
+                    .map_or(ptr::null_mut(), CString::into_raw);
                 ffi_hits.push(FCHit {
                     path: path_ptr,
                     name: name_ptr,
                     mtime: hit.modified_at.unwrap_or(0),
                     size: hit.size.unwrap_or(0),
                     score: hit.score,
+                    summary: summary_ptr,
+                    is_alias,
+                    alias_target: alias_target_ptr,
+                    snippet: snippet_ptr,
                 });
             }
             _ => {
@@ -174,10 +957,7 @@ pub extern "C" fn fc_search(query: *const FCQuery) -> FCResults {
     }
 
     if ffi_hits.is_empty() {
-        return FCResults {
-            hits: ptr::null_mut(),
-            count: 0,
-        };
+        return FCResults::empty();
     }
 
     let mut boxed = ffi_hits.into_boxed_slice();
@@ -185,7 +965,52 @@ pub extern "C" fn fc_search(query: *const FCQuery) -> FCResults {
     let ptr = boxed.as_mut_ptr();
     std::mem::forget(boxed);
 
-    FCResults { hits: ptr, count }
+    FCResults {
+        hits: ptr,
+        count,
+        timed_out: false,
+    }
+}
+
+/// Run `query` with `budget` as its time budget, so it comes back in time
+/// with what it found so far. The search moves to a worker thread so one
+/// stuck before it can check the clock (e.g. waiting on a reload) can't
+/// hold the calling (often UI) thread; if no page arrives shortly after the
+/// budget, the worker is cancelled and `None` is returned.
+fn search_within(
+    query: SearchQuery,
+    budget: Option<Duration>,
+) -> Option<anyhow::Result<SearchPage>> {
+    let Some(budget) = budget else {
+        return Some(search_page(query));
+    };
+    let query = SearchQuery {
+        time_budget: Some(budget),
+        ..query
+    };
+    let cancel = CancelToken::new();
+    let worker_cancel = cancel.clone();
+    let (sender, receiver) = mpsc::sync_channel(1);
+    let spawned = thread::Builder::new()
+        .name("fc-search".into())
+        .spawn(move || {
+            let _ = sender.send(search_page_cancellable(query, &worker_cancel));
+        });
+    if let Err(err) = spawned {
+        return Some(Err(err.into()));
+    }
+    match receiver.recv_timeout(budget + SEARCH_GRACE) {
+        Ok(Ok(Some(page))) => Some(Ok(page)),
+        Ok(Ok(None)) => None,
+        Ok(Err(err)) => Some(Err(err)),
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+            cancel.cancel();
+            None
+        }
+        Err(mpsc::RecvTimeoutError::Disconnected) => Some(Err(anyhow::anyhow!(
+            "search worker exited without a result"
+        ))),
+    }
 }
 
 #[no_mangle]
@@ -225,6 +1050,21 @@ pub extern "C" fn fc_free_results(results: *mut FCResults) {
                 drop(CString::from_raw(hit.name));
             }
         }
+        if !hit.summary.is_null() {
+            unsafe {
+                drop(CString::from_raw(hit.summary));
+            }
+        }
+        if !hit.alias_target.is_null() {
+            unsafe {
+                drop(CString::from_raw(hit.alias_target));
+            }
+        }
+        if !hit.snippet.is_null() {
+            unsafe {
+                drop(CString::from_raw(hit.snippet));
+            }
+        }
     }
 }
 
@@ -251,6 +1091,10 @@ fn file_meta_from_ffi(meta: *const FCFileMeta) -> Option<FileMeta> {
         size: meta_ref.size,
         inode: meta_ref.inode,
         dev: meta_ref.dev,
+        alias_target: None,
+        bundle: false,
+        display_name: None,
+        children: None,
     })
 }
 
@@ -262,6 +1106,21 @@ mod tests {
     use std::fs;
     use tempfile::tempdir;
 
+    #[test]
+    fn path_penalties_default_unless_the_host_sets_them() {
+        assert_eq!(penalty_lines(ptr::null()), PathPenalty::defaults());
+        let none = CString::new("").unwrap();
+        assert!(penalty_lines(none.as_ptr()).is_empty());
+        let custom = CString::new("*/old/* = 0.5\nno factor\n*=x=0.25").unwrap();
+        assert_eq!(
+            penalty_lines(custom.as_ptr()),
+            vec![
+                PathPenalty::new("*/old/*", 0.5),
+                PathPenalty::new("*=x", 0.25)
+            ]
+        );
+    }
+
     #[test]
     fn ffi_roundtrip_search() {
         let _guard = TEST_MUTEX.lock().unwrap();
@@ -297,6 +1156,17 @@ mod tests {
             glob: std::ptr::null(),
             scope: 2,
             limit: 10,
+            timeout_ms: 0,
+            snippet_chars: 40,
+            mtime_min: 0,
+            mtime_max: 0,
+            size_min: 0,
+            size_max: 0,
+            exts: ptr::null(),
+            offset: 0,
+            exclude_globs: std::ptr::null(),
+            recency_half_life_days: 0,
+            path_penalties: ptr::null(),
         };
 
         let mut results = fc_search(&query as *const _);
@@ -308,8 +1178,221 @@ mod tests {
         let hit_name = unsafe { CStr::from_ptr(first.name) }.to_str().unwrap();
         assert_eq!(hit_path, file_path.to_str().unwrap());
         assert_eq!(hit_name, "hello.txt");
+        assert!(first.summary.is_null());
+        let snippet = unsafe { CStr::from_ptr(first.snippet) }.to_str().unwrap();
+        assert_eq!(snippet, "<b>hello</b> world");
 
         fc_free_results(&mut results as *mut _);
         fc_close_index();
     }
+
+    extern "C" fn shouting_summarizer(
+        _path: *const c_char,
+        lead: *const c_char,
+        out: *mut c_char,
+        out_len: usize,
+    ) -> bool {
+        let lead = unsafe { CStr::from_ptr(lead) }
+            .to_string_lossy()
+            .to_uppercase();
+        let bytes = lead.as_bytes();
+        let len = bytes.len().min(out_len - 1);
+        unsafe {
+            ptr::copy_nonoverlapping(bytes.as_ptr() as *const c_char, out, len);
+            *out.add(len) = 0;
+        }
+        true
+    }
+
+    #[test]
+    fn ffi_summarizer_callback_populates_hits() {
+        let _guard = TEST_MUTEX.lock().unwrap();
+
+        let dir = tempdir().unwrap();
+        let index_dir = CString::new(dir.path().to_str().unwrap()).unwrap();
+        assert!(fc_init_index(index_dir.as_ptr()));
+        fc_set_summarizer(Some(shouting_summarizer));
+
+        let path_c = CString::new(dir.path().join("memo.txt").to_str().unwrap()).unwrap();
+        let name_c = CString::new("memo.txt").unwrap();
+        let content_c = CString::new("quiet memo").unwrap();
+        let meta = FCFileMeta {
+            path: path_c.as_ptr(),
+            name: name_c.as_ptr(),
+            ext: std::ptr::null(),
+            mtime: 0,
+            size: 10,
+            inode: 0,
+            dev: 0,
+        };
+        let added = fc_add_or_update(&meta, content_c.as_ptr());
+        fc_set_summarizer(None);
+        assert!(added);
+        assert!(fc_commit_and_refresh());
+
+        let query_c = CString::new("memo").unwrap();
+        let query = FCQuery {
+            q: query_c.as_ptr(),
+            glob: std::ptr::null(),
+            scope: 2,
+            limit: 10,
+            timeout_ms: 0,
+            snippet_chars: 0,
+            mtime_min: 0,
+            mtime_max: 0,
+            size_min: 0,
+            size_max: 0,
+            exts: ptr::null(),
+            offset: 0,
+            exclude_globs: std::ptr::null(),
+            recency_half_life_days: 0,
+            path_penalties: ptr::null(),
+        };
+        let mut results = fc_search(&query as *const _);
+        assert_eq!(results.count, 1);
+        let hit = unsafe { &*results.hits };
+        let summary = unsafe { CStr::from_ptr(hit.summary) }.to_str().unwrap();
+        assert_eq!(summary, "QUIET MEMO");
+
+        fc_free_results(&mut results as *mut _);
+        fc_close_index();
+    }
+
+    #[test]
+    fn ffi_search_returns_when_the_budget_runs_out() {
+        let _guard = TEST_MUTEX.lock().unwrap();
+        assert!(fc_init_index_in_memory());
+
+        let query_c = CString::new("anything").unwrap();
+        let mut query = FCQuery {
+            q: query_c.as_ptr(),
+            glob: std::ptr::null(),
+            scope: 2,
+            limit: 10,
+            timeout_ms: 50,
+            snippet_chars: 0,
+            mtime_min: 0,
+            mtime_max: 0,
+            size_min: 0,
+            size_max: 0,
+            exts: ptr::null(),
+            offset: 0,
+            exclude_globs: std::ptr::null(),
+            recency_half_life_days: 0,
+            path_penalties: ptr::null(),
+        };
+        let stall = crate::indexer::stall_index_for_test();
+        let started = std::time::Instant::now();
+        let mut results = fc_search(&query as *const _);
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+        assert!(results.timed_out);
+        assert_eq!(results.count, 0);
+        fc_free_results(&mut results as *mut _);
+        drop(stall);
+        // The abandoned worker was cancelled, so it never completes a search.
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(quick_stats().searches, 0);
+
+        query.timeout_ms = 5_000;
+        let results = fc_search(&query as *const _);
+        assert!(!results.timed_out);
+        assert_eq!(quick_stats().searches, 1);
+        fc_close_index();
+    }
+
+    static ASYNC_DONE: Mutex<Vec<(u64, c_int)>> = Mutex::new(Vec::new());
+
+    extern "C" fn record_async(request_id: u64, mut results: FCResults) {
+        ASYNC_DONE.lock().unwrap().push((request_id, results.count));
+        fc_free_results(&mut results);
+    }
+
+    #[test]
+    fn ffi_async_search_skips_cancelled_and_superseded_requests() {
+        let _guard = TEST_MUTEX.lock().unwrap();
+        assert!(fc_init_index_in_memory());
+        let path_c = CString::new("/tmp/ledger.txt").unwrap();
+        let name_c = CString::new("ledger.txt").unwrap();
+        let meta = FCFileMeta {
+            path: path_c.as_ptr(),
+            name: name_c.as_ptr(),
+            ext: std::ptr::null(),
+            mtime: 0,
+            size: 0,
+            inode: 0,
+            dev: 0,
+        };
+        assert!(fc_add_or_update(&meta, std::ptr::null()));
+        assert!(fc_commit_and_refresh());
+
+        let query_c = CString::new("ledger").unwrap();
+        let query = FCQuery {
+            q: query_c.as_ptr(),
+            glob: std::ptr::null(),
+            scope: 0,
+            limit: 10,
+            timeout_ms: 0,
+            snippet_chars: 0,
+            mtime_min: 0,
+            mtime_max: 0,
+            size_min: 0,
+            size_max: 0,
+            exts: ptr::null(),
+            offset: 0,
+            exclude_globs: std::ptr::null(),
+            recency_half_life_days: 0,
+            path_penalties: ptr::null(),
+        };
+        let stall = crate::indexer::stall_index_for_test();
+        assert!(fc_search_async(&query, 1, Some(record_async)));
+        assert!(fc_cancel_search(1));
+        assert!(!fc_cancel_search(1));
+        assert!(fc_search_async(&query, 2, Some(record_async)));
+        assert!(fc_search_async(&query, 3, Some(record_async)));
+        assert!(fc_search_async(&query, 5, Some(record_async)));
+        // Superseded by 5, so no longer in flight.
+        assert!(!fc_cancel_search(2));
+        assert!(!fc_cancel_search(3));
+        drop(stall);
+
+        let started = std::time::Instant::now();
+        while !ASYNC_DONE.lock().unwrap().iter().any(|(id, _)| *id == 5) {
+            assert!(started.elapsed() < std::time::Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(5));
+        }
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(*ASYNC_DONE.lock().unwrap(), vec![(5, 1)]);
+        fc_close_index();
+    }
+
+    #[test]
+    fn reindex_path_rewrites_one_file_and_reports_errors() {
+        let _guard = TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        let index_dir = CString::new(dir.path().join("index").to_str().unwrap()).unwrap();
+        assert!(fc_init_index(index_dir.as_ptr()));
+
+        let file_path = dir.path().join("ledger.txt");
+        fs::write(&file_path, "opening balance").unwrap();
+        let path_c = CString::new(file_path.to_str().unwrap()).unwrap();
+
+        let mut first = fc_reindex_path(path_c.as_ptr());
+        assert_eq!((first.update, first.content_indexed), (0, true));
+        assert!(first.error.is_null());
+        fc_free_reindex_result(&mut first);
+
+        let mut again = fc_reindex_path(path_c.as_ptr());
+        assert_eq!(again.update, 1);
+        fc_free_reindex_result(&mut again);
+
+        let missing_c = CString::new(dir.path().join("gone.txt").to_str().unwrap()).unwrap();
+        let mut missing = fc_reindex_path(missing_c.as_ptr());
+        assert_eq!(missing.update, -1);
+        let message = unsafe { CStr::from_ptr(missing.error) }
+            .to_string_lossy()
+            .into_owned();
+        assert!(message.contains("gone.txt"), "{message}");
+        fc_free_reindex_result(&mut missing);
+        assert!(missing.error.is_null());
+    }
 }
@@ -0,0 +1,95 @@
+use crate::scanner::FileMeta;
+
+const DEFAULT_MAX_AVG_LINE_BYTES: usize = 400;
+const DEFAULT_MAX_LINE_BYTES: usize = 16 * 1024;
+const DEFAULT_MAX_JSON_BYTES: u64 = 512 * 1024;
+const DEFAULT_MIN_CHECK_BYTES: usize = 2 * 1024;
+
+/// Name suffixes that are generated by build tools.
+const GENERATED_SUFFIXES: &[&str] = &[".min.js", ".min.css", ".js.map", ".css.map", ".bundle.js"];
+
+/// Thresholds for spotting machine-generated files (minified bundles, source
+/// maps, JSON dumps) whose content would only add noise to the index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GeneratedRules {
+    /// Content whose average line is longer than this is treated as minified.
+    pub max_avg_line_bytes: usize,
+    /// Any single line longer than this marks the file as generated.
+    pub max_line_bytes: usize,
+    /// JSON files larger than this are data dumps rather than documents.
+    pub max_json_bytes: u64,
+    /// Shorter content is never flagged; small files are cheap to keep.
+    pub min_check_bytes: usize,
+}
+
+impl Default for GeneratedRules {
+    fn default() -> Self {
+        Self {
+            max_avg_line_bytes: DEFAULT_MAX_AVG_LINE_BYTES,
+            max_line_bytes: DEFAULT_MAX_LINE_BYTES,
+            max_json_bytes: DEFAULT_MAX_JSON_BYTES,
+            min_check_bytes: DEFAULT_MIN_CHECK_BYTES,
+        }
+    }
+}
+
+impl GeneratedRules {
+    /// Judge by name and size alone, before anything is read.
+    pub fn matches_meta(&self, meta: &FileMeta) -> bool {
+        let name = meta.name.to_lowercase();
+        if GENERATED_SUFFIXES
+            .iter()
+            .any(|suffix| name.ends_with(suffix))
+        {
+            return true;
+        }
+        let is_json = meta
+            .ext
+            .as_deref()
+            .map(|ext| ext.eq_ignore_ascii_case("json"))
+            .unwrap_or(false);
+        is_json && meta.size > self.max_json_bytes
+    }
+
+    /// Judge extracted text by its line shape.
+    pub fn matches_content(&self, text: &str) -> bool {
+        if text.len() < self.min_check_bytes {
+            return false;
+        }
+        let mut lines = 0usize;
+        for line in text.lines() {
+            if line.len() > self.max_line_bytes {
+                return true;
+            }
+            lines += 1;
+        }
+        text.len() / lines.max(1) > self.max_avg_line_bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GeneratedRules;
+    use crate::scanner::FileMeta;
+
+    #[test]
+    fn flags_minified_names_and_long_lines() {
+        let rules = GeneratedRules::default();
+        let meta = |name: &str, size: u64| FileMeta {
+            name: name.into(),
+            ext: name.rsplit('.').next().map(str::to_string),
+            size,
+            ..FileMeta::default()
+        };
+        assert!(rules.matches_meta(&meta("app.min.js", 10)));
+        assert!(rules.matches_meta(&meta("export.json", 10 * 1024 * 1024)));
+        assert!(!rules.matches_meta(&meta("package.json", 900)));
+        assert!(!rules.matches_meta(&meta("notes.md", 10 * 1024 * 1024)));
+
+        let minified = "var a=1;".repeat(1_000);
+        assert!(rules.matches_content(&minified));
+        let prose = "A normal sentence of prose.\n".repeat(200);
+        assert!(!rules.matches_content(&prose));
+        assert!(!rules.matches_content("short=1;"));
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Answers "is this file tracked by the git repository that contains it?"
+/// for files found by the scanner. Each repository is listed once with
+/// `git ls-files`; files outside any repository are always kept.
+#[derive(Default)]
+pub(crate) struct TrackedFiles {
+    /// Directory → enclosing repository work tree, if any.
+    repo_of_dir: HashMap<PathBuf, Option<PathBuf>>,
+    /// Work tree → tracked paths, or `None` when git couldn't list them and
+    /// every file is kept.
+    tracked: HashMap<PathBuf, Option<HashSet<PathBuf>>>,
+}
+
+impl TrackedFiles {
+    /// True for files inside a repository that git does not track, such as
+    /// build output or scratch files.
+    pub(crate) fn is_untracked(&mut self, path: &Path) -> bool {
+        let Some(dir) = path.parent() else {
+            return false;
+        };
+        let Some(repo) = self.repo_for(dir) else {
+            return false;
+        };
+        let tracked = self
+            .tracked
+            .entry(repo.clone())
+            .or_insert_with(|| list_tracked(&repo));
+        match tracked {
+            Some(tracked) => !tracked.contains(path),
+            None => false,
+        }
+    }
+
+    fn repo_for(&mut self, dir: &Path) -> Option<PathBuf> {
+        if let Some(cached) = self.repo_of_dir.get(dir) {
+            return cached.clone();
+        }
+        let repo = if dir.join(".git").exists() {
+            Some(dir.to_path_buf())
+        } else {
+            dir.parent().and_then(|parent| self.repo_for(parent))
+        };
+        self.repo_of_dir.insert(dir.to_path_buf(), repo.clone());
+        repo
+    }
+}
+
+fn list_tracked(repo: &Path) -> Option<HashSet<PathBuf>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["ls-files", "-z", "--cached"])
+        .output();
+    match output {
+        Ok(output) if output.status.success() => Some(
+            output
+                .stdout
+                .split(|byte| *byte == 0)
+                .filter(|entry| !entry.is_empty())
+                .map(|entry| repo.join(String::from_utf8_lossy(entry).as_ref()))
+                .collect(),
+        ),
+        Ok(output) => {
+            eprintln!(
+                "[git] ls-files failed in {}: {}",
+                repo.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            None
+        }
+        Err(err) => {
+            eprintln!("[git] could not run git for {}: {err}", repo.display());
+            None
+        }
+    }
+}
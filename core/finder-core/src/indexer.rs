@@ -1,25 +1,88 @@
+use crate::diff::ResultSnapshots;
+use crate::diff::RESULT_SNAPSHOTS_FILE_NAME;
+use crate::entities::extract_entities;
+use crate::events::{self, IndexEvent};
+use crate::facets::folder_facet;
+use crate::normalize::{ContentNormalizer, NormalizeOptions};
+use crate::plugins::{PluginRegistry, PLUGINS_FILE_NAME};
+use crate::pressure;
+use crate::query::{EntryKind, QueryLimits, RankingConfig, ResultCache};
+use crate::retry::{PendingRetry, RetryQueue};
 use crate::scanner::FileMeta;
-use crate::schema::build_schema;
+use crate::schema::{
+    build_schema_with, name_prefix_analyzer, name_words, ContentAnalyzer, NAME_PARTS_TOKENIZER,
+    NAME_PREFIX_TOKENIZER, SCHEMA_VERSION, SCHEMA_VERSION_FILE_NAME,
+};
+use crate::sidecar::IdentitySidecar;
+use crate::standing;
+use crate::stats;
+use crate::stopwords::{
+    configured_stop_words, load_stop_words, save_stop_words, STOP_WORDS_FILE_NAME,
+};
+use crate::summary::{current_summarizer, SUMMARY_SOURCE_BYTES};
+use crate::synonyms::{Synonyms, SYNONYMS_FILE_NAME};
+use crate::textnorm::{content_analyzer, folding_analyzer, identifier_analyzer, identifier_words};
 use anyhow::{anyhow, Context, Result};
 use once_cell::sync::Lazy;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
-use std::sync::{Arc, Mutex, RwLock};
-use tantivy::collector::TopDocs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use tantivy::collector::{Count, TopDocs};
 use tantivy::directory::MmapDirectory;
 use tantivy::merge_policy::LogMergePolicy;
-use tantivy::query::TermQuery;
+use tantivy::query::{BooleanQuery, Occur, Query, TermQuery};
 use tantivy::schema::{Field, IndexRecordOption, Schema, TantivyDocument, Value};
-use tantivy::{DocAddress, Index, IndexReader, IndexWriter, Term};
+use tantivy::space_usage::PerFieldSpaceUsage;
+use tantivy::{DocAddress, Index, IndexReader, IndexWriter, ReloadPolicy, Searcher, Term};
 
 const DEFAULT_WRITER_MEM_BYTES: usize = 384 * 1024 * 1024;
 const DEFAULT_WRITER_THREADS: usize = 0; // will be replaced with num_cpus at runtime
+const MIN_WRITER_MEM_BYTES: usize = 16 * 1024 * 1024;
+const DEFAULT_DOC_STORE_CACHE_BLOCKS: usize = 100;
+/// Low-memory mode keeps only a handful of decompressed stored-field blocks.
+const LOW_MEMORY_DOC_STORE_CACHE_BLOCKS: usize = 4;
+/// Tantivy's default doc store block size, used to estimate cache footprint.
+const DOC_STORE_BLOCK_BYTES: u64 = 16 * 1024;
+/// Content bytes stored on one document; longer content continues on chunk
+/// documents.
+pub(crate) const CONTENT_CHUNK_BYTES: usize = 256 * 1024;
 
 #[derive(Debug, Clone, Copy)]
 pub struct IndexSettings {
     pub writer_threads: usize,
     pub writer_heap_bytes: usize,
+    /// Run the entity analyzer over extracted content and populate the
+    /// `email`, `phone`, `mentions_date` and `amount` fields.
+    pub extract_entities: bool,
+    /// Cleanup passes applied to content before it is indexed.
+    pub normalize: NormalizeOptions,
+    /// Privacy mode: never read or index file content, only names, paths and
+    /// metadata. Documents previously indexed with content are stripped when
+    /// they are next visited.
+    pub metadata_only: bool,
+    /// Cold-start mode for small devices (Raspberry Pi, NAS): a single writer
+    /// thread with the minimum heap and a tiny stored-field cache. Postings,
+    /// fast fields and stored fields stay on disk and are paged in on demand.
+    pub low_memory: bool,
+    /// Soft cap on writer heap plus in-flight extraction buffers, in bytes.
+    /// Over it, [`index_root`](crate::index_root) waits for buffers to drain
+    /// (or sheds low-priority roots) instead of reading more files. 0 means
+    /// no limit.
+    pub soft_memory_limit_bytes: usize,
+    /// Ceilings on what a single search may ask for.
+    pub query_limits: QueryLimits,
+    /// Weights of name, content, prefix and typo matches in scores.
+    pub ranking: RankingConfig,
+    /// How content is split into words in indexes created from now on.
+    /// An existing index keeps the analyzer recorded when it was created;
+    /// rebuild it to switch.
+    pub content_analyzer: ContentAnalyzer,
+    /// Pages of [`search`](crate::search) results kept for recently run
+    /// queries, so a UI rerunning the same query on every keystroke doesn't
+    /// search again until the next commit. 0 turns the cache off.
+    pub result_cache_entries: usize,
 }
 
 impl Default for IndexSettings {
@@ -27,6 +90,15 @@ impl Default for IndexSettings {
         Self {
             writer_threads: DEFAULT_WRITER_THREADS,
             writer_heap_bytes: DEFAULT_WRITER_MEM_BYTES,
+            extract_entities: false,
+            normalize: NormalizeOptions::default(),
+            metadata_only: false,
+            low_memory: false,
+            soft_memory_limit_bytes: 0,
+            query_limits: QueryLimits::default(),
+            ranking: RankingConfig::default(),
+            content_analyzer: ContentAnalyzer::default(),
+            result_cache_entries: 0,
         }
     }
 }
@@ -43,6 +115,8 @@ pub(crate) struct IndexFields {
     pub path: Field,
     pub name: Field,
     pub name_raw: Field,
+    pub name_prefix: Field,
+    pub name_parts: Field,
     pub ext: Field,
     pub identity: Field,
     pub mtime: Field,
@@ -50,6 +124,22 @@ pub(crate) struct IndexFields {
     pub inode: Field,
     pub dev: Field,
     pub content: Field,
+    pub email: Field,
+    pub phone: Field,
+    pub mentions_date: Field,
+    pub amount: Field,
+    pub summary: Field,
+    pub has_content: Field,
+    pub dir: Field,
+    pub alias_target: Field,
+    pub kind: Field,
+    pub children: Field,
+    pub folder: Field,
+    pub columns: Field,
+    pub rows: Field,
+    pub chunk: Field,
+    pub depth: Field,
+    pub name_len: Field,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -57,6 +147,7 @@ pub struct IndexedDocument {
     pub path: String,
     pub mtime: i64,
     pub size: u64,
+    pub has_content: bool,
 }
 
 impl IndexedDocument {
@@ -65,6 +156,7 @@ impl IndexedDocument {
             path: meta.path.clone(),
             mtime: meta.modified_at,
             size: meta.size,
+            has_content: false,
         }
     }
 
@@ -76,8 +168,81 @@ impl IndexedDocument {
 struct IndexHandle {
     index: Index,
     reader: IndexReader,
-    writer: Mutex<IndexWriter>,
+    /// `None` when the index was opened read-only by a search process.
+    writer: Option<Mutex<IndexWriter>>,
     fields: IndexFields,
+    settings: IndexSettings,
+    budget: MemoryBudget,
+    /// Commit counter shared between processes; absent for in-memory indexes.
+    generation: Option<GenerationFile>,
+    /// Identity → dedup metadata for every document written through this
+    /// handle, including uncommitted ones. Warmed once at open so dedup
+    /// checks are hash lookups instead of index searches. Read-only handles
+    /// don't keep one.
+    known: Option<RwLock<HashMap<String, IndexedDocument>>>,
+    /// On-disk copy of `known` for fast startup; on-disk writable indexes only.
+    sidecar: Option<IdentitySidecar>,
+    /// Files to re-read after transient extraction failures; persisted for
+    /// on-disk writable indexes.
+    retries: Arc<RetryQueue>,
+    /// Saved-search results for `diff_results`; persisted for on-disk indexes.
+    snapshots: Arc<ResultSnapshots>,
+    /// Query-time synonym groups; persisted for on-disk indexes.
+    synonyms: Arc<Synonyms>,
+    /// Extractor plugins registered for every root; persisted for on-disk
+    /// indexes.
+    plugins: Arc<PluginRegistry>,
+    /// Recent search results, dropped whenever the reader reloads.
+    results: Arc<ResultCache>,
+    /// Where the index lives; `None` for in-memory indexes.
+    dir: Option<PathBuf>,
+}
+
+/// Name of the file, inside the index directory, holding the number of
+/// commits made by the writer process.
+pub const GENERATION_FILE_NAME: &str = ".finder-generation";
+
+/// File-based commit counter that lets a read-only process notice when the
+/// writer process has committed, without a socket or any other IPC channel.
+struct GenerationFile {
+    path: PathBuf,
+    /// Last generation the local reader was reloaded at.
+    seen: AtomicU64,
+}
+
+impl GenerationFile {
+    fn new(dir: &Path) -> Self {
+        let path = dir.join(GENERATION_FILE_NAME);
+        let seen = AtomicU64::new(read_generation(&path));
+        Self { path, seen }
+    }
+
+    fn bump(&self) -> Result<u64> {
+        let next = read_generation(&self.path) + 1;
+        // Write then rename so readers never observe a half-written counter.
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, next.to_string())
+            .with_context(|| format!("failed writing {}", tmp.display()))?;
+        fs::rename(&tmp, &self.path)
+            .with_context(|| format!("failed publishing {}", self.path.display()))?;
+        self.seen.store(next, Ordering::SeqCst);
+        Ok(next)
+    }
+}
+
+fn read_generation(path: &Path) -> u64 {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|raw| raw.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// Resolved memory knobs the index was opened with.
+#[derive(Debug, Clone, Copy)]
+struct MemoryBudget {
+    writer_threads: usize,
+    writer_heap_bytes: usize,
+    doc_store_cache_blocks: usize,
 }
 
 static INDEX_STATE: Lazy<RwLock<Option<Arc<IndexHandle>>>> = Lazy::new(|| RwLock::new(None));
@@ -90,54 +255,311 @@ pub fn configure(settings: IndexSettings) {
 }
 
 pub fn init_index(path: &str) -> Result<()> {
-    let schema = build_schema();
     let path = Path::new(path);
     fs::create_dir_all(path)
         .with_context(|| format!("failed creating index directory: {}", path.display()))?;
 
     let directory = MmapDirectory::open(path)
         .with_context(|| format!("failed opening mmap directory: {}", path.display()))?;
-    let index = Index::open_or_create(directory, schema.clone())
-        .context("failed to open or create tantivy index")?;
+    let index = open_or_create(directory, path)
+        .context("failed to open or create tantivy index")
+        .map_err(events::report_corruption)?;
+    let schema = index.schema();
 
-    let reader = index.reader().context("failed to create tantivy reader")?;
+    install_index(index, &schema, Some(path), true).map_err(events::report_corruption)
+}
 
-    let settings = current_settings();
-    let threads = if settings.writer_threads == 0 {
-        num_cpus::get().max(1)
+/// Open the index in `directory` (at `path`), which must have this build's
+/// schema with whichever content analyzer it records, or create one with
+/// the configured analyzer and stop words. An index from an older schema
+/// version is dropped first; the next scan of each root fills the new one.
+fn open_or_create(mut directory: MmapDirectory, path: &Path) -> Result<Index> {
+    if Index::exists(&directory)? {
+        let version = read_schema_version(path);
+        if version == SCHEMA_VERSION {
+            let index = Index::open(directory)?;
+            let expected = ContentAnalyzer::of_schema(&index.schema())
+                .map(|(analyzer, stop_words)| build_schema_with(analyzer, stop_words));
+            if expected.as_ref() != Some(&index.schema()) {
+                anyhow::bail!("an index exists but the schema does not match");
+            }
+            return Ok(index);
+        }
+        check_schema_version(version)?;
+        eprintln!(
+            "[indexer] rebuilding the index at {}: it has schema version {version}, this build writes {SCHEMA_VERSION}",
+            path.display()
+        );
+        drop(directory);
+        drop_index_files(path)?;
+        directory = MmapDirectory::open(path)
+            .with_context(|| format!("failed opening mmap directory: {}", path.display()))?;
+    }
+    let schema = new_schema(Some(path))?;
+    let index = Index::create(directory, schema, Default::default())?;
+    write_schema_version(path)?;
+    Ok(index)
+}
+
+/// Schema version of the index in `dir`; 1 when it has no marker.
+fn read_schema_version(dir: &Path) -> u32 {
+    fs::read_to_string(dir.join(SCHEMA_VERSION_FILE_NAME))
+        .ok()
+        .and_then(|raw| raw.trim().parse().ok())
+        .unwrap_or(1)
+}
+
+/// Record that the index just created in `dir` has this build's schema.
+pub(crate) fn write_schema_version(dir: &Path) -> Result<()> {
+    let path = dir.join(SCHEMA_VERSION_FILE_NAME);
+    fs::write(&path, SCHEMA_VERSION.to_string())
+        .with_context(|| format!("failed writing {}", path.display()))
+}
+
+/// Refuse indexes written by a newer build, which this one can't read and
+/// must not drop.
+fn check_schema_version(version: u32) -> Result<()> {
+    if version > SCHEMA_VERSION {
+        anyhow::bail!(
+            "the index has schema version {version}, newer than this build's {SCHEMA_VERSION}"
+        );
+    }
+    Ok(())
+}
+
+/// Delete everything in `dir` but the user's settings kept beside the index:
+/// stop words, synonyms, extractor plugins, saved result snapshots and the
+/// generation counter, which keeps counting up so readers notice the
+/// rebuilt index.
+fn drop_index_files(dir: &Path) -> Result<()> {
+    const KEEP: [&str; 5] = [
+        STOP_WORDS_FILE_NAME,
+        SYNONYMS_FILE_NAME,
+        PLUGINS_FILE_NAME,
+        RESULT_SNAPSHOTS_FILE_NAME,
+        GENERATION_FILE_NAME,
+    ];
+    let entries = fs::read_dir(dir).with_context(|| format!("failed listing {}", dir.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        if KEEP.iter().any(|keep| entry.file_name() == *keep) {
+            continue;
+        }
+        let path = entry.path();
+        let removed = if entry.file_type()?.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        removed.with_context(|| format!("failed removing {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// The schema for an index about to be created in `dir`, with the
+/// configured content analyzer and stop words. A non-empty stop word list
+/// is saved there first, so the index always finds the list it names.
+pub(crate) fn new_schema(dir: Option<&Path>) -> Result<Schema> {
+    let stop_words = configured_stop_words();
+    if let Some(dir) = dir.filter(|_| !stop_words.is_empty()) {
+        save_stop_words(dir, &stop_words)?;
+    }
+    Ok(build_schema_with(
+        current_settings().content_analyzer,
+        !stop_words.is_empty(),
+    ))
+}
+
+/// Open an existing on-disk index for searching only, e.g. from a GUI while a
+/// background helper owns the writer. Call [`refresh_if_changed`] to pick up
+/// the helper's commits.
+pub fn init_index_read_only(path: &str) -> Result<()> {
+    let path = Path::new(path);
+    let directory = MmapDirectory::open(path)
+        .with_context(|| format!("failed opening mmap directory: {}", path.display()))?;
+    let index = Index::open(directory)
+        .context("failed to open tantivy index read-only")
+        .map_err(events::report_corruption)?;
+    let version = read_schema_version(path);
+    check_schema_version(version)?;
+    if version < SCHEMA_VERSION {
+        anyhow::bail!(
+            "the index has schema version {version}; open it for writing once to rebuild it"
+        );
+    }
+    let schema = index.schema();
+
+    install_index(index, &schema, Some(path), false).map_err(events::report_corruption)
+}
+
+/// Open a throwaway index held entirely in RAM. Nothing touches disk and the
+/// contents vanish on [`close`] or when another index is initialized.
+pub fn init_index_in_memory() -> Result<()> {
+    let schema = new_schema(None)?;
+    let index = Index::create_in_ram(schema.clone());
+    install_index(index, &schema, None, true)
+}
+
+fn install_index(index: Index, schema: &Schema, dir: Option<&Path>, writable: bool) -> Result<()> {
+    let mut settings = current_settings();
+    let (analyzer, drops_stop_words) = ContentAnalyzer::of_schema(schema)
+        .context("the index's content analyzer is unknown to this version")?;
+    settings.content_analyzer = analyzer;
+    let stop_words = match dir {
+        Some(dir) if drops_stop_words => load_stop_words(dir)?,
+        None if drops_stop_words => configured_stop_words(),
+        _ => Vec::new(),
+    };
+    index
+        .tokenizers()
+        .register(NAME_PREFIX_TOKENIZER, name_prefix_analyzer());
+    index
+        .tokenizers()
+        .register(NAME_PARTS_TOKENIZER, identifier_analyzer());
+    // `TEXT` fields and the query parser use "default"; folding there keeps
+    // names, contents and queries in one normal form.
+    index.tokenizers().register("default", folding_analyzer());
+    for analyzer in ContentAnalyzer::ALL {
+        index.tokenizers().register(
+            analyzer.tokenizer(true),
+            content_analyzer(analyzer, &stop_words),
+        );
+    }
+    index.tokenizers().register(
+        ContentAnalyzer::English.tokenizer(false),
+        content_analyzer(ContentAnalyzer::English, &[]),
+    );
+    let budget = if settings.low_memory {
+        MemoryBudget {
+            writer_threads: 1,
+            writer_heap_bytes: MIN_WRITER_MEM_BYTES,
+            doc_store_cache_blocks: LOW_MEMORY_DOC_STORE_CACHE_BLOCKS,
+        }
+    } else {
+        let threads = if settings.writer_threads == 0 {
+            num_cpus::get().max(1)
+        } else {
+            settings.writer_threads.max(1)
+        };
+        MemoryBudget {
+            writer_threads: threads,
+            writer_heap_bytes: settings.writer_heap_bytes.max(MIN_WRITER_MEM_BYTES),
+            doc_store_cache_blocks: DEFAULT_DOC_STORE_CACHE_BLOCKS,
+        }
+    };
+
+    // Read-only handles reload when the generation counter moves, not on
+    // their own schedule.
+    let reload_policy = if writable {
+        ReloadPolicy::OnCommitWithDelay
     } else {
-        settings.writer_threads.max(1)
+        ReloadPolicy::Manual
     };
-    let writer = index
-        .writer_with_num_threads(threads, settings.writer_heap_bytes.max(16 * 1024 * 1024))
-        .context("failed to create tantivy writer")?;
+    let mut reader_builder = index
+        .reader_builder()
+        .reload_policy(reload_policy)
+        .doc_store_cache_num_blocks(budget.doc_store_cache_blocks);
+    if settings.low_memory {
+        reader_builder = reader_builder.num_warming_threads(1);
+    }
+    let reader = reader_builder
+        .try_into()
+        .context("failed to create tantivy reader")?;
 
-    let mut merge_policy = LogMergePolicy::default();
-    merge_policy.set_level_log_size(1.2);
-    writer.set_merge_policy(Box::new(merge_policy));
+    let writer = if writable {
+        let writer: IndexWriter = index
+            .writer_with_num_threads(budget.writer_threads, budget.writer_heap_bytes)
+            .context("failed to create tantivy writer")?;
+
+        let mut merge_policy = LogMergePolicy::default();
+        merge_policy.set_level_log_size(1.2);
+        writer.set_merge_policy(Box::new(merge_policy));
+        Some(Mutex::new(writer))
+    } else {
+        None
+    };
 
     let fields = IndexFields {
-        path: field(&schema, "path")?,
-        name: field(&schema, "name")?,
-        name_raw: field(&schema, "name_raw")?,
-        ext: field(&schema, "ext")?,
-        identity: field(&schema, "identity")?,
-        mtime: field(&schema, "mtime")?,
-        size: field(&schema, "size")?,
-        inode: field(&schema, "inode")?,
-        dev: field(&schema, "dev")?,
-        content: field(&schema, "content")?,
+        path: field(schema, "path")?,
+        name: field(schema, "name")?,
+        name_raw: field(schema, "name_raw")?,
+        name_prefix: field(schema, "name_prefix")?,
+        name_parts: field(schema, "name_parts")?,
+        ext: field(schema, "ext")?,
+        identity: field(schema, "identity")?,
+        mtime: field(schema, "mtime")?,
+        size: field(schema, "size")?,
+        inode: field(schema, "inode")?,
+        dev: field(schema, "dev")?,
+        content: field(schema, "content")?,
+        email: field(schema, "email")?,
+        phone: field(schema, "phone")?,
+        mentions_date: field(schema, "mentions_date")?,
+        amount: field(schema, "amount")?,
+        summary: field(schema, "summary")?,
+        has_content: field(schema, "has_content")?,
+        dir: field(schema, "dir")?,
+        alias_target: field(schema, "alias_target")?,
+        kind: field(schema, "kind")?,
+        children: field(schema, "children")?,
+        folder: field(schema, "folder")?,
+        columns: field(schema, "columns")?,
+        rows: field(schema, "rows")?,
+        chunk: field(schema, "chunk")?,
+        depth: field(schema, "depth")?,
+        name_len: field(schema, "name_len")?,
+    };
+
+    pressure::configure(settings.soft_memory_limit_bytes, budget.writer_heap_bytes);
+
+    let sidecar = match (&writer, dir) {
+        (Some(_), Some(dir)) => Some(IdentitySidecar::new(dir)),
+        _ => None,
+    };
+    let known = if writer.is_some() {
+        let opstamp = index
+            .load_metas()
+            .context("failed to read index metadata")?
+            .opstamp;
+        let cached = sidecar.as_ref().and_then(|sidecar| sidecar.load(opstamp));
+        let map = match cached {
+            Some(map) => map,
+            None => {
+                let map = read_index_state(&reader, &fields)?;
+                if let Some(sidecar) = &sidecar {
+                    if let Err(err) = sidecar.save(opstamp, &map) {
+                        eprintln!("[indexer] failed saving identity map: {err}");
+                    }
+                }
+                map
+            }
+        };
+        Some(RwLock::new(map))
+    } else {
+        None
     };
 
     let handle = Arc::new(IndexHandle {
         index,
         reader,
-        writer: Mutex::new(writer),
+        writer,
         fields,
+        settings,
+        budget,
+        generation: dir.map(GenerationFile::new),
+        known,
+        sidecar,
+        retries: Arc::new(RetryQueue::open(dir.filter(|_| writable))),
+        snapshots: Arc::new(ResultSnapshots::open(dir)),
+        synonyms: Arc::new(Synonyms::open(dir)),
+        plugins: Arc::new(PluginRegistry::open(dir)),
+        results: Arc::new(ResultCache::new(settings.result_cache_entries)),
+        dir: dir.map(Path::to_path_buf),
     });
 
     let mut guard = INDEX_STATE.write().unwrap();
     *guard = Some(handle);
+    stats::reset();
 
     Ok(())
 }
@@ -147,79 +569,654 @@ pub fn add_or_update_file(
     content_opt: Option<String>,
     force_reindex: bool,
 ) -> Result<IndexUpdate> {
-    let handle = index_handle()?;
-    let identity = meta.identity();
+    add_or_update_file_streaming(meta, force_reindex, |sink| {
+        if let Some(content) = content_opt {
+            sink.push_owned(content);
+        }
+        Ok(())
+    })
+}
+
+/// Appends content to a file's documents as it is read. The first
+/// [`CONTENT_CHUNK_BYTES`] of text go on the file's own document; the rest
+/// fills chunk documents that are handed to the writer as soon as they are
+/// full, so no document, and no buffer, grows with the file.
+pub struct ContentSink<'a> {
+    doc: &'a mut TantivyDocument,
+    fields: &'a IndexFields,
+    settings: &'a IndexSettings,
+    /// Carries normalization across pushes; `None` when it is off.
+    normalizer: Option<ContentNormalizer>,
+    bytes: usize,
+    /// Leading text kept for the summarizer; `None` when no summarizer is set.
+    lead: Option<String>,
+    /// Fields every chunk document repeats, so filters and sorting treat it
+    /// like the file.
+    template: &'a TantivyDocument,
+    /// Chunk document being filled; `None` while text still goes on `doc`.
+    chunk: Option<TantivyDocument>,
+    /// Content bytes on the document being filled.
+    filled: usize,
+    chunks: u64,
+    emit: &'a mut dyn FnMut(TantivyDocument) -> Result<()>,
+    /// First failure handing a chunk document to the writer.
+    failed: Option<anyhow::Error>,
+}
 
-    let mut update = IndexUpdate::Added;
+impl ContentSink<'_> {
+    pub fn push(&mut self, chunk: &str) {
+        if chunk.is_empty() {
+            return;
+        }
+        self.push_owned(chunk.to_string());
+    }
 
-    if !force_reindex {
-        if let Some(existing) = find_existing(&handle, &identity)? {
-            if existing.matches_meta(&meta) {
-                return Ok(IndexUpdate::Skipped);
+    pub fn push_owned(&mut self, chunk: String) {
+        let chunk = match self.normalizer.as_mut() {
+            Some(normalizer) => normalizer.push(&chunk),
+            None => chunk,
+        };
+        self.append(chunk);
+    }
+
+    fn append(&mut self, chunk: String) {
+        if chunk.is_empty() || self.failed.is_some() {
+            return;
+        }
+        if let Some(lead) = self.lead.as_mut() {
+            append_lead(lead, &chunk);
+        }
+        self.bytes += chunk.len();
+        if self.filled + chunk.len() <= CONTENT_CHUNK_BYTES {
+            self.add_piece(chunk);
+            return;
+        }
+        let mut rest = chunk.as_str();
+        while !rest.is_empty() && self.failed.is_none() {
+            let room = CONTENT_CHUNK_BYTES - self.filled;
+            let take = if rest.len() <= room {
+                rest.len()
+            } else {
+                split_point(rest, room, self.filled == 0)
+            };
+            if take == 0 {
+                self.next_chunk();
+                continue;
             }
-            update = IndexUpdate::Updated;
+            let (piece, tail) = rest.split_at(take);
+            self.add_piece(piece.to_string());
+            rest = tail;
         }
     }
 
-    {
-        let writer = handle.writer.lock().expect("index writer mutex poisoned");
+    fn add_piece(&mut self, piece: String) {
+        self.filled += piece.len();
+        let doc = self.chunk.as_mut().unwrap_or(&mut *self.doc);
+        if self.settings.extract_entities {
+            add_entities(doc, self.fields, &piece);
+        }
+        doc.add_text(self.fields.content, piece);
+    }
+
+    /// Hand the full chunk document, if any, to the writer and start the
+    /// next one.
+    fn next_chunk(&mut self) {
+        if let Some(full) = self.chunk.take() {
+            if let Err(err) = (self.emit)(full) {
+                self.failed = Some(err);
+                return;
+            }
+        }
+        self.chunks += 1;
+        let mut next = self.template.clone();
+        next.add_u64(self.fields.chunk, self.chunks);
+        self.chunk = Some(next);
+        self.filled = 0;
+    }
+
+    /// Hand over the last chunk document; returns the text bytes and the
+    /// summarizer's lead.
+    fn finish(mut self) -> Result<(usize, Option<String>)> {
+        if let Some(mut normalizer) = self.normalizer.take() {
+            let rest = normalizer.finish();
+            self.append(rest);
+        }
+        if let Some(last) = self.chunk.take() {
+            if self.failed.is_none() {
+                if let Err(err) = (self.emit)(last) {
+                    self.failed = Some(err);
+                }
+            }
+        }
+        match self.failed {
+            Some(err) => Err(err),
+            None => Ok((self.bytes, self.lead)),
+        }
+    }
 
-        let identity_term = Term::from_field_text(handle.fields.identity, &identity);
-        writer.delete_term(identity_term);
+    /// Total bytes of text appended so far, counting text normalization
+    /// still holds back.
+    pub fn bytes(&self) -> usize {
+        self.bytes + self.normalizer.as_ref().map_or(0, ContentNormalizer::held)
+    }
 
-        let mut doc = TantivyDocument::new();
-        doc.add_text(handle.fields.path, meta.path.clone());
-        doc.add_text(handle.fields.name, meta.name.clone());
-        doc.add_text(handle.fields.name_raw, meta.name);
-        if let Some(ext) = meta.ext.clone() {
-            doc.add_text(handle.fields.ext, ext);
-        }
-        doc.add_text(handle.fields.identity, identity);
-        doc.add_i64(handle.fields.mtime, meta.modified_at);
-        doc.add_u64(handle.fields.size, meta.size);
-        doc.add_u64(handle.fields.inode, meta.inode);
-        doc.add_u64(handle.fields.dev, meta.dev);
-        if let Some(content) = content_opt {
-            if !content.is_empty() {
-                doc.add_text(handle.fields.content, content);
+    /// Record the column and row count of a delimited data file.
+    pub fn set_table_shape(&mut self, columns: u64, rows: u64) {
+        self.doc.add_u64(self.fields.columns, columns);
+        self.doc.add_u64(self.fields.rows, rows);
+    }
+}
+
+/// Like [`add_or_update_file`], but content is supplied incrementally through
+/// `feed`. `feed` only runs when the file actually needs (re)indexing, so
+/// unchanged files are never read.
+pub fn add_or_update_file_streaming<F>(
+    meta: FileMeta,
+    force_reindex: bool,
+    feed: F,
+) -> Result<IndexUpdate>
+where
+    F: FnOnce(&mut ContentSink<'_>) -> Result<()>,
+{
+    upsert(meta, force_reindex, false, feed)
+}
+
+/// Shared add/update path. `metadata_only` lets callers (e.g. a per-root
+/// privacy setting) enforce names-only indexing on top of the global setting;
+/// `feed` is never invoked in that case.
+pub(crate) fn upsert<F>(
+    meta: FileMeta,
+    force_reindex: bool,
+    metadata_only: bool,
+    feed: F,
+) -> Result<IndexUpdate>
+where
+    F: FnOnce(&mut ContentSink<'_>) -> Result<()>,
+{
+    let handle = index_handle()?;
+    if handle.writer.is_none() {
+        return Err(anyhow!("index opened read-only"));
+    }
+    let identity = meta.identity();
+    // Aliases are indexed as pointers to their target, never by content.
+    let metadata_only = metadata_only || handle.settings.metadata_only || meta.skips_content();
+
+    let existing = if force_reindex {
+        None
+    } else {
+        handle.existing(&identity)?
+    };
+    let Some(update) = dedup(existing.as_ref(), &meta, metadata_only) else {
+        stats::record_unchanged_skip();
+        return Ok(IndexUpdate::Skipped);
+    };
+
+    let identity_term = Term::from_field_text(handle.fields.identity, &identity);
+    // Chunk documents reach the writer while the file is still being read;
+    // the first one replaces the file's old documents, and the file counts
+    // as unindexed until its own document follows.
+    let mut replaced = false;
+    let mut emit = |chunk: TantivyDocument| -> Result<()> {
+        let writer = handle.writer()?;
+        if !replaced {
+            writer.delete_term(identity_term.clone());
+            handle.forget(&identity);
+            replaced = true;
+        }
+        writer
+            .add_document(chunk)
+            .context("failed adding chunk document to index")?;
+        Ok(())
+    };
+    let built = build_document(&handle, &meta, &identity, metadata_only, feed, &mut emit);
+    let (doc, state) = match built {
+        Ok(built) => built,
+        Err(err) => {
+            if replaced {
+                // Drop the chunks already written.
+                handle.writer()?.delete_term(identity_term);
             }
+            return Err(err);
+        }
+    };
+
+    {
+        let writer = handle.writer()?;
+        if !replaced {
+            writer.delete_term(identity_term);
         }
 
         writer
             .add_document(doc)
             .context("failed adding document to index")?;
+        stats::record_docs_added(1);
+        // Recorded under the writer lock so a commit never sees a map that
+        // is ahead of or behind what it persists.
+        handle.remember(identity, state);
     }
 
     Ok(update)
 }
 
+/// Add or update many files under a single writer lock, for bursts of
+/// watcher events. Results line up with `files`.
+pub fn add_or_update_batch(
+    files: Vec<(FileMeta, Option<String>)>,
+    force_reindex: bool,
+) -> Result<Vec<IndexUpdate>> {
+    let handle = index_handle()?;
+    if handle.writer.is_none() {
+        return Err(anyhow!("index opened read-only"));
+    }
+    let mut updates = Vec::with_capacity(files.len());
+    // Keyed by identity so a file listed twice is written once, last wins.
+    let mut pending: HashMap<String, PendingFile> = HashMap::new();
+    for (meta, content) in files {
+        let identity = meta.identity();
+        let metadata_only = handle.settings.metadata_only || meta.skips_content();
+        let existing = if force_reindex {
+            None
+        } else {
+            handle.existing(&identity)?
+        };
+        let Some(update) = dedup(existing.as_ref(), &meta, metadata_only) else {
+            stats::record_unchanged_skip();
+            updates.push(IndexUpdate::Skipped);
+            continue;
+        };
+        let mut chunks = Vec::new();
+        let mut emit = |chunk| {
+            chunks.push(chunk);
+            Ok(())
+        };
+        let feed = |sink: &mut ContentSink<'_>| {
+            if let Some(content) = content {
+                sink.push_owned(content);
+            }
+            Ok(())
+        };
+        let (doc, state) =
+            build_document(&handle, &meta, &identity, metadata_only, feed, &mut emit)?;
+        updates.push(update);
+        pending.insert(identity, (doc, chunks, state));
+    }
+
+    if !pending.is_empty() {
+        let writer = handle.writer()?;
+        for identity in pending.keys() {
+            writer.delete_term(Term::from_field_text(handle.fields.identity, identity));
+        }
+        for (identity, (doc, chunks, state)) in pending {
+            for chunk in chunks {
+                writer
+                    .add_document(chunk)
+                    .context("failed adding chunk document to index")?;
+            }
+            writer
+                .add_document(doc)
+                .context("failed adding document to index")?;
+            handle.remember(identity, state);
+            stats::record_docs_added(1);
+        }
+    }
+
+    Ok(updates)
+}
+
+/// A file's own document, its chunk documents and its dedup metadata.
+type PendingFile = (TantivyDocument, Vec<TantivyDocument>, IndexedDocument);
+
+/// `None` when the stored document is current; otherwise whether writing
+/// `meta` adds or replaces a document.
+fn dedup(
+    existing: Option<&IndexedDocument>,
+    meta: &FileMeta,
+    metadata_only: bool,
+) -> Option<IndexUpdate> {
+    let Some(existing) = existing else {
+        return Some(IndexUpdate::Added);
+    };
+    let strip_content = metadata_only && existing.has_content;
+    if existing.matches_meta(meta) && !strip_content {
+        None
+    } else {
+        Some(IndexUpdate::Updated)
+    }
+}
+
+/// Build the document for `meta` without holding the writer lock, so
+/// extraction never runs under it. Chunk documents for content past the
+/// first [`CONTENT_CHUNK_BYTES`] go to `emit` as they fill up.
+fn build_document<F>(
+    handle: &IndexHandle,
+    meta: &FileMeta,
+    identity: &str,
+    metadata_only: bool,
+    feed: F,
+    emit: &mut dyn FnMut(TantivyDocument) -> Result<()>,
+) -> Result<(TantivyDocument, IndexedDocument)>
+where
+    F: FnOnce(&mut ContentSink<'_>) -> Result<()>,
+{
+    // Shared with the chunk documents.
+    let mut shared = TantivyDocument::new();
+    shared.add_text(handle.fields.path, meta.path.clone());
+    shared.add_text(handle.fields.name_raw, meta.name.clone());
+    if let Some(ext) = meta.ext.clone() {
+        shared.add_text(handle.fields.ext, ext);
+    }
+    shared.add_text(handle.fields.identity, identity);
+    shared.add_i64(handle.fields.mtime, meta.modified_at);
+    shared.add_u64(handle.fields.size, meta.size);
+    shared.add_u64(
+        handle.fields.depth,
+        Path::new(&meta.path).components().count() as u64,
+    );
+    shared.add_u64(handle.fields.name_len, meta.name.chars().count() as u64);
+    shared.add_u64(handle.fields.inode, meta.inode);
+    shared.add_u64(handle.fields.dev, meta.dev);
+    let kind = if meta.is_folder() {
+        EntryKind::Folder
+    } else {
+        EntryKind::File
+    };
+    shared.add_text(handle.fields.kind, kind.term());
+    for dir in Path::new(&meta.path).ancestors().skip(1) {
+        if dir.parent().is_none() {
+            break;
+        }
+        shared.add_text(handle.fields.dir, dir.to_string_lossy());
+    }
+    if let Some(folder) = Path::new(&meta.path).parent() {
+        shared.add_facet(handle.fields.folder, folder_facet(folder));
+    }
+
+    let mut doc = shared.clone();
+    doc.add_u64(handle.fields.chunk, 0);
+    doc.add_text(handle.fields.name, meta.name.clone());
+    doc.add_text(handle.fields.name_parts, meta.name.clone());
+    if let Some(display_name) = &meta.display_name {
+        doc.add_text(handle.fields.name, display_name);
+        doc.add_text(handle.fields.name_parts, display_name);
+    }
+    let names = std::iter::once(&meta.name).chain(&meta.display_name);
+    for name in names {
+        for word in name_words(name).into_iter().chain(identifier_words(name)) {
+            doc.add_text(handle.fields.name_prefix, word);
+        }
+    }
+    if let Some(target) = &meta.alias_target {
+        doc.add_text(handle.fields.alias_target, target);
+    }
+    if let Some(children) = meta.children {
+        doc.add_u64(handle.fields.children, children);
+    }
+
+    let mut template = shared;
+    template.add_u64(handle.fields.has_content, 1);
+    let summarizer = current_summarizer();
+    let (content_bytes, lead) = {
+        let mut sink = ContentSink {
+            doc: &mut doc,
+            fields: &handle.fields,
+            settings: &handle.settings,
+            normalizer: handle
+                .settings
+                .normalize
+                .is_enabled()
+                .then(|| ContentNormalizer::new(handle.settings.normalize)),
+            bytes: 0,
+            lead: summarizer.as_ref().map(|_| String::new()),
+            template: &template,
+            chunk: None,
+            filled: 0,
+            chunks: 0,
+            emit,
+            failed: None,
+        };
+        if !metadata_only {
+            feed(&mut sink)?;
+        }
+        sink.finish()?
+    };
+    doc.add_u64(handle.fields.has_content, u64::from(content_bytes > 0));
+    if let (Some(summarizer), Some(lead)) = (summarizer, lead) {
+        if let Some(summary) = summarizer.summarize(meta, &lead) {
+            doc.add_text(handle.fields.summary, summary);
+        }
+    }
+    let state = IndexedDocument {
+        has_content: content_bytes > 0,
+        ..IndexedDocument::from_meta(meta)
+    };
+    Ok((doc, state))
+}
+
+fn append_lead(lead: &mut String, chunk: &str) {
+    let remaining = SUMMARY_SOURCE_BYTES.saturating_sub(lead.len());
+    if remaining == 0 {
+        return;
+    }
+    let mut end = remaining.min(chunk.len());
+    while !chunk.is_char_boundary(end) {
+        end -= 1;
+    }
+    lead.push_str(&chunk[..end]);
+}
+
+/// Bytes of `text` that fit in `room`, ending after whitespace so words stay
+/// whole; 0 when no word fits, unless the document is `fresh` and a word
+/// longer than a whole chunk has to be cut.
+fn split_point(text: &str, room: usize, fresh: bool) -> usize {
+    let mut end = room.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    match text[..end].rfind(char::is_whitespace) {
+        Some(at) => at + text[at..].chars().next().map_or(1, char::len_utf8),
+        None if fresh => end,
+        None => 0,
+    }
+}
+
+fn add_entities(doc: &mut TantivyDocument, fields: &IndexFields, content: &str) {
+    let entities = extract_entities(content);
+    for email in entities.emails {
+        doc.add_text(fields.email, email);
+    }
+    for phone in entities.phones {
+        doc.add_text(fields.phone, phone);
+    }
+    for date in entities.dates {
+        doc.add_text(fields.mentions_date, date);
+    }
+    for amount in entities.amounts {
+        doc.add_text(fields.amount, amount);
+    }
+}
+
 pub fn should_reindex(meta: &FileMeta) -> Result<bool> {
     let handle = index_handle()?;
     let identity = meta.identity();
 
-    if let Some(existing) = find_existing(&handle, &identity)? {
+    if let Some(existing) = handle.existing(&identity)? {
         Ok(!existing.matches_meta(meta))
     } else {
         Ok(true)
     }
 }
 
-pub fn commit() -> Result<()> {
+/// Delete every document stored under `dir` whose identity is not in
+/// `keep`, e.g. files that vanished since `dir` was last scanned. Returns
+/// how many files were removed.
+pub(crate) fn prune_under(dir: &Path, keep: &HashSet<String>) -> Result<usize> {
     let handle = index_handle()?;
-    {
-        let mut writer = handle.writer.lock().expect("index writer mutex poisoned");
-        writer.commit().context("tantivy commit failed")?;
+    let known = handle
+        .known
+        .as_ref()
+        .ok_or_else(|| anyhow!("index opened read-only"))?;
+    let writer = handle.writer()?;
+    let mut known = known.write().unwrap();
+    let vanished: Vec<String> = known
+        .iter()
+        .filter(|(identity, doc)| {
+            !keep.contains(*identity) && Path::new(&doc.path).starts_with(dir)
+        })
+        .map(|(identity, _)| identity.clone())
+        .collect();
+    for identity in &vanished {
+        writer.delete_term(Term::from_field_text(handle.fields.identity, identity));
+        known.remove(identity);
+    }
+    if !vanished.is_empty() {
+        if let Some(sidecar) = &handle.sidecar {
+            sidecar.mark_changed();
+        }
+    }
+    Ok(vanished.len())
+}
+
+/// Delete the documents of `path`, or of everything below it when it is a
+/// directory, and commit. `None` on read-only handles, whose writer process
+/// notices on its next scan.
+pub(crate) fn forget_path(path: &Path) -> Result<Option<usize>> {
+    if index_handle()?.writer.is_none() {
+        return Ok(None);
     }
+    let removed = prune_under(path, &HashSet::new())?;
+    if removed > 0 {
+        commit()?;
+    }
+    Ok(Some(removed))
+}
+
+/// Whether the index already holds a document for `meta`'s file.
+pub(crate) fn is_indexed(meta: &FileMeta) -> Result<bool> {
+    Ok(index_handle()?.existing(&meta.identity())?.is_some())
+}
+
+pub fn commit() -> Result<()> {
+    let handle = index_handle()?;
+    let opstamp = {
+        let mut writer = handle.writer()?;
+        let opstamp = writer
+            .commit()
+            .context("tantivy commit failed")
+            .map_err(events::report_corruption)?;
+        if let (Some(sidecar), Some(known)) = (&handle.sidecar, &handle.known) {
+            sidecar.after_commit(opstamp, &known.read().unwrap())?;
+        }
+        opstamp
+    };
+    let generation = match &handle.generation {
+        Some(generation) => generation.bump()?,
+        None => 0,
+    };
     handle
         .reader
         .reload()
         .context("failed to reload index reader")?;
+    stats::record_reader_reload();
+
+    events::emit(IndexEvent::CommitCompleted {
+        opstamp,
+        generation,
+    });
+    standing::check_standing_queries();
+    if events::size_alert_bytes().is_some() {
+        match handle.reader.searcher().space_usage() {
+            Ok(usage) => events::check_index_size(usage.total().get_bytes()),
+            Err(err) => eprintln!("[indexer] failed computing index size: {err}"),
+        }
+    }
     Ok(())
 }
 
+/// Reload the reader if the writer process has committed since the last
+/// reload. Returns whether a reload happened. Cheap enough to call before
+/// every search.
+pub fn refresh_if_changed() -> Result<bool> {
+    let handle = index_handle()?;
+    let Some(generation) = &handle.generation else {
+        return Ok(false);
+    };
+    let current = read_generation(&generation.path);
+    if generation.seen.swap(current, Ordering::SeqCst) == current {
+        return Ok(false);
+    }
+    handle
+        .reader
+        .reload()
+        .context("failed to reload index reader")?;
+    stats::record_reader_reload();
+    standing::check_standing_queries();
+    Ok(true)
+}
+
+/// Commit generation last published by the writer, or 0 when the index has
+/// no generation file (in-memory, or never committed).
+pub fn index_generation() -> Result<u64> {
+    let handle = index_handle()?;
+    Ok(handle
+        .generation
+        .as_ref()
+        .map(|generation| read_generation(&generation.path))
+        .unwrap_or(0))
+}
+
+impl IndexHandle {
+    /// Dedup metadata for `identity`, from the in-memory map when this handle
+    /// keeps one and from the index otherwise.
+    fn existing(&self, identity: &str) -> Result<Option<IndexedDocument>> {
+        match &self.known {
+            Some(known) => Ok(known.read().unwrap().get(identity).cloned()),
+            None => find_existing(self, identity),
+        }
+    }
+
+    fn remember(&self, identity: String, state: IndexedDocument) {
+        if let Some(known) = &self.known {
+            known.write().unwrap().insert(identity, state);
+        }
+        if let Some(sidecar) = &self.sidecar {
+            sidecar.mark_changed();
+        }
+    }
+
+    fn forget(&self, identity: &str) {
+        if let Some(known) = &self.known {
+            known.write().unwrap().remove(identity);
+        }
+        if let Some(sidecar) = &self.sidecar {
+            sidecar.mark_changed();
+        }
+    }
+
+    fn writer(&self) -> Result<MutexGuard<'_, IndexWriter>> {
+        let writer = self
+            .writer
+            .as_ref()
+            .ok_or_else(|| anyhow!("index opened read-only"))?;
+        Ok(writer.lock().expect("index writer mutex poisoned"))
+    }
+}
+
 pub fn close() {
     let mut guard = INDEX_STATE.write().unwrap();
-    *guard = None;
+    if let Some(handle) = guard.take() {
+        if let (Some(sidecar), Some(known)) = (&handle.sidecar, &handle.known) {
+            if let Ok(metas) = handle.index.load_metas() {
+                sidecar.on_close(metas.opstamp, &known.read().unwrap());
+            }
+        }
+    }
+}
+
+/// Blocks every index lookup until the returned guard drops, so tests can
+/// simulate a search that stalls.
+#[cfg(test)]
+pub(crate) fn stall_index_for_test() -> impl Sized {
+    INDEX_STATE.write().unwrap()
 }
 
 fn index_handle() -> Result<Arc<IndexHandle>> {
@@ -236,18 +1233,49 @@ fn field(schema: &Schema, name: &str) -> Result<Field> {
         .with_context(|| format!("schema missing expected field: {}", name))
 }
 
-pub(crate) fn index() -> Result<Index> {
-    Ok(index_handle()?.index.clone())
-}
-
 pub(crate) fn reader() -> Result<IndexReader> {
     Ok(index_handle()?.reader.clone())
 }
 
+/// Settings the current index was opened with.
+pub(crate) fn settings() -> Result<IndexSettings> {
+    Ok(index_handle()?.settings)
+}
+
 pub(crate) fn fields() -> Result<IndexFields> {
     Ok(index_handle()?.fields.clone())
 }
 
+pub(crate) fn retries() -> Result<Arc<RetryQueue>> {
+    Ok(index_handle()?.retries.clone())
+}
+
+pub(crate) fn result_snapshots() -> Result<Arc<ResultSnapshots>> {
+    Ok(index_handle()?.snapshots.clone())
+}
+
+pub(crate) fn result_cache() -> Result<Arc<ResultCache>> {
+    Ok(index_handle()?.results.clone())
+}
+
+pub(crate) fn synonyms() -> Result<Arc<Synonyms>> {
+    Ok(index_handle()?.synonyms.clone())
+}
+
+pub(crate) fn plugins() -> Result<Arc<PluginRegistry>> {
+    Ok(index_handle()?.plugins.clone())
+}
+
+/// Directory of the open on-disk index, if any.
+pub(crate) fn open_dir() -> Option<PathBuf> {
+    index_handle().ok()?.dir.clone()
+}
+
+/// Files queued for another extraction attempt, soonest first.
+pub fn pending_retries() -> Result<Vec<PendingRetry>> {
+    Ok(index_handle()?.retries.pending())
+}
+
 fn extract_indexed_document(
     doc: &TantivyDocument,
     fields: &IndexFields,
@@ -268,13 +1296,44 @@ fn extract_indexed_document(
         .and_then(|value| value.as_u64())
         .ok_or_else(|| anyhow!("existing document missing size"))?;
 
-    Ok(IndexedDocument { path, mtime, size })
+    let has_content = doc
+        .get_first(fields.has_content)
+        .and_then(|value| value.as_u64())
+        .unwrap_or(0)
+        == 1;
+
+    Ok(IndexedDocument {
+        path,
+        mtime,
+        size,
+        has_content,
+    })
 }
 
 fn find_existing(handle: &IndexHandle, identity: &str) -> Result<Option<IndexedDocument>> {
     let searcher = handle.reader.searcher();
-    let term = Term::from_field_text(handle.fields.identity, identity);
-    let query = TermQuery::new(term, IndexRecordOption::Basic);
+    let Some(doc) = file_document(&searcher, &handle.fields, identity)? else {
+        return Ok(None);
+    };
+    let existing = extract_indexed_document(&doc, &handle.fields)?;
+    Ok(Some(existing))
+}
+
+/// The file's own document for `identity`, as opposed to its chunk
+/// documents.
+pub(crate) fn file_document(
+    searcher: &Searcher,
+    fields: &IndexFields,
+    identity: &str,
+) -> Result<Option<TantivyDocument>> {
+    let term = Term::from_field_text(fields.identity, identity);
+    let query = BooleanQuery::new(vec![
+        (
+            Occur::Must,
+            Box::new(TermQuery::new(term, IndexRecordOption::Basic)) as Box<dyn Query>,
+        ),
+        (Occur::Must, Box::new(own_documents(fields))),
+    ]);
     let top_docs = searcher
         .search(&query, &TopDocs::with_limit(1))
         .context("term query failed")?;
@@ -286,14 +1345,28 @@ fn find_existing(handle: &IndexHandle, identity: &str) -> Result<Option<IndexedD
     let doc: TantivyDocument = searcher
         .doc(address)
         .context("failed to fetch existing doc")?;
+    Ok(Some(doc))
+}
 
-    let existing = extract_indexed_document(&doc, &handle.fields)?;
-    Ok(Some(existing))
+/// Matches each file's own document and none of its chunk documents.
+pub(crate) fn own_documents(fields: &IndexFields) -> TermQuery {
+    TermQuery::new(
+        Term::from_field_u64(fields.chunk, 0),
+        IndexRecordOption::Basic,
+    )
 }
 
 pub fn load_index_state() -> Result<HashMap<String, IndexedDocument>> {
     let handle = index_handle()?;
-    let searcher = handle.reader.searcher();
+    read_index_state(&handle.reader, &handle.fields)
+}
+
+/// Walk every live stored document and collect its dedup metadata.
+fn read_index_state(
+    reader: &IndexReader,
+    fields: &IndexFields,
+) -> Result<HashMap<String, IndexedDocument>> {
+    let searcher = reader.searcher();
     let mut state = HashMap::new();
 
     for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
@@ -309,13 +1382,20 @@ pub fn load_index_state() -> Result<HashMap<String, IndexedDocument>> {
                 )
             })?;
 
+            if doc
+                .get_first(fields.chunk)
+                .and_then(|value| value.as_u64())
+                .is_some_and(|chunk| chunk > 0)
+            {
+                continue;
+            }
             let identity = doc
-                .get_first(handle.fields.identity)
+                .get_first(fields.identity)
                 .and_then(|value| value.as_str())
                 .ok_or_else(|| anyhow!("indexed document missing identity"))?
                 .to_string();
 
-            let metadata = extract_indexed_document(&doc, &handle.fields)?;
+            let metadata = extract_indexed_document(&doc, fields)?;
             state.insert(identity, metadata);
         }
     }
@@ -323,14 +1403,221 @@ pub fn load_index_state() -> Result<HashMap<String, IndexedDocument>> {
     Ok(state)
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct IndexStats {
+    /// Indexed files and folders; chunk documents of long files are not
+    /// counted.
+    pub num_docs: u64,
+    pub num_segments: usize,
+    /// Documents whose content was indexed.
+    pub content_docs: u64,
+    /// Documents indexed by name and metadata only.
+    pub metadata_only_docs: u64,
+    /// Whether the index was opened in global metadata-only mode.
+    pub metadata_only: bool,
+}
+
+pub fn index_stats() -> Result<IndexStats> {
+    let handle = index_handle()?;
+    let searcher = handle.reader.searcher();
+    let num_docs = searcher
+        .search(&own_documents(&handle.fields), &Count)
+        .context("failed counting documents")? as u64;
+    let content_query = BooleanQuery::new(vec![
+        (
+            Occur::Must,
+            Box::new(TermQuery::new(
+                Term::from_field_u64(handle.fields.has_content, 1),
+                IndexRecordOption::Basic,
+            )) as Box<dyn Query>,
+        ),
+        (Occur::Must, Box::new(own_documents(&handle.fields))),
+    ]);
+    let content_docs = searcher
+        .search(&content_query, &Count)
+        .context("failed counting content documents")? as u64;
+
+    Ok(IndexStats {
+        num_docs,
+        num_segments: searcher.segment_readers().len(),
+        content_docs,
+        metadata_only_docs: num_docs.saturating_sub(content_docs),
+        metadata_only: handle.settings.metadata_only,
+    })
+}
+
+/// Memory-related metrics for tuning constrained deployments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MemoryStats {
+    pub low_memory: bool,
+    pub writer_threads: usize,
+    /// Heap budget handed to the tantivy writer (shared by its threads).
+    pub writer_heap_bytes: usize,
+    /// Maximum decompressed stored-field blocks kept per segment.
+    pub doc_store_cache_blocks: usize,
+    pub doc_store_cache_entries: usize,
+    /// Estimated bytes held by the stored-field caches right now.
+    pub doc_store_cache_bytes: u64,
+    pub doc_store_cache_hits: usize,
+    pub doc_store_cache_misses: usize,
+    /// Size of the index files, which are memory-mapped and paged in lazily.
+    pub index_disk_bytes: u64,
+    /// [`IndexSettings::soft_memory_limit_bytes`]; 0 when unlimited.
+    pub soft_limit_bytes: usize,
+    /// Bytes reserved by extractions that are currently running.
+    pub extraction_in_flight_bytes: usize,
+    /// Writer heap plus in-flight extraction as a percentage of the soft
+    /// limit; 0 when unlimited.
+    pub pressure_percent: u32,
+    /// Times ingestion blocked waiting for extraction buffers to drain.
+    pub backpressure_waits: u64,
+    /// Low-priority files deferred because the limit was reached.
+    pub shed_files: u64,
+}
+
+pub fn memory_stats() -> Result<MemoryStats> {
+    let handle = index_handle()?;
+    let searcher = handle.reader.searcher();
+    let cache = searcher.doc_store_cache_stats();
+    let index_disk_bytes = searcher
+        .space_usage()
+        .context("failed computing index space usage")?
+        .total()
+        .get_bytes();
+    let pressure = pressure::snapshot();
+
+    Ok(MemoryStats {
+        low_memory: handle.settings.low_memory,
+        writer_threads: handle.budget.writer_threads,
+        writer_heap_bytes: handle.budget.writer_heap_bytes,
+        doc_store_cache_blocks: handle.budget.doc_store_cache_blocks,
+        doc_store_cache_entries: cache.num_entries,
+        doc_store_cache_bytes: cache.num_entries as u64 * DOC_STORE_BLOCK_BYTES,
+        doc_store_cache_hits: cache.cache_hits,
+        doc_store_cache_misses: cache.cache_misses,
+        index_disk_bytes,
+        soft_limit_bytes: pressure.limit_bytes,
+        extraction_in_flight_bytes: pressure.in_flight_bytes,
+        pressure_percent: pressure.percent,
+        backpressure_waits: pressure.waits,
+        shed_files: pressure.shed,
+    })
+}
+
+/// Bytes used by one segment, split by index component.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SegmentSize {
+    pub segment_id: String,
+    pub num_docs: u32,
+    pub termdict_bytes: u64,
+    pub postings_bytes: u64,
+    pub positions_bytes: u64,
+    pub fast_field_bytes: u64,
+    pub fieldnorm_bytes: u64,
+    /// Compressed stored-field blocks (paths, names, summaries).
+    pub store_bytes: u64,
+    pub deletes_bytes: u64,
+    pub total_bytes: u64,
+}
+
+/// Bytes attributed to one schema field, summed over all segments. Stored
+/// fields are compressed together per document block, so they only appear in
+/// [`SegmentSize::store_bytes`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FieldSize {
+    pub field: String,
+    pub termdict_bytes: u64,
+    pub postings_bytes: u64,
+    pub positions_bytes: u64,
+    pub fast_field_bytes: u64,
+    pub fieldnorm_bytes: u64,
+    pub total_bytes: u64,
+}
+
+/// Where the index's bytes go, for judging what new fields cost.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SizeBreakdown {
+    pub total_bytes: u64,
+    pub segments: Vec<SegmentSize>,
+    /// Sorted largest first.
+    pub fields: Vec<FieldSize>,
+}
+
+/// Picks the [`FieldSize`] counter a per-field component adds to.
+type FieldSlot = fn(&mut FieldSize) -> &mut u64;
+
+pub fn index_size_breakdown() -> Result<SizeBreakdown> {
+    let handle = index_handle()?;
+    let searcher = handle.reader.searcher();
+    let schema = handle.index.schema();
+    let mut fields: HashMap<Field, FieldSize> = HashMap::new();
+    let mut breakdown = SizeBreakdown::default();
+
+    for segment_reader in searcher.segment_readers() {
+        let usage = segment_reader
+            .space_usage()
+            .context("failed computing segment space usage")?;
+
+        let components: [(&PerFieldSpaceUsage, FieldSlot); 5] = [
+            (usage.termdict(), |size| &mut size.termdict_bytes),
+            (usage.postings(), |size| &mut size.postings_bytes),
+            (usage.positions(), |size| &mut size.positions_bytes),
+            (usage.fast_fields(), |size| &mut size.fast_field_bytes),
+            (usage.fieldnorms(), |size| &mut size.fieldnorm_bytes),
+        ];
+        for (per_field, slot) in components {
+            for (field, field_usage) in per_field.fields() {
+                let entry = fields.entry(*field).or_insert_with(|| FieldSize {
+                    field: schema.get_field_name(*field).to_string(),
+                    ..FieldSize::default()
+                });
+                let bytes = field_usage.total().get_bytes();
+                *slot(entry) += bytes;
+                entry.total_bytes += bytes;
+            }
+        }
+
+        let segment = SegmentSize {
+            segment_id: segment_reader.segment_id().uuid_string(),
+            num_docs: usage.num_docs(),
+            termdict_bytes: usage.termdict().total().get_bytes(),
+            postings_bytes: usage.postings().total().get_bytes(),
+            positions_bytes: usage.positions().total().get_bytes(),
+            fast_field_bytes: usage.fast_fields().total().get_bytes(),
+            fieldnorm_bytes: usage.fieldnorms().total().get_bytes(),
+            store_bytes: usage.store().total().get_bytes(),
+            deletes_bytes: usage.deletes().get_bytes(),
+            total_bytes: usage.total().get_bytes(),
+        };
+        breakdown.total_bytes += segment.total_bytes;
+        breakdown.segments.push(segment);
+    }
+
+    breakdown.fields = fields.into_values().collect();
+    breakdown.fields.sort_by(|a, b| {
+        b.total_bytes
+            .cmp(&a.total_bytes)
+            .then_with(|| a.field.cmp(&b.field))
+    });
+    Ok(breakdown)
+}
+
 fn current_settings() -> IndexSettings {
     *INDEX_SETTINGS.read().unwrap()
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{add_or_update_file, commit, init_index, IndexUpdate};
+    use super::{
+        add_or_update_file, add_or_update_file_streaming, commit, configure, folding_analyzer,
+        identifier_analyzer, init_index, init_index_read_only, name_prefix_analyzer,
+        refresh_if_changed, ContentAnalyzer, GenerationFile, IndexSettings, IndexUpdate,
+        MIN_WRITER_MEM_BYTES, NAME_PARTS_TOKENIZER, NAME_PREFIX_TOKENIZER,
+    };
+    use crate::query::{search, SearchDomain, SearchQuery};
     use crate::scanner::FileMeta;
+    use tantivy::schema::TantivyDocument;
+    use tantivy::{Index, IndexWriter};
     use tempfile::tempdir;
 
     #[test]
@@ -347,6 +1634,10 @@ mod tests {
             size: 42,
             inode: 1,
             dev: 1,
+            alias_target: None,
+            bundle: false,
+            display_name: None,
+            children: None,
         };
 
         assert!(matches!(
@@ -360,4 +1651,577 @@ mod tests {
         let searcher = reader.searcher();
         assert_eq!(searcher.num_docs(), 1);
     }
+
+    #[test]
+    fn indexes_extracted_entities_when_enabled() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        configure(IndexSettings {
+            extract_entities: true,
+            ..IndexSettings::default()
+        });
+        let dir = tempdir().unwrap();
+        let result = init_index(dir.path().to_str().unwrap());
+        configure(IndexSettings::default());
+        result.unwrap();
+
+        let meta = FileMeta {
+            path: dir
+                .path()
+                .join("contract.txt")
+                .to_string_lossy()
+                .to_string(),
+            name: "contract.txt".into(),
+            ext: Some("txt".into()),
+            modified_at: 123,
+            size: 42,
+            inode: 2,
+            dev: 1,
+            alias_target: None,
+            bundle: false,
+            display_name: None,
+            children: None,
+        };
+        add_or_update_file(
+            meta,
+            Some("Send to legal@acme.com before 2023-07-14.".into()),
+            false,
+        )
+        .unwrap();
+        commit().unwrap();
+
+        for term in ["email:@acme.com", "mentions_date:2023-07"] {
+            let hits = search(SearchQuery {
+                term: term.into(),
+                search_in: SearchDomain::Content,
+                path_globs: Vec::new(),
+                limit: 10,
+                ..SearchQuery::default()
+            })
+            .unwrap();
+            assert_eq!(hits.len(), 1, "expected a hit for {term}");
+        }
+    }
+
+    #[test]
+    fn streaming_adds_content_in_chunks_and_skips_unchanged() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+
+        let meta = FileMeta {
+            path: dir.path().join("big.log").to_string_lossy().to_string(),
+            name: "big.log".into(),
+            ext: Some("log".into()),
+            modified_at: 7,
+            size: 1024,
+            inode: 3,
+            dev: 1,
+            alias_target: None,
+            bundle: false,
+            display_name: None,
+            children: None,
+        };
+
+        let update = add_or_update_file_streaming(meta.clone(), false, |sink| {
+            sink.push("first chunk mentions alpha ");
+            sink.push("second chunk mentions omega");
+            assert_eq!(sink.bytes(), 54);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(update, IndexUpdate::Added);
+        commit().unwrap();
+
+        for term in ["alpha", "omega"] {
+            let hits = search(SearchQuery {
+                term: term.into(),
+                search_in: SearchDomain::Content,
+                path_globs: Vec::new(),
+                limit: 10,
+                ..SearchQuery::default()
+            })
+            .unwrap();
+            assert_eq!(hits.len(), 1, "expected a hit for {term}");
+        }
+
+        let update = add_or_update_file_streaming(meta, false, |_| {
+            panic!("unchanged files must not be read")
+        })
+        .unwrap();
+        assert_eq!(update, IndexUpdate::Skipped);
+    }
+
+    #[test]
+    fn in_memory_index_supports_the_same_api() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        super::init_index_in_memory().unwrap();
+
+        let meta = FileMeta {
+            path: "/downloads/boarding-pass.pdf".into(),
+            name: "boarding-pass.pdf".into(),
+            ext: Some("pdf".into()),
+            modified_at: 1,
+            size: 10,
+            inode: 9,
+            dev: 9,
+            alias_target: None,
+            bundle: false,
+            display_name: None,
+            children: None,
+        };
+        add_or_update_file(meta.clone(), Some("gate B12".into()), false).unwrap();
+        commit().unwrap();
+
+        let hits = search(SearchQuery {
+            term: "boarding".into(),
+            search_in: SearchDomain::Name,
+            path_globs: Vec::new(),
+            limit: 10,
+            ..SearchQuery::default()
+        })
+        .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert!(!super::should_reindex(&meta).unwrap());
+        super::close();
+    }
+
+    #[test]
+    fn low_memory_mode_caps_writer_and_cache() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        configure(IndexSettings {
+            low_memory: true,
+            ..IndexSettings::default()
+        });
+        let opened = init_index(dir.path().to_str().unwrap());
+        configure(IndexSettings::default());
+        opened.unwrap();
+
+        let meta = FileMeta {
+            path: dir.path().join("nas.txt").to_string_lossy().to_string(),
+            name: "nas.txt".into(),
+            ext: Some("txt".into()),
+            modified_at: 5,
+            size: 12,
+            inode: 4,
+            dev: 1,
+            alias_target: None,
+            bundle: false,
+            display_name: None,
+            children: None,
+        };
+        add_or_update_file(meta, Some("raid array status".into()), false).unwrap();
+        commit().unwrap();
+
+        let hits = search(SearchQuery {
+            term: "raid".into(),
+            search_in: SearchDomain::Content,
+            path_globs: Vec::new(),
+            limit: 10,
+            ..SearchQuery::default()
+        })
+        .unwrap();
+        assert_eq!(hits.len(), 1);
+
+        let stats = super::memory_stats().unwrap();
+        assert!(stats.low_memory);
+        assert_eq!(stats.writer_threads, 1);
+        assert_eq!(stats.writer_heap_bytes, 16 * 1024 * 1024);
+        assert_eq!(stats.doc_store_cache_blocks, 4);
+        assert!(stats.doc_store_cache_entries <= 4);
+        assert!(stats.index_disk_bytes > 0);
+    }
+
+    #[test]
+    fn content_analyzer_is_recorded_in_the_index() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        configure(IndexSettings {
+            content_analyzer: ContentAnalyzer::English,
+            ..IndexSettings::default()
+        });
+        let opened = init_index(path);
+        configure(IndexSettings::default());
+        opened.unwrap();
+
+        let meta = FileMeta {
+            path: dir.path().join("log.txt").to_string_lossy().to_string(),
+            name: "log.txt".into(),
+            ext: Some("txt".into()),
+            modified_at: 5,
+            size: 12,
+            inode: 4,
+            dev: 1,
+            alias_target: None,
+            bundle: false,
+            display_name: None,
+            children: None,
+        };
+        add_or_update_file(meta.clone(), Some("running two invoices".into()), false).unwrap();
+        commit().unwrap();
+        let content_hits = |term: &str| {
+            search(SearchQuery {
+                term: term.into(),
+                search_in: SearchDomain::Content,
+                ..SearchQuery::default()
+            })
+            .unwrap()
+            .len()
+        };
+        assert_eq!(content_hits("run"), 1);
+        assert_eq!(content_hits("\"invoice\""), 1);
+
+        // Reopened under the plain default, the index keeps stemming.
+        super::close();
+        init_index(path).unwrap();
+        assert_eq!(
+            super::settings().unwrap().content_analyzer,
+            ContentAnalyzer::English
+        );
+        assert_eq!(content_hits("invoice"), 1);
+
+        let plain = tempdir().unwrap();
+        init_index(plain.path().to_str().unwrap()).unwrap();
+        add_or_update_file(
+            FileMeta {
+                path: plain.path().join("log.txt").to_string_lossy().to_string(),
+                ..meta
+            },
+            Some("running two invoices".into()),
+            false,
+        )
+        .unwrap();
+        commit().unwrap();
+        assert_eq!(content_hits("run"), 0);
+    }
+
+    #[test]
+    fn read_only_handle_reloads_when_writer_bumps_generation() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        let index_dir = dir.path().to_str().unwrap();
+        let meta = |name: &str, inode: u64| FileMeta {
+            path: dir.path().join(name).to_string_lossy().to_string(),
+            name: name.into(),
+            ext: Some("txt".into()),
+            modified_at: 1,
+            size: 4,
+            inode,
+            dev: 1,
+            alias_target: None,
+            bundle: false,
+            display_name: None,
+            children: None,
+        };
+        let count = || {
+            search(SearchQuery {
+                term: "shared".into(),
+                search_in: SearchDomain::Content,
+                path_globs: Vec::new(),
+                limit: 10,
+                ..SearchQuery::default()
+            })
+            .unwrap()
+            .len()
+        };
+
+        init_index(index_dir).unwrap();
+        add_or_update_file(meta("one.txt", 1), Some("shared".into()), false).unwrap();
+        commit().unwrap();
+        assert_eq!(super::index_generation().unwrap(), 1);
+        super::close();
+
+        init_index_read_only(index_dir).unwrap();
+        assert_eq!(count(), 1);
+        assert!(!refresh_if_changed().unwrap());
+        assert!(add_or_update_file(meta("two.txt", 2), Some("shared".into()), false).is_err());
+
+        // Play the helper process: write through a separate writer, then
+        // publish the next generation the way `commit` does.
+        let index = Index::open_in_dir(dir.path()).unwrap();
+        index
+            .tokenizers()
+            .register(NAME_PREFIX_TOKENIZER, name_prefix_analyzer());
+        index
+            .tokenizers()
+            .register(NAME_PARTS_TOKENIZER, identifier_analyzer());
+        index.tokenizers().register("default", folding_analyzer());
+        let fields = super::fields().unwrap();
+        let mut writer: IndexWriter = index.writer(MIN_WRITER_MEM_BYTES).unwrap();
+        let mut doc = TantivyDocument::new();
+        doc.add_text(fields.path, "two.txt");
+        doc.add_text(fields.content, "shared");
+        writer.add_document(doc).unwrap();
+        writer.commit().unwrap();
+        GenerationFile::new(dir.path()).bump().unwrap();
+
+        assert_eq!(count(), 1);
+        assert!(refresh_if_changed().unwrap());
+        assert_eq!(count(), 2);
+        assert!(!refresh_if_changed().unwrap());
+        super::close();
+    }
+
+    #[test]
+    fn size_breakdown_reports_fields_and_segments() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        let meta = FileMeta {
+            path: dir.path().join("big.txt").to_string_lossy().to_string(),
+            name: "big.txt".into(),
+            ext: Some("txt".into()),
+            modified_at: 1,
+            size: 10,
+            inode: 9,
+            dev: 1,
+            alias_target: None,
+            bundle: false,
+            display_name: None,
+            children: None,
+        };
+        add_or_update_file(meta, Some("lorem ipsum dolor sit amet ".repeat(50)), false).unwrap();
+        commit().unwrap();
+
+        let breakdown = super::index_size_breakdown().unwrap();
+        assert_eq!(breakdown.segments.len(), 1);
+        assert_eq!(breakdown.segments[0].num_docs, 1);
+        assert!(breakdown.segments[0].store_bytes > 0);
+        let content = breakdown
+            .fields
+            .iter()
+            .find(|field| field.field == "content")
+            .unwrap();
+        assert!(content.postings_bytes > 0);
+        assert!(content.positions_bytes > 0);
+        let mtime = breakdown
+            .fields
+            .iter()
+            .find(|field| field.field == "mtime")
+            .unwrap();
+        assert!(mtime.fast_field_bytes > 0);
+        let per_field: u64 = breakdown.fields.iter().map(|field| field.total_bytes).sum();
+        assert!(per_field <= breakdown.total_bytes);
+    }
+
+    #[test]
+    fn batched_updates_dedup_and_replace_in_one_pass() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        let meta = |i: u64, mtime: i64| FileMeta {
+            path: format!("/burst/file-{i}.txt"),
+            name: format!("file-{i}.txt"),
+            ext: Some("txt".into()),
+            modified_at: mtime,
+            size: 5,
+            inode: 100 + i,
+            dev: 1,
+            alias_target: None,
+            bundle: false,
+            display_name: None,
+            children: None,
+        };
+
+        let first: Vec<_> = (0..50)
+            .map(|i| (meta(i, 1), Some("original".to_string())))
+            .collect();
+        let updates = super::add_or_update_batch(first, false).unwrap();
+        assert!(updates.iter().all(|update| *update == IndexUpdate::Added));
+        commit().unwrap();
+
+        // Touch half the files; the rest are unchanged and skipped.
+        let burst: Vec<_> = (0..50)
+            .map(|i| {
+                let mtime = if i % 2 == 0 { 2 } else { 1 };
+                (meta(i, mtime), Some("revised".to_string()))
+            })
+            .collect();
+        let updates = super::add_or_update_batch(burst, false).unwrap();
+        commit().unwrap();
+        assert_eq!(
+            updates
+                .iter()
+                .filter(|update| **update == IndexUpdate::Updated)
+                .count(),
+            25
+        );
+        assert_eq!(
+            updates
+                .iter()
+                .filter(|update| **update == IndexUpdate::Skipped)
+                .count(),
+            25
+        );
+
+        let count = |term: &str| {
+            search(SearchQuery {
+                term: term.into(),
+                search_in: SearchDomain::Content,
+                path_globs: Vec::new(),
+                limit: 100,
+                ..SearchQuery::default()
+            })
+            .unwrap()
+            .len()
+        };
+        assert_eq!(count("revised"), 25);
+        assert_eq!(count("original"), 25);
+        assert_eq!(super::index_stats().unwrap().num_docs, 50);
+    }
+
+    #[test]
+    fn identity_map_dedups_uncommitted_and_reopened_documents() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        let index_dir = dir.path().to_str().unwrap();
+        init_index(index_dir).unwrap();
+        let meta = FileMeta {
+            path: "/docs/plan.txt".into(),
+            name: "plan.txt".into(),
+            ext: Some("txt".into()),
+            modified_at: 10,
+            size: 4,
+            inode: 77,
+            dev: 1,
+            alias_target: None,
+            bundle: false,
+            display_name: None,
+            children: None,
+        };
+
+        let first = add_or_update_file(meta.clone(), Some("plan".into()), false).unwrap();
+        assert_eq!(first, IndexUpdate::Added);
+        // Not committed yet, but the map already knows about it.
+        assert!(!super::should_reindex(&meta).unwrap());
+        let again = add_or_update_file(meta.clone(), Some("plan".into()), false).unwrap();
+        assert_eq!(again, IndexUpdate::Skipped);
+        commit().unwrap();
+        super::close();
+
+        init_index(index_dir).unwrap();
+        let reopened = add_or_update_file(meta.clone(), Some("plan".into()), false).unwrap();
+        assert_eq!(reopened, IndexUpdate::Skipped);
+        let touched = FileMeta {
+            modified_at: 11,
+            ..meta
+        };
+        assert!(super::should_reindex(&touched).unwrap());
+        super::close();
+    }
+
+    #[test]
+    fn startup_trusts_a_current_sidecar_and_rebuilds_a_stale_one() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        let index_dir = dir.path().to_str().unwrap();
+        let meta = |inode: u64| FileMeta {
+            path: format!("/docs/{inode}.txt"),
+            name: format!("{inode}.txt"),
+            modified_at: 1,
+            size: 1,
+            inode,
+            dev: 1,
+            ..FileMeta::default()
+        };
+
+        init_index(index_dir).unwrap();
+        add_or_update_file(meta(1), None, false).unwrap();
+        commit().unwrap();
+        super::close();
+        let sidecar = crate::sidecar::IdentitySidecar::new(dir.path());
+        let opstamp = tantivy::Index::open_in_dir(dir.path())
+            .unwrap()
+            .load_metas()
+            .unwrap()
+            .opstamp;
+        let mut map = sidecar.load(opstamp).expect("sidecar saved at commit");
+        assert_eq!(map.len(), 1);
+
+        // A planted entry proves startup read the sidecar, not the index.
+        map.insert(
+            meta(2).identity(),
+            super::IndexedDocument::from_meta(&meta(2)),
+        );
+        sidecar.save(opstamp, &map).unwrap();
+        init_index(index_dir).unwrap();
+        assert!(!super::should_reindex(&meta(2)).unwrap());
+        super::close();
+
+        sidecar.save(opstamp + 1, &map).unwrap();
+        init_index(index_dir).unwrap();
+        assert!(super::should_reindex(&meta(2)).unwrap());
+        assert!(!super::should_reindex(&meta(1)).unwrap());
+        super::close();
+    }
+
+    #[test]
+    fn rebuilds_an_index_written_with_the_baseline_schema() {
+        use tantivy::schema::{NumericOptions, SchemaBuilder, STORED, STRING, TEXT};
+
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        let index_dir = dir.path().to_str().unwrap();
+
+        // The schema the first release wrote, before any version marker.
+        let mut builder = SchemaBuilder::default();
+        let path = builder.add_text_field("path", STRING | STORED);
+        builder.add_text_field("name", TEXT | STORED);
+        builder.add_text_field("name_raw", STRING | STORED);
+        builder.add_text_field("ext", STRING);
+        builder.add_text_field("identity", STRING | STORED);
+        let stored_fast = NumericOptions::default().set_stored().set_fast();
+        builder.add_i64_field("mtime", stored_fast.clone());
+        builder.add_u64_field("size", stored_fast);
+        builder.add_u64_field("inode", NumericOptions::default().set_stored());
+        builder.add_u64_field("dev", NumericOptions::default().set_stored());
+        let content = builder.add_text_field("content", TEXT);
+        let baseline = Index::create_in_dir(dir.path(), builder.build()).unwrap();
+        let mut writer: IndexWriter = baseline.writer(MIN_WRITER_MEM_BYTES).unwrap();
+        let mut doc = TantivyDocument::new();
+        doc.add_text(path, "/docs/old.txt");
+        doc.add_text(content, "stale");
+        writer.add_document(doc).unwrap();
+        writer.commit().unwrap();
+        drop(writer);
+        drop(baseline);
+        std::fs::write(
+            dir.path().join(crate::SYNONYMS_FILE_NAME),
+            "car, automobile\n",
+        )
+        .unwrap();
+
+        // A reader can't rebuild, so it asks for the writer to open it first.
+        assert!(init_index_read_only(index_dir).is_err());
+
+        init_index(index_dir).unwrap();
+        assert_eq!(super::index_stats().unwrap().num_docs, 0);
+        let marker = dir.path().join(crate::SCHEMA_VERSION_FILE_NAME);
+        assert_eq!(
+            std::fs::read_to_string(&marker).unwrap(),
+            crate::SCHEMA_VERSION.to_string()
+        );
+        assert!(dir.path().join(crate::SYNONYMS_FILE_NAME).exists());
+        let fresh = FileMeta {
+            path: "/docs/new.txt".into(),
+            name: "new.txt".into(),
+            modified_at: 1,
+            size: 1,
+            ..FileMeta::default()
+        };
+        add_or_update_file(fresh, Some("fresh".into()), false).unwrap();
+        commit().unwrap();
+        super::close();
+
+        // Reopening keeps the rebuilt index.
+        init_index(index_dir).unwrap();
+        assert_eq!(super::index_stats().unwrap().content_docs, 1);
+        super::close();
+
+        // An index from a newer build is refused, not dropped.
+        std::fs::write(&marker, (crate::SCHEMA_VERSION + 1).to_string()).unwrap();
+        assert!(init_index(index_dir).is_err());
+        assert!(init_index_read_only(index_dir).is_err());
+    }
 }
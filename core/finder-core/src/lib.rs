@@ -1,16 +1,146 @@
+mod actions;
+mod alias;
+mod bootstrap;
+mod cancel;
+mod corpus;
+mod coverage;
+mod data_dir;
+mod dates;
+mod diff;
+mod entities;
+mod eval;
+mod events;
+mod export;
 mod extract_plain;
+mod extract_structured;
+mod extract_table;
+mod facets;
 pub mod ffi;
+mod generated;
+mod git;
 mod indexer;
+mod locations;
+mod min_match;
+mod normalize;
+mod pathfilter;
+mod pipeline;
+mod plist;
+mod plugins;
+mod preserve;
+mod pressure;
+mod profiles;
 mod query;
+mod query_builder;
+mod report;
+mod retry;
+mod rewrite;
+mod roots;
 mod scanner;
 mod schema;
+mod session;
+mod sha256;
+mod sidecar;
+mod sizes;
+mod soak;
+mod standing;
+mod stats;
+mod stopwords;
+mod summary;
+mod synonyms;
+mod term_filters;
+mod textnorm;
+mod truncate;
+mod validate;
+mod warmup;
+mod wildcards;
+mod worker;
 
-pub use crate::query::{SearchDomain, SearchHit, SearchQuery};
-pub use crate::scanner::{scan_root, FileMeta};
-pub use crate::schema::build_schema;
-pub use extract_plain::{read_plain_text, PlainTextExtraction};
+pub use crate::actions::{forget_removed, move_to_trash, reveal_target};
+pub use crate::alias::resolve_alias;
+pub use crate::bootstrap::{
+    bootstrap_roots, BootstrapOptions, BootstrapPhase, BootstrapProgress, BootstrapReport,
+};
+pub use crate::cancel::CancelToken;
+pub use crate::corpus::{generate_corpus, CorpusKind, CorpusReport, CorpusSpec};
+pub use crate::coverage::{extraction_coverage, reset_extraction_coverage, ExtensionCoverage};
+pub use crate::data_dir::{default_index_dir, LEGACY_INDEX_DIR};
+pub use crate::dates::{set_date_locale, DateLocale};
+pub use crate::diff::{
+    diff_hits, diff_results, snapshot_results, ResultsDiff, RESULT_SNAPSHOTS_FILE_NAME,
+};
+pub use crate::entities::{extract_entities, ExtractedEntities};
+pub use crate::eval::{evaluate, load_judgments, parse_judgments, EvalReport, Judgment, QueryEval};
+pub use crate::events::{
+    add_index_observer, remove_index_observer, set_index_size_alert, IndexEvent, IndexObserver,
+};
+pub use crate::export::{export_hits, ExportConflict, ExportMode, ExportProgress, ExportReport};
+pub use crate::facets::{
+    count_by_extension, count_by_extension_approx, count_by_folder, counts_by_root, ExtensionCount,
+    FacetCounts, FolderCount, FolderLevel, RootCount,
+};
+pub use crate::generated::GeneratedRules;
+pub use crate::locations::{suggest_locations, LocationSuggestion};
+pub use crate::normalize::{normalize_content, NormalizeOptions};
+pub use crate::pathfilter::{PathFilter, PathFilterEngine};
+pub use crate::pipeline::{
+    index_root, index_roots, reindex_path, reindex_subtree, IndexReport, PathReindex, RootOptions,
+};
+pub use crate::plugins::{
+    extractor_plugins, set_extractor_plugins, ExtractorPlugin, PLUGINS_FILE_NAME,
+};
+pub use crate::preserve::{
+    preserve, verify_preservation, Preservation, PreservedFile, PRESERVE_MANIFEST_FILE_NAME,
+};
+pub use crate::profiles::{create_index, delete_index, list_indexes, IndexProfile};
+pub use crate::query::{
+    count, refine, search_cancellable, search_page, search_page_cancellable, search_prefix,
+    search_two_tier, DefaultOperator, EntryKind, HitSnippet, MinShouldMatch, PathPenalty,
+    PriorityFolder, QueryLimit, QueryLimitExceeded, QueryLimits, QueryMode, RankingConfig,
+    RankingProfile, RecencyBoost, SearchDomain, SearchHit, SearchPage, SearchQuery, SearchWave,
+    ShouldMatch, SortBy,
+};
+pub use crate::query_builder::{Match, QueryBuilder};
+pub use crate::report::{save_report, write_report, ReportFormat};
+pub use crate::retry::{PendingRetry, RETRY_QUEUE_FILE_NAME};
+pub use crate::rewrite::{set_query_rewriter, QueryMacros, QueryRewriter};
+pub use crate::roots::{normalize_roots, suggest_roots, NormalizedRoots, RootKind, RootSuggestion};
+pub use crate::scanner::{
+    noise_checksum, scan_root, scan_root_with_rules, FileMeta, NoiseRules, ScanReport,
+};
+pub use crate::schema::{
+    build_schema, build_schema_with, ContentAnalyzer, SCHEMA_VERSION, SCHEMA_VERSION_FILE_NAME,
+};
+pub use crate::session::{search_stream, SearchSession, SearchStream};
+pub use crate::sidecar::IDENTITY_MAP_FILE_NAME;
+pub use crate::soak::{run_soak, SoakConfig, SoakReport};
+pub use crate::standing::{add_standing_query, remove_standing_query, standing_queries};
+pub use crate::stats::{quick_stats, QuickStats};
+pub use crate::stopwords::{set_stop_words, STOP_WORDS_FILE_NAME};
+pub use crate::summary::{set_summarizer, LeadSentences, Summarizer, SUMMARY_SOURCE_BYTES};
+pub use crate::synonyms::{set_synonyms, synonym_groups, SYNONYMS_FILE_NAME};
+pub use crate::textnorm::{fold_for_search, to_nfc};
+pub use crate::truncate::truncate_graphemes;
+pub use crate::validate::{
+    validate_query, ClauseKind, ClauseRequirement, ParsedQueryDescription, QueryClause,
+    QuerySyntaxError,
+};
+pub use crate::warmup::{warm_up, WarmUpReport};
+pub use crate::worker::{run_extract_worker_if_requested, ExtractWorker, EXTRACT_WORKER_ARG};
+pub use extract_plain::{
+    looks_binary, read_plain_text, read_plain_text_with, sniff, stream_plain_text,
+    PlainTextExtraction, SniffConfig, SniffDecision, StreamedExtraction, TextEncoding,
+};
+pub use extract_structured::{
+    flatten_structured, read_structured_text, StructuredExtraction, StructuredFormat,
+};
+pub use extract_table::{
+    read_table_sample, TableExtraction, TableFormat, DEFAULT_TABLE_SAMPLE_ROWS,
+};
 pub use indexer::{
-    configure as configure_indexer, load_index_state, IndexSettings, IndexUpdate, IndexedDocument,
+    configure as configure_indexer, index_generation, index_size_breakdown, index_stats,
+    load_index_state, memory_stats, pending_retries, ContentSink, FieldSize, IndexSettings,
+    IndexStats, IndexUpdate, IndexedDocument, MemoryStats, SegmentSize, SizeBreakdown,
+    GENERATION_FILE_NAME,
 };
 
 #[cfg(test)]
@@ -27,6 +157,14 @@ pub fn init_index(path: &str) -> Result<()> {
     indexer::init_index(path)
 }
 
+pub fn init_index_in_memory() -> Result<()> {
+    indexer::init_index_in_memory()
+}
+
+pub fn init_index_read_only(path: &str) -> Result<()> {
+    indexer::init_index_read_only(path)
+}
+
 pub fn add_or_update_file(
     meta: FileMeta,
     content_opt: Option<String>,
@@ -35,6 +173,24 @@ pub fn add_or_update_file(
     indexer::add_or_update_file(meta, content_opt, force_reindex)
 }
 
+pub fn add_or_update_batch(
+    files: Vec<(FileMeta, Option<String>)>,
+    force_reindex: bool,
+) -> Result<Vec<IndexUpdate>> {
+    indexer::add_or_update_batch(files, force_reindex)
+}
+
+pub fn add_or_update_file_streaming<F>(
+    meta: FileMeta,
+    force_reindex: bool,
+    feed: F,
+) -> Result<IndexUpdate>
+where
+    F: FnOnce(&mut ContentSink<'_>) -> Result<()>,
+{
+    indexer::add_or_update_file_streaming(meta, force_reindex, feed)
+}
+
 pub fn should_reindex(meta: &FileMeta) -> Result<bool> {
     indexer::should_reindex(meta)
 }
@@ -43,6 +199,10 @@ pub fn commit() -> Result<()> {
     indexer::commit()
 }
 
+pub fn refresh_if_changed() -> Result<bool> {
+    indexer::refresh_if_changed()
+}
+
 pub fn close_index() {
     indexer::close()
 }
@@ -0,0 +1,130 @@
+use crate::indexer;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, MAIN_SEPARATOR};
+
+/// An indexed folder offered by [`suggest_locations`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocationSuggestion {
+    pub path: String,
+    /// Indexed files somewhere below the folder. Counts can include recently
+    /// replaced documents until segments merge.
+    pub file_count: u64,
+}
+
+/// Complete `prefix` against folders that contain indexed files, without
+/// touching the filesystem. A prefix starting with a path separator matches
+/// full paths (`/Users/me/Doc` → `/Users/me/Documents`); anything else
+/// matches folder names case-insensitively (`proj` → `.../Projects`).
+/// Folders holding the most files come first.
+pub fn suggest_locations(prefix: &str, limit: usize) -> Result<Vec<LocationSuggestion>> {
+    let prefix = prefix.trim();
+    if prefix.is_empty() || limit == 0 {
+        return Ok(Vec::new());
+    }
+
+    let reader = indexer::reader()?;
+    let fields = indexer::fields()?;
+    let searcher = reader.searcher();
+    let by_path = prefix.starts_with(MAIN_SEPARATOR) || prefix.starts_with('/');
+    let name_prefix = prefix.to_lowercase();
+
+    let mut counts: HashMap<String, u64> = HashMap::new();
+    for segment_reader in searcher.segment_readers() {
+        let inverted = segment_reader
+            .inverted_index(fields.dir)
+            .context("failed opening folder terms")?;
+        let terms = inverted.terms();
+        let mut stream = if by_path {
+            terms.range().ge(prefix.as_bytes()).into_stream()?
+        } else {
+            terms.stream()?
+        };
+        while stream.advance() {
+            let Ok(dir) = std::str::from_utf8(stream.key()) else {
+                continue;
+            };
+            if by_path {
+                if !dir.starts_with(prefix) {
+                    break;
+                }
+            } else if !folder_name(dir).starts_with(&name_prefix) {
+                continue;
+            }
+            *counts.entry(dir.to_string()).or_default() += u64::from(stream.value().doc_freq);
+        }
+    }
+
+    let mut suggestions: Vec<LocationSuggestion> = counts
+        .into_iter()
+        .map(|(path, file_count)| LocationSuggestion { path, file_count })
+        .collect();
+    suggestions.sort_by(|a, b| {
+        b.file_count
+            .cmp(&a.file_count)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    suggestions.truncate(limit);
+    Ok(suggestions)
+}
+
+fn folder_name(dir: &str) -> String {
+    Path::new(dir)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::suggest_locations;
+    use crate::scanner::FileMeta;
+    use crate::{add_or_update_file, commit, init_index};
+    use tempfile::tempdir;
+
+    #[test]
+    fn completes_folder_paths_and_names() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        for (inode, path) in [
+            "/home/me/Documents/tax/2023.pdf",
+            "/home/me/Documents/tax/2024.pdf",
+            "/home/me/Documents/letter.txt",
+            "/home/me/Downloads/setup.dmg",
+            "/srv/projects/site/index.html",
+        ]
+        .iter()
+        .enumerate()
+        {
+            let meta = FileMeta {
+                path: path.to_string(),
+                name: path.rsplit('/').next().unwrap().to_string(),
+                inode: inode as u64 + 1,
+                dev: 1,
+                ..FileMeta::default()
+            };
+            add_or_update_file(meta, None, false).unwrap();
+        }
+        commit().unwrap();
+
+        let by_path = suggest_locations("/home/me/Do", 10).unwrap();
+        let paths: Vec<_> = by_path.iter().map(|s| s.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "/home/me/Documents",
+                "/home/me/Documents/tax",
+                "/home/me/Downloads"
+            ]
+        );
+        assert_eq!(by_path[0].file_count, 3);
+
+        let by_name = suggest_locations("PROJ", 10).unwrap();
+        assert_eq!(by_name.len(), 1);
+        assert_eq!(by_name[0].path, "/srv/projects");
+
+        assert_eq!(suggest_locations("/home/me/D", 1).unwrap().len(), 1);
+        assert!(suggest_locations("  ", 10).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,149 @@
+use tantivy::query::{EnableScoring, Explanation, Query, Scorer, Weight};
+use tantivy::{DocId, DocSet, Score, SegmentReader, TantivyError, Term, TERMINATED};
+
+/// Documents matching at least `minimum` of `clauses`, scored by the sum of
+/// the clauses they match. Tantivy's `BooleanQuery` has no such count
+/// between "all" and "any".
+#[derive(Debug)]
+pub(crate) struct MinimumMatchQuery {
+    clauses: Vec<Box<dyn Query>>,
+    minimum: usize,
+}
+
+impl MinimumMatchQuery {
+    /// `minimum` is held to `1..=clauses.len()`.
+    pub fn new(clauses: Vec<Box<dyn Query>>, minimum: usize) -> Self {
+        let minimum = minimum.clamp(1, clauses.len().max(1));
+        Self { clauses, minimum }
+    }
+}
+
+impl Clone for MinimumMatchQuery {
+    fn clone(&self) -> Self {
+        Self {
+            clauses: self
+                .clauses
+                .iter()
+                .map(|clause| clause.box_clone())
+                .collect(),
+            minimum: self.minimum,
+        }
+    }
+}
+
+impl Query for MinimumMatchQuery {
+    fn weight(&self, enable_scoring: EnableScoring<'_>) -> tantivy::Result<Box<dyn Weight>> {
+        let weights = self
+            .clauses
+            .iter()
+            .map(|clause| clause.weight(enable_scoring))
+            .collect::<tantivy::Result<Vec<_>>>()?;
+        Ok(Box::new(MinimumMatchWeight {
+            weights,
+            minimum: self.minimum,
+        }))
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        for clause in &self.clauses {
+            clause.query_terms(visitor);
+        }
+    }
+}
+
+struct MinimumMatchWeight {
+    weights: Vec<Box<dyn Weight>>,
+    minimum: usize,
+}
+
+impl Weight for MinimumMatchWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> tantivy::Result<Box<dyn Scorer>> {
+        let scorers = self
+            .weights
+            .iter()
+            .map(|weight| weight.scorer(reader, boost))
+            .collect::<tantivy::Result<Vec<_>>>()?;
+        let mut scorer = MinimumMatchScorer {
+            scorers,
+            minimum: self.minimum,
+            doc: TERMINATED,
+            score: 0.0,
+        };
+        scorer.find_match();
+        Ok(Box::new(scorer))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> tantivy::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(TantivyError::InvalidArgument(format!(
+                "document #({doc}) does not match"
+            )));
+        }
+        let description = format!("at least {} of the clauses", self.minimum);
+        Ok(Explanation::new_with_string(description, scorer.score()))
+    }
+}
+
+/// Walks the clauses' scorers together, each left on the first document it
+/// hasn't yet counted.
+struct MinimumMatchScorer {
+    scorers: Vec<Box<dyn Scorer>>,
+    minimum: usize,
+    doc: DocId,
+    score: Score,
+}
+
+impl MinimumMatchScorer {
+    /// Move to the next document enough clauses agree on.
+    fn find_match(&mut self) -> DocId {
+        loop {
+            let Some(doc) = self.scorers.iter().map(|scorer| scorer.doc()).min() else {
+                self.doc = TERMINATED;
+                return TERMINATED;
+            };
+            if doc == TERMINATED {
+                self.doc = TERMINATED;
+                return TERMINATED;
+            }
+            let mut matched = 0;
+            let mut score = 0.0;
+            for scorer in &mut self.scorers {
+                if scorer.doc() == doc {
+                    matched += 1;
+                    score += scorer.score();
+                    scorer.advance();
+                }
+            }
+            if matched >= self.minimum {
+                self.doc = doc;
+                self.score = score;
+                return doc;
+            }
+        }
+    }
+}
+
+impl DocSet for MinimumMatchScorer {
+    fn advance(&mut self) -> DocId {
+        self.find_match()
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.scorers
+            .iter()
+            .map(|scorer| scorer.size_hint())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl Scorer for MinimumMatchScorer {
+    fn score(&mut self) -> Score {
+        self.score
+    }
+}
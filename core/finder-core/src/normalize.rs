@@ -0,0 +1,462 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+
+/// Shortest run of base64 alphabet characters treated as an encoded blob.
+const MIN_BASE64_BLOB_CHARS: usize = 64;
+/// Page-based formats need at least this many pages before a line counts as
+/// a repeated header or footer.
+const MIN_PAGES_FOR_BOILERPLATE: usize = 3;
+/// Form feed, emitted between pages by pdftotext and similar converters.
+const PAGE_BREAK: char = '\u{0c}';
+/// Pages headers and footers are picked from when text is normalized in
+/// pieces; later pages are cleaned as they arrive.
+const BOILERPLATE_SAMPLE_PAGES: usize = 16;
+/// Most text held back between pieces: the page being read, the pages
+/// still sampled for boilerplate, or a run that may be a base64 blob.
+const MAX_HELD_BYTES: usize = 256 * 1024;
+
+static BASE64_BLOB_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(
+        r"[A-Za-z0-9+/]{{{},}}={{0,2}}",
+        MIN_BASE64_BLOB_CHARS
+    ))
+    .unwrap()
+});
+
+static DIGITS_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\d+").unwrap());
+
+/// Which cleanup passes run over extracted content before it is indexed.
+/// Everything is off by default so content is indexed verbatim.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NormalizeOptions {
+    /// Replace runs of whitespace with a single space (newlines with one newline).
+    pub collapse_whitespace: bool,
+    /// Remove header/footer lines repeated across form-feed separated pages.
+    pub strip_page_boilerplate: bool,
+    /// Drop long base64 runs such as inline images or mail attachments.
+    pub drop_base64: bool,
+}
+
+impl NormalizeOptions {
+    pub fn all() -> Self {
+        Self {
+            collapse_whitespace: true,
+            strip_page_boilerplate: true,
+            drop_base64: true,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.collapse_whitespace || self.strip_page_boilerplate || self.drop_base64
+    }
+}
+
+/// Apply the enabled normalization passes to `text`.
+pub fn normalize_content(text: &str, options: &NormalizeOptions) -> String {
+    if !options.is_enabled() {
+        return text.to_string();
+    }
+    let mut normalizer = ContentNormalizer::new(*options);
+    let mut out = normalizer.push(text);
+    out.push_str(&normalizer.finish());
+    out
+}
+
+/// Applies [`NormalizeOptions`] to content that arrives in pieces, the way
+/// [`normalize_content`] treats the whole text. Headers and footers are
+/// picked from the first pages, and text that may continue into the next
+/// piece is held back until it ends, up to [`MAX_HELD_BYTES`].
+pub(crate) struct ContentNormalizer {
+    options: NormalizeOptions,
+    pages: PageCleaner,
+    base64: Base64Filter,
+    whitespace: WhitespaceCollapser,
+}
+
+impl ContentNormalizer {
+    pub(crate) fn new(options: NormalizeOptions) -> Self {
+        Self {
+            options,
+            pages: PageCleaner::default(),
+            base64: Base64Filter::default(),
+            whitespace: WhitespaceCollapser::default(),
+        }
+    }
+
+    /// Normalized text ready to follow what earlier pushes returned.
+    pub(crate) fn push(&mut self, text: &str) -> String {
+        let text = if self.options.strip_page_boilerplate {
+            self.pages.push(text)
+        } else {
+            text.to_string()
+        };
+        self.after_pages(text, false)
+    }
+
+    /// Whatever was held back, once the content has ended.
+    pub(crate) fn finish(&mut self) -> String {
+        let text = if self.options.strip_page_boilerplate {
+            self.pages.finish()
+        } else {
+            String::new()
+        };
+        self.after_pages(text, true)
+    }
+
+    /// Bytes pushed but not yet returned.
+    pub(crate) fn held(&self) -> usize {
+        self.pages.held() + self.base64.tail.len()
+    }
+
+    fn after_pages(&mut self, text: String, last: bool) -> String {
+        let text = if self.options.drop_base64 {
+            self.base64.push(&text, last)
+        } else {
+            text
+        };
+        if self.options.collapse_whitespace {
+            self.whitespace.push(&text)
+        } else {
+            text
+        }
+    }
+}
+
+/// Real base64 mixes cases and digits; long identifiers or hex dumps don't.
+fn looks_like_base64(candidate: &str) -> bool {
+    let upper = candidate.bytes().any(|b| b.is_ascii_uppercase());
+    let lower = candidate.bytes().any(|b| b.is_ascii_lowercase());
+    let digit = candidate.bytes().any(|b| b.is_ascii_digit());
+    upper && lower && digit
+}
+
+fn is_base64_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || matches!(ch, '+' | '/' | '=')
+}
+
+fn drop_base64(text: &str) -> String {
+    BASE64_BLOB_RE
+        .replace_all(text, |caps: &regex::Captures| {
+            if looks_like_base64(&caps[0]) {
+                String::new()
+            } else {
+                caps[0].to_string()
+            }
+        })
+        .into_owned()
+}
+
+#[derive(Default)]
+struct Base64Filter {
+    /// Trailing run of base64 characters the next piece may extend.
+    tail: String,
+    /// The last piece ended inside a run already dropped as a blob.
+    dropping: bool,
+}
+
+impl Base64Filter {
+    fn push(&mut self, text: &str, last: bool) -> String {
+        let mut text = text;
+        if self.dropping {
+            text = text.trim_start_matches(is_base64_char);
+            if text.is_empty() && !last {
+                return String::new();
+            }
+            self.dropping = false;
+        }
+        let mut ready = std::mem::take(&mut self.tail);
+        ready.push_str(text);
+        let split = if last {
+            ready.len()
+        } else {
+            ready.trim_end_matches(is_base64_char).len()
+        };
+        let held = ready.split_off(split);
+        if held.len() <= MAX_HELD_BYTES {
+            self.tail = held;
+        } else if looks_like_base64(&held) {
+            self.dropping = true;
+        } else {
+            ready.push_str(&held);
+        }
+        drop_base64(&ready)
+    }
+}
+
+#[derive(Default)]
+struct WhitespaceCollapser {
+    /// Separator owed before the next word.
+    pending: Option<char>,
+    started: bool,
+}
+
+impl WhitespaceCollapser {
+    fn push(&mut self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        for ch in text.chars() {
+            if ch.is_whitespace() {
+                // A newline anywhere in the run wins so paragraph structure survives.
+                if ch == '\n' || self.pending.is_none() {
+                    self.pending = Some(if ch == '\n' { '\n' } else { ' ' });
+                }
+                continue;
+            }
+            if let Some(separator) = self.pending.take() {
+                if self.started {
+                    out.push(separator);
+                }
+            }
+            self.started = true;
+            out.push(ch);
+        }
+        out
+    }
+}
+
+/// Removes header and footer lines repeated across form-feed separated
+/// pages. Which lines those are is decided over the first
+/// [`BOILERPLATE_SAMPLE_PAGES`] pages, or over all of them in shorter text.
+#[derive(Default)]
+struct PageCleaner {
+    /// Text of the page being read.
+    page: String,
+    /// The page being read outgrew [`MAX_HELD_BYTES`] and goes through as is.
+    overlong: bool,
+    /// Complete pages held until the boilerplate is picked.
+    sample: Vec<String>,
+    sample_bytes: usize,
+    /// Masked lines found opening or closing enough pages; `None` until picked.
+    boilerplate: Option<HashSet<String>>,
+}
+
+impl PageCleaner {
+    fn push(&mut self, text: &str) -> String {
+        let mut out = String::new();
+        let mut rest = text;
+        while let Some(at) = rest.find(PAGE_BREAK) {
+            self.read(&rest[..at], &mut out);
+            let page = std::mem::take(&mut self.page);
+            if std::mem::take(&mut self.overlong) {
+                out.push(PAGE_BREAK);
+            } else {
+                self.page_done(page, &mut out);
+            }
+            rest = &rest[at + PAGE_BREAK.len_utf8()..];
+        }
+        self.read(rest, &mut out);
+        out
+    }
+
+    fn finish(&mut self) -> String {
+        let mut out = String::new();
+        let page = std::mem::take(&mut self.page);
+        match &self.boilerplate {
+            _ if self.overlong => {}
+            Some(boilerplate) => out.push_str(&strip_page(&page, boilerplate)),
+            None => {
+                self.sample.push(page);
+                self.pick();
+                let boilerplate = self.boilerplate.as_ref().unwrap();
+                let pages: Vec<String> = self
+                    .sample
+                    .drain(..)
+                    .map(|page| strip_page(&page, boilerplate))
+                    .collect();
+                out.push_str(&pages.join(&PAGE_BREAK.to_string()));
+            }
+        }
+        out
+    }
+
+    fn held(&self) -> usize {
+        self.page.len() + self.sample_bytes
+    }
+
+    /// Text of the page being read; a page too long to hold goes through
+    /// uncleaned, with the pages held so far cleaned ahead of it.
+    fn read(&mut self, text: &str, out: &mut String) {
+        if self.overlong {
+            out.push_str(text);
+            return;
+        }
+        self.page.push_str(text);
+        if self.page.len() > MAX_HELD_BYTES {
+            self.flush_sample(out);
+            out.push_str(&std::mem::take(&mut self.page));
+            self.overlong = true;
+        }
+    }
+
+    fn page_done(&mut self, page: String, out: &mut String) {
+        if let Some(boilerplate) = &self.boilerplate {
+            out.push_str(&strip_page(&page, boilerplate));
+            out.push(PAGE_BREAK);
+            return;
+        }
+        self.sample_bytes += page.len();
+        self.sample.push(page);
+        if self.sample.len() >= BOILERPLATE_SAMPLE_PAGES || self.sample_bytes > MAX_HELD_BYTES {
+            self.flush_sample(out);
+        }
+    }
+
+    /// Pick the boilerplate if that's still open and clean the held pages.
+    fn flush_sample(&mut self, out: &mut String) {
+        if self.boilerplate.is_none() {
+            self.pick();
+        }
+        let boilerplate = self.boilerplate.as_ref().unwrap();
+        for page in self.sample.drain(..) {
+            out.push_str(&strip_page(&page, boilerplate));
+            out.push(PAGE_BREAK);
+        }
+        self.sample_bytes = 0;
+    }
+
+    fn pick(&mut self) {
+        let mut boilerplate = HashSet::new();
+        if self.sample.len() >= MIN_PAGES_FOR_BOILERPLATE {
+            let mut counts: HashMap<String, usize> = HashMap::new();
+            for page in &self.sample {
+                let (first, last) = page_edges(page);
+                for line in first.into_iter().chain(last) {
+                    *counts.entry(boilerplate_key(line)).or_default() += 1;
+                }
+            }
+            let threshold = self.sample.len().div_ceil(2);
+            boilerplate.extend(
+                counts
+                    .into_iter()
+                    .filter(|(_, count)| *count >= threshold)
+                    .map(|(line, _)| line),
+            );
+        }
+        self.boilerplate = Some(boilerplate);
+    }
+}
+
+/// Page numbers vary per page, so lines are compared with digits masked out.
+fn boilerplate_key(line: &str) -> String {
+    DIGITS_RE.replace_all(line.trim(), "#").into_owned()
+}
+
+/// The first and last non-blank lines of a page, where headers and footers sit.
+fn page_edges(page: &str) -> (Option<&str>, Option<&str>) {
+    let mut lines = page.lines().filter(|line| !line.trim().is_empty());
+    let first = lines.next();
+    (first, lines.next_back())
+}
+
+/// `page` without its header and footer lines when they are boilerplate;
+/// the same text elsewhere on the page stays.
+fn strip_page(page: &str, boilerplate: &HashSet<String>) -> String {
+    if boilerplate.is_empty() {
+        return page.to_string();
+    }
+    let (first, last) = page_edges(page);
+    let is_edge = |line: &str| {
+        [first, last]
+            .into_iter()
+            .flatten()
+            .any(|edge| std::ptr::eq(edge, line))
+    };
+    page.lines()
+        .filter(|line| !(is_edge(line) && boilerplate.contains(&boilerplate_key(line))))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize_content, ContentNormalizer, NormalizeOptions, PAGE_BREAK};
+
+    #[test]
+    fn disabled_options_leave_text_untouched() {
+        let text = "a   b\n\n\nc";
+        assert_eq!(normalize_content(text, &NormalizeOptions::default()), text);
+    }
+
+    #[test]
+    fn collapses_whitespace_preserving_line_breaks() {
+        let options = NormalizeOptions {
+            collapse_whitespace: true,
+            ..NormalizeOptions::default()
+        };
+        assert_eq!(
+            normalize_content("  alpha \t beta\n\n\n  gamma  ", &options),
+            "alpha beta\ngamma"
+        );
+    }
+
+    #[test]
+    fn strips_repeated_headers_and_footers() {
+        let page = |n: usize| format!("ACME Corp Confidential\nBody of page {n}\nPage {n} of 3");
+        let text = [page(1), page(2), page(3)].join("\u{0c}");
+        let options = NormalizeOptions {
+            strip_page_boilerplate: true,
+            ..NormalizeOptions::default()
+        };
+        let cleaned = normalize_content(&text, &options);
+        assert!(!cleaned.contains("Confidential"));
+        assert!(!cleaned.contains("of 3"));
+        assert!(cleaned.contains("Body of page 2"));
+    }
+
+    #[test]
+    fn drops_base64_but_keeps_long_words() {
+        let blob = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg==";
+        let identifier = "a".repeat(80);
+        let text = format!("see image {blob} and {identifier}");
+        let cleaned = normalize_content(&text, &NormalizeOptions::all());
+        assert_eq!(cleaned, format!("see image and {identifier}"));
+    }
+
+    #[test]
+    fn keeps_body_lines_that_repeat_the_header() {
+        let page = |n: usize| {
+            format!(
+                "ACME Corp Confidential\nBody of page {n}\nACME Corp Confidential\nPage {n} of 3"
+            )
+        };
+        let text = [page(1), page(2), page(3)].join("\u{0c}");
+        let options = NormalizeOptions {
+            strip_page_boilerplate: true,
+            ..NormalizeOptions::default()
+        };
+        let cleaned = normalize_content(&text, &options);
+        assert_eq!(
+            cleaned,
+            [1, 2, 3]
+                .map(|n| format!("Body of page {n}\nACME Corp Confidential"))
+                .join("\u{0c}")
+        );
+    }
+
+    #[test]
+    fn normalizes_text_pushed_in_pieces_like_the_whole() {
+        let blob = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg==";
+        let pages: Vec<String> = (1..=20)
+            .map(|n| format!("Quarterly Report\nitem {n}   with {blob} inline\n\nPage {n}"))
+            .collect();
+        let text = pages.join(&PAGE_BREAK.to_string());
+        let whole = normalize_content(&text, &NormalizeOptions::all());
+        assert!(!whole.contains("Quarterly") && !whole.contains("iVBOR"));
+        assert!(whole.contains("item 20 with inline"));
+
+        // Pieces cut through lines, page breaks and the blobs.
+        let mut normalizer = ContentNormalizer::new(NormalizeOptions::all());
+        let mut pieced = String::new();
+        let mut rest = text.as_str();
+        while !rest.is_empty() {
+            let mut cut = rest.len().min(37);
+            while !rest.is_char_boundary(cut) {
+                cut += 1;
+            }
+            pieced.push_str(&normalizer.push(&rest[..cut]));
+            rest = &rest[cut..];
+        }
+        pieced.push_str(&normalizer.finish());
+        assert_eq!(pieced, whole);
+    }
+}
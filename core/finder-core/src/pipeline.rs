@@ -0,0 +1,1209 @@
+use crate::coverage;
+use crate::events::{self, IndexEvent};
+use crate::extract_plain::{stream_plain_text, SniffConfig};
+use crate::extract_structured::{read_structured_text, StructuredFormat};
+use crate::extract_table::{read_table_sample, TableFormat, DEFAULT_TABLE_SAMPLE_ROWS};
+use crate::generated::GeneratedRules;
+use crate::git::TrackedFiles;
+use crate::indexer::{self, IndexUpdate};
+use crate::plugins::{extract_with_plugin, plugin_for, ExtractorPlugin};
+use crate::pressure;
+use crate::retry;
+use crate::roots::normalize_roots;
+use crate::scanner::{build_meta, build_meta_with, scan_root_with, FileMeta, NoiseRules};
+use crate::worker::{read_structured_text_isolated, ExtractWorker};
+use anyhow::{bail, Context, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_MAX_CONTENT_BYTES: u64 = 1_572_864;
+const DEFAULT_SNIFF_BYTES: usize = 8192;
+const DEFAULT_SKIP_EXTS: &[&str] = &["pkg", "dmg"];
+/// Immediate re-reads of a file that keeps changing under the reader before
+/// it is left to the retry queue.
+const MAX_CHANGED_REREADS: usize = 2;
+/// Plain text is decoded and handed to the index this many bytes at a time.
+const STREAM_CHUNK_BYTES: usize = 64 * 1024;
+
+/// Per-root ingestion options for [`index_root`].
+#[derive(Debug, Clone)]
+pub struct RootOptions {
+    /// Files larger than this are indexed by name only.
+    pub max_content_bytes: u64,
+    pub sniff_bytes: usize,
+    pub sniff: SniffConfig,
+    /// Lowercased extensions (without the dot) skipped entirely.
+    pub skip_exts: Vec<String>,
+    pub noise: NoiseRules,
+    /// Never read content for files under this root, regardless of the
+    /// global [`IndexSettings::metadata_only`](crate::IndexSettings) flag.
+    /// Scanning doesn't open files either, so checksum noise rules, alias
+    /// targets and bundle display names don't apply.
+    pub metadata_only: bool,
+    /// Bypass dedup and rewrite every document.
+    pub force_reindex: bool,
+    /// Only files modified within this window get content; older files are
+    /// indexed by name only (and lose previously indexed content when next
+    /// visited). `None` indexes content regardless of age.
+    pub content_max_age: Option<Duration>,
+    /// Background work that yields under memory pressure: when the soft
+    /// limit is reached, files are deferred to a later pass instead of
+    /// waiting for memory to free up.
+    pub low_priority: bool,
+    /// Index machine-generated files (minified bundles, source maps, JSON
+    /// dumps) by name only. `None` keeps their content.
+    pub skip_generated: Option<GeneratedRules>,
+    /// Inside git repositories, index only files git tracks; untracked
+    /// files (build output, caches, scratch) are skipped entirely.
+    pub git_tracked_only: bool,
+    /// CSV/TSV files index their header plus this many rows, whatever their
+    /// size; `max_content_bytes` does not apply to them.
+    pub table_sample_rows: usize,
+    /// Parse JSON, YAML and plist files in a worker process, so one that
+    /// crashes a parser is indexed as plain text instead of taking the host
+    /// down. `None` parses in process.
+    pub extract_worker: Option<ExtractWorker>,
+    /// External extractors for formats not read in process; the first one
+    /// registered for a file's extension is used, ahead of those given to
+    /// [`set_extractor_plugins`](crate::set_extractor_plugins). Those files are sent to
+    /// the plugin whatever their size, and get `max_content_bytes` of text.
+    pub extractor_plugins: Vec<ExtractorPlugin>,
+}
+
+impl Default for RootOptions {
+    fn default() -> Self {
+        Self {
+            max_content_bytes: DEFAULT_MAX_CONTENT_BYTES,
+            sniff_bytes: DEFAULT_SNIFF_BYTES,
+            sniff: SniffConfig::default(),
+            skip_exts: DEFAULT_SKIP_EXTS
+                .iter()
+                .map(|ext| ext.to_string())
+                .collect(),
+            noise: NoiseRules::default(),
+            metadata_only: false,
+            force_reindex: false,
+            content_max_age: None,
+            low_priority: false,
+            skip_generated: None,
+            git_tracked_only: false,
+            table_sample_rows: DEFAULT_TABLE_SAMPLE_ROWS,
+            extract_worker: None,
+            extractor_plugins: Vec::new(),
+        }
+    }
+}
+
+/// Counters describing one [`index_root`] run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IndexReport {
+    pub files_seen: usize,
+    pub skipped_noise: usize,
+    pub skipped_ext: usize,
+    /// Files already seen under another root (or another path) in this run.
+    pub skipped_duplicate: usize,
+    /// Files skipped by [`RootOptions::git_tracked_only`].
+    pub skipped_untracked: usize,
+    pub added: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    /// Documents written with extracted content.
+    pub content_indexed: usize,
+    /// Of those, JSON/YAML/plist files indexed as flattened key-value lines.
+    pub structured: usize,
+    /// Of those, CSV/TSV files indexed by header and sampled rows.
+    pub tables: usize,
+    /// Documents written by name and metadata only.
+    pub names_only: usize,
+    pub too_large: usize,
+    /// Files indexed names-only because of [`RootOptions::content_max_age`].
+    pub too_old: usize,
+    /// Files indexed names-only because they look machine-generated.
+    pub generated: usize,
+    /// Low-priority files left untouched because of memory pressure.
+    pub deferred: usize,
+    pub binary: usize,
+    pub read_errors: usize,
+    pub bytes_read: usize,
+    /// Files re-read because they changed while their content was read.
+    pub changed_during_read: usize,
+    /// Message of the most recent content read failure.
+    pub last_read_error: Option<String>,
+    /// Documents removed because their file vanished or is now excluded.
+    pub pruned: usize,
+    /// Files whose extraction worker crashed or timed out; indexed as plain
+    /// text instead.
+    pub worker_failures: usize,
+    /// Of the content-indexed documents, files read by an extractor plugin.
+    pub plugin_extracted: usize,
+    /// Files whose extractor plugin crashed, timed out or answered garbage;
+    /// indexed as plain text instead.
+    pub plugin_failures: usize,
+    /// Folders written as documents of their own, new or changed.
+    pub folders: usize,
+}
+
+/// Outcome of [`reindex_path`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathReindex {
+    pub update: IndexUpdate,
+    /// The document was written with extracted content.
+    pub content_indexed: bool,
+    /// Why content could not be read, when it couldn't.
+    pub error: Option<String>,
+}
+
+/// Scan `root` and add or update every discovered file in the open index.
+/// Content is read only for files that actually changed, and never when
+/// metadata-only mode applies. The caller decides when to [`commit`](crate::commit).
+pub fn index_root<P: AsRef<Path>>(root: P, options: &RootOptions) -> Result<IndexReport> {
+    let root = root.as_ref();
+    let mut report = IndexReport::default();
+    index_scanned(root, options, &mut HashSet::new(), &mut report)?;
+    scan_finished(vec![root.to_string_lossy().into_owned()], &report);
+    Ok(report)
+}
+
+/// Like [`index_root`] for several roots sharing `options`. Overlapping
+/// roots are collapsed with [`normalize_roots`] and a file reachable from
+/// more than one root (hard links, symlinked folders) is indexed once.
+pub fn index_roots<P: AsRef<Path>>(roots: &[P], options: &RootOptions) -> Result<IndexReport> {
+    let mut report = IndexReport::default();
+    let mut seen = HashSet::new();
+    let roots = normalize_roots(roots).roots;
+    for root in &roots {
+        index_scanned(root, options, &mut seen, &mut report)?;
+    }
+    let roots = roots
+        .iter()
+        .map(|root| root.to_string_lossy().into_owned())
+        .collect();
+    scan_finished(roots, &report);
+    Ok(report)
+}
+
+/// Stat, extract and rewrite the single file at `path`, bypassing dedup,
+/// skipped extensions and low-priority deferral: for when the user asks for
+/// one file to be indexed again. The caller decides when to
+/// [`commit`](crate::commit).
+pub fn reindex_path<P: AsRef<Path>>(path: P, options: &RootOptions) -> Result<PathReindex> {
+    let path = path.as_ref();
+    let metadata_only = options.metadata_only || indexer::settings()?.metadata_only;
+    let meta = build_meta_with(path, metadata_only)
+        .with_context(|| format!("failed to stat {}", path.display()))?;
+    let existed = indexer::is_indexed(&meta)?;
+    let options = RootOptions {
+        low_priority: false,
+        ..options.clone()
+    };
+    let mut report = IndexReport::default();
+    let update = match index_file(meta, &options, true, metadata_only, &mut report)? {
+        IndexUpdate::Added if existed => IndexUpdate::Updated,
+        update => update,
+    };
+    Ok(PathReindex {
+        update,
+        content_indexed: report.content_indexed > 0,
+        error: report.last_read_error,
+    })
+}
+
+/// Rescan only the directory `dir` with `options`, index what changed and
+/// drop documents under it whose files are gone or now excluded by the
+/// rules. Much cheaper than [`index_root`] when the caller knows where the
+/// changes are. The caller decides when to [`commit`](crate::commit).
+pub fn reindex_subtree<P: AsRef<Path>>(dir: P, options: &RootOptions) -> Result<IndexReport> {
+    let dir = dir.as_ref();
+    if !dir.is_dir() {
+        bail!("not a directory: {}", dir.display());
+    }
+    let mut report = IndexReport::default();
+    let mut indexed = index_scanned(dir, options, &mut HashSet::new(), &mut report)?;
+    // `dir`'s own folder document belongs to the scan of its parent.
+    if let Ok(meta) = build_meta(dir) {
+        indexed.insert(meta.identity());
+    }
+    report.pruned = indexer::prune_under(dir, &indexed)?;
+    scan_finished(vec![dir.to_string_lossy().into_owned()], &report);
+    Ok(report)
+}
+
+fn scan_finished(roots: Vec<String>, report: &IndexReport) {
+    events::emit(IndexEvent::ScanFinished {
+        roots,
+        report: report.clone(),
+    });
+}
+
+fn index_scanned(
+    root: &Path,
+    options: &RootOptions,
+    seen: &mut HashSet<String>,
+    report: &mut IndexReport,
+) -> Result<HashSet<String>> {
+    let metadata_only = options.metadata_only || indexer::settings()?.metadata_only;
+    let scan = scan_root_with(root, &options.noise, metadata_only)?;
+    report.skipped_noise += scan.skipped_noise;
+    let mut tracked = options.git_tracked_only.then(TrackedFiles::default);
+    let mut indexed = HashSet::new();
+
+    for meta in scan.files {
+        report.files_seen += 1;
+
+        if !seen.insert(meta.identity()) {
+            report.skipped_duplicate += 1;
+            continue;
+        }
+
+        if let Some(tracked) = tracked.as_mut() {
+            if tracked.is_untracked(Path::new(&meta.path)) {
+                report.skipped_untracked += 1;
+                continue;
+            }
+        }
+
+        if has_skipped_ext(meta.ext.as_deref(), &options.skip_exts) {
+            coverage::record_skipped_ext(meta.ext.as_deref());
+            report.skipped_ext += 1;
+            continue;
+        }
+
+        indexed.insert(meta.identity());
+        index_file(meta, options, options.force_reindex, metadata_only, report)?;
+    }
+
+    // Folders go in by name and path alone, like files in a names-only root.
+    for meta in scan.folders {
+        if !seen.insert(meta.identity()) {
+            continue;
+        }
+        indexed.insert(meta.identity());
+        let update = indexer::upsert(meta, options.force_reindex, true, |_| Ok(()))?;
+        if update != IndexUpdate::Skipped {
+            report.folders += 1;
+        }
+    }
+
+    Ok(indexed)
+}
+
+/// Add or update one scanned file, reading its content through `options`
+/// unless `metadata_only`, and tally the outcome in `report`. Deferred
+/// low-priority files report [`IndexUpdate::Skipped`].
+pub(crate) fn index_file(
+    meta: FileMeta,
+    options: &RootOptions,
+    force_reindex: bool,
+    metadata_only: bool,
+    report: &mut IndexReport,
+) -> Result<IndexUpdate> {
+    let ext = meta.ext.clone();
+    let path = meta.path.clone();
+    let before = report.clone();
+    let mut meta = meta;
+    let mut force_reindex = force_reindex;
+    let mut rereads = 0;
+    let result = loop {
+        let attempt_start = report.clone();
+        let (update, changed) =
+            match index_file_counted(meta, options, force_reindex, metadata_only, report) {
+                Ok(outcome) => outcome,
+                Err(err) => break Err(err),
+            };
+        if !changed {
+            break Ok(update);
+        }
+        // The stored content may mix old and new bytes under the old
+        // metadata, which would keep the new version from ever being read.
+        report.changed_during_read += 1;
+        let fresh = build_meta_with(Path::new(&path), metadata_only).ok();
+        match fresh {
+            Some(fresh) if rereads < MAX_CHANGED_REREADS => {
+                // Only the final attempt's outcome counts; bytes read and
+                // rereads are real work either way.
+                *report = IndexReport {
+                    bytes_read: report.bytes_read,
+                    changed_during_read: report.changed_during_read,
+                    ..attempt_start
+                };
+                rereads += 1;
+                meta = fresh;
+                force_reindex = true;
+            }
+            Some(_) => {
+                if let Err(err) = indexer::retries()?.record_failure(&path, retry::unix_now()) {
+                    eprintln!("[pipeline] failed updating retry queue for {path}: {err}");
+                }
+                break Ok(update);
+            }
+            // Deleted mid-read; the next pass drops it.
+            None => break Ok(update),
+        }
+    };
+    coverage::record_file(ext.as_deref(), &before, report);
+    result
+}
+
+/// Modification time and size, precise enough to notice a rewrite within
+/// the same second as long as the size or sub-second mtime moved.
+fn fingerprint(path: &str) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+fn fingerprint_matches_meta(fingerprint: Option<(SystemTime, u64)>, mtime: i64, size: u64) -> bool {
+    fingerprint.is_some_and(|(modified, len)| {
+        let secs = modified
+            .duration_since(UNIX_EPOCH)
+            .map(|dur| dur.as_secs() as i64)
+            .unwrap_or_default();
+        secs == mtime && len == size
+    })
+}
+
+/// Test hook run after a file's pre-read fingerprint is taken and before
+/// its content is read, to simulate a concurrent writer.
+#[cfg(test)]
+pub(crate) static BEFORE_READ: std::sync::Mutex<Option<fn(&str)>> = std::sync::Mutex::new(None);
+
+/// Like [`index_file`] for a single attempt; also reports whether the file
+/// changed while its content was being read.
+fn index_file_counted(
+    meta: FileMeta,
+    options: &RootOptions,
+    force_reindex: bool,
+    metadata_only: bool,
+    report: &mut IndexReport,
+) -> Result<(IndexUpdate, bool)> {
+    // Files that failed transiently are re-read once their backoff is over,
+    // even though their metadata hasn't changed.
+    let retries = indexer::retries()?;
+    let now = retry::unix_now();
+    let force_reindex = force_reindex || retries.is_due(&meta.path, now);
+    let mut transient_failure = false;
+    let plugin = match plugin_for(&options.extractor_plugins, meta.ext.as_deref()) {
+        Some(plugin) => Some(plugin.clone()),
+        None => indexer::plugins()?.plugin_for(meta.ext.as_deref()),
+    };
+    let table = meta.ext.as_deref().and_then(TableFormat::from_ext);
+    let too_large = table.is_none() && plugin.is_none() && meta.size > options.max_content_bytes;
+    let too_old = !metadata_only && is_older_than(meta.modified_at, options.content_max_age);
+    let generated_name = !metadata_only
+        && options
+            .skip_generated
+            .map(|rules| rules.matches_meta(&meta))
+            .unwrap_or(false);
+    let names_only = metadata_only || too_old || generated_name;
+    let mut generated = generated_name;
+    let path = meta.path.clone();
+    let structured = meta.ext.as_deref().and_then(StructuredFormat::from_ext);
+    let mut wrote_content = false;
+
+    // Reserve roughly what extraction will hold in memory; the reservation
+    // lives until the document has been handed to the writer.
+    let reserve_bytes = meta.size.min(options.max_content_bytes) as usize;
+    let reads_content = !(names_only || too_large);
+    let mut reservation = None;
+    if reads_content && options.low_priority {
+        match pressure::try_reserve(reserve_bytes) {
+            Some(held) => reservation = Some(held),
+            None => {
+                report.deferred += 1;
+                return Ok((IndexUpdate::Skipped, false));
+            }
+        }
+    }
+
+    let (scanned_mtime, scanned_size) = (meta.modified_at, meta.size);
+    let mut read_started = None;
+    let update = indexer::upsert(meta, force_reindex, names_only, |sink| {
+        if too_large {
+            report.too_large += 1;
+            return Ok(());
+        }
+        read_started = Some(fingerprint(&path));
+        #[cfg(test)]
+        if let Some(hook) = *BEFORE_READ.lock().unwrap() {
+            hook(&path);
+        }
+        if reservation.is_none() {
+            reservation = Some(pressure::reserve(reserve_bytes));
+        }
+        let limit = options.max_content_bytes.min(usize::MAX as u64) as usize;
+        if let Some(plugin) = &plugin {
+            let extracted = extract_with_plugin(plugin, &path, limit).unwrap_or_else(|err| {
+                report.plugin_failures += 1;
+                Err(err)
+            });
+            match extracted {
+                Ok(Some(content)) => {
+                    report.bytes_read += content.len();
+                    report.plugin_extracted += 1;
+                    sink.push_owned(content);
+                    wrote_content = sink.bytes() > 0;
+                    return Ok(());
+                }
+                Ok(None) => {}
+                Err(err) => eprintln!("[pipeline] {} failed on {path}: {err}", plugin.name),
+            }
+        }
+        if let Some(format) = table {
+            match read_table_sample(&path, format, options.table_sample_rows) {
+                Ok(extraction) => {
+                    report.bytes_read += extraction.bytes_read;
+                    report.tables += 1;
+                    sink.set_table_shape(extraction.columns, extraction.rows);
+                    sink.push_owned(extraction.content);
+                    wrote_content = sink.bytes() > 0;
+                }
+                Err(err) => {
+                    eprintln!("[pipeline] failed to read {path}: {err}");
+                    report.read_errors += 1;
+                    report.last_read_error = Some(format!("{err:#}"));
+                    transient_failure = retry::is_transient(&err);
+                }
+            }
+            return Ok(());
+        }
+        // Configuration files index as `key.path: value` lines; anything that
+        // doesn't parse falls back to plain text.
+        if let Some(format) = structured {
+            let parsed = match &options.extract_worker {
+                Some(worker) => read_structured_text_isolated(worker, &path, format, limit)
+                    .unwrap_or_else(|err| {
+                        report.worker_failures += 1;
+                        Err(err)
+                    }),
+                None => read_structured_text(&path, format, limit),
+            };
+            match parsed {
+                Ok(Some(extraction)) => {
+                    report.bytes_read += extraction.bytes_read;
+                    report.structured += 1;
+                    sink.push_owned(extraction.content);
+                    wrote_content = sink.bytes() > 0;
+                    return Ok(());
+                }
+                Ok(None) => {}
+                Err(err) => eprintln!("[pipeline] failed to parse {path}: {err}"),
+            }
+        }
+        // Generated-content detection looks at the first chunk; the rest of
+        // a generated file is skipped unread by the index.
+        let mut first = true;
+        let streamed = stream_plain_text(
+            &path,
+            limit,
+            options.sniff_bytes,
+            STREAM_CHUNK_BYTES,
+            &options.sniff,
+            |text| {
+                if first {
+                    first = false;
+                    generated = options
+                        .skip_generated
+                        .is_some_and(|rules| rules.matches_content(text));
+                }
+                if !generated {
+                    sink.push(text);
+                }
+                Ok(())
+            },
+        );
+        match streamed {
+            Ok(extraction) => {
+                report.bytes_read += extraction.bytes_read;
+                if extraction.was_binary {
+                    report.binary += 1;
+                }
+            }
+            Err(err) => {
+                eprintln!("[pipeline] failed to read {path}: {err}");
+                report.read_errors += 1;
+                report.last_read_error = Some(format!("{err:#}"));
+                transient_failure = retry::is_transient(&err);
+            }
+        }
+        wrote_content = sink.bytes() > 0;
+        Ok(())
+    })?;
+    drop(reservation);
+    // Changed since the scan, or during the read itself.
+    let changed = read_started.is_some_and(|started| {
+        !fingerprint_matches_meta(started, scanned_mtime, scanned_size)
+            || started != fingerprint(&path)
+    });
+
+    let queued = if transient_failure {
+        retries.record_failure(&path, now)
+    } else if update == IndexUpdate::Skipped {
+        Ok(())
+    } else {
+        retries.clear(&path)
+    };
+    if let Err(err) = queued {
+        eprintln!("[pipeline] failed updating retry queue for {path}: {err}");
+    }
+
+    match update {
+        IndexUpdate::Added => report.added += 1,
+        IndexUpdate::Updated => report.updated += 1,
+        IndexUpdate::Skipped => {
+            report.unchanged += 1;
+            return Ok((update, false));
+        }
+    }
+    if too_old {
+        report.too_old += 1;
+    }
+    if generated {
+        report.generated += 1;
+    }
+    if wrote_content {
+        report.content_indexed += 1;
+    } else {
+        report.names_only += 1;
+    }
+    Ok((update, changed))
+}
+
+fn is_older_than(modified_at: i64, max_age: Option<Duration>) -> bool {
+    let Some(max_age) = max_age else {
+        return false;
+    };
+    let cutoff = SystemTime::now()
+        .checked_sub(max_age)
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|dur| dur.as_secs() as i64)
+        .unwrap_or(0);
+    modified_at < cutoff
+}
+
+pub(crate) fn has_skipped_ext(ext: Option<&str>, skip_exts: &[String]) -> bool {
+    ext.map(|ext| skip_exts.contains(&ext.to_lowercase()))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        index_root, index_roots, reindex_subtree, RootOptions, BEFORE_READ, MAX_CHANGED_REREADS,
+    };
+    use crate::commit;
+    use crate::coverage::{extraction_coverage, reset_extraction_coverage};
+    use crate::facets::count_by_extension;
+    use crate::generated::GeneratedRules;
+    use crate::indexer::{configure, index_stats, init_index, pending_retries, IndexSettings};
+    use crate::query::{search, EntryKind, SearchDomain, SearchQuery};
+    use crate::scanner::{noise_checksum, scan_root_with_rules, NoiseRules};
+    use std::fs;
+    use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+    use std::time::{Duration, SystemTime};
+    use tempfile::tempdir;
+
+    fn hits(term: &str, search_in: SearchDomain) -> usize {
+        search(SearchQuery {
+            term: term.into(),
+            search_in,
+            path_globs: Vec::new(),
+            limit: 10,
+            ..SearchQuery::default()
+        })
+        .unwrap()
+        .len()
+    }
+
+    #[test]
+    fn per_root_metadata_only_never_indexes_content() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let docs = tempdir().unwrap();
+        fs::write(
+            docs.path().join("diagnosis.txt"),
+            "confidential patient record",
+        )
+        .unwrap();
+        let index_dir = tempdir().unwrap();
+        init_index(index_dir.path().to_str().unwrap()).unwrap();
+
+        let private = RootOptions {
+            metadata_only: true,
+            ..RootOptions::default()
+        };
+        let report = index_root(docs.path(), &private).unwrap();
+        commit().unwrap();
+        assert_eq!(report.names_only, 1);
+        assert_eq!(report.bytes_read, 0);
+        assert_eq!(hits("confidential", SearchDomain::Content), 0);
+        assert_eq!(hits("diagnosis", SearchDomain::Name), 1);
+
+        // Opting a root back into content needs a forced pass, since dedup
+        // can't tell a names-only document from an unreadable one.
+        let with_content = RootOptions {
+            force_reindex: true,
+            ..RootOptions::default()
+        };
+        index_root(docs.path(), &with_content).unwrap();
+        commit().unwrap();
+        assert_eq!(hits("confidential", SearchDomain::Content), 1);
+        assert_eq!(index_stats().unwrap().content_docs, 1);
+
+        // Switching the root back to metadata-only strips the stored content.
+        let report = index_root(docs.path(), &private).unwrap();
+        commit().unwrap();
+        assert_eq!(report.updated, 1);
+        assert_eq!(hits("confidential", SearchDomain::Content), 0);
+        let stats = index_stats().unwrap();
+        assert_eq!(stats.metadata_only_docs, 1);
+        assert!(!stats.metadata_only);
+    }
+
+    #[test]
+    fn metadata_only_roots_are_scanned_without_opening_files() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let docs = tempdir().unwrap();
+        let template = docs.path().join("template.txt");
+        fs::write(&template, "boilerplate").unwrap();
+        let app = docs.path().join("Preview.app/Contents");
+        fs::create_dir_all(&app).unwrap();
+        fs::write(
+            app.join("Info.plist"),
+            crate::plist::tests::binary_plist(&[("CFBundleDisplayName", "Image Viewer")]),
+        )
+        .unwrap();
+        let noise = NoiseRules::default().with_hashes([noise_checksum(&template).unwrap()]);
+        let full_scan = scan_root_with_rules(docs.path(), &noise).unwrap();
+        assert_eq!(full_scan.skipped_noise, 1);
+        let index_dir = tempdir().unwrap();
+        init_index(index_dir.path().to_str().unwrap()).unwrap();
+
+        let private = RootOptions {
+            metadata_only: true,
+            noise,
+            ..RootOptions::default()
+        };
+        let report = index_root(docs.path(), &private).unwrap();
+        commit().unwrap();
+        // Matching the checksum rule would mean reading the file.
+        assert_eq!(report.skipped_noise, 0);
+        assert_eq!(hits("template", SearchDomain::Name), 1);
+        // Nor is the bundle's Info.plist parsed for its display name.
+        assert_eq!(hits("preview", SearchDomain::Name), 1);
+        assert_eq!(hits("viewer", SearchDomain::Name), 0);
+        assert_eq!(report.bytes_read, 0);
+    }
+
+    #[test]
+    fn long_files_are_streamed_into_chunk_documents() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let docs = tempdir().unwrap();
+        let book = docs.path().join("book.txt");
+        let filler = "lorem ipsum dolor sit amet\n".repeat(12_000);
+        fs::write(
+            &book,
+            format!("prologue\n{filler}interlude\n{filler}epilogue\n"),
+        )
+        .unwrap();
+        let index_dir = tempdir().unwrap();
+        init_index(index_dir.path().to_str().unwrap()).unwrap();
+
+        let report = index_root(docs.path(), &RootOptions::default()).unwrap();
+        commit().unwrap();
+        assert_eq!(report.content_indexed, 1);
+        for word in ["prologue", "interlude", "epilogue"] {
+            assert_eq!(hits(word, SearchDomain::Content), 1, "{word}");
+        }
+        let ipsum = search(SearchQuery {
+            term: "ipsum".into(),
+            search_in: SearchDomain::Content,
+            ..SearchQuery::default()
+        })
+        .unwrap();
+        assert_eq!(ipsum.len(), 1);
+        assert_eq!(ipsum[0].name, "book.txt");
+        assert!(ipsum[0].match_count > 2);
+        for word in ["ipsum", "epilogue"] {
+            let counts = count_by_extension(SearchQuery {
+                term: word.into(),
+                search_in: SearchDomain::Content,
+                ..SearchQuery::default()
+            })
+            .unwrap();
+            assert_eq!(counts[0].count, 1, "{word}");
+        }
+        assert_eq!(index_stats().unwrap().content_docs, 1);
+
+        let report = index_root(docs.path(), &RootOptions::default()).unwrap();
+        assert_eq!(report.unchanged, 1);
+
+        // Replacing the file drops every chunk of the old content.
+        fs::write(&book, "prologue only").unwrap();
+        index_root(docs.path(), &RootOptions::default()).unwrap();
+        commit().unwrap();
+        assert_eq!(hits("epilogue", SearchDomain::Content), 0);
+        assert_eq!(hits("prologue", SearchDomain::Content), 1);
+    }
+
+    #[test]
+    fn global_metadata_only_applies_to_every_root() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let docs = tempdir().unwrap();
+        fs::write(docs.path().join("notes.md"), "salary negotiation").unwrap();
+        let index_dir = tempdir().unwrap();
+        configure(IndexSettings {
+            metadata_only: true,
+            ..IndexSettings::default()
+        });
+        let opened = init_index(index_dir.path().to_str().unwrap());
+        configure(IndexSettings::default());
+        opened.unwrap();
+
+        let report = index_root(docs.path(), &RootOptions::default()).unwrap();
+        commit().unwrap();
+        assert_eq!(report.names_only, 1);
+        assert_eq!(hits("salary", SearchDomain::Content), 0);
+
+        // Direct API calls are held to the same guarantee.
+        crate::add_or_update_file(
+            crate::FileMeta {
+                path: "/elsewhere/secret.txt".into(),
+                name: "secret.txt".into(),
+                ..Default::default()
+            },
+            Some("salary".into()),
+            false,
+        )
+        .unwrap();
+        commit().unwrap();
+        assert_eq!(hits("salary", SearchDomain::Content), 0);
+
+        let stats = index_stats().unwrap();
+        assert!(stats.metadata_only);
+        assert_eq!(stats.content_docs, 0);
+        assert_eq!(stats.num_docs, 2);
+    }
+
+    #[test]
+    fn content_age_cutoff_indexes_old_files_by_name_only() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let docs = tempdir().unwrap();
+        let archived = docs.path().join("archive-1998.txt");
+        fs::write(&archived, "millennium bug checklist").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&archived)
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(20 * 365 * 86_400))
+            .unwrap();
+        fs::write(docs.path().join("todo.txt"), "renew passport").unwrap();
+        let index_dir = tempdir().unwrap();
+        init_index(index_dir.path().to_str().unwrap()).unwrap();
+
+        let options = RootOptions {
+            content_max_age: Some(Duration::from_secs(5 * 365 * 86_400)),
+            ..RootOptions::default()
+        };
+        let report = index_root(docs.path(), &options).unwrap();
+        commit().unwrap();
+        assert_eq!(report.too_old, 1);
+        assert_eq!(report.content_indexed, 1);
+        assert_eq!(hits("passport", SearchDomain::Content), 1);
+        assert_eq!(hits("millennium", SearchDomain::Content), 0);
+        assert_eq!(hits("archive", SearchDomain::Name), 1);
+    }
+
+    #[test]
+    fn overlapping_roots_index_each_file_once() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let home = tempdir().unwrap();
+        let docs = home.path().join("Documents");
+        fs::create_dir(&docs).unwrap();
+        fs::write(docs.join("lease.txt"), "tenancy agreement").unwrap();
+        fs::write(home.path().join("todo.txt"), "call landlord").unwrap();
+        let index_dir = tempdir().unwrap();
+        init_index(index_dir.path().to_str().unwrap()).unwrap();
+
+        let report = index_roots(
+            &[docs.clone(), home.path().to_path_buf()],
+            &RootOptions::default(),
+        )
+        .unwrap();
+        commit().unwrap();
+        assert_eq!(report.files_seen, 2);
+        assert_eq!(report.added, 2);
+        assert_eq!(
+            index_stats().unwrap().num_docs,
+            3,
+            "two files and Documents"
+        );
+        assert_eq!(hits("tenancy", SearchDomain::Content), 1);
+    }
+
+    #[test]
+    fn folders_are_indexed_with_their_child_count_and_total_size() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let docs = tempdir().unwrap();
+        let taxes = docs.path().join("2023 taxes");
+        fs::create_dir_all(taxes.join("receipts")).unwrap();
+        fs::write(taxes.join("return.txt"), "form 1040").unwrap();
+        fs::write(taxes.join("receipts").join("taxi.txt"), "fare").unwrap();
+        let index_dir = tempdir().unwrap();
+        init_index(index_dir.path().to_str().unwrap()).unwrap();
+
+        let report = index_root(docs.path(), &RootOptions::default()).unwrap();
+        commit().unwrap();
+        assert_eq!(report.folders, 2);
+        let find = |term: &str, kind| {
+            search(SearchQuery {
+                term: term.into(),
+                search_in: SearchDomain::Name,
+                kind,
+                ..SearchQuery::default()
+            })
+            .unwrap()
+        };
+        let folders = find("taxes", Some(EntryKind::Folder));
+        assert_eq!(folders.len(), 1);
+        assert!(folders[0].is_folder());
+        assert_eq!(folders[0].children, Some(2));
+        assert_eq!(folders[0].size, Some(13));
+        assert_eq!(find("receipts kind:folder", None).len(), 1);
+        assert!(find("taxi", Some(EntryKind::Folder)).is_empty());
+        assert_eq!(find("taxi", Some(EntryKind::File)).len(), 1);
+
+        // An unchanged folder is left alone on the next scan.
+        let again = index_root(docs.path(), &RootOptions::default()).unwrap();
+        assert_eq!(again.folders, 0);
+    }
+
+    #[test]
+    fn generated_files_are_indexed_by_name_only() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let docs = tempdir().unwrap();
+        fs::write(docs.path().join("vendor.min.js"), "zebra();").unwrap();
+        fs::write(
+            docs.path().join("bundle.js"),
+            format!("{}giraffe", "function(){return 1};".repeat(500)),
+        )
+        .unwrap();
+        fs::write(docs.path().join("readme.md"), "okapi notes").unwrap();
+        let index_dir = tempdir().unwrap();
+        init_index(index_dir.path().to_str().unwrap()).unwrap();
+
+        let options = RootOptions {
+            skip_generated: Some(GeneratedRules::default()),
+            ..RootOptions::default()
+        };
+        let report = index_root(docs.path(), &options).unwrap();
+        commit().unwrap();
+        assert_eq!(report.generated, 2);
+        assert_eq!(report.content_indexed, 1);
+        assert_eq!(hits("zebra", SearchDomain::Content), 0);
+        assert_eq!(hits("giraffe", SearchDomain::Content), 0);
+        assert_eq!(hits("okapi", SearchDomain::Content), 1);
+        assert_eq!(hits("bundle", SearchDomain::Name), 1);
+    }
+
+    #[test]
+    fn git_tracked_only_skips_untracked_files_in_repositories() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let root = tempdir().unwrap();
+        let repo = root.path().join("project");
+        fs::create_dir_all(repo.join("target")).unwrap();
+        fs::write(repo.join("main.rs"), "fn main() {}").unwrap();
+        fs::write(repo.join("target/out.txt"), "artifact").unwrap();
+        fs::write(root.path().join("loose.txt"), "outside any repo").unwrap();
+        let git = |args: &[&str]| {
+            std::process::Command::new("git")
+                .arg("-C")
+                .arg(&repo)
+                .args(args)
+                .output()
+                .map(|output| output.status.success())
+                .unwrap_or(false)
+        };
+        if !git(&["init", "-q"]) || !git(&["add", "main.rs"]) {
+            eprintln!("git unavailable; skipping");
+            return;
+        }
+        let index_dir = tempdir().unwrap();
+        init_index(index_dir.path().to_str().unwrap()).unwrap();
+
+        let options = RootOptions {
+            git_tracked_only: true,
+            ..RootOptions::default()
+        };
+        let report = index_root(root.path(), &options).unwrap();
+        commit().unwrap();
+        assert_eq!(report.skipped_untracked, 1);
+        assert_eq!(report.added, 2);
+        assert_eq!(hits("out", SearchDomain::Name), 0);
+        assert_eq!(hits("loose", SearchDomain::Name), 1);
+    }
+
+    #[test]
+    fn aliases_are_indexed_as_pointers_to_their_targets() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let docs = tempdir().unwrap();
+        let bookmark =
+            crate::alias::tests::bookmark_bytes(&["Users", "me", "Reports", "quarterly.pdf"]);
+        fs::write(docs.path().join("Quarterly alias"), bookmark).unwrap();
+        let shortcut = crate::alias::tests::lnk_bytes("C:\\Budgets\\", "forecast.xlsx");
+        fs::write(docs.path().join("Forecast.lnk"), shortcut).unwrap();
+        let index_dir = tempdir().unwrap();
+        init_index(index_dir.path().to_str().unwrap()).unwrap();
+
+        let report = index_root(docs.path(), &RootOptions::default()).unwrap();
+        commit().unwrap();
+        assert_eq!(report.added, 2);
+        assert_eq!(report.content_indexed, 0);
+
+        let found = search(SearchQuery {
+            term: "quarterly.pdf".into(),
+            search_in: SearchDomain::Name,
+            path_globs: Vec::new(),
+            limit: 10,
+            ..SearchQuery::default()
+        })
+        .unwrap();
+        assert_eq!(found.len(), 1);
+        assert!(found[0].is_alias());
+        assert_eq!(
+            found[0].alias_target.as_deref(),
+            Some("/Users/me/Reports/quarterly.pdf")
+        );
+        assert_eq!(hits("forecast", SearchDomain::Name), 1);
+        assert_eq!(hits("Reports", SearchDomain::Content), 0);
+    }
+
+    #[test]
+    fn bundles_are_found_by_name_and_display_name() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let docs = tempdir().unwrap();
+        let contents = docs.path().join("Xcode.app/Contents");
+        fs::create_dir_all(contents.join("Resources")).unwrap();
+        fs::write(contents.join("Resources/notes.txt"), "internal walrus").unwrap();
+        fs::write(
+            contents.join("Info.plist"),
+            "<plist><dict><key>CFBundleName</key><string>Developer Studio</string></dict></plist>",
+        )
+        .unwrap();
+        let index_dir = tempdir().unwrap();
+        init_index(index_dir.path().to_str().unwrap()).unwrap();
+
+        let report = index_root(docs.path(), &RootOptions::default()).unwrap();
+        commit().unwrap();
+        assert_eq!(report.added, 1);
+        assert_eq!(report.content_indexed, 0);
+        assert_eq!(hits("xcode", SearchDomain::Name), 1);
+        assert_eq!(hits("studio", SearchDomain::Name), 1);
+        assert_eq!(hits("walrus", SearchDomain::Both), 0);
+    }
+
+    #[test]
+    fn config_files_index_keys_and_values() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let docs = tempdir().unwrap();
+        fs::write(
+            docs.path().join("settings.json"),
+            r#"{"network": {"ProxyHost": "squid.lan"}}"#,
+        )
+        .unwrap();
+        fs::write(
+            docs.path().join("compose.yml"),
+            "services:\n  db:\n    image: postgres\n",
+        )
+        .unwrap();
+        fs::write(docs.path().join("broken.json"), "{ not json, ProxyPort }").unwrap();
+        let index_dir = tempdir().unwrap();
+        init_index(index_dir.path().to_str().unwrap()).unwrap();
+
+        let report = index_root(docs.path(), &RootOptions::default()).unwrap();
+        commit().unwrap();
+        assert_eq!(report.structured, 2);
+        assert_eq!(report.content_indexed, 3);
+        assert_eq!(hits("ProxyHost", SearchDomain::Content), 1);
+        assert_eq!(hits("postgres", SearchDomain::Content), 1);
+        assert_eq!(hits("ProxyPort", SearchDomain::Content), 1);
+    }
+
+    #[test]
+    fn data_files_index_header_sample_and_shape() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let docs = tempdir().unwrap();
+        let mut csv = String::from("patient_id,diagnosis\n");
+        for row in 0..500 {
+            csv.push_str(&format!("{row},condition{row}\n"));
+        }
+        fs::write(docs.path().join("cohort.csv"), &csv).unwrap();
+        let index_dir = tempdir().unwrap();
+        init_index(index_dir.path().to_str().unwrap()).unwrap();
+
+        let options = RootOptions {
+            max_content_bytes: 64,
+            table_sample_rows: 10,
+            ..RootOptions::default()
+        };
+        let report = index_root(docs.path(), &options).unwrap();
+        commit().unwrap();
+        assert_eq!(report.tables, 1);
+        assert_eq!(report.too_large, 0);
+        assert_eq!(hits("patient_id", SearchDomain::Content), 1);
+        assert_eq!(hits("condition5", SearchDomain::Content), 1);
+        assert_eq!(hits("condition400", SearchDomain::Content), 0);
+
+        let found = search(SearchQuery {
+            term: "diagnosis".into(),
+            search_in: SearchDomain::Content,
+            path_globs: Vec::new(),
+            limit: 10,
+            ..SearchQuery::default()
+        })
+        .unwrap();
+        assert_eq!((found[0].columns, found[0].rows), (Some(2), Some(500)));
+    }
+
+    #[test]
+    fn coverage_explains_missing_content_per_extension() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let index_dir = tempdir().unwrap();
+        init_index(index_dir.path().to_str().unwrap()).unwrap();
+        let docs = tempdir().unwrap();
+        fs::write(docs.path().join("notes.txt"), "plain notes").unwrap();
+        fs::write(docs.path().join("scan.pdf"), b"%PDF-1.7\0\0\x01binary").unwrap();
+        fs::write(docs.path().join("huge.pdf"), vec![b'a'; 64]).unwrap();
+        fs::write(docs.path().join("setup.dmg"), b"image").unwrap();
+
+        reset_extraction_coverage();
+        let options = RootOptions {
+            max_content_bytes: 32,
+            ..RootOptions::default()
+        };
+        index_root(docs.path(), &options).unwrap();
+        commit().unwrap();
+        index_root(docs.path(), &options).unwrap();
+
+        let coverage = extraction_coverage();
+        let row = |ext: &str| coverage.iter().find(|row| row.ext == ext).unwrap().clone();
+        let pdf = row("pdf");
+        assert_eq!(coverage[0].ext, "pdf");
+        assert_eq!(
+            (pdf.files_seen, pdf.too_large, pdf.binary, pdf.unchanged),
+            (4, 1, 1, 2)
+        );
+        assert_eq!((pdf.content_indexed, pdf.names_only()), (0, 2));
+        assert_eq!(row("txt").content_indexed, 1);
+        assert_eq!(row("dmg").skipped_ext, 2);
+    }
+
+    #[test]
+    fn due_retries_are_reread_even_when_unchanged() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let index_dir = tempdir().unwrap();
+        init_index(index_dir.path().to_str().unwrap()).unwrap();
+        let docs = tempdir().unwrap();
+        let draft = docs.path().join("draft.txt");
+        fs::write(&draft, "synced draft").unwrap();
+        let draft = draft.to_str().unwrap();
+
+        let options = RootOptions::default();
+        index_root(docs.path(), &options).unwrap();
+        commit().unwrap();
+
+        // As if the first read had hit a file still downloading from the cloud.
+        let retries = crate::indexer::retries().unwrap();
+        retries.record_failure(draft, 0).unwrap();
+        let report = index_root(docs.path(), &options).unwrap();
+        assert_eq!((report.unchanged, report.content_indexed), (0, 1));
+        assert!(pending_retries().unwrap().is_empty());
+
+        retries.record_failure(draft, i64::MAX / 2).unwrap();
+        let report = index_root(docs.path(), &options).unwrap();
+        assert_eq!(report.unchanged, 1);
+        assert_eq!(pending_retries().unwrap().len(), 1);
+    }
+
+    static WRITES: AtomicUsize = AtomicUsize::new(0);
+
+    /// Rewrites the file the first `limit` times it is about to be read.
+    fn rewrite_while_reading(path: &str, limit: usize) {
+        let write = WRITES.fetch_add(1, AtomicOrdering::SeqCst);
+        if write < limit {
+            fs::write(path, format!("revision {} of the minutes", write + 2)).unwrap();
+        }
+    }
+
+    #[test]
+    fn files_changing_during_read_are_reread_or_queued() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let index_dir = tempdir().unwrap();
+        init_index(index_dir.path().to_str().unwrap()).unwrap();
+        let docs = tempdir().unwrap();
+        let minutes = docs.path().join("minutes.txt");
+        fs::write(&minutes, "revision 1").unwrap();
+
+        WRITES.store(0, AtomicOrdering::SeqCst);
+        *BEFORE_READ.lock().unwrap() = Some(|path| rewrite_while_reading(path, 1));
+        let report = index_root(docs.path(), &RootOptions::default());
+        *BEFORE_READ.lock().unwrap() = None;
+        let report = report.unwrap();
+        commit().unwrap();
+        assert_eq!(report.changed_during_read, 1);
+        assert_eq!((report.added, report.content_indexed), (1, 1));
+        assert_eq!(hits("minutes", SearchDomain::Content), 1);
+        assert!(pending_retries().unwrap().is_empty());
+
+        // A writer that never stops is left to the retry queue.
+        WRITES.store(0, AtomicOrdering::SeqCst);
+        *BEFORE_READ.lock().unwrap() = Some(|path| rewrite_while_reading(path, usize::MAX));
+        let options = RootOptions {
+            force_reindex: true,
+            ..RootOptions::default()
+        };
+        let report = index_root(docs.path(), &options);
+        *BEFORE_READ.lock().unwrap() = None;
+        assert_eq!(report.unwrap().changed_during_read, MAX_CHANGED_REREADS + 1);
+        let pending = pending_retries().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert!(pending[0].path.ends_with("minutes.txt"));
+    }
+
+    #[test]
+    fn reindex_subtree_touches_only_that_folder_and_prunes_vanished_files() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let index_dir = tempdir().unwrap();
+        init_index(index_dir.path().to_str().unwrap()).unwrap();
+        let docs = tempdir().unwrap();
+        let project = docs.path().join("project");
+        fs::create_dir(&project).unwrap();
+        fs::write(project.join("todo.txt"), "ship the beta").unwrap();
+        fs::write(project.join("old.txt"), "obsolete plan").unwrap();
+        fs::write(project.join("disk.dmg"), "image").unwrap();
+        fs::write(docs.path().join("elsewhere.txt"), "obsolete too").unwrap();
+        let all = RootOptions {
+            skip_exts: Vec::new(),
+            ..RootOptions::default()
+        };
+        index_root(docs.path(), &all).unwrap();
+        commit().unwrap();
+        assert_eq!(hits("obsolete", SearchDomain::Content), 2);
+
+        // Written first so it can't reuse the removed file's inode.
+        fs::write(project.join("new.txt"), "fresh plan").unwrap();
+        fs::remove_file(project.join("old.txt")).unwrap();
+        let report = reindex_subtree(&project, &RootOptions::default()).unwrap();
+        commit().unwrap();
+
+        // todo.txt unchanged, new.txt added, old.txt gone, disk.dmg now skipped.
+        assert_eq!(
+            (report.files_seen, report.added, report.unchanged),
+            (3, 1, 1)
+        );
+        assert_eq!(report.pruned, 2);
+        assert_eq!(hits("obsolete", SearchDomain::Content), 1);
+        assert_eq!(hits("fresh", SearchDomain::Content), 1);
+        assert_eq!(hits("disk", SearchDomain::Name), 0);
+        assert!(reindex_subtree(project.join("todo.txt"), &RootOptions::default()).is_err());
+    }
+}
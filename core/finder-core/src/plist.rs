@@ -0,0 +1,378 @@
+use std::fs;
+use std::path::Path;
+
+/// Property lists bigger than this are data stores, not configuration.
+const MAX_PLIST_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Nesting bound so malformed or cyclic binary plists can't recurse forever.
+const MAX_DEPTH: usize = 64;
+
+/// A parsed property list. Dates, data blobs and UIDs are kept only as
+/// [`PlistValue::Other`]; nothing in the index needs their contents.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum PlistValue {
+    String(String),
+    Integer(i64),
+    Real(f64),
+    Bool(bool),
+    Array(Vec<PlistValue>),
+    Dict(Vec<(String, PlistValue)>),
+    Other,
+}
+
+impl PlistValue {
+    /// Look up a key in a dictionary value.
+    pub(crate) fn get(&self, key: &str) -> Option<&PlistValue> {
+        match self {
+            PlistValue::Dict(entries) => entries
+                .iter()
+                .find(|(entry, _)| entry == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            PlistValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Read an XML or binary property list from disk.
+pub(crate) fn read_plist(path: &Path) -> Option<PlistValue> {
+    let size = fs::metadata(path).ok()?.len();
+    if size > MAX_PLIST_BYTES {
+        return None;
+    }
+    parse_plist(&fs::read(path).ok()?)
+}
+
+pub(crate) fn parse_plist(bytes: &[u8]) -> Option<PlistValue> {
+    if bytes.starts_with(b"bplist00") {
+        BinaryPlist::new(bytes)?.root()
+    } else {
+        let text = std::str::from_utf8(bytes).ok()?;
+        let body = text.find("<plist").map(|at| &text[at..])?;
+        let body = &body[body.find('>')? + 1..];
+        XmlPlist { rest: body }.value(0)
+    }
+}
+
+struct XmlPlist<'a> {
+    rest: &'a str,
+}
+
+impl<'a> XmlPlist<'a> {
+    /// Next tag name, skipping text, comments and processing instructions.
+    /// Self-closing tags come back with a trailing `/`.
+    fn next_tag(&mut self) -> Option<&'a str> {
+        loop {
+            let start = self.rest.find('<')?;
+            self.rest = &self.rest[start..];
+            if let Some(comment) = self.rest.strip_prefix("<!--") {
+                self.rest = &comment[comment.find("-->")? + 3..];
+                continue;
+            }
+            let end = self.rest.find('>')?;
+            let tag = &self.rest[1..end];
+            self.rest = &self.rest[end + 1..];
+            if tag.starts_with('?') || tag.starts_with('!') {
+                continue;
+            }
+            let tag = tag.trim();
+            return Some(match tag.find(char::is_whitespace) {
+                Some(space) if !tag.ends_with('/') => &tag[..space],
+                _ => tag,
+            });
+        }
+    }
+
+    /// Text up to the closing tag `name`, with entities decoded.
+    fn text_until(&mut self, name: &str) -> Option<String> {
+        let close = format!("</{name}>");
+        let end = self.rest.find(&close)?;
+        let text = unescape(&self.rest[..end]);
+        self.rest = &self.rest[end + close.len()..];
+        Some(text)
+    }
+
+    fn value(&mut self, depth: usize) -> Option<PlistValue> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        let tag = self.next_tag()?;
+        self.value_for(tag, depth)
+    }
+
+    fn value_for(&mut self, tag: &str, depth: usize) -> Option<PlistValue> {
+        Some(match tag {
+            "string" => PlistValue::String(self.text_until("string")?),
+            "string/" => PlistValue::String(String::new()),
+            "integer" => PlistValue::Integer(self.text_until("integer")?.trim().parse().ok()?),
+            "real" => PlistValue::Real(self.text_until("real")?.trim().parse().ok()?),
+            "true/" => PlistValue::Bool(true),
+            "false/" => PlistValue::Bool(false),
+            "date" | "data" => {
+                self.text_until(tag)?;
+                PlistValue::Other
+            }
+            "date/" | "data/" => PlistValue::Other,
+            "array/" => PlistValue::Array(Vec::new()),
+            "dict/" => PlistValue::Dict(Vec::new()),
+            "array" => {
+                let mut items = Vec::new();
+                loop {
+                    match self.next_tag()? {
+                        "/array" => break,
+                        tag => items.push(self.value_for(tag, depth + 1)?),
+                    }
+                }
+                PlistValue::Array(items)
+            }
+            "dict" => {
+                let mut entries = Vec::new();
+                loop {
+                    match self.next_tag()? {
+                        "/dict" => break,
+                        "key" => {
+                            let key = self.text_until("key")?;
+                            entries.push((key, self.value(depth + 1)?));
+                        }
+                        _ => return None,
+                    }
+                }
+                PlistValue::Dict(entries)
+            }
+            _ => return None,
+        })
+    }
+}
+
+fn unescape(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// `bplist00`: objects addressed through an offset table described by a
+/// 32-byte trailer at the end of the file.
+struct BinaryPlist<'a> {
+    bytes: &'a [u8],
+    offsets: Vec<usize>,
+    ref_size: usize,
+    top: usize,
+}
+
+impl<'a> BinaryPlist<'a> {
+    fn new(bytes: &'a [u8]) -> Option<Self> {
+        let trailer = bytes.get(bytes.len().checked_sub(32)?..)?;
+        let offset_size = trailer[6] as usize;
+        let ref_size = trailer[7] as usize;
+        let count = be_uint(&trailer[8..16]) as usize;
+        let top = be_uint(&trailer[16..24]) as usize;
+        let table = be_uint(&trailer[24..32]) as usize;
+        if offset_size == 0 || ref_size == 0 || count > bytes.len() {
+            return None;
+        }
+        let offsets = (0..count)
+            .map(|index| {
+                let at = table.checked_add(index * offset_size)?;
+                bytes
+                    .get(at..at + offset_size)
+                    .map(|raw| be_uint(raw) as usize)
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self {
+            bytes,
+            offsets,
+            ref_size,
+            top,
+        })
+    }
+
+    fn root(&self) -> Option<PlistValue> {
+        self.object(self.top, 0)
+    }
+
+    fn object(&self, index: usize, depth: usize) -> Option<PlistValue> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        let at = *self.offsets.get(index)?;
+        let marker = *self.bytes.get(at)?;
+        let low = (marker & 0x0f) as usize;
+        Some(match marker >> 4 {
+            0x0 => match marker {
+                0x08 => PlistValue::Bool(false),
+                0x09 => PlistValue::Bool(true),
+                _ => PlistValue::Other,
+            },
+            0x1 => {
+                let raw = self.bytes.get(at + 1..at + 1 + (1 << low))?;
+                PlistValue::Integer(be_uint(raw) as i64)
+            }
+            0x2 => {
+                let raw = self.bytes.get(at + 1..at + 1 + (1 << low))?;
+                match raw.len() {
+                    4 => PlistValue::Real(f32::from_be_bytes(raw.try_into().ok()?) as f64),
+                    8 => PlistValue::Real(f64::from_be_bytes(raw.try_into().ok()?)),
+                    _ => return None,
+                }
+            }
+            0x5 => {
+                let (len, start) = self.length(at, low)?;
+                let raw = self.bytes.get(start..start.checked_add(len)?)?;
+                PlistValue::String(String::from_utf8_lossy(raw).into_owned())
+            }
+            0x6 => {
+                let (len, start) = self.length(at, low)?;
+                let raw = self
+                    .bytes
+                    .get(start..start.checked_add(len.checked_mul(2)?)?)?;
+                let units: Vec<u16> = raw
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                    .collect();
+                PlistValue::String(String::from_utf16_lossy(&units))
+            }
+            0xa => {
+                let (len, start) = self.length(at, low)?;
+                let items = (0..len)
+                    .map(|slot| self.object(self.reference(start, slot)?, depth + 1))
+                    .collect::<Option<Vec<_>>>()?;
+                PlistValue::Array(items)
+            }
+            0xd => {
+                let (len, start) = self.length(at, low)?;
+                let mut entries = Vec::with_capacity(len.min(1024));
+                for slot in 0..len {
+                    let key = self.object(self.reference(start, slot)?, depth + 1)?;
+                    let value = self.object(self.reference(start, len + slot)?, depth + 1)?;
+                    let PlistValue::String(key) = key else {
+                        return None;
+                    };
+                    entries.push((key, value));
+                }
+                PlistValue::Dict(entries)
+            }
+            _ => PlistValue::Other,
+        })
+    }
+
+    /// Element count and where the payload starts. A low nibble of 0xf means
+    /// the count follows as an integer object.
+    fn length(&self, at: usize, low: usize) -> Option<(usize, usize)> {
+        if low != 0x0f {
+            return Some((low, at + 1));
+        }
+        let marker = *self.bytes.get(at + 1)?;
+        if marker >> 4 != 0x1 {
+            return None;
+        }
+        let width = 1usize << (marker & 0x0f);
+        let raw = self.bytes.get(at + 2..at + 2 + width)?;
+        let len = be_uint(raw) as usize;
+        (len <= self.bytes.len()).then_some((len, at + 2 + width))
+    }
+
+    fn reference(&self, start: usize, slot: usize) -> Option<usize> {
+        let at = start.checked_add(slot.checked_mul(self.ref_size)?)?;
+        let raw = self.bytes.get(at..at.checked_add(self.ref_size)?)?;
+        Some(be_uint(raw) as usize)
+    }
+}
+
+fn be_uint(raw: &[u8]) -> u64 {
+    raw.iter()
+        .take(8)
+        .fold(0u64, |acc, byte| (acc << 8) | u64::from(*byte))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{parse_plist, PlistValue};
+
+    /// Binary plist holding a single flat dictionary of ASCII strings.
+    pub(crate) fn binary_plist(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut bytes = b"bplist00".to_vec();
+        let mut offsets = Vec::new();
+        let count = 1 + entries.len() * 2;
+        offsets.push(bytes.len());
+        bytes.push(0xd0 | entries.len() as u8);
+        for slot in 1..count {
+            bytes.push(slot as u8);
+        }
+        let strings = entries
+            .iter()
+            .map(|(key, _)| *key)
+            .chain(entries.iter().map(|(_, value)| *value));
+        for string in strings {
+            offsets.push(bytes.len());
+            bytes.push(0x5f);
+            bytes.extend([0x10, string.len() as u8]);
+            bytes.extend(string.as_bytes());
+        }
+        let table = bytes.len();
+        for offset in &offsets {
+            bytes.push(*offset as u8);
+        }
+        bytes.extend([0u8; 6]);
+        bytes.extend([1u8, 1u8]);
+        bytes.extend((count as u64).to_be_bytes());
+        bytes.extend(0u64.to_be_bytes());
+        bytes.extend((table as u64).to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn parses_xml_and_binary_plists() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <!-- shown in Finder -->
+    <key>CFBundleDisplayName</key>
+    <string>Tom &amp; Jerry</string>
+    <key>LSUIElement</key>
+    <true/>
+    <key>Ports</key>
+    <array><integer>80</integer><real>1.5</real><string/></array>
+</dict>
+</plist>"#;
+        let root = parse_plist(xml.as_bytes()).unwrap();
+        assert_eq!(
+            root.get("CFBundleDisplayName").and_then(PlistValue::as_str),
+            Some("Tom & Jerry")
+        );
+        assert_eq!(root.get("LSUIElement"), Some(&PlistValue::Bool(true)));
+        assert_eq!(
+            root.get("Ports"),
+            Some(&PlistValue::Array(vec![
+                PlistValue::Integer(80),
+                PlistValue::Real(1.5),
+                PlistValue::String(String::new()),
+            ]))
+        );
+
+        let binary = binary_plist(&[("CFBundleName", "Preview"), ("Kind", "viewer")]);
+        let root = parse_plist(&binary).unwrap();
+        assert_eq!(
+            root.get("CFBundleName").and_then(PlistValue::as_str),
+            Some("Preview")
+        );
+        assert_eq!(
+            root.get("Kind").and_then(PlistValue::as_str),
+            Some("viewer")
+        );
+
+        assert!(parse_plist(b"bplist00 truncated").is_none());
+        assert!(parse_plist(b"<plist><dict><key>a</key>").is_none());
+    }
+}
@@ -0,0 +1,508 @@
+use crate::extract_structured::json_fields;
+use crate::indexer;
+use crate::report::json_string;
+use anyhow::{anyhow, bail, Context, Result};
+use once_cell::sync::Lazy;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const DEFAULT_PLUGIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Name of the file, inside the index directory, holding the plugins
+/// registered with [`set_extractor_plugins`]: one per line,
+/// `name | extensions | program and arguments`, with an optional fourth
+/// field giving the timeout in seconds, e.g.
+/// `books | epub, mobi | /usr/local/bin/epub-text --plain | 60`. The
+/// program and its arguments are separated by whitespace. Lines starting
+/// with `#` are comments.
+pub const PLUGINS_FILE_NAME: &str = ".finder-plugins";
+
+/// A program that extracts text from formats finder-core doesn't read
+/// itself, such as EPUB books or CAD drawings. Registered for every root
+/// with [`set_extractor_plugins`], or for one through
+/// [`RootOptions::extractor_plugins`](crate::RootOptions); the program is
+/// started on first use and kept running for later files.
+///
+/// It reads one JSON request per line on stdin,
+/// `{"path": "/books/moby.epub", "max_bytes": 1572864}`, and answers each
+/// with one JSON line on stdout that repeats the request's path,
+/// `{"path": "/books/moby.epub", "text": "Call me Ishmael.", "metadata":
+/// {"title": "Moby Dick"}}`; answers for another path are skipped.
+/// Text past `max_bytes` is cut, and metadata values are indexed as
+/// `key: value` lines after it. `{"error": "..."}`, or no `text`, makes the
+/// file fall back to plain-text extraction. Stdout lines not starting with
+/// `{` are ignored, so a plugin may log there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtractorPlugin {
+    /// Shown in log messages.
+    pub name: String,
+    /// Lowercased extensions (without the dot) the plugin extracts; these
+    /// take precedence over the built-in extractors.
+    pub extensions: Vec<String>,
+    pub program: PathBuf,
+    pub args: Vec<String>,
+    /// Longest one file may take before the plugin is killed.
+    pub timeout: Duration,
+}
+
+impl ExtractorPlugin {
+    pub fn new<I, S>(name: &str, program: impl Into<PathBuf>, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self {
+            name: name.to_string(),
+            extensions: extensions
+                .into_iter()
+                .map(|ext| ext.as_ref().trim_start_matches('.').to_ascii_lowercase())
+                .collect(),
+            program: program.into(),
+            args: Vec::new(),
+            timeout: DEFAULT_PLUGIN_TIMEOUT,
+        }
+    }
+
+    pub(crate) fn handles(&self, ext: &str) -> bool {
+        self.extensions
+            .iter()
+            .any(|handled| handled.eq_ignore_ascii_case(ext))
+    }
+}
+
+/// Register `plugins` for every root indexed into the open index, after
+/// any a root lists in
+/// [`RootOptions::extractor_plugins`](crate::RootOptions). Replaces the
+/// plugins registered before and saves them next to the index; an index
+/// opened later reads them back.
+pub fn set_extractor_plugins(plugins: Vec<ExtractorPlugin>) -> Result<()> {
+    indexer::plugins()?.replace(plugins)
+}
+
+/// The plugins registered with [`set_extractor_plugins`].
+pub fn extractor_plugins() -> Result<Vec<ExtractorPlugin>> {
+    Ok(indexer::plugins()?.plugins.read().unwrap().clone())
+}
+
+/// Extractor plugins registered for one index, loaded from and saved to its
+/// directory; in-memory indexes keep them for the process lifetime.
+pub(crate) struct PluginRegistry {
+    path: Option<PathBuf>,
+    plugins: RwLock<Vec<ExtractorPlugin>>,
+}
+
+impl PluginRegistry {
+    pub(crate) fn open(dir: Option<&Path>) -> Self {
+        let path = dir.map(|dir| dir.join(PLUGINS_FILE_NAME));
+        let plugins = path
+            .as_deref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|raw| parse_plugins(&raw))
+            .unwrap_or_default();
+        Self {
+            path,
+            plugins: RwLock::new(plugins),
+        }
+    }
+
+    fn replace(&self, plugins: Vec<ExtractorPlugin>) -> Result<()> {
+        let mut guard = self.plugins.write().unwrap();
+        if let Some(path) = &self.path {
+            let mut raw = String::new();
+            for plugin in &plugins {
+                raw.push_str(&plugin_line(plugin)?);
+                raw.push('\n');
+            }
+            if raw.is_empty() {
+                raw.push('\n');
+            }
+            fs::write(path, raw).with_context(|| format!("failed writing {}", path.display()))?;
+        }
+        *guard = plugins;
+        Ok(())
+    }
+
+    /// The first registered plugin for `ext`.
+    pub(crate) fn plugin_for(&self, ext: Option<&str>) -> Option<ExtractorPlugin> {
+        plugin_for(&self.plugins.read().unwrap(), ext).cloned()
+    }
+}
+
+fn plugin_line(plugin: &ExtractorPlugin) -> Result<String> {
+    let program = plugin.program.to_string_lossy();
+    let words = std::iter::once(program.as_ref()).chain(plugin.args.iter().map(String::as_str));
+    let mut command = Vec::new();
+    for word in words {
+        if word.is_empty() || word.contains(char::is_whitespace) || word.contains('|') {
+            bail!(
+                "extractor plugin {} can't be saved with {word:?}",
+                plugin.name
+            );
+        }
+        command.push(word);
+    }
+    if plugin.name.contains(['|', '\n']) {
+        bail!("extractor plugin name {:?} can't be saved", plugin.name);
+    }
+    Ok(format!(
+        "{} | {} | {} | {}",
+        plugin.name.trim(),
+        plugin.extensions.join(", "),
+        command.join(" "),
+        plugin.timeout.as_secs_f64()
+    ))
+}
+
+fn parse_plugins(raw: &str) -> Vec<ExtractorPlugin> {
+    raw.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let parsed = parse_plugin(line);
+            if parsed.is_none() {
+                eprintln!("[plugins] ignoring malformed line in {PLUGINS_FILE_NAME}: {line}");
+            }
+            parsed
+        })
+        .collect()
+}
+
+fn parse_plugin(line: &str) -> Option<ExtractorPlugin> {
+    let mut fields = line.split('|').map(str::trim);
+    let name = fields.next().filter(|name| !name.is_empty())?;
+    let extensions: Vec<&str> = fields
+        .next()?
+        .split(',')
+        .map(str::trim)
+        .filter(|ext| !ext.is_empty())
+        .collect();
+    let mut command = fields.next()?.split_whitespace();
+    let program = command.next()?;
+    let timeout = match fields.next() {
+        Some(secs) => Duration::try_from_secs_f64(secs.parse().ok()?).ok()?,
+        None => DEFAULT_PLUGIN_TIMEOUT,
+    };
+    if fields.next().is_some() {
+        return None;
+    }
+    Some(ExtractorPlugin {
+        args: command.map(str::to_string).collect(),
+        timeout,
+        ..ExtractorPlugin::new(name, program, extensions)
+    })
+}
+
+/// The first of `plugins` registered for `ext`.
+pub(crate) fn plugin_for<'a>(
+    plugins: &'a [ExtractorPlugin],
+    ext: Option<&str>,
+) -> Option<&'a ExtractorPlugin> {
+    let ext = ext?;
+    plugins.iter().find(|plugin| plugin.handles(ext))
+}
+
+/// Text a plugin extracted, with its metadata lines appended.
+type Answer = Result<Option<String>>;
+
+/// A spawned plugin, its request pipe, and the lines its reader thread
+/// has read off stdout.
+struct RunningPlugin {
+    child: Child,
+    stdin: ChildStdin,
+    answers: Receiver<String>,
+}
+
+impl Drop for RunningPlugin {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// One plugin's process, locked on its own so files waiting on a slow
+/// plugin don't hold up the others.
+struct PluginSlot {
+    config: ExtractorPlugin,
+    /// `None` until first use, and after a crash or timeout.
+    running: Mutex<Option<RunningPlugin>>,
+}
+
+/// Plugins used so far, each process reused until it crashes or times out.
+static PLUGINS: Lazy<Mutex<Vec<Arc<PluginSlot>>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Ask `plugin` for the text of `path`, at most `max_bytes` of it. The
+/// outer error means the plugin crashed, answered garbage or took over its
+/// timeout; it is restarted for the next file.
+pub(crate) fn extract_with_plugin(
+    plugin: &ExtractorPlugin,
+    path: &str,
+    max_bytes: usize,
+) -> Result<Answer> {
+    let slot = {
+        let mut slots = PLUGINS.lock().unwrap();
+        match slots.iter().find(|slot| slot.config == *plugin) {
+            Some(slot) => slot.clone(),
+            None => {
+                let slot = Arc::new(PluginSlot {
+                    config: plugin.clone(),
+                    running: Mutex::new(None),
+                });
+                slots.push(slot.clone());
+                slot
+            }
+        }
+    };
+    let mut running = slot.running.lock().unwrap();
+    if running.is_none() {
+        *running = Some(spawn(plugin)?);
+    }
+    let answer = ask(plugin, running.as_mut().unwrap(), path, max_bytes);
+    if answer.is_err() {
+        // Killed on drop; the next file starts a fresh one.
+        *running = None;
+    }
+    answer
+}
+
+fn ask(
+    plugin: &ExtractorPlugin,
+    running: &mut RunningPlugin,
+    path: &str,
+    max_bytes: usize,
+) -> Result<Answer> {
+    let request = format!(
+        "{{\"path\": {}, \"max_bytes\": {max_bytes}}}\n",
+        json_string(path)
+    );
+    running
+        .stdin
+        .write_all(request.as_bytes())
+        .and_then(|()| running.stdin.flush())
+        .with_context(|| format!("extractor plugin {} stopped reading", plugin.name))?;
+    let deadline = Instant::now() + plugin.timeout;
+    loop {
+        let wait = deadline.saturating_duration_since(Instant::now());
+        let line = match running.answers.recv_timeout(wait) {
+            Ok(line) => line,
+            Err(RecvTimeoutError::Timeout) => bail!(
+                "extractor plugin {} took over {:?} on {path}",
+                plugin.name,
+                plugin.timeout
+            ),
+            Err(RecvTimeoutError::Disconnected) => {
+                bail!("extractor plugin {} died reading {path}", plugin.name)
+            }
+        };
+        let fields = json_fields(&line)
+            .ok_or_else(|| anyhow!("extractor plugin {} answered {line:?}", plugin.name))?;
+        match fields.iter().find(|(key, _)| key == "path") {
+            Some((_, answered)) if answered == path => {
+                return Ok(answer_from_fields(fields, max_bytes))
+            }
+            Some((_, answered)) => {
+                eprintln!(
+                    "[plugins] {} answered for {answered} while asked for {path}; skipped",
+                    plugin.name
+                );
+            }
+            None => bail!(
+                "extractor plugin {} answered without the path it was asked for",
+                plugin.name
+            ),
+        }
+    }
+}
+
+fn answer_from_fields(fields: Vec<(String, String)>, max_bytes: usize) -> Answer {
+    let mut text = None;
+    let mut metadata = String::new();
+    for (key, value) in fields {
+        if key == "error" {
+            return Err(anyhow!(value));
+        }
+        if key == "text" {
+            text = Some(value);
+        } else if let Some(key) = key.strip_prefix("metadata.") {
+            if !value.is_empty() {
+                metadata.push_str(&format!("{key}: {value}\n"));
+            }
+        }
+    }
+    let Some(mut text) = text.filter(|text| !text.is_empty()) else {
+        return Ok(None);
+    };
+    if text.len() > max_bytes {
+        let mut end = max_bytes;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+    text.push('\n');
+    text.push_str(&metadata);
+    Ok(Some(text))
+}
+
+fn spawn(plugin: &ExtractorPlugin) -> Result<RunningPlugin> {
+    let mut child = Command::new(&plugin.program)
+        .args(&plugin.args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .with_context(|| {
+            format!(
+                "failed starting extractor plugin {} ({})",
+                plugin.name,
+                plugin.program.display()
+            )
+        })?;
+    let stdin = child.stdin.take().expect("piped stdin");
+    let stdout = child.stdout.take().expect("piped stdout");
+    let (answers_tx, answers) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            let Ok(line) = line else { return };
+            let line = line.trim();
+            if line.starts_with('{') && answers_tx.send(line.to_string()).is_err() {
+                return;
+            }
+        }
+    });
+    Ok(RunningPlugin {
+        child,
+        stdin,
+        answers,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extractor_plugins, set_extractor_plugins, ExtractorPlugin};
+    use crate::extract_structured::json_fields;
+    use crate::pipeline::{index_root, RootOptions};
+    use crate::query::{SearchDomain, SearchQuery};
+    use crate::report::json_string;
+    use crate::{close_index, commit, init_index, init_index_in_memory, search};
+    use std::io::{self, BufRead, Write};
+    use std::time::Duration;
+    use std::{env, fs};
+    use tempfile::tempdir;
+
+    const CHILD_PLUGIN_ARG: &str = "--finder-test-plugin";
+
+    fn book_plugin() -> ExtractorPlugin {
+        ExtractorPlugin {
+            args: [
+                "plugins::tests::child_plugin",
+                "--exact",
+                "--nocapture",
+                "--",
+                CHILD_PLUGIN_ARG,
+            ]
+            .map(String::from)
+            .to_vec(),
+            timeout: Duration::from_secs(2),
+            ..ExtractorPlugin::new("books", env::current_exe().unwrap(), [".BOOK"])
+        }
+    }
+
+    fn content_hits(term: &str) -> Vec<String> {
+        let mut names: Vec<String> = search(SearchQuery {
+            term: term.into(),
+            search_in: SearchDomain::Content,
+            ..SearchQuery::default()
+        })
+        .unwrap()
+        .into_iter()
+        .map(|hit| hit.name)
+        .collect();
+        names.sort();
+        names
+    }
+
+    /// A plugin served from this test binary: answers with the file's text
+    /// upper-cased and its name as the title, hangs on `hang.book` and
+    /// answers for another file before `chatty.book`.
+    #[test]
+    fn child_plugin() {
+        if !env::args().any(|arg| arg == CHILD_PLUGIN_ARG) {
+            return;
+        }
+        let mut stdout = io::stdout().lock();
+        // Ends the line the test harness started.
+        writeln!(stdout).unwrap();
+        for line in io::stdin().lock().lines() {
+            let fields = json_fields(&line.unwrap()).unwrap();
+            let path = &fields.iter().find(|(key, _)| key == "path").unwrap().1;
+            if path.ends_with("hang.book") {
+                std::thread::sleep(Duration::from_secs(60));
+            }
+            if path.ends_with("chatty.book") {
+                writeln!(stdout, "{{\"path\": \"/elsewhere\", \"text\": \"STALE\"}}").unwrap();
+            }
+            let text = fs::read_to_string(path).unwrap().to_uppercase();
+            let title = path.rsplit('/').next().unwrap();
+            writeln!(
+                stdout,
+                "{{\"path\": {}, \"text\": {}, \"metadata\": {{\"title\": {}, \"pages\": 3}}}}",
+                json_string(path),
+                json_string(&text),
+                json_string(title)
+            )
+            .unwrap();
+            stdout.flush().unwrap();
+        }
+        std::process::exit(0);
+    }
+
+    #[test]
+    fn plugins_extract_their_formats_and_survive_hangs() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        init_index_in_memory().unwrap();
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("moby.book"), "call me ishmael").unwrap();
+        fs::write(dir.path().join("hang.book"), "never read").unwrap();
+        fs::write(dir.path().join("plain.txt"), "call me later").unwrap();
+        let options = RootOptions {
+            extractor_plugins: vec![book_plugin()],
+            ..RootOptions::default()
+        };
+        let report = index_root(dir.path(), &options).unwrap();
+        assert_eq!(report.plugin_extracted, 1);
+        assert_eq!(report.plugin_failures, 1);
+        commit().unwrap();
+
+        assert_eq!(content_hits("ishmael"), ["moby.book"]);
+        assert_eq!(content_hits("title moby"), ["moby.book"]);
+        // The hung file fell back to plain text.
+        assert_eq!(content_hits("never"), ["hang.book"]);
+    }
+
+    #[test]
+    fn registered_plugins_persist_and_serve_every_root() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let index_dir = tempdir().unwrap();
+        let index_path = index_dir.path().to_str().unwrap();
+        init_index(index_path).unwrap();
+        set_extractor_plugins(vec![book_plugin()]).unwrap();
+        close_index();
+        init_index(index_path).unwrap();
+        assert_eq!(extractor_plugins().unwrap(), vec![book_plugin()]);
+
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("chatty.book"), "queequeg harpoons").unwrap();
+        let report = index_root(dir.path(), &RootOptions::default()).unwrap();
+        assert_eq!(report.plugin_extracted, 1);
+        commit().unwrap();
+        assert_eq!(content_hits("queequeg"), ["chatty.book"]);
+        // The answer for another file was skipped.
+        assert!(content_hits("stale").is_empty());
+    }
+}
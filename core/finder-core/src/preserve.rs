@@ -0,0 +1,407 @@
+use crate::export::{export_hits, ExportConflict, ExportMode};
+use crate::query::{search_all, SearchQuery};
+use crate::report::{format_utc, now};
+use crate::sha256::{hash_bytes, hash_file, Sha256};
+use anyhow::{bail, Context, Result};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+/// Manifest written next to the preserved copies.
+pub const PRESERVE_MANIFEST_FILE_NAME: &str = "manifest.tsv";
+/// The manifest's own digest, in `shasum -a 256 -c` format.
+const MANIFEST_DIGEST_FILE_NAME: &str = "manifest.tsv.sha256";
+/// Copies go in this subfolder, keeping their file names.
+const FILES_DIR: &str = "files";
+
+/// One preserved document, as listed in the manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreservedFile {
+    pub original: String,
+    /// Where the copy lives, relative to the preservation folder.
+    pub copy: String,
+    /// SHA-256 of the copy, lowercase hex. Folders such as bundles hash the
+    /// list of their files with each file's hash.
+    pub sha256: String,
+    /// Bytes of the copy, which are those of the original when it was
+    /// copied; folders count the files in them.
+    pub size: u64,
+    /// The original's modification time when it was copied, also given to
+    /// the copy.
+    pub modified_at: Option<i64>,
+    /// The original's size and modification time are the ones last
+    /// indexed. False when it changed since, so the copy may differ from
+    /// what the search matched; the manifest says `changed`.
+    pub matches_index: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Preservation {
+    pub files: Vec<PreservedFile>,
+    /// Original path and error for matches that could not be copied.
+    pub failed: Vec<(String, String)>,
+    /// SHA-256 of the manifest; record it outside the folder to prove later
+    /// that nothing in it changed.
+    pub manifest_sha256: String,
+}
+
+/// Copy every document `query` matches into the new or empty folder `dest`,
+/// keeping modification times, and write a manifest of each copy's SHA-256,
+/// size, timestamp and original path. Size and timestamp are read from the
+/// original as it is copied; one that changes meanwhile, or whose copy
+/// comes out a different size, is listed in `failed`. Copies, manifest and folders are then
+/// made read-only, and the folder is never added to again. Check it later
+/// with [`verify_preservation`].
+pub fn preserve(query: SearchQuery, dest: &Path) -> Result<Preservation> {
+    if fs::read_dir(dest).is_ok_and(|mut entries| entries.next().is_some()) {
+        bail!(
+            "{} is not empty; preservations are never added to",
+            dest.display()
+        );
+    }
+    let files_dir = dest.join(FILES_DIR);
+    fs::create_dir_all(&files_dir)
+        .with_context(|| format!("failed creating {}", files_dir.display()))?;
+    let term = query.term.clone();
+    let hits = search_all(query)?;
+
+    let mut preservation = Preservation::default();
+    for hit in &hits {
+        let original = Path::new(&hit.path);
+        let before = match DiskState::of(original) {
+            Ok(state) => state,
+            Err(err) => {
+                preservation
+                    .failed
+                    .push((hit.path.clone(), format!("{err:#}")));
+                continue;
+            }
+        };
+        let exported = export_hits(
+            std::slice::from_ref(hit),
+            &files_dir,
+            ExportMode::Copy,
+            ExportConflict::Rename,
+            |_| {},
+        )?;
+        if let Some((original, err)) = exported.failed.into_iter().next() {
+            preservation.failed.push((original, err));
+            continue;
+        }
+        let Some(copy) = exported.exported.into_iter().next() else {
+            continue;
+        };
+        let recorded = (|| -> Result<PreservedFile> {
+            if copy.is_file() {
+                File::options()
+                    .write(true)
+                    .open(&copy)?
+                    .set_modified(before.modified)?;
+            }
+            if DiskState::of(original)? != before {
+                bail!("changed while it was being copied");
+            }
+            let copied = DiskState::of(&copy)?;
+            if copied.size != before.size {
+                bail!(
+                    "the copy holds {} bytes, the original {}",
+                    copied.size,
+                    before.size
+                );
+            }
+            let modified_at = before.unix_modified();
+            let matches_index =
+                hit.modified_at == modified_at && (copy.is_dir() || hit.size == Some(before.size));
+            let relative = copy.strip_prefix(dest)?.to_string_lossy().into_owned();
+            Ok(PreservedFile {
+                original: hit.path.clone(),
+                copy: relative,
+                sha256: hash_path(&copy)?,
+                size: before.size,
+                modified_at,
+                matches_index,
+            })
+        })();
+        match recorded {
+            Ok(file) => preservation.files.push(file),
+            Err(err) => {
+                // Not listed, so it must not stay behind either.
+                let removed = if copy.is_dir() {
+                    fs::remove_dir_all(&copy)
+                } else {
+                    fs::remove_file(&copy)
+                };
+                if let Err(remove_err) = removed {
+                    eprintln!(
+                        "[preserve] failed removing {}: {remove_err}",
+                        copy.display()
+                    );
+                }
+                preservation
+                    .failed
+                    .push((hit.path.clone(), format!("{err:#}")));
+            }
+        }
+    }
+
+    let manifest = render_manifest(&term, &preservation.files);
+    preservation.manifest_sha256 = hash_bytes(manifest.as_bytes());
+    let manifest_path = dest.join(PRESERVE_MANIFEST_FILE_NAME);
+    fs::write(&manifest_path, manifest)
+        .with_context(|| format!("failed writing {}", manifest_path.display()))?;
+    fs::write(
+        dest.join(MANIFEST_DIGEST_FILE_NAME),
+        format!(
+            "{}  {PRESERVE_MANIFEST_FILE_NAME}\n",
+            preservation.manifest_sha256
+        ),
+    )
+    .context("failed writing manifest digest")?;
+    seal(dest)?;
+    Ok(preservation)
+}
+
+/// Re-hash a folder made by [`preserve`]. Returns the copies that are
+/// missing, changed or were added since, relative to `dest`; empty when the
+/// folder is intact. Fails when the manifest itself was altered.
+pub fn verify_preservation(dest: &Path) -> Result<Vec<String>> {
+    let manifest_path = dest.join(PRESERVE_MANIFEST_FILE_NAME);
+    let manifest = fs::read_to_string(&manifest_path)
+        .with_context(|| format!("failed reading {}", manifest_path.display()))?;
+    let digest = fs::read_to_string(dest.join(MANIFEST_DIGEST_FILE_NAME))
+        .context("failed reading manifest digest")?;
+    if digest.split_whitespace().next() != Some(hash_bytes(manifest.as_bytes()).as_str()) {
+        bail!("{} was modified", manifest_path.display());
+    }
+
+    let mut problems = Vec::new();
+    let mut listed = HashSet::new();
+    for line in manifest.lines().filter(|line| !line.starts_with('#')) {
+        let mut parts = line.split('\t');
+        let (Some(sha256), Some(copy)) = (parts.next(), parts.nth(2)) else {
+            continue;
+        };
+        listed.insert(copy.to_string());
+        if hash_path(&dest.join(copy)).ok().as_deref() != Some(sha256) {
+            problems.push(copy.to_string());
+        }
+    }
+    let files_dir = dest.join(FILES_DIR);
+    for entry in fs::read_dir(&files_dir)
+        .with_context(|| format!("failed reading {}", files_dir.display()))?
+    {
+        let entry = entry?;
+        let relative = entry
+            .path()
+            .strip_prefix(dest)?
+            .to_string_lossy()
+            .into_owned();
+        if !listed.contains(&relative) {
+            problems.push(relative);
+        }
+    }
+    Ok(problems)
+}
+
+/// `#` lines describe the preservation; every other line is
+/// `sha256<TAB>size<TAB>modified<TAB>copy<TAB>original<TAB>index`, with
+/// `modified` in UTC ISO 8601, `copy` relative to the folder and `index`
+/// either `current` or `changed` (see [`PreservedFile::matches_index`]).
+fn render_manifest(term: &str, files: &[PreservedFile]) -> String {
+    let mut out = String::new();
+    out.push_str("# preservation manifest v2\n");
+    out.push_str(&format!("# query\t{}\n", term.replace(['\n', '\t'], " ")));
+    out.push_str(&format!("# created\t{}\n", format_utc(now())));
+    out.push_str(&format!("# files\t{}\n", files.len()));
+    out.push_str("# sha256\tsize\tmodified\tcopy\toriginal\tindex\n");
+    for file in files {
+        out.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\n",
+            file.sha256,
+            file.size,
+            file.modified_at.map(format_utc).unwrap_or_default(),
+            file.copy.replace(['\n', '\t'], " "),
+            file.original.replace(['\n', '\t'], " "),
+            if file.matches_index {
+                "current"
+            } else {
+                "changed"
+            },
+        ));
+    }
+    out
+}
+
+/// Size and modification time of a file, or of a folder and the files in it.
+#[derive(Debug, PartialEq, Eq)]
+struct DiskState {
+    size: u64,
+    modified: SystemTime,
+}
+
+impl DiskState {
+    fn of(path: &Path) -> Result<Self> {
+        let meta =
+            fs::metadata(path).with_context(|| format!("failed reading {}", path.display()))?;
+        let mut size = meta.len();
+        if meta.is_dir() {
+            size = 0;
+            for entry in WalkDir::new(path) {
+                let entry = entry?;
+                if entry.file_type().is_file() {
+                    size += entry.metadata()?.len();
+                }
+            }
+        }
+        Ok(Self {
+            size,
+            modified: meta.modified()?,
+        })
+    }
+
+    /// Whole seconds, as the index stores them.
+    fn unix_modified(&self) -> Option<i64> {
+        let since = self.modified.duration_since(UNIX_EPOCH).ok()?;
+        Some(since.as_secs() as i64)
+    }
+}
+
+/// Files hash their contents. Folders hash each file's relative path and
+/// hash, in path order, so bundles can be checked too.
+fn hash_path(path: &Path) -> Result<String> {
+    if !fs::metadata(path)?.is_dir() {
+        return Ok(hash_file(path)?);
+    }
+    let mut hasher = Sha256::new();
+    for entry in WalkDir::new(path).sort_by_file_name() {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            let relative = entry.path().strip_prefix(path)?.to_string_lossy();
+            hasher.update(relative.as_bytes());
+            hasher.update(b"\0");
+            hasher.update(hash_file(entry.path())?.as_bytes());
+            hasher.update(b"\n");
+        }
+    }
+    Ok(hasher.finish_hex())
+}
+
+/// Make everything under `dest` read-only, folders last so their entries
+/// can still be changed first.
+fn seal(dest: &Path) -> Result<()> {
+    for entry in WalkDir::new(dest).contents_first(true) {
+        let entry = entry?;
+        let mut permissions = entry.metadata()?.permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(entry.path(), permissions)
+            .with_context(|| format!("failed sealing {}", entry.path().display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{preserve, verify_preservation, PRESERVE_MANIFEST_FILE_NAME};
+    use crate::query::{SearchDomain, SearchQuery};
+    use crate::{commit, index_root, init_index_in_memory, RootOptions};
+    use std::fs;
+    use std::path::Path;
+    use tempfile::tempdir;
+    use walkdir::WalkDir;
+
+    #[allow(clippy::permissions_set_readonly_false)]
+    fn unseal(dir: &Path) {
+        for entry in WalkDir::new(dir) {
+            let entry = entry.unwrap();
+            let mut permissions = entry.metadata().unwrap().permissions();
+            permissions.set_readonly(false);
+            fs::set_permissions(entry.path(), permissions).unwrap();
+        }
+    }
+
+    #[test]
+    fn preserves_matches_with_a_verifiable_manifest() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        init_index_in_memory().unwrap();
+        let docs = tempdir().unwrap();
+        fs::create_dir_all(docs.path().join("2023")).unwrap();
+        fs::write(docs.path().join("hold.txt"), "project falcon budget").unwrap();
+        fs::write(docs.path().join("2023/hold.txt"), "falcon kickoff").unwrap();
+        fs::write(docs.path().join("other.txt"), "unrelated").unwrap();
+        index_root(docs.path(), &RootOptions::default()).unwrap();
+        commit().unwrap();
+
+        let out = tempdir().unwrap();
+        let dest = out.path().join("hold-2024-001");
+        let query = SearchQuery {
+            term: "falcon".into(),
+            search_in: SearchDomain::Content,
+            ..SearchQuery::default()
+        };
+        let preserved = preserve(query.clone(), &dest).unwrap();
+        let mut copies: Vec<_> = preserved.files.iter().map(|f| f.copy.as_str()).collect();
+        copies.sort();
+        assert_eq!(copies, vec!["files/hold (2).txt", "files/hold.txt"]);
+        assert!(preserved.failed.is_empty());
+        let manifest = fs::read_to_string(dest.join(PRESERVE_MANIFEST_FILE_NAME)).unwrap();
+        assert!(manifest.contains("# query\tfalcon\n"));
+        assert!(verify_preservation(&dest).unwrap().is_empty());
+        assert!(preserve(query, &dest).is_err());
+
+        unseal(&dest);
+        fs::write(dest.join("files/hold.txt"), "tampered").unwrap();
+        fs::write(dest.join("files/extra.txt"), "added").unwrap();
+        let mut problems = verify_preservation(&dest).unwrap();
+        problems.sort();
+        assert_eq!(problems, vec!["files/extra.txt", "files/hold.txt"]);
+        fs::write(dest.join(PRESERVE_MANIFEST_FILE_NAME), "# forged\n").unwrap();
+        assert!(verify_preservation(&dest).is_err());
+    }
+
+    #[test]
+    fn records_the_copied_state_and_flags_files_changed_since_indexing() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        init_index_in_memory().unwrap();
+        let docs = tempdir().unwrap();
+        fs::write(docs.path().join("kept.txt"), "falcon notes").unwrap();
+        fs::write(docs.path().join("edited.txt"), "falcon draft").unwrap();
+        index_root(docs.path(), &RootOptions::default()).unwrap();
+        commit().unwrap();
+        fs::write(
+            docs.path().join("edited.txt"),
+            "falcon draft, now much longer",
+        )
+        .unwrap();
+
+        let out = tempdir().unwrap();
+        let dest = out.path().join("hold");
+        let query = SearchQuery {
+            term: "falcon".into(),
+            search_in: SearchDomain::Content,
+            ..SearchQuery::default()
+        };
+        let preserved = preserve(query, &dest).unwrap();
+        assert!(preserved.failed.is_empty());
+        let edited = preserved
+            .files
+            .iter()
+            .find(|f| f.copy == "files/edited.txt")
+            .unwrap();
+        assert!(!edited.matches_index);
+        assert_eq!(edited.size, "falcon draft, now much longer".len() as u64);
+        let kept = preserved
+            .files
+            .iter()
+            .find(|f| f.copy == "files/kept.txt")
+            .unwrap();
+        assert!(kept.matches_index);
+        let manifest = fs::read_to_string(dest.join(PRESERVE_MANIFEST_FILE_NAME)).unwrap();
+        assert!(manifest.contains("files/edited.txt\t"));
+        assert!(manifest
+            .lines()
+            .any(|l| l.contains("files/edited.txt") && l.ends_with("\tchanged")));
+        assert!(verify_preservation(&dest).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,145 @@
+use once_cell::sync::Lazy;
+use std::sync::{Condvar, Mutex};
+
+/// Tracks bytes held by in-flight content extraction against the soft memory
+/// limit, after subtracting the writer heap (which tantivy caps on its own).
+struct Governor {
+    state: Mutex<GovernorState>,
+    released: Condvar,
+}
+
+#[derive(Default)]
+struct GovernorState {
+    /// Soft limit for writer heap plus extraction buffers; 0 disables it.
+    limit_bytes: usize,
+    writer_heap_bytes: usize,
+    in_flight_bytes: usize,
+    waits: u64,
+    shed: u64,
+}
+
+impl GovernorState {
+    fn extraction_budget(&self) -> usize {
+        self.limit_bytes.saturating_sub(self.writer_heap_bytes)
+    }
+
+    /// A single reservation is always granted, however large, so ingestion
+    /// slows down under pressure but never deadlocks.
+    fn fits(&self, bytes: usize) -> bool {
+        self.limit_bytes == 0
+            || self.in_flight_bytes == 0
+            || self.in_flight_bytes + bytes <= self.extraction_budget()
+    }
+}
+
+static GOVERNOR: Lazy<Governor> = Lazy::new(|| Governor {
+    state: Mutex::new(GovernorState::default()),
+    released: Condvar::new(),
+});
+
+/// Extraction bytes counted against the limit until dropped.
+pub(crate) struct Reservation {
+    bytes: usize,
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        let mut state = GOVERNOR.state.lock().unwrap();
+        state.in_flight_bytes = state.in_flight_bytes.saturating_sub(self.bytes);
+        GOVERNOR.released.notify_all();
+    }
+}
+
+/// Snapshot of the governor for [`MemoryStats`](crate::MemoryStats).
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct PressureSnapshot {
+    pub limit_bytes: usize,
+    pub in_flight_bytes: usize,
+    /// Writer heap plus in-flight extraction as a percentage of the limit.
+    pub percent: u32,
+    pub waits: u64,
+    pub shed: u64,
+}
+
+pub(crate) fn configure(limit_bytes: usize, writer_heap_bytes: usize) {
+    let mut state = GOVERNOR.state.lock().unwrap();
+    state.limit_bytes = limit_bytes;
+    state.writer_heap_bytes = writer_heap_bytes;
+    GOVERNOR.released.notify_all();
+}
+
+/// Reserve `bytes` for an extraction, blocking while other extractions hold
+/// the budget.
+pub(crate) fn reserve(bytes: usize) -> Reservation {
+    let mut state = GOVERNOR.state.lock().unwrap();
+    if !state.fits(bytes) {
+        state.waits += 1;
+        while !state.fits(bytes) {
+            state = GOVERNOR.released.wait(state).unwrap();
+        }
+    }
+    state.in_flight_bytes += bytes;
+    Reservation { bytes }
+}
+
+/// Reserve without blocking; `None` means the caller should shed the work.
+pub(crate) fn try_reserve(bytes: usize) -> Option<Reservation> {
+    let mut state = GOVERNOR.state.lock().unwrap();
+    if !state.fits(bytes) {
+        state.shed += 1;
+        return None;
+    }
+    state.in_flight_bytes += bytes;
+    Some(Reservation { bytes })
+}
+
+pub(crate) fn snapshot() -> PressureSnapshot {
+    let state = GOVERNOR.state.lock().unwrap();
+    let percent = if state.limit_bytes == 0 {
+        0
+    } else {
+        ((state.writer_heap_bytes + state.in_flight_bytes) as u128 * 100
+            / state.limit_bytes as u128) as u32
+    };
+    PressureSnapshot {
+        limit_bytes: state.limit_bytes,
+        in_flight_bytes: state.in_flight_bytes,
+        percent,
+        waits: state.waits,
+        shed: state.shed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{configure, reserve, snapshot, try_reserve};
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn blocks_and_sheds_over_the_soft_limit() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        configure(100, 40);
+
+        let first = reserve(50);
+        assert!(try_reserve(20).is_none());
+        assert_eq!(snapshot().percent, 90);
+
+        let (tx, rx) = mpsc::channel();
+        let waiter = thread::spawn(move || {
+            let _second = reserve(30);
+            tx.send(()).unwrap();
+        });
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+        drop(first);
+        rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        waiter.join().unwrap();
+
+        let stats = snapshot();
+        assert_eq!(stats.in_flight_bytes, 0);
+        assert_eq!(stats.waits, 1);
+        assert_eq!(stats.shed, 1);
+        configure(0, 0);
+    }
+}
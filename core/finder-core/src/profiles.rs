@@ -0,0 +1,161 @@
+use crate::indexer;
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tantivy::Index;
+
+/// One named index under a shared base directory, e.g. "Work" and
+/// "Personal" under the app's data directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexProfile {
+    pub name: String,
+    pub path: PathBuf,
+    /// Bytes used by the index files, sidecars included.
+    pub size_bytes: u64,
+    /// Whether this is the index currently opened with `init_index*`.
+    pub in_use: bool,
+}
+
+/// Every index directly under `base`, by name. Directories without an index
+/// are left out; a missing `base` lists nothing.
+pub fn list_indexes(base: &Path) -> Result<Vec<IndexProfile>> {
+    let entries = match fs::read_dir(base) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).with_context(|| format!("failed listing {}", base.display())),
+    };
+    let mut profiles = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        if entry.file_type()?.is_dir() && holds_index(&entry.path()) {
+            profiles.push(profile(name, entry.path())?);
+        }
+    }
+    profiles.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(profiles)
+}
+
+/// Create an empty index named `name` under `base`, with the configured
+/// [`ContentAnalyzer`](crate::ContentAnalyzer) and stop words. It is not
+/// opened; pass the returned path to [`init_index`](crate::init_index) to
+/// switch to it.
+pub fn create_index(base: &Path, name: &str) -> Result<IndexProfile> {
+    let path = profile_path(base, name)?;
+    if holds_index(&path) {
+        bail!("index {name:?} already exists");
+    }
+    fs::create_dir_all(&path).with_context(|| format!("failed creating {}", path.display()))?;
+    let schema = indexer::new_schema(Some(&path))?;
+    Index::create_in_dir(&path, schema)
+        .with_context(|| format!("failed creating index in {}", path.display()))?;
+    indexer::write_schema_version(&path)?;
+    profile(name.to_string(), path)
+}
+
+/// Delete the index named `name` under `base` and return the bytes freed.
+/// Refuses the index that is currently open and directories that don't hold
+/// an index, so a bad name can't wipe unrelated data.
+pub fn delete_index(base: &Path, name: &str) -> Result<u64> {
+    let path = profile_path(base, name)?;
+    if !holds_index(&path) {
+        bail!("no index named {name:?} in {}", base.display());
+    }
+    let profile = profile(name.to_string(), path)?;
+    if profile.in_use {
+        bail!("index {name:?} is open; close it before deleting");
+    }
+    fs::remove_dir_all(&profile.path)
+        .with_context(|| format!("failed deleting {}", profile.path.display()))?;
+    Ok(profile.size_bytes)
+}
+
+/// Names become directory names, so they must stay a single, visible path
+/// component.
+fn profile_path(base: &Path, name: &str) -> Result<PathBuf> {
+    let valid = !name.trim().is_empty()
+        && !name.starts_with('.')
+        && !name
+            .chars()
+            .any(|ch| matches!(ch, '/' | '\\' | ':') || ch.is_control());
+    if !valid {
+        bail!("invalid index name {name:?}");
+    }
+    Ok(base.join(name))
+}
+
+fn holds_index(path: &Path) -> bool {
+    path.join("meta.json").is_file()
+}
+
+fn profile(name: String, path: PathBuf) -> Result<IndexProfile> {
+    let size_bytes = dir_size(&path)?;
+    let in_use = indexer::open_dir().is_some_and(|open| same_dir(&open, &path));
+    Ok(IndexProfile {
+        name,
+        path,
+        size_bytes,
+        in_use,
+    })
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+fn dir_size(path: &Path) -> Result<u64> {
+    let mut total = 0;
+    for entry in fs::read_dir(path).with_context(|| format!("failed listing {}", path.display()))? {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        total += if meta.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            meta.len()
+        };
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{create_index, delete_index, list_indexes};
+    use crate::{close_index, init_index};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn creates_lists_and_safely_deletes_profiles() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let base = tempdir().unwrap();
+        assert!(list_indexes(&base.path().join("missing"))
+            .unwrap()
+            .is_empty());
+
+        let work = create_index(base.path(), "Work").unwrap();
+        create_index(base.path(), "Personal").unwrap();
+        fs::create_dir(base.path().join("Downloads")).unwrap();
+        assert!(create_index(base.path(), "Work").is_err());
+        assert!(create_index(base.path(), "../escape").is_err());
+
+        init_index(work.path.to_str().unwrap()).unwrap();
+        let profiles = list_indexes(base.path()).unwrap();
+        let names: Vec<_> = profiles.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["Personal", "Work"]);
+        assert!(profiles.iter().all(|p| p.size_bytes > 0));
+        assert!(profiles[1].in_use && !profiles[0].in_use);
+
+        assert!(delete_index(base.path(), "Work").is_err());
+        assert!(delete_index(base.path(), "Downloads").is_err());
+        assert!(delete_index(base.path(), "Personal").unwrap() > 0);
+        close_index();
+        delete_index(base.path(), "Work").unwrap();
+        assert!(list_indexes(base.path()).unwrap().is_empty());
+        assert!(base.path().join("Downloads").exists());
+    }
+}
@@ -1,31 +1,468 @@
-use crate::indexer;
-use anyhow::{Context, Result};
-use globset::{GlobBuilder, GlobMatcher};
-use regex::escape;
-use std::cmp::Ordering;
-use tantivy::collector::TopDocs;
-use tantivy::query::{BooleanQuery, BoostQuery, Occur, Query, QueryParser, RegexQuery};
-use tantivy::schema::{Field, TantivyDocument, Value};
+use crate::cancel::{expired, CancelToken, Cancellable};
+use crate::events;
+use crate::extract_plain::read_plain_text;
+use crate::indexer::{self, IndexFields};
+use crate::min_match::MinimumMatchQuery;
+use crate::pathfilter::{PathFilter, PathFilterEngine};
+use crate::report;
+use crate::rewrite;
+use crate::schema::{name_words, MAX_PREFIX_CHARS};
+use crate::stats;
+use crate::term_filters::{extract_term_filters, TermFilters};
+use crate::wildcards::{extract_wildcards, Wildcard};
+use anyhow::{anyhow, bail, Context, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use regex::{escape, RegexBuilder};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::ops::{Bound, Range};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tantivy::collector::{Collector, Count, SegmentCollector};
+use tantivy::columnar::{Column, StrColumn};
+use tantivy::query::{
+    AllQuery, BooleanQuery, BoostQuery, ConstScoreQuery, EmptyQuery, FuzzyTermQuery, Occur, Query,
+    QueryParser, RangeQuery, RegexQuery, TermQuery, TermSetQuery,
+};
+use tantivy::query_grammar::{self, Delimiter, UserInputAst, UserInputLeaf, UserInputLiteral};
+use tantivy::schema::{Field, IndexRecordOption, TantivyDocument, Value};
+use tantivy::snippet::SnippetGenerator;
+use tantivy::{DocAddress, DocId, Index, Score, Searcher, SegmentOrdinal, SegmentReader, Term};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Candidates fetched per requested hit, to leave room for collapsing
+/// documents that belong to the same file.
+const COLLAPSE_OVERFETCH: usize = 4;
+/// Snippets are cut from this much of the start of each file.
+const SNIPPET_SOURCE_BYTES: usize = 256 * 1024;
+const SNIPPET_SNIFF_BYTES: usize = 8192;
+/// Cap on a compiled regex, which bounds the automaton run over the term
+/// dictionary.
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// Whether a document is a file or a folder indexed in its own right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EntryKind {
+    #[default]
+    File,
+    Folder,
+}
+
+impl EntryKind {
+    /// The value of the `kind` field, e.g. in `kind:folder`.
+    pub(crate) fn term(self) -> &'static str {
+        match self {
+            EntryKind::File => "file",
+            EntryKind::Folder => "folder",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum SearchDomain {
     Name,
     Content,
+    #[default]
     Both,
 }
 
-impl Default for SearchDomain {
-    fn default() -> Self {
-        SearchDomain::Both
-    }
-}
-
 #[derive(Debug, Clone)]
 pub struct SearchQuery {
     pub term: String,
     pub search_in: SearchDomain,
-    pub path_glob: Option<String>,
+    /// Only paths matching at least one of these globs, e.g. `**/*.md` and
+    /// `**/*.rst`; empty allows any. Matched case-insensitively against the
+    /// full path.
+    pub path_globs: Vec<String>,
+    /// Leave out paths matching any of these globs, e.g.
+    /// `**/node_modules/**`. Applied like `path_globs`.
+    pub exclude_globs: Vec<String>,
     pub limit: usize,
+    /// Hits to skip before the `limit` returned, for showing later pages.
+    pub offset: usize,
+    /// How `term` is read.
+    pub mode: QueryMode,
+    /// Whether plain words in `term` all have to match or any may.
+    pub default_operator: DefaultOperator,
+    /// How many plain words have to match in names and in content, where
+    /// neither "all" nor "any" suits, e.g. long natural-language queries.
+    pub min_should_match: MinShouldMatch,
+    /// File names have to contain the words of `term` with the case typed,
+    /// so `Makefile` skips `makefile.txt`. Checked against the name as
+    /// stored, on top of the usual matching; [`QueryMode::Regex`] is always
+    /// case-sensitive unless it starts with `(?i)`.
+    pub case_sensitive: bool,
+    /// Show each file once: documents sharing an identity (chunks, hard
+    /// links, stale copies awaiting a merge) or a path (one file indexed
+    /// with and without an inode) fold into the first hit, which is the
+    /// best-scoring under [`SortBy::Score`]. Off lists every matching
+    /// document, e.g. to inspect chunks. On by default.
+    pub collapse: bool,
+    /// Order of the hits; anything but [`SortBy::Score`] is applied while
+    /// collecting, so each page is the true top of that order.
+    ///
+    /// The order is total and stable: hits that tie on the sort key go by
+    /// score, then newest first, then path, so the same index gives the
+    /// same hits in the same order on every refresh, however its segments
+    /// are laid out. With [`SortBy::Score`] the [`RankingProfile`]'s
+    /// tie-breaks come before modification time.
+    pub sort_by: SortBy,
+    /// How hits with equal scores are ordered.
+    pub ranking: RankingProfile,
+    /// Blend how recently a file was modified into its score, so with
+    /// [`SortBy::Score`] fresh files float up the way they do in a
+    /// launcher. `None` ranks on the text match alone.
+    pub recency: Option<RecencyBoost>,
+    /// Folders whose matching documents rank higher, e.g. the Desktop or
+    /// the project the user is working in.
+    pub priority_folders: Vec<PriorityFolder>,
+    /// Scale down hits whose path looks like a backup or an old copy.
+    pub path_penalties: Vec<PathPenalty>,
+    /// Attach a content preview of at most this many characters to hits
+    /// that have indexed content; `None` skips the extra file reads.
+    pub snippet_chars: Option<usize>,
+    /// Stop collecting and loading hits after this long and return what
+    /// was found, with [`SearchPage::timed_out`] set, so a slow regex or a
+    /// huge index can't hold up the UI. `None` waits for every match.
+    pub time_budget: Option<Duration>,
+    /// Also match file names within this many edits (1 or 2) of each query
+    /// word, so typos still find the file. Exact matches still rank first.
+    pub fuzzy: Option<u8>,
+    /// Only files modified at or after this time (unix seconds).
+    pub mtime_min: Option<i64>,
+    /// Only files modified at or before this time (unix seconds).
+    pub mtime_max: Option<i64>,
+    /// Only files of at least this many bytes.
+    pub size_min: Option<u64>,
+    /// Only files of at most this many bytes.
+    pub size_max: Option<u64>,
+    /// Only files with one of these extensions (`"md"` or `".md"`); empty
+    /// allows any. Matches the extension as given, lowercased or uppercased.
+    pub extensions: Vec<String>,
+    /// Only files anywhere below one of these folders; empty allows any.
+    /// Matched on whole path components, so `/work/app` leaves out
+    /// `/work/app-old`.
+    pub roots: Vec<PathBuf>,
+    /// Only files, or only folders; `None` allows both. `kind:folder` in
+    /// `term` does the same.
+    pub kind: Option<EntryKind>,
+    /// Only these paths, e.g. the hits of an earlier search being narrowed
+    /// down; `None` allows any. See [`refine`].
+    pub within: Option<Vec<String>>,
+}
+
+/// A folder whose documents get `boost` added to their score when they
+/// match a query. Applies to everything below the folder.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriorityFolder {
+    pub path: String,
+    pub boost: f32,
+}
+
+impl PriorityFolder {
+    pub fn new(path: impl Into<String>, boost: f32) -> Self {
+        Self {
+            path: path.into(),
+            boost,
+        }
+    }
+}
+
+/// Scales each hit's score by `1 + weight * 0.5^(age / half_life)`: a
+/// file modified just now scores up to `1 + weight` times its text
+/// match, one a half-life old `1 + weight / 2` times, and old files keep
+/// their plain score. Files without a modification time get no boost.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RecencyBoost {
+    pub half_life_days: f32,
+    pub weight: f32,
+}
+
+impl Default for RecencyBoost {
+    /// A month's half-life, doubling the score of files modified today.
+    fn default() -> Self {
+        Self {
+            half_life_days: 30.0,
+            weight: 1.0,
+        }
+    }
+}
+
+impl RecencyBoost {
+    pub fn new(half_life_days: f32, weight: f32) -> Self {
+        Self {
+            half_life_days,
+            weight,
+        }
+    }
+
+    /// What a score is multiplied by for a file modified at `mtime`.
+    pub(crate) fn factor(&self, now: i64, mtime: Option<i64>) -> f32 {
+        let (Some(mtime), true) = (mtime, self.half_life_days > 0.0) else {
+            return 1.0;
+        };
+        let age_days = now.saturating_sub(mtime).max(0) as f64 / 86_400.0;
+        let decay = 0.5f64.powf(age_days / f64::from(self.half_life_days));
+        1.0 + self.weight.max(0.0) * decay as f32
+    }
+}
+
+/// Multiplies the score of hits whose full path matches `pattern`
+/// (a case-insensitive glob) by `factor`. Hits matching several penalties
+/// take all of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathPenalty {
+    pub pattern: String,
+    pub factor: f32,
+}
+
+impl PathPenalty {
+    pub fn new(pattern: impl Into<String>, factor: f32) -> Self {
+        Self {
+            pattern: pattern.into(),
+            factor,
+        }
+    }
+
+    /// Backup folders, archives and Finder/Explorer-style copies.
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            Self::new("*backup*", 0.5),
+            Self::new("*/archive/*", 0.5),
+            Self::new("*/archives/*", 0.5),
+            Self::new("* copy*", 0.6),
+            Self::new("*copy of *", 0.6),
+            Self::new("*.bak", 0.5),
+        ]
+    }
+}
+
+/// Deterministic ordering among hits that score the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RankingProfile {
+    /// Newer files first.
+    Recent,
+    /// Shallower paths first, then shorter names, then newer files, so
+    /// `report.pdf` beats `backup/old/copy of report (3).pdf`.
+    #[default]
+    Shallow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QueryMode {
+    /// Words, quoted phrases and operators such as `-draft`.
+    #[default]
+    Words,
+    /// A regular expression over file names (and paths, unless searching
+    /// [`SearchDomain::Name`]), e.g. `^report_\d{4}\.xlsx$`.
+    Regex,
+    /// As-you-type lookup: every typed word must start a word of the file
+    /// name, so `ann rep` finds `Annual Report.pdf`. Always searches names.
+    Prefix,
+}
+
+/// How [`QueryMode::Words`] combines words that carry no `+` or `-`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DefaultOperator {
+    /// Every word has to match: `budget 2024` finds files with both.
+    #[default]
+    And,
+    /// Any word may match, and files matching more words score higher;
+    /// suits short file names that rarely hold every word typed.
+    Or,
+}
+
+/// How many of the plain words of a [`QueryMode::Words`] term a file has
+/// to match, field by field, in place of the [`DefaultOperator`]. With
+/// `content: Some(ShouldMatch::Words(2))`, `quarterly budget review` finds
+/// files whose content has any two of the three words. A field left `None`
+/// follows the default operator; words marked `+` or `-`, phrases and field
+/// clauses keep their own meaning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MinShouldMatch {
+    pub name: Option<ShouldMatch>,
+    pub content: Option<ShouldMatch>,
+}
+
+impl MinShouldMatch {
+    pub fn is_set(&self) -> bool {
+        self.name.is_some() || self.content.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShouldMatch {
+    /// This many words; more than were typed means all of them.
+    Words(usize),
+    /// This share of the words typed, rounded down.
+    Percent(u8),
+}
+
+impl ShouldMatch {
+    /// Words out of `typed` that have to match, at least one.
+    pub fn of(self, typed: usize) -> usize {
+        let needed = match self {
+            ShouldMatch::Words(words) => words,
+            ShouldMatch::Percent(percent) => typed * usize::from(percent.min(100)) / 100,
+        };
+        needed.clamp(1, typed.max(1))
+    }
+}
+
+/// What hits are ordered by. Hits that tie keep the better-scoring one
+/// first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortBy {
+    /// Best match first, ties broken by [`RankingProfile`].
+    #[default]
+    Score,
+    /// Newest first.
+    Modified,
+    /// Largest first.
+    Size,
+    /// A to Z, ignoring case.
+    Name,
+}
+
+/// Ceilings on what one query may ask for, so a misbehaving client can't
+/// make the process collect millions of hits. Set per index through
+/// [`IndexSettings::query_limits`](crate::IndexSettings).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueryLimits {
+    /// Largest `limit`.
+    pub max_limit: usize,
+    /// Deepest `offset`; every skipped hit is still collected.
+    pub max_offset: usize,
+    /// Longest include or exclude glob or penalty pattern, in bytes.
+    pub max_glob_len: usize,
+    /// Longest term in [`QueryMode::Regex`], in bytes.
+    pub max_regex_len: usize,
+    /// Most `*` or `?` words (`rep*rt`, `*draft*`) in one
+    /// [`QueryMode::Words`] term; each walks the term dictionary.
+    pub max_wildcards: usize,
+}
+
+impl Default for QueryLimits {
+    fn default() -> Self {
+        Self {
+            max_limit: 10_000,
+            max_offset: 100_000,
+            max_glob_len: 1_024,
+            max_regex_len: 512,
+            max_wildcards: 8,
+        }
+    }
+}
+
+/// How much each kind of match counts towards a hit's score in
+/// [`QueryMode::Words`]. Set per index through
+/// [`IndexSettings::ranking`](crate::IndexSettings); the defaults favour
+/// file names over content.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RankingConfig {
+    /// Words found in the file name, within the parsed query.
+    pub name: f32,
+    /// Words found in the file's content, within the parsed query.
+    pub content: f32,
+    /// The parsed query as a whole when names are searched, relative to
+    /// the prefix and typo matches added alongside it.
+    pub words: f32,
+    /// A single-word term that starts the file name, e.g. `rep` for
+    /// `report.pdf`.
+    pub prefix: f32,
+    /// Name words within the query's [`SearchQuery::fuzzy`] edit distance;
+    /// typo matches should score well below exact ones.
+    pub fuzzy: f32,
+}
+
+impl Default for RankingConfig {
+    fn default() -> Self {
+        Self {
+            name: 2.0,
+            content: 1.0,
+            words: 1.5,
+            prefix: 3.0,
+            fuzzy: 0.5,
+        }
+    }
+}
+
+/// Which of the [`QueryLimits`] a query went over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryLimit {
+    Limit,
+    Offset,
+    GlobLength,
+    RegexLength,
+    Wildcards,
+}
+
+/// Why a query was refused before running. Returned inside the
+/// `anyhow::Error` from searches; downcast to it to tell the client which
+/// ceiling it hit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueryLimitExceeded {
+    pub limit: QueryLimit,
+    pub requested: usize,
+    pub maximum: usize,
+}
+
+impl fmt::Display for QueryLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let what = match self.limit {
+            QueryLimit::Limit => "result limit",
+            QueryLimit::Offset => "result offset",
+            QueryLimit::GlobLength => "glob length",
+            QueryLimit::RegexLength => "regex length",
+            QueryLimit::Wildcards => "wildcard count",
+        };
+        write!(
+            f,
+            "{what} of {} exceeds the maximum of {}",
+            self.requested, self.maximum
+        )
+    }
+}
+
+impl std::error::Error for QueryLimitExceeded {}
+
+/// Refuse `query` when it asks for more than `limits` allow.
+pub(crate) fn check_limits(
+    query: &SearchQuery,
+    limits: &QueryLimits,
+) -> Result<(), QueryLimitExceeded> {
+    let check = |limit, requested: usize, maximum: usize| {
+        if requested > maximum {
+            Err(QueryLimitExceeded {
+                limit,
+                requested,
+                maximum,
+            })
+        } else {
+            Ok(())
+        }
+    };
+    check(QueryLimit::Limit, query.limit, limits.max_limit)?;
+    check(QueryLimit::Offset, query.offset, limits.max_offset)?;
+    let globs = query
+        .path_globs
+        .iter()
+        .chain(&query.exclude_globs)
+        .chain(query.path_penalties.iter().map(|penalty| &penalty.pattern));
+    for glob in globs {
+        check(QueryLimit::GlobLength, glob.len(), limits.max_glob_len)?;
+    }
+    if query.mode == QueryMode::Regex {
+        let length = query.term.trim().len();
+        check(QueryLimit::RegexLength, length, limits.max_regex_len)?;
+    }
+    if query.mode == QueryMode::Words {
+        let wildcards = extract_wildcards(&query.term).1.len();
+        check(QueryLimit::Wildcards, wildcards, limits.max_wildcards)?;
+    }
+    Ok(())
 }
 
 impl Default for SearchQuery {
@@ -33,8 +470,54 @@ impl Default for SearchQuery {
         SearchQuery {
             term: String::new(),
             search_in: SearchDomain::Both,
-            path_glob: None,
+            path_globs: Vec::new(),
+            exclude_globs: Vec::new(),
             limit: 50,
+            offset: 0,
+            mode: QueryMode::default(),
+            default_operator: DefaultOperator::default(),
+            min_should_match: MinShouldMatch::default(),
+            collapse: true,
+            sort_by: SortBy::default(),
+            ranking: RankingProfile::default(),
+            priority_folders: Vec::new(),
+            path_penalties: Vec::new(),
+            snippet_chars: None,
+            time_budget: None,
+            fuzzy: None,
+            mtime_min: None,
+            mtime_max: None,
+            size_min: None,
+            size_max: None,
+            extensions: Vec::new(),
+            roots: Vec::new(),
+            kind: None,
+            within: None,
+            recency: None,
+            case_sensitive: false,
+        }
+    }
+}
+
+impl SearchQuery {
+    /// Files whose content has `words` next to each other and in this
+    /// order, e.g. `SearchQuery::phrase("annual general meeting")`, without
+    /// writing query syntax. Quotes and backslashes in `words` are ignored,
+    /// as punctuation is in indexed text.
+    pub fn phrase(words: &str) -> Self {
+        let words: Vec<&str> = words
+            .split(['"', '\\'])
+            .flat_map(str::split_whitespace)
+            .collect();
+        let term = if words.is_empty() {
+            String::new()
+        } else {
+            format!("\"{}\"", words.join(" "))
+        };
+        SearchQuery {
+            term,
+            search_in: SearchDomain::Content,
+            ..SearchQuery::default()
         }
     }
 }
@@ -46,92 +529,640 @@ pub struct SearchHit {
     pub score: f32,
     pub modified_at: Option<i64>,
     pub size: Option<u64>,
+    pub summary: Option<String>,
+    /// Set when the hit is a Finder alias or shortcut; holds its target.
+    pub alias_target: Option<String>,
+    /// Column and row counts for CSV/TSV files.
+    pub columns: Option<u64>,
+    pub rows: Option<u64>,
+    /// Set when the hit is a folder: how many files and folders it holds
+    /// directly. `size` is then the total size of the files below it.
+    pub children: Option<u64>,
+    /// Matching documents collapsed into this hit; above 1 when several
+    /// chunks or copies of the same file matched.
+    pub match_count: usize,
+    /// Preview of the best-matching passage, when the query asked for one.
+    pub snippet: Option<HitSnippet>,
+}
+
+/// A passage of a hit's content with the matched terms marked.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HitSnippet {
+    pub text: String,
+    /// Byte ranges of `text` that matched the query, in order.
+    pub highlighted: Vec<Range<usize>>,
+}
+
+impl HitSnippet {
+    /// `text` HTML-escaped, with matches wrapped in `<b>`.
+    pub fn to_html(&self) -> String {
+        let mut html = String::with_capacity(self.text.len() + 16);
+        let mut from = 0;
+        for range in &self.highlighted {
+            if range.start < from || range.end > self.text.len() {
+                continue;
+            }
+            push_escaped(&mut html, &self.text[from..range.start]);
+            html.push_str("<b>");
+            push_escaped(&mut html, &self.text[range.clone()]);
+            html.push_str("</b>");
+            from = range.end;
+        }
+        push_escaped(&mut html, &self.text[from..]);
+        html
+    }
+}
+
+fn push_escaped(out: &mut String, text: &str) {
+    for ch in text.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(ch),
+        }
+    }
+}
+
+impl SearchHit {
+    pub fn is_alias(&self) -> bool {
+        self.alias_target.is_some()
+    }
+
+    pub fn is_folder(&self) -> bool {
+        self.children.is_some()
+    }
+}
+
+/// One page of hits and how many there are in all.
+#[derive(Debug, Clone, Default)]
+pub struct SearchPage {
+    pub hits: Vec<SearchHit>,
+    /// Hits across all pages, counted as [`count`] does: path globs
+    /// apply, and with [`collapse`](SearchQuery::collapse) each file counts
+    /// once.
+    pub total: usize,
+    /// The query's [`time_budget`](SearchQuery::time_budget) ran out, so
+    /// `hits` and `total` cover only the part of the index searched and
+    /// some hits may lack snippets.
+    pub timed_out: bool,
 }
 
 pub fn search(query: SearchQuery) -> Result<Vec<SearchHit>> {
-    let trimmed = query.term.trim();
-    if trimmed.is_empty() {
-        return Ok(Vec::new());
+    Ok(search_page(query)?.hits)
+}
+
+/// Like [`search`], with the total number of matches for paging.
+pub fn search_page(query: SearchQuery) -> Result<SearchPage> {
+    let searcher = current_searcher()?;
+    let cache = indexer::result_cache()?;
+    // Recency scores move with the clock, so those pages are never reused.
+    if cache.capacity == 0 || query.recency.is_some() {
+        return Ok(run_search_page(query, &searcher, None)?.unwrap_or_default());
+    }
+    let key = format!("{query:?}");
+    let generation = searcher.generation().generation_id();
+    if let Some(page) = cache.get(&key, generation) {
+        stats::record_search();
+        return Ok(page);
     }
+    let page = run_search_page(query, &searcher, None)?.unwrap_or_default();
+    if !page.timed_out {
+        cache.insert(key, generation, &page);
+    }
+    Ok(page)
+}
 
-    let index = indexer::index().context("index not initialized")?;
-    let reader = indexer::reader().context("reader not available")?;
-    let fields = indexer::fields()?;
+/// The last [`IndexSettings::result_cache_entries`](crate::IndexSettings)
+/// pages [`search_page`] returned, keyed by their query. Entries belong to
+/// one reader generation, so a commit or reload empties the cache; so do
+/// the settings that change how a query reads, such as synonyms and stop
+/// words. Relative dates (`modified:today`) keep their first answer until
+/// then; queries with a [`RecencyBoost`] are not cached. A page served from
+/// the cache still counts as a search in [`quick_stats`](crate::quick_stats).
+pub(crate) struct ResultCache {
+    capacity: usize,
+    entries: Mutex<CachedPages>,
+}
+
+#[derive(Default)]
+struct CachedPages {
+    generation: u64,
+    /// Least recently used first.
+    pages: VecDeque<(String, SearchPage)>,
+}
 
-    let mut search_fields = Vec::new();
-    match query.search_in {
-        SearchDomain::Name => search_fields.push(fields.name),
-        SearchDomain::Content => search_fields.push(fields.content),
-        SearchDomain::Both => {
-            search_fields.push(fields.name);
-            search_fields.push(fields.content);
+impl ResultCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(CachedPages::default()),
         }
     }
 
-    let mut parser = QueryParser::for_index(&index, search_fields.clone());
-    if matches!(query.search_in, SearchDomain::Name | SearchDomain::Both) {
-        parser.set_field_boost(fields.name, 2.0);
+    fn get(&self, key: &str, generation: u64) -> Option<SearchPage> {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.generation != generation {
+            entries.generation = generation;
+            entries.pages.clear();
+            return None;
+        }
+        let at = entries.pages.iter().position(|(cached, _)| cached == key)?;
+        let entry = entries.pages.remove(at)?;
+        let page = entry.1.clone();
+        entries.pages.push_back(entry);
+        Some(page)
     }
-    parser.set_conjunction_by_default();
 
-    let parsed_query = parser
-        .parse_query(trimmed)
-        .with_context(|| format!("failed to parse search query: {}", trimmed))?;
+    fn insert(&self, key: String, generation: u64, page: &SearchPage) {
+        let mut entries = self.entries.lock().unwrap();
+        // A reload happened while this page was being searched.
+        if entries.generation != generation {
+            return;
+        }
+        if entries.pages.len() >= self.capacity {
+            entries.pages.pop_front();
+        }
+        entries.pages.push_back((key, page.clone()));
+    }
 
-    let mut subqueries: Vec<(Occur, Box<dyn Query>)> = Vec::new();
-    let main_query: Box<dyn Query> =
-        if matches!(query.search_in, SearchDomain::Name | SearchDomain::Both) {
-            Box::new(BoostQuery::new(parsed_query, 1.5))
-        } else {
-            parsed_query
+    pub(crate) fn clear(&self) {
+        self.entries.lock().unwrap().pages.clear();
+    }
+}
+
+/// Forget cached results of the open index, if any, after a change to how
+/// queries are read.
+pub(crate) fn clear_result_cache() {
+    if let Ok(cache) = indexer::result_cache() {
+        cache.clear();
+    }
+}
+
+/// Instant results while typing: the first `limit` files whose name has
+/// words starting with each word of `typed`. Runs without snippets or
+/// filters; use [`QueryMode::Prefix`] to combine it with other options.
+pub fn search_prefix(typed: &str, limit: usize) -> Result<Vec<SearchHit>> {
+    search(SearchQuery {
+        term: typed.to_string(),
+        search_in: SearchDomain::Name,
+        mode: QueryMode::Prefix,
+        limit,
+        ..SearchQuery::default()
+    })
+}
+
+/// Every file `query` matches, whatever its `limit` and `offset`; fails
+/// when that is more than [`QueryLimits::max_limit`].
+pub(crate) fn search_all(query: SearchQuery) -> Result<Vec<SearchHit>> {
+    let total = search_page(SearchQuery {
+        limit: 1,
+        offset: 0,
+        snippet_chars: None,
+        ..query.clone()
+    })?
+    .total;
+    search(SearchQuery {
+        limit: total.max(1),
+        offset: 0,
+        ..query
+    })
+}
+
+/// How many hits `query` has across all pages, without fetching any, e.g.
+/// to show "1,204 matches". Path globs apply, and with
+/// [`collapse`](SearchQuery::collapse) a file matched in several chunk
+/// documents counts once. Paging and ordering options don't matter.
+pub fn count(query: SearchQuery) -> Result<usize> {
+    let searcher = current_searcher()?;
+    let fields = indexer::fields()?;
+    let Some(built) = build_query(&query, searcher.index(), &fields)? else {
+        return Ok(0);
+    };
+    let path_filter = PathFilter::new(
+        &query.path_globs,
+        &query.exclude_globs,
+        PathFilterEngine::Auto,
+    )?;
+    if path_filter.is_none() && !query.collapse {
+        return searcher
+            .search(&*built, &Count)
+            .context("tantivy search execution failed");
+    }
+    let per_path = searcher
+        .search(&*built, &MatchesPerPath)
+        .context("tantivy search execution failed")?;
+    Ok(total_per_path(
+        per_path,
+        path_filter.as_ref(),
+        query.collapse,
+    ))
+}
+
+/// The hits [`MatchesPerPath`] found, less paths `filter` leaves out,
+/// counting each file once when `collapse` is set.
+fn total_per_path(
+    per_path: HashMap<String, usize>,
+    filter: Option<&PathFilter>,
+    collapse: bool,
+) -> usize {
+    per_path
+        .into_iter()
+        .filter(|(path, _)| filter.is_none_or(|filter| filter.allows(path)))
+        .map(|(_, docs)| if collapse { 1 } else { docs })
+        .sum()
+}
+
+/// Matching documents per path, read from the `path` fast field so
+/// [`count`] can apply globs and collapse without loading documents. A
+/// file's chunk documents share its path.
+struct MatchesPerPath;
+
+struct SegmentMatchesPerPath {
+    paths: Option<StrColumn>,
+    by_ord: Vec<usize>,
+}
+
+impl Collector for MatchesPerPath {
+    type Fruit = HashMap<String, usize>;
+    type Child = SegmentMatchesPerPath;
+
+    fn for_segment(
+        &self,
+        _segment_local_id: SegmentOrdinal,
+        segment: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        let paths = segment.fast_fields().str("path")?;
+        let terms = paths.as_ref().map_or(0, |paths| paths.num_terms());
+        Ok(SegmentMatchesPerPath {
+            paths,
+            by_ord: vec![0; terms],
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, segment_fruits: Vec<Self::Fruit>) -> tantivy::Result<Self::Fruit> {
+        let mut merged = HashMap::new();
+        for fruit in segment_fruits {
+            for (path, docs) in fruit {
+                *merged.entry(path).or_insert(0) += docs;
+            }
+        }
+        Ok(merged)
+    }
+}
+
+impl SegmentCollector for SegmentMatchesPerPath {
+    type Fruit = HashMap<String, usize>;
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        if let Some(ord) = self
+            .paths
+            .as_ref()
+            .and_then(|paths| paths.term_ords(doc).next())
+        {
+            self.by_ord[ord as usize] += 1;
+        }
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        let mut per_path = HashMap::new();
+        let Some(paths) = self.paths else {
+            return per_path;
         };
-    subqueries.push((Occur::Should, main_query));
+        for (ord, docs) in self.by_ord.into_iter().enumerate() {
+            let mut path = String::new();
+            if docs > 0 && paths.ord_to_str(ord as u64, &mut path).unwrap_or(false) {
+                per_path.insert(path, docs);
+            }
+        }
+        per_path
+    }
+}
+
+/// Like [`search`], but stops early once `cancel` is set and returns
+/// `Ok(None)` instead of results nobody is waiting for.
+pub fn search_cancellable(
+    query: SearchQuery,
+    cancel: &CancelToken,
+) -> Result<Option<Vec<SearchHit>>> {
+    let searcher = current_searcher()?;
+    run_search(query, &searcher, Some(cancel))
+}
+
+/// Like [`search_cancellable`], with the total and whether the query's
+/// [`time_budget`](SearchQuery::time_budget) ran out.
+pub fn search_page_cancellable(
+    query: SearchQuery,
+    cancel: &CancelToken,
+) -> Result<Option<SearchPage>> {
+    let searcher = current_searcher()?;
+    run_search_page(query, &searcher, Some(cancel))
+}
+
+/// Which wave of [`search_two_tier`] a batch of hits belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchWave {
+    /// Files whose name matches; searched first, as that is quick.
+    Names,
+    /// Further files whose content matches, without the ones already
+    /// given with the names.
+    Content,
+}
+
+/// Search names, hand those hits to `on_hits`, then search content and
+/// hand over the files not already given, so filename matches show up at
+/// once even while the content search takes its time on a busy index.
+/// Both waves read one snapshot of the index and together hold at most
+/// `query.limit` hits; `query.search_in` and `query.offset` are ignored.
+/// Modes that don't read content ([`QueryMode::Regex`],
+/// [`QueryMode::Prefix`]) get an empty content wave. Returns `Ok(false)`
+/// when `cancel` was set before both waves were delivered.
+pub fn search_two_tier<F>(query: SearchQuery, cancel: &CancelToken, mut on_hits: F) -> Result<bool>
+where
+    F: FnMut(SearchWave, Vec<SearchHit>),
+{
+    let searcher = current_searcher()?;
+    let names_query = SearchQuery {
+        search_in: SearchDomain::Name,
+        offset: 0,
+        ..query.clone()
+    };
+    let Some(names) = run_search(names_query, &searcher, Some(cancel))? else {
+        return Ok(false);
+    };
+    let seen: HashSet<String> = names.iter().map(|hit| hit.path.clone()).collect();
+    let room = query.limit.saturating_sub(names.len());
+    on_hits(SearchWave::Names, names);
+    if room == 0 || query.mode != QueryMode::Words {
+        on_hits(SearchWave::Content, Vec::new());
+        return Ok(true);
+    }
+    let content_query = SearchQuery {
+        search_in: SearchDomain::Content,
+        offset: 0,
+        // Files already shown are dropped after the search.
+        limit: room + seen.len(),
+        ..query
+    };
+    let Some(mut content) = run_search(content_query, &searcher, Some(cancel))? else {
+        return Ok(false);
+    };
+    content.retain(|hit| !seen.contains(&hit.path));
+    content.truncate(room);
+    on_hits(SearchWave::Content, content);
+    Ok(true)
+}
+
+/// Narrow `previous` hits to those `query` also matches, e.g. as the user
+/// adds words to search within results. Only the earlier files are
+/// searched, and the survivors keep their earlier order, scores and
+/// snippets, so the list doesn't reshuffle while it shrinks. `within`,
+/// `limit` and `offset` of `query` are ignored.
+pub fn refine(previous: &[SearchHit], query: SearchQuery) -> Result<Vec<SearchHit>> {
+    if previous.is_empty() {
+        return Ok(Vec::new());
+    }
+    let query = SearchQuery {
+        within: Some(previous.iter().map(|hit| hit.path.clone()).collect()),
+        limit: previous.len(),
+        offset: 0,
+        snippet_chars: None,
+        ..query
+    };
+    let matched: HashSet<String> = search(query)?.into_iter().map(|hit| hit.path).collect();
+    Ok(previous
+        .iter()
+        .filter(|hit| matched.contains(&hit.path))
+        .cloned()
+        .collect())
+}
+
+fn current_searcher() -> Result<Searcher> {
+    Ok(indexer::reader()
+        .context("reader not available")?
+        .searcher())
+}
+
+/// Run `query` against a specific searcher snapshot; `None` when cancelled.
+pub(crate) fn run_search(
+    query: SearchQuery,
+    searcher: &Searcher,
+    cancel: Option<&CancelToken>,
+) -> Result<Option<Vec<SearchHit>>> {
+    Ok(run_search_page(query, searcher, cancel)?.map(|page| page.hits))
+}
+
+pub(crate) fn run_search_page(
+    query: SearchQuery,
+    searcher: &Searcher,
+    cancel: Option<&CancelToken>,
+) -> Result<Option<SearchPage>> {
+    Ok(run_search_window(query, searcher, cancel, Window::Page)?.map(|(page, _)| page))
+}
+
+/// Where a stream of hits has got to: the last document it read, and the
+/// time recency boosts count from, kept so every page ranks alike.
+#[derive(Debug, Clone)]
+pub(crate) struct StreamCursor {
+    last: Option<(Rank<String>, DocAddress)>,
+    now: i64,
+}
 
-    if matches!(query.search_in, SearchDomain::Name | SearchDomain::Both)
-        && !trimmed.is_empty()
-        && !trimmed.contains(char::is_whitespace)
-    {
-        let escaped = escape(trimmed);
-        let pattern = format!("^{}.*", escaped);
-        if let Ok(regex_query) = RegexQuery::from_pattern(&pattern, fields.name_raw) {
-            let boosted = BoostQuery::new(Box::new(regex_query), 3.0);
-            subqueries.push((Occur::Should, Box::new(boosted)));
+impl StreamCursor {
+    pub(crate) fn start() -> Self {
+        Self {
+            last: None,
+            now: report::now(),
         }
     }
+}
 
-    let combined: Box<dyn Query> = if subqueries.len() == 1 {
-        subqueries.into_iter().next().unwrap().1
-    } else {
-        Box::new(BooleanQuery::new(subqueries))
+/// The next `query.limit` hits after `cursor`, ignoring `query.offset`,
+/// and the cursor to continue from; `None` once no documents are left.
+/// Each call collects only documents ranked after the cursor, so a stream
+/// costs the same per page however far it has got.
+pub(crate) fn run_search_after(
+    query: SearchQuery,
+    searcher: &Searcher,
+    cursor: &StreamCursor,
+) -> Result<(Vec<SearchHit>, Option<StreamCursor>)> {
+    let window = run_search_window(query, searcher, None, Window::After(cursor))?;
+    Ok(window.map_or((Vec::new(), None), |(page, next)| (page.hits, next)))
+}
+
+/// Which hits [`run_search_window`] returns.
+#[derive(Clone, Copy)]
+enum Window<'a> {
+    /// `query.offset` and `query.limit` pick the page.
+    Page,
+    /// The first `query.limit` after a stream's cursor.
+    After(&'a StreamCursor),
+}
+
+fn run_search_window(
+    query: SearchQuery,
+    searcher: &Searcher,
+    cancel: Option<&CancelToken>,
+    window: Window<'_>,
+) -> Result<Option<(SearchPage, Option<StreamCursor>)>> {
+    let cancelled = || cancel.is_some_and(CancelToken::is_cancelled);
+    let deadline = query.time_budget.map(|budget| Instant::now() + budget);
+    let timed_out = Arc::new(AtomicBool::new(false));
+    let out_of_time = || expired(deadline, &timed_out);
+    let index = searcher.index();
+    let fields = indexer::fields()?;
+    let Some(combined) = build_query(&query, index, &fields)? else {
+        return Ok(Some((SearchPage::default(), None)));
     };
 
-    let searcher = reader.searcher();
-    let top_docs = searcher
-        .search(&combined, &TopDocs::with_limit(query.limit.max(1)))
-        .context("tantivy search execution failed")?;
+    let streaming = matches!(window, Window::After(_));
+    let (offset, after, now) = match window {
+        Window::Page => (query.offset, None, report::now()),
+        Window::After(cursor) => (0, cursor.last.clone(), cursor.now),
+    };
+    // Several documents can belong to one file (the chunk documents of a
+    // long file, or a file indexed under an old identity as well); fetch
+    // extra so collapsing them still fills the page.
+    let fetch = offset
+        .saturating_add(query.limit.max(1))
+        .saturating_mul(COLLAPSE_OVERFETCH);
+    let path_filter = PathFilter::new(
+        &query.path_globs,
+        &query.exclude_globs,
+        PathFilterEngine::Auto,
+    )?;
+    // The total is counted as in `count`: a plain count unless globs or
+    // collapsing call for the matches per path.
+    let per_path = path_filter.is_some() || query.collapse;
+    let collector = (
+        StableTopDocs {
+            limit: fetch,
+            sort_by: query.sort_by,
+            recency: query.recency.map(|boost| (boost, now)),
+            ranking: query.ranking,
+            after,
+        },
+        (!per_path).then_some(Count),
+        per_path.then_some(MatchesPerPath),
+    );
+    let collected = if cancel.is_some() || deadline.is_some() {
+        searcher.search(
+            &combined,
+            &Cancellable {
+                inner: collector,
+                token: cancel.cloned(),
+                deadline,
+                timed_out: timed_out.clone(),
+            },
+        )
+    } else {
+        searcher.search(&combined, &collector)
+    };
+    if cancelled() {
+        return Ok(None);
+    }
+    let (top_docs, docs, matches_per_path) =
+        collected.context("tantivy search execution failed")?;
+    let total = match matches_per_path {
+        Some(matches) => total_per_path(matches, path_filter.as_ref(), query.collapse),
+        None => docs.unwrap_or_default(),
+    };
 
-    let glob_matcher = build_glob_matcher(query.path_glob.as_deref())?;
+    let penalties = build_penalties(&query.path_penalties)?;
 
-    let mut hits = Vec::with_capacity(top_docs.len());
-    for (score, address) in top_docs {
+    let mut hits: Vec<SearchHit> = Vec::with_capacity(top_docs.len());
+    let mut by_identity: HashMap<String, usize> = HashMap::new();
+    let mut by_path: HashMap<String, usize> = HashMap::new();
+    let mut with_content: HashSet<String> = HashSet::new();
+    // Documents read before the loop stopped; a stream continues after them.
+    let mut consumed = top_docs.len();
+    for (at, (rank, address)) in top_docs.iter().enumerate() {
+        if cancelled() {
+            return Ok(None);
+        }
+        if out_of_time() {
+            consumed = at;
+            break;
+        }
+        let mut score = rank.score;
         let doc = searcher
-            .doc(address)
-            .context("failed to fetch stored document")?;
+            .doc(*address)
+            .context("failed to fetch stored document")
+            .map_err(events::report_corruption)?;
 
         let path = field_text(&doc, fields.path)
             .unwrap_or_default()
             .to_string();
-        if let Some(ref matcher) = glob_matcher {
-            if !matcher.is_match(&path) {
+        if path_filter
+            .as_ref()
+            .is_some_and(|filter| !filter.allows(&path))
+        {
+            continue;
+        }
+
+        if let Some((ref set, ref factors)) = penalties {
+            for slot in set.matches(&path) {
+                score *= factors[slot];
+            }
+        }
+
+        // Hits arrive best-first, so the first document seen for a file is
+        // the one shown; later ones only add to its match count.
+        let identity = field_text(&doc, fields.identity)
+            .unwrap_or(&path)
+            .to_string();
+        if query.collapse {
+            let seen = by_identity
+                .get(&identity)
+                .or_else(|| by_path.get(&path))
+                .copied();
+            if let Some(slot) = seen {
+                hits[slot].match_count += 1;
+                by_identity.entry(identity).or_insert(slot);
                 continue;
             }
         }
 
+        if streaming && hits.len() >= query.limit.max(1) {
+            consumed = at;
+            break;
+        }
+
+        // A chunk document only holds content; names and the rest come from
+        // the file's own document.
+        let doc = if field_u64(&doc, fields.chunk).is_some_and(|chunk| chunk > 0) {
+            match indexer::file_document(searcher, &fields, &identity)? {
+                Some(own) => own,
+                // Chunks written ahead of a document still being built.
+                None => continue,
+            }
+        } else {
+            doc
+        };
+        if query.collapse {
+            by_identity.insert(identity, hits.len());
+            by_path.insert(path.clone(), hits.len());
+        }
+
         let name = field_text(&doc, fields.name)
             .unwrap_or_default()
             .to_string();
         let modified_at = field_i64(&doc, fields.mtime);
         let size = field_u64(&doc, fields.size);
+        let summary = field_text(&doc, fields.summary).map(str::to_string);
+        let alias_target = field_text(&doc, fields.alias_target).map(str::to_string);
+        let columns = field_u64(&doc, fields.columns);
+        let rows = field_u64(&doc, fields.rows);
+        let children = field_u64(&doc, fields.children);
+        if field_u64(&doc, fields.has_content) == Some(1) {
+            with_content.insert(path.clone());
+        }
 
         hits.push(SearchHit {
             path,
@@ -139,30 +1170,1076 @@ pub fn search(query: SearchQuery) -> Result<Vec<SearchHit>> {
             score,
             modified_at,
             size,
+            summary,
+            alias_target,
+            columns,
+            rows,
+            children,
+            match_count: 1,
+            snippet: None,
         });
     }
 
-    hits.sort_by(|a, b| {
-        b.score
-            .partial_cmp(&a.score)
-            .unwrap_or(Ordering::Equal)
-            .then_with(|| b.modified_at.unwrap_or(0).cmp(&a.modified_at.unwrap_or(0)))
-    });
-
-    Ok(hits)
-}
-
-fn build_glob_matcher(pattern: Option<&str>) -> Result<Option<GlobMatcher>> {
-    let Some(raw) = pattern.map(str::trim).filter(|p| !p.is_empty()) else {
-        return Ok(None);
+    // Hits come out of the collector in order; only path penalties, which
+    // change scores afterwards, can call for another sort.
+    if query.sort_by == SortBy::Score && penalties.is_some() {
+        hits.sort_by(|a, b| hit_order(a, b, query.ranking));
+    }
+    hits.drain(..offset.min(hits.len()));
+    hits.truncate(query.limit.max(1));
+    // Documents remain if the loop stopped early or the collector filled
+    // all it fetched.
+    let exhausted = consumed == top_docs.len() && top_docs.len() < fetch;
+    let next = match consumed.checked_sub(1) {
+        Some(last) if streaming && !exhausted => Some(StreamCursor {
+            last: Some(top_docs[last].clone()),
+            now,
+        }),
+        _ => None,
     };
 
-    let glob = GlobBuilder::new(raw)
-        .case_insensitive(true)
-        .build()
-        .with_context(|| format!("invalid glob pattern: {}", raw))?;
+    if let Some(max_chars) = query.snippet_chars.filter(|chars| *chars > 0) {
+        let mut generator = SnippetGenerator::create(searcher, &*combined, fields.content)
+            .context("failed to prepare snippets")?;
+        generator.set_max_num_chars(max_chars);
+        for hit in hits
+            .iter_mut()
+            .filter(|hit| with_content.contains(&hit.path))
+        {
+            if cancelled() {
+                return Ok(None);
+            }
+            if out_of_time() {
+                break;
+            }
+            hit.snippet = snippet_for(&generator, &hit.path).map(|s| s.window(max_chars));
+        }
+    }
+
+    stats::record_search();
+    let page = SearchPage {
+        hits,
+        total,
+        timed_out: timed_out.load(AtomicOrdering::Relaxed),
+    };
+    Ok(Some((page, next)))
+}
+
+/// The tantivy query for `query`, filters included; `None` when the term
+/// is empty.
+pub(crate) fn build_query(
+    query: &SearchQuery,
+    index: &Index,
+    fields: &IndexFields,
+) -> Result<Option<Box<dyn Query>>> {
+    let settings = indexer::settings()?;
+    check_limits(query, &settings.query_limits)?;
+    // Rewriter macros work on words and would mangle a regex.
+    let rewritten = match query.mode {
+        QueryMode::Words => rewrite::rewrite_query(query.term.trim()),
+        QueryMode::Regex | QueryMode::Prefix => query.term.trim().to_string(),
+    };
+    // `modified:` and `size:` filters become mtime and size bounds; a term
+    // of only filters matches every file in range.
+    let extracted = match query.mode {
+        QueryMode::Words => extract_term_filters(&rewritten)?,
+        QueryMode::Regex | QueryMode::Prefix => TermFilters::plain(rewritten),
+    };
+    let trimmed = extracted.term.trim();
+    if trimmed.is_empty() && extracted.is_empty() {
+        return Ok(None);
+    }
+
+    let mut combined: Box<dyn Query> = match query.mode {
+        QueryMode::Words if trimmed.is_empty() => Box::new(AllQuery),
+        QueryMode::Words => words_query(index, fields, query, trimmed, &settings.ranking)?,
+        QueryMode::Regex => regex_query(fields, trimmed, query.search_in)?,
+        QueryMode::Prefix => prefix_query(fields, trimmed),
+    };
+
+    // Priority folders only lift documents that already match; they never
+    // pull in documents on their own.
+    let priority: Vec<(Occur, Box<dyn Query>)> = query
+        .priority_folders
+        .iter()
+        .filter_map(|folder| {
+            let path = folder.path.trim_end_matches(['/', '\\']);
+            (!path.is_empty() && folder.boost > 0.0).then(|| {
+                let term = Term::from_field_text(fields.dir, path);
+                let clause = TermQuery::new(term, IndexRecordOption::Basic);
+                let boosted: Box<dyn Query> =
+                    Box::new(ConstScoreQuery::new(Box::new(clause), folder.boost));
+                (Occur::Should, boosted)
+            })
+        })
+        .collect();
+    if !priority.is_empty() {
+        let mut clauses = vec![(Occur::Must, combined)];
+        clauses.extend(priority);
+        combined = Box::new(BooleanQuery::new(clauses));
+    }
+
+    // Filters narrow the matches on the `mtime` and `size` fast fields and
+    // the `ext` and `dir` terms, and leave scores as they are.
+    let field_name = |field: Field| index.schema().get_field_name(field).to_string();
+    let mut filters: Vec<Box<dyn Query>> = Vec::new();
+    let mtime_min = query.mtime_min.into_iter().chain(extracted.mtime_min).max();
+    let mtime_max = query.mtime_max.into_iter().chain(extracted.mtime_max).min();
+    if mtime_min.is_some() || mtime_max.is_some() {
+        filters.push(Box::new(RangeQuery::new_i64_bounds(
+            field_name(fields.mtime),
+            inclusive(mtime_min),
+            inclusive(mtime_max),
+        )));
+    }
+    let size_min = query.size_min.into_iter().chain(extracted.size_min).max();
+    let size_max = query.size_max.into_iter().chain(extracted.size_max).min();
+    if size_min.is_some() || size_max.is_some() {
+        filters.push(Box::new(RangeQuery::new_u64_bounds(
+            field_name(fields.size),
+            inclusive(size_min),
+            inclusive(size_max),
+        )));
+    }
+    if !query.extensions.is_empty() {
+        filters.push(Box::new(extension_filter(fields.ext, &query.extensions)));
+    }
+    if let Some(roots) = root_filter(fields.dir, &query.roots) {
+        filters.push(Box::new(roots));
+    }
+    if let Some(paths) = &query.within {
+        let terms = paths
+            .iter()
+            .map(|path| Term::from_field_text(fields.path, path));
+        filters.push(Box::new(TermSetQuery::new(terms)));
+    }
+    if let Some(kind) = query.kind {
+        let term = Term::from_field_text(fields.kind, kind.term());
+        filters.push(Box::new(TermQuery::new(term, IndexRecordOption::Basic)));
+    }
+    if query.case_sensitive && query.mode != QueryMode::Regex {
+        if let Some(names) =
+            case_sensitive_filter(fields.name_raw, trimmed, query.default_operator)?
+        {
+            filters.push(names);
+        }
+    }
+    if !filters.is_empty() {
+        let mut clauses = vec![(Occur::Must, combined)];
+        for filter in filters {
+            let filter: Box<dyn Query> = Box::new(ConstScoreQuery::new(filter, 0.0));
+            clauses.push((Occur::Must, filter));
+        }
+        combined = Box::new(BooleanQuery::new(clauses));
+    }
+
+    Ok(Some(combined))
+}
+
+/// The query string as words, phrases and operators, plus the name-prefix
+/// and typo matches that lift likely file names. Exclusions apply to the
+/// whole query, so a prefix or typo match can't bring back an excluded
+/// file, and a query of only exclusions matches everything else.
+fn words_query(
+    index: &Index,
+    fields: &IndexFields,
+    query: &SearchQuery,
+    trimmed: &str,
+    ranking: &RankingConfig,
+) -> Result<Box<dyn Query>> {
+    let parser = query_parser(
+        index,
+        fields,
+        query.search_in,
+        query.default_operator,
+        ranking,
+    );
+    let parse_error = || format!("failed to parse search query: {}", trimmed);
+    let (text, wildcards) = extract_wildcards(trimmed);
+    let (positive, excluded) = if text.is_empty() {
+        (None, Vec::new())
+    } else {
+        match query_grammar::parse_query(&text) {
+            Ok(ast) => {
+                let ast = indexer::synonyms()?.expand(ast);
+                split_exclusions(widen_fields(ast))
+            }
+            // The parser describes syntax errors better than the grammar.
+            Err(_) => {
+                let err = match parser.parse_query(&text) {
+                    Err(err) => anyhow::Error::from(err),
+                    Ok(_) => anyhow!("syntax error"),
+                };
+                return Err(err).with_context(parse_error);
+            }
+        }
+    };
+    let wildcard_matches = wildcards
+        .iter()
+        .filter(|wildcard| wildcard.required != Some(false))
+        .count();
+    let matched = if positive.is_none() && wildcard_matches > 0 {
+        None
+    } else {
+        Some(text_query(
+            index, fields, query, &parser, &text, positive, ranking,
+        )?)
+    };
+
+    let mut clauses: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+    let optional = match query.default_operator {
+        DefaultOperator::And => Occur::Must,
+        DefaultOperator::Or => Occur::Should,
+    };
+    if let Some(matched) = matched {
+        let occur = if wildcard_matches > 0 {
+            optional
+        } else {
+            Occur::Must
+        };
+        clauses.push((occur, matched));
+    }
+    for wildcard in &wildcards {
+        let occur = match wildcard.required {
+            Some(true) => Occur::Must,
+            Some(false) => Occur::MustNot,
+            None => optional,
+        };
+        clauses.push((
+            occur,
+            wildcard_query(fields, query.search_in, wildcard, ranking)?,
+        ));
+    }
+    if !excluded.is_empty() {
+        let excluded = UserInputAst::Clause(
+            excluded
+                .into_iter()
+                .map(|ast| (Some(Occur::Should), ast))
+                .collect(),
+        );
+        let excluded = parser
+            .build_query_from_user_input_ast(excluded)
+            .with_context(parse_error)?;
+        clauses.push((Occur::MustNot, excluded));
+    }
+    if let [(Occur::Must, _)] = clauses.as_slice() {
+        return Ok(clauses.pop().unwrap().1);
+    }
+    Ok(Box::new(BooleanQuery::new(clauses)))
+}
+
+/// The parsed words of `text` with the name-prefix and typo matches added
+/// alongside them; everything when `positive` is `None`.
+fn text_query(
+    index: &Index,
+    fields: &IndexFields,
+    query: &SearchQuery,
+    parser: &QueryParser,
+    text: &str,
+    positive: Option<UserInputAst>,
+    ranking: &RankingConfig,
+) -> Result<Box<dyn Query>> {
+    let parse_error = || format!("failed to parse search query: {}", text);
+    let mut subqueries: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+    let parsed_query: Box<dyn Query> = match positive {
+        Some(ast) if query.min_should_match.is_set() => {
+            count_plain_words(index, fields, query, parser, ast, ranking)
+                .with_context(parse_error)?
+        }
+        Some(ast) => parser
+            .build_query_from_user_input_ast(ast)
+            .with_context(parse_error)?,
+        None => Box::new(AllQuery),
+    };
+    let main_query: Box<dyn Query> =
+        if matches!(query.search_in, SearchDomain::Name | SearchDomain::Both) {
+            Box::new(BoostQuery::new(parsed_query, ranking.words))
+        } else {
+            parsed_query
+        };
+    subqueries.push((Occur::Should, main_query));
+
+    let words = positive_words(text);
+    if let [word] = words.as_slice() {
+        if matches!(query.search_in, SearchDomain::Name | SearchDomain::Both)
+            && !word.contains(char::is_whitespace)
+        {
+            // The term dictionary regex is anchored at both ends already
+            // and has no `^`; with one, the regex failed to build and the
+            // prefix match was silently left out.
+            let pattern = format!("(?i){}.*", escape(word));
+            if let Ok(regex_query) = RegexQuery::from_pattern(&pattern, fields.name_raw) {
+                let boosted = BoostQuery::new(Box::new(regex_query), ranking.prefix);
+                subqueries.push((Occur::Should, Box::new(boosted)));
+            }
+        }
+    }
+
+    if let Some(distance) = query.fuzzy.filter(|distance| *distance > 0) {
+        if matches!(query.search_in, SearchDomain::Name | SearchDomain::Both) {
+            if let Some(fuzzy) = fuzzy_name_query(
+                index,
+                fields.name,
+                &words.join(" "),
+                distance.min(2),
+                query.default_operator,
+                ranking.fuzzy,
+            )? {
+                subqueries.push((Occur::Should, fuzzy));
+            }
+        }
+    }
+
+    Ok(if subqueries.len() == 1 {
+        subqueries.into_iter().next().unwrap().1
+    } else {
+        Box::new(BooleanQuery::new(subqueries))
+    })
+}
+
+/// `ast` with its plain words (no `+`, `-`, quotes or field) matched
+/// under [`SearchQuery::min_should_match`], one count per searched field.
+/// Fewer than two plain words are parsed as usual.
+fn count_plain_words(
+    index: &Index,
+    fields: &IndexFields,
+    query: &SearchQuery,
+    parser: &QueryParser,
+    ast: UserInputAst,
+    ranking: &RankingConfig,
+) -> Result<Box<dyn Query>> {
+    let UserInputAst::Clause(children) = ast else {
+        return Ok(parser.build_query_from_user_input_ast(ast)?);
+    };
+    let (plain, rest): (Vec<_>, Vec<_>) = children.into_iter().partition(|(occur, child)| {
+        let UserInputAst::Leaf(leaf) = child else {
+            return false;
+        };
+        occur.is_none()
+            && matches!(leaf.as_ref(), UserInputLeaf::Literal(literal)
+                if literal.field_name.is_none()
+                    && literal.delimiter == Delimiter::None
+                    && !literal.prefix)
+    });
+    if plain.len() < 2 {
+        let children = plain.into_iter().chain(rest).collect();
+        return Ok(parser.build_query_from_user_input_ast(UserInputAst::Clause(children))?);
+    }
+    let mut searched = Vec::new();
+    if matches!(query.search_in, SearchDomain::Name | SearchDomain::Both) {
+        searched.push((SearchDomain::Name, query.min_should_match.name));
+    }
+    if matches!(query.search_in, SearchDomain::Content | SearchDomain::Both) {
+        searched.push((SearchDomain::Content, query.min_should_match.content));
+    }
+    let mut counts: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+    for (domain, should_match) in searched {
+        let field_parser = query_parser(index, fields, domain, query.default_operator, ranking);
+        let mut words = plain
+            .iter()
+            .map(|(_, word)| field_parser.build_query_from_user_input_ast(word.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Stop words come back empty and can't count towards the minimum.
+        words.retain(|word| !word.is::<EmptyQuery>());
+        if words.is_empty() {
+            continue;
+        }
+        let minimum = match (should_match, query.default_operator) {
+            (Some(should_match), _) => should_match.of(words.len()),
+            (None, DefaultOperator::And) => words.len(),
+            (None, DefaultOperator::Or) => 1,
+        };
+        counts.push((
+            Occur::Should,
+            Box::new(MinimumMatchQuery::new(words, minimum)),
+        ));
+    }
+    let counted: Box<dyn Query> = if counts.is_empty() {
+        Box::new(EmptyQuery)
+    } else {
+        Box::new(BooleanQuery::new(counts))
+    };
+    if rest.is_empty() {
+        return Ok(counted);
+    }
+    let rest = parser.build_query_from_user_input_ast(UserInputAst::Clause(rest))?;
+    let rest_occur = match query.default_operator {
+        DefaultOperator::And => Occur::Must,
+        DefaultOperator::Or => Occur::Should,
+    };
+    Ok(Box::new(BooleanQuery::new(vec![
+        (Occur::Must, counted),
+        (rest_occur, rest),
+    ])))
+}
+
+/// Words of the searched fields matching `wildcard`, weighed like the
+/// parsed words of the same fields.
+fn wildcard_query(
+    fields: &IndexFields,
+    domain: SearchDomain,
+    wildcard: &Wildcard,
+    ranking: &RankingConfig,
+) -> Result<Box<dyn Query>> {
+    let pattern = wildcard.pattern()?;
+    RegexBuilder::new(&pattern)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .with_context(|| format!("wildcard {:?} is too complex", wildcard.word))?;
+    let targets = match domain {
+        SearchDomain::Name => vec![(fields.name, ranking.name)],
+        SearchDomain::Content => vec![(fields.content, ranking.content)],
+        SearchDomain::Both => vec![
+            (fields.name, ranking.name),
+            (fields.content, ranking.content),
+        ],
+    };
+    let clauses = targets
+        .into_iter()
+        .map(|(field, boost)| -> Result<(Occur, Box<dyn Query>)> {
+            let regex = RegexQuery::from_pattern(&pattern, field)
+                .with_context(|| format!("invalid wildcard: {}", wildcard.word))?;
+            Ok((
+                Occur::Should,
+                Box::new(BoostQuery::new(Box::new(regex), boost)),
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Box::new(BooleanQuery::new(clauses)))
+}
+
+/// The query's top-level exclusions (`-draft`, `NOT path:/old`) apart from
+/// the rest, which is `None` when nothing but exclusions is left.
+fn split_exclusions(ast: UserInputAst) -> (Option<UserInputAst>, Vec<UserInputAst>) {
+    let UserInputAst::Clause(children) = ast else {
+        return (Some(ast), Vec::new());
+    };
+    let (excluded, kept): (Vec<_>, Vec<_>) = children
+        .into_iter()
+        .partition(|(occur, _)| *occur == Some(Occur::MustNot));
+    let excluded = excluded.into_iter().map(|(_, ast)| ast).collect();
+    let kept = (!kept.is_empty()).then_some(UserInputAst::Clause(kept));
+    (kept, excluded)
+}
+
+/// Field clauses read the way users mean them: `ext:log` in any common
+/// case, as extensions are stored as found on disk, and `path:/a/b` for
+/// that file or anything below that folder.
+fn widen_fields(ast: UserInputAst) -> UserInputAst {
+    match ast {
+        UserInputAst::Clause(children) => UserInputAst::Clause(
+            children
+                .into_iter()
+                .map(|(occur, child)| (occur, widen_fields(child)))
+                .collect(),
+        ),
+        UserInputAst::Boost(inner, boost) => {
+            UserInputAst::Boost(Box::new(widen_fields(*inner)), boost)
+        }
+        UserInputAst::Leaf(leaf) => match *leaf {
+            UserInputLeaf::Literal(literal) => {
+                let variants: Vec<(String, String)> = match literal.field_name.as_deref() {
+                    Some("ext") => {
+                        let ext = literal.phrase.trim_start_matches('.');
+                        let mut spellings =
+                            vec![ext.to_string(), ext.to_lowercase(), ext.to_uppercase()];
+                        spellings.dedup();
+                        spellings
+                            .into_iter()
+                            .map(|ext| ("ext".to_string(), ext))
+                            .collect()
+                    }
+                    Some("path") => {
+                        let folder = literal.phrase.trim_end_matches(['/', '\\']);
+                        vec![
+                            ("path".to_string(), literal.phrase.clone()),
+                            ("dir".to_string(), folder.to_string()),
+                        ]
+                    }
+                    _ => return UserInputAst::Leaf(Box::new(UserInputLeaf::Literal(literal))),
+                };
+                UserInputAst::Clause(
+                    variants
+                        .into_iter()
+                        .map(|(field, phrase)| {
+                            let leaf = UserInputLeaf::Literal(UserInputLiteral {
+                                field_name: Some(field),
+                                phrase,
+                                ..literal.clone()
+                            });
+                            (Some(Occur::Should), UserInputAst::Leaf(Box::new(leaf)))
+                        })
+                        .collect(),
+                )
+            }
+            other => UserInputAst::Leaf(Box::new(other)),
+        },
+    }
+}
+
+/// `pattern` must match the whole file name, or in [`SearchDomain::Both`]
+/// the name or the whole path. Leading `^` and trailing `$` are accepted
+/// and implied; leave them out to match anywhere. Case-sensitive unless the
+/// pattern starts with `(?i)`.
+pub(crate) fn regex_query(
+    fields: &IndexFields,
+    pattern: &str,
+    domain: SearchDomain,
+) -> Result<Box<dyn Query>> {
+    if domain == SearchDomain::Content {
+        bail!("regex search covers names and paths, not content");
+    }
+    let (flags, body) = match pattern.strip_prefix("(?i)") {
+        Some(rest) => ("(?i)", rest),
+        None => ("", pattern),
+    };
+    let (start, body) = match body.strip_prefix('^') {
+        Some(rest) => ("", rest),
+        None => (".*", body),
+    };
+    let (body, end) = match body.strip_suffix('$').filter(|rest| !rest.ends_with('\\')) {
+        Some(rest) => (rest, ""),
+        None => (body, ".*"),
+    };
+    let full = format!("{flags}{start}(?:{body}){end}");
+    // The term dictionary is walked with this automaton, so refuse ones
+    // that would take long to build or run.
+    RegexBuilder::new(&full)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .with_context(|| format!("invalid regex: {pattern}"))?;
+    let mut targets = vec![fields.name_raw];
+    if domain == SearchDomain::Both {
+        targets.push(fields.path);
+    }
+    let clauses = targets
+        .into_iter()
+        .map(|field| -> Result<(Occur, Box<dyn Query>)> {
+            let regex = RegexQuery::from_pattern(&full, field)
+                .with_context(|| format!("invalid regex: {pattern}"))?;
+            Ok((Occur::Should, Box::new(regex)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Box::new(BooleanQuery::new(clauses)))
+}
+
+/// One term lookup per typed word in the `name_prefix` field. Nothing
+/// matches when `text` has no letters or digits.
+pub(crate) fn prefix_query(fields: &IndexFields, text: &str) -> Box<dyn Query> {
+    let clauses: Vec<(Occur, Box<dyn Query>)> = name_words(text)
+        .into_iter()
+        .map(|word| {
+            let prefix: String = word.chars().take(MAX_PREFIX_CHARS).collect();
+            let term = Term::from_field_text(fields.name_prefix, &prefix);
+            let clause: Box<dyn Query> =
+                Box::new(TermQuery::new(term, IndexRecordOption::WithFreqs));
+            (Occur::Must, clause)
+        })
+        .collect();
+    Box::new(BooleanQuery::new(clauses))
+}
+
+/// Best score first; ties go by `ranking`, then path. Total, so equal hits
+/// always come back in one order.
+fn hit_order(a: &SearchHit, b: &SearchHit, ranking: RankingProfile) -> Ordering {
+    let by_score = b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal);
+    let by_recency = || b.modified_at.unwrap_or(0).cmp(&a.modified_at.unwrap_or(0));
+    match ranking {
+        RankingProfile::Recent => by_score.then_with(by_recency),
+        RankingProfile::Shallow => by_score
+            .then_with(|| path_depth(&a.path).cmp(&path_depth(&b.path)))
+            .then_with(|| a.name.chars().count().cmp(&b.name.chars().count()))
+            .then_with(by_recency),
+    }
+    .then_with(|| a.path.cmp(&b.path))
+}
+
+/// Top `limit` documents by sort key, then score, then the `ranking`
+/// tie-breaks, then path; the same order as [`hit_order`]. `TopDocs` would
+/// settle exact ties by segment order, which changes as segments merge and
+/// made equal hits swap places between refreshes.
+struct StableTopDocs {
+    limit: usize,
+    sort_by: SortBy,
+    /// Applied to scores as they are collected, with the time it counts
+    /// ages from.
+    recency: Option<(RecencyBoost, i64)>,
+    ranking: RankingProfile,
+    /// Only documents ranked after this one, for the next page of a stream.
+    after: Option<(Rank<String>, DocAddress)>,
+}
+
+/// Where a document ranks; greater is better. Within one segment the path
+/// is its term ordinal, which follows path order there; candidates carry
+/// the path itself once segments are merged.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+struct Rank<P> {
+    key: SortKey,
+    score: Score,
+    /// Path depth and name length under [`RankingProfile::Shallow`]; 0
+    /// otherwise.
+    depth: Reverse<u64>,
+    name_len: Reverse<u64>,
+    mtime: i64,
+    path: Reverse<P>,
+}
+
+struct StableSegmentTopDocs {
+    limit: usize,
+    segment_ord: SegmentOrdinal,
+    key: Box<dyn FnMut(DocId, Score) -> (SortKey, Score)>,
+    /// Depth and name length columns, read for [`RankingProfile::Shallow`].
+    shallow: Option<(Column<u64>, Column<u64>)>,
+    mtimes: Option<Column<i64>>,
+    paths: Option<StrColumn>,
+    after: Option<(Rank<String>, DocAddress)>,
+    candidates: Vec<(Rank<u64>, DocId)>,
+}
+
+/// Best first; identical ranks (documents of one file) by document id.
+fn by_rank<P: PartialOrd, D: Ord>(a: &(Rank<P>, D), b: &(Rank<P>, D)) -> Ordering {
+    b.0.partial_cmp(&a.0)
+        .unwrap_or(Ordering::Equal)
+        .then_with(|| a.1.cmp(&b.1))
+}
+
+/// [`by_rank`] on everything but the path, which may be an ordinal on one
+/// side and text on the other.
+fn by_rank_before_path<P, Q>(a: &Rank<P>, b: &Rank<Q>) -> Ordering {
+    b.key
+        .partial_cmp(&a.key)
+        .unwrap_or(Ordering::Equal)
+        .then_with(|| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal))
+        .then_with(|| b.depth.cmp(&a.depth))
+        .then_with(|| b.name_len.cmp(&a.name_len))
+        .then_with(|| b.mtime.cmp(&a.mtime))
+}
+
+impl Collector for StableTopDocs {
+    type Fruit = Vec<(Rank<String>, DocAddress)>;
+    type Child = StableSegmentTopDocs;
+
+    fn for_segment(
+        &self,
+        segment_ord: SegmentOrdinal,
+        segment: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        let fast = segment.fast_fields();
+        Ok(StableSegmentTopDocs {
+            limit: self.limit.max(1),
+            segment_ord,
+            key: sort_key_reader(segment, self.sort_by, self.recency),
+            shallow: match self.ranking {
+                RankingProfile::Shallow => fast.u64("depth").ok().zip(fast.u64("name_len").ok()),
+                RankingProfile::Recent => None,
+            },
+            mtimes: fast.i64("mtime").ok(),
+            paths: fast.str("path").ok().flatten(),
+            after: self.after.clone(),
+            candidates: Vec::new(),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        true
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<Vec<(Rank<String>, DocAddress)>>,
+    ) -> tantivy::Result<Self::Fruit> {
+        let mut merged: Vec<_> = segment_fruits.into_iter().flatten().collect();
+        merged.sort_by(by_rank);
+        merged.truncate(self.limit.max(1));
+        Ok(merged)
+    }
+}
+
+impl StableSegmentTopDocs {
+    /// Whether `doc` ranks after the stream position being continued from.
+    fn is_after(&self, rank: &Rank<u64>, doc: DocId) -> bool {
+        let Some((last, address)) = &self.after else {
+            return true;
+        };
+        match by_rank_before_path(rank, last) {
+            Ordering::Less => return false,
+            Ordering::Greater => return true,
+            Ordering::Equal => {}
+        }
+        let path = self.path_text(rank.path.0);
+        path.cmp(&last.path.0)
+            .then_with(|| DocAddress::new(self.segment_ord, doc).cmp(address))
+            == Ordering::Greater
+    }
+
+    /// The path of term ordinal `ord`; paths that can't be read sort last.
+    fn path_text(&self, ord: u64) -> String {
+        let mut path = String::new();
+        let found = self
+            .paths
+            .as_ref()
+            .is_some_and(|column| ord != u64::MAX && column.ord_to_str(ord, &mut path).is_ok());
+        if !found || path.is_empty() {
+            path = char::MAX.to_string();
+        }
+        path
+    }
+
+    /// Keep only the best `limit`; run when the candidates double, so
+    /// collecting stays linear.
+    fn prune(&mut self) {
+        if self.candidates.len() > self.limit {
+            self.candidates.select_nth_unstable_by(self.limit, by_rank);
+            self.candidates.truncate(self.limit);
+        }
+    }
+}
+
+impl SegmentCollector for StableSegmentTopDocs {
+    type Fruit = Vec<(Rank<String>, DocAddress)>;
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        let (key, score) = (self.key)(doc, score);
+        let (depth, name_len) = self.shallow.as_ref().map_or((0, 0), |(depth, name_len)| {
+            (
+                depth.first(doc).unwrap_or(u64::MAX),
+                name_len.first(doc).unwrap_or(u64::MAX),
+            )
+        });
+        let mtime = self.mtimes.as_ref().and_then(|column| column.first(doc));
+        let path = self
+            .paths
+            .as_ref()
+            .and_then(|column| column.term_ords(doc).next());
+        let rank = Rank {
+            key,
+            score,
+            depth: Reverse(depth),
+            name_len: Reverse(name_len),
+            mtime: mtime.unwrap_or(i64::MIN),
+            path: Reverse(path.unwrap_or(u64::MAX)),
+        };
+        if !self.is_after(&rank, doc) {
+            return;
+        }
+        self.candidates.push((rank, doc));
+        if self.candidates.len() >= self.limit.saturating_mul(2) {
+            self.prune();
+        }
+    }
+
+    fn harvest(mut self) -> Self::Fruit {
+        self.prune();
+        let candidates = std::mem::take(&mut self.candidates);
+        candidates
+            .into_iter()
+            .map(|(rank, doc)| {
+                let rank = Rank {
+                    key: rank.key,
+                    score: rank.score,
+                    depth: rank.depth,
+                    name_len: rank.name_len,
+                    mtime: rank.mtime,
+                    path: Reverse(self.path_text(rank.path.0)),
+                };
+                (rank, DocAddress::new(self.segment_ord, doc))
+            })
+            .collect()
+    }
+}
+
+/// What the collector keeps the top of; every document of one search
+/// carries the same variant.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+enum SortKey {
+    Score,
+    Modified(i64),
+    Size(u64),
+    Name(Reverse<String>),
+}
+
+/// Reads each document's sort key from the fast fields of `segment`,
+/// paired with its score for ties, boosted by `recency` when given.
+/// Documents missing the value sort last.
+fn sort_key_reader(
+    segment: &SegmentReader,
+    sort_by: SortBy,
+    recency: Option<(RecencyBoost, i64)>,
+) -> Box<dyn FnMut(DocId, Score) -> (SortKey, Score)> {
+    let fast = segment.fast_fields();
+    if let Some((boost, now)) = recency {
+        let mut inner = sort_key_reader(segment, sort_by, None);
+        let column = fast.i64("mtime").ok();
+        return Box::new(move |doc, score| {
+            let mtime = column.as_ref().and_then(|column| column.first(doc));
+            inner(doc, score * boost.factor(now, mtime))
+        });
+    }
+    match sort_by {
+        SortBy::Score => Box::new(|_, score| (SortKey::Score, score)),
+        SortBy::Modified => {
+            let column = fast.i64("mtime").ok();
+            Box::new(move |doc, score| {
+                let mtime = column.as_ref().and_then(|column| column.first(doc));
+                (SortKey::Modified(mtime.unwrap_or(i64::MIN)), score)
+            })
+        }
+        SortBy::Size => {
+            let column = fast.u64("size").ok();
+            Box::new(move |doc, score| {
+                let size = column.as_ref().and_then(|column| column.first(doc));
+                (SortKey::Size(size.unwrap_or(0)), score)
+            })
+        }
+        SortBy::Name => {
+            let column = fast.str("name_raw").ok().flatten();
+            Box::new(move |doc, score| {
+                let mut name = String::new();
+                if let Some(column) = &column {
+                    if let Some(ord) = column.term_ords(doc).next() {
+                        let _ = column.ord_to_str(ord, &mut name);
+                    }
+                }
+                if name.is_empty() {
+                    name.push(char::MAX);
+                }
+                (SortKey::Name(Reverse(name.to_lowercase())), score)
+            })
+        }
+    }
+}
+
+/// Fields a bare word is matched against in `domain`.
+pub(crate) fn default_fields(fields: &IndexFields, domain: SearchDomain) -> Vec<Field> {
+    match domain {
+        SearchDomain::Name => vec![fields.name, fields.name_parts, fields.alias_target],
+        SearchDomain::Content => vec![fields.content],
+        SearchDomain::Both => vec![
+            fields.name,
+            fields.name_parts,
+            fields.alias_target,
+            fields.content,
+        ],
+    }
+}
+
+/// The parser every search in `domain` goes through.
+pub(crate) fn query_parser(
+    index: &Index,
+    fields: &IndexFields,
+    domain: SearchDomain,
+    operator: DefaultOperator,
+    ranking: &RankingConfig,
+) -> QueryParser {
+    let mut parser = QueryParser::for_index(index, default_fields(fields, domain));
+    if matches!(domain, SearchDomain::Name | SearchDomain::Both) {
+        parser.set_field_boost(fields.name, ranking.name);
+        parser.set_field_boost(fields.name_parts, ranking.name);
+    }
+    if matches!(domain, SearchDomain::Content | SearchDomain::Both) {
+        parser.set_field_boost(fields.content, ranking.content);
+    }
+    if operator == DefaultOperator::And {
+        parser.set_conjunction_by_default();
+    }
+    parser
+}
+
+/// Content is not stored in the index, so snippets come from re-reading
+/// the start of the file. Files that can no longer be read get none.
+fn snippet_for(generator: &SnippetGenerator, path: &str) -> Option<HitSnippet> {
+    let text = read_plain_text(path, SNIPPET_SOURCE_BYTES, SNIPPET_SNIFF_BYTES)
+        .ok()?
+        .content?;
+    let snippet = generator.snippet(&text);
+    (!snippet.is_empty()).then(|| HitSnippet {
+        text: snippet.fragment().to_string(),
+        highlighted: snippet.highlighted().to_vec(),
+    })
+}
+
+/// Every word of `text` (any word, with [`DefaultOperator::Or`]) must
+/// match a name word within `max_distance` edits. Short words allow fewer edits, since two edits turn most
+/// three-letter words into each other.
+fn fuzzy_name_query(
+    index: &Index,
+    name: Field,
+    text: &str,
+    max_distance: u8,
+    operator: DefaultOperator,
+    boost: f32,
+) -> Result<Option<Box<dyn Query>>> {
+    let occur = match operator {
+        DefaultOperator::And => Occur::Must,
+        DefaultOperator::Or => Occur::Should,
+    };
+    let mut tokenizer = index
+        .tokenizer_for_field(name)
+        .context("failed to load name tokenizer")?;
+    let mut words = Vec::new();
+    tokenizer
+        .token_stream(text)
+        .process(&mut |token| words.push(token.text.clone()));
+
+    let clauses: Vec<(Occur, Box<dyn Query>)> = words
+        .into_iter()
+        .map(|word| {
+            let distance = match word.chars().count() {
+                0..=2 => 0,
+                3..=5 => max_distance.min(1),
+                _ => max_distance,
+            };
+            let term = Term::from_field_text(name, &word);
+            let clause: Box<dyn Query> = if distance == 0 {
+                Box::new(TermQuery::new(term, IndexRecordOption::Basic))
+            } else {
+                Box::new(FuzzyTermQuery::new(term, distance, true))
+            };
+            (occur, clause)
+        })
+        .collect();
+    if clauses.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(Box::new(BoostQuery::new(
+        Box::new(BooleanQuery::new(clauses)),
+        boost,
+    ))))
+}
+
+/// Extensions are indexed with the case they have on disk, so each one is
+/// looked up as given and in both common spellings.
+fn extension_filter(field: Field, extensions: &[String]) -> TermSetQuery {
+    let terms = extensions.iter().flat_map(|ext| {
+        let ext = ext.trim().trim_start_matches('.');
+        [ext.to_string(), ext.to_lowercase(), ext.to_uppercase()]
+            .map(|spelling| Term::from_field_text(field, &spelling))
+    });
+    TermSetQuery::new(terms)
+}
+
+/// Names containing each word of `term` (any, with
+/// [`DefaultOperator::Or`]) in the case typed, matched on the raw
+/// `name_raw` term. `None` when the term has no such words.
+fn case_sensitive_filter(
+    field: Field,
+    term: &str,
+    operator: DefaultOperator,
+) -> Result<Option<Box<dyn Query>>> {
+    let occur = match operator {
+        DefaultOperator::And => Occur::Must,
+        DefaultOperator::Or => Occur::Should,
+    };
+    let clauses = positive_words(term)
+        .into_iter()
+        .map(|word| -> Result<(Occur, Box<dyn Query>)> {
+            let pattern = format!(".*{}.*", escape(&word));
+            let regex = RegexQuery::from_pattern(&pattern, field)
+                .with_context(|| format!("invalid name fragment: {word}"))?;
+            Ok((occur, Box::new(regex)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if clauses.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(Box::new(BooleanQuery::new(clauses))))
+}
+
+/// Words and phrases of `term` that files are looked for by, as a prefix,
+/// typo or case-sensitive name match:
+/// everything but excluded (`-word`) and field (`ext:pdf`) clauses and
+/// the operators themselves.
+pub(crate) fn positive_words(term: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut rest = term.trim();
+    while !rest.is_empty() {
+        let excluded = rest.starts_with('-');
+        let item = rest.trim_start_matches(['+', '-', '(']);
+        let end = match item.find(['"', ' ', '\t', '\n']) {
+            // A quoted phrase, or a field clause with a quoted value.
+            Some(open) if item[open..].starts_with('"') => item[open + 1..]
+                .find('"')
+                .map_or(item.len(), |close| open + close + 2),
+            Some(space) => space,
+            None => item.len(),
+        };
+        let (word, after) = item.split_at(end);
+        let word = word.trim_end_matches(')').trim_matches('"').trim();
+        let field = word.contains(':') && !item.starts_with('"');
+        if !excluded && !field && !word.is_empty() && !matches!(word, "AND" | "OR" | "NOT") {
+            words.push(word.to_string());
+        }
+        rest = after.trim_start();
+    }
+    words
+}
+
+/// Files below any of `roots`, looked up on the `dir` field, which holds
+/// every ancestor folder of each file. `None` when no root narrows the
+/// search, which includes a filesystem root such as `/`.
+fn root_filter(field: Field, roots: &[PathBuf]) -> Option<TermSetQuery> {
+    let mut terms = Vec::with_capacity(roots.len());
+    for root in roots {
+        let root = root.to_string_lossy();
+        let trimmed = root.trim_end_matches(['/', '\\']);
+        // A filesystem root has no parent and every file is below it.
+        Path::new(trimmed).parent()?;
+        terms.push(Term::from_field_text(field, trimmed));
+    }
+    (!terms.is_empty()).then(|| TermSetQuery::new(terms))
+}
+
+fn inclusive<T>(value: Option<T>) -> Bound<T> {
+    value.map_or(Bound::Unbounded, Bound::Included)
+}
+
+fn path_depth(path: &str) -> usize {
+    Path::new(path).components().count()
+}
+
+/// Case-insensitive set of the non-blank `patterns`; `None` when there are
+/// none. `what` names them in errors.
+pub(crate) fn build_glob_set(patterns: &[String], what: &str) -> Result<Option<GlobSet>> {
+    let patterns: Vec<&str> = patterns
+        .iter()
+        .map(|pattern| pattern.trim())
+        .filter(|pattern| !pattern.is_empty())
+        .collect();
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = GlobBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .with_context(|| format!("invalid {what} pattern: {}", pattern))?;
+        builder.add(glob);
+    }
+    let set = builder
+        .build()
+        .with_context(|| format!("failed to build {what} patterns"))?;
+    Ok(Some(set))
+}
 
-    Ok(Some(glob.compile_matcher()))
+pub(crate) fn build_penalties(penalties: &[PathPenalty]) -> Result<Option<(GlobSet, Vec<f32>)>> {
+    if penalties.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    let mut factors = Vec::with_capacity(penalties.len());
+    for penalty in penalties {
+        let glob = GlobBuilder::new(penalty.pattern.trim())
+            .case_insensitive(true)
+            .build()
+            .with_context(|| format!("invalid penalty pattern: {}", penalty.pattern))?;
+        builder.add(glob);
+        factors.push(penalty.factor.clamp(0.0, 1.0));
+    }
+    let set = builder
+        .build()
+        .context("failed to build penalty patterns")?;
+    Ok(Some((set, factors)))
 }
 
 fn field_text(doc: &TantivyDocument, field: Field) -> Option<&str> {
@@ -179,10 +2256,24 @@ fn field_u64(doc: &TantivyDocument, field: Field) -> Option<u64> {
 
 #[cfg(test)]
 mod tests {
-    use super::{search, SearchDomain, SearchQuery};
+    use super::{
+        count, refine, search, search_page, search_prefix, search_two_tier, DefaultOperator,
+        MinShouldMatch, PathPenalty, PriorityFolder, QueryLimit, QueryLimitExceeded, QueryLimits,
+        QueryMode, RankingConfig, RankingProfile, RecencyBoost, SearchDomain, SearchQuery,
+        SearchWave, ShouldMatch, SortBy, COLLAPSE_OVERFETCH,
+    };
+    use crate::cancel::CancelToken;
+    use crate::indexer::{add_or_update_file_streaming, index_stats, CONTENT_CHUNK_BYTES};
+    use crate::query_builder::{Match, QueryBuilder};
+    use crate::rewrite::{set_query_rewriter, QueryRewriter};
     use crate::scanner::FileMeta;
+    use crate::stats::quick_stats;
+    use crate::summary::{set_summarizer, LeadSentences};
     use crate::{add_or_update_file, commit, init_index};
+    use std::path::PathBuf;
     use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
     use tempfile::tempdir;
 
     static NEXT_INODE: AtomicU64 = AtomicU64::new(1);
@@ -197,6 +2288,10 @@ mod tests {
             size: 42,
             inode,
             dev: 1,
+            alias_target: None,
+            bundle: false,
+            display_name: None,
+            children: None,
         }
     }
 
@@ -231,8 +2326,9 @@ mod tests {
         let content_hits = search(SearchQuery {
             term: "rust".into(),
             search_in: SearchDomain::Content,
-            path_glob: None,
+            path_globs: Vec::new(),
             limit: 10,
+            ..SearchQuery::default()
         })
         .unwrap();
         assert_eq!(content_hits.len(), 1);
@@ -241,8 +2337,9 @@ mod tests {
         let name_hits = search(SearchQuery {
             term: "main".into(),
             search_in: SearchDomain::Name,
-            path_glob: None,
+            path_globs: Vec::new(),
             limit: 10,
+            ..SearchQuery::default()
         })
         .unwrap();
         assert_eq!(name_hits.len(), 1);
@@ -280,12 +2377,1369 @@ mod tests {
         let hits = search(SearchQuery {
             term: "introduction".into(),
             search_in: SearchDomain::Both,
-            path_glob: Some("**/*.md".into()),
+            path_globs: vec!["**/*.md".into()],
             limit: 10,
+            ..SearchQuery::default()
         })
         .unwrap();
 
         assert_eq!(hits.len(), 1);
         assert!(hits[0].path.ends_with("readme.md"));
+
+        let hits = search(SearchQuery {
+            term: "introduction".into(),
+            path_globs: vec!["**/*.md".into(), "**/docs/*.TXT".into()],
+            ..SearchQuery::default()
+        })
+        .unwrap();
+        assert_eq!(hits.len(), 2);
+    }
+
+    #[test]
+    fn exclude_globs_drop_matching_paths() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        for path in [
+            "/app/src/index.js",
+            "/app/node_modules/left-pad/index.js",
+            "/app/Target/debug/index.js",
+        ] {
+            let name = path.rsplit('/').next().unwrap();
+            add_or_update_file(meta(path, name, Some("js")), None, false).unwrap();
+        }
+        commit().unwrap();
+
+        let query = SearchQuery {
+            term: "index".into(),
+            search_in: SearchDomain::Name,
+            exclude_globs: vec!["**/node_modules/**".into(), "**/target/**".into()],
+            ..SearchQuery::default()
+        };
+        let hits = search(query.clone()).unwrap();
+        let paths: Vec<_> = hits.iter().map(|hit| hit.path.as_str()).collect();
+        assert_eq!(paths, vec!["/app/src/index.js"]);
+        assert_eq!(count(query.clone()).unwrap(), 1);
+        assert_eq!(search_page(query.clone()).unwrap().total, 1);
+
+        let broken = SearchQuery {
+            exclude_globs: vec!["[".into()],
+            ..query
+        };
+        assert!(search(broken).is_err());
+    }
+
+    #[test]
+    fn returns_stored_summaries_with_hits() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+
+        set_summarizer(Some(Arc::new(LeadSentences::default())));
+        let added = add_or_update_file(
+            meta(
+                dir.path().join("minutes.txt").to_str().unwrap(),
+                "minutes.txt",
+                Some("txt"),
+            ),
+            Some("Board meeting minutes. Budget approved. Next meeting in May.".into()),
+            false,
+        );
+        set_summarizer(None);
+        added.unwrap();
+        commit().unwrap();
+
+        let hits = search(SearchQuery {
+            term: "budget".into(),
+            search_in: SearchDomain::Content,
+            path_globs: Vec::new(),
+            limit: 10,
+            ..SearchQuery::default()
+        })
+        .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(
+            hits[0].summary.as_deref(),
+            Some("Board meeting minutes. Budget approved.")
+        );
+    }
+
+    #[test]
+    fn collapses_chunks_of_one_file_into_a_single_hit() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+
+        // Each chapter overflows a chunk, so each starts on its own document.
+        let filler = "cell ".repeat(CONTENT_CHUNK_BYTES / 5);
+        let long = meta("/docs/thesis.txt", "thesis.txt", Some("txt"));
+        add_or_update_file_streaming(long, false, |sink| {
+            for chapter in ["one", "two", "three"] {
+                sink.push(&format!("mitochondria chapter {chapter} {filler}"));
+            }
+            Ok(())
+        })
+        .unwrap();
+        let short = meta("/docs/notes.txt", "notes.txt", Some("txt"));
+        add_or_update_file(short, Some("mitochondria aside".into()), false).unwrap();
+        commit().unwrap();
+
+        let query = SearchQuery {
+            term: "mitochondria".into(),
+            search_in: SearchDomain::Content,
+            path_globs: Vec::new(),
+            limit: 2,
+            ..SearchQuery::default()
+        };
+        assert_eq!(count(query.clone()).unwrap(), 2);
+        assert_eq!(search_page(query.clone()).unwrap().total, 2);
+        let uncollapsed = SearchQuery {
+            collapse: false,
+            ..query.clone()
+        };
+        assert_eq!(count(uncollapsed.clone()).unwrap(), 4);
+        assert_eq!(search_page(uncollapsed).unwrap().total, 4);
+        let hits = search(query).unwrap();
+        let mut counts: Vec<_> = hits
+            .iter()
+            .map(|hit| (hit.name.as_str(), hit.match_count))
+            .collect();
+        counts.sort();
+        assert_eq!(counts, vec![("notes.txt", 1), ("thesis.txt", 3)]);
+        assert_eq!(index_stats().unwrap().num_docs, 2);
+    }
+
+    #[test]
+    fn collapses_one_file_indexed_under_two_identities() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        let scanned = meta("/docs/ledger.txt", "ledger.txt", Some("txt"));
+        let without_inode = FileMeta {
+            inode: 0,
+            dev: 0,
+            ..scanned.clone()
+        };
+        add_or_update_file(scanned, None, false).unwrap();
+        add_or_update_file(without_inode, None, false).unwrap();
+        commit().unwrap();
+
+        let hits = |collapse| {
+            search(SearchQuery {
+                term: "ledger".into(),
+                search_in: SearchDomain::Name,
+                collapse,
+                ..SearchQuery::default()
+            })
+            .unwrap()
+        };
+        let collapsed = hits(true);
+        assert_eq!(collapsed.len(), 1);
+        assert_eq!(collapsed[0].match_count, 2);
+        assert_eq!(hits(false).len(), 2);
+    }
+
+    #[test]
+    fn equal_scores_prefer_shallow_paths_and_short_names() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+
+        let mut newest = meta("/backup/old/report.pdf", "report.pdf", Some("pdf"));
+        newest.modified_at = 300;
+        let mut long_name = meta("/docs/report.pdf.pdf", "report.pdf.pdf", Some("pdf"));
+        long_name.modified_at = 200;
+        let shallow = meta("/docs/report.pdf", "report.pdf", Some("pdf"));
+        for meta in [newest, long_name, shallow] {
+            add_or_update_file(meta, None, false).unwrap();
+        }
+        commit().unwrap();
+
+        let paths = |ranking| {
+            search(SearchQuery {
+                term: "report.pdf".into(),
+                search_in: SearchDomain::Name,
+                ranking,
+                ..SearchQuery::default()
+            })
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.path)
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(paths(RankingProfile::Shallow)[0], "/docs/report.pdf");
+        assert_eq!(paths(RankingProfile::Recent)[0], "/backup/old/report.pdf");
+    }
+
+    #[test]
+    fn shallow_ties_win_even_past_the_collected_candidates() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+
+        // Deep copies sort first by path and outnumber what a one-hit page
+        // fetches, so only the collector's own tie-break finds the shallow one.
+        for n in 0..COLLAPSE_OVERFETCH * 2 {
+            let copy = meta(
+                &format!("/a/copy-{n}/old/report.pdf"),
+                "report.pdf",
+                Some("pdf"),
+            );
+            add_or_update_file(copy, None, false).unwrap();
+        }
+        add_or_update_file(
+            meta("/z/report.pdf", "report.pdf", Some("pdf")),
+            None,
+            false,
+        )
+        .unwrap();
+        commit().unwrap();
+
+        let hits = search(SearchQuery {
+            term: "report".into(),
+            search_in: SearchDomain::Name,
+            limit: 1,
+            ..SearchQuery::default()
+        })
+        .unwrap();
+        assert_eq!(hits[0].path, "/z/report.pdf");
+    }
+
+    #[test]
+    fn recency_boost_lifts_fresh_files_over_better_old_matches() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+
+        let now = crate::report::now();
+        let mut old = meta("/docs/budget plan.md", "budget plan.md", Some("md"));
+        old.modified_at = now - 365 * 86_400;
+        let mut fresh = meta("/docs/plan.md", "plan.md", Some("md"));
+        fresh.modified_at = now - 3_600;
+        for meta in [old, fresh] {
+            add_or_update_file(meta, None, false).unwrap();
+        }
+        commit().unwrap();
+
+        let paths = |recency| {
+            search(SearchQuery {
+                term: "budget plan".into(),
+                search_in: SearchDomain::Name,
+                default_operator: DefaultOperator::Or,
+                recency,
+                ..SearchQuery::default()
+            })
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.path)
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(paths(None), vec!["/docs/budget plan.md", "/docs/plan.md"]);
+        assert_eq!(
+            paths(Some(RecencyBoost::new(30.0, 4.0))),
+            vec!["/docs/plan.md", "/docs/budget plan.md"]
+        );
+    }
+
+    #[test]
+    fn priority_folders_lift_matches_without_adding_new_ones() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+
+        for (path, content) in [
+            ("/home/me/Archive/plan.txt", "launch plan launch plan"),
+            ("/home/me/Desktop/plan.txt", "launch plan"),
+            ("/home/me/Desktop/unrelated.txt", "groceries"),
+        ] {
+            let name = path.rsplit('/').next().unwrap();
+            add_or_update_file(meta(path, name, Some("txt")), Some(content.into()), false).unwrap();
+        }
+        commit().unwrap();
+
+        let query = |priority_folders| SearchQuery {
+            term: "launch".into(),
+            search_in: SearchDomain::Content,
+            priority_folders,
+            ..SearchQuery::default()
+        };
+        let plain = search(query(Vec::new())).unwrap();
+        assert_eq!(plain[0].path, "/home/me/Archive/plan.txt");
+
+        let boosted = search(query(vec![PriorityFolder::new("/home/me/Desktop/", 5.0)])).unwrap();
+        let paths: Vec<_> = boosted.iter().map(|hit| hit.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec!["/home/me/Desktop/plan.txt", "/home/me/Archive/plan.txt"]
+        );
+    }
+
+    #[test]
+    fn path_penalties_sink_backups_without_dropping_them() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+
+        for (path, content) in [
+            ("/home/me/Backup 2023/budget.txt", "quarterly budget budget"),
+            (
+                "/home/me/Documents/budget copy.txt",
+                "quarterly budget budget",
+            ),
+            ("/home/me/Documents/budget.txt", "quarterly budget"),
+        ] {
+            let name = path.rsplit('/').next().unwrap();
+            add_or_update_file(meta(path, name, Some("txt")), Some(content.into()), false).unwrap();
+        }
+        commit().unwrap();
+
+        let query = |path_penalties| SearchQuery {
+            term: "budget".into(),
+            search_in: SearchDomain::Content,
+            path_penalties,
+            ..SearchQuery::default()
+        };
+        let plain = search(query(Vec::new())).unwrap();
+        assert_eq!(plain[2].path, "/home/me/Documents/budget.txt");
+
+        let penalized = search(query(PathPenalty::defaults())).unwrap();
+        assert_eq!(penalized.len(), 3);
+        assert_eq!(penalized[0].path, "/home/me/Documents/budget.txt");
+
+        let invalid = search(query(vec![PathPenalty::new("[", 0.5)]));
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn snippets_highlight_matches_in_reread_content() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().join("index").to_str().unwrap()).unwrap();
+        let file = dir.path().join("trial.txt");
+        let text = "Enrollment opened in March. The <primary> endpoint was met & reported.";
+        std::fs::write(&file, text).unwrap();
+        let path = file.to_str().unwrap();
+        add_or_update_file(
+            meta(path, "trial.txt", Some("txt")),
+            Some(text.into()),
+            false,
+        )
+        .unwrap();
+        commit().unwrap();
+
+        let query = |snippet_chars| SearchQuery {
+            term: "endpoint".into(),
+            search_in: SearchDomain::Content,
+            snippet_chars,
+            ..SearchQuery::default()
+        };
+        assert!(search(query(None)).unwrap()[0].snippet.is_none());
+
+        let hit = search(query(Some(60))).unwrap().remove(0);
+        let snippet = hit.snippet.expect("snippet requested");
+        assert!(snippet.text.chars().count() <= 60);
+        let marked: Vec<_> = snippet
+            .highlighted
+            .iter()
+            .map(|range| &snippet.text[range.clone()])
+            .collect();
+        assert_eq!(marked, vec!["endpoint"]);
+        assert!(snippet
+            .to_html()
+            .contains("&lt;primary&gt; <b>endpoint</b>"));
+    }
+
+    #[test]
+    fn fuzzy_mode_forgives_typos_in_names() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        for (path, name) in [
+            ("/docs/receipt.pdf", "receipt.pdf"),
+            ("/docs/report.pdf", "report.pdf"),
+        ] {
+            add_or_update_file(meta(path, name, Some("pdf")), None, false).unwrap();
+        }
+        commit().unwrap();
+
+        let names = |fuzzy| -> Vec<String> {
+            search(SearchQuery {
+                term: "recipt.pdf".into(),
+                search_in: SearchDomain::Name,
+                fuzzy,
+                ..SearchQuery::default()
+            })
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.name)
+            .collect()
+        };
+        assert!(names(None).is_empty());
+        assert_eq!(names(Some(1)), vec!["receipt.pdf"]);
+    }
+
+    #[test]
+    fn mtime_range_keeps_only_recent_files() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        for (path, name, mtime) in [
+            ("/docs/budget-2020.txt", "budget-2020.txt", 1_000),
+            ("/docs/budget-2024.txt", "budget-2024.txt", 5_000),
+            ("/docs/budget-future.txt", "budget-future.txt", 9_000),
+        ] {
+            let meta = FileMeta {
+                modified_at: mtime,
+                ..meta(path, name, Some("txt"))
+            };
+            add_or_update_file(meta, None, false).unwrap();
+        }
+        commit().unwrap();
+
+        let names = |mtime_min, mtime_max| -> Vec<String> {
+            let mut names: Vec<String> = search(SearchQuery {
+                term: "budget".into(),
+                search_in: SearchDomain::Name,
+                mtime_min,
+                mtime_max,
+                ..SearchQuery::default()
+            })
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.name)
+            .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names(Some(5_000), Some(8_999)), vec!["budget-2024.txt"]);
+        assert_eq!(
+            names(Some(4_000), None),
+            vec!["budget-2024.txt", "budget-future.txt"]
+        );
+        assert_eq!(names(None, Some(1_000)), vec!["budget-2020.txt"]);
+    }
+
+    #[test]
+    fn size_range_filters_in_the_index() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        for (name, size) in [("small.log", 1_000), ("big.log", 20_000_000)] {
+            let meta = FileMeta {
+                size,
+                ..meta(&format!("/var/{name}"), name, Some("log"))
+            };
+            add_or_update_file(meta, None, false).unwrap();
+        }
+        commit().unwrap();
+
+        let names = |size_min, size_max| -> Vec<String> {
+            search(SearchQuery {
+                term: "log".into(),
+                search_in: SearchDomain::Name,
+                size_min,
+                size_max,
+                ..SearchQuery::default()
+            })
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.name)
+            .collect()
+        };
+        assert_eq!(names(Some(10 * 1024 * 1024), None), vec!["big.log"]);
+        assert_eq!(names(None, Some(1_000)), vec!["small.log"]);
+        assert!(names(Some(2_000), Some(3_000)).is_empty());
+    }
+
+    #[test]
+    fn or_operator_matches_any_plain_word() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        for name in ["budget.txt", "travel.txt", "budget travel.txt", "other.txt"] {
+            let path = format!("/notes/{name}");
+            add_or_update_file(meta(&path, name, Some("txt")), None, false).unwrap();
+        }
+        commit().unwrap();
+
+        let names = |default_operator, term: &str| -> Vec<String> {
+            search(SearchQuery {
+                term: term.into(),
+                search_in: SearchDomain::Name,
+                default_operator,
+                ..SearchQuery::default()
+            })
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.name)
+            .collect()
+        };
+        assert_eq!(
+            names(DefaultOperator::And, "budget travel"),
+            vec!["budget travel.txt"]
+        );
+        let any = names(DefaultOperator::Or, "budget travel");
+        assert_eq!(any.len(), 3);
+        assert_eq!(any[0], "budget travel.txt");
+        assert_eq!(
+            names(DefaultOperator::Or, "budget travel -txt"),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn roots_keep_files_below_those_folders() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        for path in [
+            "/work/app/plan.md",
+            "/work/app/docs/deep/plan.md",
+            "/work/app-old/plan.md",
+            "/home/notes/plan.md",
+            "/tmp/plan.md",
+        ] {
+            add_or_update_file(meta(path, "plan.md", Some("md")), None, false).unwrap();
+        }
+        commit().unwrap();
+
+        let paths = |roots: &[&str]| -> Vec<String> {
+            let mut paths: Vec<String> = search(SearchQuery {
+                term: "plan".into(),
+                search_in: SearchDomain::Name,
+                roots: roots.iter().map(PathBuf::from).collect(),
+                ..SearchQuery::default()
+            })
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.path)
+            .collect();
+            paths.sort();
+            paths
+        };
+        assert_eq!(
+            paths(&["/work/app/", "/home/notes"]),
+            vec![
+                "/home/notes/plan.md",
+                "/work/app/docs/deep/plan.md",
+                "/work/app/plan.md"
+            ]
+        );
+        assert_eq!(paths(&["/nowhere"]), Vec::<String>::new());
+        assert_eq!(paths(&["/"]).len(), 5);
+    }
+
+    #[test]
+    fn modified_and_size_filters_in_the_term_narrow_hits() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        // 2024-02-20, 2024-03-10 and 2024-04-01, all at noon UTC.
+        for (path, mtime) in [
+            ("/notes/feb.md", 1_708_430_400),
+            ("/notes/march.md", 1_710_072_000),
+            ("/notes/april.md", 1_711_972_800),
+        ] {
+            let mut file = meta(path, path.trim_start_matches("/notes/"), Some("md"));
+            file.modified_at = mtime;
+            add_or_update_file(file, None, false).unwrap();
+        }
+        commit().unwrap();
+
+        let names = |term: &str| -> Vec<String> {
+            let mut names: Vec<String> = search(SearchQuery {
+                term: term.into(),
+                search_in: SearchDomain::Name,
+                ..SearchQuery::default()
+            })
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.name)
+            .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names("md modified:2024-03"), vec!["march.md"]);
+        assert_eq!(names("modified:>=2024-03"), vec!["april.md", "march.md"]);
+        assert_eq!(names("modified:<2024-03 md"), vec!["feb.md"]);
+        assert_eq!(names("size:<1kb modified:2024-03"), vec!["march.md"]);
+        assert!(names("md size:>1kb").is_empty());
+        assert!(search(SearchQuery {
+            term: "modified:someday".into(),
+            ..SearchQuery::default()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn case_sensitive_names_tell_makefile_from_makefile_txt() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        for (path, name, ext) in [
+            ("/src/Makefile", "Makefile", None),
+            ("/src/makefile.txt", "makefile.txt", Some("txt")),
+            ("/src/Makefile.am", "Makefile.am", Some("am")),
+        ] {
+            add_or_update_file(meta(path, name, ext), None, false).unwrap();
+        }
+        commit().unwrap();
+
+        let names = |term: &str, case_sensitive| {
+            let mut names: Vec<String> = search(SearchQuery {
+                term: term.into(),
+                search_in: SearchDomain::Name,
+                case_sensitive,
+                ..SearchQuery::default()
+            })
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.name)
+            .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names("makefile", false).len(), 3);
+        assert_eq!(names("Makefile", true), vec!["Makefile", "Makefile.am"]);
+        assert_eq!(names("makefile", true), vec!["makefile.txt"]);
+        assert_eq!(names("Makefile -am", true), vec!["Makefile"]);
+        assert_eq!(
+            super::positive_words("+Read (Me OR \"Big Plan\") -Draft ext:md name:\"A b\""),
+            vec!["Read", "Me", "Big Plan"]
+        );
+    }
+
+    #[test]
+    fn exclusions_hold_against_prefix_matches_in_every_domain() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        for (path, name, ext, content) in [
+            ("/docs/report.pdf", "report.pdf", "pdf", None),
+            ("/docs/report draft.pdf", "report draft.pdf", "pdf", None),
+            ("/docs/reporting.log", "reporting.log", "log", None),
+            ("/docs/old/report.pdf", "report.pdf", "pdf", None),
+            (
+                "/docs/minutes.txt",
+                "minutes.txt",
+                "txt",
+                Some("draft agenda"),
+            ),
+        ] {
+            let content = content.map(String::from);
+            add_or_update_file(meta(path, name, Some(ext)), content, false).unwrap();
+        }
+        commit().unwrap();
+
+        let paths = |query: SearchQuery| {
+            let mut paths: Vec<String> = search(query)
+                .unwrap()
+                .into_iter()
+                .map(|hit| hit.path)
+                .collect();
+            paths.sort();
+            paths
+        };
+        let term = |term: &str, search_in| SearchQuery {
+            term: term.into(),
+            search_in,
+            ..SearchQuery::default()
+        };
+        // `rep` only matches through the name-prefix subquery, which must
+        // not bring the excluded files back.
+        assert_eq!(
+            paths(term("rep -draft", SearchDomain::Both)),
+            vec![
+                "/docs/old/report.pdf",
+                "/docs/report.pdf",
+                "/docs/reporting.log"
+            ]
+        );
+        assert_eq!(
+            paths(term("rep -ext:LOG NOT path:/docs/old", SearchDomain::Both)),
+            vec!["/docs/report draft.pdf", "/docs/report.pdf"]
+        );
+        // Only exclusions: everything else, with content counted only when
+        // content is searched.
+        assert_eq!(paths(term("-draft", SearchDomain::Both)).len(), 3);
+        assert_eq!(paths(term("-draft", SearchDomain::Name)).len(), 4);
+
+        let built = QueryBuilder::new()
+            .must_not(Match::term("draft"))
+            .exclude_extension(".LOG")
+            .search_in(SearchDomain::Name)
+            .build();
+        assert_eq!(built.term, "-draft -ext:\"LOG\"");
+        assert_eq!(
+            paths(built),
+            vec![
+                "/docs/minutes.txt",
+                "/docs/old/report.pdf",
+                "/docs/report.pdf"
+            ]
+        );
+    }
+
+    #[test]
+    fn wildcards_match_within_name_words() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        for name in ["report.pdf", "final draft v2.txt", "drafts.md"] {
+            let ext = name.rsplit('.').next();
+            add_or_update_file(meta(&format!("/docs/{name}"), name, ext), None, false).unwrap();
+        }
+        commit().unwrap();
+
+        let names = |term: &str| {
+            let mut names: Vec<String> = search(SearchQuery {
+                term: term.into(),
+                search_in: SearchDomain::Name,
+                ..SearchQuery::default()
+            })
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.name)
+            .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names("rep*rt"), vec!["report.pdf"]);
+        assert_eq!(names("*draft*"), vec!["drafts.md", "final draft v2.txt"]);
+        assert_eq!(names("dr?ft"), vec!["final draft v2.txt"]);
+        assert_eq!(names("*draft* txt"), vec!["final draft v2.txt"]);
+        assert_eq!(names("-*draft*"), vec!["report.pdf"]);
+        assert!(search(SearchQuery {
+            term: "*a*".into(),
+            ..SearchQuery::default()
+        })
+        .is_err());
+
+        let err = search(SearchQuery {
+            term: "ab* cd* ef* gh* ij* kl* mn* op* qr*".into(),
+            ..SearchQuery::default()
+        })
+        .unwrap_err();
+        let err = err.downcast_ref::<QueryLimitExceeded>().unwrap();
+        assert_eq!(err.limit, QueryLimit::Wildcards);
+    }
+
+    #[test]
+    fn min_should_match_counts_plain_words_per_field() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        for (name, content) in [
+            ("a.txt", "quarterly budget review meeting"),
+            ("b.txt", "budget review"),
+            ("c.txt", "quarterly numbers"),
+        ] {
+            let meta = meta(&format!("/notes/{name}"), name, Some("txt"));
+            add_or_update_file(meta, Some(content.into()), false).unwrap();
+        }
+        commit().unwrap();
+
+        let names = |term: &str, content: Option<ShouldMatch>| {
+            let mut names: Vec<String> = search(SearchQuery {
+                term: term.into(),
+                search_in: SearchDomain::Content,
+                min_should_match: MinShouldMatch {
+                    name: None,
+                    content,
+                },
+                ..SearchQuery::default()
+            })
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.name)
+            .collect();
+            names.sort();
+            names
+        };
+        let term = "quarterly budget review";
+        assert_eq!(names(term, None), vec!["a.txt"]);
+        assert_eq!(
+            names(term, Some(ShouldMatch::Words(2))),
+            vec!["a.txt", "b.txt"]
+        );
+        assert_eq!(names(term, Some(ShouldMatch::Percent(30))).len(), 3);
+        assert_eq!(names(term, Some(ShouldMatch::Words(9))), vec!["a.txt"]);
+        assert_eq!(
+            names(
+                "quarterly budget review -meeting",
+                Some(ShouldMatch::Words(2))
+            ),
+            vec!["b.txt"]
+        );
+        assert_eq!(
+            names(
+                "quarterly budget \"budget review\"",
+                Some(ShouldMatch::Words(1))
+            ),
+            vec!["a.txt", "b.txt"]
+        );
+    }
+
+    #[test]
+    fn two_tier_search_gives_names_then_new_content_matches() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        for (name, content) in [
+            ("budget.txt", "numbers"),
+            ("budget old.txt", "last year's budget"),
+            ("notes.txt", "budget for q3"),
+        ] {
+            let meta = meta(&format!("/docs/{name}"), name, Some("txt"));
+            add_or_update_file(meta, Some(content.into()), false).unwrap();
+        }
+        commit().unwrap();
+
+        let waves = |limit: usize, cancel: &CancelToken| {
+            let mut waves = Vec::new();
+            let done = search_two_tier(
+                SearchQuery {
+                    term: "budget".into(),
+                    limit,
+                    ..SearchQuery::default()
+                },
+                cancel,
+                |wave, hits| {
+                    let mut names: Vec<String> = hits.into_iter().map(|hit| hit.name).collect();
+                    names.sort();
+                    waves.push((wave, names));
+                },
+            )
+            .unwrap();
+            (done, waves)
+        };
+        let (done, found) = waves(10, &CancelToken::new());
+        assert!(done);
+        assert_eq!(
+            found,
+            vec![
+                (
+                    SearchWave::Names,
+                    vec!["budget old.txt".to_string(), "budget.txt".to_string()]
+                ),
+                (SearchWave::Content, vec!["notes.txt".to_string()]),
+            ]
+        );
+        assert_eq!(
+            waves(2, &CancelToken::new()).1[1],
+            (SearchWave::Content, vec![])
+        );
+
+        let cancelled = CancelToken::new();
+        cancelled.cancel();
+        assert_eq!(waves(10, &cancelled), (false, vec![]));
+    }
+
+    #[test]
+    fn refine_narrows_earlier_hits_in_their_order() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        for (name, content) in [
+            ("report draft.txt", "quarterly sales"),
+            ("report final.txt", "quarterly sales and costs"),
+            ("report q2.txt", "costs only"),
+            ("costs.txt", "quarterly costs"),
+        ] {
+            let meta = meta(&format!("/docs/{name}"), name, Some("txt"));
+            add_or_update_file(meta, Some(content.into()), false).unwrap();
+        }
+        commit().unwrap();
+
+        let first = search(SearchQuery {
+            term: "report".into(),
+            ..SearchQuery::default()
+        })
+        .unwrap();
+        assert_eq!(first.len(), 3);
+        let narrowed = refine(
+            &first,
+            SearchQuery {
+                term: "costs".into(),
+                ..SearchQuery::default()
+            },
+        )
+        .unwrap();
+        let expected: Vec<&str> = first
+            .iter()
+            .map(|hit| hit.name.as_str())
+            .filter(|name| *name != "report draft.txt")
+            .collect();
+        let names: Vec<&str> = narrowed.iter().map(|hit| hit.name.as_str()).collect();
+        assert_eq!(names, expected);
+        assert_eq!(narrowed[0].score, first[0].score);
+
+        let nothing = SearchQuery {
+            term: "costs".into(),
+            within: Some(Vec::new()),
+            ..SearchQuery::default()
+        };
+        assert!(search(nothing).unwrap().is_empty());
+    }
+
+    #[test]
+    fn extension_filter_ignores_dots_and_case() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        for (name, ext) in [("plan.md", "md"), ("plan.TXT", "TXT"), ("plan.pdf", "pdf")] {
+            add_or_update_file(
+                meta(&format!("/notes/{name}"), name, Some(ext)),
+                None,
+                false,
+            )
+            .unwrap();
+        }
+        commit().unwrap();
+
+        let mut names: Vec<String> = search(SearchQuery {
+            term: "plan".into(),
+            search_in: SearchDomain::Name,
+            extensions: vec!["md".into(), ".txt".into()],
+            ..SearchQuery::default()
+        })
+        .unwrap()
+        .into_iter()
+        .map(|hit| hit.name)
+        .collect();
+        names.sort();
+        assert_eq!(names, vec!["plan.TXT", "plan.md"]);
+    }
+
+    #[test]
+    fn spent_time_budget_returns_partial_results_flagged() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        for n in 0..3 {
+            let name = format!("scan-{n}.pdf");
+            let meta = meta(&format!("/scans/{name}"), &name, Some("pdf"));
+            add_or_update_file(meta, None, false).unwrap();
+        }
+        commit().unwrap();
+
+        let page = |time_budget| {
+            search_page(SearchQuery {
+                term: "scan".into(),
+                search_in: SearchDomain::Name,
+                time_budget,
+                ..SearchQuery::default()
+            })
+            .unwrap()
+        };
+        let spent = page(Some(Duration::ZERO));
+        assert!(spent.timed_out);
+        assert!(spent.hits.is_empty());
+        let ample = page(Some(Duration::from_secs(60)));
+        assert!(!ample.timed_out);
+        assert_eq!(ample.hits.len(), 3);
+    }
+
+    #[test]
+    fn pages_through_hits_with_a_total() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        for n in 0..5 {
+            let name = format!("scan-{n}.pdf");
+            add_or_update_file(
+                meta(&format!("/scans/{name}"), &name, Some("pdf")),
+                None,
+                false,
+            )
+            .unwrap();
+        }
+        commit().unwrap();
+
+        let page = |offset| {
+            search_page(SearchQuery {
+                term: "scan".into(),
+                search_in: SearchDomain::Name,
+                limit: 2,
+                offset,
+                ..SearchQuery::default()
+            })
+            .unwrap()
+        };
+        let mut seen = Vec::new();
+        for offset in [0, 2, 4] {
+            let page = page(offset);
+            assert_eq!(page.total, 5);
+            seen.extend(page.hits.into_iter().map(|hit| hit.name));
+        }
+        seen.sort();
+        seen.dedup();
+        assert_eq!(seen.len(), 5);
+        assert!(page(6).hits.is_empty());
+        let everything = SearchQuery {
+            term: "scan".into(),
+            search_in: SearchDomain::Name,
+            ..SearchQuery::default()
+        };
+        assert_eq!(count(everything.clone()).unwrap(), 5);
+        let none = SearchQuery {
+            extensions: vec!["txt".into()],
+            ..everything
+        };
+        assert_eq!(count(none).unwrap(), 0);
+    }
+
+    #[test]
+    fn sorts_by_fast_fields_while_collecting() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        for (name, mtime, size) in [
+            ("b-memo.txt", 300, 10),
+            ("A-memo.txt", 100, 30),
+            ("c-memo.txt", 200, 20),
+        ] {
+            let meta = FileMeta {
+                modified_at: mtime,
+                size,
+                ..meta(&format!("/memos/{name}"), name, Some("txt"))
+            };
+            add_or_update_file(meta, None, false).unwrap();
+        }
+        commit().unwrap();
+
+        let names = |sort_by, offset| -> Vec<String> {
+            search(SearchQuery {
+                term: "memo".into(),
+                search_in: SearchDomain::Name,
+                sort_by,
+                limit: 2,
+                offset,
+                ..SearchQuery::default()
+            })
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.name)
+            .collect()
+        };
+        assert_eq!(names(SortBy::Modified, 0), vec!["b-memo.txt", "c-memo.txt"]);
+        assert_eq!(names(SortBy::Size, 0), vec!["A-memo.txt", "c-memo.txt"]);
+        assert_eq!(names(SortBy::Name, 0), vec!["A-memo.txt", "b-memo.txt"]);
+        assert_eq!(names(SortBy::Name, 2), vec!["c-memo.txt"]);
+    }
+
+    #[test]
+    fn equal_hits_come_back_in_path_order_across_segments() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        // More exact ties than one page fetches, spread over segments in
+        // an order unrelated to their paths.
+        let folders = ["k", "c", "h", "a", "l", "e", "j", "b", "g", "d", "i", "f"];
+        for (n, folder) in folders.iter().enumerate() {
+            let path = format!("/ties/{folder}/plan.txt");
+            add_or_update_file(meta(&path, "plan.txt", Some("txt")), None, false).unwrap();
+            if n % 3 == 2 {
+                commit().unwrap();
+            }
+        }
+        commit().unwrap();
+
+        let page = |sort_by, offset| -> Vec<String> {
+            search(SearchQuery {
+                term: "plan".into(),
+                search_in: SearchDomain::Name,
+                sort_by,
+                limit: 2,
+                offset,
+                ..SearchQuery::default()
+            })
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.path)
+            .collect()
+        };
+        for sort_by in [SortBy::Score, SortBy::Modified, SortBy::Size] {
+            let all: Vec<String> = (0..6).flat_map(|n| page(sort_by, n * 2)).collect();
+            let mut sorted = all.clone();
+            sorted.sort();
+            assert_eq!(all, sorted, "{sort_by:?}");
+            assert_eq!(all.len(), folders.len());
+        }
+    }
+
+    #[test]
+    fn regex_mode_matches_whole_names_or_paths() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        for path in [
+            "/fin/report_2023.xlsx",
+            "/fin/report_2023.xlsx.bak",
+            "/fin/Report_24.xlsx",
+            "/reports/summary.txt",
+        ] {
+            let name = path.rsplit('/').next().unwrap();
+            add_or_update_file(meta(path, name, None), None, false).unwrap();
+        }
+        commit().unwrap();
+
+        let names = |term: &str, search_in| -> Result<Vec<String>, anyhow::Error> {
+            let mut names: Vec<String> = search(SearchQuery {
+                term: term.into(),
+                search_in,
+                mode: QueryMode::Regex,
+                ..SearchQuery::default()
+            })?
+            .into_iter()
+            .map(|hit| hit.name)
+            .collect();
+            names.sort();
+            Ok(names)
+        };
+        let name = SearchDomain::Name;
+        assert_eq!(
+            names(r"^report_\d{4}\.xlsx$", name).unwrap(),
+            vec!["report_2023.xlsx"]
+        );
+        assert_eq!(
+            names(r"(?i)^report_\d+\.xlsx", name).unwrap(),
+            vec!["Report_24.xlsx", "report_2023.xlsx", "report_2023.xlsx.bak"]
+        );
+        assert_eq!(
+            names("^/reports/", SearchDomain::Both).unwrap(),
+            vec!["summary.txt"]
+        );
+        assert!(names("^/reports/", name).unwrap().is_empty());
+        assert!(names("report(", name).is_err());
+        assert!(names("report", SearchDomain::Content).is_err());
+        assert!(names(&"a".repeat(600), name).is_err());
+    }
+
+    #[test]
+    fn phrase_queries_need_the_words_in_order() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        for (name, content) in [
+            ("minutes.txt", "Minutes of the annual general meeting, 2024"),
+            ("memo.txt", "general notes for the annual meeting"),
+        ] {
+            let meta = meta(&format!("/board/{name}"), name, Some("txt"));
+            add_or_update_file(meta, Some(content.into()), false).unwrap();
+        }
+        commit().unwrap();
+
+        let query = SearchQuery::phrase("Annual  \"general\" meeting");
+        assert_eq!(query.term, "\"Annual general meeting\"");
+        let names: Vec<String> = search(query)
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.name)
+            .collect();
+        assert_eq!(names, vec!["minutes.txt"]);
+        assert!(SearchQuery::phrase(" \" ").term.is_empty());
+    }
+
+    #[test]
+    fn ranking_config_reweighs_name_and_content_matches() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let first_path = |ranking: RankingConfig| {
+            crate::indexer::configure(crate::IndexSettings {
+                ranking,
+                ..crate::IndexSettings::default()
+            });
+            let dir = tempdir().unwrap();
+            let opened = init_index(dir.path().to_str().unwrap());
+            crate::indexer::configure(crate::IndexSettings::default());
+            opened.unwrap();
+            let titled = meta("/notes/quarterly.txt", "quarterly.txt", Some("txt"));
+            let mentions = meta("/notes/minutes.txt", "minutes.txt", Some("txt"));
+            add_or_update_file(titled, Some("nothing to see".into()), false).unwrap();
+            add_or_update_file(mentions, Some("the quarterly numbers".into()), false).unwrap();
+            commit().unwrap();
+            search(SearchQuery {
+                term: "quarterly".into(),
+                ..SearchQuery::default()
+            })
+            .unwrap()[0]
+                .path
+                .clone()
+        };
+
+        assert_eq!(first_path(RankingConfig::default()), "/notes/quarterly.txt");
+        let content_first = RankingConfig {
+            name: 0.1,
+            content: 10.0,
+            prefix: 0.0,
+            ..RankingConfig::default()
+        };
+        assert_eq!(first_path(content_first), "/notes/minutes.txt");
+    }
+
+    #[test]
+    fn refuses_queries_over_the_index_limits() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        crate::indexer::configure(crate::IndexSettings {
+            query_limits: QueryLimits {
+                max_limit: 100,
+                ..QueryLimits::default()
+            },
+            ..crate::IndexSettings::default()
+        });
+        let dir = tempdir().unwrap();
+        let opened = init_index(dir.path().to_str().unwrap());
+        crate::indexer::configure(crate::IndexSettings::default());
+        opened.unwrap();
+
+        let exceeded = |query: SearchQuery| {
+            *search(query)
+                .unwrap_err()
+                .downcast_ref::<QueryLimitExceeded>()
+                .unwrap()
+        };
+        let err = exceeded(SearchQuery {
+            term: "x".into(),
+            limit: 10_000_000,
+            ..SearchQuery::default()
+        });
+        assert_eq!(
+            (err.limit, err.requested, err.maximum),
+            (QueryLimit::Limit, 10_000_000, 100)
+        );
+        let err = exceeded(SearchQuery {
+            term: "x".into(),
+            path_penalties: vec![PathPenalty::new("*".repeat(2_000), 0.5)],
+            ..SearchQuery::default()
+        });
+        assert_eq!(err.limit, QueryLimit::GlobLength);
+        assert!(search(SearchQuery {
+            term: "x".into(),
+            limit: 100,
+            ..SearchQuery::default()
+        })
+        .is_ok());
+    }
+
+    #[test]
+    fn prefix_search_matches_word_starts() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        for name in [
+            "Annual Report 2024.pdf",
+            "annual-budget.xlsx",
+            "reannual.txt",
+            "an extraordinarily-long-wordforprefixes.md",
+        ] {
+            add_or_update_file(meta(&format!("/docs/{name}"), name, None), None, false).unwrap();
+        }
+        commit().unwrap();
+
+        let names = |typed: &str| -> Vec<String> {
+            let mut names: Vec<String> = search_prefix(typed, 10)
+                .unwrap()
+                .into_iter()
+                .map(|hit| hit.name)
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(
+            names("ann"),
+            vec!["Annual Report 2024.pdf", "annual-budget.xlsx"]
+        );
+        assert_eq!(names("ANN rep"), vec!["Annual Report 2024.pdf"]);
+        assert_eq!(names("budget ann"), vec!["annual-budget.xlsx"]);
+        assert_eq!(
+            names("extraordinarily-long"),
+            vec!["an extraordinarily-long-wordforprefixes.md"]
+        );
+        assert!(names("nual").is_empty());
+        assert!(names("--").is_empty());
+    }
+
+    /// Notes every query string a search parses, which a page served from
+    /// the result cache skips.
+    #[derive(Default)]
+    struct ParsedQueries(Mutex<Vec<String>>);
+
+    impl QueryRewriter for ParsedQueries {
+        fn rewrite(&self, raw: &str) -> Option<String> {
+            self.0.lock().unwrap().push(raw.to_string());
+            None
+        }
+    }
+
+    impl ParsedQueries {
+        fn take(&self) -> Vec<String> {
+            std::mem::take(&mut *self.0.lock().unwrap())
+        }
+    }
+
+    #[test]
+    fn caches_repeated_queries_until_the_next_commit() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        crate::indexer::configure(crate::IndexSettings {
+            result_cache_entries: 2,
+            ..crate::IndexSettings::default()
+        });
+        let dir = tempdir().unwrap();
+        let opened = init_index(dir.path().to_str().unwrap());
+        crate::indexer::configure(crate::IndexSettings::default());
+        opened.unwrap();
+        let parsed = Arc::new(ParsedQueries::default());
+        set_query_rewriter(Some(parsed.clone()));
+        let add = |name: &str| {
+            let meta = meta(&format!("/notes/{name}"), name, Some("txt"));
+            add_or_update_file(meta, None, false).unwrap();
+        };
+        add("alpha plan.txt");
+        commit().unwrap();
+
+        let query = |term: &str| SearchQuery {
+            term: term.into(),
+            search_in: SearchDomain::Name,
+            ..SearchQuery::default()
+        };
+        let names = |term: &str| -> Vec<String> {
+            let mut names: Vec<String> = search(query(term))
+                .unwrap()
+                .into_iter()
+                .map(|hit| hit.name)
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names("plan"), ["alpha plan.txt"]);
+        assert_eq!(names("alpha"), ["alpha plan.txt"]);
+        assert_eq!(names("plan"), ["alpha plan.txt"]);
+        assert_eq!(parsed.take(), ["plan", "alpha"]);
+        assert_eq!(quick_stats().searches, 3);
+        // Holds two pages, so the least recently used one makes room.
+        assert!(names("beta").is_empty());
+        assert_eq!(names("plan"), ["alpha plan.txt"]);
+        assert_eq!(names("alpha"), ["alpha plan.txt"]);
+        assert_eq!(parsed.take(), ["beta", "alpha"]);
+
+        add("beta plan.txt");
+        assert_eq!(names("plan"), ["alpha plan.txt"], "not committed yet");
+        commit().unwrap();
+        assert_eq!(names("plan"), ["alpha plan.txt", "beta plan.txt"]);
+        assert_eq!(parsed.take(), ["plan"]);
+
+        let recent = SearchQuery {
+            recency: Some(RecencyBoost::default()),
+            ..query("plan")
+        };
+        search(recent.clone()).unwrap();
+        search(recent).unwrap();
+        assert_eq!(parsed.take(), ["plan", "plan"]);
+
+        crate::set_stop_words(["zzz"]);
+        names("plan");
+        assert_eq!(parsed.take(), ["plan"]);
+        crate::set_stop_words(Vec::<String>::new());
+        set_query_rewriter(None);
     }
 }
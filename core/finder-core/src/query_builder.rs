@@ -0,0 +1,269 @@
+use crate::query::{SearchDomain, SearchQuery};
+use std::path::PathBuf;
+
+/// One thing a document has to contain: a word or an exact phrase,
+/// optionally only in the name or only in the content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    words: Vec<String>,
+    phrase: bool,
+    field: SearchDomain,
+}
+
+impl Match {
+    /// A single word. Punctuation splits it the way indexed text is split,
+    /// so `report-2024` must appear as `report` then `2024`.
+    pub fn term(word: &str) -> Self {
+        Self {
+            words: clean_words(word),
+            phrase: false,
+            field: SearchDomain::Both,
+        }
+    }
+
+    /// Words next to each other and in this order.
+    pub fn phrase(words: &str) -> Self {
+        Self {
+            words: clean_words(words),
+            phrase: true,
+            field: SearchDomain::Both,
+        }
+    }
+
+    /// Only look in the file name (`SearchDomain::Name`) or the content
+    /// (`SearchDomain::Content`); `SearchDomain::Both` follows the query's
+    /// own `search_in`.
+    pub fn within(mut self, field: SearchDomain) -> Self {
+        self.field = field;
+        self
+    }
+
+    /// Query syntax for this match, or `None` when no words are left.
+    fn render(&self) -> Option<String> {
+        let prefix = match self.field {
+            SearchDomain::Name => "name:",
+            SearchDomain::Content => "content:",
+            SearchDomain::Both => "",
+        };
+        // A term with several words is written as a phrase too, which is
+        // what the parser makes of `report-2024` anyway.
+        let text = match self.words.as_slice() {
+            [] => return None,
+            [word] if !self.phrase && !is_operator(word) => word.clone(),
+            words => format!("\"{}\"", words.join(" ")),
+        };
+        Some(format!("{prefix}{text}"))
+    }
+}
+
+/// Builds a [`SearchQuery`] from typed parts instead of a hand-written
+/// query string, so host input can never be misread as query syntax.
+///
+/// ```ignore
+/// let query = QueryBuilder::new()
+///     .must(Match::term("budget"))
+///     .should(Match::phrase("annual report").within(SearchDomain::Content))
+///     .should(Match::term("forecast"))
+///     .must_not(Match::term("draft").within(SearchDomain::Name))
+///     .extension("xlsx")
+///     .build();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct QueryBuilder {
+    must: Vec<Match>,
+    should: Vec<Match>,
+    must_not: Vec<Match>,
+    excluded_extensions: Vec<String>,
+    query: SearchQuery,
+}
+
+impl QueryBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Documents have to match this.
+    pub fn must(mut self, clause: Match) -> Self {
+        self.must.push(clause);
+        self
+    }
+
+    /// Documents have to match at least one of the `should` clauses.
+    pub fn should(mut self, clause: Match) -> Self {
+        self.should.push(clause);
+        self
+    }
+
+    /// Documents matching this are left out; with no `must` or `should`
+    /// clause, every other document matches.
+    pub fn must_not(mut self, clause: Match) -> Self {
+        self.must_not.push(clause);
+        self
+    }
+
+    pub fn search_in(mut self, domain: SearchDomain) -> Self {
+        self.query.search_in = domain;
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.query.limit = limit;
+        self
+    }
+
+    /// Keep files with this extension; repeat to allow several.
+    pub fn extension(mut self, ext: impl Into<String>) -> Self {
+        self.query.extensions.push(ext.into());
+        self
+    }
+
+    /// Leave out files with this extension (`"log"` or `".log"`), in any
+    /// case; repeat to leave out several.
+    pub fn exclude_extension(mut self, ext: &str) -> Self {
+        let ext = ext.trim().trim_start_matches('.');
+        if !ext.is_empty() && ext.chars().all(char::is_alphanumeric) {
+            self.excluded_extensions.push(ext.to_string());
+        }
+        self
+    }
+
+    /// Keep files modified within these unix-second bounds, inclusive.
+    pub fn modified_between(mut self, min: Option<i64>, max: Option<i64>) -> Self {
+        self.query.mtime_min = min;
+        self.query.mtime_max = max;
+        self
+    }
+
+    /// Keep files within these byte sizes, inclusive.
+    pub fn size_between(mut self, min: Option<u64>, max: Option<u64>) -> Self {
+        self.query.size_min = min;
+        self.query.size_max = max;
+        self
+    }
+
+    /// Keep files below this folder; repeat to allow several.
+    pub fn under(mut self, root: impl Into<PathBuf>) -> Self {
+        self.query.roots.push(root.into());
+        self
+    }
+
+    /// Keep paths matching this glob; repeat to allow several.
+    pub fn path_glob(mut self, glob: impl Into<String>) -> Self {
+        self.query.path_globs.push(glob.into());
+        self
+    }
+
+    /// Leave out paths matching this glob.
+    pub fn exclude_glob(mut self, glob: impl Into<String>) -> Self {
+        self.query.exclude_globs.push(glob.into());
+        self
+    }
+
+    /// The finished query; its `term` is empty, and matches nothing, when
+    /// no clause has any words.
+    pub fn build(self) -> SearchQuery {
+        SearchQuery {
+            term: self.render(),
+            ..self.query
+        }
+    }
+
+    fn render(&self) -> String {
+        // Explicit `+` so the query means the same under either
+        // `DefaultOperator`.
+        let mut parts: Vec<String> = self
+            .must
+            .iter()
+            .filter_map(Match::render)
+            .map(|clause| format!("+{clause}"))
+            .collect();
+        let alternatives: Vec<String> = self.should.iter().filter_map(Match::render).collect();
+        match alternatives.len() {
+            0 => {}
+            1 => parts.push(format!("+{}", alternatives[0])),
+            _ => parts.push(format!("+({})", alternatives.join(" OR "))),
+        }
+        parts.extend(
+            self.must_not
+                .iter()
+                .filter_map(Match::render)
+                .map(|clause| format!("-{clause}")),
+        );
+        parts.extend(
+            self.excluded_extensions
+                .iter()
+                .map(|ext| format!("-ext:\"{ext}\"")),
+        );
+        parts.join(" ")
+    }
+}
+
+/// Words the parser would read as boolean operators when left bare.
+fn is_operator(word: &str) -> bool {
+    matches!(word, "AND" | "OR" | "NOT" | "IN")
+}
+
+/// Alphanumeric runs of `text`; everything else, query syntax included,
+/// only separates words, as it does in indexed text.
+fn clean_words(text: &str) -> Vec<String> {
+    text.split(|ch: char| !ch.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Match, QueryBuilder};
+    use crate::query::{search, SearchDomain};
+    use crate::{commit, index_root, init_index_in_memory, RootOptions};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn builds_queries_the_parser_reads_as_meant() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        init_index_in_memory().unwrap();
+        let docs = tempdir().unwrap();
+        for (name, content) in [
+            ("budget 2024.txt", "annual report for the board"),
+            ("budget draft.txt", "annual report, first pass"),
+            ("budget notes.md", "forecast only"),
+            ("roadmap.txt", "annual report"),
+        ] {
+            fs::write(docs.path().join(name), content).unwrap();
+        }
+        index_root(docs.path(), &RootOptions::default()).unwrap();
+        commit().unwrap();
+
+        let query = QueryBuilder::new()
+            .must(Match::term("budget").within(SearchDomain::Name))
+            .should(Match::phrase("annual report").within(SearchDomain::Content))
+            .should(Match::term("forecast"))
+            .must_not(Match::term("draft"))
+            .build();
+        assert_eq!(
+            query.term,
+            "+name:budget +(content:\"annual report\" OR forecast) -draft"
+        );
+        let mut names: Vec<String> = search(query)
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["budget 2024.txt", "budget notes.md"]);
+
+        // Query syntax in host input is only ever text.
+        let hostile = QueryBuilder::new()
+            .must(Match::term("name:(budget"))
+            .must(Match::phrase("\"2024\\"))
+            .extension("txt")
+            .build();
+        assert_eq!(hostile.term, "+\"name budget\" +\"2024\"");
+        let operator = QueryBuilder::new().must(Match::term("OR")).build();
+        assert_eq!(operator.term, "+\"OR\"");
+        assert!(search(hostile).is_ok());
+        assert_eq!(QueryBuilder::new().build().term, "");
+    }
+}
@@ -0,0 +1,221 @@
+use crate::dates::civil_from_days;
+use crate::query::{search_all, SearchHit, SearchQuery};
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Snippet length used when the query doesn't ask for one.
+const REPORT_SNIPPET_CHARS: usize = 240;
+const CSV_COLUMNS: [&str; 9] = [
+    "path",
+    "name",
+    "modified",
+    "size",
+    "score",
+    "matches",
+    "alias_target",
+    "summary",
+    "snippet",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReportFormat {
+    /// A header row, then one row per hit: path, name, modified, size,
+    /// score, matches, alias_target, summary, snippet.
+    #[default]
+    Csv,
+    /// An object with the query, when it ran, and a `hits` array.
+    Json,
+}
+
+/// Run `query` for every file it matches (its `limit` and `offset` are
+/// ignored) and write one entry per hit to `out`, with path, modification
+/// date (UTC, ISO 8601), size, summary and a content snippet — e.g. every
+/// document mentioning a name, for a discovery request. Returns the number
+/// of hits written.
+pub fn write_report<W: Write>(
+    query: SearchQuery,
+    format: ReportFormat,
+    out: &mut W,
+) -> Result<usize> {
+    let term = query.term.clone();
+    let hits = search_all(SearchQuery {
+        snippet_chars: Some(query.snippet_chars.unwrap_or(REPORT_SNIPPET_CHARS)),
+        ..query
+    })?;
+    match format {
+        ReportFormat::Csv => write_csv(&hits, out)?,
+        ReportFormat::Json => write_json(&term, &hits, out)?,
+    }
+    out.flush().context("failed writing report")?;
+    Ok(hits.len())
+}
+
+/// [`write_report`] into a new file at `path`, replacing any file there.
+pub fn save_report(query: SearchQuery, format: ReportFormat, path: &Path) -> Result<usize> {
+    let file = File::create(path).with_context(|| format!("failed creating {}", path.display()))?;
+    write_report(query, format, &mut BufWriter::new(file))
+}
+
+fn write_csv<W: Write>(hits: &[SearchHit], out: &mut W) -> Result<()> {
+    writeln!(out, "{}", CSV_COLUMNS.join(","))?;
+    for hit in hits {
+        let row = columns(hit).map(|value| csv_field(&value));
+        writeln!(out, "{}", row.join(","))?;
+    }
+    Ok(())
+}
+
+fn write_json<W: Write>(term: &str, hits: &[SearchHit], out: &mut W) -> Result<()> {
+    writeln!(out, "{{")?;
+    writeln!(out, "  \"query\": {},", json_string(term))?;
+    writeln!(
+        out,
+        "  \"generated_at\": {},",
+        json_string(&format_utc(now()))
+    )?;
+    writeln!(out, "  \"count\": {},", hits.len())?;
+    write!(out, "  \"hits\": [")?;
+    for (n, hit) in hits.iter().enumerate() {
+        let fields: Vec<String> = CSV_COLUMNS
+            .iter()
+            .zip(columns(hit))
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(name, value)| match *name {
+                "size" | "score" | "matches" => format!("\"{name}\": {value}"),
+                _ => format!("\"{name}\": {}", json_string(&value)),
+            })
+            .collect();
+        let separator = if n == 0 { "" } else { "," };
+        write!(out, "{separator}\n    {{{}}}", fields.join(", "))?;
+    }
+    writeln!(out, "\n  ]\n}}")?;
+    Ok(())
+}
+
+/// A hit's values in [`CSV_COLUMNS`] order; empty when unknown.
+fn columns(hit: &SearchHit) -> [String; 9] {
+    [
+        hit.path.clone(),
+        hit.name.clone(),
+        hit.modified_at.map(format_utc).unwrap_or_default(),
+        hit.size.map(|size| size.to_string()).unwrap_or_default(),
+        format!("{:.3}", hit.score),
+        hit.match_count.to_string(),
+        hit.alias_target.clone().unwrap_or_default(),
+        hit.summary.clone().unwrap_or_default(),
+        hit.snippet
+            .as_ref()
+            .map(|snippet| snippet.text.clone())
+            .unwrap_or_default(),
+    ]
+}
+
+/// Quote per RFC 4180. Values a spreadsheet would run as a formula get a
+/// leading `'`, since file names and contents come from anyone.
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{value}")
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+pub(crate) fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
+
+pub(crate) fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Unix seconds as `YYYY-MM-DDTHH:MM:SSZ`.
+pub(crate) fn format_utc(secs: i64) -> String {
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    let time = secs.rem_euclid(86_400);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_utc, write_report, ReportFormat};
+    use crate::query::{SearchDomain, SearchQuery};
+    use crate::{commit, index_root, init_index_in_memory, RootOptions};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn formats_dates_as_utc() {
+        assert_eq!(format_utc(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_utc(1_700_000_000), "2023-11-14T22:13:20Z");
+        assert_eq!(format_utc(951_782_400), "2000-02-29T00:00:00Z");
+    }
+
+    #[test]
+    fn writes_every_match_as_csv_and_json() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        init_index_in_memory().unwrap();
+        let docs = tempdir().unwrap();
+        fs::write(docs.path().join("a, memo.txt"), "Acme merger \"draft\"").unwrap();
+        fs::write(docs.path().join("b.txt"), "notes on the Acme merger").unwrap();
+        fs::write(docs.path().join("c.txt"), "unrelated").unwrap();
+        index_root(docs.path(), &RootOptions::default()).unwrap();
+        commit().unwrap();
+        let query = SearchQuery {
+            term: "acme".into(),
+            search_in: SearchDomain::Content,
+            limit: 1,
+            ..SearchQuery::default()
+        };
+
+        let mut csv = Vec::new();
+        assert_eq!(
+            write_report(query.clone(), ReportFormat::Csv, &mut csv).unwrap(),
+            2
+        );
+        let csv = String::from_utf8(csv).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("path,name,modified,size,score,matches,alias_target,summary,snippet")
+        );
+        assert!(csv.contains(",\"a, memo.txt\","));
+        assert!(csv.contains(",\"Acme merger \"\"draft"));
+
+        let mut json = Vec::new();
+        write_report(query, ReportFormat::Json, &mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.contains("\"query\": \"acme\""));
+        assert!(json.contains("\"count\": 2"));
+        assert!(json.contains("\"snippet\": \"Acme merger \\\"draft"));
+    }
+}
@@ -0,0 +1,206 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Name of the retry queue sidecar inside the index directory.
+pub const RETRY_QUEUE_FILE_NAME: &str = ".finder-retries";
+const FIRST_RETRY_SECS: i64 = 60;
+const MAX_RETRY_SECS: i64 = 24 * 60 * 60;
+/// After this many failed attempts the file is left to the next mtime change.
+const MAX_ATTEMPTS: u32 = 10;
+
+/// A file whose content could not be read for a reason that should pass
+/// (locked by another app, cloud download pending).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingRetry {
+    pub path: String,
+    /// Failed attempts so far.
+    pub attempts: u32,
+    /// Unix seconds after which the next pass re-reads the file.
+    pub next_attempt_at: i64,
+}
+
+/// Files to re-extract on a later pass even though they look unchanged,
+/// with exponential backoff between attempts. Saved next to the index after
+/// every change; in-memory indexes keep the queue for the process lifetime.
+pub(crate) struct RetryQueue {
+    path: Option<PathBuf>,
+    entries: Mutex<HashMap<String, PendingRetry>>,
+}
+
+impl RetryQueue {
+    pub(crate) fn open(dir: Option<&Path>) -> Self {
+        let path = dir.map(|dir| dir.join(RETRY_QUEUE_FILE_NAME));
+        let entries = path
+            .as_deref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|raw| parse_entries(&raw))
+            .unwrap_or_default();
+        Self {
+            path,
+            entries: Mutex::new(entries),
+        }
+    }
+
+    /// True when `path` is queued and its backoff has elapsed.
+    pub(crate) fn is_due(&self, path: &str, now: i64) -> bool {
+        self.entries
+            .lock()
+            .unwrap()
+            .get(path)
+            .is_some_and(|entry| entry.next_attempt_at <= now)
+    }
+
+    /// Queue `path` after a transient failure, doubling its backoff.
+    pub(crate) fn record_failure(&self, path: &str, now: i64) -> Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+        let attempts = entries.get(path).map_or(0, |entry| entry.attempts) + 1;
+        if attempts > MAX_ATTEMPTS {
+            entries.remove(path);
+        } else {
+            let delay = (FIRST_RETRY_SECS << (attempts - 1).min(20)).min(MAX_RETRY_SECS);
+            entries.insert(
+                path.to_string(),
+                PendingRetry {
+                    path: path.to_string(),
+                    attempts,
+                    next_attempt_at: now + delay,
+                },
+            );
+        }
+        self.save(&entries)
+    }
+
+    /// Forget `path` once it has been read, or when it no longer matters.
+    pub(crate) fn clear(&self, path: &str) -> Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.remove(path).is_none() {
+            return Ok(());
+        }
+        self.save(&entries)
+    }
+
+    pub(crate) fn pending(&self) -> Vec<PendingRetry> {
+        let mut pending: Vec<_> = self.entries.lock().unwrap().values().cloned().collect();
+        pending.sort_by(|a, b| {
+            a.next_attempt_at
+                .cmp(&b.next_attempt_at)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        pending
+    }
+
+    fn save(&self, entries: &HashMap<String, PendingRetry>) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut out = String::new();
+        for entry in entries.values() {
+            out.push_str(&format!(
+                "{}\t{}\t{}\n",
+                entry.attempts, entry.next_attempt_at, entry.path
+            ));
+        }
+        // Write then rename so a crash never leaves a truncated queue.
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, out).with_context(|| format!("failed writing {}", tmp.display()))?;
+        fs::rename(&tmp, path).with_context(|| format!("failed publishing {}", path.display()))
+    }
+}
+
+/// One `attempts<TAB>next_attempt_at<TAB>path` line per entry; malformed
+/// lines are dropped.
+fn parse_entries(raw: &str) -> HashMap<String, PendingRetry> {
+    raw.lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, '\t');
+            let attempts = parts.next()?.parse().ok()?;
+            let next_attempt_at = parts.next()?.parse().ok()?;
+            let path = parts.next().filter(|path| !path.is_empty())?;
+            Some((
+                path.to_string(),
+                PendingRetry {
+                    path: path.to_string(),
+                    attempts,
+                    next_attempt_at,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Whether a read failure is worth retrying later: the file was locked,
+/// busy, or still being materialized by a cloud provider.
+pub(crate) fn is_transient(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|cause| cause.downcast_ref::<io::Error>())
+        .any(|err| {
+            matches!(
+                err.kind(),
+                io::ErrorKind::WouldBlock
+                    | io::ErrorKind::TimedOut
+                    | io::ErrorKind::Interrupted
+                    | io::ErrorKind::ResourceBusy
+                    | io::ErrorKind::Deadlock
+            ) || is_sharing_violation(err)
+        })
+}
+
+/// Windows reports files held open by another program as sharing or lock
+/// violations rather than a portable error kind.
+fn is_sharing_violation(err: &io::Error) -> bool {
+    cfg!(windows) && matches!(err.raw_os_error(), Some(32) | Some(33))
+}
+
+pub(crate) fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|dur| dur.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_transient, RetryQueue, FIRST_RETRY_SECS, MAX_ATTEMPTS};
+    use anyhow::Context;
+    use std::io;
+    use tempfile::tempdir;
+
+    #[test]
+    fn backs_off_and_survives_reopening() {
+        let dir = tempdir().unwrap();
+        let queue = RetryQueue::open(Some(dir.path()));
+        let path = "/Users/me/iCloud/draft\twith tab.pages";
+        queue.record_failure(path, 1_000).unwrap();
+        queue.record_failure(path, 1_000).unwrap();
+        assert!(!queue.is_due(path, 1_000 + FIRST_RETRY_SECS));
+        assert!(queue.is_due(path, 1_000 + 2 * FIRST_RETRY_SECS));
+
+        let reopened = RetryQueue::open(Some(dir.path()));
+        let pending = reopened.pending();
+        assert_eq!(pending.len(), 1);
+        assert_eq!((pending[0].path.as_str(), pending[0].attempts), (path, 2));
+
+        reopened.clear(path).unwrap();
+        assert!(RetryQueue::open(Some(dir.path())).pending().is_empty());
+
+        for _ in 0..=MAX_ATTEMPTS {
+            queue.record_failure("/stuck", 0).unwrap();
+        }
+        assert!(queue.pending().iter().all(|entry| entry.path != "/stuck"));
+    }
+
+    #[test]
+    fn only_lock_and_timeout_errors_are_transient() {
+        let busy = Err::<(), _>(io::Error::from(io::ErrorKind::ResourceBusy))
+            .context("failed to read /a")
+            .unwrap_err();
+        assert!(is_transient(&busy));
+        let missing = anyhow::Error::from(io::Error::from(io::ErrorKind::NotFound));
+        assert!(!is_transient(&missing));
+    }
+}
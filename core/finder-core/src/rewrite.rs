@@ -0,0 +1,122 @@
+use crate::query;
+use once_cell::sync::Lazy;
+use std::sync::{Arc, RwLock};
+
+/// Rewrites the raw query string before it is parsed, e.g. to inject a
+/// scope or expand user macros. Runs for every search, sessions included.
+pub trait QueryRewriter: Send + Sync {
+    /// Return the replacement query, or `None` to search `raw` unchanged.
+    fn rewrite(&self, raw: &str) -> Option<String>;
+}
+
+/// Built-in rewriter that replaces whole words with their expansions, so
+/// `@inv 2024` can become `invoice pdf 2024`.
+#[derive(Debug, Clone, Default)]
+pub struct QueryMacros {
+    macros: Vec<(String, String)>,
+}
+
+impl QueryMacros {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace the expansion for `word`.
+    pub fn with(mut self, word: impl Into<String>, expansion: impl Into<String>) -> Self {
+        let word = word.into();
+        let expansion = expansion.into();
+        match self.macros.iter_mut().find(|(known, _)| *known == word) {
+            Some(slot) => slot.1 = expansion,
+            None => self.macros.push((word, expansion)),
+        }
+        self
+    }
+}
+
+impl QueryRewriter for QueryMacros {
+    fn rewrite(&self, raw: &str) -> Option<String> {
+        let mut expanded = false;
+        let words: Vec<&str> = raw
+            .split_whitespace()
+            .map(
+                |word| match self.macros.iter().find(|(known, _)| known == word) {
+                    Some((_, expansion)) => {
+                        expanded = true;
+                        expansion.as_str()
+                    }
+                    None => word,
+                },
+            )
+            .collect();
+        expanded.then(|| words.join(" "))
+    }
+}
+
+static REWRITER: Lazy<RwLock<Option<Arc<dyn QueryRewriter>>>> = Lazy::new(|| RwLock::new(None));
+
+/// Install the rewriter applied to every search from now on, or `None` to
+/// search query strings as given.
+pub fn set_query_rewriter(rewriter: Option<Arc<dyn QueryRewriter>>) {
+    let mut guard = REWRITER.write().unwrap();
+    *guard = rewriter;
+    drop(guard);
+    query::clear_result_cache();
+}
+
+/// `raw` after the installed rewriter, if any, has had its say.
+pub(crate) fn rewrite_query(raw: &str) -> String {
+    let rewriter = REWRITER.read().unwrap().clone();
+    rewriter
+        .and_then(|rewriter| rewriter.rewrite(raw))
+        .unwrap_or_else(|| raw.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{set_query_rewriter, QueryMacros, QueryRewriter};
+    use crate::query::{SearchDomain, SearchQuery};
+    use crate::scanner::FileMeta;
+    use crate::{add_or_update_file, commit, init_index, search};
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    #[test]
+    fn macros_expand_whole_words_only() {
+        let macros = QueryMacros::new()
+            .with("@inv", "invoice")
+            .with("@inv", "invoice pdf");
+        assert_eq!(
+            macros.rewrite("@inv  2024").as_deref(),
+            Some("invoice pdf 2024")
+        );
+        assert!(macros.rewrite("@invoices 2024").is_none());
+    }
+
+    #[test]
+    fn installed_rewriter_applies_to_searches() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        init_index(dir.path().to_str().unwrap()).unwrap();
+        let meta = FileMeta {
+            path: "/docs/invoice-march.txt".into(),
+            name: "invoice-march.txt".into(),
+            ext: Some("txt".into()),
+            size: 10,
+            inode: 42,
+            ..FileMeta::default()
+        };
+        add_or_update_file(meta, Some("paid in full".into()), false).unwrap();
+        commit().unwrap();
+
+        let query = || SearchQuery {
+            term: "@inv".into(),
+            search_in: SearchDomain::Name,
+            ..SearchQuery::default()
+        };
+        set_query_rewriter(Some(Arc::new(QueryMacros::new().with("@inv", "invoice"))));
+        let hits = search(query());
+        set_query_rewriter(None);
+        assert_eq!(hits.unwrap().len(), 1);
+        assert!(search(query()).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,266 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Size estimation stops after visiting this many entries per root so
+/// first-run setup stays responsive on huge volumes.
+const MAX_ESTIMATE_ENTRIES: u64 = 200_000;
+
+/// Folders in the home directory offered as roots on every platform.
+const HOME_FOLDERS: &[(&str, RootKind)] = &[
+    ("Documents", RootKind::Documents),
+    ("Desktop", RootKind::Desktop),
+    ("Downloads", RootKind::Downloads),
+];
+
+/// iCloud Drive's local mirror, relative to the home directory.
+const ICLOUD_DRIVE_DIR: &str = "Library/Mobile Documents/com~apple~CloudDocs";
+
+/// Volume names that belong to the OS rather than the user.
+const SYSTEM_VOLUME_NAMES: &[&str] = &[
+    "Macintosh HD",
+    "Macintosh HD - Data",
+    "Recovery",
+    "Preboot",
+    "VM",
+    "Update",
+    "com.apple.TimeMachine.localsnapshots",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RootKind {
+    Documents,
+    Desktop,
+    Downloads,
+    CloudDrive,
+    Volume,
+}
+
+/// A candidate root for first-run setup.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RootSuggestion {
+    pub path: String,
+    /// Display name, e.g. "Documents" or a volume's name.
+    pub label: String,
+    pub kind: RootKind,
+    pub estimated_files: u64,
+    pub estimated_bytes: u64,
+    /// True when the walk hit its entry budget, so the estimate is a lower bound.
+    pub estimate_truncated: bool,
+}
+
+/// Propose default roots for this machine: the usual home folders, iCloud
+/// Drive when present, and mounted user volumes. Only existing directories
+/// are returned, each with a rough size estimate.
+pub fn suggest_roots() -> Vec<RootSuggestion> {
+    let home = home_dir();
+    suggest_roots_in(home.as_deref(), &volume_parents())
+}
+
+fn suggest_roots_in(home: Option<&Path>, volume_parents: &[PathBuf]) -> Vec<RootSuggestion> {
+    let mut candidates: Vec<(PathBuf, String, RootKind)> = Vec::new();
+
+    if let Some(home) = home {
+        for (folder, kind) in HOME_FOLDERS {
+            candidates.push((home.join(folder), folder.to_string(), *kind));
+        }
+        candidates.push((
+            home.join(ICLOUD_DRIVE_DIR),
+            "iCloud Drive".to_string(),
+            RootKind::CloudDrive,
+        ));
+    }
+
+    for parent in volume_parents {
+        let Ok(entries) = fs::read_dir(parent) else {
+            continue;
+        };
+        let mut volumes: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| is_user_volume(path))
+            .collect();
+        volumes.sort();
+        for volume in volumes {
+            let label = volume
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            candidates.push((volume, label, RootKind::Volume));
+        }
+    }
+
+    candidates
+        .into_iter()
+        .filter(|(path, _, _)| path.is_dir())
+        .map(|(path, label, kind)| {
+            let (estimated_files, estimated_bytes, estimate_truncated) = estimate_size(&path);
+            RootSuggestion {
+                path: path.to_string_lossy().to_string(),
+                label,
+                kind,
+                estimated_files,
+                estimated_bytes,
+                estimate_truncated,
+            }
+        })
+        .collect()
+}
+
+/// Roots after removing duplicates and roots nested inside other roots.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NormalizedRoots {
+    /// Canonical, non-overlapping roots in their original order.
+    pub roots: Vec<PathBuf>,
+    /// Each dropped root paired with the root that already covers it.
+    pub covered: Vec<(PathBuf, PathBuf)>,
+}
+
+/// Canonicalize `roots` (resolving `..` and symlinks) and drop any root that
+/// equals or sits inside another, so adding both `~` and `~/Documents` scans
+/// the documents once. Roots that can't be canonicalized are kept as given.
+pub fn normalize_roots<P: AsRef<Path>>(roots: &[P]) -> NormalizedRoots {
+    let canonical: Vec<PathBuf> = roots
+        .iter()
+        .map(|root| {
+            let root = root.as_ref();
+            fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf())
+        })
+        .collect();
+
+    let mut normalized = NormalizedRoots::default();
+    for (i, root) in canonical.iter().enumerate() {
+        // Cover with the outermost root containing this one; among identical
+        // roots the first listed survives.
+        let cover = canonical
+            .iter()
+            .enumerate()
+            .filter(|(_, other)| root.starts_with(other))
+            .min_by_key(|(j, other)| (other.components().count(), *j))
+            .filter(|(j, _)| *j != i);
+        match cover {
+            Some((_, other)) => normalized.covered.push((root.clone(), other.clone())),
+            None => normalized.roots.push(root.clone()),
+        }
+    }
+    normalized
+}
+
+fn is_user_volume(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if name.starts_with('.') || SYSTEM_VOLUME_NAMES.contains(&name.as_str()) {
+        return false;
+    }
+    // macOS links the boot volume into /Volumes; following it would suggest "/".
+    if fs::symlink_metadata(path)
+        .map(|meta| meta.file_type().is_symlink())
+        .unwrap_or(true)
+    {
+        return false;
+    }
+    path.is_dir()
+}
+
+fn estimate_size(root: &Path) -> (u64, u64, bool) {
+    let mut files = 0;
+    let mut bytes = 0;
+    let mut visited = 0;
+    let walker = WalkDir::new(root).into_iter().filter_entry(|entry| {
+        entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.')
+    });
+    for entry in walker.filter_map(|entry| entry.ok()) {
+        visited += 1;
+        if visited > MAX_ESTIMATE_ENTRIES {
+            return (files, bytes, true);
+        }
+        if entry.file_type().is_file() {
+            files += 1;
+            bytes += entry.metadata().map(|meta| meta.len()).unwrap_or(0);
+        }
+    }
+    (files, bytes, false)
+}
+
+fn home_dir() -> Option<PathBuf> {
+    let var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    env::var_os(var)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Directories whose children are mounted removable or network volumes.
+fn volume_parents() -> Vec<PathBuf> {
+    if cfg!(target_os = "macos") {
+        vec![PathBuf::from("/Volumes")]
+    } else if cfg!(target_os = "linux") {
+        let mut parents = vec![PathBuf::from("/mnt")];
+        if let Some(user) = env::var_os("USER") {
+            parents.push(Path::new("/media").join(&user));
+            parents.push(Path::new("/run/media").join(&user));
+        }
+        parents
+    } else {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize_roots, suggest_roots_in, RootKind};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn suggests_existing_home_folders_and_volumes_with_sizes() {
+        let home = tempdir().unwrap();
+        fs::create_dir(home.path().join("Documents")).unwrap();
+        fs::write(home.path().join("Documents/a.txt"), "12345").unwrap();
+        fs::create_dir(home.path().join("Downloads")).unwrap();
+
+        let volumes = tempdir().unwrap();
+        fs::create_dir(volumes.path().join("Backup")).unwrap();
+        fs::write(volumes.path().join("Backup/b.bin"), [0u8; 10]).unwrap();
+        fs::create_dir(volumes.path().join("Recovery")).unwrap();
+        fs::create_dir(volumes.path().join(".hidden")).unwrap();
+
+        let suggestions = suggest_roots_in(Some(home.path()), &[volumes.path().to_path_buf()]);
+        let labels: Vec<_> = suggestions.iter().map(|s| s.label.as_str()).collect();
+        assert_eq!(labels, vec!["Documents", "Downloads", "Backup"]);
+
+        assert_eq!(suggestions[0].kind, RootKind::Documents);
+        assert_eq!(suggestions[0].estimated_files, 1);
+        assert_eq!(suggestions[0].estimated_bytes, 5);
+        assert_eq!(suggestions[1].estimated_files, 0);
+        assert_eq!(suggestions[2].kind, RootKind::Volume);
+        assert_eq!(suggestions[2].estimated_bytes, 10);
+        assert!(!suggestions[2].estimate_truncated);
+    }
+
+    #[test]
+    fn drops_duplicate_and_nested_roots() {
+        let home = tempdir().unwrap();
+        let home_path = home.path().canonicalize().unwrap();
+        let docs = home_path.join("Documents");
+        let other = tempdir().unwrap();
+        fs::create_dir(&docs).unwrap();
+
+        let normalized = normalize_roots(&[
+            docs.clone(),
+            home_path.clone(),
+            docs.join("..").join("Documents"),
+            other.path().to_path_buf(),
+            home_path.clone(),
+        ]);
+        assert_eq!(
+            normalized.roots,
+            vec![home_path.clone(), other.path().canonicalize().unwrap()]
+        );
+        assert_eq!(normalized.covered.len(), 3);
+        assert!(normalized.covered.iter().all(|(_, by)| *by == home_path));
+    }
+}
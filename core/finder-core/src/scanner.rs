@@ -1,8 +1,13 @@
+use crate::alias::resolve_alias;
+use crate::plist::{read_plist, PlistValue};
+use crate::textnorm::{fold_for_search, to_nfc};
 use anyhow::Result;
 use ignore::WalkBuilder;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::time::UNIX_EPOCH;
 
@@ -15,6 +20,21 @@ pub struct FileMeta {
     pub size: u64,
     pub inode: u64,
     pub dev: u64,
+    /// Target path when the file is a Finder alias or Windows shortcut.
+    #[serde(default)]
+    pub alias_target: Option<String>,
+    /// True for a macOS bundle (`.app`, `.framework`, ...) indexed as one
+    /// document in place of its internals.
+    #[serde(default)]
+    pub bundle: bool,
+    /// Bundle display name from its `Info.plist`, when it declares one.
+    #[serde(default)]
+    pub display_name: Option<String>,
+    /// Set for a folder indexed as a document of its own: how many scanned
+    /// files and folders it holds directly. `size` is then the total size
+    /// of the files anywhere below it.
+    #[serde(default)]
+    pub children: Option<u64>,
 }
 
 impl FileMeta {
@@ -27,19 +47,223 @@ impl FileMeta {
             format!("path:{}", self.path)
         }
     }
+
+    pub fn is_folder(&self) -> bool {
+        self.children.is_some()
+    }
+
+    /// Aliases, bundles and folders stand in for something else; reading
+    /// their bytes as text would only index noise.
+    pub fn skips_content(&self) -> bool {
+        self.bundle || self.alias_target.is_some() || self.is_folder()
+    }
 }
 
 const SKIP_DIR_NAMES: &[&str] = &[".git", "Library", "node_modules", ".Trash"];
 
+/// Directory extensions macOS presents as a single item in Finder.
+const BUNDLE_EXTENSIONS: &[&str] = &[
+    "app",
+    "appex",
+    "bundle",
+    "framework",
+    "kext",
+    "plugin",
+    "prefpane",
+    "qlgenerator",
+    "mdimporter",
+    "xpc",
+    "photoslibrary",
+    "musiclibrary",
+    "tvlibrary",
+    "imovielibrary",
+    "fcpbundle",
+];
+
+/// Where bundles keep their `Info.plist`, most common layout first.
+const BUNDLE_INFO_PLISTS: &[&str] = &["Contents/Info.plist", "Resources/Info.plist", "Info.plist"];
+
+/// Well-known files that never carry user content worth searching.
+const NOISE_FILE_NAMES: &[&str] = &[
+    ".DS_Store",
+    ".localized",
+    "Icon\r",
+    "Thumbs.db",
+    "ehthumbs.db",
+    "desktop.ini",
+];
+
+/// Package manager lockfiles, noise inside a dependency's folder but the
+/// user's own work at the top of a project.
+const LOCKFILE_NAMES: &[&str] = &[
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "npm-shrinkwrap.json",
+];
+
+/// Folders holding installed dependencies; [`LOCKFILE_NAMES`] below them
+/// are skipped. `node_modules` itself is never scanned.
+const DEPENDENCY_DIR_NAMES: &[&str] = &["node_modules", "bower_components", "jspm_packages"];
+
+/// AppleDouble resource-fork companions (`._report.pdf`) created on non-HFS volumes.
+const NOISE_NAME_PREFIXES: &[&str] = &["._"];
+
+/// Files larger than this are never hashed for noise matching.
+const MAX_NOISE_HASH_BYTES: u64 = 64 * 1024;
+
+/// Name and checksum rules for files that should be dropped at scan time.
+/// Starts from the built-in list and can be extended by the host.
+#[derive(Debug, Clone)]
+pub struct NoiseRules {
+    names: HashSet<String>,
+    /// Names skipped only below a dependency folder.
+    lockfiles: HashSet<String>,
+    prefixes: Vec<String>,
+    hashes: HashSet<String>,
+}
+
+impl Default for NoiseRules {
+    fn default() -> Self {
+        Self {
+            names: NOISE_FILE_NAMES
+                .iter()
+                .map(|name| fold_for_search(name))
+                .collect(),
+            lockfiles: LOCKFILE_NAMES
+                .iter()
+                .map(|name| fold_for_search(name))
+                .collect(),
+            prefixes: NOISE_NAME_PREFIXES.iter().map(|p| p.to_string()).collect(),
+            hashes: HashSet::new(),
+        }
+    }
+}
+
+impl NoiseRules {
+    /// Rules that match nothing, for callers that want every file.
+    pub fn none() -> Self {
+        Self {
+            names: HashSet::new(),
+            lockfiles: HashSet::new(),
+            prefixes: Vec::new(),
+            hashes: HashSet::new(),
+        }
+    }
+
+    /// Add exact file names (case-insensitive) to skip.
+    pub fn with_names<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.names
+            .extend(names.into_iter().map(|name| fold_for_search(name.as_ref())));
+        self
+    }
+
+    /// Add content checksums (as produced by [`noise_checksum`]) to skip,
+    /// regardless of file name.
+    pub fn with_hashes<I, S>(mut self, hashes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.hashes
+            .extend(hashes.into_iter().map(|hash| hash.as_ref().to_lowercase()));
+        self
+    }
+
+    pub fn is_noise(&self, path: &Path, size: u64) -> bool {
+        if self.is_noise_by_name(path) {
+            return true;
+        }
+        if self.hashes.is_empty() || size > MAX_NOISE_HASH_BYTES {
+            return false;
+        }
+        noise_checksum(path)
+            .map(|hash| self.hashes.contains(&hash))
+            .unwrap_or(false)
+    }
+
+    /// Name and prefix rules only; checksum rules need the file's bytes.
+    fn is_noise_by_name(&self, path: &Path) -> bool {
+        let name = path
+            .file_name()
+            .map(|s| fold_for_search(&s.to_string_lossy()))
+            .unwrap_or_default();
+        self.names.contains(&name)
+            || self.prefixes.iter().any(|prefix| name.starts_with(prefix))
+            || (self.lockfiles.contains(&name) && in_dependency_dir(path))
+    }
+}
+
+fn in_dependency_dir(path: &Path) -> bool {
+    path.ancestors().skip(1).any(|dir| {
+        dir.file_name()
+            .is_some_and(|name| DEPENDENCY_DIR_NAMES.iter().any(|dep| name == *dep))
+    })
+}
+
+/// FNV-1a 64-bit checksum of a file's contents, hex encoded. Stable across
+/// releases so users can persist hashes in their configuration.
+pub fn noise_checksum<P: AsRef<Path>>(path: P) -> Result<String> {
+    let mut file = fs::File::open(path.as_ref())?;
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let mut buffer = [0u8; 8192];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        for &byte in &buffer[..read] {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    Ok(format!("{hash:016x}"))
+}
+
+/// Result of a scan, including how many files were dropped by [`NoiseRules`].
+#[derive(Debug, Clone, Default)]
+pub struct ScanReport {
+    pub files: Vec<FileMeta>,
+    /// Folders below the root, with their child counts and total sizes
+    /// worked out from the scanned files.
+    pub folders: Vec<FileMeta>,
+    pub skipped_noise: usize,
+}
+
 /// Scan the provided root directory, respecting ignore files, and return discovered file metadata.
+/// Built-in noise files are skipped; use [`scan_root_with_rules`] to customize or count them.
 pub fn scan_root<P: AsRef<Path>>(root: P) -> Result<Vec<FileMeta>> {
-    let root = root.as_ref();
+    Ok(scan_root_with_rules(root, &NoiseRules::default())?.files)
+}
+
+/// Scan like [`scan_root`], dropping files matched by `rules` and reporting how many were skipped.
+pub fn scan_root_with_rules<P: AsRef<Path>>(root: P, rules: &NoiseRules) -> Result<ScanReport> {
+    scan_root_with(root.as_ref(), rules, false)
+}
+
+/// Scan like [`scan_root_with_rules`]. With `metadata_only`, no file is
+/// opened: checksum rules are skipped, aliases aren't resolved and bundle
+/// `Info.plist` files aren't parsed.
+pub(crate) fn scan_root_with(
+    root: &Path,
+    rules: &NoiseRules,
+    metadata_only: bool,
+) -> Result<ScanReport> {
     let mut builder = WalkBuilder::new(root);
     builder.standard_filters(true);
     builder.filter_entry(|entry| {
         if entry.depth() == 0 {
             return true;
         }
+        // A bundle is one item; its internals are never walked. A root that
+        // is itself a bundle is still scanned, since the user asked for it.
+        if entry.depth() > 1 && entry.path().parent().is_some_and(is_bundle_path) {
+            return false;
+        }
         let name = entry.file_name().to_string_lossy();
         let is_dir = entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false);
         if is_dir {
@@ -55,28 +279,121 @@ pub fn scan_root<P: AsRef<Path>>(root: P) -> Result<Vec<FileMeta>> {
 
     let walker = builder.build();
 
-    let paths: Vec<PathBuf> = walker
-        .filter_map(|entry| entry.ok())
-        .filter(|entry| entry.file_type().map(|ft| ft.is_file()).unwrap_or(false))
-        .map(|entry| entry.into_path())
-        .collect();
+    let mut paths: Vec<PathBuf> = Vec::new();
+    let mut folders: Vec<PathBuf> = Vec::new();
+    for entry in walker.filter_map(|entry| entry.ok()) {
+        let Some(ft) = entry.file_type() else {
+            continue;
+        };
+        if ft.is_file() || (ft.is_dir() && entry.depth() > 0 && is_bundle_path(entry.path())) {
+            paths.push(entry.into_path());
+        } else if ft.is_dir() && entry.depth() > 0 {
+            folders.push(entry.into_path());
+        }
+    }
 
-    let mut entries: Vec<_> = paths
+    let metas: Vec<_> = paths
         .par_iter()
-        .filter_map(|path| build_meta(path).ok())
+        .filter_map(|path| build_meta_with(path, metadata_only).ok())
+        .collect();
+
+    let total = metas.len();
+    let mut entries: Vec<_> = metas
+        .into_par_iter()
+        .filter(|meta| {
+            let path = Path::new(&meta.path);
+            if metadata_only {
+                !rules.is_noise_by_name(path)
+            } else {
+                !rules.is_noise(path, meta.size)
+            }
+        })
         .collect();
+    let skipped_noise = total - entries.len();
 
     // Sort by modified time (newest first) for recent-first indexing
     entries.par_sort_by(|a, b| b.modified_at.cmp(&a.modified_at));
-    Ok(entries)
+    let folders = folder_metas(root, folders, &entries);
+    Ok(ScanReport {
+        files: entries,
+        folders,
+        skipped_noise,
+    })
 }
 
-fn build_meta(path: &Path) -> Result<FileMeta> {
+/// Metadata for `folders`, each holding the `files` and other folders
+/// directly inside it and sized by the files anywhere below it.
+fn folder_metas(root: &Path, folders: Vec<PathBuf>, files: &[FileMeta]) -> Vec<FileMeta> {
+    let mut children: HashMap<&Path, u64> = HashMap::new();
+    let mut sizes: HashMap<&Path, u64> = HashMap::new();
+    for file in files {
+        let path = Path::new(&file.path);
+        if let Some(parent) = path.parent() {
+            *children.entry(parent).or_default() += 1;
+        }
+        for dir in path.ancestors().skip(1) {
+            if dir == root {
+                break;
+            }
+            *sizes.entry(dir).or_default() += file.size;
+        }
+    }
+    for folder in &folders {
+        if let Some(parent) = folder.parent() {
+            *children.entry(parent).or_default() += 1;
+        }
+    }
+    folders
+        .par_iter()
+        .filter_map(|folder| {
+            let mut meta = build_meta_with(folder, true).ok()?;
+            let folder = folder.as_path();
+            meta.ext = None;
+            meta.bundle = false;
+            meta.display_name = None;
+            meta.size = sizes.get(folder).copied().unwrap_or(0);
+            meta.children = Some(children.get(folder).copied().unwrap_or(0));
+            Some(meta)
+        })
+        .collect()
+}
+
+fn is_bundle_path(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| {
+            let ext = ext.to_string_lossy();
+            BUNDLE_EXTENSIONS
+                .iter()
+                .any(|bundle| ext.eq_ignore_ascii_case(bundle))
+        })
+        .unwrap_or(false)
+}
+
+/// The name Finder shows for a bundle, when its `Info.plist` sets one.
+fn bundle_display_name(bundle: &Path) -> Option<String> {
+    let info = BUNDLE_INFO_PLISTS
+        .iter()
+        .find_map(|relative| read_plist(&bundle.join(relative)))?;
+    ["CFBundleDisplayName", "CFBundleName"]
+        .iter()
+        .filter_map(|key| info.get(key).and_then(PlistValue::as_str))
+        .map(str::trim)
+        .find(|name| !name.is_empty())
+        .map(str::to_string)
+}
+
+pub(crate) fn build_meta(path: &Path) -> Result<FileMeta> {
+    build_meta_with(path, false)
+}
+
+/// [`build_meta`] from the file system entry alone when `metadata_only`:
+/// aliases and bundles then get no target or display name.
+pub(crate) fn build_meta_with(path: &Path, metadata_only: bool) -> Result<FileMeta> {
     let metadata = fs::symlink_metadata(path)?;
 
     let name = path
         .file_name()
-        .map(|s| s.to_string_lossy().to_string())
+        .map(|s| to_nfc(&s.to_string_lossy()))
         .unwrap_or_default();
     let ext = path
         .extension()
@@ -99,6 +416,18 @@ fn build_meta(path: &Path) -> Result<FileMeta> {
     #[cfg(not(unix))]
     let (inode, dev) = (0, 0);
 
+    let bundle = metadata.is_dir();
+    let display_name = if bundle && !metadata_only {
+        bundle_display_name(path)
+    } else {
+        None
+    };
+    let alias_target = if bundle || metadata_only {
+        None
+    } else {
+        resolve_alias(path, ext.as_deref(), metadata.len())
+    };
+
     Ok(FileMeta {
         path: path.to_string_lossy().to_string(),
         name,
@@ -107,12 +436,16 @@ fn build_meta(path: &Path) -> Result<FileMeta> {
         size: metadata.len(),
         inode,
         dev,
+        alias_target,
+        bundle,
+        display_name,
+        children: None,
     })
 }
 
 #[cfg(test)]
 mod tests {
-    use super::scan_root;
+    use super::{noise_checksum, scan_root, scan_root_with_rules, NoiseRules};
     use std::fs;
     use tempfile::tempdir;
 
@@ -130,4 +463,82 @@ mod tests {
         assert!(names.contains(&"a.txt"));
         assert!(names.contains(&"b.md"));
     }
+
+    #[test]
+    fn skips_noise_files_by_name_and_hash() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("notes.txt"), "keep me").unwrap();
+        fs::write(root.join("Thumbs.db"), "explorer junk").unwrap();
+        fs::write(root.join("desktop.ini"), "[.ShellClassInfo]").unwrap();
+        fs::write(root.join("custom.lock"), "generated").unwrap();
+        fs::write(root.join("boilerplate.txt"), "same template").unwrap();
+
+        let default_scan = scan_root_with_rules(root, &NoiseRules::default()).unwrap();
+        assert_eq!(default_scan.files.len(), 3);
+        assert_eq!(default_scan.skipped_noise, 2);
+
+        let hash = noise_checksum(root.join("boilerplate.txt")).unwrap();
+        let rules = NoiseRules::default()
+            .with_names(["CUSTOM.lock"])
+            .with_hashes([hash]);
+        let custom_scan = scan_root_with_rules(root, &rules).unwrap();
+        let names: Vec<_> = custom_scan.files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["notes.txt"]);
+        assert_eq!(custom_scan.skipped_noise, 4);
+
+        let everything = scan_root_with_rules(root, &NoiseRules::none()).unwrap();
+        assert_eq!(everything.files.len(), 5);
+        assert_eq!(everything.skipped_noise, 0);
+    }
+
+    #[test]
+    fn skips_lockfiles_only_inside_dependency_folders() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("package-lock.json"), "{}").unwrap();
+        let dependency = root.join("bower_components/left-pad");
+        fs::create_dir_all(&dependency).unwrap();
+        fs::write(dependency.join("yarn.lock"), "# yarn lockfile v1").unwrap();
+        fs::write(dependency.join("README.md"), "pads left").unwrap();
+
+        let scan = scan_root_with_rules(root, &NoiseRules::default()).unwrap();
+        let mut names: Vec<_> = scan.files.iter().map(|f| f.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["README.md", "package-lock.json"]);
+        assert_eq!(scan.skipped_noise, 1);
+    }
+
+    #[test]
+    fn treats_bundles_as_single_items() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let app = root.join("Preview.app/Contents");
+        fs::create_dir_all(app.join("MacOS")).unwrap();
+        fs::write(app.join("MacOS/Preview"), "binary").unwrap();
+        fs::write(
+            app.join("Info.plist"),
+            crate::plist::tests::binary_plist(&[("CFBundleDisplayName", "Image Viewer")]),
+        )
+        .unwrap();
+        fs::create_dir_all(root.join("Pictures.photoslibrary/originals")).unwrap();
+        fs::write(root.join("Pictures.photoslibrary/originals/1.jpg"), "jpg").unwrap();
+        fs::write(root.join("readme.txt"), "hello").unwrap();
+
+        let mut files = scan_root(root).unwrap();
+        files.sort_by(|a, b| a.name.cmp(&b.name));
+        let names: Vec<_> = files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["Pictures.photoslibrary", "Preview.app", "readme.txt"]
+        );
+        assert!(files[1].bundle && files[1].skips_content());
+        assert_eq!(files[1].display_name.as_deref(), Some("Image Viewer"));
+        assert_eq!(files[0].display_name, None);
+        assert!(!files[2].bundle);
+
+        // Scanning a bundle directly walks its internals.
+        let inside = scan_root(root.join("Preview.app")).unwrap();
+        assert_eq!(inside.len(), 2);
+    }
 }
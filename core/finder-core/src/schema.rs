@@ -1,13 +1,115 @@
-use tantivy::schema::{NumericOptions, Schema, SchemaBuilder, STORED, STRING, TEXT};
+use crate::textnorm::fold_for_search;
+use tantivy::schema::{
+    FacetOptions, FieldType, IndexRecordOption, NumericOptions, Schema, SchemaBuilder,
+    TextFieldIndexing, TextOptions, FAST, STORED, STRING, TEXT,
+};
+use tantivy::tokenizer::{NgramTokenizer, TextAnalyzer};
 
+/// Tokenizer of the `name_prefix` field; registered on every opened index.
+pub(crate) const NAME_PREFIX_TOKENIZER: &str = "name_prefix";
+/// Tokenizer of the `name_parts` field; registered on every opened index.
+pub(crate) const NAME_PARTS_TOKENIZER: &str = "name_parts";
+/// Longest word prefix indexed for typeahead. Longer typed words are
+/// matched on their first this many characters.
+pub(crate) const MAX_PREFIX_CHARS: usize = 16;
+
+/// How the `content` field is broken into words. Chosen through
+/// [`IndexSettings::content_analyzer`](crate::IndexSettings) when an index
+/// is created and recorded in its schema; an existing index keeps the one
+/// it was built with, and queries read content the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContentAnalyzer {
+    /// Folded words as written.
+    #[default]
+    Plain,
+    /// Folded words reduced to their English stems, so `running` finds
+    /// `runs` and `invoices` finds `invoice`.
+    English,
+}
+
+impl ContentAnalyzer {
+    pub(crate) const ALL: [ContentAnalyzer; 2] = [ContentAnalyzer::Plain, ContentAnalyzer::English];
+
+    /// Name of the tokenizer the `content` field records, with or without
+    /// the index's stop words taken out.
+    pub(crate) fn tokenizer(self, stop_words: bool) -> &'static str {
+        match (self, stop_words) {
+            // The plain analyzer is registered as "default", which `TEXT`
+            // fields name, so indexes made before the choice existed read
+            // as plain.
+            (ContentAnalyzer::Plain, false) => "default",
+            (ContentAnalyzer::Plain, true) => "content_stop",
+            (ContentAnalyzer::English, false) => "en_stem",
+            (ContentAnalyzer::English, true) => "en_stem_stop",
+        }
+    }
+
+    /// The analyzer recorded in `schema` and whether it drops stop words,
+    /// or `None` when its `content` field names a tokenizer this build
+    /// doesn't know.
+    pub(crate) fn of_schema(schema: &Schema) -> Option<(Self, bool)> {
+        let field = schema.get_field("content").ok()?;
+        let FieldType::Str(options) = schema.get_field_entry(field).field_type() else {
+            return None;
+        };
+        let tokenizer = options.get_indexing_options()?.tokenizer();
+        Self::ALL
+            .into_iter()
+            .flat_map(|analyzer| [(analyzer, false), (analyzer, true)])
+            .find(|(analyzer, stop_words)| analyzer.tokenizer(*stop_words) == tokenizer)
+    }
+}
+
+/// Version of the layout [`build_schema_with`] produces, bumped with every
+/// change to it. An index written with an older version lacks fields this
+/// build relies on, so it is dropped and rebuilt when opened for writing.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Name of the file, inside the index directory, holding the
+/// [`SCHEMA_VERSION`] the index was created with. Indexes from before the
+/// marker existed have none and count as version 1.
+pub const SCHEMA_VERSION_FILE_NAME: &str = ".finder-schema-version";
+
+/// The schema of a new index with plain content words.
 pub fn build_schema() -> Schema {
+    build_schema_with(ContentAnalyzer::default(), false)
+}
+
+/// The schema of a new index whose `content` field uses `content`, with
+/// [stop words](crate::set_stop_words) left out when `stop_words` is set.
+pub fn build_schema_with(content: ContentAnalyzer, stop_words: bool) -> Schema {
     let mut builder = SchemaBuilder::default();
 
-    builder.add_text_field("path", STRING | STORED);
+    // Fast so hits that tie otherwise can be ordered by path while collecting.
+    builder.add_text_field("path", STRING | STORED | FAST);
     builder.add_text_field("name", TEXT | STORED);
-    builder.add_text_field("name_raw", STRING | STORED);
-    builder.add_text_field("ext", STRING);
+    // Fast so results can be sorted by name while collecting.
+    builder.add_text_field("name_raw", STRING | STORED | FAST);
+    // Every prefix of every name word, for as-you-type lookups that are a
+    // single term lookup each.
+    let name_prefix = TextOptions::default().set_indexing_options(
+        TextFieldIndexing::default()
+            .set_tokenizer(NAME_PREFIX_TOKENIZER)
+            .set_index_option(IndexRecordOption::WithFreqs),
+    );
+    builder.add_text_field("name_prefix", name_prefix);
+    // Name words with identifiers split at case and digit changes, so
+    // `invoice` finds `MyInvoiceParser_v2.ts`. Positions, for phrases over
+    // the parts.
+    let name_parts = TextOptions::default().set_indexing_options(
+        TextFieldIndexing::default()
+            .set_tokenizer(NAME_PARTS_TOKENIZER)
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+    );
+    builder.add_text_field("name_parts", name_parts);
+    builder.add_text_field("ext", STRING | FAST);
+    // "file" or "folder", so `kind:folder` finds folders alone.
+    builder.add_text_field("kind", STRING | STORED);
     builder.add_text_field("identity", STRING | STORED);
+    // Every ancestor directory of the file, for folder filters and suggestions.
+    builder.add_text_field("dir", STRING);
+    // The file's folder as a hierarchical facet, for drill-down counts.
+    builder.add_facet_field("folder", FacetOptions::default());
 
     let mtime = NumericOptions::default().set_stored().set_fast();
     builder.add_i64_field("mtime", mtime);
@@ -15,13 +117,75 @@ pub fn build_schema() -> Schema {
     let size = NumericOptions::default().set_stored().set_fast();
     builder.add_u64_field("size", size);
 
+    // Path components and name characters, so equal hits can prefer
+    // shallow paths and short names while collecting.
+    let tie_break = NumericOptions::default().set_fast();
+    builder.add_u64_field("depth", tie_break.clone());
+    builder.add_u64_field("name_len", tie_break);
+
     let inode = NumericOptions::default().set_stored();
     builder.add_u64_field("inode", inode);
 
     let dev = NumericOptions::default().set_stored();
     builder.add_u64_field("dev", dev);
 
-    builder.add_text_field("content", TEXT);
+    // Positions, which phrase queries need.
+    let content = TextOptions::default().set_indexing_options(
+        TextFieldIndexing::default()
+            .set_tokenizer(content.tokenizer(stop_words))
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+    );
+    builder.add_text_field("content", content);
+
+    builder.add_text_field("email", STRING);
+    builder.add_text_field("phone", STRING);
+    builder.add_text_field("mentions_date", STRING);
+    builder.add_text_field("amount", STRING);
+
+    builder.add_text_field("summary", STORED);
+
+    // Where an alias or shortcut points; tokenized so the target's name finds it.
+    builder.add_text_field("alias_target", TEXT | STORED);
+
+    // Shape of CSV/TSV files; absent on everything else.
+    let table_shape = NumericOptions::default().set_stored().set_fast();
+    builder.add_u64_field("columns", table_shape.clone());
+    builder.add_u64_field("rows", table_shape);
+
+    // Entries directly inside a folder; absent on files.
+    let children = NumericOptions::default().set_stored();
+    builder.add_u64_field("children", children);
+
+    let has_content = NumericOptions::default()
+        .set_indexed()
+        .set_stored()
+        .set_fast();
+    builder.add_u64_field("has_content", has_content);
+
+    // 0 on a file's own document; 1, 2, ... on the chunk documents holding
+    // the rest of a long file's content.
+    let chunk = NumericOptions::default()
+        .set_indexed()
+        .set_stored()
+        .set_fast();
+    builder.add_u64_field("chunk", chunk);
 
     builder.build()
 }
+
+/// Indexes `1..=MAX_PREFIX_CHARS`-character prefixes of each value, which
+/// are single words from [`name_words`].
+pub(crate) fn name_prefix_analyzer() -> TextAnalyzer {
+    let prefixes = NgramTokenizer::prefix_only(1, MAX_PREFIX_CHARS).expect("valid ngram bounds");
+    TextAnalyzer::from(prefixes)
+}
+
+/// Words of a file name as typeahead sees them: runs of letters and
+/// digits after [`fold_for_search`].
+pub(crate) fn name_words(text: &str) -> Vec<String> {
+    fold_for_search(text)
+        .split(|ch: char| !ch.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
@@ -0,0 +1,217 @@
+use crate::indexer;
+use crate::query::{
+    run_search, run_search_after, run_search_page, SearchHit, SearchPage, SearchQuery, StreamCursor,
+};
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::vec;
+use tantivy::Searcher;
+
+/// A search snapshot that ignores later commits. Paging through results with
+/// one session keeps documents and their order stable even while background
+/// indexing commits; drop the session to release the snapshot's segments.
+pub struct SearchSession {
+    searcher: Searcher,
+}
+
+impl SearchSession {
+    /// Pin the index as of the last reload.
+    pub fn pin() -> Result<Self> {
+        let reader = indexer::reader().context("reader not available")?;
+        Ok(Self {
+            searcher: reader.searcher(),
+        })
+    }
+
+    /// Run `query` against the pinned snapshot.
+    pub fn search(&self, query: SearchQuery) -> Result<Vec<SearchHit>> {
+        Ok(run_search(query, &self.searcher, None)?.unwrap_or_default())
+    }
+
+    /// [`search_page`](crate::search_page) against the pinned snapshot, so
+    /// pages neither skip nor repeat hits while indexing commits.
+    pub fn search_page(&self, query: SearchQuery) -> Result<SearchPage> {
+        Ok(run_search_page(query, &self.searcher, None)?.unwrap_or_default())
+    }
+
+    /// The hits of `query` one at a time, fetched a page of `query.limit`
+    /// at a time from the pinned snapshot; see [`search_stream`].
+    pub fn stream(&self, query: SearchQuery) -> SearchStream {
+        SearchStream {
+            searcher: self.searcher.clone(),
+            skip: query.offset,
+            query,
+            page: Vec::new().into_iter(),
+            cursor: Some(StreamCursor::start()),
+            shown: HashSet::new(),
+        }
+    }
+
+    /// Identifies the pinned snapshot; sessions pinned between the same two
+    /// reloads share a generation.
+    pub fn generation(&self) -> u64 {
+        self.searcher.generation().generation_id()
+    }
+}
+
+/// Every hit of `query`, best first, without holding them all at once: the
+/// index is pinned, and hits are fetched a page of `query.limit` at a time
+/// as the iterator is drained, so a UI can show the first page while it
+/// asks for more. The first `query.offset` hits are passed over. Each page
+/// picks up after the last document the previous one read, so later pages
+/// cost no more than the first.
+pub fn search_stream(query: SearchQuery) -> Result<SearchStream> {
+    Ok(SearchSession::pin()?.stream(query))
+}
+
+/// Iterator returned by [`search_stream`] and [`SearchSession::stream`].
+pub struct SearchStream {
+    searcher: Searcher,
+    query: SearchQuery,
+    /// Hits of `query.offset` still to pass over.
+    skip: usize,
+    page: vec::IntoIter<SearchHit>,
+    /// Where the next page starts; `None` once the index has no more.
+    cursor: Option<StreamCursor>,
+    /// Files already yielded, so one whose documents straddle two pages
+    /// shows up once when collapsing.
+    shown: HashSet<String>,
+}
+
+impl Iterator for SearchStream {
+    type Item = Result<SearchHit>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            for hit in self.page.by_ref() {
+                if self.query.collapse && !self.shown.insert(hit.path.clone()) {
+                    continue;
+                }
+                if self.skip > 0 {
+                    self.skip -= 1;
+                    continue;
+                }
+                return Some(Ok(hit));
+            }
+            // A page can come back empty when globs filter all it read;
+            // only a missing cursor means the end.
+            let cursor = self.cursor.take()?;
+            match run_search_after(self.query.clone(), &self.searcher, &cursor) {
+                Ok((hits, next)) => {
+                    self.page = hits.into_iter();
+                    self.cursor = next;
+                }
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{search_stream, SearchSession};
+    use crate::query::{search, SearchDomain, SearchQuery};
+    use crate::scanner::FileMeta;
+    use crate::{add_or_update_file, commit, init_index_in_memory};
+
+    fn add(name: &str, inode: u64) {
+        add_in("/notes", name, inode);
+    }
+
+    fn add_in(folder: &str, name: &str, inode: u64) {
+        let meta = FileMeta {
+            path: format!("{folder}/{name}"),
+            name: name.into(),
+            inode,
+            dev: 1,
+            ..FileMeta::default()
+        };
+        add_or_update_file(meta, None, false).unwrap();
+    }
+
+    #[test]
+    fn pinned_session_ignores_later_commits() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        init_index_in_memory().unwrap();
+        add("budget 2023.txt", 1);
+        commit().unwrap();
+
+        let query = || SearchQuery {
+            term: "budget".into(),
+            search_in: SearchDomain::Name,
+            path_globs: Vec::new(),
+            limit: 10,
+            ..SearchQuery::default()
+        };
+        let session = SearchSession::pin().unwrap();
+        let generation = session.generation();
+        add("budget 2024.txt", 2);
+        commit().unwrap();
+
+        assert_eq!(session.search(query()).unwrap().len(), 1);
+        assert_eq!(search(query()).unwrap().len(), 2);
+        assert_eq!(session.generation(), generation);
+        assert_ne!(SearchSession::pin().unwrap().generation(), generation);
+    }
+
+    #[test]
+    fn streams_every_hit_a_page_at_a_time() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        init_index_in_memory().unwrap();
+        for n in 0..7 {
+            add(&format!("scan {n}.pdf"), n + 1);
+        }
+        commit().unwrap();
+
+        let query = SearchQuery {
+            term: "scan".into(),
+            search_in: SearchDomain::Name,
+            limit: 3,
+            ..SearchQuery::default()
+        };
+        let mut stream = search_stream(query.clone()).unwrap();
+        let first = stream.next().unwrap().unwrap();
+        // Later commits don't shift the pages still to come.
+        add("scan 99.pdf", 99);
+        commit().unwrap();
+        let mut names: Vec<String> = std::iter::once(Ok(first))
+            .chain(stream)
+            .map(|hit| hit.unwrap().name)
+            .collect();
+        assert_eq!(names.len(), 7);
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), 7);
+        assert_eq!(search_stream(query).unwrap().count(), 8);
+    }
+
+    #[test]
+    fn streams_past_pages_that_globs_filter_out() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        init_index_in_memory().unwrap();
+        // The kept files rank last, behind pages of filtered ones.
+        for n in 0..40 {
+            add_in("/archive", &format!("scan {n}.pdf"), n + 1);
+        }
+        add_in("/keep", "scan kept one.pdf", 100);
+        add_in("/keep", "scan kept two.pdf", 101);
+        commit().unwrap();
+
+        let query = SearchQuery {
+            term: "scan".into(),
+            search_in: SearchDomain::Name,
+            path_globs: vec!["/keep/*".into()],
+            limit: 2,
+            ..SearchQuery::default()
+        };
+        let mut names: Vec<String> = search_stream(query.clone())
+            .unwrap()
+            .map(|hit| hit.unwrap().name)
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["scan kept one.pdf", "scan kept two.pdf"]);
+
+        let skipped = search_stream(SearchQuery { offset: 1, ..query }).unwrap();
+        assert_eq!(skipped.count(), 1);
+    }
+}
@@ -0,0 +1,243 @@
+use crate::indexer::IndexedDocument;
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Name of the identity map sidecar inside the index directory.
+pub const IDENTITY_MAP_FILE_NAME: &str = ".finder-identities";
+const MAGIC: &[u8; 8] = b"FCIDMAP1";
+/// Bytes of the header: magic, opstamp and entry count.
+const HEADER_BYTES: u64 = 24;
+/// Bytes of an entry with empty strings: two lengths, mtime, size and flag.
+const MIN_ENTRY_BYTES: u64 = 4 + 4 + 8 + 8 + 1;
+/// Rewriting the whole map on every commit would dominate a busy indexing
+/// run, so commits save at most this often. A sidecar that falls behind the
+/// index is detected by its opstamp and rebuilt.
+const MIN_SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Persisted copy of the indexer's identity map, stamped with the commit
+/// opstamp it matches so startup can trust it without walking the index.
+pub(crate) struct IdentitySidecar {
+    path: PathBuf,
+    last_save: Mutex<Option<Instant>>,
+    /// The map changed since the last save.
+    dirty: AtomicBool,
+    /// Documents were added since the last commit, so the map is ahead of
+    /// what is on disk and must not be saved.
+    uncommitted: AtomicBool,
+}
+
+impl IdentitySidecar {
+    pub(crate) fn new(dir: &Path) -> Self {
+        Self {
+            path: dir.join(IDENTITY_MAP_FILE_NAME),
+            last_save: Mutex::new(None),
+            dirty: AtomicBool::new(false),
+            uncommitted: AtomicBool::new(false),
+        }
+    }
+
+    /// The map stored for `opstamp`, or `None` when the sidecar is missing,
+    /// unreadable or written for a different commit.
+    pub(crate) fn load(&self, opstamp: u64) -> Option<HashMap<String, IndexedDocument>> {
+        match read_map(&self.path) {
+            Ok((stamp, map)) if stamp == opstamp => Some(map),
+            Ok(_) => None,
+            Err(err) => {
+                if self.path.exists() {
+                    eprintln!("[sidecar] ignoring unreadable identity map: {err}");
+                }
+                None
+            }
+        }
+    }
+
+    pub(crate) fn mark_changed(&self) {
+        self.dirty.store(true, Ordering::SeqCst);
+        self.uncommitted.store(true, Ordering::SeqCst);
+    }
+
+    /// Save after a commit if the map changed and the last save is old
+    /// enough. Must run while adds are excluded so `map` matches `opstamp`.
+    pub(crate) fn after_commit(
+        &self,
+        opstamp: u64,
+        map: &HashMap<String, IndexedDocument>,
+    ) -> Result<()> {
+        self.uncommitted.store(false, Ordering::SeqCst);
+        let due = self
+            .last_save
+            .lock()
+            .unwrap()
+            .map(|at| at.elapsed() >= MIN_SAVE_INTERVAL)
+            .unwrap_or(true);
+        if due && self.dirty.load(Ordering::SeqCst) {
+            self.save(opstamp, map)?;
+        }
+        Ok(())
+    }
+
+    /// Final save when the index closes, skipped if there are uncommitted
+    /// documents (they would be lost, and the map would claim them).
+    pub(crate) fn on_close(&self, opstamp: u64, map: &HashMap<String, IndexedDocument>) {
+        if self.dirty.load(Ordering::SeqCst) && !self.uncommitted.load(Ordering::SeqCst) {
+            if let Err(err) = self.save(opstamp, map) {
+                eprintln!("[sidecar] failed saving identity map: {err}");
+            }
+        }
+    }
+
+    pub(crate) fn save(&self, opstamp: u64, map: &HashMap<String, IndexedDocument>) -> Result<()> {
+        let tmp = self.path.with_extension("tmp");
+        write_map(&tmp, opstamp, map)?;
+        fs::rename(&tmp, &self.path)
+            .with_context(|| format!("failed publishing {}", self.path.display()))?;
+        self.dirty.store(false, Ordering::SeqCst);
+        *self.last_save.lock().unwrap() = Some(Instant::now());
+        Ok(())
+    }
+}
+
+fn write_map(path: &Path, opstamp: u64, map: &HashMap<String, IndexedDocument>) -> Result<()> {
+    let file =
+        fs::File::create(path).with_context(|| format!("failed creating {}", path.display()))?;
+    let mut out = BufWriter::new(file);
+    out.write_all(MAGIC)?;
+    out.write_all(&opstamp.to_le_bytes())?;
+    out.write_all(&(map.len() as u64).to_le_bytes())?;
+    for (identity, doc) in map {
+        write_str(&mut out, identity)?;
+        write_str(&mut out, &doc.path)?;
+        out.write_all(&doc.mtime.to_le_bytes())?;
+        out.write_all(&doc.size.to_le_bytes())?;
+        out.write_all(&[u8::from(doc.has_content)])?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Counts and lengths come from the file, so each is checked against the
+/// bytes left in it before anything is allocated; a damaged file is an
+/// error, and the map is rebuilt from the index.
+fn read_map(path: &Path) -> Result<(u64, HashMap<String, IndexedDocument>)> {
+    let file = fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    if len < HEADER_BYTES {
+        bail!("identity map is truncated");
+    }
+    let mut input = BufReader::new(file);
+    let mut magic = [0u8; 8];
+    input.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        bail!("unrecognized identity map header");
+    }
+    let opstamp = read_u64(&mut input)?;
+    let count = read_u64(&mut input)?;
+    let mut left = len - HEADER_BYTES;
+    if count > left / MIN_ENTRY_BYTES {
+        bail!("identity map claims {count} entries but holds {left} bytes");
+    }
+    let mut map = HashMap::with_capacity(count as usize);
+    for _ in 0..count {
+        let identity = read_str(&mut input, &mut left)?;
+        let path = read_str(&mut input, &mut left)?;
+        let mtime = read_u64(&mut input)? as i64;
+        let size = read_u64(&mut input)?;
+        let mut flag = [0u8; 1];
+        input.read_exact(&mut flag)?;
+        left = left
+            .checked_sub(8 + 8 + 1)
+            .context("identity map is truncated")?;
+        map.insert(
+            identity,
+            IndexedDocument {
+                path,
+                mtime,
+                size,
+                has_content: flag[0] != 0,
+            },
+        );
+    }
+    Ok((opstamp, map))
+}
+
+fn write_str(out: &mut impl Write, value: &str) -> Result<()> {
+    out.write_all(&(value.len() as u32).to_le_bytes())?;
+    out.write_all(value.as_bytes())?;
+    Ok(())
+}
+
+/// A length-prefixed string, refused when it would run past the `left`
+/// bytes of the file.
+fn read_str(input: &mut impl Read, left: &mut u64) -> Result<String> {
+    let mut len = [0u8; 4];
+    input.read_exact(&mut len)?;
+    let len = u64::from(u32::from_le_bytes(len));
+    *left = left
+        .checked_sub(4 + len)
+        .context("identity map entry runs past the end of the file")?;
+    let mut bytes = vec![0u8; len as usize];
+    input.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).context("identity map entry is not UTF-8")
+}
+
+fn read_u64(input: &mut impl Read) -> Result<u64> {
+    let mut bytes = [0u8; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IdentitySidecar, IDENTITY_MAP_FILE_NAME, MAGIC};
+    use crate::indexer::IndexedDocument;
+    use std::collections::HashMap;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn round_trips_and_rejects_other_opstamps() {
+        let dir = tempdir().unwrap();
+        let sidecar = IdentitySidecar::new(dir.path());
+        assert!(sidecar.load(0).is_none());
+
+        let mut map = HashMap::new();
+        map.insert(
+            "1:42".to_string(),
+            IndexedDocument {
+                path: "/tmp/odd\tname\n.txt".into(),
+                mtime: -5,
+                size: 9,
+                has_content: true,
+            },
+        );
+        sidecar.save(7, &map).unwrap();
+        assert_eq!(sidecar.load(7), Some(map));
+        assert!(sidecar.load(8).is_none());
+    }
+
+    #[test]
+    fn rejects_counts_and_lengths_the_file_cannot_hold() {
+        let dir = tempdir().unwrap();
+        let sidecar = IdentitySidecar::new(dir.path());
+        let path = dir.path().join(IDENTITY_MAP_FILE_NAME);
+        let header = |count: u64| [&MAGIC[..], &7u64.to_le_bytes(), &count.to_le_bytes()].concat();
+
+        fs::write(&path, header(u64::MAX)).unwrap();
+        assert!(sidecar.load(7).is_none());
+
+        let mut huge_string = header(1);
+        huge_string.extend_from_slice(&u32::MAX.to_le_bytes());
+        huge_string.extend_from_slice(&[0; 40]);
+        fs::write(&path, huge_string).unwrap();
+        assert!(sidecar.load(7).is_none());
+
+        fs::write(&path, &MAGIC[..5]).unwrap();
+        assert!(sidecar.load(7).is_none());
+    }
+}
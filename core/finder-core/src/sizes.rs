@@ -0,0 +1,117 @@
+use anyhow::{bail, Result};
+
+/// Inclusive byte bounds for `op value` in a `size:` filter: `>10MB`,
+/// `<=512k`, a range such as `1..5gb` (either end may be left open, and
+/// a unit on the upper end carries to a bare lower one), or a bare size
+/// for exactly that many bytes.
+///
+/// `KB`, `MB`, `GB` and `TB` (or just `k`, `m`, `g`, `t`) are decimal, as
+/// Finder counts them; `KiB`, `MiB`, `GiB` and `TiB` are binary. Fractions such as `1.5gb` are allowed.
+pub(crate) fn size_bounds(op: &str, value: &str) -> Result<(Option<u64>, Option<u64>)> {
+    let value = value.trim().to_lowercase();
+    if let Some((low, high)) = value.split_once("..") {
+        if !op.is_empty() {
+            bail!("a size range takes no comparison: size:{op}{value}");
+        }
+        let high_unit = unit_of(high);
+        let low_unit = if unit_of(low).is_empty() {
+            high_unit
+        } else {
+            unit_of(low)
+        };
+        let bound = |text: &str, unit: &str| -> Result<Option<u64>> {
+            if text.trim().is_empty() {
+                return Ok(None);
+            }
+            Ok(Some(parse_size(text, unit)?))
+        };
+        let (min, max) = (bound(low, low_unit)?, bound(high, high_unit)?);
+        if let (Some(min), Some(max)) = (min, max) {
+            if min > max {
+                bail!("size range {value:?} runs backwards");
+            }
+        }
+        return Ok((min, max));
+    }
+    let size = parse_size(&value, unit_of(&value))?;
+    Ok(match op {
+        ">" => (Some(size.saturating_add(1)), None),
+        ">=" => (Some(size), None),
+        "<" => (None, Some(size.saturating_sub(1))),
+        "<=" => (None, Some(size)),
+        _ => (Some(size), Some(size)),
+    })
+}
+
+/// Whatever follows the number, e.g. `mb` in `10mb`.
+fn unit_of(text: &str) -> &str {
+    let text = text.trim();
+    let start = text
+        .find(|ch: char| !ch.is_ascii_digit() && ch != '.')
+        .unwrap_or(text.len());
+    text[start..].trim()
+}
+
+fn parse_size(text: &str, unit: &str) -> Result<u64> {
+    let text = text.trim();
+    let number = text[..text.len() - unit_of(text).len()].trim();
+    let multiplier: f64 = match unit {
+        "" | "b" | "byte" | "bytes" => 1.0,
+        "k" | "kb" => 1e3,
+        "m" | "mb" => 1e6,
+        "g" | "gb" => 1e9,
+        "t" | "tb" => 1e12,
+        "kib" => 1024.0,
+        "mib" => 1024.0 * 1024.0,
+        "gib" => 1024.0 * 1024.0 * 1024.0,
+        "tib" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        _ => bail!("unrecognized size unit {unit:?}; try 10kb, 2.5MB, 1GiB or 4096"),
+    };
+    let bytes = match number.parse::<f64>() {
+        Ok(count) if count >= 0.0 => (count * multiplier).round(),
+        _ => bail!("unrecognized size {text:?}; try 10kb, 2.5MB, 1GiB or 4096"),
+    };
+    if bytes >= u64::MAX as f64 {
+        bail!("size {text:?} is too large");
+    }
+    Ok(bytes as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::size_bounds;
+    use crate::term_filters::extract_at;
+    use crate::DateLocale;
+
+    #[test]
+    fn reads_units_comparisons_and_ranges() {
+        let bounds = |op: &str, value: &str| size_bounds(op, value).unwrap();
+        assert_eq!(bounds(">", "10MB"), (Some(10_000_001), None));
+        assert_eq!(bounds("<=", "1KiB"), (None, Some(1_024)));
+        assert_eq!(bounds("<", "512"), (None, Some(511)));
+        assert_eq!(
+            bounds("", "1.5gb"),
+            (Some(1_500_000_000), Some(1_500_000_000))
+        );
+        assert_eq!(
+            bounds("", "1..5gb"),
+            (Some(1_000_000_000), Some(5_000_000_000))
+        );
+        assert_eq!(bounds("", "100k..2mib"), (Some(100_000), Some(2_097_152)));
+        assert_eq!(bounds("", "1gb.."), (Some(1_000_000_000), None));
+        assert!(size_bounds("", "5..1gb").is_err());
+        assert!(size_bounds(">", "1..2").is_err());
+        assert!(size_bounds("", "ten mb").is_err());
+        assert!(size_bounds("", "10 parsecs").is_err());
+
+        let filters = extract_at(
+            "video size:>100mb size:<=2GB",
+            0,
+            &DateLocale::from_name("en_US", 0),
+        )
+        .unwrap();
+        assert_eq!(filters.term, "video");
+        assert_eq!(filters.size_min, Some(100_000_001));
+        assert_eq!(filters.size_max, Some(2_000_000_000));
+    }
+}
@@ -0,0 +1,386 @@
+use crate::indexer::commit;
+use crate::pipeline::{reindex_subtree, RootOptions};
+use crate::query::{count, search, SearchDomain, SearchQuery};
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Violations kept in the report; the count keeps going past this.
+const MAX_REPORTED_VIOLATIONS: usize = 50;
+
+/// How long and how hard to soak.
+#[derive(Debug, Clone)]
+pub struct SoakConfig {
+    pub duration: Duration,
+    /// Files kept in the tree at any moment, roughly.
+    pub files: usize,
+    /// Pause between mutations.
+    pub mutation_interval: Duration,
+    /// Pause between indexing passes, standing in for a file watcher.
+    pub index_interval: Duration,
+    /// Threads searching while the tree changes.
+    pub query_threads: usize,
+    /// A change older than this must be visible to search: new and renamed
+    /// files found, deleted and renamed-away paths gone.
+    pub settle: Duration,
+    pub seed: u64,
+}
+
+impl Default for SoakConfig {
+    fn default() -> Self {
+        Self {
+            duration: Duration::from_secs(60),
+            files: 200,
+            mutation_interval: Duration::from_millis(5),
+            index_interval: Duration::from_millis(250),
+            query_threads: 2,
+            settle: Duration::from_secs(3),
+            seed: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SoakReport {
+    pub mutations: usize,
+    pub index_passes: usize,
+    /// Passes that failed, e.g. when a file vanished mid-scan; the next
+    /// pass has to catch up.
+    pub index_errors: usize,
+    pub queries: usize,
+    pub checks: usize,
+    pub violation_count: usize,
+    /// The first violations, in the order found.
+    pub violations: Vec<String>,
+}
+
+impl SoakReport {
+    pub fn is_clean(&self) -> bool {
+        self.violation_count == 0
+    }
+
+    fn violation(&mut self, message: String) {
+        self.violation_count += 1;
+        if self.violations.len() < MAX_REPORTED_VIOLATIONS {
+            self.violations.push(message);
+        }
+    }
+}
+
+/// A file the mutator created, under its current name and content version.
+struct Tracked {
+    path: PathBuf,
+    rename: usize,
+    version: usize,
+    changed: Instant,
+}
+
+/// A path or content version that must stop matching once settled.
+struct Gone {
+    term: String,
+    path: PathBuf,
+    domain: SearchDomain,
+    since: Instant,
+}
+
+/// Create, modify, rename and delete files under the new or empty folder
+/// `dir` for `config.duration` while a background thread keeps reindexing
+/// it and others search, checking that settled changes are reflected:
+/// no missing files and no stale hits. Needs an open index; panics and
+/// query errors count as violations. After the run, one last indexing pass
+/// is made and every file checked.
+pub fn run_soak(dir: &Path, config: &SoakConfig) -> Result<SoakReport> {
+    if fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_some()) {
+        bail!("{} is not empty", dir.display());
+    }
+    fs::create_dir_all(dir).with_context(|| format!("failed creating {}", dir.display()))?;
+    let dir = fs::canonicalize(dir)?;
+    let stop = Arc::new(AtomicBool::new(false));
+    let report = Arc::new(Mutex::new(SoakReport::default()));
+    let queries = Arc::new(AtomicUsize::new(0));
+
+    let indexer = {
+        let (dir, stop, report) = (dir.clone(), stop.clone(), report.clone());
+        let interval = config.index_interval;
+        thread::spawn(move || {
+            while !stop.load(Ordering::Relaxed) {
+                let pass = index_pass(&dir);
+                let mut report = report.lock().unwrap();
+                report.index_passes += 1;
+                if pass.is_err() {
+                    report.index_errors += 1;
+                }
+                drop(report);
+                thread::sleep(interval);
+            }
+        })
+    };
+    let searchers: Vec<_> = (0..config.query_threads)
+        .map(|n| {
+            let (stop, report, queries) = (stop.clone(), report.clone(), queries.clone());
+            let mut rng = Rng(config.seed ^ ((n as u64 + 1) << 32));
+            let files = config.files.max(1) as u64;
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let query = SearchQuery {
+                        term: format!("soak{}r0 OR body{}v0", rng.below(files), rng.below(files)),
+                        snippet_chars: Some(80),
+                        ..SearchQuery::default()
+                    };
+                    let outcome = search(query.clone()).and_then(|_| count(query));
+                    queries.fetch_add(1, Ordering::Relaxed);
+                    if let Err(err) = outcome {
+                        report
+                            .lock()
+                            .unwrap()
+                            .violation(format!("query failed: {err:#}"));
+                    }
+                }
+            })
+        })
+        .collect();
+
+    let mutated = mutate(&dir, config, &report);
+    stop.store(true, Ordering::Relaxed);
+    for (name, handle) in
+        std::iter::once(("indexer", indexer)).chain(searchers.into_iter().map(|h| ("query", h)))
+    {
+        if handle.join().is_err() {
+            report
+                .lock()
+                .unwrap()
+                .violation(format!("{name} thread panicked"));
+        }
+    }
+    let (files, gone) = mutated?;
+
+    // Everything has settled once one more pass runs after the last change.
+    index_pass(&dir)?;
+    let mut report = Arc::try_unwrap(report)
+        .map_err(|_| anyhow::anyhow!("soak threads still running"))?
+        .into_inner()
+        .unwrap();
+    check(&files, &gone, None, &mut report);
+    report.queries = queries.load(Ordering::Relaxed);
+    Ok(report)
+}
+
+fn index_pass(dir: &Path) -> Result<()> {
+    reindex_subtree(dir, &RootOptions::default())?;
+    commit()
+}
+
+type Mutated = (BTreeMap<usize, Tracked>, Vec<Gone>);
+
+fn mutate(dir: &Path, config: &SoakConfig, report: &Mutex<SoakReport>) -> Result<Mutated> {
+    let mut rng = Rng(config.seed);
+    let mut files: BTreeMap<usize, Tracked> = BTreeMap::new();
+    let mut gone: Vec<Gone> = Vec::new();
+    let mut next_id = 0;
+    // Synthetic, strictly increasing mtimes so quick successive edits
+    // never look unchanged to the indexer's dedup.
+    let mut clock = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs())
+        .unwrap_or(0);
+    let started = Instant::now();
+    let mut last_check = started;
+
+    while started.elapsed() < config.duration {
+        clock += 1;
+        let roll = rng.below(10);
+        let target = (!files.is_empty()).then(|| {
+            let ids: Vec<usize> = files.keys().copied().collect();
+            ids[rng.below(ids.len() as u64) as usize]
+        });
+        match target {
+            Some(id) if files.len() >= config.files || roll < 3 => match roll % 3 {
+                0 => {
+                    let file = files.get_mut(&id).unwrap();
+                    gone.push(Gone {
+                        term: content_term(id, file.version),
+                        path: file.path.clone(),
+                        domain: SearchDomain::Content,
+                        since: Instant::now(),
+                    });
+                    file.version += 1;
+                    file.changed = Instant::now();
+                    write_file(&file.path, id, file.version, clock)?;
+                }
+                1 => {
+                    let file = files.get_mut(&id).unwrap();
+                    let renamed = file.path.with_file_name(file_name(id, file.rename + 1));
+                    fs::rename(&file.path, &renamed)?;
+                    gone.push(Gone {
+                        term: name_term(id, file.rename),
+                        path: file.path.clone(),
+                        domain: SearchDomain::Name,
+                        since: Instant::now(),
+                    });
+                    file.rename += 1;
+                    file.path = renamed;
+                    file.changed = Instant::now();
+                }
+                _ => {
+                    let file = files.remove(&id).unwrap();
+                    fs::remove_file(&file.path)?;
+                    gone.push(Gone {
+                        term: name_term(id, file.rename),
+                        path: file.path,
+                        domain: SearchDomain::Name,
+                        since: Instant::now(),
+                    });
+                }
+            },
+            _ => {
+                let id = next_id;
+                next_id += 1;
+                let folder = dir.join(format!("d{}", rng.below(8)));
+                fs::create_dir_all(&folder)?;
+                let path = folder.join(file_name(id, 0));
+                write_file(&path, id, 0, clock)?;
+                files.insert(
+                    id,
+                    Tracked {
+                        path,
+                        rename: 0,
+                        version: 0,
+                        changed: Instant::now(),
+                    },
+                );
+            }
+        }
+        report.lock().unwrap().mutations += 1;
+
+        if last_check.elapsed() >= config.settle / 2 {
+            last_check = Instant::now();
+            let mut checked = SoakReport::default();
+            check(&files, &gone, Some(config.settle), &mut checked);
+            let mut report = report.lock().unwrap();
+            report.checks += checked.checks;
+            for violation in checked.violations {
+                report.violation(violation);
+            }
+            gone.retain(|entry| entry.since.elapsed() < config.settle * 4);
+        }
+        thread::sleep(config.mutation_interval);
+    }
+    Ok((files, gone))
+}
+
+/// Check every change older than `settle`, or all of them when `None`.
+fn check(
+    files: &BTreeMap<usize, Tracked>,
+    gone: &[Gone],
+    settle: Option<Duration>,
+    report: &mut SoakReport,
+) {
+    let settled = |at: Instant| settle.is_none_or(|settle| at.elapsed() >= settle);
+    let found = |term: String, domain: SearchDomain, path: &Path| {
+        search(SearchQuery {
+            term,
+            search_in: domain,
+            ..SearchQuery::default()
+        })
+        .map(|hits| hits.iter().any(|hit| Path::new(&hit.path) == path))
+    };
+    for (id, file) in files.iter().filter(|(_, file)| settled(file.changed)) {
+        report.checks += 1;
+        let expected = [
+            (name_term(*id, file.rename), SearchDomain::Name),
+            (content_term(*id, file.version), SearchDomain::Content),
+        ];
+        for (term, domain) in expected {
+            match found(term.clone(), domain, &file.path) {
+                Ok(true) => {}
+                Ok(false) => report.violation(format!(
+                    "missing: {} not found by {term:?}",
+                    file.path.display()
+                )),
+                Err(err) => report.violation(format!("query {term:?} failed: {err:#}")),
+            }
+        }
+    }
+    for entry in gone.iter().filter(|entry| settled(entry.since)) {
+        report.checks += 1;
+        match found(entry.term.clone(), entry.domain, &entry.path) {
+            Ok(false) => {}
+            Ok(true) => report.violation(format!(
+                "stale: {} still found by {:?}",
+                entry.path.display(),
+                entry.term
+            )),
+            Err(err) => report.violation(format!("query {:?} failed: {err:#}", entry.term)),
+        }
+    }
+}
+
+fn name_term(id: usize, rename: usize) -> String {
+    format!("soak{id}r{rename}")
+}
+
+fn content_term(id: usize, version: usize) -> String {
+    format!("body{id}v{version}")
+}
+
+fn file_name(id: usize, rename: usize) -> String {
+    format!("{}.txt", name_term(id, rename))
+}
+
+fn write_file(path: &Path, id: usize, version: usize, mtime: u64) -> Result<()> {
+    fs::write(path, format!("soak file {}\n", content_term(id, version)))?;
+    File::options()
+        .write(true)
+        .open(path)?
+        .set_modified(UNIX_EPOCH + Duration::from_secs(mtime))?;
+    Ok(())
+}
+
+/// xorshift64*, seeded so a failing run can be repeated.
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, bound: u64) -> u64 {
+        let mut x = self.0.max(1);
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.0 = x;
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D) % bound.max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run_soak, SoakConfig};
+    use crate::init_index_in_memory;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    #[test]
+    fn short_soak_keeps_search_consistent() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        init_index_in_memory().unwrap();
+        let dir = tempdir().unwrap();
+        let config = SoakConfig {
+            duration: Duration::from_millis(1_500),
+            files: 30,
+            mutation_interval: Duration::from_millis(2),
+            index_interval: Duration::from_millis(50),
+            query_threads: 2,
+            settle: Duration::from_millis(600),
+            seed: 7,
+        };
+        let report = run_soak(&dir.path().join("tree"), &config).unwrap();
+        assert!(report.is_clean(), "{:#?}", report.violations);
+        assert!(report.mutations > 100);
+        assert!(report.index_passes > 5);
+        assert!(report.queries > 0);
+        assert!(report.checks > 30);
+    }
+}
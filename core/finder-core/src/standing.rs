@@ -0,0 +1,151 @@
+use crate::diff::diff_hits;
+use crate::events::{self, IndexEvent};
+use crate::query::{SearchHit, SearchQuery};
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// A saved search re-run after every commit so new matches can be announced.
+struct StandingQuery {
+    id: u64,
+    name: String,
+    query: SearchQuery,
+    /// Hits as of the last check.
+    last_hits: Vec<SearchHit>,
+}
+
+static STANDING: Lazy<Mutex<Vec<StandingQuery>>> = Lazy::new(|| Mutex::new(Vec::new()));
+static NEXT_STANDING_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Watch `query`: whenever a commit (or, in a read-only process, a reload
+/// through [`refresh_if_changed`](crate::refresh_if_changed)) brings new
+/// matches, [`IndexEvent::StandingQueryMatched`] lists them. Files matching
+/// today are the baseline and are not reported. Only the first
+/// `query.limit` hits are compared. Returns the id for
+/// [`remove_standing_query`]; standing queries live for the process and
+/// should be registered again after a restart.
+pub fn add_standing_query(name: impl Into<String>, query: SearchQuery) -> Result<u64> {
+    let last_hits = crate::search(query.clone())?;
+    let id = NEXT_STANDING_ID.fetch_add(1, Ordering::Relaxed);
+    STANDING.lock().unwrap().push(StandingQuery {
+        id,
+        name: name.into(),
+        query,
+        last_hits,
+    });
+    Ok(id)
+}
+
+/// Returns false when no standing query has that id.
+pub fn remove_standing_query(id: u64) -> bool {
+    let mut standing = STANDING.lock().unwrap();
+    let before = standing.len();
+    standing.retain(|entry| entry.id != id);
+    standing.len() != before
+}
+
+/// Ids and names of the registered standing queries, oldest first.
+pub fn standing_queries() -> Vec<(u64, String)> {
+    STANDING
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|entry| (entry.id, entry.name.clone()))
+        .collect()
+}
+
+/// Re-run every standing query against the freshly reloaded reader.
+pub(crate) fn check_standing_queries() {
+    let mut matched = Vec::new();
+    {
+        let mut standing = STANDING.lock().unwrap();
+        for entry in standing.iter_mut() {
+            let hits = match crate::search(entry.query.clone()) {
+                Ok(hits) => hits,
+                Err(err) => {
+                    eprintln!("[standing] query {:?} failed: {err:#}", entry.name);
+                    continue;
+                }
+            };
+            let added = diff_hits(&entry.last_hits, &hits).added;
+            entry.last_hits = hits;
+            if !added.is_empty() {
+                matched.push(IndexEvent::StandingQueryMatched {
+                    id: entry.id,
+                    name: entry.name.clone(),
+                    paths: added.into_iter().map(|hit| hit.path).collect(),
+                });
+            }
+        }
+    }
+    // Emitted after unlocking so observers may add or remove standing queries.
+    for event in matched {
+        events::emit(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{add_standing_query, remove_standing_query, standing_queries};
+    use crate::events::{add_index_observer, remove_index_observer, IndexEvent, IndexObserver};
+    use crate::query::{SearchDomain, SearchQuery};
+    use crate::scanner::FileMeta;
+    use crate::{add_or_update_file, commit, init_index_in_memory};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Matches(Mutex<Vec<(u64, Vec<String>)>>);
+
+    impl IndexObserver for Matches {
+        fn on_event(&self, event: &IndexEvent) {
+            if let IndexEvent::StandingQueryMatched { id, paths, .. } = event {
+                self.0.lock().unwrap().push((*id, paths.clone()));
+            }
+        }
+    }
+
+    fn add(path: &str, inode: u64, content: &str) {
+        let meta = FileMeta {
+            path: path.into(),
+            name: path.rsplit('/').next().unwrap().into(),
+            inode,
+            dev: 1,
+            ..FileMeta::default()
+        };
+        add_or_update_file(meta, Some(content.into()), false).unwrap();
+    }
+
+    #[test]
+    fn announces_only_new_matches_after_commits() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        init_index_in_memory().unwrap();
+        add("/legal/old.txt", 1, "contract 4189 draft");
+        commit().unwrap();
+
+        let matches = Arc::new(Matches::default());
+        let observer = add_index_observer(matches.clone());
+        let query = SearchQuery {
+            term: "\"contract 4189\"".into(),
+            search_in: SearchDomain::Content,
+            ..SearchQuery::default()
+        };
+        let id = add_standing_query("contract 4189", query).unwrap();
+        assert!(standing_queries().contains(&(id, "contract 4189".to_string())));
+
+        add("/legal/signed.txt", 2, "signed contract 4189");
+        add("/legal/other.txt", 3, "contract 77");
+        commit().unwrap();
+        commit().unwrap();
+
+        assert!(remove_standing_query(id));
+        add("/legal/late.txt", 4, "contract 4189 amendment");
+        commit().unwrap();
+        remove_index_observer(observer);
+
+        assert_eq!(
+            *matches.0.lock().unwrap(),
+            vec![(id, vec!["/legal/signed.txt".to_string()])]
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static DOCS_ADDED: AtomicU64 = AtomicU64::new(0);
+static SEARCHES: AtomicU64 = AtomicU64::new(0);
+static UNCHANGED_SKIPS: AtomicU64 = AtomicU64::new(0);
+static READER_RELOADS: AtomicU64 = AtomicU64::new(0);
+
+/// Activity since the index was opened. Plain counters, so a status bar
+/// can poll every second without taking any index lock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct QuickStats {
+    /// Documents written, new files and updates alike; visible to searches
+    /// after the next commit.
+    pub docs_added: u64,
+    /// Searches that returned hits to a caller, prefix lookups included.
+    pub searches: u64,
+    /// Files the known-file cache showed unchanged, so they were skipped
+    /// without being read or written.
+    pub unchanged_skips: u64,
+    /// Times the reader was reloaded to see new commits.
+    pub reader_reloads: u64,
+}
+
+pub fn quick_stats() -> QuickStats {
+    QuickStats {
+        docs_added: DOCS_ADDED.load(Ordering::Relaxed),
+        searches: SEARCHES.load(Ordering::Relaxed),
+        unchanged_skips: UNCHANGED_SKIPS.load(Ordering::Relaxed),
+        reader_reloads: READER_RELOADS.load(Ordering::Relaxed),
+    }
+}
+
+/// Start counting afresh for a newly opened index.
+pub(crate) fn reset() {
+    for counter in [&DOCS_ADDED, &SEARCHES, &UNCHANGED_SKIPS, &READER_RELOADS] {
+        counter.store(0, Ordering::Relaxed);
+    }
+}
+
+pub(crate) fn record_docs_added(count: u64) {
+    DOCS_ADDED.fetch_add(count, Ordering::Relaxed);
+}
+
+pub(crate) fn record_search() {
+    SEARCHES.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn record_unchanged_skip() {
+    UNCHANGED_SKIPS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn record_reader_reload() {
+    READER_RELOADS.fetch_add(1, Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::quick_stats;
+    use crate::{add_or_update_file, commit, init_index_in_memory, search, SearchQuery};
+    use crate::{scanner::FileMeta, SearchDomain};
+
+    #[test]
+    fn counts_writes_skips_searches_and_reloads() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        init_index_in_memory().unwrap();
+        assert_eq!(quick_stats(), Default::default());
+
+        let meta = FileMeta {
+            path: "/notes/plan.txt".into(),
+            name: "plan.txt".into(),
+            ext: Some("txt".into()),
+            modified_at: 100,
+            size: 42,
+            inode: 1,
+            ..FileMeta::default()
+        };
+        add_or_update_file(meta.clone(), None, false).unwrap();
+        add_or_update_file(meta, None, false).unwrap();
+        commit().unwrap();
+        search(SearchQuery {
+            term: "plan".into(),
+            search_in: SearchDomain::Name,
+            ..SearchQuery::default()
+        })
+        .unwrap();
+
+        let stats = quick_stats();
+        assert_eq!(stats.docs_added, 1);
+        assert_eq!(stats.unchanged_skips, 1);
+        assert_eq!(stats.searches, 1);
+        assert_eq!(stats.reader_reloads, 1);
+    }
+}
@@ -0,0 +1,123 @@
+use crate::query;
+use crate::textnorm::fold_for_search;
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use std::fs;
+use std::path::Path;
+use std::sync::RwLock;
+
+/// Name of the file, inside the index directory, holding the stop words
+/// the index was created with, one per line.
+pub const STOP_WORDS_FILE_NAME: &str = ".finder-stop-words";
+
+static STOP_WORDS: Lazy<RwLock<Vec<String>>> = Lazy::new(|| RwLock::new(Vec::new()));
+
+/// Leave `words` (e.g. "the", or boilerplate every document repeats) out of
+/// content in indexes created from now on, and out of content queries
+/// against them, so they stop dominating scores. Matched after folding, so
+/// `The` and `the` are one word. An existing index keeps the list it was
+/// created with; rebuild it to switch. An empty list keeps every word.
+pub fn set_stop_words<I, S>(words: I)
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut folded: Vec<String> = words
+        .into_iter()
+        .map(|word| fold_for_search(word.as_ref().trim()))
+        .filter(|word| !word.is_empty())
+        .collect();
+    folded.sort();
+    folded.dedup();
+    *STOP_WORDS.write().unwrap() = folded;
+    query::clear_result_cache();
+}
+
+pub(crate) fn configured_stop_words() -> Vec<String> {
+    STOP_WORDS.read().unwrap().clone()
+}
+
+/// The list an index in `dir` was created with.
+pub(crate) fn load_stop_words(dir: &Path) -> Result<Vec<String>> {
+    let path = dir.join(STOP_WORDS_FILE_NAME);
+    let raw = fs::read_to_string(&path)
+        .with_context(|| format!("failed reading stop words from {}", path.display()))?;
+    Ok(raw
+        .lines()
+        .map(str::trim)
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+pub(crate) fn save_stop_words(dir: &Path, words: &[String]) -> Result<()> {
+    let path = dir.join(STOP_WORDS_FILE_NAME);
+    let mut raw = words.join("\n");
+    raw.push('\n');
+    fs::write(&path, raw).with_context(|| format!("failed writing {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{set_stop_words, STOP_WORDS_FILE_NAME};
+    use crate::query::{search, SearchDomain, SearchQuery};
+    use crate::{add_or_update_file, commit, init_index, FileMeta};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn stop_words_are_left_out_of_content_and_kept_by_the_index() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        set_stop_words(["The", "ACME", " "]);
+        let opened = init_index(path);
+        set_stop_words(Vec::<String>::new());
+        opened.unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join(STOP_WORDS_FILE_NAME)).unwrap(),
+            "acme\nthe\n"
+        );
+
+        let meta = |name: &str, inode| FileMeta {
+            path: format!("/notes/{name}"),
+            name: name.into(),
+            ext: Some("txt".into()),
+            modified_at: 1,
+            size: 1,
+            inode,
+            dev: 1,
+            alias_target: None,
+            bundle: false,
+            display_name: None,
+            children: None,
+        };
+        let content = "The budget, by Acme";
+        add_or_update_file(meta("a.txt", 1), Some(content.into()), false).unwrap();
+        commit().unwrap();
+        let hits = |term: &str| {
+            search(SearchQuery {
+                term: term.into(),
+                search_in: SearchDomain::Content,
+                ..SearchQuery::default()
+            })
+            .unwrap()
+            .len()
+        };
+        assert_eq!(hits("the"), 0);
+        assert_eq!(hits("the budget"), 1);
+        assert_eq!(hits("\"budget by\""), 1);
+
+        // The index keeps its list after the configured one changes.
+        crate::close_index();
+        init_index(path).unwrap();
+        assert_eq!(hits("acme"), 0);
+
+        let plain = tempdir().unwrap();
+        init_index(plain.path().to_str().unwrap()).unwrap();
+        assert!(!plain.path().join(STOP_WORDS_FILE_NAME).exists());
+        add_or_update_file(meta("a.txt", 1), Some(content.into()), false).unwrap();
+        commit().unwrap();
+        assert_eq!(hits("acme"), 1);
+    }
+}
@@ -0,0 +1,108 @@
+use crate::query;
+use crate::scanner::FileMeta;
+use once_cell::sync::Lazy;
+use std::sync::{Arc, RwLock};
+
+/// Only this much leading content is handed to the summarizer; summaries
+/// describe the start of a document, not all of it.
+pub const SUMMARY_SOURCE_BYTES: usize = 4 * 1024;
+const DEFAULT_SUMMARY_SENTENCES: usize = 2;
+const DEFAULT_SUMMARY_MAX_CHARS: usize = 240;
+
+/// Produces the short description stored alongside each document and
+/// returned with search hits.
+pub trait Summarizer: Send + Sync {
+    /// `lead` is the beginning of the extracted content (at most
+    /// [`SUMMARY_SOURCE_BYTES`]). Return `None` to store no summary.
+    fn summarize(&self, meta: &FileMeta, lead: &str) -> Option<String>;
+}
+
+/// Built-in summarizer that keeps the first few sentences of the document.
+#[derive(Debug, Clone, Copy)]
+pub struct LeadSentences {
+    pub sentences: usize,
+    pub max_chars: usize,
+}
+
+impl Default for LeadSentences {
+    fn default() -> Self {
+        Self {
+            sentences: DEFAULT_SUMMARY_SENTENCES,
+            max_chars: DEFAULT_SUMMARY_MAX_CHARS,
+        }
+    }
+}
+
+impl Summarizer for LeadSentences {
+    fn summarize(&self, _meta: &FileMeta, lead: &str) -> Option<String> {
+        let words: Vec<&str> = lead.split_whitespace().collect();
+        if words.is_empty() || self.sentences == 0 {
+            return None;
+        }
+
+        let mut summary = String::new();
+        let mut sentences = 0;
+        for word in words {
+            if summary.chars().count() + word.chars().count() + 1 > self.max_chars {
+                summary.push('…');
+                break;
+            }
+            if !summary.is_empty() {
+                summary.push(' ');
+            }
+            summary.push_str(word);
+            if word.ends_with(['.', '!', '?']) {
+                sentences += 1;
+                if sentences >= self.sentences {
+                    break;
+                }
+            }
+        }
+        Some(summary)
+    }
+}
+
+static SUMMARIZER: Lazy<RwLock<Option<Arc<dyn Summarizer>>>> = Lazy::new(|| RwLock::new(None));
+
+/// Install the summarizer used for documents indexed from now on, or `None`
+/// to stop storing summaries.
+pub fn set_summarizer(summarizer: Option<Arc<dyn Summarizer>>) {
+    let mut guard = SUMMARIZER.write().unwrap();
+    *guard = summarizer;
+    drop(guard);
+    query::clear_result_cache();
+}
+
+pub(crate) fn current_summarizer() -> Option<Arc<dyn Summarizer>> {
+    SUMMARIZER.read().unwrap().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LeadSentences, Summarizer};
+    use crate::scanner::FileMeta;
+
+    #[test]
+    fn keeps_leading_sentences() {
+        let summary = LeadSentences::default()
+            .summarize(
+                &FileMeta::default(),
+                "Quarterly report.\n\nRevenue grew   12%! Costs were flat. Appendix follows.",
+            )
+            .unwrap();
+        assert_eq!(summary, "Quarterly report. Revenue grew 12%!");
+    }
+
+    #[test]
+    fn truncates_to_max_chars() {
+        let summarizer = LeadSentences {
+            sentences: 3,
+            max_chars: 12,
+        };
+        let summary = summarizer
+            .summarize(&FileMeta::default(), "one two three four five")
+            .unwrap();
+        assert_eq!(summary, "one two…");
+        assert!(summarizer.summarize(&FileMeta::default(), "   ").is_none());
+    }
+}
@@ -0,0 +1,226 @@
+use crate::indexer;
+use crate::query;
+use crate::textnorm::fold_for_search;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use tantivy::query::Occur;
+use tantivy::query_grammar::{Delimiter, UserInputAst, UserInputLeaf, UserInputLiteral};
+
+/// Name of the file, inside the index directory, holding the synonym
+/// groups: one group per line, its words or phrases separated by commas,
+/// e.g. `jpg, jpeg`. Lines starting with `#` are comments.
+pub const SYNONYMS_FILE_NAME: &str = ".finder-synonyms";
+
+/// Make every word or phrase of each group find the others, so `jpg` also
+/// matches `jpeg` and `resume` matches `cv`. Replaces the groups of the
+/// open index and saves them next to it; an index opened later reads them
+/// back. Groups with fewer than two entries are dropped.
+pub fn set_synonyms<G, W, S>(groups: G) -> Result<()>
+where
+    G: IntoIterator<Item = W>,
+    W: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let groups = groups
+        .into_iter()
+        .filter_map(|group| {
+            normalize_group(group.into_iter().map(|word| word.as_ref().to_string()))
+        })
+        .collect();
+    indexer::synonyms()?.replace(groups)
+}
+
+/// The synonym groups of the open index, folded as they are matched.
+pub fn synonym_groups() -> Result<Vec<Vec<String>>> {
+    Ok(indexer::synonyms()?.groups.read().unwrap().clone())
+}
+
+/// Synonym groups of one index, loaded from and saved to its directory;
+/// in-memory indexes keep them for the process lifetime.
+pub(crate) struct Synonyms {
+    path: Option<PathBuf>,
+    groups: RwLock<Vec<Vec<String>>>,
+}
+
+impl Synonyms {
+    pub(crate) fn open(dir: Option<&Path>) -> Self {
+        let path = dir.map(|dir| dir.join(SYNONYMS_FILE_NAME));
+        let groups = path
+            .as_deref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|raw| parse_synonyms(&raw))
+            .unwrap_or_default();
+        Self {
+            path,
+            groups: RwLock::new(groups),
+        }
+    }
+
+    fn replace(&self, groups: Vec<Vec<String>>) -> Result<()> {
+        let mut guard = self.groups.write().unwrap();
+        if let Some(path) = &self.path {
+            let mut raw: String = groups
+                .iter()
+                .map(|group| format!("{}\n", group.join(", ")))
+                .collect();
+            if raw.is_empty() {
+                raw.push('\n');
+            }
+            fs::write(path, raw).with_context(|| format!("failed writing {}", path.display()))?;
+        }
+        *guard = groups;
+        query::clear_result_cache();
+        Ok(())
+    }
+
+    /// Replace each plain or `ext:` word that has synonyms with a group of
+    /// alternatives, e.g. `jpg` with `(jpg OR jpeg)`. Quoted text and other
+    /// fields are searched as typed.
+    pub(crate) fn expand(&self, ast: UserInputAst) -> UserInputAst {
+        let groups = self.groups.read().unwrap();
+        if groups.is_empty() {
+            return ast;
+        }
+        expand_ast(ast, &groups)
+    }
+}
+
+fn expand_ast(ast: UserInputAst, groups: &[Vec<String>]) -> UserInputAst {
+    match ast {
+        UserInputAst::Clause(children) => UserInputAst::Clause(
+            children
+                .into_iter()
+                .map(|(occur, child)| (occur, expand_ast(child, groups)))
+                .collect(),
+        ),
+        UserInputAst::Boost(inner, boost) => {
+            UserInputAst::Boost(Box::new(expand_ast(*inner, groups)), boost)
+        }
+        UserInputAst::Leaf(leaf) => match *leaf {
+            UserInputLeaf::Literal(literal)
+                if literal.delimiter == Delimiter::None
+                    && matches!(literal.field_name.as_deref(), None | Some("ext")) =>
+            {
+                let folded = fold_for_search(&literal.phrase);
+                let Some(group) = groups.iter().find(|group| group.contains(&folded)) else {
+                    return UserInputAst::Leaf(Box::new(UserInputLeaf::Literal(literal)));
+                };
+                let alternatives = group.iter().filter(|word| **word != folded).map(|word| {
+                    // Phrases still need their words together.
+                    let delimiter = if word.contains(' ') {
+                        Delimiter::DoubleQuotes
+                    } else {
+                        Delimiter::None
+                    };
+                    UserInputLiteral {
+                        phrase: word.clone(),
+                        delimiter,
+                        ..literal.clone()
+                    }
+                });
+                UserInputAst::Clause(
+                    std::iter::once(literal.clone())
+                        .chain(alternatives)
+                        .map(|literal| {
+                            let leaf = UserInputLeaf::Literal(literal);
+                            (Some(Occur::Should), UserInputAst::Leaf(Box::new(leaf)))
+                        })
+                        .collect(),
+                )
+            }
+            other => UserInputAst::Leaf(Box::new(other)),
+        },
+    }
+}
+
+fn normalize_group(words: impl Iterator<Item = String>) -> Option<Vec<String>> {
+    let mut group: Vec<String> = Vec::new();
+    for word in words {
+        let folded = fold_for_search(word.trim());
+        let folded = folded.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !folded.is_empty() && !group.contains(&folded) {
+            group.push(folded);
+        }
+    }
+    (group.len() > 1).then_some(group)
+}
+
+fn parse_synonyms(raw: &str) -> Vec<Vec<String>> {
+    raw.lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| normalize_group(line.split(',').map(str::to_string)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{set_synonyms, synonym_groups, SYNONYMS_FILE_NAME};
+    use crate::query::{SearchDomain, SearchQuery};
+    use crate::scanner::FileMeta;
+    use crate::{add_or_update_file, commit, init_index, search};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn synonyms_expand_words_and_are_kept_with_the_index() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        fs::write(
+            dir.path().join(SYNONYMS_FILE_NAME),
+            "# formats\njpg, JPEG\nlonely\n",
+        )
+        .unwrap();
+        init_index(path).unwrap();
+        assert_eq!(synonym_groups().unwrap(), vec![vec!["jpg", "jpeg"]]);
+
+        let meta = |name: &str, ext: &str, inode| FileMeta {
+            path: format!("/files/{name}"),
+            name: name.into(),
+            ext: Some(ext.into()),
+            modified_at: 1,
+            size: 1,
+            inode,
+            dev: 1,
+            alias_target: None,
+            bundle: false,
+            display_name: None,
+            children: None,
+        };
+        add_or_update_file(meta("beach.jpeg", "jpeg", 1), None, false).unwrap();
+        add_or_update_file(meta("my cv.txt", "txt", 2), None, false).unwrap();
+        add_or_update_file(meta("curriculum vitae.txt", "txt", 3), None, false).unwrap();
+        commit().unwrap();
+        let hits = |term: &str| {
+            let mut names: Vec<String> = search(SearchQuery {
+                term: term.into(),
+                search_in: SearchDomain::Name,
+                ..SearchQuery::default()
+            })
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.name)
+            .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(hits("beach jpg"), ["beach.jpeg"]);
+        assert_eq!(hits("ext:JPG"), ["beach.jpeg"]);
+        assert_eq!(hits("\"jpg\""), Vec::<String>::new());
+        assert!(hits("resume").is_empty());
+
+        set_synonyms([vec!["Resume", "cv", "curriculum vitae"]]).unwrap();
+        assert_eq!(hits("resume"), ["curriculum vitae.txt", "my cv.txt"]);
+        assert_eq!(hits("txt -resume"), Vec::<String>::new());
+        assert_eq!(hits("jpg"), Vec::<String>::new());
+
+        crate::close_index();
+        init_index(path).unwrap();
+        assert_eq!(
+            synonym_groups().unwrap(),
+            vec![vec!["resume", "cv", "curriculum vitae"]]
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use crate::dates::{date_bounds, date_locale, DateLocale};
+use crate::report::now;
+use crate::sizes::size_bounds;
+use anyhow::Result;
+
+const MODIFIED: &str = "modified:";
+const SIZE: &str = "size:";
+
+/// A query term with its `modified:` and `size:` filters taken out.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct TermFilters {
+    /// The rest of the term, for the query parser.
+    pub term: String,
+    /// Inclusive unix-second bounds every `modified:` filter agrees on.
+    pub mtime_min: Option<i64>,
+    pub mtime_max: Option<i64>,
+    /// Inclusive byte bounds every `size:` filter agrees on.
+    pub size_min: Option<u64>,
+    pub size_max: Option<u64>,
+}
+
+impl TermFilters {
+    /// `term` searched as it is, for modes that don't read filters.
+    pub fn plain(term: String) -> Self {
+        Self {
+            term,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mtime_min.is_none()
+            && self.mtime_max.is_none()
+            && self.size_min.is_none()
+            && self.size_max.is_none()
+    }
+}
+
+/// Take `modified:` and `size:` filters out of `term`, dates resolved
+/// against the current time and [`set_date_locale`](crate::set_date_locale).
+/// Several filters narrow each other.
+pub(crate) fn extract_term_filters(term: &str) -> Result<TermFilters> {
+    extract_at(term, now(), &date_locale())
+}
+
+pub(crate) fn extract_at(term: &str, now: i64, locale: &DateLocale) -> Result<TermFilters> {
+    let mut filters = TermFilters::default();
+    if find_key(term).is_none() {
+        filters.term = term.to_string();
+        return Ok(filters);
+    }
+    let mut rest = term;
+    while let Some((at, key)) = find_key(rest) {
+        filters.term.push_str(&rest[..at]);
+        let after = &rest[at + key.len()..];
+        let (op, after) = split_operator(after);
+        let (value, after) = split_value(after);
+        if key == MODIFIED {
+            let (min, max) = date_bounds(op, &value, now, locale)?;
+            filters.mtime_min = narrow(filters.mtime_min, min, Ord::max);
+            filters.mtime_max = narrow(filters.mtime_max, max, Ord::min);
+        } else {
+            let (min, max) = size_bounds(op, &value)?;
+            filters.size_min = narrow(filters.size_min, min, Ord::max);
+            filters.size_max = narrow(filters.size_max, max, Ord::min);
+        }
+        rest = after;
+    }
+    filters.term.push_str(rest);
+    filters.term = filters
+        .term
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    Ok(filters)
+}
+
+/// `known` tightened by `bound`, when there is one.
+fn narrow<T: Ord>(known: Option<T>, bound: Option<T>, pick: fn(T, T) -> T) -> Option<T> {
+    match (known, bound) {
+        (Some(known), Some(bound)) => Some(pick(known, bound)),
+        (known, bound) => known.or(bound),
+    }
+}
+
+/// Byte offset of the first filter key starting a word of `text`, outside
+/// quotes, and which key it is.
+fn find_key(text: &str) -> Option<(usize, &'static str)> {
+    let mut quoted = false;
+    let mut word_start = true;
+    for (at, ch) in text.char_indices() {
+        if ch == '"' {
+            quoted = !quoted;
+        } else if !quoted && word_start {
+            let found = [MODIFIED, SIZE].into_iter().find(|key| {
+                text[at..]
+                    .get(..key.len())
+                    .is_some_and(|head| head.eq_ignore_ascii_case(key))
+            });
+            if let Some(key) = found {
+                return Some((at, key));
+            }
+        }
+        word_start = ch.is_whitespace() || ch == '(' || (ch == '+' && word_start);
+    }
+    None
+}
+
+fn split_operator(text: &str) -> (&str, &str) {
+    for op in [">=", "<=", ">", "<", "="] {
+        if let Some(rest) = text.strip_prefix(op) {
+            return (op, rest);
+        }
+    }
+    ("", text)
+}
+
+/// A quoted value (straight or typographic quotes) or the next run of
+/// non-space characters.
+fn split_value(text: &str) -> (String, &str) {
+    for (open, close) in [('"', '"'), ('\u{201c}', '\u{201d}'), ('\'', '\'')] {
+        if let Some(inner) = text.strip_prefix(open) {
+            return match inner.find(close) {
+                Some(end) => (inner[..end].to_string(), &inner[end + close.len_utf8()..]),
+                None => (inner.to_string(), ""),
+            };
+        }
+    }
+    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+    (text[..end].to_string(), &text[end..])
+}
@@ -0,0 +1,702 @@
+use crate::schema::ContentAnalyzer;
+use std::ops::Range;
+use tantivy::tokenizer::{
+    Language, Stemmer, StopWordFilter, TextAnalyzer, Token, TokenStream, Tokenizer,
+};
+
+/// Tokens this long or longer (in bytes) are dropped, as tantivy's default
+/// analyzer does; they are hashes and base64, not words.
+const MAX_TOKEN_BYTES: usize = 40;
+
+/// Compose `text` to Unicode NFC, so a name written decomposed (as macOS
+/// file systems hand them out) reads the same as one typed on a keyboard.
+pub fn to_nfc(text: &str) -> String {
+    compose(text, false).into_iter().map(|(_, ch)| ch).collect()
+}
+
+/// The form names, contents and queries are matched in: fullwidth and
+/// halfwidth characters folded to their usual width, composed to NFC and
+/// case-folded. `ＲＥＰＯＲＴ`, `Report` and `report` all fold to `report`.
+pub fn fold_for_search(text: &str) -> String {
+    fold_with_offsets(text).0
+}
+
+/// [`fold_for_search`], plus for each byte of the folded text (and one past
+/// its end) the byte offset in `text` it came from.
+pub(crate) fn fold_with_offsets(text: &str) -> (String, Vec<usize>) {
+    let mut folded = String::with_capacity(text.len());
+    let mut origins = Vec::with_capacity(text.len() + 1);
+    for (offset, ch) in compose(text, true) {
+        match ch {
+            'ß' | 'ẞ' => folded.push_str("ss"),
+            'ς' => folded.push('σ'),
+            ch => folded.extend(ch.to_lowercase()),
+        }
+        origins.resize(folded.len(), offset);
+    }
+    origins.push(text.len());
+    (folded, origins)
+}
+
+/// Characters of `text` after composition, each with the byte offset of the
+/// first character it was made from.
+fn compose(text: &str, fold_widths: bool) -> Vec<(usize, char)> {
+    let mut out: Vec<(usize, char)> = Vec::with_capacity(text.len());
+    for (offset, ch) in text.char_indices() {
+        let ch = if fold_widths { fold_width(ch) } else { ch };
+        if let Some(last) = out.last_mut() {
+            if let Some(composed) = compose_pair(last.1, ch) {
+                last.1 = composed;
+                continue;
+            }
+        }
+        out.push((offset, ch));
+    }
+    out
+}
+
+fn compose_pair(first: char, second: char) -> Option<char> {
+    const S_BASE: u32 = 0xAC00;
+    const L_BASE: u32 = 0x1100;
+    const V_BASE: u32 = 0x1161;
+    const T_BASE: u32 = 0x11A7;
+    const V_COUNT: u32 = 21;
+    const T_COUNT: u32 = 28;
+    const S_COUNT: u32 = 11_172;
+    let (a, b) = (first as u32, second as u32);
+    if (L_BASE..L_BASE + 19).contains(&a) && (V_BASE..V_BASE + V_COUNT).contains(&b) {
+        let syllable = S_BASE + ((a - L_BASE) * V_COUNT + (b - V_BASE)) * T_COUNT;
+        return char::from_u32(syllable);
+    }
+    if (S_BASE..S_BASE + S_COUNT).contains(&a)
+        && (a - S_BASE).is_multiple_of(T_COUNT)
+        && (T_BASE + 1..T_BASE + T_COUNT).contains(&b)
+    {
+        return char::from_u32(a + (b - T_BASE));
+    }
+    COMPOSITIONS
+        .binary_search_by(|(x, y, _)| (*x, *y).cmp(&(first, second)))
+        .ok()
+        .map(|i| COMPOSITIONS[i].2)
+}
+
+fn fold_width(ch: char) -> char {
+    match ch {
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(ch as u32 - 0xFEE0).unwrap_or(ch),
+        '\u{3000}' => ' ',
+        '\u{FF61}'..='\u{FF9F}' => HALFWIDTH_KATAKANA[(ch as u32 - 0xFF61) as usize],
+        '\u{FFE0}' => '¢',
+        '\u{FFE1}' => '£',
+        '\u{FFE2}' => '¬',
+        '\u{FFE4}' => '¦',
+        '\u{FFE5}' => '¥',
+        '\u{FFE6}' => '₩',
+        _ => ch,
+    }
+}
+
+/// Splits folded text into runs of letters and digits, like tantivy's
+/// simple tokenizer.
+/// Installed as the index's `default` tokenizer, so every `TEXT` field and
+/// every parsed query goes through [`fold_for_search`]. Offsets point into
+/// the original text, for snippets.
+#[derive(Clone, Default)]
+pub(crate) struct FoldingTokenizer;
+
+pub(crate) struct FoldedTokens {
+    tokens: Vec<Token>,
+    next: usize,
+}
+
+pub(crate) fn folding_analyzer() -> TextAnalyzer {
+    TextAnalyzer::from(FoldingTokenizer)
+}
+
+/// [`folding_analyzer`] for the `content` field: `stop_words` (already
+/// folded) left out, then the rest reduced to their stems under `analyzer`.
+pub(crate) fn content_analyzer(analyzer: ContentAnalyzer, stop_words: &[String]) -> TextAnalyzer {
+    let mut builder = TextAnalyzer::builder(FoldingTokenizer).dynamic();
+    if !stop_words.is_empty() {
+        builder = builder.filter_dynamic(StopWordFilter::remove(stop_words.to_vec()));
+    }
+    if analyzer == ContentAnalyzer::English {
+        builder = builder.filter_dynamic(Stemmer::new(Language::English));
+    }
+    builder.build()
+}
+
+impl Tokenizer for FoldingTokenizer {
+    type TokenStream<'a> = FoldedTokens;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> FoldedTokens {
+        let (folded, origins) = fold_with_offsets(text);
+        let mut tokens = Vec::new();
+        let mut position = 0;
+        let mut start = None;
+        let ends = folded
+            .char_indices()
+            .map(|(offset, ch)| (offset, ch.is_alphanumeric()))
+            .chain(std::iter::once((folded.len(), false)));
+        for (offset, in_word) in ends {
+            match (start, in_word) {
+                (None, true) => start = Some(offset),
+                (Some(from), false) => {
+                    start = None;
+                    if offset - from < MAX_TOKEN_BYTES {
+                        tokens.push(Token {
+                            offset_from: origins[from],
+                            offset_to: origins[offset],
+                            position,
+                            text: folded[from..offset].to_string(),
+                            position_length: 1,
+                        });
+                    }
+                    position += 1;
+                }
+                _ => {}
+            }
+        }
+        FoldedTokens { tokens, next: 0 }
+    }
+}
+
+/// Splits the words of identifiers in file names: `MyInvoiceParser_v2.ts`
+/// gives `my invoice parser v 2 ts`, at successive positions so phrases
+/// over the parts work. Installed on the `name_parts` field, next to
+/// `name`, which keeps each whole word for queries typed that way.
+#[derive(Clone, Default)]
+pub(crate) struct IdentifierTokenizer;
+
+pub(crate) fn identifier_analyzer() -> TextAnalyzer {
+    TextAnalyzer::from(IdentifierTokenizer)
+}
+
+impl Tokenizer for IdentifierTokenizer {
+    type TokenStream<'a> = FoldedTokens;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> FoldedTokens {
+        let mut tokens = Vec::new();
+        let mut position = 0;
+        for (start, word) in words_with_offsets(text) {
+            if word.len() >= MAX_TOKEN_BYTES {
+                position += 1;
+                continue;
+            }
+            for part in identifier_parts(word) {
+                tokens.push(Token {
+                    offset_from: start + part.start,
+                    offset_to: start + part.end,
+                    position,
+                    text: fold_for_search(&word[part]),
+                    position_length: 1,
+                });
+                position += 1;
+            }
+        }
+        FoldedTokens { tokens, next: 0 }
+    }
+}
+
+/// Folded parts of each identifier-like word of `text`, for typeahead;
+/// empty for plain words.
+pub(crate) fn identifier_words(text: &str) -> Vec<String> {
+    words_with_offsets(text)
+        .filter(|(_, word)| word.len() < MAX_TOKEN_BYTES)
+        .flat_map(|(_, word)| {
+            let parts = identifier_parts(word);
+            let split = parts.len() > 1;
+            parts
+                .into_iter()
+                .filter(move |_| split)
+                .map(move |part| fold_for_search(&word[part]))
+        })
+        .collect()
+}
+
+/// Runs of letters and digits in `text`, with their byte offsets.
+fn words_with_offsets(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split(|ch: char| !ch.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(move |word| (word.as_ptr() as usize - text.as_ptr() as usize, word))
+}
+
+/// Byte ranges of the parts of `word`, split where lower case turns upper
+/// (`myInvoice`), before the last capital of an acronym (`XMLParser`), and
+/// between letters and digits (`v2`).
+fn identifier_parts(word: &str) -> Vec<Range<usize>> {
+    let chars: Vec<(usize, char)> = word.char_indices().collect();
+    let mut parts = Vec::new();
+    let mut start = 0;
+    for i in 1..chars.len() {
+        let (prev, (at, ch)) = (chars[i - 1].1, chars[i]);
+        let next_lower = chars
+            .get(i + 1)
+            .is_some_and(|(_, next)| next.is_lowercase());
+        let boundary = (prev.is_lowercase() && ch.is_uppercase())
+            || (prev.is_uppercase() && ch.is_uppercase() && next_lower)
+            || (prev.is_numeric() != ch.is_numeric());
+        if boundary {
+            parts.push(start..at);
+            start = at;
+        }
+    }
+    parts.push(start..word.len());
+    parts
+}
+
+impl TokenStream for FoldedTokens {
+    fn advance(&mut self) -> bool {
+        self.next += 1;
+        self.next <= self.tokens.len()
+    }
+
+    fn token(&self) -> &Token {
+        &self.tokens[self.next - 1]
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.tokens[self.next - 1]
+    }
+}
+
+/// Canonical compositions `(first, second, composed)` from Unicode 14.0.0,
+/// sorted for binary search. Hangul syllables are composed arithmetically.
+#[rustfmt::skip]
+const COMPOSITIONS: [(char, char, char); 941] = [
+    ('\u{3C}', '\u{338}', '\u{226E}'), ('\u{3D}', '\u{338}', '\u{2260}'), ('\u{3E}', '\u{338}', '\u{226F}'),
+    ('\u{41}', '\u{300}', '\u{C0}'), ('\u{41}', '\u{301}', '\u{C1}'), ('\u{41}', '\u{302}', '\u{C2}'),
+    ('\u{41}', '\u{303}', '\u{C3}'), ('\u{41}', '\u{304}', '\u{100}'), ('\u{41}', '\u{306}', '\u{102}'),
+    ('\u{41}', '\u{307}', '\u{226}'), ('\u{41}', '\u{308}', '\u{C4}'), ('\u{41}', '\u{309}', '\u{1EA2}'),
+    ('\u{41}', '\u{30A}', '\u{C5}'), ('\u{41}', '\u{30C}', '\u{1CD}'), ('\u{41}', '\u{30F}', '\u{200}'),
+    ('\u{41}', '\u{311}', '\u{202}'), ('\u{41}', '\u{323}', '\u{1EA0}'), ('\u{41}', '\u{325}', '\u{1E00}'),
+    ('\u{41}', '\u{328}', '\u{104}'), ('\u{42}', '\u{307}', '\u{1E02}'), ('\u{42}', '\u{323}', '\u{1E04}'),
+    ('\u{42}', '\u{331}', '\u{1E06}'), ('\u{43}', '\u{301}', '\u{106}'), ('\u{43}', '\u{302}', '\u{108}'),
+    ('\u{43}', '\u{307}', '\u{10A}'), ('\u{43}', '\u{30C}', '\u{10C}'), ('\u{43}', '\u{327}', '\u{C7}'),
+    ('\u{44}', '\u{307}', '\u{1E0A}'), ('\u{44}', '\u{30C}', '\u{10E}'), ('\u{44}', '\u{323}', '\u{1E0C}'),
+    ('\u{44}', '\u{327}', '\u{1E10}'), ('\u{44}', '\u{32D}', '\u{1E12}'), ('\u{44}', '\u{331}', '\u{1E0E}'),
+    ('\u{45}', '\u{300}', '\u{C8}'), ('\u{45}', '\u{301}', '\u{C9}'), ('\u{45}', '\u{302}', '\u{CA}'),
+    ('\u{45}', '\u{303}', '\u{1EBC}'), ('\u{45}', '\u{304}', '\u{112}'), ('\u{45}', '\u{306}', '\u{114}'),
+    ('\u{45}', '\u{307}', '\u{116}'), ('\u{45}', '\u{308}', '\u{CB}'), ('\u{45}', '\u{309}', '\u{1EBA}'),
+    ('\u{45}', '\u{30C}', '\u{11A}'), ('\u{45}', '\u{30F}', '\u{204}'), ('\u{45}', '\u{311}', '\u{206}'),
+    ('\u{45}', '\u{323}', '\u{1EB8}'), ('\u{45}', '\u{327}', '\u{228}'), ('\u{45}', '\u{328}', '\u{118}'),
+    ('\u{45}', '\u{32D}', '\u{1E18}'), ('\u{45}', '\u{330}', '\u{1E1A}'), ('\u{46}', '\u{307}', '\u{1E1E}'),
+    ('\u{47}', '\u{301}', '\u{1F4}'), ('\u{47}', '\u{302}', '\u{11C}'), ('\u{47}', '\u{304}', '\u{1E20}'),
+    ('\u{47}', '\u{306}', '\u{11E}'), ('\u{47}', '\u{307}', '\u{120}'), ('\u{47}', '\u{30C}', '\u{1E6}'),
+    ('\u{47}', '\u{327}', '\u{122}'), ('\u{48}', '\u{302}', '\u{124}'), ('\u{48}', '\u{307}', '\u{1E22}'),
+    ('\u{48}', '\u{308}', '\u{1E26}'), ('\u{48}', '\u{30C}', '\u{21E}'), ('\u{48}', '\u{323}', '\u{1E24}'),
+    ('\u{48}', '\u{327}', '\u{1E28}'), ('\u{48}', '\u{32E}', '\u{1E2A}'), ('\u{49}', '\u{300}', '\u{CC}'),
+    ('\u{49}', '\u{301}', '\u{CD}'), ('\u{49}', '\u{302}', '\u{CE}'), ('\u{49}', '\u{303}', '\u{128}'),
+    ('\u{49}', '\u{304}', '\u{12A}'), ('\u{49}', '\u{306}', '\u{12C}'), ('\u{49}', '\u{307}', '\u{130}'),
+    ('\u{49}', '\u{308}', '\u{CF}'), ('\u{49}', '\u{309}', '\u{1EC8}'), ('\u{49}', '\u{30C}', '\u{1CF}'),
+    ('\u{49}', '\u{30F}', '\u{208}'), ('\u{49}', '\u{311}', '\u{20A}'), ('\u{49}', '\u{323}', '\u{1ECA}'),
+    ('\u{49}', '\u{328}', '\u{12E}'), ('\u{49}', '\u{330}', '\u{1E2C}'), ('\u{4A}', '\u{302}', '\u{134}'),
+    ('\u{4B}', '\u{301}', '\u{1E30}'), ('\u{4B}', '\u{30C}', '\u{1E8}'), ('\u{4B}', '\u{323}', '\u{1E32}'),
+    ('\u{4B}', '\u{327}', '\u{136}'), ('\u{4B}', '\u{331}', '\u{1E34}'), ('\u{4C}', '\u{301}', '\u{139}'),
+    ('\u{4C}', '\u{30C}', '\u{13D}'), ('\u{4C}', '\u{323}', '\u{1E36}'), ('\u{4C}', '\u{327}', '\u{13B}'),
+    ('\u{4C}', '\u{32D}', '\u{1E3C}'), ('\u{4C}', '\u{331}', '\u{1E3A}'), ('\u{4D}', '\u{301}', '\u{1E3E}'),
+    ('\u{4D}', '\u{307}', '\u{1E40}'), ('\u{4D}', '\u{323}', '\u{1E42}'), ('\u{4E}', '\u{300}', '\u{1F8}'),
+    ('\u{4E}', '\u{301}', '\u{143}'), ('\u{4E}', '\u{303}', '\u{D1}'), ('\u{4E}', '\u{307}', '\u{1E44}'),
+    ('\u{4E}', '\u{30C}', '\u{147}'), ('\u{4E}', '\u{323}', '\u{1E46}'), ('\u{4E}', '\u{327}', '\u{145}'),
+    ('\u{4E}', '\u{32D}', '\u{1E4A}'), ('\u{4E}', '\u{331}', '\u{1E48}'), ('\u{4F}', '\u{300}', '\u{D2}'),
+    ('\u{4F}', '\u{301}', '\u{D3}'), ('\u{4F}', '\u{302}', '\u{D4}'), ('\u{4F}', '\u{303}', '\u{D5}'),
+    ('\u{4F}', '\u{304}', '\u{14C}'), ('\u{4F}', '\u{306}', '\u{14E}'), ('\u{4F}', '\u{307}', '\u{22E}'),
+    ('\u{4F}', '\u{308}', '\u{D6}'), ('\u{4F}', '\u{309}', '\u{1ECE}'), ('\u{4F}', '\u{30B}', '\u{150}'),
+    ('\u{4F}', '\u{30C}', '\u{1D1}'), ('\u{4F}', '\u{30F}', '\u{20C}'), ('\u{4F}', '\u{311}', '\u{20E}'),
+    ('\u{4F}', '\u{31B}', '\u{1A0}'), ('\u{4F}', '\u{323}', '\u{1ECC}'), ('\u{4F}', '\u{328}', '\u{1EA}'),
+    ('\u{50}', '\u{301}', '\u{1E54}'), ('\u{50}', '\u{307}', '\u{1E56}'), ('\u{52}', '\u{301}', '\u{154}'),
+    ('\u{52}', '\u{307}', '\u{1E58}'), ('\u{52}', '\u{30C}', '\u{158}'), ('\u{52}', '\u{30F}', '\u{210}'),
+    ('\u{52}', '\u{311}', '\u{212}'), ('\u{52}', '\u{323}', '\u{1E5A}'), ('\u{52}', '\u{327}', '\u{156}'),
+    ('\u{52}', '\u{331}', '\u{1E5E}'), ('\u{53}', '\u{301}', '\u{15A}'), ('\u{53}', '\u{302}', '\u{15C}'),
+    ('\u{53}', '\u{307}', '\u{1E60}'), ('\u{53}', '\u{30C}', '\u{160}'), ('\u{53}', '\u{323}', '\u{1E62}'),
+    ('\u{53}', '\u{326}', '\u{218}'), ('\u{53}', '\u{327}', '\u{15E}'), ('\u{54}', '\u{307}', '\u{1E6A}'),
+    ('\u{54}', '\u{30C}', '\u{164}'), ('\u{54}', '\u{323}', '\u{1E6C}'), ('\u{54}', '\u{326}', '\u{21A}'),
+    ('\u{54}', '\u{327}', '\u{162}'), ('\u{54}', '\u{32D}', '\u{1E70}'), ('\u{54}', '\u{331}', '\u{1E6E}'),
+    ('\u{55}', '\u{300}', '\u{D9}'), ('\u{55}', '\u{301}', '\u{DA}'), ('\u{55}', '\u{302}', '\u{DB}'),
+    ('\u{55}', '\u{303}', '\u{168}'), ('\u{55}', '\u{304}', '\u{16A}'), ('\u{55}', '\u{306}', '\u{16C}'),
+    ('\u{55}', '\u{308}', '\u{DC}'), ('\u{55}', '\u{309}', '\u{1EE6}'), ('\u{55}', '\u{30A}', '\u{16E}'),
+    ('\u{55}', '\u{30B}', '\u{170}'), ('\u{55}', '\u{30C}', '\u{1D3}'), ('\u{55}', '\u{30F}', '\u{214}'),
+    ('\u{55}', '\u{311}', '\u{216}'), ('\u{55}', '\u{31B}', '\u{1AF}'), ('\u{55}', '\u{323}', '\u{1EE4}'),
+    ('\u{55}', '\u{324}', '\u{1E72}'), ('\u{55}', '\u{328}', '\u{172}'), ('\u{55}', '\u{32D}', '\u{1E76}'),
+    ('\u{55}', '\u{330}', '\u{1E74}'), ('\u{56}', '\u{303}', '\u{1E7C}'), ('\u{56}', '\u{323}', '\u{1E7E}'),
+    ('\u{57}', '\u{300}', '\u{1E80}'), ('\u{57}', '\u{301}', '\u{1E82}'), ('\u{57}', '\u{302}', '\u{174}'),
+    ('\u{57}', '\u{307}', '\u{1E86}'), ('\u{57}', '\u{308}', '\u{1E84}'), ('\u{57}', '\u{323}', '\u{1E88}'),
+    ('\u{58}', '\u{307}', '\u{1E8A}'), ('\u{58}', '\u{308}', '\u{1E8C}'), ('\u{59}', '\u{300}', '\u{1EF2}'),
+    ('\u{59}', '\u{301}', '\u{DD}'), ('\u{59}', '\u{302}', '\u{176}'), ('\u{59}', '\u{303}', '\u{1EF8}'),
+    ('\u{59}', '\u{304}', '\u{232}'), ('\u{59}', '\u{307}', '\u{1E8E}'), ('\u{59}', '\u{308}', '\u{178}'),
+    ('\u{59}', '\u{309}', '\u{1EF6}'), ('\u{59}', '\u{323}', '\u{1EF4}'), ('\u{5A}', '\u{301}', '\u{179}'),
+    ('\u{5A}', '\u{302}', '\u{1E90}'), ('\u{5A}', '\u{307}', '\u{17B}'), ('\u{5A}', '\u{30C}', '\u{17D}'),
+    ('\u{5A}', '\u{323}', '\u{1E92}'), ('\u{5A}', '\u{331}', '\u{1E94}'), ('\u{61}', '\u{300}', '\u{E0}'),
+    ('\u{61}', '\u{301}', '\u{E1}'), ('\u{61}', '\u{302}', '\u{E2}'), ('\u{61}', '\u{303}', '\u{E3}'),
+    ('\u{61}', '\u{304}', '\u{101}'), ('\u{61}', '\u{306}', '\u{103}'), ('\u{61}', '\u{307}', '\u{227}'),
+    ('\u{61}', '\u{308}', '\u{E4}'), ('\u{61}', '\u{309}', '\u{1EA3}'), ('\u{61}', '\u{30A}', '\u{E5}'),
+    ('\u{61}', '\u{30C}', '\u{1CE}'), ('\u{61}', '\u{30F}', '\u{201}'), ('\u{61}', '\u{311}', '\u{203}'),
+    ('\u{61}', '\u{323}', '\u{1EA1}'), ('\u{61}', '\u{325}', '\u{1E01}'), ('\u{61}', '\u{328}', '\u{105}'),
+    ('\u{62}', '\u{307}', '\u{1E03}'), ('\u{62}', '\u{323}', '\u{1E05}'), ('\u{62}', '\u{331}', '\u{1E07}'),
+    ('\u{63}', '\u{301}', '\u{107}'), ('\u{63}', '\u{302}', '\u{109}'), ('\u{63}', '\u{307}', '\u{10B}'),
+    ('\u{63}', '\u{30C}', '\u{10D}'), ('\u{63}', '\u{327}', '\u{E7}'), ('\u{64}', '\u{307}', '\u{1E0B}'),
+    ('\u{64}', '\u{30C}', '\u{10F}'), ('\u{64}', '\u{323}', '\u{1E0D}'), ('\u{64}', '\u{327}', '\u{1E11}'),
+    ('\u{64}', '\u{32D}', '\u{1E13}'), ('\u{64}', '\u{331}', '\u{1E0F}'), ('\u{65}', '\u{300}', '\u{E8}'),
+    ('\u{65}', '\u{301}', '\u{E9}'), ('\u{65}', '\u{302}', '\u{EA}'), ('\u{65}', '\u{303}', '\u{1EBD}'),
+    ('\u{65}', '\u{304}', '\u{113}'), ('\u{65}', '\u{306}', '\u{115}'), ('\u{65}', '\u{307}', '\u{117}'),
+    ('\u{65}', '\u{308}', '\u{EB}'), ('\u{65}', '\u{309}', '\u{1EBB}'), ('\u{65}', '\u{30C}', '\u{11B}'),
+    ('\u{65}', '\u{30F}', '\u{205}'), ('\u{65}', '\u{311}', '\u{207}'), ('\u{65}', '\u{323}', '\u{1EB9}'),
+    ('\u{65}', '\u{327}', '\u{229}'), ('\u{65}', '\u{328}', '\u{119}'), ('\u{65}', '\u{32D}', '\u{1E19}'),
+    ('\u{65}', '\u{330}', '\u{1E1B}'), ('\u{66}', '\u{307}', '\u{1E1F}'), ('\u{67}', '\u{301}', '\u{1F5}'),
+    ('\u{67}', '\u{302}', '\u{11D}'), ('\u{67}', '\u{304}', '\u{1E21}'), ('\u{67}', '\u{306}', '\u{11F}'),
+    ('\u{67}', '\u{307}', '\u{121}'), ('\u{67}', '\u{30C}', '\u{1E7}'), ('\u{67}', '\u{327}', '\u{123}'),
+    ('\u{68}', '\u{302}', '\u{125}'), ('\u{68}', '\u{307}', '\u{1E23}'), ('\u{68}', '\u{308}', '\u{1E27}'),
+    ('\u{68}', '\u{30C}', '\u{21F}'), ('\u{68}', '\u{323}', '\u{1E25}'), ('\u{68}', '\u{327}', '\u{1E29}'),
+    ('\u{68}', '\u{32E}', '\u{1E2B}'), ('\u{68}', '\u{331}', '\u{1E96}'), ('\u{69}', '\u{300}', '\u{EC}'),
+    ('\u{69}', '\u{301}', '\u{ED}'), ('\u{69}', '\u{302}', '\u{EE}'), ('\u{69}', '\u{303}', '\u{129}'),
+    ('\u{69}', '\u{304}', '\u{12B}'), ('\u{69}', '\u{306}', '\u{12D}'), ('\u{69}', '\u{308}', '\u{EF}'),
+    ('\u{69}', '\u{309}', '\u{1EC9}'), ('\u{69}', '\u{30C}', '\u{1D0}'), ('\u{69}', '\u{30F}', '\u{209}'),
+    ('\u{69}', '\u{311}', '\u{20B}'), ('\u{69}', '\u{323}', '\u{1ECB}'), ('\u{69}', '\u{328}', '\u{12F}'),
+    ('\u{69}', '\u{330}', '\u{1E2D}'), ('\u{6A}', '\u{302}', '\u{135}'), ('\u{6A}', '\u{30C}', '\u{1F0}'),
+    ('\u{6B}', '\u{301}', '\u{1E31}'), ('\u{6B}', '\u{30C}', '\u{1E9}'), ('\u{6B}', '\u{323}', '\u{1E33}'),
+    ('\u{6B}', '\u{327}', '\u{137}'), ('\u{6B}', '\u{331}', '\u{1E35}'), ('\u{6C}', '\u{301}', '\u{13A}'),
+    ('\u{6C}', '\u{30C}', '\u{13E}'), ('\u{6C}', '\u{323}', '\u{1E37}'), ('\u{6C}', '\u{327}', '\u{13C}'),
+    ('\u{6C}', '\u{32D}', '\u{1E3D}'), ('\u{6C}', '\u{331}', '\u{1E3B}'), ('\u{6D}', '\u{301}', '\u{1E3F}'),
+    ('\u{6D}', '\u{307}', '\u{1E41}'), ('\u{6D}', '\u{323}', '\u{1E43}'), ('\u{6E}', '\u{300}', '\u{1F9}'),
+    ('\u{6E}', '\u{301}', '\u{144}'), ('\u{6E}', '\u{303}', '\u{F1}'), ('\u{6E}', '\u{307}', '\u{1E45}'),
+    ('\u{6E}', '\u{30C}', '\u{148}'), ('\u{6E}', '\u{323}', '\u{1E47}'), ('\u{6E}', '\u{327}', '\u{146}'),
+    ('\u{6E}', '\u{32D}', '\u{1E4B}'), ('\u{6E}', '\u{331}', '\u{1E49}'), ('\u{6F}', '\u{300}', '\u{F2}'),
+    ('\u{6F}', '\u{301}', '\u{F3}'), ('\u{6F}', '\u{302}', '\u{F4}'), ('\u{6F}', '\u{303}', '\u{F5}'),
+    ('\u{6F}', '\u{304}', '\u{14D}'), ('\u{6F}', '\u{306}', '\u{14F}'), ('\u{6F}', '\u{307}', '\u{22F}'),
+    ('\u{6F}', '\u{308}', '\u{F6}'), ('\u{6F}', '\u{309}', '\u{1ECF}'), ('\u{6F}', '\u{30B}', '\u{151}'),
+    ('\u{6F}', '\u{30C}', '\u{1D2}'), ('\u{6F}', '\u{30F}', '\u{20D}'), ('\u{6F}', '\u{311}', '\u{20F}'),
+    ('\u{6F}', '\u{31B}', '\u{1A1}'), ('\u{6F}', '\u{323}', '\u{1ECD}'), ('\u{6F}', '\u{328}', '\u{1EB}'),
+    ('\u{70}', '\u{301}', '\u{1E55}'), ('\u{70}', '\u{307}', '\u{1E57}'), ('\u{72}', '\u{301}', '\u{155}'),
+    ('\u{72}', '\u{307}', '\u{1E59}'), ('\u{72}', '\u{30C}', '\u{159}'), ('\u{72}', '\u{30F}', '\u{211}'),
+    ('\u{72}', '\u{311}', '\u{213}'), ('\u{72}', '\u{323}', '\u{1E5B}'), ('\u{72}', '\u{327}', '\u{157}'),
+    ('\u{72}', '\u{331}', '\u{1E5F}'), ('\u{73}', '\u{301}', '\u{15B}'), ('\u{73}', '\u{302}', '\u{15D}'),
+    ('\u{73}', '\u{307}', '\u{1E61}'), ('\u{73}', '\u{30C}', '\u{161}'), ('\u{73}', '\u{323}', '\u{1E63}'),
+    ('\u{73}', '\u{326}', '\u{219}'), ('\u{73}', '\u{327}', '\u{15F}'), ('\u{74}', '\u{307}', '\u{1E6B}'),
+    ('\u{74}', '\u{308}', '\u{1E97}'), ('\u{74}', '\u{30C}', '\u{165}'), ('\u{74}', '\u{323}', '\u{1E6D}'),
+    ('\u{74}', '\u{326}', '\u{21B}'), ('\u{74}', '\u{327}', '\u{163}'), ('\u{74}', '\u{32D}', '\u{1E71}'),
+    ('\u{74}', '\u{331}', '\u{1E6F}'), ('\u{75}', '\u{300}', '\u{F9}'), ('\u{75}', '\u{301}', '\u{FA}'),
+    ('\u{75}', '\u{302}', '\u{FB}'), ('\u{75}', '\u{303}', '\u{169}'), ('\u{75}', '\u{304}', '\u{16B}'),
+    ('\u{75}', '\u{306}', '\u{16D}'), ('\u{75}', '\u{308}', '\u{FC}'), ('\u{75}', '\u{309}', '\u{1EE7}'),
+    ('\u{75}', '\u{30A}', '\u{16F}'), ('\u{75}', '\u{30B}', '\u{171}'), ('\u{75}', '\u{30C}', '\u{1D4}'),
+    ('\u{75}', '\u{30F}', '\u{215}'), ('\u{75}', '\u{311}', '\u{217}'), ('\u{75}', '\u{31B}', '\u{1B0}'),
+    ('\u{75}', '\u{323}', '\u{1EE5}'), ('\u{75}', '\u{324}', '\u{1E73}'), ('\u{75}', '\u{328}', '\u{173}'),
+    ('\u{75}', '\u{32D}', '\u{1E77}'), ('\u{75}', '\u{330}', '\u{1E75}'), ('\u{76}', '\u{303}', '\u{1E7D}'),
+    ('\u{76}', '\u{323}', '\u{1E7F}'), ('\u{77}', '\u{300}', '\u{1E81}'), ('\u{77}', '\u{301}', '\u{1E83}'),
+    ('\u{77}', '\u{302}', '\u{175}'), ('\u{77}', '\u{307}', '\u{1E87}'), ('\u{77}', '\u{308}', '\u{1E85}'),
+    ('\u{77}', '\u{30A}', '\u{1E98}'), ('\u{77}', '\u{323}', '\u{1E89}'), ('\u{78}', '\u{307}', '\u{1E8B}'),
+    ('\u{78}', '\u{308}', '\u{1E8D}'), ('\u{79}', '\u{300}', '\u{1EF3}'), ('\u{79}', '\u{301}', '\u{FD}'),
+    ('\u{79}', '\u{302}', '\u{177}'), ('\u{79}', '\u{303}', '\u{1EF9}'), ('\u{79}', '\u{304}', '\u{233}'),
+    ('\u{79}', '\u{307}', '\u{1E8F}'), ('\u{79}', '\u{308}', '\u{FF}'), ('\u{79}', '\u{309}', '\u{1EF7}'),
+    ('\u{79}', '\u{30A}', '\u{1E99}'), ('\u{79}', '\u{323}', '\u{1EF5}'), ('\u{7A}', '\u{301}', '\u{17A}'),
+    ('\u{7A}', '\u{302}', '\u{1E91}'), ('\u{7A}', '\u{307}', '\u{17C}'), ('\u{7A}', '\u{30C}', '\u{17E}'),
+    ('\u{7A}', '\u{323}', '\u{1E93}'), ('\u{7A}', '\u{331}', '\u{1E95}'), ('\u{A8}', '\u{300}', '\u{1FED}'),
+    ('\u{A8}', '\u{301}', '\u{385}'), ('\u{A8}', '\u{342}', '\u{1FC1}'), ('\u{C2}', '\u{300}', '\u{1EA6}'),
+    ('\u{C2}', '\u{301}', '\u{1EA4}'), ('\u{C2}', '\u{303}', '\u{1EAA}'), ('\u{C2}', '\u{309}', '\u{1EA8}'),
+    ('\u{C4}', '\u{304}', '\u{1DE}'), ('\u{C5}', '\u{301}', '\u{1FA}'), ('\u{C6}', '\u{301}', '\u{1FC}'),
+    ('\u{C6}', '\u{304}', '\u{1E2}'), ('\u{C7}', '\u{301}', '\u{1E08}'), ('\u{CA}', '\u{300}', '\u{1EC0}'),
+    ('\u{CA}', '\u{301}', '\u{1EBE}'), ('\u{CA}', '\u{303}', '\u{1EC4}'), ('\u{CA}', '\u{309}', '\u{1EC2}'),
+    ('\u{CF}', '\u{301}', '\u{1E2E}'), ('\u{D4}', '\u{300}', '\u{1ED2}'), ('\u{D4}', '\u{301}', '\u{1ED0}'),
+    ('\u{D4}', '\u{303}', '\u{1ED6}'), ('\u{D4}', '\u{309}', '\u{1ED4}'), ('\u{D5}', '\u{301}', '\u{1E4C}'),
+    ('\u{D5}', '\u{304}', '\u{22C}'), ('\u{D5}', '\u{308}', '\u{1E4E}'), ('\u{D6}', '\u{304}', '\u{22A}'),
+    ('\u{D8}', '\u{301}', '\u{1FE}'), ('\u{DC}', '\u{300}', '\u{1DB}'), ('\u{DC}', '\u{301}', '\u{1D7}'),
+    ('\u{DC}', '\u{304}', '\u{1D5}'), ('\u{DC}', '\u{30C}', '\u{1D9}'), ('\u{E2}', '\u{300}', '\u{1EA7}'),
+    ('\u{E2}', '\u{301}', '\u{1EA5}'), ('\u{E2}', '\u{303}', '\u{1EAB}'), ('\u{E2}', '\u{309}', '\u{1EA9}'),
+    ('\u{E4}', '\u{304}', '\u{1DF}'), ('\u{E5}', '\u{301}', '\u{1FB}'), ('\u{E6}', '\u{301}', '\u{1FD}'),
+    ('\u{E6}', '\u{304}', '\u{1E3}'), ('\u{E7}', '\u{301}', '\u{1E09}'), ('\u{EA}', '\u{300}', '\u{1EC1}'),
+    ('\u{EA}', '\u{301}', '\u{1EBF}'), ('\u{EA}', '\u{303}', '\u{1EC5}'), ('\u{EA}', '\u{309}', '\u{1EC3}'),
+    ('\u{EF}', '\u{301}', '\u{1E2F}'), ('\u{F4}', '\u{300}', '\u{1ED3}'), ('\u{F4}', '\u{301}', '\u{1ED1}'),
+    ('\u{F4}', '\u{303}', '\u{1ED7}'), ('\u{F4}', '\u{309}', '\u{1ED5}'), ('\u{F5}', '\u{301}', '\u{1E4D}'),
+    ('\u{F5}', '\u{304}', '\u{22D}'), ('\u{F5}', '\u{308}', '\u{1E4F}'), ('\u{F6}', '\u{304}', '\u{22B}'),
+    ('\u{F8}', '\u{301}', '\u{1FF}'), ('\u{FC}', '\u{300}', '\u{1DC}'), ('\u{FC}', '\u{301}', '\u{1D8}'),
+    ('\u{FC}', '\u{304}', '\u{1D6}'), ('\u{FC}', '\u{30C}', '\u{1DA}'), ('\u{102}', '\u{300}', '\u{1EB0}'),
+    ('\u{102}', '\u{301}', '\u{1EAE}'), ('\u{102}', '\u{303}', '\u{1EB4}'), ('\u{102}', '\u{309}', '\u{1EB2}'),
+    ('\u{103}', '\u{300}', '\u{1EB1}'), ('\u{103}', '\u{301}', '\u{1EAF}'), ('\u{103}', '\u{303}', '\u{1EB5}'),
+    ('\u{103}', '\u{309}', '\u{1EB3}'), ('\u{112}', '\u{300}', '\u{1E14}'), ('\u{112}', '\u{301}', '\u{1E16}'),
+    ('\u{113}', '\u{300}', '\u{1E15}'), ('\u{113}', '\u{301}', '\u{1E17}'), ('\u{14C}', '\u{300}', '\u{1E50}'),
+    ('\u{14C}', '\u{301}', '\u{1E52}'), ('\u{14D}', '\u{300}', '\u{1E51}'), ('\u{14D}', '\u{301}', '\u{1E53}'),
+    ('\u{15A}', '\u{307}', '\u{1E64}'), ('\u{15B}', '\u{307}', '\u{1E65}'), ('\u{160}', '\u{307}', '\u{1E66}'),
+    ('\u{161}', '\u{307}', '\u{1E67}'), ('\u{168}', '\u{301}', '\u{1E78}'), ('\u{169}', '\u{301}', '\u{1E79}'),
+    ('\u{16A}', '\u{308}', '\u{1E7A}'), ('\u{16B}', '\u{308}', '\u{1E7B}'), ('\u{17F}', '\u{307}', '\u{1E9B}'),
+    ('\u{1A0}', '\u{300}', '\u{1EDC}'), ('\u{1A0}', '\u{301}', '\u{1EDA}'), ('\u{1A0}', '\u{303}', '\u{1EE0}'),
+    ('\u{1A0}', '\u{309}', '\u{1EDE}'), ('\u{1A0}', '\u{323}', '\u{1EE2}'), ('\u{1A1}', '\u{300}', '\u{1EDD}'),
+    ('\u{1A1}', '\u{301}', '\u{1EDB}'), ('\u{1A1}', '\u{303}', '\u{1EE1}'), ('\u{1A1}', '\u{309}', '\u{1EDF}'),
+    ('\u{1A1}', '\u{323}', '\u{1EE3}'), ('\u{1AF}', '\u{300}', '\u{1EEA}'), ('\u{1AF}', '\u{301}', '\u{1EE8}'),
+    ('\u{1AF}', '\u{303}', '\u{1EEE}'), ('\u{1AF}', '\u{309}', '\u{1EEC}'), ('\u{1AF}', '\u{323}', '\u{1EF0}'),
+    ('\u{1B0}', '\u{300}', '\u{1EEB}'), ('\u{1B0}', '\u{301}', '\u{1EE9}'), ('\u{1B0}', '\u{303}', '\u{1EEF}'),
+    ('\u{1B0}', '\u{309}', '\u{1EED}'), ('\u{1B0}', '\u{323}', '\u{1EF1}'), ('\u{1B7}', '\u{30C}', '\u{1EE}'),
+    ('\u{1EA}', '\u{304}', '\u{1EC}'), ('\u{1EB}', '\u{304}', '\u{1ED}'), ('\u{226}', '\u{304}', '\u{1E0}'),
+    ('\u{227}', '\u{304}', '\u{1E1}'), ('\u{228}', '\u{306}', '\u{1E1C}'), ('\u{229}', '\u{306}', '\u{1E1D}'),
+    ('\u{22E}', '\u{304}', '\u{230}'), ('\u{22F}', '\u{304}', '\u{231}'), ('\u{292}', '\u{30C}', '\u{1EF}'),
+    ('\u{391}', '\u{300}', '\u{1FBA}'), ('\u{391}', '\u{301}', '\u{386}'), ('\u{391}', '\u{304}', '\u{1FB9}'),
+    ('\u{391}', '\u{306}', '\u{1FB8}'), ('\u{391}', '\u{313}', '\u{1F08}'), ('\u{391}', '\u{314}', '\u{1F09}'),
+    ('\u{391}', '\u{345}', '\u{1FBC}'), ('\u{395}', '\u{300}', '\u{1FC8}'), ('\u{395}', '\u{301}', '\u{388}'),
+    ('\u{395}', '\u{313}', '\u{1F18}'), ('\u{395}', '\u{314}', '\u{1F19}'), ('\u{397}', '\u{300}', '\u{1FCA}'),
+    ('\u{397}', '\u{301}', '\u{389}'), ('\u{397}', '\u{313}', '\u{1F28}'), ('\u{397}', '\u{314}', '\u{1F29}'),
+    ('\u{397}', '\u{345}', '\u{1FCC}'), ('\u{399}', '\u{300}', '\u{1FDA}'), ('\u{399}', '\u{301}', '\u{38A}'),
+    ('\u{399}', '\u{304}', '\u{1FD9}'), ('\u{399}', '\u{306}', '\u{1FD8}'), ('\u{399}', '\u{308}', '\u{3AA}'),
+    ('\u{399}', '\u{313}', '\u{1F38}'), ('\u{399}', '\u{314}', '\u{1F39}'), ('\u{39F}', '\u{300}', '\u{1FF8}'),
+    ('\u{39F}', '\u{301}', '\u{38C}'), ('\u{39F}', '\u{313}', '\u{1F48}'), ('\u{39F}', '\u{314}', '\u{1F49}'),
+    ('\u{3A1}', '\u{314}', '\u{1FEC}'), ('\u{3A5}', '\u{300}', '\u{1FEA}'), ('\u{3A5}', '\u{301}', '\u{38E}'),
+    ('\u{3A5}', '\u{304}', '\u{1FE9}'), ('\u{3A5}', '\u{306}', '\u{1FE8}'), ('\u{3A5}', '\u{308}', '\u{3AB}'),
+    ('\u{3A5}', '\u{314}', '\u{1F59}'), ('\u{3A9}', '\u{300}', '\u{1FFA}'), ('\u{3A9}', '\u{301}', '\u{38F}'),
+    ('\u{3A9}', '\u{313}', '\u{1F68}'), ('\u{3A9}', '\u{314}', '\u{1F69}'), ('\u{3A9}', '\u{345}', '\u{1FFC}'),
+    ('\u{3AC}', '\u{345}', '\u{1FB4}'), ('\u{3AE}', '\u{345}', '\u{1FC4}'), ('\u{3B1}', '\u{300}', '\u{1F70}'),
+    ('\u{3B1}', '\u{301}', '\u{3AC}'), ('\u{3B1}', '\u{304}', '\u{1FB1}'), ('\u{3B1}', '\u{306}', '\u{1FB0}'),
+    ('\u{3B1}', '\u{313}', '\u{1F00}'), ('\u{3B1}', '\u{314}', '\u{1F01}'), ('\u{3B1}', '\u{342}', '\u{1FB6}'),
+    ('\u{3B1}', '\u{345}', '\u{1FB3}'), ('\u{3B5}', '\u{300}', '\u{1F72}'), ('\u{3B5}', '\u{301}', '\u{3AD}'),
+    ('\u{3B5}', '\u{313}', '\u{1F10}'), ('\u{3B5}', '\u{314}', '\u{1F11}'), ('\u{3B7}', '\u{300}', '\u{1F74}'),
+    ('\u{3B7}', '\u{301}', '\u{3AE}'), ('\u{3B7}', '\u{313}', '\u{1F20}'), ('\u{3B7}', '\u{314}', '\u{1F21}'),
+    ('\u{3B7}', '\u{342}', '\u{1FC6}'), ('\u{3B7}', '\u{345}', '\u{1FC3}'), ('\u{3B9}', '\u{300}', '\u{1F76}'),
+    ('\u{3B9}', '\u{301}', '\u{3AF}'), ('\u{3B9}', '\u{304}', '\u{1FD1}'), ('\u{3B9}', '\u{306}', '\u{1FD0}'),
+    ('\u{3B9}', '\u{308}', '\u{3CA}'), ('\u{3B9}', '\u{313}', '\u{1F30}'), ('\u{3B9}', '\u{314}', '\u{1F31}'),
+    ('\u{3B9}', '\u{342}', '\u{1FD6}'), ('\u{3BF}', '\u{300}', '\u{1F78}'), ('\u{3BF}', '\u{301}', '\u{3CC}'),
+    ('\u{3BF}', '\u{313}', '\u{1F40}'), ('\u{3BF}', '\u{314}', '\u{1F41}'), ('\u{3C1}', '\u{313}', '\u{1FE4}'),
+    ('\u{3C1}', '\u{314}', '\u{1FE5}'), ('\u{3C5}', '\u{300}', '\u{1F7A}'), ('\u{3C5}', '\u{301}', '\u{3CD}'),
+    ('\u{3C5}', '\u{304}', '\u{1FE1}'), ('\u{3C5}', '\u{306}', '\u{1FE0}'), ('\u{3C5}', '\u{308}', '\u{3CB}'),
+    ('\u{3C5}', '\u{313}', '\u{1F50}'), ('\u{3C5}', '\u{314}', '\u{1F51}'), ('\u{3C5}', '\u{342}', '\u{1FE6}'),
+    ('\u{3C9}', '\u{300}', '\u{1F7C}'), ('\u{3C9}', '\u{301}', '\u{3CE}'), ('\u{3C9}', '\u{313}', '\u{1F60}'),
+    ('\u{3C9}', '\u{314}', '\u{1F61}'), ('\u{3C9}', '\u{342}', '\u{1FF6}'), ('\u{3C9}', '\u{345}', '\u{1FF3}'),
+    ('\u{3CA}', '\u{300}', '\u{1FD2}'), ('\u{3CA}', '\u{301}', '\u{390}'), ('\u{3CA}', '\u{342}', '\u{1FD7}'),
+    ('\u{3CB}', '\u{300}', '\u{1FE2}'), ('\u{3CB}', '\u{301}', '\u{3B0}'), ('\u{3CB}', '\u{342}', '\u{1FE7}'),
+    ('\u{3CE}', '\u{345}', '\u{1FF4}'), ('\u{3D2}', '\u{301}', '\u{3D3}'), ('\u{3D2}', '\u{308}', '\u{3D4}'),
+    ('\u{406}', '\u{308}', '\u{407}'), ('\u{410}', '\u{306}', '\u{4D0}'), ('\u{410}', '\u{308}', '\u{4D2}'),
+    ('\u{413}', '\u{301}', '\u{403}'), ('\u{415}', '\u{300}', '\u{400}'), ('\u{415}', '\u{306}', '\u{4D6}'),
+    ('\u{415}', '\u{308}', '\u{401}'), ('\u{416}', '\u{306}', '\u{4C1}'), ('\u{416}', '\u{308}', '\u{4DC}'),
+    ('\u{417}', '\u{308}', '\u{4DE}'), ('\u{418}', '\u{300}', '\u{40D}'), ('\u{418}', '\u{304}', '\u{4E2}'),
+    ('\u{418}', '\u{306}', '\u{419}'), ('\u{418}', '\u{308}', '\u{4E4}'), ('\u{41A}', '\u{301}', '\u{40C}'),
+    ('\u{41E}', '\u{308}', '\u{4E6}'), ('\u{423}', '\u{304}', '\u{4EE}'), ('\u{423}', '\u{306}', '\u{40E}'),
+    ('\u{423}', '\u{308}', '\u{4F0}'), ('\u{423}', '\u{30B}', '\u{4F2}'), ('\u{427}', '\u{308}', '\u{4F4}'),
+    ('\u{42B}', '\u{308}', '\u{4F8}'), ('\u{42D}', '\u{308}', '\u{4EC}'), ('\u{430}', '\u{306}', '\u{4D1}'),
+    ('\u{430}', '\u{308}', '\u{4D3}'), ('\u{433}', '\u{301}', '\u{453}'), ('\u{435}', '\u{300}', '\u{450}'),
+    ('\u{435}', '\u{306}', '\u{4D7}'), ('\u{435}', '\u{308}', '\u{451}'), ('\u{436}', '\u{306}', '\u{4C2}'),
+    ('\u{436}', '\u{308}', '\u{4DD}'), ('\u{437}', '\u{308}', '\u{4DF}'), ('\u{438}', '\u{300}', '\u{45D}'),
+    ('\u{438}', '\u{304}', '\u{4E3}'), ('\u{438}', '\u{306}', '\u{439}'), ('\u{438}', '\u{308}', '\u{4E5}'),
+    ('\u{43A}', '\u{301}', '\u{45C}'), ('\u{43E}', '\u{308}', '\u{4E7}'), ('\u{443}', '\u{304}', '\u{4EF}'),
+    ('\u{443}', '\u{306}', '\u{45E}'), ('\u{443}', '\u{308}', '\u{4F1}'), ('\u{443}', '\u{30B}', '\u{4F3}'),
+    ('\u{447}', '\u{308}', '\u{4F5}'), ('\u{44B}', '\u{308}', '\u{4F9}'), ('\u{44D}', '\u{308}', '\u{4ED}'),
+    ('\u{456}', '\u{308}', '\u{457}'), ('\u{474}', '\u{30F}', '\u{476}'), ('\u{475}', '\u{30F}', '\u{477}'),
+    ('\u{4D8}', '\u{308}', '\u{4DA}'), ('\u{4D9}', '\u{308}', '\u{4DB}'), ('\u{4E8}', '\u{308}', '\u{4EA}'),
+    ('\u{4E9}', '\u{308}', '\u{4EB}'), ('\u{627}', '\u{653}', '\u{622}'), ('\u{627}', '\u{654}', '\u{623}'),
+    ('\u{627}', '\u{655}', '\u{625}'), ('\u{648}', '\u{654}', '\u{624}'), ('\u{64A}', '\u{654}', '\u{626}'),
+    ('\u{6C1}', '\u{654}', '\u{6C2}'), ('\u{6D2}', '\u{654}', '\u{6D3}'), ('\u{6D5}', '\u{654}', '\u{6C0}'),
+    ('\u{928}', '\u{93C}', '\u{929}'), ('\u{930}', '\u{93C}', '\u{931}'), ('\u{933}', '\u{93C}', '\u{934}'),
+    ('\u{9C7}', '\u{9BE}', '\u{9CB}'), ('\u{9C7}', '\u{9D7}', '\u{9CC}'), ('\u{B47}', '\u{B3E}', '\u{B4B}'),
+    ('\u{B47}', '\u{B56}', '\u{B48}'), ('\u{B47}', '\u{B57}', '\u{B4C}'), ('\u{B92}', '\u{BD7}', '\u{B94}'),
+    ('\u{BC6}', '\u{BBE}', '\u{BCA}'), ('\u{BC6}', '\u{BD7}', '\u{BCC}'), ('\u{BC7}', '\u{BBE}', '\u{BCB}'),
+    ('\u{C46}', '\u{C56}', '\u{C48}'), ('\u{CBF}', '\u{CD5}', '\u{CC0}'), ('\u{CC6}', '\u{CC2}', '\u{CCA}'),
+    ('\u{CC6}', '\u{CD5}', '\u{CC7}'), ('\u{CC6}', '\u{CD6}', '\u{CC8}'), ('\u{CCA}', '\u{CD5}', '\u{CCB}'),
+    ('\u{D46}', '\u{D3E}', '\u{D4A}'), ('\u{D46}', '\u{D57}', '\u{D4C}'), ('\u{D47}', '\u{D3E}', '\u{D4B}'),
+    ('\u{DD9}', '\u{DCA}', '\u{DDA}'), ('\u{DD9}', '\u{DCF}', '\u{DDC}'), ('\u{DD9}', '\u{DDF}', '\u{DDE}'),
+    ('\u{DDC}', '\u{DCA}', '\u{DDD}'), ('\u{1025}', '\u{102E}', '\u{1026}'), ('\u{1B05}', '\u{1B35}', '\u{1B06}'),
+    ('\u{1B07}', '\u{1B35}', '\u{1B08}'), ('\u{1B09}', '\u{1B35}', '\u{1B0A}'), ('\u{1B0B}', '\u{1B35}', '\u{1B0C}'),
+    ('\u{1B0D}', '\u{1B35}', '\u{1B0E}'), ('\u{1B11}', '\u{1B35}', '\u{1B12}'), ('\u{1B3A}', '\u{1B35}', '\u{1B3B}'),
+    ('\u{1B3C}', '\u{1B35}', '\u{1B3D}'), ('\u{1B3E}', '\u{1B35}', '\u{1B40}'), ('\u{1B3F}', '\u{1B35}', '\u{1B41}'),
+    ('\u{1B42}', '\u{1B35}', '\u{1B43}'), ('\u{1E36}', '\u{304}', '\u{1E38}'), ('\u{1E37}', '\u{304}', '\u{1E39}'),
+    ('\u{1E5A}', '\u{304}', '\u{1E5C}'), ('\u{1E5B}', '\u{304}', '\u{1E5D}'), ('\u{1E62}', '\u{307}', '\u{1E68}'),
+    ('\u{1E63}', '\u{307}', '\u{1E69}'), ('\u{1EA0}', '\u{302}', '\u{1EAC}'), ('\u{1EA0}', '\u{306}', '\u{1EB6}'),
+    ('\u{1EA1}', '\u{302}', '\u{1EAD}'), ('\u{1EA1}', '\u{306}', '\u{1EB7}'), ('\u{1EB8}', '\u{302}', '\u{1EC6}'),
+    ('\u{1EB9}', '\u{302}', '\u{1EC7}'), ('\u{1ECC}', '\u{302}', '\u{1ED8}'), ('\u{1ECD}', '\u{302}', '\u{1ED9}'),
+    ('\u{1F00}', '\u{300}', '\u{1F02}'), ('\u{1F00}', '\u{301}', '\u{1F04}'), ('\u{1F00}', '\u{342}', '\u{1F06}'),
+    ('\u{1F00}', '\u{345}', '\u{1F80}'), ('\u{1F01}', '\u{300}', '\u{1F03}'), ('\u{1F01}', '\u{301}', '\u{1F05}'),
+    ('\u{1F01}', '\u{342}', '\u{1F07}'), ('\u{1F01}', '\u{345}', '\u{1F81}'), ('\u{1F02}', '\u{345}', '\u{1F82}'),
+    ('\u{1F03}', '\u{345}', '\u{1F83}'), ('\u{1F04}', '\u{345}', '\u{1F84}'), ('\u{1F05}', '\u{345}', '\u{1F85}'),
+    ('\u{1F06}', '\u{345}', '\u{1F86}'), ('\u{1F07}', '\u{345}', '\u{1F87}'), ('\u{1F08}', '\u{300}', '\u{1F0A}'),
+    ('\u{1F08}', '\u{301}', '\u{1F0C}'), ('\u{1F08}', '\u{342}', '\u{1F0E}'), ('\u{1F08}', '\u{345}', '\u{1F88}'),
+    ('\u{1F09}', '\u{300}', '\u{1F0B}'), ('\u{1F09}', '\u{301}', '\u{1F0D}'), ('\u{1F09}', '\u{342}', '\u{1F0F}'),
+    ('\u{1F09}', '\u{345}', '\u{1F89}'), ('\u{1F0A}', '\u{345}', '\u{1F8A}'), ('\u{1F0B}', '\u{345}', '\u{1F8B}'),
+    ('\u{1F0C}', '\u{345}', '\u{1F8C}'), ('\u{1F0D}', '\u{345}', '\u{1F8D}'), ('\u{1F0E}', '\u{345}', '\u{1F8E}'),
+    ('\u{1F0F}', '\u{345}', '\u{1F8F}'), ('\u{1F10}', '\u{300}', '\u{1F12}'), ('\u{1F10}', '\u{301}', '\u{1F14}'),
+    ('\u{1F11}', '\u{300}', '\u{1F13}'), ('\u{1F11}', '\u{301}', '\u{1F15}'), ('\u{1F18}', '\u{300}', '\u{1F1A}'),
+    ('\u{1F18}', '\u{301}', '\u{1F1C}'), ('\u{1F19}', '\u{300}', '\u{1F1B}'), ('\u{1F19}', '\u{301}', '\u{1F1D}'),
+    ('\u{1F20}', '\u{300}', '\u{1F22}'), ('\u{1F20}', '\u{301}', '\u{1F24}'), ('\u{1F20}', '\u{342}', '\u{1F26}'),
+    ('\u{1F20}', '\u{345}', '\u{1F90}'), ('\u{1F21}', '\u{300}', '\u{1F23}'), ('\u{1F21}', '\u{301}', '\u{1F25}'),
+    ('\u{1F21}', '\u{342}', '\u{1F27}'), ('\u{1F21}', '\u{345}', '\u{1F91}'), ('\u{1F22}', '\u{345}', '\u{1F92}'),
+    ('\u{1F23}', '\u{345}', '\u{1F93}'), ('\u{1F24}', '\u{345}', '\u{1F94}'), ('\u{1F25}', '\u{345}', '\u{1F95}'),
+    ('\u{1F26}', '\u{345}', '\u{1F96}'), ('\u{1F27}', '\u{345}', '\u{1F97}'), ('\u{1F28}', '\u{300}', '\u{1F2A}'),
+    ('\u{1F28}', '\u{301}', '\u{1F2C}'), ('\u{1F28}', '\u{342}', '\u{1F2E}'), ('\u{1F28}', '\u{345}', '\u{1F98}'),
+    ('\u{1F29}', '\u{300}', '\u{1F2B}'), ('\u{1F29}', '\u{301}', '\u{1F2D}'), ('\u{1F29}', '\u{342}', '\u{1F2F}'),
+    ('\u{1F29}', '\u{345}', '\u{1F99}'), ('\u{1F2A}', '\u{345}', '\u{1F9A}'), ('\u{1F2B}', '\u{345}', '\u{1F9B}'),
+    ('\u{1F2C}', '\u{345}', '\u{1F9C}'), ('\u{1F2D}', '\u{345}', '\u{1F9D}'), ('\u{1F2E}', '\u{345}', '\u{1F9E}'),
+    ('\u{1F2F}', '\u{345}', '\u{1F9F}'), ('\u{1F30}', '\u{300}', '\u{1F32}'), ('\u{1F30}', '\u{301}', '\u{1F34}'),
+    ('\u{1F30}', '\u{342}', '\u{1F36}'), ('\u{1F31}', '\u{300}', '\u{1F33}'), ('\u{1F31}', '\u{301}', '\u{1F35}'),
+    ('\u{1F31}', '\u{342}', '\u{1F37}'), ('\u{1F38}', '\u{300}', '\u{1F3A}'), ('\u{1F38}', '\u{301}', '\u{1F3C}'),
+    ('\u{1F38}', '\u{342}', '\u{1F3E}'), ('\u{1F39}', '\u{300}', '\u{1F3B}'), ('\u{1F39}', '\u{301}', '\u{1F3D}'),
+    ('\u{1F39}', '\u{342}', '\u{1F3F}'), ('\u{1F40}', '\u{300}', '\u{1F42}'), ('\u{1F40}', '\u{301}', '\u{1F44}'),
+    ('\u{1F41}', '\u{300}', '\u{1F43}'), ('\u{1F41}', '\u{301}', '\u{1F45}'), ('\u{1F48}', '\u{300}', '\u{1F4A}'),
+    ('\u{1F48}', '\u{301}', '\u{1F4C}'), ('\u{1F49}', '\u{300}', '\u{1F4B}'), ('\u{1F49}', '\u{301}', '\u{1F4D}'),
+    ('\u{1F50}', '\u{300}', '\u{1F52}'), ('\u{1F50}', '\u{301}', '\u{1F54}'), ('\u{1F50}', '\u{342}', '\u{1F56}'),
+    ('\u{1F51}', '\u{300}', '\u{1F53}'), ('\u{1F51}', '\u{301}', '\u{1F55}'), ('\u{1F51}', '\u{342}', '\u{1F57}'),
+    ('\u{1F59}', '\u{300}', '\u{1F5B}'), ('\u{1F59}', '\u{301}', '\u{1F5D}'), ('\u{1F59}', '\u{342}', '\u{1F5F}'),
+    ('\u{1F60}', '\u{300}', '\u{1F62}'), ('\u{1F60}', '\u{301}', '\u{1F64}'), ('\u{1F60}', '\u{342}', '\u{1F66}'),
+    ('\u{1F60}', '\u{345}', '\u{1FA0}'), ('\u{1F61}', '\u{300}', '\u{1F63}'), ('\u{1F61}', '\u{301}', '\u{1F65}'),
+    ('\u{1F61}', '\u{342}', '\u{1F67}'), ('\u{1F61}', '\u{345}', '\u{1FA1}'), ('\u{1F62}', '\u{345}', '\u{1FA2}'),
+    ('\u{1F63}', '\u{345}', '\u{1FA3}'), ('\u{1F64}', '\u{345}', '\u{1FA4}'), ('\u{1F65}', '\u{345}', '\u{1FA5}'),
+    ('\u{1F66}', '\u{345}', '\u{1FA6}'), ('\u{1F67}', '\u{345}', '\u{1FA7}'), ('\u{1F68}', '\u{300}', '\u{1F6A}'),
+    ('\u{1F68}', '\u{301}', '\u{1F6C}'), ('\u{1F68}', '\u{342}', '\u{1F6E}'), ('\u{1F68}', '\u{345}', '\u{1FA8}'),
+    ('\u{1F69}', '\u{300}', '\u{1F6B}'), ('\u{1F69}', '\u{301}', '\u{1F6D}'), ('\u{1F69}', '\u{342}', '\u{1F6F}'),
+    ('\u{1F69}', '\u{345}', '\u{1FA9}'), ('\u{1F6A}', '\u{345}', '\u{1FAA}'), ('\u{1F6B}', '\u{345}', '\u{1FAB}'),
+    ('\u{1F6C}', '\u{345}', '\u{1FAC}'), ('\u{1F6D}', '\u{345}', '\u{1FAD}'), ('\u{1F6E}', '\u{345}', '\u{1FAE}'),
+    ('\u{1F6F}', '\u{345}', '\u{1FAF}'), ('\u{1F70}', '\u{345}', '\u{1FB2}'), ('\u{1F74}', '\u{345}', '\u{1FC2}'),
+    ('\u{1F7C}', '\u{345}', '\u{1FF2}'), ('\u{1FB6}', '\u{345}', '\u{1FB7}'), ('\u{1FBF}', '\u{300}', '\u{1FCD}'),
+    ('\u{1FBF}', '\u{301}', '\u{1FCE}'), ('\u{1FBF}', '\u{342}', '\u{1FCF}'), ('\u{1FC6}', '\u{345}', '\u{1FC7}'),
+    ('\u{1FF6}', '\u{345}', '\u{1FF7}'), ('\u{1FFE}', '\u{300}', '\u{1FDD}'), ('\u{1FFE}', '\u{301}', '\u{1FDE}'),
+    ('\u{1FFE}', '\u{342}', '\u{1FDF}'), ('\u{2190}', '\u{338}', '\u{219A}'), ('\u{2192}', '\u{338}', '\u{219B}'),
+    ('\u{2194}', '\u{338}', '\u{21AE}'), ('\u{21D0}', '\u{338}', '\u{21CD}'), ('\u{21D2}', '\u{338}', '\u{21CF}'),
+    ('\u{21D4}', '\u{338}', '\u{21CE}'), ('\u{2203}', '\u{338}', '\u{2204}'), ('\u{2208}', '\u{338}', '\u{2209}'),
+    ('\u{220B}', '\u{338}', '\u{220C}'), ('\u{2223}', '\u{338}', '\u{2224}'), ('\u{2225}', '\u{338}', '\u{2226}'),
+    ('\u{223C}', '\u{338}', '\u{2241}'), ('\u{2243}', '\u{338}', '\u{2244}'), ('\u{2245}', '\u{338}', '\u{2247}'),
+    ('\u{2248}', '\u{338}', '\u{2249}'), ('\u{224D}', '\u{338}', '\u{226D}'), ('\u{2261}', '\u{338}', '\u{2262}'),
+    ('\u{2264}', '\u{338}', '\u{2270}'), ('\u{2265}', '\u{338}', '\u{2271}'), ('\u{2272}', '\u{338}', '\u{2274}'),
+    ('\u{2273}', '\u{338}', '\u{2275}'), ('\u{2276}', '\u{338}', '\u{2278}'), ('\u{2277}', '\u{338}', '\u{2279}'),
+    ('\u{227A}', '\u{338}', '\u{2280}'), ('\u{227B}', '\u{338}', '\u{2281}'), ('\u{227C}', '\u{338}', '\u{22E0}'),
+    ('\u{227D}', '\u{338}', '\u{22E1}'), ('\u{2282}', '\u{338}', '\u{2284}'), ('\u{2283}', '\u{338}', '\u{2285}'),
+    ('\u{2286}', '\u{338}', '\u{2288}'), ('\u{2287}', '\u{338}', '\u{2289}'), ('\u{2291}', '\u{338}', '\u{22E2}'),
+    ('\u{2292}', '\u{338}', '\u{22E3}'), ('\u{22A2}', '\u{338}', '\u{22AC}'), ('\u{22A8}', '\u{338}', '\u{22AD}'),
+    ('\u{22A9}', '\u{338}', '\u{22AE}'), ('\u{22AB}', '\u{338}', '\u{22AF}'), ('\u{22B2}', '\u{338}', '\u{22EA}'),
+    ('\u{22B3}', '\u{338}', '\u{22EB}'), ('\u{22B4}', '\u{338}', '\u{22EC}'), ('\u{22B5}', '\u{338}', '\u{22ED}'),
+    ('\u{3046}', '\u{3099}', '\u{3094}'), ('\u{304B}', '\u{3099}', '\u{304C}'), ('\u{304D}', '\u{3099}', '\u{304E}'),
+    ('\u{304F}', '\u{3099}', '\u{3050}'), ('\u{3051}', '\u{3099}', '\u{3052}'), ('\u{3053}', '\u{3099}', '\u{3054}'),
+    ('\u{3055}', '\u{3099}', '\u{3056}'), ('\u{3057}', '\u{3099}', '\u{3058}'), ('\u{3059}', '\u{3099}', '\u{305A}'),
+    ('\u{305B}', '\u{3099}', '\u{305C}'), ('\u{305D}', '\u{3099}', '\u{305E}'), ('\u{305F}', '\u{3099}', '\u{3060}'),
+    ('\u{3061}', '\u{3099}', '\u{3062}'), ('\u{3064}', '\u{3099}', '\u{3065}'), ('\u{3066}', '\u{3099}', '\u{3067}'),
+    ('\u{3068}', '\u{3099}', '\u{3069}'), ('\u{306F}', '\u{3099}', '\u{3070}'), ('\u{306F}', '\u{309A}', '\u{3071}'),
+    ('\u{3072}', '\u{3099}', '\u{3073}'), ('\u{3072}', '\u{309A}', '\u{3074}'), ('\u{3075}', '\u{3099}', '\u{3076}'),
+    ('\u{3075}', '\u{309A}', '\u{3077}'), ('\u{3078}', '\u{3099}', '\u{3079}'), ('\u{3078}', '\u{309A}', '\u{307A}'),
+    ('\u{307B}', '\u{3099}', '\u{307C}'), ('\u{307B}', '\u{309A}', '\u{307D}'), ('\u{309D}', '\u{3099}', '\u{309E}'),
+    ('\u{30A6}', '\u{3099}', '\u{30F4}'), ('\u{30AB}', '\u{3099}', '\u{30AC}'), ('\u{30AD}', '\u{3099}', '\u{30AE}'),
+    ('\u{30AF}', '\u{3099}', '\u{30B0}'), ('\u{30B1}', '\u{3099}', '\u{30B2}'), ('\u{30B3}', '\u{3099}', '\u{30B4}'),
+    ('\u{30B5}', '\u{3099}', '\u{30B6}'), ('\u{30B7}', '\u{3099}', '\u{30B8}'), ('\u{30B9}', '\u{3099}', '\u{30BA}'),
+    ('\u{30BB}', '\u{3099}', '\u{30BC}'), ('\u{30BD}', '\u{3099}', '\u{30BE}'), ('\u{30BF}', '\u{3099}', '\u{30C0}'),
+    ('\u{30C1}', '\u{3099}', '\u{30C2}'), ('\u{30C4}', '\u{3099}', '\u{30C5}'), ('\u{30C6}', '\u{3099}', '\u{30C7}'),
+    ('\u{30C8}', '\u{3099}', '\u{30C9}'), ('\u{30CF}', '\u{3099}', '\u{30D0}'), ('\u{30CF}', '\u{309A}', '\u{30D1}'),
+    ('\u{30D2}', '\u{3099}', '\u{30D3}'), ('\u{30D2}', '\u{309A}', '\u{30D4}'), ('\u{30D5}', '\u{3099}', '\u{30D6}'),
+    ('\u{30D5}', '\u{309A}', '\u{30D7}'), ('\u{30D8}', '\u{3099}', '\u{30D9}'), ('\u{30D8}', '\u{309A}', '\u{30DA}'),
+    ('\u{30DB}', '\u{3099}', '\u{30DC}'), ('\u{30DB}', '\u{309A}', '\u{30DD}'), ('\u{30EF}', '\u{3099}', '\u{30F7}'),
+    ('\u{30F0}', '\u{3099}', '\u{30F8}'), ('\u{30F1}', '\u{3099}', '\u{30F9}'), ('\u{30F2}', '\u{3099}', '\u{30FA}'),
+    ('\u{30FD}', '\u{3099}', '\u{30FE}'), ('\u{11099}', '\u{110BA}', '\u{1109A}'), ('\u{1109B}', '\u{110BA}', '\u{1109C}'),
+    ('\u{110A5}', '\u{110BA}', '\u{110AB}'), ('\u{11131}', '\u{11127}', '\u{1112E}'), ('\u{11132}', '\u{11127}', '\u{1112F}'),
+    ('\u{11347}', '\u{1133E}', '\u{1134B}'), ('\u{11347}', '\u{11357}', '\u{1134C}'), ('\u{114B9}', '\u{114B0}', '\u{114BC}'),
+    ('\u{114B9}', '\u{114BA}', '\u{114BB}'), ('\u{114B9}', '\u{114BD}', '\u{114BE}'), ('\u{115B8}', '\u{115AF}', '\u{115BA}'),
+    ('\u{115B9}', '\u{115AF}', '\u{115BB}'), ('\u{11935}', '\u{11930}', '\u{11938}'),
+];
+
+/// Halfwidth katakana and punctuation, U+FF61 onwards, as their fullwidth forms.
+#[rustfmt::skip]
+const HALFWIDTH_KATAKANA: [char; 63] = [
+    '\u{3002}', '\u{300C}', '\u{300D}', '\u{3001}', '\u{30FB}', '\u{30F2}', '\u{30A1}', '\u{30A3}',
+    '\u{30A5}', '\u{30A7}', '\u{30A9}', '\u{30E3}', '\u{30E5}', '\u{30E7}', '\u{30C3}', '\u{30FC}',
+    '\u{30A2}', '\u{30A4}', '\u{30A6}', '\u{30A8}', '\u{30AA}', '\u{30AB}', '\u{30AD}', '\u{30AF}',
+    '\u{30B1}', '\u{30B3}', '\u{30B5}', '\u{30B7}', '\u{30B9}', '\u{30BB}', '\u{30BD}', '\u{30BF}',
+    '\u{30C1}', '\u{30C4}', '\u{30C6}', '\u{30C8}', '\u{30CA}', '\u{30CB}', '\u{30CC}', '\u{30CD}',
+    '\u{30CE}', '\u{30CF}', '\u{30D2}', '\u{30D5}', '\u{30D8}', '\u{30DB}', '\u{30DE}', '\u{30DF}',
+    '\u{30E0}', '\u{30E1}', '\u{30E2}', '\u{30E4}', '\u{30E6}', '\u{30E8}', '\u{30E9}', '\u{30EA}',
+    '\u{30EB}', '\u{30EC}', '\u{30ED}', '\u{30EF}', '\u{30F3}', '\u{3099}', '\u{309A}',
+];
+
+#[cfg(test)]
+mod tests {
+    use super::{fold_for_search, fold_with_offsets, to_nfc};
+    use crate::query::{search, QueryMode, SearchDomain, SearchQuery};
+    use crate::{commit, index_root, init_index_in_memory, RootOptions};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn folds_width_composition_and_case() {
+        assert_eq!(to_nfc("Re\u{301}sume\u{301}"), "Résumé");
+        assert_eq!(to_nfc("\u{1112}\u{1161}\u{11AB}"), "한");
+        assert_eq!(to_nfc("カ\u{3099}"), "ガ");
+        assert_eq!(to_nfc("Ａ"), "Ａ");
+        assert_eq!(fold_for_search("ＲＥＰＯＲＴ　２０２４"), "report 2024");
+        assert_eq!(fold_for_search("ｶﾞｲﾄﾞ"), "ガイド");
+        assert_eq!(fold_for_search("Straße ΟΔΟΣ"), "strasse οδοσ");
+        let (folded, origins) = fold_with_offsets("E\u{301}t");
+        assert_eq!(folded, "ét");
+        assert_eq!(origins, vec![0, 0, 3, 4]);
+    }
+
+    /// File names as different platforms and keyboards produce them, each
+    /// found by the form a user would type.
+    #[test]
+    fn finds_tricky_file_names_by_their_usual_spelling() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        init_index_in_memory().unwrap();
+        let docs = tempdir().unwrap();
+        let corpus = [
+            ("Re\u{301}sume\u{301}.pdf", "résumé"),
+            ("ＲＥＰＯＲＴ２０２４.txt", "report2024"),
+            ("🎉 party plan.txt", "party"),
+            ("カ\u{3099}イト\u{3099}.pdf", "ガイド"),
+            ("ｶﾞｲﾄﾞ.txt", "ガイド"),
+            ("Straße.md", "STRASSE"),
+            (
+                "\u{1112}\u{1161}\u{11AB}\u{1100}\u{1173}\u{11AF}.txt",
+                "한글",
+            ),
+        ];
+        for (name, _) in corpus {
+            fs::write(docs.path().join(name), "x").unwrap();
+        }
+        index_root(docs.path(), &RootOptions::default()).unwrap();
+        commit().unwrap();
+
+        for (name, typed) in corpus {
+            let hits = search(SearchQuery {
+                term: typed.into(),
+                search_in: SearchDomain::Name,
+                ..SearchQuery::default()
+            })
+            .unwrap();
+            assert!(
+                hits.iter().any(|hit| hit.name == to_nfc(name)),
+                "{typed:?} did not find {name:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn finds_code_files_by_the_words_of_their_identifiers() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        init_index_in_memory().unwrap();
+        let docs = tempdir().unwrap();
+        for name in ["MyInvoiceParser_v2.ts", "XMLHttpRequest.js", "invoices.txt"] {
+            fs::write(docs.path().join(name), "").unwrap();
+        }
+        index_root(docs.path(), &RootOptions::default()).unwrap();
+        commit().unwrap();
+
+        let names = |term: &str, mode| {
+            let mut names: Vec<String> = search(SearchQuery {
+                term: term.into(),
+                search_in: SearchDomain::Name,
+                mode,
+                ..SearchQuery::default()
+            })
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.name)
+            .collect();
+            names.sort();
+            names
+        };
+        let words = QueryMode::Words;
+        assert_eq!(
+            names("invoice", words),
+            vec!["MyInvoiceParser_v2.ts", "invoices.txt"]
+        );
+        assert_eq!(names("\"parser v2\"", words), vec!["MyInvoiceParser_v2.ts"]);
+        assert_eq!(
+            names("myinvoiceparser", words),
+            vec!["MyInvoiceParser_v2.ts"]
+        );
+        assert_eq!(names("http request", words), vec!["XMLHttpRequest.js"]);
+        assert_eq!(names("xml", words), vec!["XMLHttpRequest.js"]);
+        assert_eq!(
+            names("inv", QueryMode::Prefix),
+            vec!["MyInvoiceParser_v2.ts", "invoices.txt"]
+        );
+        assert_eq!(
+            super::identifier_words("MyInvoiceParser_v2 report"),
+            vec!["my", "invoice", "parser", "v", "2"]
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use crate::query::HitSnippet;
+
+const ELLIPSIS: &str = "…";
+
+/// Shorten `text` to at most `max_graphemes` user-perceived characters,
+/// ending in `…` when anything was cut. Never splits a character, an
+/// accent from its letter, or an emoji sequence.
+pub fn truncate_graphemes(text: &str, max_graphemes: usize) -> String {
+    let starts = grapheme_starts(text);
+    let count = starts.len() - 1;
+    if count <= max_graphemes {
+        return text.to_string();
+    }
+    if max_graphemes == 0 {
+        return String::new();
+    }
+    format!("{}{ELLIPSIS}", &text[..starts[max_graphemes - 1]])
+}
+
+impl HitSnippet {
+    /// At most `max_graphemes` of the passage, centred on the first match,
+    /// with `…` marking cuts at either end. Highlights are moved to match
+    /// and clipped to the window.
+    pub fn window(&self, max_graphemes: usize) -> HitSnippet {
+        let starts = grapheme_starts(&self.text);
+        let count = starts.len() - 1;
+        if count <= max_graphemes {
+            return self.clone();
+        }
+        if max_graphemes == 0 {
+            return HitSnippet::default();
+        }
+        let grapheme_at = |byte: usize| starts.partition_point(|start| *start <= byte) - 1;
+        let (first, last) = match self.highlighted.first() {
+            Some(range) => (grapheme_at(range.start), grapheme_at(range.end.max(1) - 1)),
+            None => (0, 0),
+        };
+
+        // Room for the text once both ellipses are in; one comes back if
+        // the window touches an end.
+        let width = max_graphemes.saturating_sub(2).max(1);
+        let matched = last + 1 - first;
+        let mut start = first.saturating_sub(width.saturating_sub(matched) / 2);
+        start = start.min(count - width);
+        let mut end = start + width;
+        if start == 0 {
+            end = (end + 1).min(count);
+        } else if end == count {
+            start -= 1;
+        }
+
+        let lead = if start > 0 { ELLIPSIS } else { "" };
+        let trail = if end < count { ELLIPSIS } else { "" };
+        let (from, to) = (starts[start], starts[end]);
+        let text = format!("{lead}{}{trail}", &self.text[from..to]);
+        let highlighted = self
+            .highlighted
+            .iter()
+            .filter_map(|range| {
+                let clipped = range.start.max(from)..range.end.min(to);
+                (clipped.start < clipped.end)
+                    .then(|| clipped.start - from + lead.len()..clipped.end - from + lead.len())
+            })
+            .collect();
+        HitSnippet { text, highlighted }
+    }
+}
+
+/// Byte offsets where each grapheme cluster starts, followed by
+/// `text.len()`. Follows the common extended-grapheme rules: CR LF,
+/// combining marks, variation selectors, emoji modifiers and tags, ZWJ
+/// sequences and regional-indicator (flag) pairs stay together.
+pub(crate) fn grapheme_starts(text: &str) -> Vec<usize> {
+    let mut starts = Vec::with_capacity(text.len() + 1);
+    let mut prev: Option<char> = None;
+    let mut unpaired_flag = false;
+    for (offset, ch) in text.char_indices() {
+        let joins = match prev {
+            None => false,
+            Some('\r') => ch == '\n',
+            Some('\u{200D}') => true,
+            Some(_) if is_regional_indicator(ch) => unpaired_flag,
+            Some(_) => is_extender(ch),
+        };
+        if is_regional_indicator(ch) {
+            unpaired_flag = !joins;
+        } else {
+            unpaired_flag = false;
+        }
+        if !joins {
+            starts.push(offset);
+        }
+        prev = Some(ch);
+    }
+    starts.push(text.len());
+    starts
+}
+
+fn is_regional_indicator(ch: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&ch)
+}
+
+/// Characters that attach to the one before them.
+fn is_extender(ch: char) -> bool {
+    matches!(ch,
+        '\u{0300}'..='\u{036F}'
+        | '\u{0483}'..='\u{0489}'
+        | '\u{0591}'..='\u{05BD}'
+        | '\u{0610}'..='\u{061A}'
+        | '\u{064B}'..='\u{065F}'
+        | '\u{0900}'..='\u{0903}'
+        | '\u{093A}'..='\u{094F}'
+        | '\u{0E31}'
+        | '\u{0E34}'..='\u{0E3A}'
+        | '\u{0E47}'..='\u{0E4E}'
+        | '\u{1160}'..='\u{11FF}'
+        | '\u{1AB0}'..='\u{1AFF}'
+        | '\u{1DC0}'..='\u{1DFF}'
+        | '\u{200C}'..='\u{200D}'
+        | '\u{20D0}'..='\u{20FF}'
+        | '\u{302A}'..='\u{302F}'
+        | '\u{3099}'..='\u{309A}'
+        | '\u{FE00}'..='\u{FE0F}'
+        | '\u{FE20}'..='\u{FE2F}'
+        | '\u{1F3FB}'..='\u{1F3FF}'
+        | '\u{E0020}'..='\u{E007F}'
+        | '\u{E0100}'..='\u{E01EF}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{grapheme_starts, truncate_graphemes};
+    use crate::query::HitSnippet;
+
+    #[test]
+    fn keeps_clusters_whole() {
+        let family = "👩\u{200D}👩\u{200D}👧";
+        let text = format!("e\u{301}{family}🇯🇵🇫🇷👍🏽\r\nx");
+        assert_eq!(grapheme_starts(&text).len() - 1, 7);
+        assert_eq!(truncate_graphemes(&text, 3), format!("e\u{301}{family}…"));
+        assert_eq!(truncate_graphemes("short", 5), "short");
+        assert_eq!(truncate_graphemes("short", 0), "");
+    }
+
+    #[test]
+    fn windows_around_the_first_match() {
+        let text = "Résumé: the quarterly budget was approved after review.";
+        let start = text.find("budget").unwrap();
+        let snippet = HitSnippet {
+            text: text.into(),
+            highlighted: vec![start..start + "budget".len(), text.len() - 7..text.len()],
+        };
+        let window = snippet.window(20);
+        assert_eq!(window.text, "…terly budget was a…");
+        assert_eq!(window.highlighted.len(), 1);
+        assert_eq!(window.text.chars().count(), 20);
+        let marked = &window.text[window.highlighted[0].clone()];
+        assert_eq!(marked, "budget");
+
+        let head = HitSnippet {
+            text: text.into(),
+            highlighted: vec![0.."Ré".len(), 3.."Résumé".len()],
+        }
+        .window(10);
+        assert_eq!(head.text, "Résumé: t…");
+        assert_eq!(&head.text[head.highlighted[0].clone()], "R\u{e9}");
+        assert_eq!(&head.text[head.highlighted[1].clone()], "sumé");
+    }
+}
@@ -0,0 +1,507 @@
+use crate::indexer;
+use crate::query::{
+    self, DefaultOperator, EntryKind, QueryMode, SearchDomain, SearchQuery, ShouldMatch,
+};
+use crate::rewrite;
+use crate::schema::name_words;
+use crate::term_filters::{extract_term_filters, TermFilters};
+use crate::wildcards::extract_wildcards;
+use anyhow::{Context, Result};
+use std::fmt;
+use tantivy::query::QueryParserError;
+use tantivy::query_grammar::{parse_query_lenient, Delimiter, Occur, UserInputAst, UserInputLeaf};
+
+/// What a query string means, worked out without running it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedQueryDescription {
+    /// The query string after the installed rewriter.
+    pub term: String,
+    /// Fields bare words are matched against.
+    pub fields: Vec<String>,
+    pub clauses: Vec<QueryClause>,
+    /// Restrictions and ranking tweaks from the options, one line each.
+    pub filters: Vec<String>,
+    /// The whole query in words, e.g.
+    /// `“invoice” and not “draft” in name or content`.
+    pub interpretation: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryClause {
+    pub requirement: ClauseRequirement,
+    /// Field named in the query (`name:report`); `None` searches the
+    /// default fields.
+    pub field: Option<String>,
+    pub kind: ClauseKind,
+    /// The word, phrase or prefix; `a TO b` for ranges, space-separated
+    /// elements for sets.
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClauseRequirement {
+    Required,
+    /// Only ranks matches higher (`a OR b`).
+    Optional,
+    Excluded,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClauseKind {
+    Word,
+    /// Quoted words; `slop` is how many words may fall in between.
+    Phrase {
+        slop: u32,
+    },
+    Prefix,
+    Range,
+    Set,
+    Exists,
+    All,
+    /// The whole term as a regular expression ([`QueryMode::Regex`]).
+    Regex,
+    /// A word with `*` or `?` in it, such as `rep*rt`.
+    Wildcard,
+}
+
+/// Why a query was rejected. Returned inside the `anyhow::Error` from
+/// [`validate_query`]; downcast to it to place an inline marker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuerySyntaxError {
+    pub message: String,
+    /// Byte offset into the checked query string, when the parser knows it.
+    pub position: Option<usize>,
+}
+
+impl fmt::Display for QuerySyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.position {
+            Some(position) => write!(f, "{} (at byte {position})", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for QuerySyntaxError {}
+
+/// Check `term` the way a search with `options` would parse it (options'
+/// own `term` is ignored) and describe it, without executing anything.
+/// Needs an open index for its field names and tokenizers.
+pub fn validate_query(term: &str, options: &SearchQuery) -> Result<ParsedQueryDescription> {
+    let rewritten = match options.mode {
+        QueryMode::Words => rewrite::rewrite_query(term.trim()),
+        QueryMode::Regex | QueryMode::Prefix => term.trim().to_string(),
+    };
+    let extracted = match options.mode {
+        QueryMode::Words => extract_term_filters(&rewritten).map_err(|err| QuerySyntaxError {
+            message: format!("{err:#}"),
+            position: None,
+        })?,
+        QueryMode::Regex | QueryMode::Prefix => TermFilters::plain(rewritten),
+    };
+    let trimmed = extracted.term.trim();
+    let reader = indexer::reader().context("reader not available")?;
+    let fields = indexer::fields()?;
+    let index = reader.searcher().index().clone();
+    let schema = index.schema();
+    let settings = indexer::settings()?;
+    query::check_limits(options, &settings.query_limits)?;
+    let searched = match (options.mode, options.search_in) {
+        (QueryMode::Words, domain) => query::default_fields(&fields, domain),
+        (QueryMode::Regex, SearchDomain::Both) => vec![fields.name_raw, fields.path],
+        (QueryMode::Regex, _) => vec![fields.name_raw],
+        (QueryMode::Prefix, _) => vec![fields.name_prefix],
+    };
+    let field_names: Vec<String> = searched
+        .into_iter()
+        .map(|field| schema.get_field_name(field).to_string())
+        .collect();
+
+    let mut clauses = Vec::new();
+    if options.mode == QueryMode::Prefix {
+        clauses.extend(name_words(trimmed).into_iter().map(|word| QueryClause {
+            requirement: ClauseRequirement::Required,
+            field: None,
+            kind: ClauseKind::Prefix,
+            text: word,
+        }));
+    } else if !trimmed.is_empty() && options.mode == QueryMode::Regex {
+        query::regex_query(&fields, trimmed, options.search_in).map_err(|err| {
+            QuerySyntaxError {
+                message: format!("{err:#}"),
+                position: None,
+            }
+        })?;
+        clauses.push(QueryClause {
+            requirement: ClauseRequirement::Required,
+            field: None,
+            kind: ClauseKind::Regex,
+            text: trimmed.to_string(),
+        });
+    } else if !trimmed.is_empty() {
+        // Wildcard words are searched apart from the parsed rest.
+        let (text, wildcards) = extract_wildcards(trimmed);
+        let parser = query::query_parser(
+            &index,
+            &fields,
+            options.search_in,
+            options.default_operator,
+            &settings.ranking,
+        );
+        let parsed = match parser.parse_query(&text) {
+            // Searches run a query of only exclusions against everything else.
+            Err(QueryParserError::AllButQueryForbidden) => Ok(()),
+            _ if text.is_empty() => Ok(()),
+            other => other.map(drop),
+        };
+        if let Err(err) = parsed {
+            let (_, errors) = parse_query_lenient(&text);
+            let syntax = match errors.into_iter().next() {
+                Some(first) => QuerySyntaxError {
+                    message: first.message,
+                    position: Some(first.pos),
+                },
+                None => QuerySyntaxError {
+                    message: err.to_string(),
+                    position: None,
+                },
+            };
+            return Err(syntax.into());
+        }
+        let unmarked = match options.default_operator {
+            DefaultOperator::And => Occur::Must,
+            DefaultOperator::Or => Occur::Should,
+        };
+        if !text.is_empty() {
+            let (ast, _) = parse_query_lenient(&text);
+            collect_clauses(&ast, Occur::Must, unmarked, &mut clauses);
+        }
+        for wildcard in wildcards {
+            wildcard.pattern().map_err(|err| QuerySyntaxError {
+                message: format!("{err:#}"),
+                position: trimmed.find(&wildcard.word),
+            })?;
+            let occur = match wildcard.required {
+                Some(true) => Occur::Must,
+                Some(false) => Occur::MustNot,
+                None => unmarked,
+            };
+            clauses.push(QueryClause {
+                requirement: requirement(occur),
+                field: None,
+                kind: ClauseKind::Wildcard,
+                text: wildcard.word,
+            });
+        }
+    }
+
+    // `modified:` and `size:` filters narrow the options' own bounds.
+    let mut narrowed = options.clone();
+    narrowed.mtime_min = options
+        .mtime_min
+        .into_iter()
+        .chain(extracted.mtime_min)
+        .max();
+    narrowed.mtime_max = options
+        .mtime_max
+        .into_iter()
+        .chain(extracted.mtime_max)
+        .min();
+    narrowed.size_min = options.size_min.into_iter().chain(extracted.size_min).max();
+    narrowed.size_max = options.size_max.into_iter().chain(extracted.size_max).min();
+    let filters = describe_filters(&narrowed)?;
+    let domain = match options.mode {
+        QueryMode::Prefix => SearchDomain::Name,
+        _ => options.search_in,
+    };
+    let interpretation = interpret(&clauses, domain, &filters);
+    Ok(ParsedQueryDescription {
+        term: trimmed.to_string(),
+        fields: field_names,
+        clauses,
+        filters,
+        interpretation,
+    })
+}
+
+/// Flatten nested groups, combining each clause's occurrence with its
+/// group's. Unmarked words get `unmarked`, the query's default operator.
+fn collect_clauses(ast: &UserInputAst, parent: Occur, unmarked: Occur, out: &mut Vec<QueryClause>) {
+    match ast {
+        UserInputAst::Clause(children) => {
+            for (occur, child) in children {
+                let occur = Occur::compose(parent, occur.unwrap_or(unmarked));
+                collect_clauses(child, occur, unmarked, out);
+            }
+        }
+        UserInputAst::Boost(inner, _) => collect_clauses(inner, parent, unmarked, out),
+        UserInputAst::Leaf(leaf) => {
+            let requirement = requirement(parent);
+            let (field, kind, text) = match leaf.as_ref() {
+                UserInputLeaf::Literal(literal) => {
+                    let kind = match literal.delimiter {
+                        Delimiter::None if literal.prefix => ClauseKind::Prefix,
+                        Delimiter::None => ClauseKind::Word,
+                        _ => ClauseKind::Phrase { slop: literal.slop },
+                    };
+                    (literal.field_name.clone(), kind, literal.phrase.clone())
+                }
+                UserInputLeaf::Range {
+                    field,
+                    lower,
+                    upper,
+                } => (
+                    field.clone(),
+                    ClauseKind::Range,
+                    format!("{} TO {}", lower.term_str(), upper.term_str()),
+                ),
+                UserInputLeaf::Set { field, elements } => {
+                    (field.clone(), ClauseKind::Set, elements.join(" "))
+                }
+                UserInputLeaf::Exists { field } => {
+                    (Some(field.clone()), ClauseKind::Exists, String::new())
+                }
+                UserInputLeaf::All => (None, ClauseKind::All, String::new()),
+            };
+            out.push(QueryClause {
+                requirement,
+                field,
+                kind,
+                text,
+            });
+        }
+    }
+}
+
+fn requirement(occur: Occur) -> ClauseRequirement {
+    match occur {
+        Occur::Must => ClauseRequirement::Required,
+        Occur::Should => ClauseRequirement::Optional,
+        Occur::MustNot => ClauseRequirement::Excluded,
+    }
+}
+
+fn describe_filters(options: &SearchQuery) -> Result<Vec<String>> {
+    let mut filters = Vec::new();
+    query::build_glob_set(&options.path_globs, "glob").map_err(|err| QuerySyntaxError {
+        message: format!("{err:#}"),
+        position: None,
+    })?;
+    let globs: Vec<&str> = options
+        .path_globs
+        .iter()
+        .map(|glob| glob.trim())
+        .filter(|glob| !glob.is_empty())
+        .collect();
+    if !globs.is_empty() {
+        filters.push(format!("path matches {}", globs.join(" or ")));
+    }
+    query::build_glob_set(&options.exclude_globs, "exclude").map_err(|err| QuerySyntaxError {
+        message: format!("{err:#}"),
+        position: None,
+    })?;
+    for glob in options
+        .exclude_globs
+        .iter()
+        .filter(|glob| !glob.trim().is_empty())
+    {
+        filters.push(format!("path doesn't match {}", glob.trim()));
+    }
+    query::build_penalties(&options.path_penalties).map_err(|err| QuerySyntaxError {
+        message: format!("{err:#}"),
+        position: None,
+    })?;
+    for penalty in &options.path_penalties {
+        filters.push(format!(
+            "paths matching {} rank ×{}",
+            penalty.pattern, penalty.factor
+        ));
+    }
+    if let Some(paths) = &options.within {
+        filters.push(format!("only within {} earlier results", paths.len()));
+    }
+    match options.kind {
+        Some(EntryKind::File) => filters.push("files only".to_string()),
+        Some(EntryKind::Folder) => filters.push("folders only".to_string()),
+        None => {}
+    }
+    if options.case_sensitive && options.mode != QueryMode::Regex {
+        filters.push("names contain the words with the case typed".to_string());
+    }
+    if options.mode == QueryMode::Words {
+        let counts = [
+            ("name", options.min_should_match.name),
+            ("content", options.min_should_match.content),
+        ];
+        for (field, should_match) in counts {
+            match should_match {
+                Some(ShouldMatch::Words(words)) => {
+                    filters.push(format!("{field} needs {words} of the plain words"))
+                }
+                Some(ShouldMatch::Percent(percent)) => {
+                    filters.push(format!("{field} needs {percent}% of the plain words"))
+                }
+                None => {}
+            }
+        }
+    }
+    if let Some(boost) = options.recency {
+        filters.push(format!(
+            "recently modified files rank higher (half-life {} days)",
+            boost.half_life_days
+        ));
+    }
+    for folder in &options.priority_folders {
+        filters.push(format!("files under {} rank higher", folder.path));
+    }
+    match (options.mtime_min, options.mtime_max) {
+        (Some(min), Some(max)) => filters.push(format!("modified between {min} and {max}")),
+        (Some(min), None) => filters.push(format!("modified at or after {min}")),
+        (None, Some(max)) => filters.push(format!("modified at or before {max}")),
+        (None, None) => {}
+    }
+    match (options.size_min, options.size_max) {
+        (Some(min), Some(max)) => filters.push(format!("between {min} and {max} bytes")),
+        (Some(min), None) => filters.push(format!("at least {min} bytes")),
+        (None, Some(max)) => filters.push(format!("at most {max} bytes")),
+        (None, None) => {}
+    }
+    if !options.extensions.is_empty() {
+        filters.push(format!("extension is {}", options.extensions.join(" or ")));
+    }
+    if !options.roots.is_empty() {
+        let roots: Vec<String> = options
+            .roots
+            .iter()
+            .map(|root| root.display().to_string())
+            .collect();
+        filters.push(format!("files under {}", roots.join(" or ")));
+    }
+    if let Some(distance) = options.fuzzy.filter(|distance| *distance > 0) {
+        filters.push(format!(
+            "names within {} typo(s) also match",
+            distance.min(2)
+        ));
+    }
+    Ok(filters)
+}
+
+fn interpret(clauses: &[QueryClause], domain: SearchDomain, filters: &[String]) -> String {
+    if clauses.is_empty() {
+        return "empty query, matches nothing".to_string();
+    }
+    let parts: Vec<String> = clauses
+        .iter()
+        .map(|clause| {
+            let what = match clause.kind {
+                ClauseKind::Word => format!("“{}”", clause.text),
+                ClauseKind::Phrase { slop: 0 } => format!("the phrase “{}”", clause.text),
+                ClauseKind::Phrase { slop } => {
+                    format!("“{}” within {slop} words", clause.text)
+                }
+                ClauseKind::Prefix => format!("words starting with “{}”", clause.text),
+                ClauseKind::Range => clause.text.clone(),
+                ClauseKind::Set => format!("any of “{}”", clause.text),
+                ClauseKind::Exists => "any value".to_string(),
+                ClauseKind::All => "everything".to_string(),
+                ClauseKind::Regex => format!("the pattern /{}/", clause.text),
+                ClauseKind::Wildcard => format!("words like “{}”", clause.text),
+            };
+            let what = match &clause.field {
+                Some(field) => format!("{field}: {what}"),
+                None => what,
+            };
+            match clause.requirement {
+                ClauseRequirement::Required => what,
+                ClauseRequirement::Optional => format!("maybe {what}"),
+                ClauseRequirement::Excluded => format!("not {what}"),
+            }
+        })
+        .collect();
+    let scope = match domain {
+        SearchDomain::Name => "name",
+        SearchDomain::Content => "content",
+        SearchDomain::Both if clauses.iter().any(|c| c.kind == ClauseKind::Regex) => "name or path",
+        SearchDomain::Both => "name or content",
+    };
+    let mut text = format!("{} in {scope}", parts.join(" and "));
+    for filter in filters {
+        text.push_str("; ");
+        text.push_str(filter);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_query, ClauseKind, ClauseRequirement, QuerySyntaxError};
+    use crate::init_index_in_memory;
+    use crate::query::{QueryMode, SearchDomain, SearchQuery};
+
+    #[test]
+    fn describes_clauses_and_reports_syntax_errors() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        init_index_in_memory().unwrap();
+        let options = SearchQuery {
+            search_in: SearchDomain::Name,
+            path_globs: vec!["/Users/me/**".into()],
+            ..SearchQuery::default()
+        };
+
+        let description = validate_query("invoice -draft \"tax year\"", &options).unwrap();
+        assert_eq!(
+            description.fields,
+            vec!["name", "name_parts", "alias_target"]
+        );
+        let clauses: Vec<_> = description
+            .clauses
+            .iter()
+            .map(|clause| (clause.requirement, clause.kind, clause.text.as_str()))
+            .collect();
+        assert_eq!(
+            clauses,
+            vec![
+                (ClauseRequirement::Required, ClauseKind::Word, "invoice"),
+                (ClauseRequirement::Excluded, ClauseKind::Word, "draft"),
+                (
+                    ClauseRequirement::Required,
+                    ClauseKind::Phrase { slop: 0 },
+                    "tax year"
+                ),
+            ]
+        );
+        assert_eq!(
+            description.interpretation,
+            "“invoice” and not “draft” and the phrase “tax year” in name; \
+             path matches /Users/me/**"
+        );
+
+        let err = validate_query("nosuchfield:x", &options).unwrap_err();
+        assert!(err.downcast_ref::<QuerySyntaxError>().is_some());
+        assert!(validate_query("name:[a TO", &options).is_err());
+        let bad_glob = SearchQuery {
+            path_globs: vec!["[".into()],
+            ..SearchQuery::default()
+        };
+        assert!(validate_query("x", &bad_glob).is_err());
+
+        let description = validate_query("rep*rt -*draft*", &SearchQuery::default()).unwrap();
+        assert_eq!(
+            description.interpretation,
+            "words like “rep*rt” and not words like “*draft*” in name or content"
+        );
+        assert!(validate_query("*a*", &SearchQuery::default()).is_err());
+
+        let regex = SearchQuery {
+            mode: QueryMode::Regex,
+            ..SearchQuery::default()
+        };
+        let description = validate_query(r"^report_\d{4}$", &regex).unwrap();
+        assert_eq!(description.fields, vec!["name_raw", "path"]);
+        assert_eq!(
+            description.interpretation,
+            r"the pattern /^report_\d{4}$/ in name or path"
+        );
+        assert!(validate_query("report(", &regex).is_err());
+    }
+}
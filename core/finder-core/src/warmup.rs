@@ -0,0 +1,143 @@
+use crate::cancel::CancelToken;
+use crate::indexer;
+use crate::query::{search, QueryMode, SearchDomain, SearchQuery};
+use anyhow::{Context, Result};
+use std::time::{Duration, Instant};
+use tantivy::SegmentReader;
+
+/// Terms or documents read between checks of the cancel flag and budget.
+const CHECK_EVERY: u64 = 4096;
+
+/// Short searches run last, one per name query path, so the parser,
+/// collectors and doc store are warm too.
+const WARM_UP_QUERIES: &[(&str, QueryMode)] = &[("e", QueryMode::Words), ("a", QueryMode::Prefix)];
+
+/// Name fields whose term dictionaries a first search reads.
+const NAME_FIELDS: &[&str] = &["name", "name_raw", "name_prefix", "name_parts"];
+
+/// What [`warm_up`] got through.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WarmUpReport {
+    /// Segments whose name terms and fast fields were read in full.
+    pub segments: usize,
+    pub terms_read: u64,
+    pub docs_read: u64,
+    pub queries_run: usize,
+    /// False when `cancel` or the budget stopped the warm-up early.
+    pub completed: bool,
+}
+
+/// Read the name fields' term dictionaries and the fast fields searches
+/// sort and filter on, then run a few short queries, so the first search
+/// after launch doesn't pay for a cold page cache. Stops once `cancel` is
+/// set or `budget` is spent, keeping whatever it warmed; run it on a
+/// background thread right after opening the index.
+pub fn warm_up(cancel: &CancelToken, budget: Duration) -> Result<WarmUpReport> {
+    let deadline = Instant::now() + budget;
+    let stop = || cancel.is_cancelled() || Instant::now() >= deadline;
+    let searcher = indexer::reader()
+        .context("reader not available")?
+        .searcher();
+    let mut report = WarmUpReport::default();
+    for segment in searcher.segment_readers() {
+        if !warm_segment(segment, &stop, &mut report)? {
+            return Ok(report);
+        }
+        report.segments += 1;
+    }
+    for (term, mode) in WARM_UP_QUERIES {
+        if stop() {
+            return Ok(report);
+        }
+        search(SearchQuery {
+            term: term.to_string(),
+            search_in: SearchDomain::Name,
+            mode: *mode,
+            limit: 10,
+            time_budget: Some(deadline.saturating_duration_since(Instant::now())),
+            ..SearchQuery::default()
+        })?;
+        report.queries_run += 1;
+    }
+    report.completed = !stop();
+    Ok(report)
+}
+
+/// Read one segment's name terms and fast fields; false when stopped.
+fn warm_segment(
+    segment: &SegmentReader,
+    stop: &impl Fn() -> bool,
+    report: &mut WarmUpReport,
+) -> Result<bool> {
+    let schema = segment.schema();
+    for name in NAME_FIELDS {
+        let field = schema.get_field(name)?;
+        let inverted = segment
+            .inverted_index(field)
+            .with_context(|| format!("failed opening {name} terms"))?;
+        let mut stream = inverted.terms().stream()?;
+        while stream.advance() {
+            report.terms_read += 1;
+            if report.terms_read.is_multiple_of(CHECK_EVERY) && stop() {
+                return Ok(false);
+            }
+        }
+    }
+
+    let fast = segment.fast_fields();
+    let mtimes = fast.i64("mtime")?;
+    let sizes = fast.u64("size")?;
+    let paths = fast.str("path")?;
+    let names = fast.str("name_raw")?;
+    for doc in 0..segment.max_doc() {
+        mtimes.first(doc);
+        sizes.first(doc);
+        for column in [&paths, &names].into_iter().flatten() {
+            column.term_ords(doc).next();
+        }
+        report.docs_read += 1;
+        if report.docs_read.is_multiple_of(CHECK_EVERY) && stop() {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::warm_up;
+    use crate::cancel::CancelToken;
+    use crate::scanner::FileMeta;
+    use crate::{add_or_update_file, commit, init_index_in_memory};
+    use std::time::Duration;
+
+    #[test]
+    fn warms_every_segment_unless_cancelled() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        init_index_in_memory().unwrap();
+        for (name, inode) in [("alpha.txt", 1), ("beta.txt", 2)] {
+            let meta = FileMeta {
+                path: format!("/notes/{name}"),
+                name: name.into(),
+                inode,
+                dev: 1,
+                ..FileMeta::default()
+            };
+            add_or_update_file(meta, None, false).unwrap();
+        }
+        commit().unwrap();
+
+        let report = warm_up(&CancelToken::new(), Duration::from_secs(60)).unwrap();
+        assert!(report.completed);
+        assert_eq!(report.segments, 1);
+        assert_eq!(report.docs_read, 2);
+        assert!(report.terms_read > 0);
+        assert_eq!(report.queries_run, 2);
+
+        let cancelled = CancelToken::new();
+        cancelled.cancel();
+        let report = warm_up(&cancelled, Duration::from_secs(60)).unwrap();
+        assert!(!report.completed);
+        assert_eq!(report.queries_run, 0);
+    }
+}
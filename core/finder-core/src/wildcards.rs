@@ -0,0 +1,96 @@
+use crate::textnorm::fold_for_search;
+use anyhow::{bail, Result};
+use regex::escape;
+
+/// Literal characters a wildcard word needs, so `*a*` can't walk the
+/// whole term dictionary.
+const MIN_LITERAL_CHARS: usize = 2;
+
+/// A word with `*` (any run of characters) or `?` (one character) in it,
+/// such as `rep*rt` or `*draft*`, matched against single indexed words.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Wildcard {
+    /// The word as typed, without its `+` or `-`.
+    pub word: String,
+    /// `Some(true)` for `+word`, `Some(false)` for `-word`.
+    pub required: Option<bool>,
+}
+
+impl Wildcard {
+    /// The word folded as names and contents are indexed, as a pattern for
+    /// the term dictionary (anchored at both ends).
+    pub fn pattern(&self) -> Result<String> {
+        let folded = fold_for_search(&self.word);
+        let literals = folded.chars().filter(|ch| !matches!(ch, '*' | '?')).count();
+        if literals < MIN_LITERAL_CHARS {
+            bail!(
+                "wildcard {:?} needs at least {MIN_LITERAL_CHARS} letters or digits",
+                self.word
+            );
+        }
+        let mut pattern = String::with_capacity(folded.len() * 2);
+        let mut previous = None;
+        for ch in folded.chars() {
+            match ch {
+                // `**` means no more than `*`.
+                '*' if previous == Some('*') => {}
+                '*' => pattern.push_str(".*"),
+                '?' => pattern.push('.'),
+                ch => pattern.push_str(&escape(ch.encode_utf8(&mut [0; 4]))),
+            }
+            previous = Some(ch);
+        }
+        Ok(pattern)
+    }
+}
+
+/// Take wildcard words out of `term`, leaving the rest for the query
+/// parser, which reads a leading `*` as a syntax error. Quoted text and
+/// field clauses such as `name:rep*` are left where they are.
+pub(crate) fn extract_wildcards(term: &str) -> (String, Vec<Wildcard>) {
+    let mut rest = Vec::new();
+    let mut wildcards = Vec::new();
+    let mut quoted = false;
+    for word in term.split_whitespace() {
+        let starts_quoted = quoted;
+        quoted ^= word.matches('"').count() % 2 == 1;
+        let (required, bare) = match word.strip_prefix(['+', '-']) {
+            Some(bare) => (Some(word.starts_with('+')), bare),
+            None => (None, word),
+        };
+        let is_wildcard = !starts_quoted
+            && bare.contains(['*', '?'])
+            && !bare.contains(['"', ':', '(', ')', '[', ']', '{', '}', '\\']);
+        if is_wildcard {
+            wildcards.push(Wildcard {
+                word: bare.to_string(),
+                required,
+            });
+        } else {
+            rest.push(word);
+        }
+    }
+    (rest.join(" "), wildcards)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_out_wildcard_words_and_anchors_their_patterns() {
+        let (rest, wildcards) = extract_wildcards(r#"notes -*draft* "a*b c?" name:rep*"#);
+        assert_eq!(rest, r#"notes "a*b c?" name:rep*"#);
+        assert_eq!(wildcards.len(), 1);
+        assert_eq!(wildcards[0].word, "*draft*");
+        assert_eq!(wildcards[0].required, Some(false));
+        assert_eq!(wildcards[0].pattern().unwrap(), ".*draft.*");
+        let single = |word: &str| Wildcard {
+            word: word.to_string(),
+            required: None,
+        };
+        assert_eq!(single("Rep?rt**").pattern().unwrap(), "rep.rt.*");
+        assert_eq!(single("v1.2*").pattern().unwrap(), r"v1\.2.*");
+        assert!(single("*a*").pattern().is_err());
+    }
+}
@@ -0,0 +1,322 @@
+use crate::extract_structured::{read_structured_text, StructuredExtraction, StructuredFormat};
+use anyhow::{anyhow, bail, Context, Result};
+use once_cell::sync::Lazy;
+use std::env;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{self, Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+/// Argument that makes a program calling [`run_extract_worker_if_requested`]
+/// serve extraction requests instead of starting normally.
+pub const EXTRACT_WORKER_ARG: &str = "--finder-extract-worker";
+
+/// Ends the line a worker writes before it serves; anything it prints
+/// earlier (a banner, a test harness header) is skipped.
+const READY_LINE: &str = "finder-extract-worker ready";
+
+const DEFAULT_WORKER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Run JSON, YAML and plist parsing in a child process, so a file that
+/// crashes or hangs a parser only costs the child; the next file gets a
+/// fresh one. Set through [`RootOptions::extract_worker`](crate::RootOptions).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtractWorker {
+    /// Spawned as the worker; it must call [`run_extract_worker_if_requested`]
+    /// first thing in `main`.
+    pub program: PathBuf,
+    pub args: Vec<String>,
+    /// Longest one file may take before the worker is killed.
+    pub timeout: Duration,
+}
+
+impl ExtractWorker {
+    /// This program, started with [`EXTRACT_WORKER_ARG`].
+    pub fn current_exe() -> Result<Self> {
+        Ok(Self {
+            program: env::current_exe().context("failed locating the running program")?,
+            args: vec![EXTRACT_WORKER_ARG.to_string()],
+            timeout: DEFAULT_WORKER_TIMEOUT,
+        })
+    }
+}
+
+/// When this process was started as an extraction worker, serve requests
+/// on stdin until it closes, then exit; otherwise return at once. Call it
+/// at the top of `main` in programs named by [`ExtractWorker::program`].
+pub fn run_extract_worker_if_requested() {
+    if !env::args().any(|arg| arg == EXTRACT_WORKER_ARG) {
+        return;
+    }
+    let status = match serve(io::stdin().lock(), io::stdout().lock()) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("[worker] {err:#}");
+            1
+        }
+    };
+    process::exit(status);
+}
+
+/// Requests are `format<TAB>size_limit<TAB>path` lines. Each answer is a
+/// `parsed<TAB>bytes_read<TAB>length` line followed by `length` bytes of
+/// content, `unparsed`, or `failed<TAB>message`.
+fn serve(input: impl BufRead, mut output: impl Write) -> Result<()> {
+    writeln!(output, "{READY_LINE}")?;
+    output.flush()?;
+    for line in input.lines() {
+        let line = line.context("failed reading extraction request")?;
+        let mut parts = line.splitn(3, '\t');
+        let (Some(format), Some(limit), Some(path)) = (parts.next(), parts.next(), parts.next())
+        else {
+            bail!("malformed extraction request: {line:?}");
+        };
+        let format = format_from_name(format)?;
+        let limit: usize = limit.parse().context("malformed size limit")?;
+        #[cfg(test)]
+        tests::misbehave(path);
+        match read_structured_text(path, format, limit) {
+            Ok(Some(extraction)) => {
+                let content = extraction.content.as_bytes();
+                writeln!(
+                    output,
+                    "parsed\t{}\t{}",
+                    extraction.bytes_read,
+                    content.len()
+                )?;
+                output.write_all(content)?;
+            }
+            Ok(None) => writeln!(output, "unparsed")?,
+            Err(err) => writeln!(output, "failed\t{}", format!("{err:#}").replace('\n', " "))?,
+        }
+        output.flush()?;
+    }
+    Ok(())
+}
+
+fn format_name(format: StructuredFormat) -> &'static str {
+    match format {
+        StructuredFormat::Json => "json",
+        StructuredFormat::Yaml => "yaml",
+        StructuredFormat::Plist => "plist",
+    }
+}
+
+fn format_from_name(name: &str) -> Result<StructuredFormat> {
+    match name {
+        "json" => Ok(StructuredFormat::Json),
+        "yaml" => Ok(StructuredFormat::Yaml),
+        "plist" => Ok(StructuredFormat::Plist),
+        other => bail!("unknown structured format {other:?}"),
+    }
+}
+
+type Answer = Result<Option<StructuredExtraction>>;
+
+/// A spawned worker, its request pipe, and the answers its reader thread
+/// has parsed off stdout.
+struct RunningWorker {
+    config: ExtractWorker,
+    child: Child,
+    stdin: ChildStdin,
+    answers: Receiver<Answer>,
+}
+
+impl Drop for RunningWorker {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// One worker, reused across files and replaced after a crash or timeout.
+static WORKER: Lazy<Mutex<Option<RunningWorker>>> = Lazy::new(|| Mutex::new(None));
+
+/// [`read_structured_text`] run by the worker `config` describes. The
+/// outer error means the worker crashed or took over its timeout, and
+/// leaves no worker behind.
+pub(crate) fn read_structured_text_isolated(
+    config: &ExtractWorker,
+    path: &str,
+    format: StructuredFormat,
+    size_limit: usize,
+) -> Result<Answer> {
+    if path.contains(['\n', '\t']) {
+        // Not expressible in a request line; parse in process instead.
+        return Ok(read_structured_text(path, format, size_limit));
+    }
+    let mut guard = WORKER.lock().unwrap();
+    if guard
+        .as_ref()
+        .is_some_and(|worker| worker.config != *config)
+    {
+        *guard = None;
+    }
+    if guard.is_none() {
+        *guard = Some(spawn(config)?);
+    }
+    let worker = guard.as_mut().expect("worker spawned above");
+    let request = format!("{}\t{size_limit}\t{path}\n", format_name(format));
+    let answer = worker
+        .stdin
+        .write_all(request.as_bytes())
+        .and_then(|()| worker.stdin.flush())
+        .map_err(anyhow::Error::from)
+        .and_then(|()| match worker.answers.recv_timeout(config.timeout) {
+            Ok(answer) => Ok(answer),
+            Err(RecvTimeoutError::Timeout) => Err(anyhow!(
+                "extraction worker took over {:?} on {path}",
+                config.timeout
+            )),
+            Err(RecvTimeoutError::Disconnected) => {
+                Err(anyhow!("extraction worker died reading {path}"))
+            }
+        });
+    match answer {
+        Ok(answer) => Ok(answer),
+        Err(err) => {
+            // Killed on drop; the next file starts a fresh one.
+            *guard = None;
+            Err(err)
+        }
+    }
+}
+
+fn spawn(config: &ExtractWorker) -> Result<RunningWorker> {
+    let mut child = Command::new(&config.program)
+        .args(&config.args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .with_context(|| format!("failed starting {}", config.program.display()))?;
+    let stdin = child.stdin.take().expect("piped stdin");
+    let stdout = child.stdout.take().expect("piped stdout");
+    let (ready_tx, ready) = mpsc::channel();
+    let (answers_tx, answers) = mpsc::channel();
+    thread::spawn(move || {
+        let mut stdout = BufReader::new(stdout);
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match stdout.read_line(&mut line) {
+                Ok(0) | Err(_) => return,
+                Ok(_) if line.trim_end().ends_with(READY_LINE) => break,
+                Ok(_) => {}
+            }
+        }
+        let _ = ready_tx.send(());
+        while let Some(answer) = read_answer(&mut stdout) {
+            if answers_tx.send(answer).is_err() {
+                return;
+            }
+        }
+    });
+    let worker = RunningWorker {
+        config: config.clone(),
+        child,
+        stdin,
+        answers,
+    };
+    match ready.recv_timeout(config.timeout) {
+        Ok(()) => Ok(worker),
+        Err(_) => bail!(
+            "{} didn't start as an extraction worker",
+            config.program.display()
+        ),
+    }
+}
+
+/// The next answer on `stdout`, or `None` once the worker is gone.
+fn read_answer(stdout: &mut impl BufRead) -> Option<Answer> {
+    let mut line = String::new();
+    if stdout.read_line(&mut line).ok()? == 0 {
+        return None;
+    }
+    let mut parts = line.trim_end_matches('\n').splitn(3, '\t');
+    match parts.next()? {
+        "parsed" => {
+            let bytes_read = parts.next()?.parse().ok()?;
+            let length: usize = parts.next()?.parse().ok()?;
+            let mut content = vec![0; length];
+            stdout.read_exact(&mut content).ok()?;
+            let content = String::from_utf8(content).ok()?;
+            Some(Ok(Some(StructuredExtraction {
+                content,
+                bytes_read,
+            })))
+        }
+        "unparsed" => Some(Ok(None)),
+        "failed" => Some(Err(anyhow!(parts.next().unwrap_or("").to_string()))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_structured_text_isolated, run_extract_worker_if_requested, ExtractWorker};
+    use crate::extract_structured::StructuredFormat;
+    use std::fs;
+    use std::path::Path;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    /// Files that make the worker crash or hang, standing in for parser bugs.
+    pub(super) fn misbehave(path: &str) {
+        match Path::new(path).file_name().and_then(|name| name.to_str()) {
+            Some("abort.json") => std::process::abort(),
+            Some("hang.json") => std::thread::sleep(Duration::from_secs(60)),
+            _ => {}
+        }
+    }
+
+    /// Entry point of the worker the other test spawns from this binary;
+    /// returns at once in a normal test run.
+    #[test]
+    fn child_worker() {
+        run_extract_worker_if_requested();
+    }
+
+    #[test]
+    fn worker_survives_crashing_and_hanging_files() {
+        let _guard = crate::TEST_MUTEX.lock().unwrap();
+        let dir = tempdir().unwrap();
+        for name in ["good.json", "abort.json", "hang.json"] {
+            fs::write(dir.path().join(name), r#"{"city": "Lisbon"}"#).unwrap();
+        }
+        let config = ExtractWorker {
+            args: [
+                "worker::tests::child_worker",
+                "--exact",
+                "--nocapture",
+                "--",
+                super::EXTRACT_WORKER_ARG,
+            ]
+            .map(String::from)
+            .to_vec(),
+            timeout: Duration::from_secs(2),
+            ..ExtractWorker::current_exe().unwrap()
+        };
+        let read = |name: &str| {
+            let path = dir.path().join(name);
+            read_structured_text_isolated(
+                &config,
+                path.to_str().unwrap(),
+                StructuredFormat::Json,
+                1 << 20,
+            )
+        };
+
+        let parsed = read("good.json").unwrap().unwrap().unwrap();
+        assert_eq!(parsed.content.trim(), "city: Lisbon");
+        assert!(read("missing.json").unwrap().is_err());
+        assert!(read("abort.json").is_err());
+        assert!(read("good.json").unwrap().unwrap().is_some());
+        let hung = read("hang.json").unwrap_err();
+        assert!(hung.to_string().contains("took over"), "{hung:#}");
+        assert!(read("good.json").unwrap().unwrap().is_some());
+    }
+}
@@ -96,6 +96,9 @@ final class IndexCoordinator: ObservableObject {
     }
 
     static func defaultIndexDirectory() -> URL {
+        if let path = FinderCore.defaultIndexDirectory() {
+            return URL(fileURLWithPath: path, isDirectory: true)
+        }
         let fm = FileManager.default
         let base = fm.urls(for: .applicationSupportDirectory, in: .userDomainMask).first ?? fm.temporaryDirectory
         return base.appendingPathComponent("MySearchBuddy/Index", isDirectory: true)
@@ -35,6 +35,16 @@ public enum FinderCore {
         fc_close_index()
     }
 
+    /// Per-user index directory chosen by finder-core, migrating a legacy
+    /// `/tmp/finder-index` there on first use.
+    public static func defaultIndexDirectory() -> String? {
+        guard let pointer = fc_default_index_dir() else {
+            return nil
+        }
+        defer { fc_free_string(pointer) }
+        return String(cString: pointer)
+    }
+
     public struct FileMeta {
         public let path: String
         public let name: String
@@ -122,47 +132,88 @@ public enum FinderCore {
         fc_commit_and_refresh()
     }
 
+    /// Moves a result to the Trash with `FileManager`, which works inside the
+    /// App Sandbox where scripting Finder doesn't, then drops it from the index.
+    @discardableResult
+    public static func moveToTrash(path: String) throws -> Int64 {
+        try FileManager.default.trashItem(at: URL(fileURLWithPath: path), resultingItemURL: nil)
+        return path.withCString { pointer in
+            fc_forget_removed(pointer)
+        }
+    }
+
     public static func search(
         _ term: String,
         scope: Scope = .both,
         glob: String? = nil,
         limit: Int32 = 50,
-        sortByModifiedDescending: Bool = true
+        sortByModifiedDescending: Bool = true,
+        timeoutMilliseconds: Int32 = 0,
+        snippetCharacters: Int32 = 0,
+        modifiedSince: Date? = nil,
+        modifiedUntil: Date? = nil,
+        minimumSize: UInt64 = 0,
+        maximumSize: UInt64 = 0,
+        extensions: [String] = [],
+        offset: Int32 = 0,
+        excludeGlobs: [String] = [],
+        recencyHalfLifeDays: Int32 = 0
     ) -> [Hit] {
         var termBuffer: [CChar] = Array(term.utf8CString)
         var globBuffer: [CChar]? = glob.map { Array($0.utf8CString) }
+        var extsBuffer: [CChar]? = extensions.isEmpty
+            ? nil
+            : Array(extensions.joined(separator: ",").utf8CString)
+        var excludeBuffer: [CChar]? = excludeGlobs.isEmpty
+            ? nil
+            : Array(excludeGlobs.joined(separator: "\n").utf8CString)
 
         return termBuffer.withUnsafeMutableBufferPointer { termPointer in
             let qPtr = termPointer.baseAddress
             return withOptionalCStringBuffer(&globBuffer) { globPtr in
-                var query = FCQuery(
-                    q: qPtr,
-                    glob: globPtr,
-                    scope: scope.rawValue,
-                    limit: limit
-                )
-
-                var results = fc_search(&query)
-                defer { fc_free_results(&results) }
-
-                guard results.count > 0, let base = results.hits else {
-                    return []
-                }
+                withOptionalCStringBuffer(&extsBuffer) { extsPtr in
+                    withOptionalCStringBuffer(&excludeBuffer) { excludePtr in
+                        var query = FCQuery(
+                            q: qPtr,
+                            glob: globPtr,
+                            scope: scope.rawValue,
+                            limit: limit,
+                            timeout_ms: timeoutMilliseconds,
+                            snippet_chars: snippetCharacters,
+                            mtime_min: modifiedSince.map { Int64($0.timeIntervalSince1970) } ?? 0,
+                            mtime_max: modifiedUntil.map { Int64($0.timeIntervalSince1970) } ?? 0,
+                            size_min: minimumSize,
+                            size_max: maximumSize,
+                            exts: extsPtr,
+                            offset: offset,
+                            exclude_globs: excludePtr,
+                            recency_half_life_days: recencyHalfLifeDays,
+                            path_penalties: nil
+                        )
+
+                        var results = fc_search(&query)
+                        defer { fc_free_results(&results) }
+
+                        guard results.count > 0, let base = results.hits else {
+                            return []
+                        }
 
-                let buffer = UnsafeBufferPointer(start: base, count: Int(results.count))
-                let hits = buffer.compactMap { raw -> Hit? in
-                    guard let pathPtr = raw.path, let namePtr = raw.name else {
-                        return nil
-                    }
-                    let path = String(cString: pathPtr)
-                    let name = String(cString: namePtr)
-                    return Hit(path: path, name: name, mtime: raw.mtime, size: raw.size, score: raw.score)
-                }
+                        let buffer = UnsafeBufferPointer(start: base, count: Int(results.count))
+                        let hits = buffer.compactMap { raw -> Hit? in
+                            guard let pathPtr = raw.path, let namePtr = raw.name else {
+                                return nil
+                            }
+                            let path = String(cString: pathPtr)
+                            let name = String(cString: namePtr)
+                            return Hit(path: path, name: name, mtime: raw.mtime, size: raw.size, score: raw.score)
+                        }
 
-                if sortByModifiedDescending {
-                    return hits.sorted { $0.mtime > $1.mtime }
+                        if sortByModifiedDescending {
+                            return hits.sorted { $0.mtime > $1.mtime }
+                        }
+                        return hits
+                    }
                 }
-                return hits
             }
         }
     }
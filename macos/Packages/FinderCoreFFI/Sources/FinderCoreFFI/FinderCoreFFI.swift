@@ -197,24 +197,45 @@ public final class FinderCoreFFI {
         return true
     }
 
-    public func search(term: String, scope: FinderCoreScope = .both, glob: String? = nil, limit: Int32 = 50) throws -> [FinderCoreHit] {
+    public func search(term: String, scope: FinderCoreScope = .both, glob: String? = nil, limit: Int32 = 50, timeoutMilliseconds: Int32 = 0, snippetCharacters: Int32 = 0, modifiedSince: Date? = nil, modifiedUntil: Date? = nil, minimumSize: UInt64 = 0, maximumSize: UInt64 = 0, extensions: [String] = [], offset: Int32 = 0, excludeGlobs: [String] = [], recencyHalfLifeDays: Int32 = 0) throws -> [FinderCoreHit] {
         var termBuffer: [CChar] = Array(term.utf8CString)
         var globBuffer: [CChar]? = glob.map { Array($0.utf8CString) }
+        var extsBuffer: [CChar]? = extensions.isEmpty
+            ? nil
+            : Array(extensions.joined(separator: ",").utf8CString)
+        var excludeBuffer: [CChar]? = excludeGlobs.isEmpty
+            ? nil
+            : Array(excludeGlobs.joined(separator: "\n").utf8CString)
 
         return try termBuffer.withUnsafeMutableBufferPointer { termPtr -> [FinderCoreHit] in
             let termBase = termPtr.baseAddress
             return try withOptionalMutableCStringBuffer(&globBuffer) { globBase in
-                var query = FCQuery(
-                    q: termBase,
-                    glob: globBase,
-                    scope: scope.domain,
-                    limit: limit
-                )
-
-                let results = withUnsafePointer(to: &query) { pointer in
-                    lib.search(pointer)
+                try withOptionalMutableCStringBuffer(&extsBuffer) { extsBase in
+                    try withOptionalMutableCStringBuffer(&excludeBuffer) { excludeBase in
+                        var query = FCQuery(
+                            q: termBase,
+                            glob: globBase,
+                            scope: scope.domain,
+                            limit: limit,
+                            timeout_ms: timeoutMilliseconds,
+                            snippet_chars: snippetCharacters,
+                            mtime_min: modifiedSince.map { Int64($0.timeIntervalSince1970) } ?? 0,
+                            mtime_max: modifiedUntil.map { Int64($0.timeIntervalSince1970) } ?? 0,
+                            size_min: minimumSize,
+                            size_max: maximumSize,
+                            exts: extsBase,
+                            offset: offset,
+                            exclude_globs: excludeBase,
+                            recency_half_life_days: recencyHalfLifeDays,
+                            path_penalties: nil
+                        )
+
+                        let results = withUnsafePointer(to: &query) { pointer in
+                            lib.search(pointer)
+                        }
+                        return try handleResults(results)
+                    }
                 }
-                return try handleResults(results)
             }
         }
     }